@@ -17,6 +17,8 @@ use std::fs::{self, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
+use crate::runtime::permissions;
+
 /// Register the ultra-fast fs module
 pub fn register_fs_module(context: &mut Context) -> JsResult<()> {
     let fs_obj = JsObject::with_null_proto();
@@ -529,9 +531,37 @@ fn register_async_wrappers(context: &mut Context) -> JsResult<()> {
             }
         };
 
+        const abortError = (signal) => {
+            const err = new Error('The operation was aborted', { cause: signal.reason });
+            err.name = 'AbortError';
+            err.code = 'ABORT_ERR';
+            return err;
+        };
+
+        // With a signal the file is read in chunks so an abort can land between them
+        async function readFileAbortable(path, options) {
+            const signal = options?.signal;
+            if (!signal) return _fs.readFileSync(path, options);
+            if (signal.aborted) throw abortError(signal);
+            const reader = __viper_file_stream(path).getReader();
+            const chunks = [];
+            try {
+                for (;;) {
+                    const { value, done } = await reader.read();
+                    if (signal.aborted) throw abortError(signal);
+                    if (done) break;
+                    chunks.push(value);
+                }
+            } finally {
+                reader.cancel().catch(() => {});
+            }
+            const data = Buffer.concat(chunks);
+            return options.encoding ? data.toString(options.encoding) : data;
+        }
+
         // fs.promises API
         _fs.promises = {
-            readFile: promisify(_fs.readFileSync),
+            readFile: readFileAbortable,
             writeFile: promisify(_fs.writeFileSync),
             appendFile: promisify(_fs.appendFileSync),
             stat: promisify(_fs.statSync),
@@ -549,6 +579,41 @@ fn register_async_wrappers(context: &mut Context) -> JsResult<()> {
             truncate: promisify(_fs.truncateSync),
         };
 
+        // Streams read files in chunks through a WHATWG byte stream
+        _fs.createReadStream = function(path, options = {}) {
+            if (typeof options === 'string') options = { encoding: options };
+            const fd = options.fd !== undefined ? options.fd : path;
+            const readable = globalThis.stream.Readable.fromWeb(
+                __viper_file_stream(fd, options),
+                { highWaterMark: options.highWaterMark }
+            );
+            readable.path = path;
+            return readable;
+        };
+
+        class FileHandle {
+            constructor(fd) {
+                this.fd = fd;
+            }
+            readableWebStream() {
+                return __viper_file_stream(this.fd);
+            }
+            async readFile(options) {
+                const chunks = [];
+                for await (const chunk of this.readableWebStream()) chunks.push(chunk);
+                const data = Buffer.concat(chunks);
+                const encoding = typeof options === 'string' ? options : options?.encoding;
+                return encoding ? data.toString(encoding) : data;
+            }
+            async close() {
+                _fs.closeSync(this.fd);
+            }
+        }
+
+        _fs.promises.open = function(path, flags = 'r') {
+            return promisify(_fs.openSync)(path, flags).then((fd) => new FileHandle(fd));
+        };
+
         // Dirent class for readdir with withFileTypes
         class Dirent {
             constructor(name, isDir, isFile, isSymlink) {
@@ -623,6 +688,7 @@ fn fs_read_file_sync(
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_read(context, &path)?;
     let options = args.get(1);
 
     // Check encoding option
@@ -664,6 +730,7 @@ fn fs_write_file_sync(
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &path)?;
     let data = args.get_or_undefined(1);
     let options = args.get(2);
 
@@ -691,6 +758,7 @@ fn fs_append_file_sync(
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &path)?;
     let data = args.get_or_undefined(1);
     let options = args.get(2);
 
@@ -715,6 +783,7 @@ fn fs_exists_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> J
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_read(context, &path)?;
     Ok(JsValue::from(Path::new(&path).exists()))
 }
 
@@ -724,6 +793,7 @@ fn fs_stat_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> JsR
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_read(context, &path)?;
 
     let metadata = fs::metadata(&path).map_err(|e| {
         JsNativeError::error().with_message(format!(
@@ -741,6 +811,7 @@ fn fs_readdir_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) ->
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_read(context, &path)?;
     let options = args.get(1);
 
     let with_file_types = if let Some(opts) = options {
@@ -814,6 +885,7 @@ fn fs_mkdir_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> Js
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &path)?;
     let options = args.get(1);
 
     let recursive = if let Some(opts) = options {
@@ -848,6 +920,7 @@ fn fs_rmdir_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> Js
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &path)?;
     let options = args.get(1);
 
     let recursive = if let Some(opts) = options {
@@ -882,6 +955,7 @@ fn fs_rm_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> JsRes
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &path)?;
     let options = args.get(1);
 
     let (recursive, force) = if let Some(opts) = options {
@@ -926,6 +1000,7 @@ fn fs_unlink_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> J
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &path)?;
 
     fs::remove_file(&path).map_err(|e| {
         JsNativeError::error().with_message(format!(
@@ -947,6 +1022,8 @@ fn fs_rename_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> J
         .get_or_undefined(1)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &old_path)?;
+    permissions::check_write(context, &new_path)?;
 
     fs::rename(&old_path, &new_path).map_err(|e| {
         JsNativeError::error().with_message(format!(
@@ -977,6 +1054,8 @@ fn fs_copy_file_sync(
         .map(|v| v.to_u32(context))
         .transpose()?
         .unwrap_or(0);
+    permissions::check_read(context, &src)?;
+    permissions::check_write(context, &dest)?;
 
     // COPYFILE_EXCL = 1: fail if dest exists
     if mode & 1 != 0 && Path::new(&dest).exists() {
@@ -1004,6 +1083,7 @@ fn fs_chmod_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> Js
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &path)?;
     let _mode = args.get_or_undefined(1).to_u32(context)?;
 
     // On Windows, chmod is limited. On Unix, we'd use std::os::unix::fs::PermissionsExt
@@ -1037,6 +1117,7 @@ fn fs_realpath_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) ->
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_read(context, &path)?;
 
     let real = fs::canonicalize(&path).map_err(|e| {
         JsNativeError::error().with_message(format!(
@@ -1056,6 +1137,7 @@ fn fs_access_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> J
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_read(context, &path)?;
     let _mode = args
         .get(1)
         .map(|v| v.to_u32(context))
@@ -1081,6 +1163,7 @@ fn fs_truncate_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) ->
         .get_or_undefined(0)
         .to_string(context)?
         .to_std_string_escaped();
+    permissions::check_write(context, &path)?;
     let len = args
         .get(1)
         .map(|v| v.to_u32(context))
@@ -1121,6 +1204,14 @@ fn fs_open_sync(_this: &JsValue, args: &[JsValue], context: &mut Context) -> JsR
         "r".to_string()
     };
 
+    // Every flag except plain "r" can modify the file
+    if flags_str.contains(['w', 'a', 'x', '+']) {
+        permissions::check_write(context, &path)?;
+    }
+    if flags_str.contains(['r', '+']) || !flags_str.contains(['w', 'a', 'x']) {
+        permissions::check_read(context, &path)?;
+    }
+
     let mut opts = OpenOptions::new();
     match flags_str.as_str() {
         "r" => {
@@ -22,7 +22,10 @@ pub use bundler::{BundleConfig, BundleError, BundleFormat, BundleResult};
 #[cfg(feature = "pm")]
 pub use pm::{PackageManager, PackageManagerConfig, PmError, PmResult};
 pub use resolver::ModuleResolver;
-pub use runtime::{Runtime, RuntimeConfig, RuntimeError, RuntimeResult};
+pub use runtime::{
+    HostError, HostModule, HostResult, ResourceLimit, ResourceLimits, Runtime, RuntimeConfig,
+    RuntimeError, RuntimeResult,
+};
 #[cfg(feature = "server")]
 pub use server::{Server, ServerConfig, ServerError, ServerResult};
 pub use transpiler::{TranspileError, Transpiler, TranspilerConfig};
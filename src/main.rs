@@ -6,22 +6,30 @@
 //! - boa_runtime: WebAPI support (console, fetch, URL, etc.)
 //! - High-performance event loop for async operations
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use colored::Colorize;
 use miette::{IntoDiagnostic, Result};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use viper::bundler::{BundleConfig, BundleFormat, simple_bundle};
 use viper::cli::Repl;
 #[cfg(feature = "pm")]
 use viper::pm::{PackageManager, PackageManagerConfig};
-use viper::runtime::{Runtime, RuntimeConfig};
+use viper::runtime::{
+    Allow, CountingAllocator, Permissions, Runtime, RuntimeConfig, RuntimeError,
+    UnhandledRejections, restore_terminal,
+};
 #[cfg(feature = "server")]
 use viper::server;
 use viper::transpiler::{Transpiler, TranspilerConfig};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// Counts heap use per thread so runtimes can enforce `max_heap_size`
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
 #[derive(Parser)]
 #[command(name = "viper")]
 #[command(version, about = format!("Viper is a fast TypeScript runtime, package manager, and bundler. ({})", VERSION))]
@@ -34,6 +42,9 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
   add       express              Add a dependency to package.json (viper a)
   remove    is-array             Remove a dependency from package.json (viper rm)
   update    react                Update outdated dependencies
+  audit                          Check dependencies for known vulnerabilities
+  pack                           Create a tarball of the package
+  publish                        Publish the package to the registry
   link      [<package>]          Register or link a local npm package
   pm        <subcommand>         Additional package management utilities
 
@@ -61,16 +72,133 @@ struct Cli {
     #[arg(long)]
     minify: bool,
 
+    #[command(flatten)]
+    run: RunOptions,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+/// Options shared by `viper <file>`, `viper -e` and `viper run`
+#[derive(Args, Clone, Default)]
+struct RunOptions {
+    /// Abort execution after this long (e.g. 30s, 5m, 1h; plain numbers are seconds)
+    #[arg(long, value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+
+    /// How to report unhandled promise rejections: throw (default), strict, warn,
+    /// warn-with-error-code or none
+    #[arg(long, value_name = "MODE")]
+    unhandled_rejections: Option<UnhandledRejections>,
+
+    /// Allow reading these paths, or any path without a value (sandboxes the run)
+    #[arg(long, value_name = "PATH", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_read: Option<Vec<PathBuf>>,
+
+    /// Allow writing these paths, or any path without a value (sandboxes the run)
+    #[arg(long, value_name = "PATH", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_write: Option<Vec<PathBuf>>,
+
+    /// Allow network access to these hosts (host or host:port), or any without a value
+    #[arg(long, value_name = "HOST", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_net: Option<Vec<String>>,
+
+    /// Allow access to these environment variables, or all without a value
+    #[arg(long, value_name = "VAR", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_env: Option<Vec<String>>,
+
+    /// Allow running these commands, or any command without a value
+    #[arg(long, value_name = "CMD", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    allow_run: Option<Vec<String>>,
+
+    /// Allow creating Workers (sandboxes the run)
+    #[arg(long)]
+    allow_worker: bool,
+
+    /// Deny every capability not granted with an --allow-* flag
+    #[arg(long)]
+    deny_all: bool,
+
+    /// Print how long each step of runtime startup took
+    #[arg(long)]
+    print_startup_timing: bool,
+}
+
+impl RunOptions {
+    /// Fill options not given after the subcommand from the top-level ones
+    fn or(self, fallback: &RunOptions) -> RunOptions {
+        RunOptions {
+            timeout: self.timeout.or(fallback.timeout),
+            unhandled_rejections: self.unhandled_rejections.or(fallback.unhandled_rejections),
+            allow_read: self.allow_read.or_else(|| fallback.allow_read.clone()),
+            allow_write: self.allow_write.or_else(|| fallback.allow_write.clone()),
+            allow_net: self.allow_net.or_else(|| fallback.allow_net.clone()),
+            allow_env: self.allow_env.or_else(|| fallback.allow_env.clone()),
+            allow_run: self.allow_run.or_else(|| fallback.allow_run.clone()),
+            allow_worker: self.allow_worker || fallback.allow_worker,
+            deny_all: self.deny_all || fallback.deny_all,
+            print_startup_timing: self.print_startup_timing || fallback.print_startup_timing,
+        }
+    }
+
+    /// Print the startup breakdown to stderr if --print-startup-timing was given
+    fn report_startup(&self, runtime: &Runtime) {
+        if self.print_startup_timing {
+            eprintln!("{}", "startup timing:".dimmed());
+            eprintln!("{}", runtime.startup_timing());
+        }
+    }
+
+    /// Any --allow-* or --deny-all flag sandboxes the run: only what is
+    /// granted is available. Without them everything is allowed.
+    fn permissions(&self) -> Permissions {
+        fn grant<T: Clone>(values: &Option<Vec<T>>) -> Allow<T> {
+            match values {
+                None => Allow::none(),
+                Some(values) if values.is_empty() => Allow::All,
+                Some(values) => Allow::Only(values.clone()),
+            }
+        }
+
+        let sandboxed = self.deny_all
+            || self.allow_worker
+            || self.allow_read.is_some()
+            || self.allow_write.is_some()
+            || self.allow_net.is_some()
+            || self.allow_env.is_some()
+            || self.allow_run.is_some();
+        if !sandboxed {
+            return Permissions::allow_all();
+        }
+        Permissions {
+            read: grant(&self.allow_read),
+            write: grant(&self.allow_write),
+            net: grant(&self.allow_net),
+            env: grant(&self.allow_env),
+            run: grant(&self.allow_run),
+            worker: self.allow_worker,
+        }
+    }
+
+    fn config(&self) -> RuntimeConfig {
+        RuntimeConfig {
+            max_runtime: self.timeout,
+            unhandled_rejections: self.unhandled_rejections.unwrap_or_default(),
+            permissions: self.permissions(),
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Execute a file with Viper
     Run {
         /// TypeScript/JavaScript file to run
         file: PathBuf,
+
+        #[command(flatten)]
+        options: RunOptions,
     },
 
     /// Start a REPL session with Viper
@@ -197,6 +325,82 @@ enum Commands {
         command: PmCommands,
     },
 
+    /// Check installed packages for known vulnerabilities
+    #[cfg(feature = "pm")]
+    Audit {
+        #[command(subcommand)]
+        command: Option<AuditCommands>,
+
+        /// Exit non-zero for advisories at or above this severity
+        /// (info, low, moderate, high, critical)
+        #[arg(long, default_value = "low", global = true)]
+        level: String,
+
+        /// Output as JSON
+        #[arg(long, global = true)]
+        json: bool,
+
+        /// Bulk advisory endpoint (defaults to the registry's)
+        #[arg(long, global = true)]
+        audit_url: Option<String>,
+
+        /// Project root directory
+        #[arg(short, long, global = true)]
+        cwd: Option<PathBuf>,
+    },
+
+    /// Create a tarball of the package for publishing
+    #[cfg(feature = "pm")]
+    Pack {
+        /// Directory to write the tarball to
+        #[arg(long, value_name = "DIR")]
+        destination: Option<PathBuf>,
+
+        /// List the files that would be packed without writing the tarball
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Don't run prepack, prepare or postpack scripts
+        #[arg(long)]
+        ignore_scripts: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Project root directory
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+    },
+
+    /// Publish the package to the registry
+    #[cfg(feature = "pm")]
+    Publish {
+        /// Dist-tag for the published version (default: latest)
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Package access level (public, restricted)
+        #[arg(long)]
+        access: Option<String>,
+
+        /// Pack and validate without uploading
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Don't run lifecycle scripts
+        #[arg(long)]
+        ignore_scripts: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Project root directory
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+    },
+
     /// Display package metadata from the registry
     #[cfg(feature = "pm")]
     Info {
@@ -238,6 +442,18 @@ enum Commands {
     Upgrade,
 }
 
+/// Audit subcommands
+#[cfg(feature = "pm")]
+#[derive(Subcommand)]
+enum AuditCommands {
+    /// Bump vulnerable packages to patched versions within their ranges
+    Fix {
+        /// Report what would change without installing
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 /// Package manager subcommands
 #[cfg(feature = "pm")]
 #[derive(Subcommand)]
@@ -245,9 +461,58 @@ enum PmCommands {
     /// List installed packages
     #[command(alias = "ls")]
     List {
-        /// Depth of dependencies to show (0 = top-level only)
-        #[arg(short, long, default_value = "0")]
-        depth: usize,
+        /// Depth of dependencies to show (0 = top-level only; the full tree
+        /// by default with --tree)
+        #[arg(short, long)]
+        depth: Option<usize>,
+
+        /// Show the dependency tree from the lockfile
+        #[arg(long)]
+        tree: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Project root directory
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+    },
+
+    /// Show every dependency path that leads to a package
+    Why {
+        /// Package name, optionally with a version (e.g., ms, ms@2.0.0)
+        package: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Project root directory
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+    },
+
+    /// Report packages installed in more than one version or location
+    Dedupe {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Project root directory
+        #[arg(short, long)]
+        cwd: Option<PathBuf>,
+    },
+
+    /// Print the lockfile, or compare it against another one
+    Lock {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Show changes from this lockfile to the current one
+        #[arg(long, value_name = "OLD_LOCKFILE")]
+        diff: Option<PathBuf>,
 
         /// Project root directory
         #[arg(short, long)]
@@ -267,9 +532,16 @@ enum PmCommands {
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // A panic must not leave stdin in raw mode either
+    let report = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        report(info);
+    }));
+
     match cli.command {
-        Some(Commands::Run { file }) => {
-            run_file_with_event_loop(&file)?;
+        Some(Commands::Run { file, options }) => {
+            run_file_with_event_loop(&file, &options.or(&cli.run))?;
         }
         Some(Commands::Repl) => {
             run_repl()?;
@@ -325,13 +597,66 @@ fn main() -> Result<()> {
             std::process::exit(1);
         }
         #[cfg(feature = "pm")]
+        Some(Commands::Audit {
+            command,
+            level,
+            json,
+            audit_url,
+            cwd,
+        }) => match command {
+            None => pm_audit(&level, json, audit_url, cwd)?,
+            Some(AuditCommands::Fix { dry_run }) => pm_audit_fix(dry_run, json, audit_url, cwd)?,
+        },
+        #[cfg(feature = "pm")]
+        Some(Commands::Pack {
+            destination,
+            dry_run,
+            ignore_scripts,
+            json,
+            cwd,
+        }) => {
+            let options = viper::pm::PackOptions {
+                destination,
+                dry_run,
+                ignore_scripts,
+            };
+            pm_pack(&options, json, cwd)?;
+        }
+        #[cfg(feature = "pm")]
+        Some(Commands::Publish {
+            tag,
+            access,
+            dry_run,
+            ignore_scripts,
+            json,
+            cwd,
+        }) => pm_publish(tag, access, dry_run, ignore_scripts, json, cwd)?,
+        #[cfg(feature = "pm")]
         Some(Commands::Info { package, versions }) => {
             pm_view(&package, versions)?;
         }
         #[cfg(feature = "pm")]
         Some(Commands::Pm { command }) => match command {
-            PmCommands::List { depth, cwd } => {
-                pm_list(depth, cwd)?;
+            PmCommands::List {
+                depth,
+                tree,
+                json,
+                cwd,
+            } => {
+                if tree {
+                    pm_tree(depth, json, cwd)?;
+                } else {
+                    pm_list(depth.unwrap_or(0), json, cwd)?;
+                }
+            }
+            PmCommands::Why { package, json, cwd } => {
+                pm_why(&package, json, cwd)?;
+            }
+            PmCommands::Dedupe { json, cwd } => {
+                pm_dedupe(json, cwd)?;
+            }
+            PmCommands::Lock { json, diff, cwd } => {
+                pm_lock(json, diff, cwd)?;
             }
             PmCommands::Ping => {
                 pm_ping()?;
@@ -364,7 +689,7 @@ fn main() -> Result<()> {
                 if cli.print {
                     print_transpiled(&code, "eval.ts", cli.minify)?;
                 } else {
-                    eval_code(&code)?;
+                    eval_code(&code, &cli.run)?;
                 }
             } else if let Some(file) = cli.file {
                 if cli.print {
@@ -375,7 +700,7 @@ fn main() -> Result<()> {
                         .unwrap_or("input.ts");
                     print_transpiled(&source, filename, cli.minify)?;
                 } else {
-                    run_file_with_event_loop(&file)?;
+                    run_file_with_event_loop(&file, &cli.run)?;
                 }
             } else {
                 // No file or code provided, start REPL
@@ -518,10 +843,11 @@ fn run_stdin() -> Result<()> {
                 let result = runtime.value_to_string(&value);
                 println!("{}", result);
             }
+            restore_terminal();
         }
         Err(e) => {
             eprintln!("{}: {}", "error".red(), e);
-            std::process::exit(1);
+            exit(1);
         }
     }
 
@@ -529,25 +855,26 @@ fn run_stdin() -> Result<()> {
 }
 
 /// Execute a TypeScript/JavaScript file with full event loop support
-fn run_file_with_event_loop(path: &PathBuf) -> Result<()> {
+fn run_file_with_event_loop(path: &std::path::Path, options: &RunOptions) -> Result<()> {
     let base_path = std::env::current_dir()
         .unwrap_or_else(|_| path.parent().map(|p| p.to_path_buf()).unwrap_or_default());
 
     let config = RuntimeConfig {
         base_path,
-        use_event_loop: true,
-        ..Default::default()
+        ..options.config()
     };
 
     let mut runtime = Runtime::with_config(config).into_diagnostic()?;
+    options.report_startup(&runtime);
 
     match runtime.run_file(path) {
         Ok(_value) => {
             // Don't print return values for file execution (unlike REPL)
+            exit_with_process_code(&mut runtime);
         }
         Err(e) => {
             eprintln!("{}: {}", "error".red(), e);
-            std::process::exit(1);
+            exit(exit_code(&e));
         }
     }
 
@@ -555,8 +882,9 @@ fn run_file_with_event_loop(path: &PathBuf) -> Result<()> {
 }
 
 /// Evaluate TypeScript code from command line
-fn eval_code(code: &str) -> Result<()> {
-    let mut runtime = Runtime::new().into_diagnostic()?;
+fn eval_code(code: &str, options: &RunOptions) -> Result<()> {
+    let mut runtime = Runtime::with_config(options.config()).into_diagnostic()?;
+    options.report_startup(&runtime);
 
     match runtime.run(code, "eval.ts") {
         Ok(value) => {
@@ -564,16 +892,68 @@ fn eval_code(code: &str) -> Result<()> {
                 let result = runtime.value_to_string(&value);
                 println!("{}", result);
             }
+            exit_with_process_code(&mut runtime);
         }
         Err(e) => {
             eprintln!("{}: {}", "error".red(), e);
-            std::process::exit(1);
+            exit(exit_code(&e));
         }
     }
 
     Ok(())
 }
 
+/// Process exit code for a failed run (13 for unsettled top-level await, as in Node.js)
+fn exit_code(error: &RuntimeError) -> i32 {
+    match error {
+        RuntimeError::UnsettledTopLevelAwait(_) => 13,
+        _ => 1,
+    }
+}
+
+/// Honor `process.exitCode` once a run finished without errors
+fn exit_with_process_code(runtime: &mut Runtime) {
+    let code = runtime.exit_code();
+    restore_terminal();
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
+
+/// Exit after a failed run, leaving the terminal as the script found it
+fn exit(code: i32) -> ! {
+    restore_terminal();
+    std::process::exit(code);
+}
+
+/// Parse a `--timeout` value such as `30s`, `5m`, `1h`, `250ms` or plain seconds
+fn parse_timeout(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid timeout '{}'", value))?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => {
+            return Err(format!(
+                "invalid timeout unit '{}' (expected ms, s, m or h)",
+                unit
+            ));
+        }
+    };
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err("timeout must be greater than zero".to_string());
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
 /// Transpile and print without executing
 fn print_transpiled(code: &str, filename: &str, minify: bool) -> Result<()> {
     let config = TranspilerConfig {
@@ -632,6 +1012,18 @@ fn transpile_file(input: &PathBuf, output: Option<&std::path::Path>, minify: boo
 // Package Manager Commands
 // =============================================================================
 
+/// Build the package manager config for `root`, including `.npmrc` settings
+#[cfg(feature = "pm")]
+fn pm_config(root: &std::path::Path) -> PackageManagerConfig {
+    match PackageManagerConfig::new(root).load_npmrc() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
 #[cfg(feature = "pm")]
 fn pm_install(root: Option<PathBuf>) -> Result<()> {
     use viper::pm::InstallResult;
@@ -640,11 +1032,14 @@ fn pm_install(root: Option<PathBuf>) -> Result<()> {
 
     println!("{} v{}", "viper install".cyan().bold(), VERSION.dimmed());
 
-    let config = PackageManagerConfig::new(&root_dir);
+    let config = pm_config(&root_dir);
     let pm = PackageManager::with_config(config);
 
     match pm.install() {
         Ok(result) => {
+            if let Some(source) = &result.imported_lockfile {
+                println!("{} pinned versions from {}", "Migrated".green(), source);
+            }
             println!();
             for pkg in &result.added_packages {
                 println!("{} {}", "+".green(), pkg);
@@ -680,7 +1075,7 @@ fn pm_add(packages: Vec<String>, dev: bool, root: Option<PathBuf>) -> Result<()>
 
     println!("{} v{}", "viper add".cyan().bold(), VERSION.dimmed());
 
-    let config = PackageManagerConfig::new(&root_dir);
+    let config = pm_config(&root_dir);
     let pm = PackageManager::with_config(config);
 
     let pkg_refs: Vec<&str> = packages.iter().map(|s| s.as_str()).collect();
@@ -729,7 +1124,7 @@ fn pm_remove(packages: Vec<String>, root: Option<PathBuf>) -> Result<()> {
 
     println!("{} v{}", "viper remove".cyan().bold(), VERSION.dimmed());
 
-    let config = PackageManagerConfig::new(&root_dir);
+    let config = pm_config(&root_dir);
     let pm = PackageManager::with_config(config);
 
     let pkg_refs: Vec<&str> = packages.iter().map(|s| s.as_str()).collect();
@@ -784,7 +1179,7 @@ fn pm_view(package: &str, show_versions: bool) -> Result<()> {
 
     let spinner = create_spinner(&format!("Fetching {}...", package));
     let start = std::time::Instant::now();
-    let config = PackageManagerConfig::new(std::env::current_dir().unwrap_or_default());
+    let config = pm_config(&std::env::current_dir().unwrap_or_default());
     let pm = PackageManager::with_config(config);
 
     match pm.view(package) {
@@ -875,10 +1270,11 @@ fn pm_view(package: &str, show_versions: bool) -> Result<()> {
 fn pm_ping() -> Result<()> {
     use viper::pm::InstallResult;
 
-    let config = PackageManagerConfig::new(std::env::current_dir().unwrap_or_default());
+    let config = pm_config(&std::env::current_dir().unwrap_or_default());
+    let registry = config.registry.to_string();
     let pm = PackageManager::with_config(config);
 
-    let spinner = create_spinner(&format!("PING {} ...", viper::pm::DEFAULT_REGISTRY));
+    let spinner = create_spinner(&format!("PING {} ...", registry));
 
     match pm.ping() {
         Ok(duration) => {
@@ -886,7 +1282,7 @@ fn pm_ping() -> Result<()> {
             println!(
                 "{} {} {} ({})",
                 "PING".cyan(),
-                viper::pm::DEFAULT_REGISTRY,
+                registry,
                 "PONG".green().bold(),
                 InstallResult::format_duration(duration)
             );
@@ -897,7 +1293,7 @@ fn pm_ping() -> Result<()> {
             println!(
                 "{} {} {}",
                 "PING".cyan(),
-                viper::pm::DEFAULT_REGISTRY,
+                registry,
                 "FAILED".red().bold()
             );
             eprintln!("{}: {}", "error".red().bold(), e);
@@ -910,7 +1306,7 @@ fn pm_ping() -> Result<()> {
 fn pm_init(name: Option<String>, _yes: bool) -> Result<()> {
     println!("{} v{}", "viper init".cyan().bold(), VERSION.dimmed());
 
-    let config = PackageManagerConfig::new(std::env::current_dir().unwrap_or_default());
+    let config = pm_config(&std::env::current_dir().unwrap_or_default());
     let pm = PackageManager::with_config(config);
 
     match pm.init(name.as_deref(), false) {
@@ -935,7 +1331,7 @@ fn pm_update(packages: Vec<String>, root: Option<PathBuf>) -> Result<()> {
 
     println!("{} v{}", "viper update".cyan().bold(), VERSION.dimmed());
 
-    let config = PackageManagerConfig::new(&root_dir);
+    let config = pm_config(&root_dir);
     let pm = PackageManager::with_config(config);
 
     let pkg_refs: Option<Vec<&str>> = if packages.is_empty() {
@@ -974,9 +1370,26 @@ fn pm_update(packages: Vec<String>, root: Option<PathBuf>) -> Result<()> {
 }
 
 #[cfg(feature = "pm")]
-fn pm_list(depth: usize, root: Option<PathBuf>) -> Result<()> {
+fn pm_list(depth: usize, json: bool, root: Option<PathBuf>) -> Result<()> {
     let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
 
+    if json {
+        let pm = PackageManager::with_config(pm_config(&root_dir));
+        match pm.list(depth) {
+            Ok(packages) => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&packages).into_diagnostic()?
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("{}: {}", "error".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     let package_json_path = root_dir.join("package.json");
     let project_name = if package_json_path.exists() {
         std::fs::read_to_string(&package_json_path)
@@ -994,7 +1407,7 @@ fn pm_list(depth: usize, root: Option<PathBuf>) -> Result<()> {
 
     println!("{}", project_name.cyan().bold());
 
-    let config = PackageManagerConfig::new(&root_dir);
+    let config = pm_config(&root_dir);
     let pm = PackageManager::with_config(config);
 
     match pm.list(depth) {
@@ -1030,6 +1443,519 @@ fn pm_list(depth: usize, root: Option<PathBuf>) -> Result<()> {
     }
 }
 
+/// Print the contents of a packed tarball
+#[cfg(feature = "pm")]
+fn print_pack(pack: &viper::pm::PackResult) {
+    println!(
+        "{} {}@{}",
+        "package:".dimmed(),
+        pack.name.bold(),
+        pack.version
+    );
+    for file in &pack.files {
+        println!("  {:>10}  {}", format_size(file.size), file.path);
+    }
+    println!();
+    println!("{} {}", "filename:".dimmed(), pack.filename);
+    println!("{} {}", "package size:".dimmed(), format_size(pack.size as u64));
+    println!(
+        "{} {}",
+        "unpacked size:".dimmed(),
+        format_size(pack.unpacked_size)
+    );
+    println!("{} {}", "shasum:".dimmed(), pack.shasum);
+    println!("{} {}", "integrity:".dimmed(), pack.integrity);
+    println!("{} {}", "total files:".dimmed(), pack.files.len());
+}
+
+#[cfg(feature = "pm")]
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1}kB", bytes as f64 / 1024.0)
+    } else {
+        format!("{}B", bytes)
+    }
+}
+
+#[cfg(feature = "pm")]
+fn pm_pack(options: &viper::pm::PackOptions, json: bool, root: Option<PathBuf>) -> Result<()> {
+    let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let pm = PackageManager::with_config(pm_config(&root_dir));
+
+    let pack = match pm.pack(options) {
+        Ok(pack) => pack,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&pack).into_diagnostic()?);
+        return Ok(());
+    }
+
+    print_pack(&pack);
+    if let Some(path) = &pack.path {
+        println!();
+        println!("{} {}", "Packed".green(), path.display());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "pm")]
+fn pm_publish(
+    tag: Option<String>,
+    access: Option<String>,
+    dry_run: bool,
+    ignore_scripts: bool,
+    json: bool,
+    root: Option<PathBuf>,
+) -> Result<()> {
+    let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let pm = PackageManager::with_config(pm_config(&root_dir));
+
+    let result = access
+        .map(|access| access.parse())
+        .transpose()
+        .and_then(|access| {
+            pm.publish(&viper::pm::PublishOptions {
+                tag,
+                access,
+                dry_run,
+                ignore_scripts,
+            })
+        });
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&result).into_diagnostic()?
+        );
+        return Ok(());
+    }
+
+    print_pack(&result.pack);
+    println!();
+    let verb = if dry_run { "Would publish" } else { "Published" };
+    println!(
+        "{} {}@{} to {} with tag {}{}",
+        verb.green(),
+        result.pack.name.bold(),
+        result.pack.version,
+        result.registry,
+        result.tag,
+        result
+            .access
+            .map(|access| format!(" and {} access", access))
+            .unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Build a package manager for `viper audit`, exiting on error
+#[cfg(feature = "pm")]
+fn audit_pm(audit_url: Option<String>, root_dir: &std::path::Path) -> PackageManager {
+    let mut config = pm_config(root_dir);
+    if let Some(url) = audit_url {
+        config = match config.audit_url(&url) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}: {}", "error".red().bold(), e);
+                std::process::exit(1);
+            }
+        };
+    }
+    PackageManager::with_config(config)
+}
+
+#[cfg(feature = "pm")]
+fn pm_audit(
+    level: &str,
+    json: bool,
+    audit_url: Option<String>,
+    root: Option<PathBuf>,
+) -> Result<()> {
+    use viper::pm::Severity;
+
+    let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let level: Severity = match level.parse() {
+        Ok(level) => level,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let pm = audit_pm(audit_url, &root_dir);
+    let spinner = (!json).then(|| create_spinner("Auditing dependencies..."));
+    let report = pm.audit();
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).into_diagnostic()?
+        );
+    } else {
+        for finding in &report.findings {
+            for advisory in &finding.advisories {
+                let severity = match advisory.severity {
+                    Severity::Critical => advisory.severity.to_string().red().bold(),
+                    Severity::High => advisory.severity.to_string().red(),
+                    Severity::Moderate => advisory.severity.to_string().yellow(),
+                    _ => advisory.severity.to_string().dimmed(),
+                };
+                println!(
+                    "{} {}@{} {}",
+                    severity,
+                    finding.name.bold(),
+                    finding.version,
+                    advisory.title
+                );
+                println!(
+                    "  {} {}",
+                    "vulnerable:".dimmed(),
+                    advisory.vulnerable_versions
+                );
+                if !advisory.url.is_empty() {
+                    println!("  {} {}", "advisory:".dimmed(), advisory.url);
+                }
+            }
+            for path in &finding.paths {
+                let chain: Vec<String> = path
+                    .iter()
+                    .map(|step| format!("{}@{}", step.name, step.version))
+                    .collect();
+                println!("  {} {}", "path:".dimmed(), chain.join(" > "));
+            }
+            println!();
+        }
+
+        if report.findings.is_empty() {
+            println!(
+                "{} ({} packages scanned)",
+                "No vulnerabilities found".green(),
+                report.packages_scanned
+            );
+        } else {
+            let counts: Vec<String> = Severity::ALL
+                .iter()
+                .filter(|s| report.count(**s) > 0)
+                .map(|s| format!("{} {}", report.count(*s), s))
+                .collect();
+            println!(
+                "{} vulnerable packages ({}) in {} scanned",
+                report.findings.len(),
+                counts.join(", "),
+                report.packages_scanned
+            );
+        }
+    }
+
+    if report.exceeds(level) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "pm")]
+fn pm_audit_fix(
+    dry_run: bool,
+    json: bool,
+    audit_url: Option<String>,
+    root: Option<PathBuf>,
+) -> Result<()> {
+    let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let pm = audit_pm(audit_url, &root_dir);
+
+    let result = match pm.audit_fix(dry_run) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&result).into_diagnostic()?
+        );
+        return Ok(());
+    }
+
+    let verb = if dry_run { "would fix" } else { "fixed" };
+    for fix in &result.fixed {
+        println!(
+            "{} {} {} -> {}",
+            "~".green(),
+            fix.name,
+            fix.from.dimmed(),
+            fix.to
+        );
+    }
+    for finding in &result.unfixable {
+        println!(
+            "{} {}@{} {}",
+            "!".red(),
+            finding.name,
+            finding.version,
+            "(no patched version resolved within the requested ranges)".dimmed()
+        );
+    }
+    println!();
+    println!(
+        "{} {}, {} need a breaking upgrade",
+        result.fixed.len(),
+        verb,
+        result.unfixable.len()
+    );
+    Ok(())
+}
+
+/// Load the dependency graph from `viper.lock`, exiting on error
+#[cfg(feature = "pm")]
+fn pm_graph(root_dir: &std::path::Path) -> viper::pm::DependencyGraph {
+    let pm = PackageManager::with_config(pm_config(root_dir));
+    match pm.graph() {
+        Ok(graph) => graph,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "pm")]
+fn pm_tree(depth: Option<usize>, json: bool, root: Option<PathBuf>) -> Result<()> {
+    use viper::pm::{DependencyKind, TreeNode};
+
+    fn print_nodes(nodes: &[TreeNode], prefix: &str) {
+        for (i, node) in nodes.iter().enumerate() {
+            let last = i + 1 == nodes.len();
+            let branch = if last { "└── " } else { "├── " };
+            let version = match (&node.version, node.missing) {
+                (_, true) => "MISSING".red().to_string(),
+                (Some(v), _) => v.dimmed().to_string(),
+                (None, _) => String::new(),
+            };
+            let mut suffix = String::new();
+            if node.kind == DependencyKind::Dev {
+                suffix.push_str(&" dev".yellow().to_string());
+            }
+            if node.deduped {
+                suffix.push_str(&" deduped".dimmed().to_string());
+            }
+            println!(
+                "{}{}{}@{}{}",
+                prefix,
+                branch.dimmed(),
+                node.name,
+                version,
+                suffix
+            );
+            let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            print_nodes(&node.dependencies, &child_prefix);
+        }
+    }
+
+    let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let graph = pm_graph(&root_dir);
+    let tree = graph.tree(depth);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tree).into_diagnostic()?);
+        return Ok(());
+    }
+
+    println!("{}", root_dir.display().to_string().cyan().bold());
+    if tree.is_empty() {
+        println!("{}", "(no dependencies)".dimmed());
+    } else {
+        print_nodes(&tree, "");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "pm")]
+fn pm_why(package: &str, json: bool, root: Option<PathBuf>) -> Result<()> {
+    let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let graph = pm_graph(&root_dir);
+    let paths = graph.why(package);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&paths).into_diagnostic()?
+        );
+        return Ok(());
+    }
+
+    if paths.is_empty() {
+        eprintln!(
+            "{}: {} is not in the dependency tree",
+            "error".red().bold(),
+            package
+        );
+        std::process::exit(1);
+    }
+
+    for path in &paths {
+        let chain: Vec<String> = path
+            .iter()
+            .map(|step| format!("{}@{}", step.name, step.version.dimmed()))
+            .collect();
+        let kind = match path.first().map(|step| step.kind) {
+            Some(viper::pm::DependencyKind::Dev) => " (dev)".yellow().to_string(),
+            _ => String::new(),
+        };
+        println!("{}{}", chain.join(&" > ".dimmed().to_string()), kind);
+    }
+    println!();
+    let label = if paths.len() == 1 { "path" } else { "paths" };
+    println!("{} dependency {} to {}", paths.len(), label, package);
+    Ok(())
+}
+
+#[cfg(feature = "pm")]
+fn pm_dedupe(json: bool, root: Option<PathBuf>) -> Result<()> {
+    let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    let graph = pm_graph(&root_dir);
+    let duplicates = graph.duplicates();
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&duplicates).into_diagnostic()?
+        );
+        return Ok(());
+    }
+
+    if duplicates.is_empty() {
+        println!("{}", "No duplicate packages".green());
+        return Ok(());
+    }
+
+    for dup in &duplicates {
+        println!("{}", dup.name.cyan().bold());
+        for version in &dup.versions {
+            println!(
+                "  {} {}",
+                version.version,
+                format!("({})", version.locations.join(", ")).dimmed()
+            );
+        }
+    }
+    println!();
+    let label = if duplicates.len() == 1 {
+        "package"
+    } else {
+        "packages"
+    };
+    println!("{} duplicated {}", duplicates.len(), label);
+    Ok(())
+}
+
+#[cfg(feature = "pm")]
+fn pm_lock(json: bool, diff: Option<PathBuf>, root: Option<PathBuf>) -> Result<()> {
+    use viper::pm::{LOCKFILE_NAME, Lockfile};
+
+    let root_dir = root.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+
+    let lockfile = match Lockfile::load(&root_dir) {
+        Ok(Some(lockfile)) => lockfile,
+        Ok(None) => {
+            eprintln!(
+                "{}: no {} found, run `viper install` first",
+                "error".red().bold(),
+                LOCKFILE_NAME
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let Some(old_path) = diff else {
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&lockfile).into_diagnostic()?
+            );
+        } else {
+            print!("{}", lockfile.to_kdl());
+        }
+        return Ok(());
+    };
+
+    let old_content = std::fs::read_to_string(&old_path).into_diagnostic()?;
+    let old = match Lockfile::parse(&old_content) {
+        Ok(old) => old,
+        Err(e) => {
+            eprintln!("{}: {}: {}", "error".red().bold(), old_path.display(), e);
+            std::process::exit(1);
+        }
+    };
+    let changes = old.diff(&lockfile);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&changes).into_diagnostic()?
+        );
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        println!("{}", "No lockfile changes".dimmed());
+        return Ok(());
+    }
+    for pkg in &changes.added {
+        println!("{} {}@{}", "+".green(), pkg.name, pkg.version.dimmed());
+    }
+    for pkg in &changes.removed {
+        println!("{} {}@{}", "-".red(), pkg.name, pkg.version.dimmed());
+    }
+    for change in &changes.changed {
+        println!(
+            "{} {} {} -> {}",
+            "~".yellow(),
+            change.name,
+            change.from.dimmed(),
+            change.to
+        );
+    }
+    println!();
+    println!(
+        "{} added, {} removed, {} changed",
+        changes.added.len(),
+        changes.removed.len(),
+        changes.changed.len()
+    );
+    Ok(())
+}
+
 #[cfg(feature = "pm")]
 fn pm_cache() -> Result<()> {
     println!("{}: ~/.viper/cache", "Cache directory".cyan());
@@ -0,0 +1,484 @@
+//! `viper audit`: check locked packages against the registry's advisory database
+//!
+//! Every `name@version` in `viper.lock` is posted to the npm bulk advisory
+//! endpoint (`/-/npm/v1/security/advisories/bulk`). Advisories are matched
+//! back to locked versions and reported with the dependency paths that pull
+//! them in. `audit fix` re-resolves vulnerable packages when a patched
+//! version exists inside every range that requests them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use async_std::task;
+use node_semver::{Range, Version};
+use serde::Serialize;
+
+use super::error::{PmError, PmResult};
+use super::graph::{DependencyGraph, PathStep};
+use super::installer::{InstallResult, PackageManager};
+use super::lockfile::{LOCKFILE_NAME, Lockfile};
+use super::registry::RegistryClient;
+
+/// Advisory severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// All severities, most severe first
+    pub const ALL: [Severity; 5] = [
+        Severity::Critical,
+        Severity::High,
+        Severity::Moderate,
+        Severity::Low,
+        Severity::Info,
+    ];
+}
+
+impl FromStr for Severity {
+    type Err = PmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "low" => Ok(Severity::Low),
+            "moderate" => Ok(Severity::Moderate),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            other => Err(PmError::Other(format!(
+                "Unknown severity '{}' (expected info, low, moderate, high or critical)",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Moderate => "moderate",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A security advisory from the registry
+#[derive(Debug, Clone, Serialize)]
+pub struct Advisory {
+    pub id: u64,
+    pub title: String,
+    pub url: String,
+    pub severity: Severity,
+    /// Semver range of affected versions
+    pub vulnerable_versions: String,
+}
+
+impl Advisory {
+    /// Whether `version` is in the vulnerable range. Unparseable versions
+    /// and ranges count as affected: report rather than hide.
+    pub fn affects(&self, version: &str) -> bool {
+        match (
+            Version::parse(version),
+            Range::parse(&self.vulnerable_versions),
+        ) {
+            (Ok(v), Ok(range)) => range.satisfies(&v),
+            _ => true,
+        }
+    }
+}
+
+/// A locked package version affected by one or more advisories
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditFinding {
+    pub name: String,
+    pub version: String,
+    pub advisories: Vec<Advisory>,
+    /// Dependency paths from the project root to this package
+    pub paths: Vec<Vec<PathStep>>,
+}
+
+impl AuditFinding {
+    /// Highest severity among this finding's advisories
+    pub fn severity(&self) -> Severity {
+        self.advisories
+            .iter()
+            .map(|a| a.severity)
+            .max()
+            .unwrap_or(Severity::Info)
+    }
+}
+
+/// Result of `viper audit`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    /// Number of distinct `name@version` pairs checked
+    pub packages_scanned: usize,
+    pub findings: Vec<AuditFinding>,
+}
+
+impl AuditReport {
+    /// Match a bulk advisory response against the locked packages
+    pub fn from_response(graph: &DependencyGraph, response: &serde_json::Value) -> PmResult<Self> {
+        let response = response.as_object().ok_or_else(|| {
+            PmError::Registry("Advisory response is not a JSON object".to_string())
+        })?;
+
+        let locked = locked_versions(graph.lockfile());
+        let mut findings = Vec::new();
+
+        for (name, versions) in &locked {
+            let Some(advisories) = response.get(name).and_then(|a| a.as_array()) else {
+                continue;
+            };
+            let advisories: Vec<Advisory> = advisories.iter().filter_map(parse_advisory).collect();
+
+            for version in versions {
+                let affecting: Vec<Advisory> = advisories
+                    .iter()
+                    .filter(|advisory| advisory.affects(version))
+                    .cloned()
+                    .collect();
+                if affecting.is_empty() {
+                    continue;
+                }
+                findings.push(AuditFinding {
+                    name: name.clone(),
+                    version: version.clone(),
+                    advisories: affecting,
+                    paths: graph.why(&format!("{}@{}", name, version)),
+                });
+            }
+        }
+
+        findings.sort_by(|a, b| b.severity().cmp(&a.severity()).then(a.name.cmp(&b.name)));
+
+        Ok(Self {
+            packages_scanned: locked.values().map(BTreeSet::len).sum(),
+            findings,
+        })
+    }
+
+    /// Number of findings whose highest severity is `severity`
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity() == severity)
+            .count()
+    }
+
+    /// Whether any finding is at or above `level`
+    pub fn exceeds(&self, level: Severity) -> bool {
+        self.findings.iter().any(|f| f.severity() >= level)
+    }
+}
+
+/// A vulnerable package bumped to a patched version
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditFix {
+    pub name: String,
+    pub from: String,
+    /// Version the reinstall resolved (the predicted patched version for dry
+    /// runs); comma-separated when dependents resolve different versions
+    pub to: String,
+}
+
+/// Result of `viper audit fix`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditFixResult {
+    pub fixed: Vec<AuditFix>,
+    /// Findings with no patched version inside the requested ranges, or
+    /// that the reinstall still resolved to an affected version
+    pub unfixable: Vec<AuditFinding>,
+    /// The reinstall that applied the fixes (not run for dry runs)
+    #[serde(skip)]
+    pub install: Option<InstallResult>,
+}
+
+/// Locked versions grouped by package name
+fn locked_versions(lockfile: &Lockfile) -> BTreeMap<String, BTreeSet<String>> {
+    let mut locked: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for pkg in lockfile.packages.values() {
+        if let Some(version) = &pkg.version {
+            locked
+                .entry(pkg.name().to_string())
+                .or_default()
+                .insert(version.clone());
+        }
+    }
+    locked
+}
+
+fn parse_advisory(value: &serde_json::Value) -> Option<Advisory> {
+    let str_field = |key: &str| value.get(key).and_then(|v| v.as_str());
+    Some(Advisory {
+        id: value.get("id").and_then(|v| v.as_u64()).unwrap_or_default(),
+        title: str_field("title").unwrap_or_default().to_string(),
+        url: str_field("url").unwrap_or_default().to_string(),
+        severity: str_field("severity")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(Severity::Info),
+        vulnerable_versions: str_field("vulnerable_versions")?.to_string(),
+    })
+}
+
+impl PackageManager {
+    /// Audit locked packages against the advisory database
+    pub fn audit(&self) -> PmResult<AuditReport> {
+        let graph = self.graph()?;
+        let body: serde_json::Map<String, serde_json::Value> = locked_versions(graph.lockfile())
+            .into_iter()
+            .map(|(name, versions)| (name, serde_json::json!(versions)))
+            .collect();
+
+        let client = RegistryClient::new(self.config())?;
+        let response = client.post_json(
+            &self.config().advisory_url()?,
+            &serde_json::Value::Object(body),
+        )?;
+        AuditReport::from_response(&graph, &response)
+    }
+
+    /// Bump vulnerable packages to patched versions within their ranges
+    pub fn audit_fix(&self, dry_run: bool) -> PmResult<AuditFixResult> {
+        task::block_on(self.audit_fix_async(dry_run))
+    }
+
+    /// Bump vulnerable packages asynchronously
+    pub async fn audit_fix_async(&self, dry_run: bool) -> PmResult<AuditFixResult> {
+        let report = self.audit()?;
+        let graph = self.graph()?;
+        let nassun = self.nassun()?;
+        let mut lockfile = graph.lockfile().clone();
+        let mut result = AuditFixResult::default();
+        // Findings to re-resolve, with the install paths of their entries
+        let mut candidates = Vec::new();
+
+        for finding in report.findings {
+            let Some(patched) = patched_version(&nassun, &graph, &finding).await? else {
+                result.unfixable.push(finding);
+                continue;
+            };
+
+            // Dropping the entries makes the resolver pick the newest
+            // in-range version again, which should be at least `patched`
+            let mut paths = Vec::new();
+            lockfile.packages.retain(|_, pkg| {
+                let vulnerable = pkg.name() == finding.name
+                    && pkg.version.as_deref() == Some(finding.version.as_str());
+                if vulnerable {
+                    paths.push(pkg.path.clone());
+                }
+                !vulnerable
+            });
+            candidates.push((finding, patched, paths));
+        }
+
+        if dry_run || candidates.is_empty() {
+            for (finding, patched, _) in candidates {
+                result.fixed.push(AuditFix {
+                    name: finding.name,
+                    from: finding.version,
+                    to: patched,
+                });
+            }
+            return Ok(result);
+        }
+
+        let root = &self.config().root;
+        async_std::fs::write(root.join(LOCKFILE_NAME), lockfile.to_kdl())
+            .await
+            .map_err(|e| PmError::Lockfile(format!("Failed to write lockfile: {}", e)))?;
+        result.install = Some(self.install_async().await?);
+
+        // Report what the reinstall actually resolved, not the prediction
+        let installed = Lockfile::load(root)?.unwrap_or_default();
+        for (finding, _, paths) in candidates {
+            match resolved_fix(&installed, &finding, &paths) {
+                Some(to) => result.fixed.push(AuditFix {
+                    name: finding.name,
+                    from: finding.version,
+                    to,
+                }),
+                None => result.unfixable.push(finding),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// The versions dependents of a finding's old install `paths` resolve to in
+/// `installed`, or `None` if any of them is missing or still affected
+fn resolved_fix(
+    installed: &Lockfile,
+    finding: &AuditFinding,
+    paths: &[Vec<String>],
+) -> Option<String> {
+    let mut versions = BTreeSet::new();
+    for path in paths {
+        let parent = &path[..path.len().saturating_sub(1)];
+        let version = installed
+            .resolve(parent, &finding.name)?
+            .version
+            .as_deref()?;
+        if finding.advisories.iter().any(|a| a.affects(version)) {
+            return None;
+        }
+        versions.insert(version);
+    }
+    if versions.is_empty() {
+        return None;
+    }
+    Some(versions.into_iter().collect::<Vec<_>>().join(", "))
+}
+
+/// Newest non-vulnerable version satisfying every range that requests the
+/// vulnerable package
+async fn patched_version(
+    nassun: &nassun::Nassun,
+    graph: &DependencyGraph,
+    finding: &AuditFinding,
+) -> PmResult<Option<String>> {
+    let requested: Vec<Range> = graph
+        .lockfile()
+        .packages
+        .iter()
+        .filter(|(_, pkg)| {
+            pkg.name() == finding.name && pkg.version.as_deref() == Some(finding.version.as_str())
+        })
+        .flat_map(|(key, _)| graph.requested_ranges(key))
+        .filter_map(|range| Range::parse(range).ok())
+        .collect();
+    let vulnerable: Vec<Range> = finding
+        .advisories
+        .iter()
+        .filter_map(|a| Range::parse(&a.vulnerable_versions).ok())
+        .collect();
+    let current = Version::parse(&finding.version).ok();
+
+    let packument = nassun
+        .resolve(&finding.name)
+        .await
+        .map_err(|e| PmError::PackageNotFound(format!("{}: {}", finding.name, e)))?
+        .packument()
+        .await
+        .map_err(|e| PmError::Network(e.to_string()))?;
+
+    let best = packument
+        .versions
+        .keys()
+        .filter(|v| current.as_ref().is_none_or(|c| *v > c))
+        .filter(|v| requested.iter().all(|range| range.satisfies(v)))
+        .filter(|v| !vulnerable.iter().any(|range| range.satisfies(v)))
+        .max();
+
+    Ok(best.map(|v| v.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_matches_locked_versions() {
+        let lockfile = Lockfile::parse(
+            r#"lockfile-version 1
+root {
+    dependencies {
+        a "^1.0.0"
+        lodash "^4.17.21"
+    }
+}
+pkg "a" {
+    version "1.0.0"
+    dependencies {
+        lodash "^4.0.0"
+    }
+}
+pkg "a" "lodash" {
+    version "4.17.20"
+}
+pkg "lodash" {
+    version "4.17.21"
+}
+"#,
+        )
+        .unwrap();
+        let graph = DependencyGraph::from_lockfile(lockfile);
+        let response = serde_json::json!({
+            "lodash": [{
+                "id": 1523,
+                "title": "Prototype Pollution in lodash",
+                "url": "https://github.com/advisories/GHSA-p6mc-m468-83gw",
+                "severity": "high",
+                "vulnerable_versions": "<4.17.21"
+            }]
+        });
+
+        let report = AuditReport::from_response(&graph, &response).unwrap();
+        assert_eq!(report.packages_scanned, 3);
+        assert_eq!(report.findings.len(), 1);
+
+        let finding = &report.findings[0];
+        assert_eq!(finding.version, "4.17.20");
+        assert_eq!(finding.paths.len(), 1);
+        assert_eq!(finding.paths[0][0].name, "a");
+        assert_eq!(report.count(Severity::High), 1);
+        assert!(report.exceeds(Severity::Moderate));
+        assert!(!report.exceeds(Severity::Critical));
+    }
+
+    #[test]
+    fn test_fix_uses_resolved_version() {
+        let finding = AuditFinding {
+            name: "lodash".to_string(),
+            version: "4.17.20".to_string(),
+            advisories: vec![Advisory {
+                id: 1523,
+                title: "Prototype Pollution in lodash".to_string(),
+                url: String::new(),
+                severity: Severity::High,
+                vulnerable_versions: "<4.17.21".to_string(),
+            }],
+            paths: Vec::new(),
+        };
+        let paths = vec![vec!["a".to_string(), "lodash".to_string()]];
+        let installed = |version: &str| {
+            Lockfile::parse(&format!(
+                "lockfile-version 1\nroot {{\n}}\npkg \"a\" {{\n    version \"1.0.0\"\n}}\n\
+                 pkg \"lodash\" {{\n    version \"{}\"\n}}\n",
+                version
+            ))
+            .unwrap()
+        };
+
+        // The nested copy is gone and `a` now resolves the hoisted one
+        assert_eq!(
+            resolved_fix(&installed("4.17.21"), &finding, &paths).as_deref(),
+            Some("4.17.21")
+        );
+        // The reinstall picked a version that is still affected
+        assert!(resolved_fix(&installed("4.17.19"), &finding, &paths).is_none());
+        // Nothing resolves for the dependent any more
+        let empty = Lockfile::parse("lockfile-version 1\nroot {\n}\n").unwrap();
+        assert!(resolved_fix(&empty, &finding, &paths).is_none());
+    }
+
+    #[test]
+    fn test_severity_order() {
+        assert!(Severity::Critical > Severity::High);
+        assert_eq!("Moderate".parse::<Severity>().unwrap(), Severity::Moderate);
+        assert!("severe".parse::<Severity>().is_err());
+    }
+}
@@ -32,6 +32,12 @@ pub enum PmError {
     #[error("Registry error: {0}")]
     Registry(String),
 
+    #[error("Lifecycle script failed: {0}")]
+    Script(String),
+
+    #[error("Invalid .npmrc: {0}")]
+    Config(String),
+
     #[error("{0}")]
     Other(String),
 }
@@ -0,0 +1,426 @@
+//! Dependency graph built from the lockfile's dependency edges
+//!
+//! Powers `viper pm why`, `viper pm ls --tree` and `viper pm dedupe`. Edges
+//! are resolved with Node's lookup rules against the locked `node_modules`
+//! layout, so the graph reflects what is actually installed.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use serde::Serialize;
+
+use super::lockfile::{LockedPackage, Lockfile, package_key};
+
+/// Key of the project root in the graph
+const ROOT: &str = "";
+
+/// How a package depends on another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    Prod,
+    Dev,
+    Optional,
+    Peer,
+}
+
+/// A dependency edge
+#[derive(Debug, Clone)]
+struct Edge {
+    name: String,
+    range: String,
+    kind: DependencyKind,
+    /// Lockfile key of the package the edge resolves to (`None` if missing)
+    target: Option<String>,
+}
+
+/// One step in a dependency path
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PathStep {
+    pub name: String,
+    pub version: String,
+    /// Range requested by the parent
+    pub range: String,
+    pub kind: DependencyKind,
+}
+
+/// A node in `viper pm ls --tree` output
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub kind: DependencyKind,
+    /// Already shown elsewhere in the tree; children omitted
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deduped: bool,
+    /// Required but not present in the lockfile
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub missing: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<TreeNode>,
+}
+
+/// A package installed in more than one place
+#[derive(Debug, Clone, Serialize)]
+pub struct Duplicate {
+    pub name: String,
+    pub versions: Vec<DuplicateVersion>,
+}
+
+/// One version of a duplicated package and where it is installed
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateVersion {
+    pub version: String,
+    /// `node_modules` locations holding this version
+    pub locations: Vec<String>,
+}
+
+/// Resolved dependency graph of a project
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    lockfile: Lockfile,
+    edges: BTreeMap<String, Vec<Edge>>,
+}
+
+impl DependencyGraph {
+    /// Build the graph from a lockfile
+    pub fn from_lockfile(lockfile: Lockfile) -> Self {
+        let mut edges = BTreeMap::new();
+
+        let root = &lockfile.root;
+        let mut root_edges = Vec::new();
+        for (deps, kind) in [
+            (&root.dependencies, DependencyKind::Prod),
+            (&root.dev_dependencies, DependencyKind::Dev),
+            (&root.optional_dependencies, DependencyKind::Optional),
+            (&root.peer_dependencies, DependencyKind::Peer),
+        ] {
+            for (name, range) in deps {
+                root_edges.push(Self::edge(&lockfile, &[], name, range, kind));
+            }
+        }
+        edges.insert(ROOT.to_string(), root_edges);
+
+        for (key, pkg) in &lockfile.packages {
+            // devDependencies of installed packages are never installed
+            let mut pkg_edges = Vec::new();
+            for (deps, kind) in [
+                (&pkg.dependencies, DependencyKind::Prod),
+                (&pkg.optional_dependencies, DependencyKind::Optional),
+                (&pkg.peer_dependencies, DependencyKind::Peer),
+            ] {
+                for (name, range) in deps {
+                    pkg_edges.push(Self::edge(&lockfile, &pkg.path, name, range, kind));
+                }
+            }
+            edges.insert(key.clone(), pkg_edges);
+        }
+
+        Self { lockfile, edges }
+    }
+
+    fn edge(
+        lockfile: &Lockfile,
+        from: &[String],
+        name: &str,
+        range: &str,
+        kind: DependencyKind,
+    ) -> Edge {
+        let target = lockfile
+            .resolve(from, name)
+            .map(|pkg| package_key(&pkg.path));
+        Edge {
+            name: name.to_string(),
+            range: range.to_string(),
+            kind,
+            target,
+        }
+    }
+
+    /// The lockfile this graph was built from
+    pub fn lockfile(&self) -> &Lockfile {
+        &self.lockfile
+    }
+
+    /// Ranges requested for the package at `key` by everything that
+    /// resolves to it
+    pub fn requested_ranges(&self, key: &str) -> Vec<&str> {
+        self.edges
+            .values()
+            .flatten()
+            .filter(|edge| edge.target.as_deref() == Some(key))
+            .map(|edge| edge.range.as_str())
+            .collect()
+    }
+
+    /// Every dependency path from the root to packages matching `query`
+    /// (`name` or `name@version`)
+    pub fn why(&self, query: &str) -> Vec<Vec<PathStep>> {
+        let (name, version) = split_query(query);
+        let matches = |pkg: &LockedPackage| {
+            pkg.name() == name && version.is_none_or(|v| pkg.version.as_deref() == Some(v))
+        };
+
+        // Only walk edges that can lead to a match, so large trees stay cheap
+        let reachable = self.can_reach(&matches);
+        let mut paths = Vec::new();
+        let mut stack = Vec::new();
+        let mut on_stack = HashSet::new();
+        self.walk_paths(
+            ROOT,
+            &matches,
+            &reachable,
+            &mut stack,
+            &mut on_stack,
+            &mut paths,
+        );
+        paths
+    }
+
+    fn walk_paths(
+        &self,
+        key: &str,
+        matches: &dyn Fn(&LockedPackage) -> bool,
+        reachable: &HashSet<String>,
+        stack: &mut Vec<PathStep>,
+        on_stack: &mut HashSet<String>,
+        paths: &mut Vec<Vec<PathStep>>,
+    ) {
+        let Some(edges) = self.edges.get(key) else {
+            return;
+        };
+        for edge in edges {
+            let Some(target) = &edge.target else { continue };
+            if !reachable.contains(target) || on_stack.contains(target) {
+                continue;
+            }
+            let pkg = &self.lockfile.packages[target];
+            stack.push(PathStep {
+                name: pkg.name().to_string(),
+                version: pkg.version.clone().unwrap_or_default(),
+                range: edge.range.clone(),
+                kind: edge.kind,
+            });
+
+            if matches(pkg) {
+                paths.push(stack.clone());
+            } else {
+                on_stack.insert(target.clone());
+                self.walk_paths(target, matches, reachable, stack, on_stack, paths);
+                on_stack.remove(target);
+            }
+            stack.pop();
+        }
+    }
+
+    /// Keys of packages that match or transitively depend on a match
+    fn can_reach(&self, matches: &dyn Fn(&LockedPackage) -> bool) -> HashSet<String> {
+        let mut reverse: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (from, edges) in &self.edges {
+            for edge in edges {
+                if let Some(target) = &edge.target {
+                    reverse
+                        .entry(target.as_str())
+                        .or_default()
+                        .push(from.as_str());
+                }
+            }
+        }
+
+        let mut reachable = HashSet::new();
+        let mut queue: VecDeque<&str> = self
+            .lockfile
+            .packages
+            .iter()
+            .filter(|(_, pkg)| matches(pkg))
+            .map(|(key, _)| key.as_str())
+            .collect();
+        while let Some(key) = queue.pop_front() {
+            if !reachable.insert(key.to_string()) {
+                continue;
+            }
+            if let Some(parents) = reverse.get(key) {
+                queue.extend(parents.iter().copied());
+            }
+        }
+        reachable
+    }
+
+    /// Dependency tree from the root, down to `max_depth` levels below the
+    /// top-level packages (`None` for the full tree)
+    pub fn tree(&self, max_depth: Option<usize>) -> Vec<TreeNode> {
+        let mut seen = HashSet::new();
+        self.subtree(ROOT, 0, max_depth, &mut seen)
+    }
+
+    fn subtree(
+        &self,
+        key: &str,
+        depth: usize,
+        max_depth: Option<usize>,
+        seen: &mut HashSet<String>,
+    ) -> Vec<TreeNode> {
+        let Some(edges) = self.edges.get(key) else {
+            return Vec::new();
+        };
+
+        let mut nodes = Vec::new();
+        for edge in edges {
+            let Some(target) = &edge.target else {
+                // Missing optional/peer deps are expected
+                if edge.kind == DependencyKind::Prod || edge.kind == DependencyKind::Dev {
+                    nodes.push(TreeNode {
+                        name: edge.name.clone(),
+                        version: None,
+                        kind: edge.kind,
+                        deduped: false,
+                        missing: true,
+                        dependencies: Vec::new(),
+                    });
+                }
+                continue;
+            };
+
+            let pkg = &self.lockfile.packages[target];
+            let deduped = !seen.insert(target.clone());
+            let dependencies = if deduped || max_depth.is_some_and(|max| depth >= max) {
+                Vec::new()
+            } else {
+                self.subtree(target, depth + 1, max_depth, seen)
+            };
+            nodes.push(TreeNode {
+                name: edge.name.clone(),
+                version: pkg.version.clone(),
+                kind: edge.kind,
+                deduped,
+                missing: false,
+                dependencies,
+            });
+        }
+        nodes
+    }
+
+    /// Packages installed at more than one location, with their versions
+    pub fn duplicates(&self) -> Vec<Duplicate> {
+        let mut by_name: BTreeMap<&str, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+        for (key, pkg) in &self.lockfile.packages {
+            by_name
+                .entry(pkg.name())
+                .or_default()
+                .entry(pkg.version.clone().unwrap_or_default())
+                .or_default()
+                .push(key.clone());
+        }
+
+        by_name
+            .into_iter()
+            .filter(|(_, versions)| versions.values().map(Vec::len).sum::<usize>() > 1)
+            .map(|(name, versions)| Duplicate {
+                name: name.to_string(),
+                versions: versions
+                    .into_iter()
+                    .map(|(version, locations)| DuplicateVersion { version, locations })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Split `name@version`, keeping the scope of `@scope/name`
+fn split_query(query: &str) -> (&str, Option<&str>) {
+    match query.rfind('@') {
+        Some(pos) if pos > 0 => (&query[..pos], Some(&query[pos + 1..])),
+        _ => (query, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> DependencyGraph {
+        let lockfile = Lockfile::parse(
+            r#"lockfile-version 1
+root {
+    dependencies {
+        a "^1.0.0"
+        b "^1.0.0"
+    }
+    dev-dependencies {
+        c "^1.0.0"
+    }
+}
+pkg "a" {
+    version "1.0.0"
+    dependencies {
+        ms "^2.0.0"
+    }
+}
+pkg "b" {
+    version "1.0.0"
+    dependencies {
+        a "^1.0.0"
+        ms "^1.0.0"
+    }
+}
+pkg "b" "ms" {
+    version "1.0.0"
+}
+pkg "c" {
+    version "1.0.0"
+    dependencies {
+        ms "^2.0.0"
+    }
+}
+pkg "ms" {
+    version "2.1.3"
+}
+"#,
+        )
+        .unwrap();
+        DependencyGraph::from_lockfile(lockfile)
+    }
+
+    #[test]
+    fn test_why() {
+        let graph = graph();
+        let paths = graph.why("ms@2.1.3");
+        let rendered: Vec<String> = paths
+            .iter()
+            .map(|p| {
+                p.iter()
+                    .map(|s| s.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(">")
+            })
+            .collect();
+        assert_eq!(rendered, vec!["a>ms", "b>a>ms", "c>ms"]);
+        assert_eq!(paths[2][0].kind, DependencyKind::Dev);
+
+        let old = graph.why("ms@1.0.0");
+        assert_eq!(old.len(), 1);
+        assert_eq!(old[0][1].range, "^1.0.0");
+        assert_eq!(graph.why("ms").len(), 4);
+    }
+
+    #[test]
+    fn test_tree_marks_deduped() {
+        let tree = graph().tree(None);
+        assert_eq!(tree.len(), 3);
+        let b = &tree[1];
+        assert_eq!(b.name, "b");
+        assert!(b.dependencies[0].deduped);
+        assert_eq!(b.dependencies[1].version.as_deref(), Some("1.0.0"));
+
+        let shallow = graph().tree(Some(0));
+        assert!(shallow.iter().all(|n| n.dependencies.is_empty()));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let dups = graph().duplicates();
+        assert_eq!(dups.len(), 1);
+        assert_eq!(dups[0].name, "ms");
+        assert_eq!(dups[0].versions.len(), 2);
+    }
+}
@@ -13,6 +13,9 @@ use oro_common::CorgiManifest;
 use url::Url;
 
 use super::error::{PmError, PmResult};
+use super::graph::DependencyGraph;
+use super::lockfile::{LOCKFILE_NAME, Lockfile};
+use super::npmrc::{Npmrc, RegistryAuth};
 use super::{DEFAULT_CONCURRENCY, DEFAULT_REGISTRY};
 
 /// Create a Bun-style spinner progress bar
@@ -29,6 +32,26 @@ fn create_spinner(msg: &str) -> ProgressBar {
     pb
 }
 
+/// Apply `.npmrc` credentials to `NassunOpts` or `NodeMaintainerOptions`,
+/// which expose the same per-registry auth setters
+macro_rules! with_credentials {
+    ($opts:expr, $config:expr) => {{
+        let mut opts = $opts;
+        for (registry, auth) in $config.credentials() {
+            opts = if let Some(token) = &auth.token {
+                opts.token_auth(registry, token)
+            } else if let Some(legacy) = &auth.legacy_auth {
+                opts.legacy_auth(registry, legacy)
+            } else if let Some(username) = &auth.username {
+                opts.basic_auth(registry, username, auth.password.as_deref())
+            } else {
+                opts
+            };
+        }
+        opts
+    }};
+}
+
 /// Package manager configuration
 #[derive(Debug, Clone)]
 pub struct PackageManagerConfig {
@@ -42,6 +65,10 @@ pub struct PackageManagerConfig {
     pub hoisted: bool,
     /// Show progress bars
     pub progress: bool,
+    /// Settings loaded from `.npmrc` files (scoped registries, auth, CA)
+    pub npmrc: Npmrc,
+    /// Bulk advisory endpoint for `viper audit` (defaults to the registry's)
+    pub audit_url: Option<Url>,
 }
 
 impl Default for PackageManagerConfig {
@@ -52,6 +79,8 @@ impl Default for PackageManagerConfig {
             concurrency: DEFAULT_CONCURRENCY,
             hoisted: true,
             progress: true,
+            npmrc: Npmrc::default(),
+            audit_url: None,
         }
     }
 }
@@ -72,6 +101,25 @@ impl PackageManagerConfig {
         Ok(self)
     }
 
+    /// Set the bulk advisory endpoint used by `viper audit`
+    pub fn audit_url(mut self, url: &str) -> PmResult<Self> {
+        self.audit_url = Some(
+            Url::parse(url).map_err(|e| PmError::Registry(format!("Invalid audit URL: {}", e)))?,
+        );
+        Ok(self)
+    }
+
+    /// Bulk advisory endpoint: the configured one, or the registry's
+    pub fn advisory_url(&self) -> PmResult<Url> {
+        match &self.audit_url {
+            Some(url) => Ok(url.clone()),
+            None => self
+                .registry
+                .join("-/npm/v1/security/advisories/bulk")
+                .map_err(|e| PmError::Registry(format!("Invalid registry URL: {}", e))),
+        }
+    }
+
     /// Set concurrency level
     pub fn concurrency(mut self, n: usize) -> Self {
         self.concurrency = n;
@@ -89,6 +137,24 @@ impl PackageManagerConfig {
         self.progress = show;
         self
     }
+
+    /// Load global, user and project `.npmrc` files.
+    ///
+    /// A `registry=` setting replaces the default registry; call
+    /// [`registry`](Self::registry) afterwards to override it.
+    pub fn load_npmrc(mut self) -> PmResult<Self> {
+        let npmrc = Npmrc::load(&self.root)?;
+        if let Some(registry) = &npmrc.registry {
+            self.registry = registry.clone();
+        }
+        self.npmrc = npmrc;
+        Ok(self)
+    }
+
+    /// `.npmrc` credentials for the configured registries
+    pub fn credentials(&self) -> Vec<(Url, RegistryAuth)> {
+        self.npmrc.credentials(&self.registry)
+    }
 }
 
 /// Viper Package Manager
@@ -109,6 +175,38 @@ impl PackageManager {
         Self { config }
     }
 
+    /// The configuration this package manager was created with
+    pub fn config(&self) -> &PackageManagerConfig {
+        &self.config
+    }
+
+    /// Orogene builds its own HTTP client with no way to add roots, so
+    /// `.npmrc` CAs can't apply to anything it downloads
+    fn check_ca(&self) -> PmResult<()> {
+        if self.config.npmrc.has_ca() {
+            return Err(PmError::Config(
+                "ca and cafile only apply to audit and publish; remove them to download packages"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Build a registry client honoring scoped registries and auth
+    pub(crate) fn nassun(&self) -> PmResult<nassun::Nassun> {
+        use nassun::NassunOpts;
+
+        self.check_ca()?;
+        let mut opts = with_credentials!(
+            NassunOpts::new().registry(self.config.registry.clone()),
+            self.config
+        );
+        for (scope, url) in &self.config.npmrc.scoped_registries {
+            opts = opts.scope_registry(scope, url.clone());
+        }
+        Ok(opts.build())
+    }
+
     /// Install all dependencies from package.json
     pub fn install(&self) -> PmResult<InstallResult> {
         task::block_on(self.install_async())
@@ -116,6 +214,7 @@ impl PackageManager {
 
     /// Install all dependencies asynchronously
     pub async fn install_async(&self) -> PmResult<InstallResult> {
+        self.check_ca()?;
         let total_start = Instant::now();
         let package_json_path = self.config.root.join("package.json");
 
@@ -150,7 +249,7 @@ impl PackageManager {
         let spinner_clone = spinner.clone();
 
         // on_resolve_progress takes |&Package, Duration|
-        let opts = NodeMaintainerOptions::new()
+        let mut opts = NodeMaintainerOptions::new()
             .root(&self.config.root)
             .registry(self.config.registry.clone())
             .concurrency(self.config.concurrency)
@@ -165,6 +264,29 @@ impl PackageManager {
                     pb.set_message(format!("Resolving: {} ({})", pkg_name, count));
                 }
             });
+        opts = with_credentials!(opts, self.config);
+        for (scope, url) in &self.config.npmrc.scoped_registries {
+            opts = opts.scope_registry(scope, url.clone());
+        }
+
+        // Keep pinned versions: prefer viper.lock, otherwise migrate another
+        // package manager's lockfile on first install
+        let mut imported_lockfile = None;
+        let existing_lock = match Lockfile::load(&self.config.root)? {
+            Some(lock) => Some(lock),
+            None => match Lockfile::import(&self.config.root, &self.config.registry)? {
+                Some((lock, source)) => {
+                    imported_lockfile = Some(source.to_string());
+                    Some(lock)
+                }
+                None => None,
+            },
+        };
+        if let Some(lock) = &existing_lock {
+            opts = opts
+                .kdl_lock(lock.to_kdl())
+                .map_err(|e| PmError::Lockfile(e.to_string()))?;
+        }
 
         // Resolve dependencies
         let resolve_start = Instant::now();
@@ -197,72 +319,33 @@ impl PackageManager {
             pb.finish_and_clear();
         }
 
-        // Write lockfile using to_lockfile() and convert to KDL format
-        let lockfile_path = self.config.root.join("viper.lock");
-        let lockfile = maintainer
+        // Write Orogene's lockfile as-is; our model of it is only used for
+        // the summary below, so a node it can't read doesn't fail the install
+        let lockfile_path = self.config.root.join(LOCKFILE_NAME);
+        let kdl = maintainer
             .to_kdl()
             .map_err(|e| PmError::Lockfile(e.to_string()))?;
+        let lockfile = Lockfile::from_kdl(&kdl).unwrap_or_default();
 
-        let lockfile_str = lockfile.to_string();
-
-        async_std::fs::write(&lockfile_path, lockfile_str)
+        async_std::fs::write(&lockfile_path, kdl.to_string())
             .await
             .map_err(|e| PmError::Lockfile(format!("Failed to write lockfile: {}", e)))?;
 
         let total_time = total_start.elapsed();
 
-        // Get resolved versions from lockfile for display
-        // Parse the KDL lockfile to find actual resolved versions
-        let mut version_map: std::collections::HashMap<String, String> =
-            std::collections::HashMap::new();
-        if let Ok(lock_content) = async_std::fs::read_to_string(&lockfile_path).await {
-            // Simple parsing: find "pkg "name" { version "x.y.z" }" patterns
-            for line in lock_content.lines() {
-                let trimmed = line.trim();
-                if trimmed.starts_with("pkg \"") {
-                    // Extract package name
-                    if let Some(end) = trimmed[5..].find('"') {
-                        let pkg_name = &trimmed[5..5 + end];
-                        // Look for version in next few lines (simplified parsing)
-                        let remaining = &lock_content[lock_content.find(trimmed).unwrap_or(0)..];
-                        for version_line in remaining.lines().take(5) {
-                            let vl = version_line.trim();
-                            if vl.starts_with("version \"") {
-                                if let Some(vend) = vl[9..].find('"') {
-                                    let version = &vl[9..9 + vend];
-                                    version_map.insert(pkg_name.to_string(), version.to_string());
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        // Read package.json to get top-level dependency names, then lookup versions
+        // Report top-level dependencies with their resolved versions
         let mut added_packages = Vec::new();
-        if let Ok(pkg_content) = async_std::fs::read_to_string(&package_json_path).await {
-            if let Ok(pkg_json) = serde_json::from_str::<serde_json::Value>(&pkg_content) {
-                if let Some(deps) = pkg_json.get("dependencies").and_then(|d| d.as_object()) {
-                    for (name, _) in deps {
-                        let version = version_map
-                            .get(name)
-                            .cloned()
-                            .unwrap_or_else(|| "*".to_string());
-                        added_packages.push(format!("{}@{}", name, version));
-                    }
-                }
-                if let Some(deps) = pkg_json.get("devDependencies").and_then(|d| d.as_object()) {
-                    for (name, _) in deps {
-                        let version = version_map
-                            .get(name)
-                            .cloned()
-                            .unwrap_or_else(|| "*".to_string());
-                        added_packages.push(format!("{}@{}", name, version));
-                    }
-                }
-            }
+        for (name, _) in lockfile
+            .root
+            .dependencies
+            .iter()
+            .chain(lockfile.root.dev_dependencies.iter())
+        {
+            let version = lockfile
+                .top_level(name)
+                .and_then(|pkg| pkg.version.clone())
+                .unwrap_or_else(|| "*".to_string());
+            added_packages.push(format!("{}@{}", name, version));
         }
 
         Ok(InstallResult {
@@ -273,6 +356,7 @@ impl PackageManager {
             total_time,
             added_packages,
             removed_packages: Vec::new(),
+            imported_lockfile,
         })
     }
 
@@ -283,6 +367,7 @@ impl PackageManager {
 
     /// Add packages asynchronously
     pub async fn add_async(&self, packages: &[&str], dev: bool) -> PmResult<InstallResult> {
+        self.check_ca()?;
         let package_json_path = self.config.root.join("package.json");
 
         // Read existing manifest or create new one
@@ -343,6 +428,7 @@ impl PackageManager {
 
     /// Remove packages asynchronously
     pub async fn remove_async(&self, packages: &[&str]) -> PmResult<()> {
+        self.check_ca()?;
         let package_json_path = self.config.root.join("package.json");
 
         if !package_json_path.exists() {
@@ -428,11 +514,7 @@ impl PackageManager {
 
     /// View package information asynchronously
     pub async fn view_async(&self, package: &str) -> PmResult<PackageInfo> {
-        use nassun::NassunOpts;
-
-        let nassun = NassunOpts::new()
-            .registry(self.config.registry.clone())
-            .build();
+        let nassun = self.nassun()?;
 
         let pkg = nassun
             .resolve(package)
@@ -519,13 +601,9 @@ impl PackageManager {
 
     /// Ping the registry asynchronously
     pub async fn ping_async(&self) -> PmResult<Duration> {
-        use nassun::NassunOpts;
-
         let start = Instant::now();
 
-        let nassun = NassunOpts::new()
-            .registry(self.config.registry.clone())
-            .build();
+        let nassun = self.nassun()?;
 
         // Try to resolve a well-known package to test connectivity
         nassun
@@ -583,6 +661,17 @@ impl PackageManager {
         Ok(())
     }
 
+    /// Dependency graph from `viper.lock`, for `why`, tree listings and dedupe
+    pub fn graph(&self) -> PmResult<DependencyGraph> {
+        let lockfile = Lockfile::load(&self.config.root)?.ok_or_else(|| {
+            PmError::Lockfile(format!(
+                "{} not found, run `viper install` first",
+                LOCKFILE_NAME
+            ))
+        })?;
+        Ok(DependencyGraph::from_lockfile(lockfile))
+    }
+
     /// List installed packages (synchronous - no async needed for local fs)
     pub fn list(&self, depth: usize) -> PmResult<Vec<InstalledPackage>> {
         let node_modules = self.config.root.join("node_modules");
@@ -709,8 +798,6 @@ impl PackageManager {
 
     /// Update packages asynchronously
     pub async fn update_async(&self, packages: Option<&[&str]>) -> PmResult<InstallResult> {
-        use nassun::NassunOpts;
-
         let package_json_path = self.config.root.join("package.json");
 
         if !package_json_path.exists() {
@@ -724,9 +811,7 @@ impl PackageManager {
         let mut manifest: serde_json::Value =
             serde_json::from_str(&content).map_err(|e| PmError::ManifestParse(e.to_string()))?;
 
-        let nassun = NassunOpts::new()
-            .registry(self.config.registry.clone())
-            .build();
+        let nassun = self.nassun()?;
 
         // Collect packages to update
         let mut to_update: Vec<(String, bool)> = Vec::new(); // (name, is_dev)
@@ -795,7 +880,7 @@ impl PackageManager {
 }
 
 /// Information about an installed package
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct InstalledPackage {
     /// Package name
     pub name: String,
@@ -822,6 +907,8 @@ pub struct InstallResult {
     pub added_packages: Vec<String>,
     /// Packages that were removed
     pub removed_packages: Vec<String>,
+    /// Foreign lockfile (e.g. `yarn.lock`) whose pins seeded this install
+    pub imported_lockfile: Option<String>,
 }
 
 impl InstallResult {
@@ -850,3 +937,76 @@ impl InstallResult {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// A registry on a local TLS port, signed by the returned CA, that
+    /// counts the requests reaching it and answers each with a 404
+    fn tls_registry(requests: Arc<AtomicUsize>) -> (String, String) {
+        let (ca, cert, key) = crate::runtime::test_certificates();
+        let certs = CertificateDer::pem_slice_iter(cert.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = PrivateKeyDer::from_pem_slice(key.as_bytes()).unwrap();
+        let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+        let config = Arc::new(config);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let conn = rustls::ServerConnection::new(Arc::clone(&config)).unwrap();
+                let mut tls = rustls::StreamOwned::new(conn, stream);
+                let mut buf = [0; 4096];
+                if tls.read(&mut buf).is_ok_and(|n| n > 0) {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    let _ = tls.write_all(
+                        b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    );
+                }
+            }
+        });
+        (format!("https://localhost:{port}/"), ca)
+    }
+
+    #[test]
+    fn test_custom_ca_is_refused_for_downloads() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let (registry, ca) = tls_registry(Arc::clone(&requests));
+        let root = std::env::temp_dir().join(format!("viper-ca-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let manifest = r#"{"name":"app","version":"1.0.0","dependencies":{"left-pad":"1.3.0"}}"#;
+        std::fs::write(root.join("package.json"), manifest).unwrap();
+        std::fs::write(root.join("ca.pem"), ca).unwrap();
+        std::fs::write(
+            root.join(".npmrc"),
+            format!("registry={registry}\ncafile=ca.pem\n"),
+        )
+        .unwrap();
+
+        let config = PackageManagerConfig::new(&root).load_npmrc().unwrap();
+        let pm = PackageManager::with_config(config);
+        let refused = |result: PmResult<()>| matches!(result, Err(PmError::Config(message)) if message.contains("cafile"));
+        assert!(refused(pm.install().map(|_| ())));
+        assert!(refused(pm.add(&["is-even"], false).map(|_| ())));
+        assert!(refused(pm.view("left-pad").map(|_| ())));
+        assert!(refused(pm.ping().map(|_| ())));
+
+        let unchanged = std::fs::read_to_string(root.join("package.json")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(unchanged, manifest);
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+}
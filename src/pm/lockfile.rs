@@ -0,0 +1,1265 @@
+//! Typed model of the `viper.lock` lockfile
+//!
+//! `viper.lock` is Orogene's KDL lockfile format:
+//!
+//! ```text
+//! lockfile-version 1
+//! root {
+//!     dependencies {
+//!         express "^5.0.0"
+//!     }
+//! }
+//! pkg "express" {
+//!     version "5.1.0"
+//!     resolved "https://registry.npmjs.org/express/-/express-5.1.0.tgz"
+//!     integrity "sha512-..."
+//!     dependencies {
+//!         debug "^4.4.0"
+//!     }
+//! }
+//! pkg "express" "debug" {
+//!     ...
+//! }
+//! ```
+//!
+//! Each `pkg` node's arguments are its install path: `pkg "a" "b"` lives at
+//! `node_modules/a/node_modules/b`. This module parses and writes that
+//! format, diffs two lockfiles, and imports `package-lock.json`,
+//! `yarn.lock` and `pnpm-lock.yaml` so migrating projects keep their pins.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+
+use kdl::{KdlDocument, KdlNode};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::error::{PmError, PmResult};
+
+/// Lockfile file name
+pub const LOCKFILE_NAME: &str = "viper.lock";
+
+/// Header comment written at the top of `viper.lock`
+const LOCKFILE_HEADER: &str =
+    "// This file is automatically generated and not intended for manual editing.";
+
+/// A parsed `viper.lock`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lockfile {
+    /// Lockfile format version
+    pub lockfile_version: u64,
+    /// The project itself (only dependency ranges are set)
+    pub root: LockedPackage,
+    /// Installed packages keyed by install location
+    /// (`node_modules/a/node_modules/b`)
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+
+/// A single locked package
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockedPackage {
+    /// Install path segments (`["a", "b"]` for `node_modules/a/node_modules/b`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<String>,
+    /// Real package name when installed under an alias
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Resolved version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Tarball URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<String>,
+    /// Subresource integrity hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+    /// Dependency name -> requested range
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dev_dependencies: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub peer_dependencies: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub optional_dependencies: BTreeMap<String, String>,
+}
+
+impl LockedPackage {
+    /// Package name (the alias target if aliased, else the install name)
+    pub fn name(&self) -> &str {
+        self.name
+            .as_deref()
+            .or(self.path.last().map(|s| s.as_str()))
+            .unwrap_or("")
+    }
+
+    /// All dependency edges (regular, optional, peer) as `(name, range)`
+    pub fn all_dependencies(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.dependencies
+            .iter()
+            .chain(self.optional_dependencies.iter())
+            .chain(self.peer_dependencies.iter())
+    }
+}
+
+/// `node_modules` key for an install path
+pub fn package_key(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| format!("node_modules/{}", segment))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl Lockfile {
+    /// Read `viper.lock` from a project root, if present
+    pub fn load(root: &Path) -> PmResult<Option<Self>> {
+        let path = root.join(LOCKFILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| PmError::Lockfile(format!("Failed to read lockfile: {}", e)))?;
+        Self::parse(&content).map(Some)
+    }
+
+    /// Parse KDL lockfile contents
+    pub fn parse(content: &str) -> PmResult<Self> {
+        let doc: KdlDocument = content
+            .parse()
+            .map_err(|e: kdl::KdlError| PmError::Lockfile(e.to_string()))?;
+        Self::from_kdl(&doc)
+    }
+
+    /// Read a parsed KDL document, such as Orogene's `to_kdl()` output.
+    ///
+    /// Unknown nodes and fields are ignored for forward compatibility.
+    pub fn from_kdl(doc: &KdlDocument) -> PmResult<Self> {
+        let mut lockfile = Lockfile::default();
+
+        for node in doc.nodes() {
+            match node.name().value() {
+                "lockfile-version" => {
+                    lockfile.lockfile_version = node
+                        .get(0)
+                        .and_then(|v| v.as_i64())
+                        .and_then(|v| u64::try_from(v).ok())
+                        .ok_or_else(|| PmError::Lockfile("Invalid lockfile-version".to_string()))?;
+                }
+                "root" => lockfile.root = LockedPackage::from_node(Vec::new(), node),
+                "pkg" => {
+                    let path = args(node);
+                    if path.is_empty() {
+                        return Err(PmError::Lockfile("pkg node without a path".to_string()));
+                    }
+                    let pkg = LockedPackage::from_node(path, node);
+                    lockfile.packages.insert(package_key(&pkg.path), pkg);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(lockfile)
+    }
+
+    /// Serialize to the KDL lockfile format
+    pub fn to_kdl(&self) -> String {
+        let mut doc = KdlDocument::new();
+        doc.set_leading(LOCKFILE_HEADER);
+
+        let mut version = KdlNode::new("lockfile-version");
+        version.push(self.lockfile_version.max(1) as i64);
+        doc.nodes_mut().push(version);
+        doc.nodes_mut()
+            .push(self.root.to_node(KdlNode::new("root")));
+
+        for pkg in self.packages.values() {
+            let mut node = KdlNode::new("pkg");
+            for segment in &pkg.path {
+                node.push(segment.as_str());
+            }
+            doc.nodes_mut().push(pkg.to_node(node));
+        }
+
+        doc.fmt();
+        doc.to_string()
+    }
+
+    /// Package installed directly under the project (`node_modules/<name>`)
+    pub fn top_level(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.get(&format!("node_modules/{}", name))
+    }
+
+    /// Resolve `name` from a package at `from`, following Node's lookup:
+    /// `from/node_modules/name`, then each ancestor, then the top level.
+    pub fn resolve(&self, from: &[String], name: &str) -> Option<&LockedPackage> {
+        for depth in (0..=from.len()).rev() {
+            let mut path = from[..depth].to_vec();
+            path.push(name.to_string());
+            if let Some(pkg) = self.packages.get(&package_key(&path)) {
+                return Some(pkg);
+            }
+        }
+        None
+    }
+
+    /// Compare two lockfiles (`self` is the old one)
+    pub fn diff(&self, new: &Lockfile) -> LockfileDiff {
+        let mut diff = LockfileDiff::default();
+
+        for (key, old_pkg) in &self.packages {
+            match new.packages.get(key) {
+                None => diff.removed.push(DiffEntry::from_package(key, old_pkg)),
+                Some(new_pkg) if new_pkg.version != old_pkg.version => {
+                    diff.changed.push(VersionChange {
+                        key: key.clone(),
+                        name: new_pkg.name().to_string(),
+                        from: old_pkg.version.clone().unwrap_or_default(),
+                        to: new_pkg.version.clone().unwrap_or_default(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, new_pkg) in &new.packages {
+            if !self.packages.contains_key(key) {
+                diff.added.push(DiffEntry::from_package(key, new_pkg));
+            }
+        }
+
+        diff
+    }
+
+    /// Import the first lockfile found from another package manager
+    /// (`package-lock.json`, `yarn.lock`, `pnpm-lock.yaml`).
+    ///
+    /// Returns the converted lockfile and the name of the file it came from.
+    pub fn import(root: &Path, registry: &Url) -> PmResult<Option<(Self, &'static str)>> {
+        let manifest: serde_json::Value = match std::fs::read_to_string(root.join("package.json")) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                PmError::ManifestParse(format!("Failed to parse package.json: {}", e))
+            })?,
+            Err(_) => serde_json::Value::Null,
+        };
+
+        type Importer = fn(&str, &serde_json::Value, &Url) -> PmResult<Lockfile>;
+        let candidates: [(&'static str, Importer); 4] = [
+            ("package-lock.json", |c, _, _| import::npm(c)),
+            ("npm-shrinkwrap.json", |c, _, _| import::npm(c)),
+            ("yarn.lock", import::yarn),
+            ("pnpm-lock.yaml", import::pnpm),
+        ];
+
+        for (file, importer) in candidates {
+            let path = root.join(file);
+            if !path.exists() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)?;
+            let lockfile = importer(&content, &manifest, registry)
+                .map_err(|e| PmError::Lockfile(format!("Failed to import {}: {}", file, e)))?;
+            return Ok(Some((lockfile, file)));
+        }
+        Ok(None)
+    }
+}
+
+impl LockedPackage {
+    fn from_node(path: Vec<String>, node: &KdlNode) -> Self {
+        let mut pkg = LockedPackage {
+            path,
+            ..Default::default()
+        };
+
+        for child in node.children().map(|c| c.nodes()).unwrap_or_default() {
+            let arg = || args(child).into_iter().next();
+            match child.name().value() {
+                "name" => pkg.name = arg(),
+                "version" => pkg.version = arg(),
+                "resolved" => pkg.resolved = arg(),
+                "integrity" => pkg.integrity = arg(),
+                "dependencies" => pkg.dependencies = deps_from_node(child),
+                "dev-dependencies" => pkg.dev_dependencies = deps_from_node(child),
+                "peer-dependencies" => pkg.peer_dependencies = deps_from_node(child),
+                "optional-dependencies" => pkg.optional_dependencies = deps_from_node(child),
+                // Unknown fields are ignored for forward compatibility
+                _ => {}
+            }
+        }
+
+        pkg
+    }
+
+    /// Add this package's fields as children of `node`
+    fn to_node(&self, mut node: KdlNode) -> KdlNode {
+        for (field, value) in [
+            ("name", &self.name),
+            ("version", &self.version),
+            ("resolved", &self.resolved),
+            ("integrity", &self.integrity),
+        ] {
+            if let Some(value) = value {
+                let mut child = KdlNode::new(field);
+                child.push(value.as_str());
+                node.ensure_children().nodes_mut().push(child);
+            }
+        }
+        for (section, deps) in [
+            ("dependencies", &self.dependencies),
+            ("dev-dependencies", &self.dev_dependencies),
+            ("peer-dependencies", &self.peer_dependencies),
+            ("optional-dependencies", &self.optional_dependencies),
+        ] {
+            if deps.is_empty() {
+                continue;
+            }
+            let mut child = KdlNode::new(section);
+            for (name, range) in deps {
+                let mut dep = KdlNode::new(name.as_str());
+                dep.push(range.as_str());
+                child.ensure_children().nodes_mut().push(dep);
+            }
+            node.ensure_children().nodes_mut().push(child);
+        }
+        node
+    }
+}
+
+/// Positional string arguments of a node (properties are skipped)
+fn args(node: &KdlNode) -> Vec<String> {
+    node.entries()
+        .iter()
+        .filter(|entry| entry.name().is_none())
+        .map(|entry| match entry.value().as_string() {
+            Some(s) => s.to_string(),
+            None => entry.value().to_string(),
+        })
+        .collect()
+}
+
+fn deps_from_node(node: &KdlNode) -> BTreeMap<String, String> {
+    node.children()
+        .map(|c| c.nodes())
+        .unwrap_or_default()
+        .iter()
+        .map(|dep| {
+            let range = args(dep)
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| "*".to_string());
+            (dep.name().value().to_string(), range)
+        })
+        .collect()
+}
+
+/// Differences between two lockfiles
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LockfileDiff {
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    pub changed: Vec<VersionChange>,
+}
+
+impl LockfileDiff {
+    /// Whether the lockfiles are equivalent
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A package present in only one of the diffed lockfiles
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffEntry {
+    pub key: String,
+    pub name: String,
+    pub version: String,
+}
+
+impl DiffEntry {
+    fn from_package(key: &str, pkg: &LockedPackage) -> Self {
+        Self {
+            key: key.to_string(),
+            name: pkg.name().to_string(),
+            version: pkg.version.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// A package whose version differs between the diffed lockfiles
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VersionChange {
+    pub key: String,
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Converters from other package managers' lockfiles
+mod import {
+    use super::*;
+
+    /// A package entry from a flat (non-nested) lockfile
+    struct FlatEntry {
+        name: String,
+        version: String,
+        resolved: Option<String>,
+        integrity: Option<String>,
+        dependencies: BTreeMap<String, String>,
+        optional_dependencies: BTreeMap<String, String>,
+    }
+
+    /// `package-lock.json` / `npm-shrinkwrap.json` (v1, v2 and v3)
+    pub fn npm(content: &str) -> PmResult<Lockfile> {
+        let lock: serde_json::Value =
+            serde_json::from_str(content).map_err(|e| PmError::Lockfile(e.to_string()))?;
+        let mut lockfile = Lockfile {
+            lockfile_version: 1,
+            ..Default::default()
+        };
+
+        if let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) {
+            // v2/v3: flat map keyed by install location
+            for (key, entry) in packages {
+                if key.is_empty() {
+                    lockfile.root = npm_package(Vec::new(), entry);
+                    continue;
+                }
+                // Workspace sources and symlinks aren't registry packages
+                if !key.starts_with("node_modules/") || entry.get("link").is_some() {
+                    continue;
+                }
+                let path: Vec<String> = key
+                    .split("node_modules/")
+                    .map(|s| s.trim_end_matches('/'))
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect();
+                let mut pkg = npm_package(path, entry);
+                if let Some(name) = entry.get("name").and_then(|n| n.as_str())
+                    && Some(name) != pkg.path.last().map(|s| s.as_str())
+                {
+                    pkg.name = Some(name.to_string());
+                }
+                lockfile.packages.insert(package_key(&pkg.path), pkg);
+            }
+        } else if let Some(deps) = lock.get("dependencies").and_then(|d| d.as_object()) {
+            // v1: nested dependency tree
+            npm_v1_tree(&[], deps, &mut lockfile);
+        }
+
+        Ok(lockfile)
+    }
+
+    fn npm_package(path: Vec<String>, entry: &serde_json::Value) -> LockedPackage {
+        let string = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(String::from);
+        LockedPackage {
+            path,
+            name: None,
+            version: string("version"),
+            resolved: string("resolved"),
+            integrity: string("integrity"),
+            dependencies: string_map(entry.get("dependencies")),
+            dev_dependencies: string_map(entry.get("devDependencies")),
+            peer_dependencies: string_map(entry.get("peerDependencies")),
+            optional_dependencies: string_map(entry.get("optionalDependencies")),
+        }
+    }
+
+    fn npm_v1_tree(
+        parent: &[String],
+        deps: &serde_json::Map<String, serde_json::Value>,
+        lockfile: &mut Lockfile,
+    ) {
+        for (name, entry) in deps {
+            let mut path = parent.to_vec();
+            path.push(name.clone());
+
+            let string = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(String::from);
+            let pkg = LockedPackage {
+                path: path.clone(),
+                version: string("version"),
+                resolved: string("resolved"),
+                integrity: string("integrity"),
+                dependencies: string_map(entry.get("requires")),
+                ..Default::default()
+            };
+            lockfile.packages.insert(package_key(&path), pkg);
+
+            if let Some(nested) = entry.get("dependencies").and_then(|d| d.as_object()) {
+                npm_v1_tree(&path, nested, lockfile);
+            }
+        }
+    }
+
+    fn string_map(value: Option<&serde_json::Value>) -> BTreeMap<String, String> {
+        value
+            .and_then(|v| v.as_object())
+            .map(|obj| {
+                obj.iter()
+                    .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Root dependency ranges from package.json
+    fn manifest_root(manifest: &serde_json::Value) -> LockedPackage {
+        LockedPackage {
+            dependencies: string_map(manifest.get("dependencies")),
+            dev_dependencies: string_map(manifest.get("devDependencies")),
+            peer_dependencies: string_map(manifest.get("peerDependencies")),
+            optional_dependencies: string_map(manifest.get("optionalDependencies")),
+            ..Default::default()
+        }
+    }
+
+    /// `yarn.lock`, both classic (v1) and Berry (YAML) formats
+    pub fn yarn(content: &str, manifest: &serde_json::Value, registry: &Url) -> PmResult<Lockfile> {
+        let mut entries = Vec::new();
+        let mut specs: HashMap<String, usize> = HashMap::new();
+
+        if content.contains("__metadata:") {
+            let doc = yaml::parse(content);
+            for (key, value) in doc.entries() {
+                if key == "__metadata" {
+                    continue;
+                }
+                let resolution = value.get_str("resolution").unwrap_or_default();
+                // Skip workspace/link/patch entries, which don't come from the registry
+                if !resolution.contains("@npm:") {
+                    continue;
+                }
+                let Some(first) = key.split(',').next() else {
+                    continue;
+                };
+                let (name, _) = split_spec(first.trim());
+                let version = value.get_str("version").unwrap_or_default().to_string();
+                entries.push(FlatEntry {
+                    resolved: Some(tarball_url(registry, name, &version)),
+                    name: name.to_string(),
+                    version,
+                    integrity: None,
+                    dependencies: value
+                        .get("dependencies")
+                        .map(|d| d.to_string_map())
+                        .unwrap_or_default(),
+                    optional_dependencies: value
+                        .get("optionalDependencies")
+                        .map(|d| d.to_string_map())
+                        .unwrap_or_default(),
+                });
+                for spec in key.split(',') {
+                    specs.insert(normalize_spec(spec.trim()), entries.len() - 1);
+                }
+            }
+        } else {
+            let mut current: Option<(Vec<String>, FlatEntry)> = None;
+            let mut section: Option<String> = None;
+
+            let flush = |current: &mut Option<(Vec<String>, FlatEntry)>,
+                         entries: &mut Vec<FlatEntry>,
+                         specs: &mut HashMap<String, usize>| {
+                if let Some((keys, entry)) = current.take() {
+                    entries.push(entry);
+                    for key in keys {
+                        specs.insert(normalize_spec(&key), entries.len() - 1);
+                    }
+                }
+            };
+
+            for line in content.lines() {
+                if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                    continue;
+                }
+                let indent = line.len() - line.trim_start().len();
+                let trimmed = line.trim();
+
+                if indent == 0 {
+                    flush(&mut current, &mut entries, &mut specs);
+                    let header = trimmed.trim_end_matches(':');
+                    let keys: Vec<String> = header
+                        .split(", ")
+                        .map(|k| unquote(k.trim()).to_string())
+                        .collect();
+                    let Some(first) = keys.first() else { continue };
+                    let (name, _) = split_spec(first);
+                    current = Some((
+                        keys.clone(),
+                        FlatEntry {
+                            name: name.to_string(),
+                            version: String::new(),
+                            resolved: None,
+                            integrity: None,
+                            dependencies: BTreeMap::new(),
+                            optional_dependencies: BTreeMap::new(),
+                        },
+                    ));
+                    section = None;
+                    continue;
+                }
+
+                let Some((_, entry)) = current.as_mut() else {
+                    continue;
+                };
+
+                if indent <= 2 {
+                    if let Some(name) = trimmed.strip_suffix(':') {
+                        section = Some(name.to_string());
+                        continue;
+                    }
+                    section = None;
+                    let (key, value) = split_pair(trimmed);
+                    match key {
+                        "version" => entry.version = value.to_string(),
+                        "resolved" => entry.resolved = Some(value.to_string()),
+                        "integrity" => entry.integrity = Some(value.to_string()),
+                        _ => {}
+                    }
+                } else if let Some(section) = &section {
+                    let (name, range) = split_pair(trimmed);
+                    let target = match section.as_str() {
+                        "dependencies" => &mut entry.dependencies,
+                        "optionalDependencies" => &mut entry.optional_dependencies,
+                        _ => continue,
+                    };
+                    target.insert(name.to_string(), range.to_string());
+                }
+            }
+            flush(&mut current, &mut entries, &mut specs);
+        }
+
+        let root = manifest_root(manifest);
+        let packages = hoist(&root_edges(&root), &entries, |name, range| {
+            specs
+                .get(&normalize_spec(&format!("{}@{}", name, range)))
+                .copied()
+        });
+
+        Ok(Lockfile {
+            lockfile_version: 1,
+            root,
+            packages,
+        })
+    }
+
+    /// `pnpm-lock.yaml` (v5, v6 and v9)
+    pub fn pnpm(content: &str, manifest: &serde_json::Value, registry: &Url) -> PmResult<Lockfile> {
+        let doc = yaml::parse(content);
+        let version: f64 = doc
+            .get_str("lockfileVersion")
+            .and_then(|v| v.trim_matches('\'').parse().ok())
+            .unwrap_or(6.0);
+        let legacy_keys = version < 6.0;
+
+        let empty = yaml::Yaml::Map(Vec::new());
+        let packages = doc.get("packages").unwrap_or(&empty);
+
+        // v9 keeps dependency edges in `snapshots`, keyed with peer suffixes
+        let mut snapshots: HashMap<&str, Vec<&yaml::Yaml>> = HashMap::new();
+        for (key, snap) in doc.get("snapshots").unwrap_or(&empty).entries() {
+            snapshots
+                .entry(strip_peer_suffix(key))
+                .or_default()
+                .push(snap);
+        }
+
+        let mut entries = Vec::new();
+        let mut by_version: HashMap<String, usize> = HashMap::new();
+
+        for (key, value) in packages.entries() {
+            let Some((name, version)) = parse_pnpm_key(key, legacy_keys) else {
+                continue;
+            };
+            let resolution = value.get("resolution");
+            let tarball = resolution
+                .and_then(|r| r.get_str("tarball"))
+                .map(String::from);
+            let integrity = resolution
+                .and_then(|r| r.get_str("integrity"))
+                .map(String::from);
+
+            let mut dependencies = value
+                .get("dependencies")
+                .map(|d| d.to_string_map())
+                .unwrap_or_default();
+            let mut optional_dependencies = value
+                .get("optionalDependencies")
+                .map(|d| d.to_string_map())
+                .unwrap_or_default();
+            for snap in snapshots.get(key).into_iter().flatten() {
+                dependencies.extend(
+                    snap.get("dependencies")
+                        .map(|d| d.to_string_map())
+                        .unwrap_or_default(),
+                );
+                optional_dependencies.extend(
+                    snap.get("optionalDependencies")
+                        .map(|d| d.to_string_map())
+                        .unwrap_or_default(),
+                );
+            }
+
+            entries.push(FlatEntry {
+                resolved: Some(tarball.unwrap_or_else(|| tarball_url(registry, &name, &version))),
+                name: name.clone(),
+                version: version.clone(),
+                integrity,
+                dependencies: strip_versions(dependencies),
+                optional_dependencies: strip_versions(optional_dependencies),
+            });
+            by_version.insert(format!("{}@{}", name, version), entries.len() - 1);
+        }
+
+        // Root edges use exact versions, from `importers['.']` (v6+) or the top level (v5)
+        let importer = doc
+            .get("importers")
+            .and_then(|i| i.get("."))
+            .unwrap_or(&doc);
+        let mut root_versions = BTreeMap::new();
+        for section in ["dependencies", "devDependencies", "optionalDependencies"] {
+            if let Some(deps) = importer.get(section) {
+                for (name, value) in deps.entries() {
+                    // v6+: { specifier, version }, v5: plain version
+                    let version = value
+                        .get_str("version")
+                        .or(value.as_str())
+                        .unwrap_or_default();
+                    root_versions.insert(name.to_string(), strip_pnpm_version(version));
+                }
+            }
+        }
+
+        let root = manifest_root(manifest);
+        let packages = hoist(&root_versions, &entries, |name, version| {
+            by_version.get(&format!("{}@{}", name, version)).copied()
+        });
+
+        Ok(Lockfile {
+            lockfile_version: 1,
+            root,
+            packages,
+        })
+    }
+
+    /// Split a pnpm `packages` key into name and version
+    fn parse_pnpm_key(key: &str, legacy: bool) -> Option<(String, String)> {
+        let key = strip_peer_suffix(key);
+        let key = key.strip_prefix('/').unwrap_or(key);
+        if legacy {
+            // /name/1.0.0 or /@scope/name/1.0.0_peer@1.0.0
+            let (name, version) = key.rsplit_once('/')?;
+            let version = version.split('_').next()?;
+            Some((name.to_string(), version.to_string()))
+        } else {
+            // /name@1.0.0 (v6) or name@1.0.0 (v9)
+            let (name, version) = split_spec(key);
+            if version.is_empty() {
+                return None;
+            }
+            Some((name.to_string(), version.to_string()))
+        }
+    }
+
+    /// Drop a `(peer@x)` suffix from a pnpm key or version
+    fn strip_peer_suffix(key: &str) -> &str {
+        key.split('(').next().unwrap_or(key)
+    }
+
+    /// Normalize a pnpm dependency version (`1.0.0(react@18.0.0)`, `1.0.0_react@18`)
+    fn strip_pnpm_version(version: &str) -> String {
+        let version = strip_peer_suffix(version);
+        version.split('_').next().unwrap_or(version).to_string()
+    }
+
+    fn strip_versions(deps: BTreeMap<String, String>) -> BTreeMap<String, String> {
+        deps.into_iter()
+            .map(|(k, v)| (k, strip_pnpm_version(&v)))
+            .collect()
+    }
+
+    /// Root dependency edges (regular, dev and optional) as `name -> range`
+    fn root_edges(root: &LockedPackage) -> BTreeMap<String, String> {
+        root.dependencies
+            .iter()
+            .chain(root.dev_dependencies.iter())
+            .chain(root.optional_dependencies.iter())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Lay flat entries out as a hoisted `node_modules` tree. `find` maps a
+    /// `(name, range)` edge to the entry that satisfies it.
+    fn hoist(
+        root_edges: &BTreeMap<String, String>,
+        entries: &[FlatEntry],
+        find: impl Fn(&str, &str) -> Option<usize>,
+    ) -> BTreeMap<String, LockedPackage> {
+        let mut placed: BTreeMap<String, usize> = BTreeMap::new();
+        let mut queue: VecDeque<(Vec<String>, usize)> = VecDeque::new();
+
+        for (name, range) in root_edges {
+            if let Some(idx) = find(name, range) {
+                let path = vec![name.clone()];
+                placed.insert(package_key(&path), idx);
+                queue.push_back((path, idx));
+            }
+        }
+
+        while let Some((path, idx)) = queue.pop_front() {
+            let entry = &entries[idx];
+            for (name, range) in entry
+                .dependencies
+                .iter()
+                .chain(entry.optional_dependencies.iter())
+            {
+                let Some(dep_idx) = find(name, range) else {
+                    continue;
+                };
+
+                let slot = |depth: usize| {
+                    let mut p = path[..depth].to_vec();
+                    p.push(name.clone());
+                    p
+                };
+
+                // Already satisfied by what Node's lookup would find?
+                let visible = (0..=path.len())
+                    .rev()
+                    .find_map(|depth| placed.get(&package_key(&slot(depth))));
+                if let Some(&visible_idx) = visible
+                    && entries[visible_idx].version == entries[dep_idx].version
+                {
+                    continue;
+                }
+
+                // Place as high as possible without being shadowed below
+                for depth in 0..=path.len() {
+                    let target = slot(depth);
+                    if placed.contains_key(&package_key(&target)) {
+                        continue;
+                    }
+                    let shadowed = (depth + 1..=path.len())
+                        .any(|d| placed.contains_key(&package_key(&slot(d))));
+                    if shadowed {
+                        continue;
+                    }
+                    placed.insert(package_key(&target), dep_idx);
+                    queue.push_back((target, dep_idx));
+                    break;
+                }
+            }
+        }
+
+        placed
+            .into_iter()
+            .map(|(key, idx)| {
+                let entry = &entries[idx];
+                let path: Vec<String> = key
+                    .split("node_modules/")
+                    .map(|s| s.trim_end_matches('/'))
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect();
+                let name = (path.last().map(|s| s.as_str()) != Some(entry.name.as_str()))
+                    .then(|| entry.name.clone());
+                let pkg = LockedPackage {
+                    path,
+                    name,
+                    version: Some(entry.version.clone()),
+                    resolved: entry.resolved.clone(),
+                    integrity: entry.integrity.clone(),
+                    dependencies: entry.dependencies.clone(),
+                    optional_dependencies: entry.optional_dependencies.clone(),
+                    ..Default::default()
+                };
+                (key, pkg)
+            })
+            .collect()
+    }
+
+    /// Split `name@range`, keeping the scope of `@scope/name@range`
+    fn split_spec(spec: &str) -> (&str, &str) {
+        match spec.rfind('@') {
+            Some(pos) if pos > 0 => (&spec[..pos], &spec[pos + 1..]),
+            _ => (spec, ""),
+        }
+    }
+
+    /// Berry writes `name@npm:^1.0.0`; classic writes `name@^1.0.0`
+    fn normalize_spec(spec: &str) -> String {
+        let (name, range) = split_spec(unquote(spec));
+        format!("{}@{}", name, range.strip_prefix("npm:").unwrap_or(range))
+    }
+
+    /// Split a yarn classic `key value` line
+    fn split_pair(line: &str) -> (&str, &str) {
+        let (key, value) = if let Some(rest) = line.strip_prefix('"') {
+            match rest.find('"') {
+                Some(end) => (&line[..end + 2], line[end + 2..].trim()),
+                None => (line, ""),
+            }
+        } else {
+            line.split_once(' ')
+                .map(|(k, v)| (k, v.trim()))
+                .unwrap_or((line, ""))
+        };
+        (unquote(key.trim_end_matches(':')), unquote(value))
+    }
+
+    fn unquote(s: &str) -> &str {
+        s.strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .or_else(|| s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+            .unwrap_or(s)
+    }
+
+    /// Default npm registry tarball URL for a package version
+    fn tarball_url(registry: &Url, name: &str, version: &str) -> String {
+        let basename = name.rsplit('/').next().unwrap_or(name);
+        format!(
+            "{}/{}/-/{}-{}.tgz",
+            registry.as_str().trim_end_matches('/'),
+            name,
+            basename,
+            version
+        )
+    }
+
+    /// Just enough YAML for pnpm and Yarn Berry lockfiles: block mappings,
+    /// scalars and single-line flow mappings
+    mod yaml {
+        #[derive(Debug, Clone)]
+        pub enum Yaml {
+            Scalar(String),
+            Map(Vec<(String, Yaml)>),
+        }
+
+        impl Yaml {
+            pub fn get(&self, key: &str) -> Option<&Yaml> {
+                match self {
+                    Yaml::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+                    _ => None,
+                }
+            }
+
+            pub fn get_str(&self, key: &str) -> Option<&str> {
+                self.get(key).and_then(|v| v.as_str())
+            }
+
+            pub fn as_str(&self) -> Option<&str> {
+                match self {
+                    Yaml::Scalar(s) => Some(s),
+                    _ => None,
+                }
+            }
+
+            pub fn entries(&self) -> impl Iterator<Item = (&str, &Yaml)> {
+                let entries: &[(String, Yaml)] = match self {
+                    Yaml::Map(entries) => entries,
+                    _ => &[],
+                };
+                entries.iter().map(|(k, v)| (k.as_str(), v))
+            }
+
+            pub fn to_string_map(&self) -> std::collections::BTreeMap<String, String> {
+                self.entries()
+                    .filter_map(|(k, v)| Some((k.to_string(), v.as_str()?.to_string())))
+                    .collect()
+            }
+        }
+
+        pub fn parse(input: &str) -> Yaml {
+            let lines: Vec<(usize, &str)> = input
+                .lines()
+                .filter(|l| {
+                    let t = l.trim();
+                    !t.is_empty() && !t.starts_with('#') && t != "---"
+                })
+                .map(|l| (l.len() - l.trim_start().len(), l.trim()))
+                .collect();
+            let mut pos = 0;
+            block(&lines, &mut pos, 0)
+        }
+
+        fn block(lines: &[(usize, &str)], pos: &mut usize, indent: usize) -> Yaml {
+            // Sequences (e.g. `os: [darwin]` lists) aren't needed for lockfiles
+            if *pos < lines.len() && lines[*pos].1.starts_with("- ") {
+                while *pos < lines.len()
+                    && lines[*pos].0 >= indent
+                    && lines[*pos].1.starts_with("- ")
+                {
+                    *pos += 1;
+                }
+                return Yaml::Scalar(String::new());
+            }
+
+            let mut entries = Vec::new();
+            while *pos < lines.len() && lines[*pos].0 == indent {
+                let line = lines[*pos].1;
+                *pos += 1;
+                let (key, value) = split_key(line);
+
+                let value = if value.is_empty() {
+                    if *pos < lines.len() && lines[*pos].0 > indent {
+                        let child_indent = lines[*pos].0;
+                        block(lines, pos, child_indent)
+                    } else {
+                        Yaml::Scalar(String::new())
+                    }
+                } else if value.starts_with('{') {
+                    flow_map(value)
+                } else {
+                    Yaml::Scalar(unquote(value).to_string())
+                };
+                entries.push((key, value));
+
+                // Skip anything more deeply indented we didn't understand
+                while *pos < lines.len() && lines[*pos].0 > indent {
+                    *pos += 1;
+                }
+            }
+            Yaml::Map(entries)
+        }
+
+        fn split_key(line: &str) -> (String, &str) {
+            if let Some(quote) = line.chars().next().filter(|c| *c == '"' || *c == '\'')
+                && let Some(end) = line[1..].find(quote)
+            {
+                let key = line[1..end + 1].to_string();
+                let rest = line[end + 2..].trim_start_matches(':').trim();
+                return (key, rest);
+            }
+            if let Some(idx) = line.find(": ") {
+                return (line[..idx].to_string(), line[idx + 2..].trim());
+            }
+            (line.trim_end_matches(':').to_string(), "")
+        }
+
+        fn flow_map(value: &str) -> Yaml {
+            let inner = value.trim_start_matches('{').trim_end_matches('}');
+            Yaml::Map(
+                inner
+                    .split(',')
+                    .filter_map(|pair| {
+                        let (k, v) = pair.split_once(':')?;
+                        Some((
+                            unquote(k.trim()).to_string(),
+                            Yaml::Scalar(unquote(v.trim()).to_string()),
+                        ))
+                    })
+                    .collect(),
+            )
+        }
+
+        fn unquote(s: &str) -> &str {
+            super::unquote(s)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"// This file is automatically generated and not intended for manual editing.
+lockfile-version 1
+root {
+    dependencies {
+        "@types/express" ">=0.0.0"
+        express "^5.0.0"
+    }
+}
+pkg "express" {
+    version "5.1.0"
+    resolved "https://registry.npmjs.org/express/-/express-5.1.0.tgz"
+    integrity "sha512-abc"
+    dependencies {
+        debug "^4.4.0"
+    }
+}
+pkg "express" "debug" {
+    version "4.4.1"
+}
+"#;
+
+    #[test]
+    fn test_parse_and_roundtrip() {
+        let lockfile = Lockfile::parse(SAMPLE).unwrap();
+        assert_eq!(lockfile.lockfile_version, 1);
+        assert_eq!(lockfile.root.dependencies.get("express").unwrap(), "^5.0.0");
+        assert_eq!(
+            lockfile.top_level("express").unwrap().version.as_deref(),
+            Some("5.1.0")
+        );
+
+        let debug = lockfile.resolve(&["express".to_string()], "debug").unwrap();
+        assert_eq!(debug.version.as_deref(), Some("4.4.1"));
+        assert_eq!(debug.name(), "debug");
+
+        let reparsed = Lockfile::parse(&lockfile.to_kdl()).unwrap();
+        assert_eq!(reparsed, lockfile);
+        assert!(
+            lockfile
+                .to_kdl()
+                .contains("        \"@types/express\" \">=0.0.0\"\n")
+        );
+        assert!(lockfile.to_kdl().contains("        express \"^5.0.0\"\n"));
+    }
+
+    #[test]
+    fn test_unknown_nodes_ignored() {
+        let content = r#"lockfile-version 1
+root {
+}
+pkg "debug" {
+    version "4.4.1"
+    funding url="https://opencollective.com/debug"
+}
+workspace "packages/a" {
+    version "1.0.0"
+}
+"#;
+        let lockfile = Lockfile::parse(content).unwrap();
+        assert_eq!(lockfile.packages.len(), 1);
+        assert_eq!(
+            lockfile.top_level("debug").unwrap().version.as_deref(),
+            Some("4.4.1")
+        );
+
+        assert!(Lockfile::parse("pkg {\n}\n").is_err());
+        assert!(Lockfile::parse("root {").is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Lockfile::parse(SAMPLE).unwrap();
+        let mut new = old.clone();
+        new.packages
+            .remove("node_modules/express/node_modules/debug");
+        new.packages
+            .get_mut("node_modules/express")
+            .unwrap()
+            .version = Some("5.2.0".to_string());
+        new.packages.insert(
+            "node_modules/ms".to_string(),
+            LockedPackage {
+                path: vec!["ms".to_string()],
+                version: Some("2.1.3".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "ms");
+        assert_eq!(diff.removed[0].name, "debug");
+        assert_eq!(diff.changed[0].from, "5.1.0");
+        assert_eq!(diff.changed[0].to, "5.2.0");
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn test_import_package_lock_v3() {
+        let lock = import::npm(
+            r#"{
+                "lockfileVersion": 3,
+                "packages": {
+                    "": { "dependencies": { "a": "^1.0.0" } },
+                    "node_modules/a": { "version": "1.2.0", "dependencies": { "b": "^2.0.0" } },
+                    "node_modules/a/node_modules/b": { "version": "2.0.1" }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(lock.root.dependencies.get("a").unwrap(), "^1.0.0");
+        let b = lock.resolve(&["a".to_string()], "b").unwrap();
+        assert_eq!(b.path, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(b.version.as_deref(), Some("2.0.1"));
+    }
+
+    #[test]
+    fn test_import_yarn_classic() {
+        let manifest = serde_json::json!({ "dependencies": { "a": "^1.0.0", "b": "^1.0.0" } });
+        let registry = Url::parse("https://registry.npmjs.org/").unwrap();
+        let lock = import::yarn(
+            r#"# yarn lockfile v1
+
+a@^1.0.0:
+  version "1.0.0"
+  resolved "https://registry.yarnpkg.com/a/-/a-1.0.0.tgz#abc"
+  integrity sha512-aaa
+  dependencies:
+    b "^2.0.0"
+
+b@^1.0.0:
+  version "1.5.0"
+
+"b@^2.0.0", b@^2.1.0:
+  version "2.1.0"
+"#,
+            &manifest,
+            &registry,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lock.top_level("b").unwrap().version.as_deref(),
+            Some("1.5.0")
+        );
+        let nested = lock.resolve(&["a".to_string()], "b").unwrap();
+        assert_eq!(nested.version.as_deref(), Some("2.1.0"));
+        assert_eq!(
+            lock.top_level("a").unwrap().integrity.as_deref(),
+            Some("sha512-aaa")
+        );
+    }
+
+    #[test]
+    fn test_import_pnpm_v9() {
+        let manifest = serde_json::json!({ "dependencies": { "@scope/a": "^1.0.0" } });
+        let registry = Url::parse("https://registry.npmjs.org/").unwrap();
+        let lock = import::pnpm(
+            r#"lockfileVersion: '9.0'
+
+importers:
+
+  .:
+    dependencies:
+      '@scope/a':
+        specifier: ^1.0.0
+        version: 1.0.0
+
+packages:
+
+  '@scope/a@1.0.0':
+    resolution: {integrity: sha512-aaa}
+
+  b@2.0.0:
+    resolution: {integrity: sha512-bbb}
+
+snapshots:
+
+  '@scope/a@1.0.0':
+    dependencies:
+      b: 2.0.0
+
+  b@2.0.0: {}
+"#,
+            &manifest,
+            &registry,
+        )
+        .unwrap();
+
+        let a = lock.top_level("@scope/a").unwrap();
+        assert_eq!(a.version.as_deref(), Some("1.0.0"));
+        assert_eq!(
+            a.resolved.as_deref(),
+            Some("https://registry.npmjs.org/@scope/a/-/a-1.0.0.tgz")
+        );
+        assert_eq!(
+            lock.top_level("b").unwrap().integrity.as_deref(),
+            Some("sha512-bbb")
+        );
+    }
+}
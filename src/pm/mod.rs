@@ -8,15 +8,32 @@
 //! - Parallel downloads and extraction
 //! - Content-addressed global cache
 //! - Hardlinks/reflinks for fast installs
-//! - Lockfile support (package-lock.json compatible)
+//! - KDL lockfile (`viper.lock`), importing package-lock.json, yarn.lock
+//!   and pnpm-lock.yaml on first install
+//! - `.npmrc` scoped registries and auth tokens
+//! - Security audits against the registry's advisory database
+//! - `pack` and `publish` producing npm-compatible tarballs
 
+mod audit;
 mod error;
+mod graph;
 mod installer;
+mod lockfile;
+mod npmrc;
+mod publish;
+mod registry;
 
+pub use audit::{Advisory, AuditFinding, AuditFix, AuditFixResult, AuditReport, Severity};
 pub use error::{PmError, PmResult};
+pub use graph::{DependencyGraph, DependencyKind, Duplicate, DuplicateVersion, PathStep, TreeNode};
 pub use installer::{
     InstallResult, InstalledPackage, PackageInfo, PackageManager, PackageManagerConfig,
 };
+pub use lockfile::{
+    DiffEntry, LOCKFILE_NAME, LockedPackage, Lockfile, LockfileDiff, VersionChange, package_key,
+};
+pub use npmrc::{Npmrc, RegistryAuth};
+pub use publish::{Access, PackOptions, PackResult, PackedFile, PublishOptions, PublishResult};
 
 /// Default npm registry URL
 pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org/";
@@ -0,0 +1,491 @@
+//! `.npmrc` configuration loading
+//!
+//! Reads the global, user and project `.npmrc` files (later files override
+//! earlier ones) and exposes the settings the package manager cares about:
+//! the default registry, `@scope:registry` mappings, per-registry auth and
+//! custom CA certificates.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use url::Url;
+
+use super::error::{PmError, PmResult};
+
+/// Credentials for a single registry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryAuth {
+    /// Bearer token (`_authToken`)
+    pub token: Option<String>,
+    /// Basic auth username
+    pub username: Option<String>,
+    /// Basic auth password (already base64-decoded)
+    pub password: Option<String>,
+    /// Pre-encoded `user:pass` credentials (`_auth`)
+    pub legacy_auth: Option<String>,
+}
+
+impl RegistryAuth {
+    /// Whether any credential is set
+    pub fn is_empty(&self) -> bool {
+        self.token.is_none()
+            && self.username.is_none()
+            && self.password.is_none()
+            && self.legacy_auth.is_none()
+    }
+
+    /// Value for an HTTP `Authorization` header
+    pub fn authorization_header(&self) -> Option<String> {
+        use base64::Engine;
+
+        if let Some(token) = &self.token {
+            return Some(format!("Bearer {}", token));
+        }
+        if let Some(auth) = &self.legacy_auth {
+            return Some(format!("Basic {}", auth));
+        }
+        match (&self.username, &self.password) {
+            (Some(user), Some(pass)) => Some(format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, pass))
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed `.npmrc` settings
+#[derive(Debug, Clone, Default)]
+pub struct Npmrc {
+    /// Default registry (`registry=`)
+    pub registry: Option<Url>,
+    /// Registries for scoped packages, keyed by scope including the `@`
+    pub scoped_registries: HashMap<String, Url>,
+    /// Credentials keyed by nerf-darted registry URI (e.g. `//npm.acme.com/`)
+    pub auth: HashMap<String, RegistryAuth>,
+    /// Credentials not tied to a registry URI (top-level `_authToken` etc.)
+    pub default_auth: RegistryAuth,
+    /// Send credentials with every request, including tarball downloads
+    pub always_auth: bool,
+    /// Path to a PEM file with additional CA certificates (`cafile=`)
+    pub cafile: Option<PathBuf>,
+    /// Inline PEM CA certificates (`ca=` / `ca[]=`)
+    pub ca: Vec<String>,
+}
+
+impl Npmrc {
+    /// Load and merge the global, user and project `.npmrc` files
+    pub fn load(root: &Path) -> PmResult<Self> {
+        let mut npmrc = Self::default();
+        for path in Self::config_paths(root) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)?;
+                npmrc
+                    .merge_str(&content, path.parent())
+                    .map_err(|e| PmError::Config(format!("{}: {}", path.display(), e)))?;
+            }
+        }
+        Ok(npmrc)
+    }
+
+    /// Candidate `.npmrc` locations, lowest precedence first
+    fn config_paths(root: &Path) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+
+        if let Ok(global) = std::env::var("NPM_CONFIG_GLOBALCONFIG") {
+            paths.push(PathBuf::from(global));
+        } else if let Some(prefix) = global_prefix() {
+            paths.push(prefix.join("etc").join("npmrc"));
+        }
+
+        if let Ok(user) = std::env::var("NPM_CONFIG_USERCONFIG") {
+            paths.push(PathBuf::from(user));
+        } else if let Some(home) = home_dir() {
+            paths.push(home.join(".npmrc"));
+        }
+
+        paths.push(root.join(".npmrc"));
+        paths
+    }
+
+    /// Parse a single `.npmrc` file's contents
+    pub fn parse(content: &str) -> PmResult<Self> {
+        let mut npmrc = Self::default();
+        npmrc.merge_str(content, None)?;
+        Ok(npmrc)
+    }
+
+    /// Merge the settings from `content` on top of this configuration.
+    /// Relative `cafile` paths are resolved against `base_dir`.
+    fn merge_str(&mut self, content: &str, base_dir: Option<&Path>) -> PmResult<()> {
+        for (line_no, raw) in content.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            let Some(eq) = line.find('=') else {
+                // npm treats a bare key as `key=true`
+                self.set(line, "true", base_dir)?;
+                continue;
+            };
+
+            let env = |name: &str| std::env::var(name).ok();
+            let key = interpolate_env(line[..eq].trim(), &env)
+                .map_err(|e| PmError::Config(format!("line {}: {}", line_no + 1, e)))?;
+            let value = interpolate_env(unquote(line[eq + 1..].trim()), &env)
+                .map_err(|e| PmError::Config(format!("line {}: {}", line_no + 1, e)))?;
+
+            self.set(&key, &value, base_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Apply a single `key=value` setting
+    fn set(&mut self, key: &str, value: &str, base_dir: Option<&Path>) -> PmResult<()> {
+        // Per-registry settings: //host/path/:_authToken=...
+        if key.starts_with("//")
+            && let Some(colon) = key.rfind(':')
+        {
+            let (uri, field) = (&key[..colon], &key[colon + 1..]);
+            let uri = normalize_nerf_dart(uri);
+            let auth = self.auth.entry(uri).or_default();
+            apply_auth_field(auth, field, value)?;
+            return Ok(());
+        }
+
+        // Scoped registries: @acme:registry=...
+        if let Some(scope) = key.strip_suffix(":registry")
+            && scope.starts_with('@')
+        {
+            let url = parse_registry_url(value)?;
+            self.scoped_registries.insert(scope.to_string(), url);
+            return Ok(());
+        }
+
+        match key {
+            "registry" => self.registry = Some(parse_registry_url(value)?),
+            "always-auth" => self.always_auth = value == "true",
+            "cafile" => {
+                let path = PathBuf::from(value);
+                self.cafile = Some(match base_dir {
+                    Some(dir) if path.is_relative() => dir.join(path),
+                    _ => path,
+                });
+            }
+            "ca" => {
+                // A plain `ca=` replaces any previous list; `ca[]=` appends
+                self.ca = if value.is_empty() || value == "null" {
+                    Vec::new()
+                } else {
+                    vec![value.replace("\\n", "\n")]
+                };
+            }
+            "ca[]" => self.ca.push(value.replace("\\n", "\n")),
+            "_authToken" | "_auth" | "username" | "_password" => {
+                apply_auth_field(&mut self.default_auth, key, value)?;
+            }
+            // Everything else is an npm setting Viper doesn't use
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Registry that serves `package` (a bare name or `@scope/name` spec)
+    pub fn registry_for(&self, package: &str, default: &Url) -> Url {
+        if package.starts_with('@')
+            && let Some(scope) = package.split('/').next()
+            && let Some(url) = self.scoped_registries.get(scope)
+        {
+            return url.clone();
+        }
+        self.registry.clone().unwrap_or_else(|| default.clone())
+    }
+
+    /// Credentials for a request to `url`, using npm's longest-prefix
+    /// nerf-dart matching. Top-level credentials only apply to the default
+    /// registry.
+    pub fn auth_for(&self, url: &Url, default_registry: &Url) -> Option<&RegistryAuth> {
+        let mut uri = nerf_dart(url);
+        loop {
+            if let Some(auth) = self.auth.get(&uri)
+                && !auth.is_empty()
+            {
+                return Some(auth);
+            }
+            // Walk up one path segment: //host/a/b/ -> //host/a/
+            let trimmed = uri.trim_end_matches('/');
+            match trimmed.rfind('/') {
+                Some(pos) if pos > 1 => uri = trimmed[..=pos].to_string(),
+                _ => break,
+            }
+        }
+
+        if !self.default_auth.is_empty() && url.host_str() == default_registry.host_str() {
+            return Some(&self.default_auth);
+        }
+        None
+    }
+
+    /// Credentials per registry, for Orogene's `token_auth` / `basic_auth`
+    /// / `legacy_auth` setters.
+    ///
+    /// Only the default and scoped registries receive credentials unless
+    /// `always-auth` is set, in which case every configured host does (e.g.
+    /// a tarball CDN separate from the registry API).
+    pub fn credentials(&self, default_registry: &Url) -> Vec<(Url, RegistryAuth)> {
+        let mut registries: Vec<Url> = vec![default_registry.clone()];
+        registries.extend(self.scoped_registries.values().cloned());
+
+        if self.always_auth {
+            for uri in self.auth.keys() {
+                if let Ok(url) = Url::parse(&format!("https:{}", uri)) {
+                    registries.push(url);
+                }
+            }
+        }
+
+        let mut creds = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for registry in registries {
+            // Orogene keys credentials by nerf dart, so one entry per prefix
+            if !seen.insert(nerf_dart(&registry)) {
+                continue;
+            }
+            if let Some(auth) = self.auth_for(&registry, default_registry) {
+                let auth = auth.clone();
+                creds.push((registry, auth));
+            }
+        }
+        creds
+    }
+
+    /// The `ca` / `cafile` certificates, for `add_root_certificate`
+    pub fn root_certificates(&self) -> PmResult<Vec<reqwest::Certificate>> {
+        let mut pem = self.ca.join("\n").into_bytes();
+        if let Some(cafile) = &self.cafile {
+            pem.extend(std::fs::read(cafile).map_err(|e| {
+                PmError::Config(format!("Failed to read cafile {}: {}", cafile.display(), e))
+            })?);
+        }
+        if pem.is_empty() {
+            return Ok(Vec::new());
+        }
+        reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| PmError::Config(format!("Invalid CA certificate: {}", e)))
+    }
+
+    /// Whether `ca` or `cafile` is set
+    pub fn has_ca(&self) -> bool {
+        self.cafile.is_some() || !self.ca.is_empty()
+    }
+}
+
+/// Apply one auth field (`_authToken`, `_auth`, `username`, `_password`)
+fn apply_auth_field(auth: &mut RegistryAuth, field: &str, value: &str) -> PmResult<()> {
+    use base64::Engine;
+
+    match field {
+        "_authToken" => auth.token = Some(value.to_string()),
+        "_auth" => auth.legacy_auth = Some(value.to_string()),
+        "username" => auth.username = Some(value.to_string()),
+        "_password" => {
+            // npm stores the password base64-encoded
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(value)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| PmError::Config("_password must be base64-encoded".to_string()))?;
+            auth.password = Some(decoded);
+        }
+        // certfile, keyfile, email, always-auth per registry: not used
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace `${VAR}` references with values from `env` (the process
+/// environment outside tests). `${VAR?}` expands to an empty string when
+/// the variable is unset.
+fn interpolate_env(value: &str, env: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        // `\${` escapes interpolation
+        if start > 0 && rest.as_bytes()[start - 1] == b'\\' {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+
+        let name = &rest[start + 2..start + 2 + len];
+        let (name, optional) = match name.strip_suffix('?') {
+            Some(n) => (n, true),
+            None => (name, false),
+        };
+        match env(name) {
+            Some(v) => out.push_str(&v),
+            None if optional => {}
+            None => return Err(format!("environment variable ${{{}}} is not set", name)),
+        }
+        rest = &rest[start + 3 + len..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Strip one level of matching quotes
+fn unquote(value: &str) -> &str {
+    let bytes = value.as_bytes();
+    if bytes.len() >= 2
+        && (bytes[0] == b'"' || bytes[0] == b'\'')
+        && bytes[bytes.len() - 1] == bytes[0]
+    {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+/// Parse a registry URL, ensuring a trailing slash so relative joins work
+fn parse_registry_url(value: &str) -> PmResult<Url> {
+    let value = if value.ends_with('/') {
+        value.to_string()
+    } else {
+        format!("{}/", value)
+    };
+    Url::parse(&value)
+        .map_err(|e| PmError::Config(format!("Invalid registry URL '{}': {}", value, e)))
+}
+
+/// npm's "nerf dart": the URL without scheme, query or credentials,
+/// truncated to the containing directory (`//host[:port]/path/`)
+pub fn nerf_dart(url: &Url) -> String {
+    let path = url.path();
+    let dir = match path.rfind('/') {
+        Some(pos) => &path[..=pos],
+        None => "/",
+    };
+    format!("//{}{}", host_key(url).unwrap_or_default(), dir)
+}
+
+/// Ensure a configured `//host/path` key ends with a slash
+fn normalize_nerf_dart(uri: &str) -> String {
+    if uri.ends_with('/') {
+        uri.to_string()
+    } else {
+        format!("{}/", uri)
+    }
+}
+
+/// `host[:port]` for a URL
+fn host_key(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+fn global_prefix() -> Option<PathBuf> {
+    if let Some(prefix) = std::env::var_os("NPM_CONFIG_PREFIX") {
+        return Some(PathBuf::from(prefix));
+    }
+    #[cfg(windows)]
+    {
+        std::env::var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join("npm"))
+    }
+    #[cfg(not(windows))]
+    {
+        Some(PathBuf::from("/usr/local"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scoped_registry_and_token() {
+        let npmrc = Npmrc::parse(
+            "# company registry\n\
+             @acme:registry=https://npm.acme.com/api\n\
+             //npm.acme.com/api/:_authToken=secret\n\
+             always-auth=true\n",
+        )
+        .unwrap();
+
+        let default = Url::parse("https://registry.npmjs.org/").unwrap();
+        let acme = npmrc.registry_for("@acme/widgets", &default);
+        assert_eq!(acme.as_str(), "https://npm.acme.com/api/");
+        assert_eq!(npmrc.registry_for("lodash", &default), default);
+        assert!(npmrc.always_auth);
+
+        let tarball =
+            Url::parse("https://npm.acme.com/api/@acme/widgets/-/widgets-1.0.0.tgz").unwrap();
+        let auth = npmrc.auth_for(&tarball, &default).unwrap();
+        assert_eq!(
+            auth.authorization_header().as_deref(),
+            Some("Bearer secret")
+        );
+        assert!(npmrc.auth_for(&default, &default).is_none());
+    }
+
+    #[test]
+    fn test_env_interpolation() {
+        let env = |name: &str| (name == "TOKEN").then(|| "from-env".to_string());
+        assert_eq!(
+            interpolate_env("${TOKEN}-x", &env).as_deref(),
+            Ok("from-env-x")
+        );
+        assert_eq!(
+            interpolate_env("\\${TOKEN}", &env).as_deref(),
+            Ok("${TOKEN}")
+        );
+        assert!(interpolate_env("${OTHER}", &env).is_err());
+        assert_eq!(interpolate_env("${OTHER?}", &env).as_deref(), Ok(""));
+
+        assert!(Npmrc::parse("_authToken=${VIPER_NPMRC_TEST_MISSING}").is_err());
+        let optional = Npmrc::parse("_authToken=${VIPER_NPMRC_TEST_MISSING?}").unwrap();
+        assert_eq!(optional.default_auth.token.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_credentials_for_orogene() {
+        let npmrc = Npmrc::parse(
+            "registry=http://localhost:4873\n\
+             //localhost:4873/:username=ci\n\
+             //localhost:4873/:_password=aHVudGVyMg==\n",
+        )
+        .unwrap();
+        let default = npmrc.registry.clone().unwrap();
+        let creds = npmrc.credentials(&default);
+        assert_eq!(creds.len(), 1);
+        assert_eq!(creds[0].0, default);
+        assert_eq!(creds[0].1.username.as_deref(), Some("ci"));
+        assert_eq!(creds[0].1.password.as_deref(), Some("hunter2"));
+    }
+
+    #[test]
+    fn test_ca_entries() {
+        let npmrc = Npmrc::parse("ca[]=CERT-A\nca[]=CERT-B\n").unwrap();
+        assert_eq!(npmrc.ca, vec!["CERT-A".to_string(), "CERT-B".to_string()]);
+
+        let npmrc = Npmrc::parse("ca[]=CERT-A\nca=CERT-C\n").unwrap();
+        assert_eq!(npmrc.ca, vec!["CERT-C".to_string()]);
+    }
+}
@@ -0,0 +1,806 @@
+//! `viper pack` and `viper publish`: build and upload package tarballs
+//!
+//! Tarballs use npm's layout: every file sits under `package/` with a fixed
+//! mtime, so identical sources produce identical archives. Files are chosen
+//! the way npm-packlist does: the `files` whitelist or `.npmignore` (falling
+//! back to `.gitignore`), plus the files npm always includes or excludes.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+use base64::Engine;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use node_semver::Version;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+use url::Url;
+
+use super::error::{PmError, PmResult};
+use super::installer::PackageManager;
+use super::registry::RegistryClient;
+
+/// mtime npm stamps on every tarball entry (1985-10-26T08:15:00Z)
+const TARBALL_MTIME: u64 = 499162500;
+
+/// Never packed, whatever `files` or ignore files say
+const ALWAYS_EXCLUDED: &[&str] = &[
+    ".git",
+    ".svn",
+    ".hg",
+    "CVS",
+    ".lock-wscript",
+    ".wafpickle-*",
+    ".*.swp",
+    "._*",
+    ".DS_Store",
+    "npm-debug.log",
+    ".npmrc",
+    "node_modules",
+    "config.gypi",
+    "*.orig",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "viper.lock",
+    ".npmignore",
+    ".gitignore",
+];
+
+/// Root files packed even when `files` or ignore rules leave them out
+const ALWAYS_INCLUDED: &[&str] = &["package.json", "README*", "LICENSE*", "LICENCE*"];
+
+/// Options for `viper pack`
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+    /// Directory to write the tarball to (defaults to the project root)
+    pub destination: Option<PathBuf>,
+    /// Build the tarball but don't write it
+    pub dry_run: bool,
+    /// Skip `prepack`, `prepare` and `postpack`
+    pub ignore_scripts: bool,
+}
+
+/// A file inside a packed tarball
+#[derive(Debug, Clone, Serialize)]
+pub struct PackedFile {
+    /// Path relative to the package root
+    pub path: String,
+    pub size: u64,
+}
+
+/// Result of `viper pack`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackResult {
+    pub name: String,
+    pub version: String,
+    /// Tarball file name (`name-version.tgz`, scope folded in)
+    pub filename: String,
+    pub files: Vec<PackedFile>,
+    /// Compressed size in bytes
+    pub size: usize,
+    pub unpacked_size: u64,
+    /// Hex SHA-1 of the tarball
+    pub shasum: String,
+    /// Subresource integrity (`sha512-...`) of the tarball
+    pub integrity: String,
+    /// Where the tarball was written (`None` for dry runs)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(skip)]
+    pub tarball: Vec<u8>,
+}
+
+/// Registry access level for a published package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Public,
+    Restricted,
+}
+
+impl FromStr for Access {
+    type Err = PmError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(Access::Public),
+            "restricted" => Ok(Access::Restricted),
+            other => Err(PmError::Other(format!(
+                "Unknown access '{}' (expected public or restricted)",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Access::Public => "public",
+            Access::Restricted => "restricted",
+        })
+    }
+}
+
+/// Options for `viper publish`
+#[derive(Debug, Clone, Default)]
+pub struct PublishOptions {
+    /// Dist-tag to point at the new version (`publishConfig.tag`, then `latest`)
+    pub tag: Option<String>,
+    /// Access level (`publishConfig.access`, then the registry default)
+    pub access: Option<Access>,
+    /// Pack and validate without uploading
+    pub dry_run: bool,
+    /// Skip lifecycle scripts
+    pub ignore_scripts: bool,
+}
+
+/// Result of `viper publish`
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishResult {
+    #[serde(flatten)]
+    pub pack: PackResult,
+    pub registry: String,
+    pub tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access: Option<Access>,
+    pub dry_run: bool,
+}
+
+impl PackageManager {
+    /// Pack the project into an npm-compatible tarball
+    pub fn pack(&self, options: &PackOptions) -> PmResult<PackResult> {
+        let root = &self.config().root;
+        if !options.ignore_scripts {
+            let manifest = read_manifest(root)?;
+            run_script(root, &manifest, "prepack")?;
+            run_script(root, &manifest, "prepare")?;
+        }
+
+        // Scripts may rewrite package.json (version bumps, generated fields)
+        let manifest = read_manifest(root)?;
+        let mut result = build_tarball(root, &manifest)?;
+
+        if !options.dry_run {
+            let dir = options.destination.as_deref().unwrap_or(root);
+            std::fs::create_dir_all(dir)?;
+            let path = dir.join(&result.filename);
+            std::fs::write(&path, &result.tarball)?;
+            result.path = Some(path);
+        }
+
+        if !options.ignore_scripts {
+            run_script(root, &manifest, "postpack")?;
+        }
+        Ok(result)
+    }
+
+    /// Pack the project and upload it to its registry
+    pub fn publish(&self, options: &PublishOptions) -> PmResult<PublishResult> {
+        let config = self.config();
+        let root = &config.root;
+
+        let manifest = read_manifest(root)?;
+        if manifest.get("private").and_then(|p| p.as_bool()) == Some(true) {
+            return Err(PmError::Other(
+                "This package has been marked as private; remove \"private\": true from package.json to publish it".to_string(),
+            ));
+        }
+        if !options.ignore_scripts {
+            run_script(root, &manifest, "prepublishOnly")?;
+        }
+
+        let pack = self.pack(&PackOptions {
+            destination: None,
+            dry_run: true,
+            ignore_scripts: options.ignore_scripts,
+        })?;
+        let manifest = read_manifest(root)?;
+        let publish_config = manifest.get("publishConfig");
+        let publish_str = |key: &str| {
+            publish_config
+                .and_then(|c| c.get(key))
+                .and_then(|v| v.as_str())
+        };
+
+        let tag = options
+            .tag
+            .clone()
+            .or_else(|| publish_str("tag").map(String::from))
+            .unwrap_or_else(|| "latest".to_string());
+        if Version::parse(&tag).is_ok() {
+            return Err(PmError::Other(format!(
+                "Tag '{}' is a version; dist-tags must not be valid semver",
+                tag
+            )));
+        }
+
+        let access = match options.access {
+            Some(access) => Some(access),
+            None => publish_str("access").map(str::parse).transpose()?,
+        };
+        if access == Some(Access::Restricted) && !pack.name.starts_with('@') {
+            return Err(PmError::Other(
+                "Unscoped packages are always public; --access restricted needs a @scope/name"
+                    .to_string(),
+            ));
+        }
+
+        let registry = match publish_str("registry") {
+            Some(url) => Url::parse(url)
+                .map_err(|e| PmError::Registry(format!("Invalid publishConfig.registry: {}", e)))?,
+            None => config.npmrc.registry_for(&pack.name, &config.registry),
+        };
+        let package_url = registry
+            .join(&pack.name.replace('/', "%2f"))
+            .map_err(|e| PmError::Registry(format!("Invalid registry URL: {}", e)))?;
+
+        if !options.dry_run {
+            if config
+                .npmrc
+                .auth_for(&package_url, &config.registry)
+                .is_none_or(|auth| auth.authorization_header().is_none())
+            {
+                return Err(PmError::Registry(format!(
+                    "Not logged in to {}; add an auth token for it to .npmrc",
+                    registry
+                )));
+            }
+
+            let body = publish_body(&manifest, &pack, &registry, &tag, access)?;
+            RegistryClient::new(config)?.put_json(&package_url, &body)?;
+
+            if !options.ignore_scripts {
+                run_script(root, &manifest, "publish")?;
+                run_script(root, &manifest, "postpublish")?;
+            }
+        }
+
+        Ok(PublishResult {
+            pack,
+            registry: registry.to_string(),
+            tag,
+            access,
+            dry_run: options.dry_run,
+        })
+    }
+}
+
+fn read_manifest(root: &Path) -> PmResult<serde_json::Value> {
+    let content = std::fs::read_to_string(root.join("package.json"))
+        .map_err(|e| PmError::ManifestParse(format!("Failed to read package.json: {}", e)))?;
+    serde_json::from_str(&content).map_err(|e| PmError::ManifestParse(e.to_string()))
+}
+
+/// Run a `package.json` lifecycle script, if defined, with `node_modules/.bin`
+/// on `PATH`
+fn run_script(root: &Path, manifest: &serde_json::Value, event: &str) -> PmResult<()> {
+    let Some(script) = manifest
+        .get("scripts")
+        .and_then(|s| s.get(event))
+        .and_then(|s| s.as_str())
+    else {
+        return Ok(());
+    };
+
+    let mut path = vec![root.join("node_modules").join(".bin")];
+    if let Some(existing) = std::env::var_os("PATH") {
+        path.extend(std::env::split_paths(&existing));
+    }
+    let path = std::env::join_paths(path).map_err(|e| PmError::Other(e.to_string()))?;
+
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/d", "/s", "/c", script]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    };
+    let str_field = |key: &str| manifest.get(key).and_then(|v| v.as_str()).unwrap_or("");
+    let status = command
+        .current_dir(root)
+        .env("PATH", path)
+        .env("npm_lifecycle_event", event)
+        .env("npm_lifecycle_script", script)
+        .env("npm_package_name", str_field("name"))
+        .env("npm_package_version", str_field("version"))
+        .status()?;
+
+    if !status.success() {
+        return Err(PmError::Script(format!(
+            "{} (`{}`) exited with {}",
+            event, script, status
+        )));
+    }
+    Ok(())
+}
+
+/// Build the gzipped tarball for the package at `root`
+fn build_tarball(root: &Path, manifest: &serde_json::Value) -> PmResult<PackResult> {
+    let name = manifest
+        .get("name")
+        .and_then(|n| n.as_str())
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| PmError::ManifestParse("package.json has no \"name\"".to_string()))?;
+    let version = manifest
+        .get("version")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| PmError::ManifestParse("package.json has no \"version\"".to_string()))?;
+    let version = Version::parse(version)
+        .map_err(|e| PmError::ManifestParse(format!("Invalid version '{}': {}", version, e)))?
+        .to_string();
+
+    let bins = bin_paths(manifest);
+    let files = pack_files(root, manifest, &bins)?;
+
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::best()));
+    let mut packed = Vec::with_capacity(files.len());
+    for path in &files {
+        let data = std::fs::read(root.join(path))?;
+        let mut header = tar::Header::new_ustar();
+        header.set_size(data.len() as u64);
+        header.set_mtime(TARBALL_MTIME);
+        header.set_mode(if bins.contains(path) { 0o755 } else { 0o644 });
+        header.set_entry_type(tar::EntryType::Regular);
+        builder.append_data(&mut header, format!("package/{}", path), data.as_slice())?;
+        packed.push(PackedFile {
+            path: path.clone(),
+            size: data.len() as u64,
+        });
+    }
+    let tarball = builder.into_inner()?.finish()?;
+
+    let integrity = format!(
+        "sha512-{}",
+        base64::engine::general_purpose::STANDARD.encode(Sha512::digest(&tarball))
+    );
+    Ok(PackResult {
+        name: name.to_string(),
+        filename: format!(
+            "{}-{}.tgz",
+            name.trim_start_matches('@').replace('/', "-"),
+            version
+        ),
+        version,
+        unpacked_size: packed.iter().map(|f| f.size).sum(),
+        files: packed,
+        size: tarball.len(),
+        shasum: hex::encode(Sha1::digest(&tarball)),
+        integrity,
+        path: None,
+        tarball,
+    })
+}
+
+/// Paths of `bin` entries, relative to the package root
+fn bin_paths(manifest: &serde_json::Value) -> Vec<String> {
+    let paths: Vec<&str> = match manifest.get("bin") {
+        Some(serde_json::Value::String(path)) => vec![path.as_str()],
+        Some(serde_json::Value::Object(bins)) => bins.values().filter_map(|v| v.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    paths.into_iter().map(normalize_path).collect()
+}
+
+fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .trim_start_matches("./")
+        .trim_start_matches('/')
+        .to_string()
+}
+
+/// Relative paths of every file that goes into the tarball, sorted
+fn pack_files(root: &Path, manifest: &serde_json::Value, bins: &[String]) -> PmResult<Vec<String>> {
+    let whitelist: Option<Vec<Rule>> =
+        manifest
+            .get("files")
+            .and_then(|f| f.as_array())
+            .map(|files| {
+                files
+                    .iter()
+                    .filter_map(|f| f.as_str())
+                    .filter_map(Rule::parse)
+                    .collect()
+            });
+
+    let mut files = Vec::new();
+    let mut ignores = Vec::new();
+    walk(root, "", whitelist.as_deref(), &mut ignores, &mut files)?;
+
+    // npm packs these no matter what `files` or ignore files say
+    let main = manifest
+        .get("main")
+        .and_then(|m| m.as_str())
+        .map(normalize_path);
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let upper = name.to_ascii_uppercase();
+        if entry.file_type()?.is_file()
+            && ALWAYS_INCLUDED
+                .iter()
+                .any(|pattern| glob_match(&pattern.to_ascii_uppercase(), &upper))
+        {
+            files.push(name);
+        }
+    }
+    for path in main.iter().chain(bins) {
+        if root.join(path).is_file() {
+            files.push(path.clone());
+        }
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// One `.npmignore`/`.gitignore` and the directory it applies to
+struct IgnoreFile {
+    base: String,
+    rules: Vec<Rule>,
+}
+
+/// A gitignore-style pattern
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    negate: bool,
+    dir_only: bool,
+    /// Contains a `/`, so matches from the base rather than any level
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let line = line.strip_prefix("./").unwrap_or(line);
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = line.trim_start_matches('/').to_string();
+        if pattern.is_empty() {
+            return None;
+        }
+        Some(Self {
+            pattern,
+            negate,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the rule matches `path` (relative to the rule's base)
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            glob_match(&self.pattern, path)
+        } else {
+            glob_match(&self.pattern, path.rsplit('/').next().unwrap_or(path))
+        }
+    }
+}
+
+/// Whether a path is ignored by the stacked ignore files; later files and
+/// later rules win
+fn is_ignored(ignores: &[IgnoreFile], path: &str, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for file in ignores {
+        let relative = if file.base.is_empty() {
+            path
+        } else {
+            match path
+                .strip_prefix(&file.base)
+                .and_then(|p| p.strip_prefix('/'))
+            {
+                Some(relative) => relative,
+                None => continue,
+            }
+        };
+        for rule in &file.rules {
+            if rule.matches(relative, is_dir) {
+                ignored = !rule.negate;
+            }
+        }
+    }
+    ignored
+}
+
+/// Whether the `files` whitelist includes `path`, directly or through a
+/// listed parent directory
+fn is_whitelisted(rules: &[Rule], path: &str) -> bool {
+    let mut included = false;
+    for rule in rules {
+        let mut prefix = path;
+        let hit = loop {
+            if glob_match(&rule.pattern, prefix) {
+                break true;
+            }
+            match prefix.rfind('/') {
+                Some(pos) => prefix = &prefix[..pos],
+                None => break false,
+            }
+        };
+        if hit {
+            included = !rule.negate;
+        }
+    }
+    included
+}
+
+fn walk(
+    root: &Path,
+    dir: &str,
+    whitelist: Option<&[Rule]>,
+    ignores: &mut Vec<IgnoreFile>,
+    files: &mut Vec<String>,
+) -> PmResult<()> {
+    let abs = root.join(dir);
+
+    // A root `.npmignore` is overridden by `files`; nested ones still apply
+    let pushed = if whitelist.is_some() && dir.is_empty() {
+        false
+    } else {
+        let ignore_file = [".npmignore", ".gitignore"]
+            .iter()
+            .map(|name| abs.join(name))
+            .find(|path| path.is_file());
+        match ignore_file {
+            Some(path) => {
+                ignores.push(IgnoreFile {
+                    base: dir.to_string(),
+                    rules: std::fs::read_to_string(path)?
+                        .lines()
+                        .filter_map(Rule::parse)
+                        .collect(),
+                });
+                true
+            }
+            None => false,
+        }
+    };
+
+    let mut entries: Vec<_> = std::fs::read_dir(&abs)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if ALWAYS_EXCLUDED
+            .iter()
+            .any(|pattern| glob_match(pattern, &name))
+        {
+            continue;
+        }
+        let path = if dir.is_empty() {
+            name
+        } else {
+            format!("{}/{}", dir, name)
+        };
+
+        // Follow symlinks the way npm does; skip dangling ones
+        let Ok(metadata) = std::fs::metadata(entry.path()) else {
+            continue;
+        };
+        if is_ignored(ignores, &path, metadata.is_dir()) {
+            continue;
+        }
+        if metadata.is_dir() {
+            walk(root, &path, whitelist, ignores, files)?;
+        } else if whitelist.is_none_or(|rules| is_whitelisted(rules, &path)) {
+            files.push(path);
+        }
+    }
+
+    if pushed {
+        ignores.pop();
+    }
+    Ok(())
+}
+
+/// Match `text` against a glob with `*`, `?`, `**` and `[...]` classes.
+/// `*` and `?` never cross `/`; a `**` segment matches any number of
+/// directories.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let text: Vec<&str> = text.split('/').collect();
+    match_segments(&pattern, &text)
+}
+
+fn match_segments(pattern: &[&str], text: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((&"**", rest)) => (0..=text.len()).any(|skip| match_segments(rest, &text[skip..])),
+        Some((segment, rest)) => match text.split_first() {
+            Some((name, text_rest)) => {
+                match_segment(segment.as_bytes(), name.as_bytes())
+                    && match_segments(rest, text_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| match_segment(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && match_segment(rest, &text[1..]),
+        Some((b'[', rest)) => {
+            let Some(close) = rest.iter().skip(1).position(|&b| b == b']').map(|p| p + 1) else {
+                return text.first() == Some(&b'[') && match_segment(rest, &text[1..]);
+            };
+            let Some((&c, text_rest)) = text.split_first() else {
+                return false;
+            };
+            let class = &rest[..close];
+            let (negate, class) = match class.split_first() {
+                Some((b'!' | b'^', class)) => (true, class),
+                _ => (false, class),
+            };
+            let mut hit = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == b'-' {
+                    hit |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    hit |= class[i] == c;
+                    i += 1;
+                }
+            }
+            hit != negate && match_segment(&rest[close + 1..], text_rest)
+        }
+        Some((&p, rest)) => text.first() == Some(&p) && match_segment(rest, &text[1..]),
+    }
+}
+
+/// Registry document for `PUT /<name>`: the version manifest, the dist-tag
+/// and the tarball as a base64 attachment
+fn publish_body(
+    manifest: &serde_json::Value,
+    pack: &PackResult,
+    registry: &Url,
+    tag: &str,
+    access: Option<Access>,
+) -> PmResult<serde_json::Value> {
+    let unscoped = pack.name.rsplit('/').next().unwrap_or(&pack.name);
+    let attachment = format!("{}-{}.tgz", unscoped, pack.version);
+    let tarball_url = registry
+        .join(&format!("{}/-/{}", pack.name, attachment))
+        .map_err(|e| PmError::Registry(format!("Invalid registry URL: {}", e)))?;
+
+    let mut version = manifest.clone();
+    if let Some(fields) = version.as_object_mut() {
+        fields.insert(
+            "_id".to_string(),
+            format!("{}@{}", pack.name, pack.version).into(),
+        );
+        fields.insert("version".to_string(), pack.version.clone().into());
+        fields.insert(
+            "dist".to_string(),
+            serde_json::json!({
+                "shasum": pack.shasum,
+                "integrity": pack.integrity,
+                "tarball": tarball_url.to_string(),
+            }),
+        );
+    }
+
+    Ok(serde_json::json!({
+        "_id": pack.name,
+        "name": pack.name,
+        "description": manifest.get("description"),
+        "dist-tags": { tag: pack.version },
+        "versions": { pack.version.as_str(): version },
+        "access": access,
+        "_attachments": {
+            attachment: {
+                "content_type": "application/octet-stream",
+                "data": base64::engine::general_purpose::STANDARD.encode(&pack.tarball),
+                "length": pack.tarball.len(),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("viper-pack-{}", uuid::Uuid::new_v4()));
+        for (path, content) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.js", "index.js"));
+        assert!(!glob_match("*.js", "lib/index.js"));
+        assert!(glob_match("lib/**/*.js", "lib/a/b/c.js"));
+        assert!(glob_match("lib/**", "lib/a.js"));
+        assert!(glob_match("file[0-9].txt", "file7.txt"));
+        assert!(!glob_match("file[!0-9].txt", "file7.txt"));
+        assert!(glob_match("README*", "README.MD"));
+    }
+
+    #[test]
+    fn test_pack_files_whitelist() {
+        let root = project(&[
+            (
+                "package.json",
+                r#"{"name":"@acme/tool","version":"1.2.0","main":"index.js","bin":{"tool":"./bin/tool.js"},"files":["dist","!dist/**/*.test.js"]}"#,
+            ),
+            ("index.js", ""),
+            ("README.md", ""),
+            ("bin/tool.js", ""),
+            ("dist/a.js", ""),
+            ("dist/a.test.js", ""),
+            ("dist/.npmignore", "*.map\n"),
+            ("dist/a.js.map", ""),
+            ("src/a.ts", ""),
+            (".npmignore", "dist\n"),
+            ("node_modules/x/index.js", ""),
+        ]);
+        let manifest = read_manifest(&root).unwrap();
+        let files = pack_files(&root, &manifest, &bin_paths(&manifest)).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                "README.md",
+                "bin/tool.js",
+                "dist/a.js",
+                "index.js",
+                "package.json"
+            ]
+        );
+
+        let pack = build_tarball(&root, &manifest).unwrap();
+        assert_eq!(pack.filename, "acme-tool-1.2.0.tgz");
+        assert!(pack.integrity.starts_with("sha512-"));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_pack_files_npmignore() {
+        let root = project(&[
+            ("package.json", r#"{"name":"tool","version":"1.0.0"}"#),
+            ("index.js", ""),
+            ("test/a.js", ""),
+            ("lib/a.js", ""),
+            ("lib/keep.log", ""),
+            ("debug.log", ""),
+            (".npmignore", "test/\n*.log\n!lib/keep.log\n"),
+            (".gitignore", "lib\n"),
+        ]);
+        let manifest = read_manifest(&root).unwrap();
+        let files = pack_files(&root, &manifest, &[]).unwrap();
+        assert_eq!(
+            files,
+            vec!["index.js", "lib/a.js", "lib/keep.log", "package.json"]
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
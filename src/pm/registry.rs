@@ -0,0 +1,91 @@
+//! HTTP client for registry endpoints Orogene doesn't cover
+//!
+//! Audit and publish talk to the registry directly. Requests carry the
+//! `.npmrc` credentials for the target URL and trust its custom CAs.
+
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use url::Url;
+
+use super::error::{PmError, PmResult};
+use super::installer::PackageManagerConfig;
+
+/// Registry request timeout
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Authenticated registry HTTP client
+pub(crate) struct RegistryClient {
+    client: Client,
+    config: PackageManagerConfig,
+}
+
+impl RegistryClient {
+    /// Build a client for the given package manager configuration
+    pub fn new(config: &PackageManagerConfig) -> PmResult<Self> {
+        let mut builder = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(format!("viper/{}", env!("CARGO_PKG_VERSION")));
+
+        for cert in config.npmrc.root_certificates()? {
+            builder = builder.add_root_certificate(cert);
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| PmError::Network(e.to_string()))?;
+        Ok(Self {
+            client,
+            config: config.clone(),
+        })
+    }
+
+    /// Attach `.npmrc` credentials for `url`, if any
+    fn authorize(&self, request: RequestBuilder, url: &Url) -> RequestBuilder {
+        match self
+            .config
+            .npmrc
+            .auth_for(url, &self.config.registry)
+            .and_then(|auth| auth.authorization_header())
+        {
+            Some(header) => request.header(reqwest::header::AUTHORIZATION, header),
+            None => request,
+        }
+    }
+
+    /// POST a JSON body and parse the JSON response
+    pub fn post_json(&self, url: &Url, body: &serde_json::Value) -> PmResult<serde_json::Value> {
+        let request = self
+            .authorize(self.client.post(url.clone()), url)
+            .json(body);
+        Self::json_response(request.send())
+    }
+
+    /// PUT a JSON body and parse the JSON response
+    pub fn put_json(&self, url: &Url, body: &serde_json::Value) -> PmResult<serde_json::Value> {
+        let request = self.authorize(self.client.put(url.clone()), url).json(body);
+        Self::json_response(request.send())
+    }
+
+    fn json_response(response: reqwest::Result<Response>) -> PmResult<serde_json::Value> {
+        let response = response.map_err(|e| PmError::Network(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .map_err(|e| PmError::Network(e.to_string()))?;
+
+        if !status.is_success() {
+            // npm registries put a human-readable reason in `error`
+            let reason = serde_json::from_str::<serde_json::Value>(&text)
+                .ok()
+                .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(String::from))
+                .unwrap_or(text);
+            return Err(PmError::Registry(format!("{}: {}", status, reason)));
+        }
+        if text.trim().is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        serde_json::from_str(&text)
+            .map_err(|e| PmError::Registry(format!("Invalid JSON response: {}", e)))
+    }
+}
@@ -9,7 +9,11 @@
 //! - Relative paths (e.g., "./utils")
 
 use oxc_resolver::{ResolveOptions, Resolver};
-use std::path::{Path, PathBuf};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
 /// Errors that can occur during module resolution
@@ -25,11 +29,20 @@ pub enum ResolverError {
 /// Result type for module resolution operations
 pub type ResolverResult<T> = Result<T, ResolverError>;
 
+/// The `type` field of a package.json
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageType {
+    Module,
+    CommonJs,
+}
+
 /// Node.js/Bun-compatible module resolver
 pub struct ModuleResolver {
     resolver: Resolver,
     cjs_resolver: Resolver,
     base_path: PathBuf,
+    /// Package type by directory, filled as lookups walk up the tree
+    package_types: RefCell<HashMap<PathBuf, Option<PackageType>>>,
 }
 
 impl ModuleResolver {
@@ -75,7 +88,14 @@ impl ModuleResolver {
             condition_names: vec!["require".into(), "node".into(), "default".into()],
 
             // Extensions to try - prefer .js and .cjs for CommonJS
-            extensions: vec![".js".into(), ".cjs".into(), ".json".into(), ".mjs".into()],
+            extensions: vec![
+                ".js".into(),
+                ".cjs".into(),
+                ".json".into(),
+                ".mjs".into(),
+                ".ts".into(),
+                ".cts".into(),
+            ],
 
             // Main fields - prefer main over module for CommonJS
             main_fields: vec!["main".into()],
@@ -96,6 +116,7 @@ impl ModuleResolver {
             resolver,
             cjs_resolver,
             base_path: base,
+            package_types: RefCell::new(HashMap::new()),
         }
     }
 
@@ -132,8 +153,12 @@ impl ModuleResolver {
     pub fn resolve_cjs(&self, specifier: &str, referrer: &Path) -> ResolverResult<PathBuf> {
         // Get the directory of the referrer for relative resolution
         let context = referrer.parent().unwrap_or(&self.base_path);
+        self.resolve_cjs_in(specifier, context)
+    }
 
-        match self.cjs_resolver.resolve(context, specifier) {
+    /// Resolve a `require()` specifier relative to the directory `dir`
+    pub fn resolve_cjs_in(&self, specifier: &str, dir: &Path) -> ResolverResult<PathBuf> {
+        match self.cjs_resolver.resolve(dir, specifier) {
             Ok(resolution) => Ok(resolution.path().to_path_buf()),
             Err(error) => Err(ResolverError::ResolutionFailed(
                 specifier.to_string(),
@@ -141,6 +166,38 @@ impl ModuleResolver {
             )),
         }
     }
+
+    /// The `type` of the nearest package.json above `path`
+    ///
+    /// `None` when there is no package.json or it doesn't set a type.
+    pub fn package_type(&self, path: &Path) -> Option<PackageType> {
+        let mut walked = Vec::new();
+        let mut found = None;
+        for dir in path.ancestors().skip(1) {
+            if let Some(cached) = self.package_types.borrow().get(dir) {
+                found = *cached;
+                break;
+            }
+            walked.push(dir.to_path_buf());
+            let Ok(content) = std::fs::read_to_string(dir.join("package.json")) else {
+                continue;
+            };
+            found = serde_json::from_str::<serde_json::Value>(&content)
+                .ok()
+                .and_then(|pkg| match pkg.get("type")?.as_str()? {
+                    "module" => Some(PackageType::Module),
+                    "commonjs" => Some(PackageType::CommonJs),
+                    _ => None,
+                });
+            break;
+        }
+
+        let mut cache = self.package_types.borrow_mut();
+        for dir in walked {
+            cache.insert(dir, found);
+        }
+        found
+    }
 }
 
 #[cfg(test)]
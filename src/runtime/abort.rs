@@ -0,0 +1,125 @@
+//! DOM events and cancellation
+//!
+//! Provides the `Event`, `EventTarget`, `DOMException`, `AbortController`
+//! and `AbortSignal` globals, including `AbortSignal.timeout()` and
+//! `AbortSignal.any()`.
+
+use boa_engine::{Context, JsResult, Source};
+
+/// Register the event and abort globals
+pub fn register_abort(context: &mut Context) -> JsResult<()> {
+    let abort_code = include_str!("abort_module.js");
+    let source = Source::from_bytes(abort_code.as_bytes());
+    context.eval(source)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::{Runtime, seen};
+
+    #[test]
+    fn test_abort_reasons() {
+        let code = r#"
+            const controller = new AbortController();
+            let events = 0;
+            controller.signal.addEventListener('abort', () => events++);
+            controller.abort();
+            const reason = controller.signal.reason;
+            controller.abort('again');
+
+            const custom = new AbortController();
+            custom.abort('stop');
+            let thrown;
+            try { custom.signal.throwIfAborted(); } catch (e) { thrown = e; }
+
+            globalThis.seen = [
+                reason instanceof DOMException && reason.name,
+                reason.code,
+                controller.signal.reason === reason,
+                events,
+                thrown,
+                AbortSignal.abort(42).reason,
+            ];
+        "#;
+        assert_eq!(seen(code), "AbortError,20,true,1,stop,42");
+    }
+
+    #[test]
+    fn test_timeout_signal() {
+        let code = r#"
+            import { setTimeout as sleep } from 'node:timers/promises';
+            const signal = AbortSignal.timeout(10);
+            const slept = await sleep(1000, null, { signal }).then(() => 'ok', (e) => e.name);
+            globalThis.seen = [slept, signal.reason.name, signal.reason.code];
+        "#;
+        assert_eq!(seen(code), "AbortError,TimeoutError,23");
+    }
+
+    #[test]
+    fn test_any_signal() {
+        let code = r#"
+            const first = new AbortController();
+            const second = new AbortController();
+            const any = AbortSignal.any([first.signal, second.signal]);
+            second.abort('second');
+            first.abort('first');
+
+            const already = AbortSignal.any([new AbortController().signal, AbortSignal.abort('early')]);
+            globalThis.seen = [any.aborted, any.reason, already.aborted, already.reason];
+        "#;
+        assert_eq!(seen(code), "true,second,true,early");
+    }
+
+    #[test]
+    fn test_events_once_abort() {
+        let code = r#"
+            import { once } from 'node:events';
+            const controller = new AbortController();
+            const waiting = once(new EventTarget(), 'ready', { signal: controller.signal });
+            controller.abort('stop');
+            globalThis.seen = [await waiting.then(() => 'ok', (e) => e.name + ':' + e.cause)];
+        "#;
+        assert_eq!(seen(code), "AbortError:stop");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_spawn_abort() {
+        let code = r#"
+            import { spawn } from 'node:child_process';
+            const controller = new AbortController();
+            const child = spawn('sleep', ['5'], { signal: controller.signal });
+            const failed = new Promise((resolve) => child.on('error', (e) => resolve(e.name)));
+            const exited = new Promise((resolve) => child.on('exit', (code, signal) => resolve(signal)));
+            controller.abort();
+            globalThis.seen = [await failed, await exited];
+        "#;
+        assert_eq!(seen(code), "AbortError,SIGTERM");
+    }
+
+    #[test]
+    fn test_fs_abort() {
+        let dir = std::env::temp_dir().join(format!("viper-abort-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("data.txt"), "hello").unwrap();
+
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            import fs from 'node:fs';
+            const path = import.meta.dirname + '/data.txt';
+            const read = await fs.promises.readFile(path, { signal: AbortSignal.abort() })
+                .then(() => 'ok', (e) => e.name);
+            const text = await fs.promises.readFile(path, {
+                encoding: 'utf8',
+                signal: new AbortController().signal,
+            });
+            globalThis.seen = [read, text];
+        "#;
+        let result = runtime.run(code, &dir.join("main.mjs").to_string_lossy());
+        let seen = runtime.eval("String(globalThis.seen)", "check.js");
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+        assert_eq!(runtime.value_to_string(&seen.unwrap()), "AbortError,hello");
+    }
+}
@@ -0,0 +1,471 @@
+/**
+ * DOM events and cancellation
+ *
+ * `Event`, `EventTarget`, `DOMException`, `AbortController` and
+ * `AbortSignal` (with `abort()`, `timeout()` and `any()`), following the DOM
+ * Standard.
+ */
+(function () {
+  "use strict";
+
+  // ==========================================================================
+  // DOMException
+  // ==========================================================================
+
+  // Legacy code of each name that has one
+  const legacyCodes = {
+    IndexSizeError: 1,
+    HierarchyRequestError: 3,
+    WrongDocumentError: 4,
+    InvalidCharacterError: 5,
+    NoModificationAllowedError: 7,
+    NotFoundError: 8,
+    NotSupportedError: 9,
+    InvalidStateError: 11,
+    SyntaxError: 12,
+    InvalidModificationError: 13,
+    NamespaceError: 14,
+    InvalidAccessError: 15,
+    TypeMismatchError: 17,
+    SecurityError: 18,
+    NetworkError: 19,
+    AbortError: 20,
+    URLMismatchError: 21,
+    QuotaExceededError: 22,
+    TimeoutError: 23,
+    InvalidNodeTypeError: 24,
+    DataCloneError: 25,
+  };
+
+  class DOMException extends Error {
+    #name;
+
+    constructor(message = "", options = "Error") {
+      const cause = options !== null && typeof options === "object" && "cause" in options;
+      super(String(message), cause ? { cause: options.cause } : undefined);
+      this.#name =
+        options !== null && typeof options === "object"
+          ? options.name === undefined
+            ? "Error"
+            : String(options.name)
+          : String(options);
+    }
+
+    get name() {
+      return this.#name;
+    }
+
+    get code() {
+      return legacyCodes[this.#name] ?? 0;
+    }
+  }
+
+  const constantNames = {
+    INDEX_SIZE_ERR: 1,
+    DOMSTRING_SIZE_ERR: 2,
+    HIERARCHY_REQUEST_ERR: 3,
+    WRONG_DOCUMENT_ERR: 4,
+    INVALID_CHARACTER_ERR: 5,
+    NO_DATA_ALLOWED_ERR: 6,
+    NO_MODIFICATION_ALLOWED_ERR: 7,
+    NOT_FOUND_ERR: 8,
+    NOT_SUPPORTED_ERR: 9,
+    INUSE_ATTRIBUTE_ERR: 10,
+    INVALID_STATE_ERR: 11,
+    SYNTAX_ERR: 12,
+    INVALID_MODIFICATION_ERR: 13,
+    NAMESPACE_ERR: 14,
+    INVALID_ACCESS_ERR: 15,
+    VALIDATION_ERR: 16,
+    TYPE_MISMATCH_ERR: 17,
+    SECURITY_ERR: 18,
+    NETWORK_ERR: 19,
+    ABORT_ERR: 20,
+    URL_MISMATCH_ERR: 21,
+    QUOTA_EXCEEDED_ERR: 22,
+    TIMEOUT_ERR: 23,
+    INVALID_NODE_TYPE_ERR: 24,
+    DATA_CLONE_ERR: 25,
+  };
+  for (const [name, value] of Object.entries(constantNames)) {
+    Object.defineProperty(DOMException, name, { value, enumerable: true });
+    Object.defineProperty(DOMException.prototype, name, { value, enumerable: true });
+  }
+
+  // ==========================================================================
+  // Event
+  // ==========================================================================
+
+  const kDispatch = Symbol("viper.event.dispatch");
+
+  class Event {
+    #type;
+    #bubbles;
+    #cancelable;
+    #composed;
+    #canceled = false;
+    #stop = false;
+    #stopImmediate = false;
+    #target = null;
+    #currentTarget = null;
+    #phase = 0;
+    #dispatching = false;
+    #inPassiveListener = false;
+    #timeStamp;
+
+    constructor(type, init = {}) {
+      if (arguments.length === 0) {
+        throw new TypeError("The event type is required");
+      }
+      this.#type = String(type);
+      this.#bubbles = Boolean(init?.bubbles);
+      this.#cancelable = Boolean(init?.cancelable);
+      this.#composed = Boolean(init?.composed);
+      this.#timeStamp =
+        typeof globalThis.performance?.now === "function" ? globalThis.performance.now() : Date.now();
+    }
+
+    get type() {
+      return this.#type;
+    }
+
+    get target() {
+      return this.#target;
+    }
+
+    get srcElement() {
+      return this.#target;
+    }
+
+    get currentTarget() {
+      return this.#currentTarget;
+    }
+
+    get eventPhase() {
+      return this.#phase;
+    }
+
+    get bubbles() {
+      return this.#bubbles;
+    }
+
+    get cancelable() {
+      return this.#cancelable;
+    }
+
+    get composed() {
+      return this.#composed;
+    }
+
+    get defaultPrevented() {
+      return this.#canceled;
+    }
+
+    get returnValue() {
+      return !this.#canceled;
+    }
+
+    set returnValue(value) {
+      if (!value) this.preventDefault();
+    }
+
+    get cancelBubble() {
+      return this.#stop;
+    }
+
+    set cancelBubble(value) {
+      if (value) this.#stop = true;
+    }
+
+    get isTrusted() {
+      return false;
+    }
+
+    get timeStamp() {
+      return this.#timeStamp;
+    }
+
+    composedPath() {
+      return this.#dispatching && this.#currentTarget !== null ? [this.#currentTarget] : [];
+    }
+
+    preventDefault() {
+      if (this.#cancelable && !this.#inPassiveListener) this.#canceled = true;
+    }
+
+    stopPropagation() {
+      this.#stop = true;
+    }
+
+    stopImmediatePropagation() {
+      this.#stop = true;
+      this.#stopImmediate = true;
+    }
+
+    // Runs `listeners` of `target` for this event; used by dispatchEvent
+    [kDispatch](target, listeners) {
+      if (this.#dispatching) {
+        throw new DOMException("The event is already being dispatched", "InvalidStateError");
+      }
+      this.#dispatching = true;
+      this.#target = target;
+      this.#currentTarget = target;
+      this.#phase = 2;
+      for (const listener of listeners) {
+        if (this.#stopImmediate) break;
+        if (listener.removed) continue;
+        if (listener.once) listener.remove();
+        this.#inPassiveListener = listener.passive;
+        try {
+          const callback = listener.callback;
+          if (typeof callback === "function") {
+            callback.call(target, this);
+          } else if (callback && typeof callback.handleEvent === "function") {
+            callback.handleEvent(this);
+          }
+        } catch (err) {
+          reportException(err);
+        }
+        this.#inPassiveListener = false;
+      }
+      this.#dispatching = false;
+      this.#currentTarget = null;
+      this.#phase = 0;
+      this.#stop = false;
+      this.#stopImmediate = false;
+      return !this.#canceled;
+    }
+  }
+
+  for (const [name, value] of Object.entries({
+    NONE: 0,
+    CAPTURING_PHASE: 1,
+    AT_TARGET: 2,
+    BUBBLING_PHASE: 3,
+  })) {
+    Object.defineProperty(Event, name, { value, enumerable: true });
+    Object.defineProperty(Event.prototype, name, { value, enumerable: true });
+  }
+
+  // A throwing listener doesn't stop the others; report it like Node does
+  function reportException(err) {
+    queueMicrotask(() => {
+      throw err;
+    });
+  }
+
+  // ==========================================================================
+  // EventTarget
+  // ==========================================================================
+
+  function flattenOptions(options) {
+    if (typeof options === "boolean") return { capture: options };
+    if (options === null || typeof options !== "object") return { capture: false };
+    return options;
+  }
+
+  class EventTarget {
+    #listeners = new Map();
+
+    addEventListener(type, callback, options = {}) {
+      if (callback === null || callback === undefined) return;
+      const { capture = false, once = false, passive = false, signal } = flattenOptions(options);
+      if (signal !== undefined && signal.aborted) return;
+
+      type = String(type);
+      let list = this.#listeners.get(type);
+      if (list === undefined) {
+        list = [];
+        this.#listeners.set(type, list);
+      }
+      if (list.some((l) => l.callback === callback && l.capture === Boolean(capture))) return;
+
+      const listener = {
+        callback,
+        capture: Boolean(capture),
+        once: Boolean(once),
+        passive: Boolean(passive),
+        removed: false,
+        remove: () => {
+          listener.removed = true;
+          const current = this.#listeners.get(type);
+          if (current === undefined) return;
+          const index = current.indexOf(listener);
+          if (index !== -1) current.splice(index, 1);
+        },
+      };
+      list.push(listener);
+
+      if (signal !== undefined) {
+        signal.addEventListener("abort", () => listener.remove(), { once: true });
+      }
+    }
+
+    removeEventListener(type, callback, options = {}) {
+      const { capture = false } = flattenOptions(options);
+      const list = this.#listeners.get(String(type));
+      if (list === undefined) return;
+      const listener = list.find((l) => l.callback === callback && l.capture === Boolean(capture));
+      if (listener !== undefined) listener.remove();
+    }
+
+    dispatchEvent(event) {
+      if (!(event instanceof Event)) {
+        throw new TypeError("The event must be an instance of Event");
+      }
+      const list = this.#listeners.get(event.type);
+      return event[kDispatch](this, list === undefined ? [] : list.slice());
+    }
+  }
+
+  // ==========================================================================
+  // AbortSignal and AbortController
+  // ==========================================================================
+
+  const kCreate = Symbol("viper.abortSignal.create");
+  const kSignalAbort = Symbol("viper.abortSignal.signalAbort");
+
+  function defaultReason() {
+    return new DOMException("This operation was aborted", "AbortError");
+  }
+
+  class AbortSignal extends EventTarget {
+    #aborted = false;
+    #reason = undefined;
+    #onabort = null;
+    #dependents = [];
+
+    constructor(token) {
+      if (token !== kCreate) {
+        throw new TypeError("Illegal constructor");
+      }
+      super();
+    }
+
+    static abort(reason) {
+      const signal = new AbortSignal(kCreate);
+      signal.#aborted = true;
+      signal.#reason = reason === undefined ? defaultReason() : reason;
+      return signal;
+    }
+
+    static timeout(milliseconds) {
+      const delay = Number(milliseconds);
+      if (!Number.isFinite(delay) || delay < 0) {
+        throw new TypeError("The timeout must be a non-negative number");
+      }
+      const signal = new AbortSignal(kCreate);
+      const timer = setTimeout(() => {
+        signal[kSignalAbort](new DOMException("The operation was aborted due to timeout", "TimeoutError"));
+      }, delay);
+      // A pending timeout signal doesn't keep the program alive
+      timer?.unref?.();
+      return signal;
+    }
+
+    static any(signals) {
+      const signal = new AbortSignal(kCreate);
+      const sources = Array.from(signals);
+      for (const source of sources) {
+        if (!(source instanceof AbortSignal)) {
+          throw new TypeError("Each signal must be an AbortSignal");
+        }
+        if (source.aborted) {
+          signal.#aborted = true;
+          signal.#reason = source.reason;
+          return signal;
+        }
+      }
+      for (const source of sources) source.#dependents.push(signal);
+      return signal;
+    }
+
+    get aborted() {
+      return this.#aborted;
+    }
+
+    get reason() {
+      return this.#reason;
+    }
+
+    get onabort() {
+      return this.#onabort;
+    }
+
+    set onabort(handler) {
+      this.#onabort = typeof handler === "function" ? handler : null;
+    }
+
+    throwIfAborted() {
+      if (this.#aborted) throw this.#reason;
+    }
+
+    [kSignalAbort](reason) {
+      if (this.#aborted) return;
+      this.#aborted = true;
+      this.#reason = reason === undefined ? defaultReason() : reason;
+
+      // Dependents see the abort before any listener runs
+      const dependents = this.#dependents;
+      this.#dependents = [];
+      const toNotify = [];
+      for (const dependent of dependents) {
+        if (dependent.#aborted) continue;
+        dependent.#aborted = true;
+        dependent.#reason = this.#reason;
+        toNotify.push(dependent);
+      }
+
+      this.#fireAbort();
+      for (const dependent of toNotify) dependent.#fireAbort();
+    }
+
+    #fireAbort() {
+      const event = new Event("abort");
+      if (this.#onabort !== null) {
+        try {
+          this.#onabort.call(this, event);
+        } catch (err) {
+          reportException(err);
+        }
+      }
+      this.dispatchEvent(event);
+    }
+  }
+
+  Object.defineProperty(AbortSignal.prototype, Symbol.toStringTag, {
+    value: "AbortSignal",
+    configurable: true,
+  });
+
+  class AbortController {
+    #signal;
+
+    constructor() {
+      this.#signal = new AbortSignal(kCreate);
+    }
+
+    get signal() {
+      return this.#signal;
+    }
+
+    abort(reason) {
+      this.#signal[kSignalAbort](reason);
+    }
+  }
+
+  Object.defineProperty(AbortController.prototype, Symbol.toStringTag, {
+    value: "AbortController",
+    configurable: true,
+  });
+
+  const exports = { DOMException, Event, EventTarget, AbortController, AbortSignal };
+  for (const [name, value] of Object.entries(exports)) {
+    Object.defineProperty(globalThis, name, {
+      value,
+      writable: true,
+      configurable: true,
+      enumerable: false,
+    });
+  }
+})();
@@ -0,0 +1,562 @@
+//! Non-JavaScript modules: JSON, text, bytes and WebAssembly
+//!
+//! Boa's module loader only sees specifiers, so import attributes are moved
+//! into them before a module is parsed: [`rewrite_import_attributes`] turns
+//! `import data from './x.json' with { type: 'json' }` into an import of a
+//! tagged specifier the loader splits again with [`split_specifier`]. Static
+//! imports, re-exports and `import()` calls with a literal options object are
+//! rewritten; the legacy `assert { ... }` form is treated like `with`.
+//!
+//! | `type`    | Default export                        |
+//! |-----------|---------------------------------------|
+//! | `json`    | the parsed document                   |
+//! | `text`    | the file contents as a string         |
+//! | `bytes`   | a `Uint8Array` with the file contents |
+//!
+//! `.json` and `.wasm` files load as their type without an attribute. A
+//! WebAssembly module is instantiated through the `WebAssembly` global with
+//! its imports taken from the modules it names, and re-exports the instance's
+//! exports. Viper doesn't ship a WebAssembly engine; importing `.wasm` fails
+//! unless the embedder installs one.
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64_STANDARD};
+use oxc_allocator::Allocator;
+use oxc_ast::{
+    AstKind,
+    ast::{Expression, ImportAttributeKey, ObjectPropertyKind, WithClause},
+};
+use oxc_parser::Parser;
+use oxc_semantic::SemanticBuilder;
+use oxc_span::{GetSpan, SourceType, Span};
+use std::{collections::BTreeSet, path::Path};
+
+use super::host;
+
+/// Marks a specifier carrying an import attribute; can't occur in a path
+const TAG: &str = "\0asset:";
+
+/// Marks a specifier of `import()` carrying an import attribute. Boa 0.21
+/// asserts a dynamic import resolves to the module a static import of the same
+/// specifier did, but compares against the importing module instead, so the
+/// two never share a specifier.
+const DYNAMIC_TAG: &str = "\0asset-dynamic:";
+
+/// How a module that isn't JavaScript is loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Json,
+    Text,
+    Bytes,
+    Wasm,
+}
+
+impl AssetKind {
+    /// The kind requested by `with { type }`
+    fn from_type(ty: &str) -> Option<Self> {
+        match ty {
+            "json" => Some(Self::Json),
+            "text" => Some(Self::Text),
+            "bytes" => Some(Self::Bytes),
+            _ => None,
+        }
+    }
+
+    /// The kind a file loads as without an attribute
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Some(Self::Json),
+            Some("wasm") => Some(Self::Wasm),
+            _ => None,
+        }
+    }
+}
+
+/// Split a specifier produced by [`rewrite_import_attributes`]
+pub fn split_specifier(specifier: &str) -> Result<(&str, Option<AssetKind>), String> {
+    let Some(tagged) = specifier
+        .strip_prefix(TAG)
+        .or_else(|| specifier.strip_prefix(DYNAMIC_TAG))
+    else {
+        return Ok((specifier, None));
+    };
+    let (ty, specifier) = tagged.split_once(':').unwrap_or((tagged, ""));
+    match AssetKind::from_type(ty) {
+        Some(kind) => Ok((specifier, Some(kind))),
+        None => Err(format!(
+            "Unsupported import attribute type '{}' for '{}'",
+            ty, specifier
+        )),
+    }
+}
+
+// ============================================================================
+// Import attribute rewriting
+// ============================================================================
+
+/// An edit of the importing module's source
+struct Edit {
+    /// Replaced by the tagged specifier
+    specifier: Span,
+    /// Removed: the attributes clause with its `with` keyword, or the options
+    /// argument with the comma before it for `import()`
+    remove: Span,
+    specifier_value: String,
+    ty: String,
+    /// Whether the edit is of an `import()` call
+    dynamic: bool,
+}
+
+/// Move `type` import attributes of `code` into the imported specifiers
+///
+/// Returns `code` unchanged when it has no attributes or doesn't parse.
+pub fn rewrite_import_attributes(code: &str) -> String {
+    if !has_attribute_clause(code) {
+        return code.to_string();
+    }
+
+    let allocator = Allocator::default();
+    let source_type = SourceType::mjs().with_jsx(true);
+    let parsed = Parser::new(&allocator, code, source_type).parse();
+    if !parsed.errors.is_empty() {
+        // Boa reports the syntax error when it parses the module
+        return code.to_string();
+    }
+    let semantic = SemanticBuilder::new().build(&parsed.program).semantic;
+
+    let mut edits = Vec::new();
+    for node in semantic.nodes().iter() {
+        match node.kind() {
+            AstKind::ImportDeclaration(decl) => {
+                edits.extend(static_edit(
+                    decl.source.span,
+                    &decl.source.value,
+                    decl.with_clause.as_deref(),
+                ));
+            }
+            AstKind::ExportNamedDeclaration(decl) => {
+                if let Some(source) = &decl.source {
+                    edits.extend(static_edit(
+                        source.span,
+                        &source.value,
+                        decl.with_clause.as_deref(),
+                    ));
+                }
+            }
+            AstKind::ExportAllDeclaration(decl) => {
+                edits.extend(static_edit(
+                    decl.source.span,
+                    &decl.source.value,
+                    decl.with_clause.as_deref(),
+                ));
+            }
+            AstKind::ImportExpression(expr) => {
+                let (Expression::StringLiteral(source), Some(options)) =
+                    (&expr.source, &expr.options)
+                else {
+                    continue;
+                };
+                if let Some(ty) = dynamic_type(options) {
+                    edits.push(Edit {
+                        specifier: source.span,
+                        remove: Span::new(source.span.end, options.span().end),
+                        specifier_value: source.value.to_string(),
+                        ty,
+                        dynamic: true,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if edits.is_empty() {
+        return code.to_string();
+    }
+    apply(code, edits)
+}
+
+/// Cheap check for `with {` or `assert {`, or the `with:` key of an
+/// `import()` options object, before parsing
+fn has_attribute_clause(code: &str) -> bool {
+    ["with", "assert"].iter().any(|keyword| {
+        code.match_indices(keyword).any(|(index, _)| {
+            let rest = code[index + keyword.len()..].trim_start_matches(['"', '\'']);
+            rest.trim_start().starts_with(['{', ':'])
+        })
+    })
+}
+
+fn static_edit(specifier: Span, value: &str, clause: Option<&WithClause>) -> Option<Edit> {
+    let clause = clause?;
+    let ty = clause.with_entries.iter().find_map(|attribute| {
+        let key = match &attribute.key {
+            ImportAttributeKey::Identifier(ident) => ident.name.as_str(),
+            ImportAttributeKey::StringLiteral(lit) => lit.value.as_str(),
+        };
+        (key == "type").then(|| attribute.value.value.to_string())
+    });
+    Some(Edit {
+        specifier,
+        // The clause span starts at `{`; take the `with` keyword along
+        remove: Span::new(specifier.end, clause.span.end),
+        specifier_value: value.to_string(),
+        // A clause without `type` is dropped; the file loads by its extension
+        ty: ty.unwrap_or_default(),
+        dynamic: false,
+    })
+}
+
+/// `type` of `import(x, { with: { type } })`
+fn dynamic_type(options: &Expression) -> Option<String> {
+    let Expression::ObjectExpression(options) = options else {
+        return None;
+    };
+    options.properties.iter().find_map(|property| {
+        let ObjectPropertyKind::ObjectProperty(property) = property else {
+            return None;
+        };
+        let key = property.key.static_name()?;
+        if key != "with" && key != "assert" {
+            return None;
+        }
+        let Expression::ObjectExpression(attributes) = &property.value else {
+            return None;
+        };
+        attributes.properties.iter().find_map(|attribute| {
+            let ObjectPropertyKind::ObjectProperty(attribute) = attribute else {
+                return None;
+            };
+            if attribute.key.static_name()? != "type" {
+                return None;
+            }
+            match &attribute.value {
+                Expression::StringLiteral(ty) => Some(ty.value.to_string()),
+                _ => None,
+            }
+        })
+    })
+}
+
+fn apply(code: &str, mut edits: Vec<Edit>) -> String {
+    edits.sort_by_key(|edit| edit.specifier.start);
+    let mut out = String::with_capacity(code.len());
+    let mut pos = 0;
+    for edit in edits {
+        let specifier = if edit.ty.is_empty() {
+            edit.specifier_value
+        } else {
+            let tag = if edit.dynamic { DYNAMIC_TAG } else { TAG };
+            format!("{}{}:{}", tag, edit.ty, edit.specifier_value)
+        };
+        out.push_str(&code[pos..edit.specifier.start as usize]);
+        out.push_str(&js_string_literal(&specifier));
+        out.push_str(&code[edit.specifier.end as usize..edit.remove.start as usize]);
+        pos = edit.remove.end as usize;
+    }
+    out.push_str(&code[pos..]);
+    out
+}
+
+/// A double-quoted JavaScript string literal for `value`
+fn js_string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+// ============================================================================
+// Module sources
+// ============================================================================
+
+/// Source of the ES module exposing the file at `path` loaded as `kind`
+pub fn module_source(path: &Path, kind: AssetKind) -> Result<String, String> {
+    let display = path.display();
+    let read_error = |e: std::io::Error| format!("Failed to read module '{}': {}", display, e);
+    match kind {
+        AssetKind::Json => {
+            let text = std::fs::read_to_string(path).map_err(read_error)?;
+            serde_json::from_str::<serde_json::Value>(&text)
+                .map_err(|e| format!("Invalid JSON in '{}': {}", display, e))?;
+            Ok(format!(
+                "export default JSON.parse({});\n",
+                js_string_literal(&text)
+            ))
+        }
+        AssetKind::Text => {
+            let text = std::fs::read_to_string(path).map_err(read_error)?;
+            Ok(format!("export default {};\n", js_string_literal(&text)))
+        }
+        AssetKind::Bytes => {
+            let bytes = std::fs::read(path).map_err(read_error)?;
+            Ok(format!(
+                "export default new Uint8Array(Buffer.from({}, 'base64'));\n",
+                js_string_literal(&BASE64_STANDARD.encode(bytes))
+            ))
+        }
+        AssetKind::Wasm => {
+            let bytes = std::fs::read(path).map_err(read_error)?;
+            let interface = wasm_interface(&bytes)
+                .ok_or_else(|| format!("Invalid WebAssembly module '{}'", display))?;
+            Ok(wasm_source(&bytes, &interface))
+        }
+    }
+}
+
+/// Import module names and export names of a WebAssembly binary
+struct WasmInterface {
+    imports: BTreeSet<String>,
+    exports: Vec<String>,
+}
+
+fn wasm_source(bytes: &[u8], interface: &WasmInterface) -> String {
+    let mut code = String::new();
+    for (index, module) in interface.imports.iter().enumerate() {
+        code.push_str(&format!(
+            "import * as import{} from {};\n",
+            index,
+            js_string_literal(module)
+        ));
+    }
+    let imports = interface
+        .imports
+        .iter()
+        .enumerate()
+        .map(|(index, module)| format!("{}: import{}", js_string_literal(module), index))
+        .collect::<Vec<_>>()
+        .join(", ");
+    code.push_str(&format!(
+        "const bytes = new Uint8Array(Buffer.from({}, 'base64'));\n\
+         const instance = new WebAssembly.Instance(new WebAssembly.Module(bytes), {{ {} }});\n",
+        js_string_literal(&BASE64_STANDARD.encode(bytes)),
+        imports
+    ));
+    for (index, name) in interface.exports.iter().enumerate() {
+        let literal = js_string_literal(name);
+        // Names that aren't identifiers need string export names
+        let exported = if host::is_identifier(name) {
+            name.clone()
+        } else {
+            literal.clone()
+        };
+        code.push_str(&format!(
+            "const export{} = instance.exports[{}];\nexport {{ export{} as {} }};\n",
+            index, literal, index, exported
+        ));
+    }
+    code
+}
+
+/// Read the import and export sections of a WebAssembly binary
+fn wasm_interface(bytes: &[u8]) -> Option<WasmInterface> {
+    let mut reader = WasmReader { bytes, pos: 0 };
+    if reader.take(8)? != b"\0asm\x01\0\0\0" {
+        return None;
+    }
+    let mut interface = WasmInterface {
+        imports: BTreeSet::new(),
+        exports: Vec::new(),
+    };
+    while reader.pos < bytes.len() {
+        let id = reader.byte()?;
+        let size = reader.leb()? as usize;
+        let mut section = WasmReader {
+            bytes: reader.take(size)?,
+            pos: 0,
+        };
+        match id {
+            // Import section: module, name, description
+            2 => {
+                for _ in 0..section.leb()? {
+                    interface.imports.insert(section.name()?);
+                    section.name()?;
+                    match section.byte()? {
+                        // Function or tag: type index (a tag has an attribute first)
+                        0x00 => {
+                            section.leb()?;
+                        }
+                        0x04 => {
+                            section.byte()?;
+                            section.leb()?;
+                        }
+                        // Table: reference type and limits
+                        0x01 => {
+                            section.byte()?;
+                            section.limits()?;
+                        }
+                        0x02 => section.limits()?,
+                        // Global: value type and mutability
+                        0x03 => {
+                            section.byte()?;
+                            section.byte()?;
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            // Export section: name, kind, index
+            7 => {
+                for _ in 0..section.leb()? {
+                    interface.exports.push(section.name()?);
+                    section.byte()?;
+                    section.leb()?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(interface)
+}
+
+struct WasmReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> WasmReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    /// Unsigned LEB128
+    fn leb(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let len = self.leb()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn limits(&mut self) -> Option<()> {
+        let flags = self.byte()?;
+        self.leb()?;
+        if flags & 0x01 != 0 {
+            self.leb()?;
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{Runtime, RuntimeResult};
+
+    /// Run `code` as `main.mjs` next to a JSON, a text and a broken JSON file
+    fn run_with_assets(name: &str, code: &str) -> RuntimeResult<String> {
+        let dir = std::env::temp_dir().join(format!("viper-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("config.json"),
+            r#"{ "name": "viper", "port": 8080 }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "hello\n").unwrap();
+        std::fs::write(dir.join("broken.json"), "{ name: ").unwrap();
+
+        let mut runtime = Runtime::new().unwrap();
+        let result = runtime.run(code, &dir.join("main.mjs").to_string_lossy());
+        std::fs::remove_dir_all(&dir).unwrap();
+        result?;
+        let seen = runtime.eval("String(globalThis.seen)", "check.js")?;
+        Ok(runtime.value_to_string(&seen))
+    }
+
+    #[test]
+    fn test_json_imports() {
+        let code = r#"
+            import config from './config.json' with { type: 'json' };
+            import again from './config.json';
+            globalThis.seen = [config.name, config.port, config === again];
+        "#;
+        assert_eq!(
+            run_with_assets("json-assets", code).unwrap(),
+            "viper,8080,true"
+        );
+    }
+
+    #[test]
+    fn test_text_and_bytes_imports() {
+        let code = r#"
+            import notes from './notes.txt' with { type: 'text' };
+            import bytes from './notes.txt' with { type: 'bytes' };
+            globalThis.seen = [JSON.stringify(notes), bytes instanceof Uint8Array && bytes.length];
+        "#;
+        assert_eq!(
+            run_with_assets("text-assets", code).unwrap(),
+            r#""hello\n",6"#
+        );
+    }
+
+    #[test]
+    fn test_dynamic_asset_imports() {
+        let code = r#"
+            import notes from './notes.txt' with { type: 'text' };
+            const dynamic = await import('./notes.txt', { with: { type: 'text' } });
+            const bytes = await import('./notes.txt', { with: { type: 'bytes' } });
+            globalThis.seen = [dynamic.default === notes, bytes.default.length];
+        "#;
+        assert_eq!(run_with_assets("dynamic-assets", code).unwrap(), "true,6");
+    }
+
+    #[test]
+    fn test_asset_import_errors() {
+        // The loader rejects with its message, like any module that fails to load
+        let code = r#"
+            const fail = (promise) => promise.then(() => 'ok', (e) => String(e).split(" '")[0]);
+            globalThis.seen = [
+                await fail(import('./broken.json', { with: { type: 'json' } })),
+                await fail(import('./missing.txt', { with: { type: 'text' } })),
+            ];
+        "#;
+        assert_eq!(
+            run_with_assets("broken-assets", code).unwrap(),
+            "Invalid JSON in,Failed to resolve module"
+        );
+
+        let code = "import style from './site.css' with { type: 'css' };";
+        assert!(run_with_assets("css-assets", code).is_err());
+    }
+
+    #[test]
+    fn test_rewrite_import_attributes() {
+        let code = r#"import config from './config.json' with { type: 'json' };
+export { default as notes } from './notes.txt' with { type: 'text' };
+const bytes = await import('./notes.txt', { with: { type: 'bytes' } });
+const plain = await import('./lib.js');"#;
+        let rewritten = rewrite_import_attributes(code);
+        assert_eq!(
+            rewritten,
+            r#"import config from "\u0000asset:json:./config.json";
+export { default as notes } from "\u0000asset:text:./notes.txt";
+const bytes = await import("\u0000asset-dynamic:bytes:./notes.txt");
+const plain = await import('./lib.js');"#
+        );
+    }
+
+    #[test]
+    fn test_split_specifier() {
+        assert_eq!(
+            split_specifier("\0asset:json:./config.json"),
+            Ok(("./config.json", Some(AssetKind::Json)))
+        );
+        assert_eq!(
+            split_specifier("\0asset-dynamic:text:./notes.txt"),
+            Ok(("./notes.txt", Some(AssetKind::Text)))
+        );
+        assert_eq!(split_specifier("./lib.js"), Ok(("./lib.js", None)));
+        assert!(split_specifier("\0asset:css:./site.css").is_err());
+    }
+}
@@ -7,6 +7,7 @@
 //! - Buffer.alloc(size[, fill[, encoding]]) - Allocate zero-filled buffer
 //! - Buffer.allocUnsafe(size) - Allocate uninitialized buffer (fast)
 //! - Buffer.from(array|string|buffer[, encoding]) - Create from data
+//! - Buffer.from(arrayBuffer[, byteOffset[, length]]) - View shared memory
 //! - Buffer.concat(list[, totalLength]) - Concatenate buffers
 //! - Buffer.byteLength(string[, encoding]) - Get byte length
 //! - Buffer.compare(buf1, buf2) - Compare two buffers
@@ -912,6 +913,40 @@ fn buffer_from(_this: &JsValue, args: &[JsValue], context: &mut Context) -> JsRe
 
     // Handle array-like (including arrays and other buffers)
     if let Some(obj) = source.as_object() {
+        // An ArrayBuffer is shared, not copied: the Buffer is a view over
+        // byteOffset..byteOffset + length
+        if let Ok(array_buffer) = JsArrayBuffer::from_object(obj.clone()) {
+            let byte_length = array_buffer.byte_length();
+            let offset = match args.get_or_undefined(1) {
+                value if value.is_undefined() => 0,
+                value => value.to_length(context)? as usize,
+            };
+            let length = match args.get_or_undefined(2) {
+                value if value.is_undefined() => byte_length.saturating_sub(offset),
+                value => value.to_length(context)? as usize,
+            };
+            if offset > byte_length || length > byte_length - offset {
+                return Err(JsNativeError::range()
+                    .with_message("\"offset\" or \"length\" is outside of buffer bounds")
+                    .into());
+            }
+            let view = context
+                .intrinsics()
+                .constructors()
+                .typed_uint8_array()
+                .constructor()
+                .construct(
+                    &[
+                        obj.clone().into(),
+                        JsValue::from(offset as f64),
+                        JsValue::from(length as f64),
+                    ],
+                    None,
+                    context,
+                )?;
+            return create_buffer_from_uint8array(JsUint8Array::from_object(view)?, context);
+        }
+
         // Check if it's a Uint8Array or similar TypedArray
         if let Ok(typed_array) = JsUint8Array::from_object(obj.clone()) {
             let len = typed_array.length(context)?;
@@ -0,0 +1,159 @@
+//! Built-in `node:` modules - one table for `require()` and `import`
+//!
+//! Every built-in is listed once in [`BUILTINS`] with the expression that
+//! produces its `module.exports`. `require('x')`, `require('node:x')`,
+//! `process.getBuiltinModule('x')` and `import ... from 'node:x'` all go
+//! through that table:
+//! - CommonJS gets `module.exports` as is
+//! - ES modules get it as the `default` export, with each of its own
+//!   properties that is a valid identifier as a named export
+//!
+//! Modules without a registration of their own (timers, child_process,
+//! worker_threads, ...) are implemented in `builtins_module.js` and reached
+//! through `lib`. An unknown `node:` specifier fails with an "Unsupported
+//! builtin module" error instead of being looked up on disk.
+
+use boa_engine::{Context, JsError, JsNativeError, JsResult, JsValue, Source, js_string};
+
+/// Module name (without `node:`) and the expression for its exports
+const BUILTINS: &[(&str, &str)] = &[
+    ("assert", "globalThis.assert"),
+    ("assert/strict", "globalThis.assert.strict"),
+    ("async_hooks", "lib.async_hooks()"),
+    ("buffer", "lib.buffer()"),
+    ("child_process", "lib.child_process()"),
+    ("constants", "lib.constants()"),
+    ("crypto", "globalThis.crypto"),
+    ("dgram", "globalThis.dgram"),
+    ("dns", "globalThis.dns"),
+    ("dns/promises", "globalThis.dns.promises"),
+    ("events", "lib.events()"),
+    ("fs", "globalThis.fs"),
+    ("fs/promises", "globalThis.fs.promises"),
+    ("http", "globalThis.http"),
+    ("https", "globalThis.https"),
+    ("module", "lib.module()"),
+    ("net", "globalThis.net"),
+    ("os", "globalThis.os"),
+    ("path", "globalThis.path"),
+    ("path/posix", "globalThis.path.posix"),
+    ("path/win32", "globalThis.path.win32"),
+    ("perf_hooks", "lib.perf_hooks()"),
+    ("process", "globalThis.process"),
+    ("punycode", "lib.punycode()"),
+    ("querystring", "globalThis.querystring"),
+    ("readline", "globalThis.readline"),
+    ("readline/promises", "globalThis.readline.promises"),
+    ("stream", "globalThis.stream"),
+    ("stream/promises", "globalThis.stream.promises"),
+    ("stream/web", "lib.stream_web()"),
+    ("string_decoder", "globalThis.string_decoder"),
+    ("timers", "lib.timers()"),
+    ("timers/promises", "lib.timers_promises()"),
+    ("tls", "globalThis.tls"),
+    ("tty", "globalThis.tty"),
+    ("url", "globalThis.url"),
+    ("util", "globalThis.util"),
+    ("util/types", "globalThis.util.types"),
+    ("worker_threads", "lib.worker_threads()"),
+    ("zlib", "globalThis.zlib"),
+];
+
+/// Look up a module specifier
+///
+/// Returns the module name for a built-in, `None` for anything else, and an
+/// error for a `node:` specifier that names no built-in.
+pub(crate) fn resolve(specifier: &str) -> Result<Option<&'static str>, String> {
+    let name = specifier.strip_prefix("node:").unwrap_or(specifier);
+    match BUILTINS.iter().find(|(builtin, _)| *builtin == name) {
+        Some((builtin, _)) => Ok(Some(builtin)),
+        None if specifier.starts_with("node:") => {
+            Err(format!("Unsupported builtin module '{}'", specifier))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Source of the ES module re-exporting built-in `name`
+pub(crate) fn esm_source(name: &str, context: &mut Context) -> JsResult<String> {
+    let generate = context
+        .global_object()
+        .get(js_string!("__viper_builtin_esm"), context)?;
+    let generate = generate
+        .as_callable()
+        .ok_or_else(|| JsNativeError::typ().with_message("builtin table not registered"))?;
+    let source = generate.call(
+        &JsValue::undefined(),
+        &[JsValue::from(js_string!(name))],
+        context,
+    )?;
+    Ok(source.to_string(context)?.to_std_string_escaped())
+}
+
+/// Register the built-in table
+pub fn register_builtins(context: &mut Context) -> JsResult<()> {
+    let factories = BUILTINS
+        .iter()
+        .map(|(name, expr)| format!("  {:?}: () => {},\n", name, expr))
+        .collect::<String>();
+    let factories = format!("(lib) => ({{\n{}}})", factories);
+
+    let table = context.eval(Source::from_bytes(include_str!("builtins_module.js")))?;
+    let table = table.as_callable().ok_or_else(|| {
+        JsError::from(JsNativeError::typ().with_message("builtins_module.js must be a function"))
+    })?;
+    let factories = context.eval(Source::from_bytes(factories.as_bytes()))?;
+    table.call(&JsValue::undefined(), &[factories], context)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_named_and_default_exports() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            import os, { platform } from 'node:os';
+            import { isMainThread } from 'node:worker_threads';
+            import { builtinModules } from 'node:module';
+            globalThis.seen = [platform === os.platform, isMainThread, builtinModules.includes('child_process')];
+        "#;
+        runtime.run(code, "test.mjs").unwrap();
+        let result = runtime.eval("seen.join(',')", "check.js").unwrap();
+        assert_eq!(runtime.value_to_string(&result), "true,true,true");
+    }
+
+    #[test]
+    fn test_require_and_import_share_exports() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            import { setTimeout as sleep } from 'node:timers/promises';
+            globalThis.same = require('node:timers').promises.setTimeout === sleep
+                && require('timers/promises') === process.getBuiltinModule('node:timers/promises');
+            await sleep(1);
+        "#;
+        runtime.run(code, "test.mjs").unwrap();
+        let result = runtime.eval("same", "check.js").unwrap();
+        assert_eq!(result.as_boolean(), Some(true));
+    }
+
+    #[test]
+    fn test_unknown_builtin_require() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = "try { require('node:nope'); } catch (e) { e.code }";
+        let result = runtime.eval(code, "test.js").unwrap();
+        assert_eq!(
+            runtime.value_to_string(&result),
+            "ERR_UNKNOWN_BUILTIN_MODULE"
+        );
+    }
+
+    #[test]
+    fn test_unknown_builtin_import() {
+        let mut runtime = Runtime::new().unwrap();
+        let result = runtime.run("import 'node:nope';", "unknown.mjs");
+        assert!(matches!(result, Err(e) if e.to_string().contains("Unsupported builtin")));
+    }
+}
@@ -0,0 +1,712 @@
+/**
+ * Node.js built-in module table
+ *
+ * Called with a function mapping `lib` (the modules defined here) to the
+ * factories from `builtins.rs`. Both `require()` and the ESM loader go
+ * through `__viper_builtin`, so every built-in has the same CommonJS and ES
+ * module face: `module.exports` is the ES default export and its properties
+ * are the named exports.
+ */
+(function (makeFactories) {
+  "use strict";
+
+  const kUnsupported = "ERR_UNKNOWN_BUILTIN_MODULE";
+
+  // ==========================================================================
+  // Modules without a native registration of their own
+  // ==========================================================================
+
+  function once(factory) {
+    let value;
+    let done = false;
+    return () => {
+      if (!done) {
+        value = factory();
+        done = true;
+      }
+      return value;
+    };
+  }
+
+  function notSupported(name) {
+    return function () {
+      const err = new Error(`${name} is not supported by Viper`);
+      err.code = "ERR_NOT_SUPPORTED";
+      throw err;
+    };
+  }
+
+  const lib = {};
+
+  // Buffer that can also be called without `new`, as old packages do
+  lib.buffer = once(() => {
+    const _Buffer = globalThis.Buffer;
+    function Buffer(arg, encodingOrOffset, length) {
+      return _Buffer.from(arg, encodingOrOffset, length);
+    }
+    for (const key of Object.getOwnPropertyNames(_Buffer)) {
+      if (key !== "name" && key !== "length" && key !== "prototype") {
+        Buffer[key] = _Buffer[key];
+      }
+    }
+    Buffer.prototype = Object.create(Uint8Array.prototype);
+    Buffer.prototype.constructor = Buffer;
+    Buffer.allocUnsafe = _Buffer.allocUnsafe || _Buffer.alloc;
+    Buffer.allocUnsafeSlow = _Buffer.allocUnsafeSlow || _Buffer.alloc;
+    Buffer.poolSize = 8192;
+    Object.defineProperty(Buffer, Symbol.hasInstance, {
+      value: (value) => _Buffer.isBuffer(value),
+    });
+
+    const b = globalThis.buffer || {};
+    return {
+      Buffer,
+      SlowBuffer: Buffer,
+      constants: b.constants || {},
+      kMaxLength: b.kMaxLength ?? 2147483647,
+      kStringMaxLength: b.constants?.MAX_STRING_LENGTH ?? 536870888,
+      INSPECT_MAX_BYTES: b.INSPECT_MAX_BYTES ?? 50,
+      Blob: globalThis.Blob,
+      File: globalThis.File,
+      atob: globalThis.atob,
+      btoa: globalThis.btoa,
+      transcode(source, fromEnc, toEnc) {
+        return Buffer.from(source.toString(fromEnc), toEnc);
+      },
+      isUtf8(input) {
+        try {
+          const str = _Buffer.from(input).toString("utf8");
+          return _Buffer.from(str, "utf8").equals(_Buffer.from(input));
+        } catch {
+          return false;
+        }
+      },
+      isAscii(input) {
+        for (let i = 0; i < input.length; i++) {
+          if (input[i] > 127) return false;
+        }
+        return true;
+      },
+    };
+  });
+
+  // `require('events')` is EventEmitter itself, carrying the helpers
+  lib.events = once(() => {
+    const events = globalThis.events;
+    const EventEmitter = events.EventEmitter;
+    for (const key of Object.keys(events)) {
+      if (key !== "default" && !(key in EventEmitter)) {
+        EventEmitter[key] = events[key];
+      }
+    }
+    EventEmitter.EventEmitter = EventEmitter;
+    return EventEmitter;
+  });
+
+  // The error every signal-aware API rejects with once aborted
+  function abortError(signal) {
+    const err = new Error("The operation was aborted", { cause: signal?.reason });
+    err.name = "AbortError";
+    err.code = "ABORT_ERR";
+    return err;
+  }
+
+  lib.timers_promises = once(() => {
+    function schedule(start, clear, args, value, options = {}) {
+      const { signal, ref = true } = options;
+      if (signal?.aborted) return Promise.reject(abortError(signal));
+      return new Promise((resolve, reject) => {
+        const onAbort = () => {
+          clear(handle);
+          reject(abortError(signal));
+        };
+        const handle = start(() => {
+          signal?.removeEventListener?.("abort", onAbort);
+          resolve(value);
+        }, ...args);
+        if (!ref) handle.unref?.();
+        signal?.addEventListener?.("abort", onAbort, { once: true });
+      });
+    }
+
+    const setTimeoutPromise = (delay, value, options) =>
+      schedule(globalThis.setTimeout, globalThis.clearTimeout, [delay], value, options);
+    const setImmediatePromise = (value, options) =>
+      schedule(globalThis.setImmediate, globalThis.clearImmediate, [], value, options);
+
+    async function* setIntervalPromise(delay, value, options = {}) {
+      const { signal, ref = true } = options;
+      if (signal?.aborted) throw abortError(signal);
+      let pending = 0;
+      let wake = null;
+      const handle = globalThis.setInterval(() => {
+        pending++;
+        if (wake) wake();
+      }, delay);
+      if (!ref) handle.unref?.();
+      const onAbort = () => wake?.();
+      signal?.addEventListener?.("abort", onAbort, { once: true });
+      try {
+        while (true) {
+          if (pending === 0) {
+            await new Promise((resolve) => (wake = resolve));
+            wake = null;
+          }
+          if (signal?.aborted) throw abortError(signal);
+          pending--;
+          yield value;
+        }
+      } finally {
+        globalThis.clearInterval(handle);
+        signal?.removeEventListener?.("abort", onAbort);
+      }
+    }
+
+    return {
+      setTimeout: setTimeoutPromise,
+      setImmediate: setImmediatePromise,
+      setInterval: setIntervalPromise,
+      scheduler: {
+        wait: (delay, options) => setTimeoutPromise(delay, undefined, options),
+        yield: () => setImmediatePromise(),
+      },
+    };
+  });
+
+  lib.timers = once(() => ({
+    setTimeout: globalThis.setTimeout,
+    clearTimeout: globalThis.clearTimeout,
+    setInterval: globalThis.setInterval,
+    clearInterval: globalThis.clearInterval,
+    setImmediate: globalThis.setImmediate,
+    clearImmediate: globalThis.clearImmediate,
+    promises: lib.timers_promises(),
+  }));
+
+  // child_process on top of the spawn natives; output is delivered when the
+  // child exits, not streamed
+  lib.child_process = once(() => {
+    const EventEmitter = lib.events();
+    const { Buffer } = lib.buffer();
+    const { Readable, Writable } = globalThis.stream;
+
+    function normalizeArgs(args, options) {
+      if (!Array.isArray(args)) {
+        options = args;
+        args = [];
+      }
+      return [args, options || {}];
+    }
+
+    function decode(bytes, encoding) {
+      const buf = Buffer.from(bytes);
+      return encoding && encoding !== "buffer" ? buf.toString(encoding) : buf;
+    }
+
+    function exitError(command, result, stderr) {
+      const err = new Error(`Command failed: ${command}\n${stderr}`);
+      err.code = result.exitCode;
+      err.status = result.exitCode;
+      err.cmd = command;
+      return err;
+    }
+
+    class ChildProcess extends EventEmitter {
+      constructor() {
+        super();
+        this.pid = undefined;
+        this.exitCode = null;
+        this.signalCode = null;
+        this.killed = false;
+        this.stdin = new Writable({ write: (chunk, encoding, callback) => callback() });
+        this.stdout = new Readable({ read() {} });
+        this.stderr = new Readable({ read() {} });
+        this.stdio = [this.stdin, this.stdout, this.stderr];
+      }
+
+      kill(signal = "SIGTERM") {
+        const signals = globalThis.os?.constants?.signals ?? {};
+        const number = typeof signal === "number" ? signal : signals[signal] ?? 15;
+        if (this._job === undefined || !__viper_spawn_kill(this._job, number)) return false;
+        this.killed = true;
+        this._killSignal = typeof signal === "number"
+          ? Object.keys(signals).find((name) => signals[name] === signal) ?? null
+          : signal;
+        return true;
+      }
+
+      ref() {
+        return this;
+      }
+
+      unref() {
+        return this;
+      }
+    }
+
+    function run(child, command, args, options) {
+      const signal = options.signal;
+      if (signal?.aborted) {
+        queueMicrotask(() => child.emit("error", abortError(signal)));
+        return;
+      }
+      const onAbort = () => {
+        child.kill(options.killSignal ?? "SIGTERM");
+        child.emit("error", abortError(signal));
+      };
+      let job;
+      const source = __viper_loop_source(() => {
+        __viper_loop_close(source);
+        signal?.removeEventListener("abort", onAbort);
+        let result;
+        try {
+          result = __viper_spawn_result(job);
+        } catch (err) {
+          child.emit("error", err);
+          child.emit("close", -2, null);
+          return;
+        }
+        // A child that died from our signal has no exit code
+        const killedBy = child.killed && result.exitCode === -1 ? child._killSignal : null;
+        child.exitCode = killedBy ? null : result.exitCode;
+        child.signalCode = killedBy;
+        if (result.stdout.length) child.stdout.push(Buffer.from(result.stdout));
+        if (result.stderr.length) child.stderr.push(Buffer.from(result.stderr));
+        child.stdout.push(null);
+        child.stderr.push(null);
+        child.emit("exit", child.exitCode, killedBy);
+        child.emit("close", child.exitCode, killedBy);
+      });
+      try {
+        job = __viper_spawn_start(command, args, options, source);
+        child._job = job;
+        signal?.addEventListener("abort", onAbort, { once: true });
+        queueMicrotask(() => child.emit("spawn"));
+      } catch (err) {
+        __viper_loop_close(source);
+        queueMicrotask(() => child.emit("error", err));
+      }
+    }
+
+    function spawn(command, args, options) {
+      [args, options] = normalizeArgs(args, options);
+      const child = new ChildProcess();
+      child.spawnfile = command;
+      child.spawnargs = [command, ...args];
+      run(child, command, args, options);
+      return child;
+    }
+
+    function collect(child, command, options, callback) {
+      const stdout = [];
+      const stderr = [];
+      child.stdout.on("data", (chunk) => stdout.push(chunk));
+      child.stderr.on("data", (chunk) => stderr.push(chunk));
+      let failed = false;
+      child.on("error", (err) => {
+        failed = true;
+        callback?.(err, "", "");
+      });
+      child.on("close", (code) => {
+        if (!callback || failed) return;
+        const out = decode(Buffer.concat(stdout), options.encoding ?? "utf8");
+        const err = decode(Buffer.concat(stderr), options.encoding ?? "utf8");
+        callback(code === 0 ? null : exitError(command, { exitCode: code ?? -1 }, err), out, err);
+      });
+      return child;
+    }
+
+    function execFile(file, args, options, callback) {
+      if (typeof args === "function") [args, options, callback] = [[], {}, args];
+      if (typeof options === "function") [options, callback] = [{}, options];
+      [args, options] = normalizeArgs(args, options);
+      return collect(spawn(file, args, options), file, options, callback);
+    }
+
+    function exec(command, options, callback) {
+      if (typeof options === "function") [options, callback] = [{}, options];
+      options = options || {};
+      return collect(spawn(command, [], { ...options, shell: true }), command, options, callback);
+    }
+
+    function spawnSync(command, args, options) {
+      [args, options] = normalizeArgs(args, options);
+      try {
+        const result = __viper_spawn(command, args, options);
+        const stdout = decode(result.stdout, options.encoding);
+        const stderr = decode(result.stderr, options.encoding);
+        return {
+          pid: 0,
+          status: result.exitCode,
+          signal: null,
+          stdout,
+          stderr,
+          output: [null, stdout, stderr],
+        };
+      } catch (error) {
+        return { pid: 0, status: null, signal: null, output: null, stdout: null, stderr: null, error };
+      }
+    }
+
+    function checkSync(command, result) {
+      if (result.error) throw result.error;
+      if (result.status !== 0) {
+        const err = exitError(command, { exitCode: result.status }, String(result.stderr));
+        err.stdout = result.stdout;
+        err.stderr = result.stderr;
+        throw err;
+      }
+      return result.stdout;
+    }
+
+    function execFileSync(file, args, options) {
+      [args, options] = normalizeArgs(args, options);
+      return checkSync(file, spawnSync(file, args, options));
+    }
+
+    function execSync(command, options = {}) {
+      return checkSync(command, spawnSync(command, [], { ...options, shell: true }));
+    }
+
+    return {
+      ChildProcess,
+      spawn,
+      spawnSync,
+      exec,
+      execSync,
+      execFile,
+      execFileSync,
+      fork: notSupported("child_process.fork()"),
+    };
+  });
+
+  lib.perf_hooks = once(() => {
+    let performance = globalThis.performance;
+    if (!performance) {
+      const origin = process.hrtime.bigint();
+      const entries = [];
+      const now = () => Number(process.hrtime.bigint() - origin) / 1e6;
+      const entry = (name, entryType, startTime, duration, detail) => ({
+        name,
+        entryType,
+        startTime,
+        duration,
+        detail: detail ?? null,
+        toJSON() {
+          return { name, entryType, startTime, duration, detail: detail ?? null };
+        },
+      });
+      const find = (name) => entries.findLast((e) => e.entryType === "mark" && e.name === name);
+      const clear = (type, name) => {
+        for (let i = entries.length - 1; i >= 0; i--) {
+          if (entries[i].entryType === type && (name === undefined || entries[i].name === name)) {
+            entries.splice(i, 1);
+          }
+        }
+      };
+      performance = globalThis.performance = {
+        timeOrigin: Date.now(),
+        now,
+        mark(name, options = {}) {
+          const mark = entry(name, "mark", options.startTime ?? now(), 0, options.detail);
+          entries.push(mark);
+          return mark;
+        },
+        measure(name, start, end) {
+          const options = typeof start === "object" && start !== null ? start : { start, end };
+          const at = (value, fallback) =>
+            typeof value === "string" ? find(value)?.startTime ?? 0 : value ?? fallback;
+          const startTime = at(options.start, 0);
+          const endTime = at(options.end, now());
+          const measure = entry(name, "measure", startTime, endTime - startTime, options.detail);
+          entries.push(measure);
+          return measure;
+        },
+        getEntries: () => entries.slice(),
+        getEntriesByName: (name, type) =>
+          entries.filter((e) => e.name === name && (type === undefined || e.entryType === type)),
+        getEntriesByType: (type) => entries.filter((e) => e.entryType === type),
+        clearMarks: (name) => clear("mark", name),
+        clearMeasures: (name) => clear("measure", name),
+        toJSON() {
+          return { timeOrigin: this.timeOrigin };
+        },
+      };
+    }
+
+    class PerformanceObserver {
+      static supportedEntryTypes = ["mark", "measure"];
+      constructor(callback) {
+        this.callback = callback;
+      }
+      observe() {}
+      disconnect() {}
+      takeRecords() {
+        return [];
+      }
+    }
+
+    return {
+      performance,
+      PerformanceObserver,
+      constants: {},
+      monitorEventLoopDelay: notSupported("perf_hooks.monitorEventLoopDelay()"),
+      createHistogram: notSupported("perf_hooks.createHistogram()"),
+    };
+  });
+
+  // Async context only follows synchronous calls: Boa offers no hook to carry
+  // it across promise jobs
+  lib.async_hooks = once(() => {
+    class AsyncLocalStorage {
+      #store = undefined;
+      #enabled = false;
+
+      getStore() {
+        return this.#enabled ? this.#store : undefined;
+      }
+
+      run(store, callback, ...args) {
+        const [previous, wasEnabled] = [this.#store, this.#enabled];
+        this.#store = store;
+        this.#enabled = true;
+        try {
+          return callback(...args);
+        } finally {
+          this.#store = previous;
+          this.#enabled = wasEnabled;
+        }
+      }
+
+      exit(callback, ...args) {
+        const wasEnabled = this.#enabled;
+        this.#enabled = false;
+        try {
+          return callback(...args);
+        } finally {
+          this.#enabled = wasEnabled;
+        }
+      }
+
+      enterWith(store) {
+        this.#store = store;
+        this.#enabled = true;
+      }
+
+      disable() {
+        this.#store = undefined;
+        this.#enabled = false;
+      }
+
+      static bind(fn) {
+        return fn;
+      }
+
+      static snapshot() {
+        return (fn, ...args) => fn(...args);
+      }
+    }
+
+    let nextId = 1;
+    class AsyncResource {
+      constructor(type) {
+        this.type = type;
+        this.id = ++nextId;
+      }
+      runInAsyncScope(fn, thisArg, ...args) {
+        return fn.apply(thisArg, args);
+      }
+      bind(fn) {
+        return fn.bind(this);
+      }
+      emitDestroy() {
+        return this;
+      }
+      asyncId() {
+        return this.id;
+      }
+      triggerAsyncId() {
+        return 1;
+      }
+      static bind(fn, type) {
+        return new AsyncResource(type || "bound-anonymous-fn").bind(fn);
+      }
+    }
+
+    return {
+      AsyncLocalStorage,
+      AsyncResource,
+      createHook: () => ({
+        enable() {
+          return this;
+        },
+        disable() {
+          return this;
+        },
+      }),
+      executionAsyncId: () => 1,
+      triggerAsyncId: () => 0,
+      executionAsyncResource: () => ({}),
+    };
+  });
+
+  lib.worker_threads = once(() => {
+    const EventEmitter = lib.events();
+    const shared = globalThis.__worker_threads || {};
+    const isMainThread = globalThis.Viper?.isMainThread !== false;
+
+    let parentPort = null;
+    if (!isMainThread) {
+      parentPort = new EventEmitter();
+      parentPort.postMessage = (data, transfer) => globalThis.postMessage(data, transfer);
+      parentPort.close = () => globalThis.close();
+      parentPort.ref = parentPort.unref = () => parentPort;
+      parentPort.start = () => {};
+      globalThis.onmessage = (event) => parentPort.emit("message", event.data);
+    }
+
+    return {
+      isMainThread,
+      parentPort,
+      workerData: shared.workerData ?? null,
+      threadId: isMainThread ? 0 : globalThis.__viper_worker_id,
+      resourceLimits: {},
+      Worker: globalThis.Worker,
+      MessageChannel: globalThis.MessageChannel,
+      MessagePort: globalThis.MessagePort,
+      BroadcastChannel: globalThis.BroadcastChannel,
+      SHARE_ENV: Symbol.for("nodejs.worker_threads.SHARE_ENV"),
+      setEnvironmentData: (key, value) => shared.setEnvironmentData?.(key, value),
+      getEnvironmentData: (key) => shared.getEnvironmentData?.(key),
+      markAsUntransferable() {},
+      isMarkedAsUntransferable: () => false,
+      receiveMessageOnPort: () => undefined,
+    };
+  });
+
+  lib.module = once(() => {
+    const builtinModules = names.slice();
+    function createRequire(filename) {
+      const path = String(filename instanceof URL ? filename.pathname : filename).replace(
+        /^file:\/\//,
+        "",
+      );
+      const dir = path.endsWith("/") ? path : globalThis.path.dirname(path);
+      return globalThis.__viper_create_require(dir);
+    }
+    function Module(id = "", parent) {
+      this.id = id;
+      this.parent = parent;
+      this.exports = {};
+      this.filename = null;
+      this.loaded = false;
+      this.children = [];
+      this.paths = [];
+    }
+    Module.builtinModules = builtinModules;
+    Module.createRequire = createRequire;
+    Module.isBuiltin = isBuiltin;
+    Module.Module = Module;
+    Module.register = () => {};
+    Module.syncBuiltinESMExports = () => {};
+    Module.findSourceMap = () => undefined;
+    return Module;
+  });
+
+  lib.constants = once(() => ({
+    ...(globalThis.os?.constants?.signals ?? {}),
+    ...(globalThis.os?.constants?.errno ?? {}),
+    ...(globalThis.fs?.constants ?? {}),
+  }));
+
+  lib.punycode = once(() => ({
+    encode: (str) => str,
+    decode: (str) => str,
+    toASCII: (str) => globalThis.url?.domainToASCII?.(str) ?? str,
+    toUnicode: (str) => globalThis.url?.domainToUnicode?.(str) ?? str,
+  }));
+
+  lib.stream_web = once(() => ({
+    ReadableStream,
+    ReadableStreamDefaultReader,
+    ReadableStreamBYOBReader,
+    ReadableStreamBYOBRequest,
+    ReadableStreamDefaultController,
+    ReadableByteStreamController,
+    WritableStream,
+    WritableStreamDefaultWriter,
+    WritableStreamDefaultController,
+    TransformStream,
+    TransformStreamDefaultController,
+    ByteLengthQueuingStrategy,
+    CountQueuingStrategy,
+    TextEncoderStream,
+    TextDecoderStream,
+  }));
+
+  // ==========================================================================
+  // The table
+  // ==========================================================================
+
+  const factories = makeFactories(lib);
+  const names = Object.keys(factories).sort();
+  const cache = new Map();
+
+  function builtinName(specifier) {
+    const name = specifier.startsWith("node:") ? specifier.slice(5) : specifier;
+    return Object.hasOwn(factories, name) ? name : null;
+  }
+
+  function isBuiltin(specifier) {
+    return typeof specifier === "string" && builtinName(specifier) !== null;
+  }
+
+  // module.exports of a built-in; undefined for bare names that aren't one,
+  // so require() can look in node_modules
+  function builtin(specifier) {
+    const name = builtinName(specifier);
+    if (name === null) {
+      if (specifier.startsWith("node:")) {
+        const err = new Error(`Unsupported builtin module '${specifier}'`);
+        err.code = kUnsupported;
+        throw err;
+      }
+      return undefined;
+    }
+    if (!cache.has(name)) cache.set(name, factories[name]());
+    return cache.get(name);
+  }
+
+  const reserved = new Set(
+    (
+      "await break case catch class const continue debugger default delete do else enum " +
+      "export extends false finally for function if implements import in instanceof " +
+      "interface let new null package private protected public return static super switch " +
+      "this throw true try typeof var void while with yield arguments eval"
+    ).split(" "),
+  );
+  const functionOwn = new Set(["length", "name", "prototype", "caller", "arguments"]);
+
+  // ES module source whose default export is module.exports and whose named
+  // exports are its properties
+  function esmSource(name) {
+    const exports = builtin(name);
+    let keys = [];
+    if (exports !== null && (typeof exports === "object" || typeof exports === "function")) {
+      keys = Object.getOwnPropertyNames(exports).filter(
+        (key) =>
+          /^[A-Za-z_$][\w$]*$/.test(key) &&
+          !reserved.has(key) &&
+          !(typeof exports === "function" && functionOwn.has(key)),
+      );
+    }
+    let code = `const m = __viper_builtin(${JSON.stringify(name)});\nexport default m;\n`;
+    if (keys.length) code += `export const { ${keys.join(", ")} } = m;\n`;
+    return code;
+  }
+
+  globalThis.__viper_builtin = builtin;
+  globalThis.__viper_builtin_esm = esmSource;
+  globalThis.__viper_is_builtin = isBuiltin;
+  globalThis.__viper_builtin_names = names;
+});
@@ -0,0 +1,533 @@
+//! CommonJS modules: format detection, named exports and `require()`
+//!
+//! A file's format is decided the way Node decides it:
+//! - `.mjs`/`.mts` are ES modules and `.cjs`/`.cts` are CommonJS
+//! - otherwise the nearest package.json `"type"` decides
+//! - without one the file is parsed: `import`/`export` make it an ES module,
+//!   free references to `require`, `module` or `exports` make it CommonJS
+//!
+//! Importing a CommonJS module from ESM evaluates it through the same
+//! `require()` CommonJS code uses, lazily and with `require` calls resolved
+//! at runtime, so computed and conditional requires work. `module.exports`
+//! is the default export. Named exports are detected statically, like
+//! `cjs-module-lexer` does, from:
+//! - `exports.x = ...`, `module.exports.x = ...` and `exports['x'] = ...`
+//! - `Object.defineProperty(exports, 'x', ...)`
+//! - `module.exports = { x, y: ..., ...require('./z') }`
+//! - re-exports: `module.exports = require('./z')`,
+//!   `__exportStar(require('./z'), exports)` and `__export(require('./z'))`,
+//!   followed into other CommonJS files
+//!
+//! Exports assigned any other way are only reachable through the default
+//! export.
+
+use boa_engine::{Context, JsError, JsNativeError, JsResult, JsValue, NativeFunction, js_string};
+use boa_gc::{Finalize, Trace};
+use oxc_allocator::Allocator;
+use oxc_ast::{
+    AstKind,
+    ast::{Argument, Expression, ObjectPropertyKind},
+};
+use oxc_parser::Parser;
+use oxc_semantic::SemanticBuilder;
+use oxc_span::SourceType;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use super::{host, limits, permissions};
+use crate::resolver::{ModuleResolver, PackageType};
+use crate::transpiler::Transpiler;
+
+/// Error code of `require()` on an ES module
+const ERR_REQUIRE_ESM: &str = "ERR_REQUIRE_ESM";
+
+/// How a JavaScript file is evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleFormat {
+    Esm,
+    CommonJs,
+}
+
+/// Decide the format of the file at `path` with (transpiled) source `code`
+pub fn module_format(resolver: &ModuleResolver, path: &Path, code: &str) -> ModuleFormat {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("mjs" | "mts") => return ModuleFormat::Esm,
+        Some("cjs" | "cts") => return ModuleFormat::CommonJs,
+        _ => {}
+    }
+    match resolver.package_type(path) {
+        Some(PackageType::Module) => ModuleFormat::Esm,
+        Some(PackageType::CommonJs) => ModuleFormat::CommonJs,
+        None => detect_format(code),
+    }
+}
+
+/// Syntax detection for files no extension or package.json decides
+fn detect_format(code: &str) -> ModuleFormat {
+    let allocator = Allocator::default();
+    let source_type = SourceType::mjs().with_jsx(true);
+    let parsed = Parser::new(&allocator, code, source_type).parse();
+    if !parsed.errors.is_empty() {
+        return ModuleFormat::CommonJs;
+    }
+    // oxc reports strict mode violations from the semantic pass
+    let checked = SemanticBuilder::new()
+        .with_check_syntax_error(true)
+        .build(&parsed.program);
+    if !checked.errors.is_empty() {
+        // Sloppy-mode syntax (`with`, legacy octals) only parses as a script
+        return ModuleFormat::CommonJs;
+    }
+    if parsed.module_record.has_module_syntax {
+        return ModuleFormat::Esm;
+    }
+
+    let semantic = checked.semantic;
+    let unresolved = semantic.scoping().root_unresolved_references();
+    if ["require", "module", "exports"]
+        .iter()
+        .any(|name| unresolved.contains_key(*name))
+    {
+        ModuleFormat::CommonJs
+    } else {
+        ModuleFormat::Esm
+    }
+}
+
+/// Named exports of the CommonJS module at `path`, including re-exports
+pub fn export_names(resolver: &ModuleResolver, path: &Path, code: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    collect_exports(resolver, path, code, &mut names, &mut visited);
+    names.remove("default");
+    names
+}
+
+fn collect_exports(
+    resolver: &ModuleResolver,
+    path: &Path,
+    code: &str,
+    names: &mut BTreeSet<String>,
+    visited: &mut BTreeSet<PathBuf>,
+) {
+    if !visited.insert(path.to_path_buf()) {
+        return;
+    }
+
+    let lexed = lex(code);
+    names.extend(lexed.exports);
+    for specifier in lexed.reexports {
+        let Ok(target) = resolver.resolve_cjs(&specifier, path) else {
+            continue;
+        };
+        let Ok(code) = read_source(&target) else {
+            continue;
+        };
+        if module_format(resolver, &target, &code) == ModuleFormat::CommonJs {
+            collect_exports(resolver, &target, &code, names, visited);
+        }
+    }
+}
+
+/// What the lexer found in one file
+#[derive(Default)]
+struct Lexed {
+    exports: BTreeSet<String>,
+    /// Specifiers whose exports are re-exported
+    reexports: Vec<String>,
+}
+
+fn lex(code: &str) -> Lexed {
+    let mut lexed = Lexed::default();
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, code, SourceType::cjs()).parse();
+    if !parsed.errors.is_empty() {
+        // `require()` reports the syntax error when the module runs
+        return lexed;
+    }
+    let semantic = SemanticBuilder::new().build(&parsed.program).semantic;
+
+    for node in semantic.nodes().iter() {
+        match node.kind() {
+            AstKind::AssignmentExpression(assign) => {
+                let Some(member) = assign.left.as_member_expression() else {
+                    continue;
+                };
+
+                if is_exports(member.object()) {
+                    // exports.x = ... / module.exports.x = ...
+                    if let Some(name) = member.static_property_name() {
+                        lexed.exports.insert(name.to_string());
+                    }
+                } else if is_module(member.object())
+                    && member.static_property_name() == Some("exports")
+                {
+                    // module.exports = ...
+                    lex_module_exports(&assign.right, &mut lexed);
+                }
+            }
+            AstKind::CallExpression(call) => {
+                let callee = call.callee.without_parentheses();
+                let first = call.arguments.first().and_then(Argument::as_expression);
+
+                if is_define_property(callee) {
+                    // Object.defineProperty(exports, 'x', ...)
+                    let name = call.arguments.get(1).and_then(Argument::as_expression);
+                    if let (Some(target), Some(name)) = (first, name.and_then(string_value))
+                        && is_exports(target)
+                    {
+                        lexed.exports.insert(name);
+                    }
+                } else if is_export_star(callee) {
+                    // __exportStar(require('x'), exports) / __export(require('x'))
+                    if let Some(specifier) = first.and_then(require_specifier) {
+                        lexed.reexports.push(specifier);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    lexed
+}
+
+/// The right-hand side of `module.exports = ...`
+fn lex_module_exports(value: &Expression, lexed: &mut Lexed) {
+    let value = value.without_parentheses();
+    if let Some(specifier) = require_specifier(value) {
+        lexed.reexports.push(specifier);
+        return;
+    }
+    let Expression::ObjectExpression(object) = value else {
+        return;
+    };
+    for property in &object.properties {
+        match property {
+            ObjectPropertyKind::ObjectProperty(property) => {
+                if let Some(name) = property.key.static_name() {
+                    lexed.exports.insert(name.to_string());
+                }
+            }
+            ObjectPropertyKind::SpreadProperty(spread) => {
+                if let Some(specifier) = require_specifier(&spread.argument) {
+                    lexed.reexports.push(specifier);
+                }
+            }
+        }
+    }
+}
+
+/// `exports` or `module.exports`
+fn is_exports(expr: &Expression) -> bool {
+    match expr.without_parentheses() {
+        Expression::Identifier(ident) => ident.name == "exports",
+        expr => expr.as_member_expression().is_some_and(|member| {
+            is_module(member.object()) && member.static_property_name() == Some("exports")
+        }),
+    }
+}
+
+fn is_module(expr: &Expression) -> bool {
+    matches!(expr.without_parentheses(), Expression::Identifier(ident) if ident.name == "module")
+}
+
+/// `Object.defineProperty`
+fn is_define_property(callee: &Expression) -> bool {
+    callee.as_member_expression().is_some_and(|member| {
+        matches!(member.object(), Expression::Identifier(ident) if ident.name == "Object")
+            && member.static_property_name() == Some("defineProperty")
+    })
+}
+
+/// The helpers TypeScript, tslib and Babel emit for `export * from`
+fn is_export_star(callee: &Expression) -> bool {
+    const HELPERS: [&str; 3] = ["__exportStar", "__export", "_exportStar"];
+    match callee {
+        Expression::Identifier(ident) => HELPERS.contains(&ident.name.as_str()),
+        // tslib.__exportStar(...)
+        expr => expr
+            .as_member_expression()
+            .and_then(|member| member.static_property_name())
+            .is_some_and(|name| HELPERS.contains(&name)),
+    }
+}
+
+/// The specifier of `require('x')`
+fn require_specifier(expr: &Expression) -> Option<String> {
+    let Expression::CallExpression(call) = expr.without_parentheses() else {
+        return None;
+    };
+    if !matches!(&call.callee, Expression::Identifier(ident) if ident.name == "require") {
+        return None;
+    }
+    string_value(call.arguments.first()?.as_expression()?)
+}
+
+/// A string literal or a template literal without substitutions
+fn string_value(expr: &Expression) -> Option<String> {
+    match expr.without_parentheses() {
+        Expression::StringLiteral(literal) => Some(literal.value.to_string()),
+        Expression::TemplateLiteral(template) if template.expressions.is_empty() => template
+            .quasis
+            .first()
+            .and_then(|quasi| quasi.value.cooked.as_ref())
+            .map(|cooked| cooked.to_string()),
+        _ => None,
+    }
+}
+
+/// ES module source importing the CommonJS module at `path`
+pub fn esm_wrapper(path: &Path, names: &BTreeSet<String>) -> String {
+    let key = serde_json::to_string(&path.to_string_lossy()).unwrap_or_default();
+    let mut code = format!(
+        "const m = globalThis.__viper_require_cjs({});\nexport default m;\n",
+        key
+    );
+    for (i, name) in names.iter().enumerate() {
+        let exported = if host::is_identifier(name) {
+            name.clone()
+        } else {
+            serde_json::to_string(name).unwrap_or_default()
+        };
+        let literal = serde_json::to_string(name).unwrap_or_default();
+        code.push_str(&format!(
+            "const export{i} = m?.[{literal}];\nexport {{ export{i} as {exported} }};\n"
+        ));
+    }
+    code
+}
+
+/// Read a module for `require()`, transpiling TypeScript
+fn read_source(path: &Path) -> Result<String, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read module '{}': {}", path.display(), e))?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("ts" | "tsx" | "cts" | "mts") => {
+            let filename = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("module.ts");
+            Transpiler::new()
+                .transpile(&source, filename)
+                .map_err(|e| e.to_string())
+        }
+        _ => Ok(source),
+    }
+}
+
+/// The resolver `require()` resolves with
+#[derive(Trace, Finalize)]
+struct Loader {
+    #[unsafe_ignore_trace]
+    resolver: Rc<ModuleResolver>,
+}
+
+fn string_arg(args: &[JsValue], index: usize, context: &mut Context) -> JsResult<String> {
+    Ok(args
+        .get(index)
+        .cloned()
+        .unwrap_or_default()
+        .to_string(context)?
+        .to_std_string_escaped())
+}
+
+/// Register the natives the `require()` shim is built on
+pub fn register_cjs(context: &mut Context, resolver: Rc<ModuleResolver>) -> JsResult<()> {
+    // __viper_cjs_resolve(specifier, fromDir) -> path or null
+    let resolve_fn = NativeFunction::from_copy_closure_with_captures(
+        |_this, args, loader, context| {
+            let specifier = string_arg(args, 0, context)?;
+            let dir = string_arg(args, 1, context)?;
+            Ok(
+                match loader.resolver.resolve_cjs_in(&specifier, Path::new(&dir)) {
+                    Ok(path) => JsValue::from(js_string!(path.to_string_lossy().to_string())),
+                    Err(_) => JsValue::null(),
+                },
+            )
+        },
+        Loader {
+            resolver: Rc::clone(&resolver),
+        },
+    );
+    context.register_global_callable(js_string!("__viper_cjs_resolve"), 2, resolve_fn)?;
+
+    // __viper_cjs_source(path) -> source to wrap, JSON as is
+    let source_fn = NativeFunction::from_copy_closure_with_captures(
+        |_this, args, loader, context| {
+            let path = string_arg(args, 0, context)?;
+            permissions::check_read(context, &path)?;
+            let path = PathBuf::from(path);
+            let code = read_source(&path)
+                .map_err(|message| JsError::from(JsNativeError::error().with_message(message)))?;
+            if path.extension().is_some_and(|e| e == "json") {
+                return Ok(JsValue::from(js_string!(code)));
+            }
+            if module_format(&loader.resolver, &path, &code) == ModuleFormat::Esm {
+                return Err(require_esm_error(&path, context));
+            }
+            let code = limits::with_safepoints(context, code, false);
+            Ok(JsValue::from(js_string!(code)))
+        },
+        Loader { resolver },
+    );
+    context.register_global_callable(js_string!("__viper_cjs_source"), 1, source_fn)?;
+    Ok(())
+}
+
+/// The error `require()` of an ES module throws
+fn require_esm_error(path: &Path, context: &mut Context) -> JsError {
+    let error = JsError::from(JsNativeError::error().with_message(format!(
+        "require() of ES Module {} is not supported; use import() instead",
+        path.display()
+    )));
+    let value = error.to_opaque(context);
+    if let Some(object) = value.as_object() {
+        let _ = object.set(
+            js_string!("code"),
+            js_string!(ERR_REQUIRE_ESM),
+            false,
+            context,
+        );
+    }
+    JsError::from_opaque(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format("import fs from 'fs';"), ModuleFormat::Esm);
+        assert_eq!(
+            detect_format("const fs = require('fs');"),
+            ModuleFormat::CommonJs
+        );
+        assert_eq!(detect_format("module.exports = 1;"), ModuleFormat::CommonJs);
+        assert_eq!(
+            detect_format("with (Math) { max(1, 2); }"),
+            ModuleFormat::CommonJs
+        );
+        // A local `require` is not the CommonJS one
+        assert_eq!(
+            detect_format("const require = () => 1; require();"),
+            ModuleFormat::Esm
+        );
+        assert_eq!(detect_format("console.log(1);"), ModuleFormat::Esm);
+    }
+
+    #[test]
+    fn test_lex_exports() {
+        let lexed = lex(r#"
+            // exports.commented = 1
+            exports.a = 1;
+            exports['b'] = 2;
+            module.exports.c = 3;
+            Object.defineProperty(exports, 'd', { value: 4 });
+            exports[name] = 5;
+        "#);
+        let exports: Vec<_> = lexed.exports.into_iter().collect();
+        assert_eq!(exports, ["a", "b", "c", "d"]);
+        assert!(lexed.reexports.is_empty());
+    }
+
+    #[test]
+    fn test_lex_module_exports() {
+        let lexed = lex("module.exports = { a, b: 2, ...require('./c') };");
+        let exports: Vec<_> = lexed.exports.into_iter().collect();
+        assert_eq!(exports, ["a", "b"]);
+        assert_eq!(lexed.reexports, ["./c"]);
+
+        let lexed = lex("module.exports = require('./d'); __exportStar(require('./e'), exports);");
+        assert_eq!(lexed.reexports, ["./d", "./e"]);
+
+        // Syntax errors are left for require() to report
+        assert!(lex("exports.a = ;").exports.is_empty());
+    }
+
+    /// Run `code` as `main.mjs` in a directory holding `files`
+    fn run_in(name: &str, files: &[(&str, &str)], code: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("viper-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, source) in files {
+            std::fs::write(dir.join(file), source).unwrap();
+        }
+        let mut runtime = Runtime::new().unwrap();
+        let result = runtime.run(code, &dir.join("main.mjs").to_string_lossy());
+        let seen = runtime.eval("String(globalThis.seen)", "check.js");
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+        runtime.value_to_string(&seen.unwrap())
+    }
+
+    #[test]
+    fn test_named_exports_of_commonjs() {
+        let lib = r#"
+            const name = './' + 'extra';
+            exports.readFile = () => 'read';
+            Object.defineProperty(exports, 'version', { value: 2, enumerable: true });
+            module.exports.extra = require(name).extra;
+        "#;
+        let files = [
+            ("lib.js", lib),
+            ("extra.js", "module.exports = { extra: 'x' };"),
+        ];
+        let code = r#"
+            import lib, { readFile, version, extra } from './lib.js';
+            globalThis.seen = [readFile(), version, extra, lib.extra === extra];
+        "#;
+        assert_eq!(run_in("cjs-named", &files, code), "read,2,x,true");
+    }
+
+    /// What TypeScript emits for `export * from './star.cjs'`
+    const EXPORT_STAR: &str = r#"
+        var __exportStar = (m, exports) => Object.assign(exports, m);
+        __exportStar(require('./star.cjs'), exports);
+        exports.own = 1;
+    "#;
+
+    #[test]
+    fn test_reexports_of_commonjs() {
+        let files = [
+            ("lib.js", EXPORT_STAR),
+            ("star.cjs", "exports.starred = true;"),
+            (
+                "assign.js",
+                "Object.assign(module.exports, require('./star.cjs'));",
+            ),
+        ];
+        let code = r#"
+            import * as ns from './lib.js';
+            import * as assigned from './assign.js';
+            globalThis.seen = [ns.starred, ns.own, 'starred' in assigned, assigned.default.starred];
+        "#;
+        assert_eq!(run_in("cjs-star", &files, code), "true,1,false,true");
+    }
+
+    #[test]
+    fn test_import_and_require_share_modules() {
+        let files = [("lib.js", "exports.value = {};")];
+        let code = r#"
+            import lib from './lib.js';
+            import { createRequire } from 'node:module';
+            const require = createRequire(import.meta.url);
+            globalThis.seen = [lib === require('./lib.js')];
+        "#;
+        assert_eq!(run_in("cjs-shared", &files, code), "true");
+    }
+
+    #[test]
+    fn test_require_esm() {
+        let files = [("esm.mjs", "export const value = 1;")];
+        let code = r#"
+            import { createRequire } from 'node:module';
+            const require = createRequire(import.meta.url);
+            try { require('./esm.mjs'); } catch (e) { globalThis.seen = [e.code]; }
+        "#;
+        assert_eq!(run_in("cjs-esm", &files, code), ERR_REQUIRE_ESM);
+    }
+}
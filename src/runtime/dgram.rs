@@ -0,0 +1,866 @@
+//! Dgram module - Node.js compatible UDP sockets
+//!
+//! A bound socket is a non-blocking mio handle registered with the event
+//! loop's poller under a loop source, like a `net` socket. When it fires,
+//! `__dgram_ready` reads every datagram waiting (up to a budget) and sends
+//! what is still queued: a datagram the kernel won't take yet waits here, and
+//! its `send()` callback runs once it has gone out.
+//!
+//! Sockets are built with socket2 so `reuseAddr`, `ipv6Only` and the buffer
+//! sizes can be set before binding. The `dgram` module itself lives in
+//! `dgram_module.js`; the natives are:
+//! - `__dgram_bind(address, port, options)` -> `{ id, address, port, family }`
+//! - `__dgram_attach(id, source)`, `__dgram_close(id)`
+//! - `__dgram_ready(id)` -> `{ messages, queued, queuedBytes, error, code }`
+//! - `__dgram_send(id, chunk, host, address, port)` -> `{ queued, queuedBytes, error, code }`
+//! - `__dgram_connect(id, host, address, port)`, `__dgram_disconnect(id)`
+//! - `__dgram_address(id, remote)` -> `{ address, family, port }`
+//! - `__dgram_set_option(id, name, value)`, `__dgram_get_option(id, name)`
+//! - `__dgram_membership(id, join, group, interface, source)`
+//!
+//! Failures come back as `{ error, code }` rather than exceptions.
+
+use boa_engine::{
+    Context, JsArgs, JsNativeError, JsObject, JsResult, JsValue, NativeFunction, Source, js_string,
+    object::builtins::{JsArray, JsUint8Array},
+};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::dns;
+use super::event_loop::{self, HandleId};
+use super::net;
+use super::permissions;
+
+/// Largest datagram UDP can carry
+const MAX_DATAGRAM: usize = 64 * 1024;
+
+/// Datagrams read per readiness callback, so one busy socket can't starve
+/// the loop; the source is notified again to read the rest on the next turn
+const READ_BUDGET: usize = 64;
+
+static SOCKET_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A bound UDP socket and the datagrams it still has to send
+struct Datagram {
+    socket: mio::net::UdpSocket,
+    source: Option<HandleId>,
+    /// Datagrams the kernel wouldn't take yet, with their destination
+    /// (`None` on a connected socket)
+    queued: VecDeque<(Vec<u8>, Option<SocketAddr>)>,
+}
+
+impl Datagram {
+    /// Send queued datagrams until the socket would block. A datagram that
+    /// fails is dropped and its error returned.
+    fn flush(&mut self) -> io::Result<()> {
+        while let Some((data, target)) = self.queued.front() {
+            let sent = match target {
+                Some(addr) => self.socket.send_to(data, *addr),
+                None => self.socket.send(data),
+            };
+            match sent {
+                Ok(_) => {
+                    self.queued.pop_front();
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.queued.pop_front();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn queued_bytes(&self) -> usize {
+        self.queued.iter().map(|(data, _)| data.len()).sum()
+    }
+
+    fn is_ipv6(&self) -> bool {
+        self.socket
+            .local_addr()
+            .map(|addr| addr.is_ipv6())
+            .unwrap_or(false)
+    }
+}
+
+/// Bound sockets by id
+static SOCKETS: Mutex<Option<HashMap<u64, Datagram>>> = Mutex::new(None);
+
+/// Run `f` on socket `id`, or fail with EBADF when it is not bound
+fn with_socket<T>(id: u64, f: impl FnOnce(&mut Datagram) -> io::Result<T>) -> io::Result<T> {
+    let mut sockets = SOCKETS.lock().unwrap();
+    match sockets.get_or_insert_with(HashMap::new).get_mut(&id) {
+        Some(datagram) => f(datagram),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "Socket is not bound",
+        )),
+    }
+}
+
+/// The Node error code for a failed socket call
+fn error_code(error: &io::Error) -> &'static str {
+    #[cfg(unix)]
+    {
+        match error.raw_os_error() {
+            Some(libc::EMSGSIZE) => return "EMSGSIZE",
+            Some(libc::EBADF) => return "EBADF",
+            _ => {}
+        }
+    }
+    // with_socket's own error for an id that isn't bound
+    if error.kind() == io::ErrorKind::NotConnected && error.raw_os_error().is_none() {
+        return "EBADF";
+    }
+    net::error_code(error)
+}
+
+/// Set `{ error, code }` on `result`
+fn set_error(result: &JsObject, error: &io::Error, context: &mut Context) -> JsResult<()> {
+    result.set(
+        js_string!("error"),
+        js_string!(error.to_string()),
+        false,
+        context,
+    )?;
+    result.set(
+        js_string!("code"),
+        js_string!(error_code(error)),
+        false,
+        context,
+    )?;
+    Ok(())
+}
+
+fn error_result(error: &io::Error, context: &mut Context) -> JsResult<JsValue> {
+    let result = JsObject::with_null_proto();
+    set_error(&result, error, context)?;
+    Ok(result.into())
+}
+
+/// `undefined` on success, `{ error, code }` otherwise
+fn unit_result(outcome: io::Result<()>, context: &mut Context) -> JsResult<JsValue> {
+    match outcome {
+        Ok(()) => Ok(JsValue::undefined()),
+        Err(e) => error_result(&e, context),
+    }
+}
+
+/// `{ address, family, port }`
+fn address_object(addr: SocketAddr, context: &mut Context) -> JsResult<JsObject> {
+    let result = JsObject::with_null_proto();
+    result.set(
+        js_string!("address"),
+        js_string!(addr.ip().to_string()),
+        false,
+        context,
+    )?;
+    result.set(
+        js_string!("family"),
+        js_string!(if addr.is_ipv4() { "IPv4" } else { "IPv6" }),
+        false,
+        context,
+    )?;
+    result.set(js_string!("port"), addr.port(), false, context)?;
+    Ok(result)
+}
+
+fn parse_ip(address: &str) -> io::Result<IpAddr> {
+    address
+        .trim_matches(['[', ']'])
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid IP address"))
+}
+
+fn string_arg(value: &JsValue, context: &mut Context) -> JsResult<Option<String>> {
+    if value.is_null_or_undefined() {
+        return Ok(None);
+    }
+    Ok(Some(value.to_string(context)?.to_std_string_escaped()))
+}
+
+/// The index of an IPv6 interface: `eth0`, `2`, or an address with a scope
+/// (`::%eth0`); none means the default interface
+fn interface_index(interface: Option<&str>) -> io::Result<u32> {
+    let Some(interface) = interface else {
+        return Ok(0);
+    };
+    let name = interface.rsplit('%').next().unwrap_or(interface);
+    if name.is_empty() || name.contains(':') {
+        return Ok(0);
+    }
+    if let Ok(index) = name.parse::<u32>() {
+        return Ok(index);
+    }
+    #[cfg(unix)]
+    {
+        let name = std::ffi::CString::new(name)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid interface name"))?;
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if index != 0 {
+            return Ok(index);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Unknown interface: {}", interface),
+    ))
+}
+
+/// An IPv4 interface address; none means the default interface
+fn interface_v4(interface: Option<&str>) -> io::Result<Ipv4Addr> {
+    match interface {
+        None => Ok(Ipv4Addr::UNSPECIFIED),
+        Some(address) => match parse_ip(address)? {
+            IpAddr::V4(ip) => Ok(ip),
+            IpAddr::V6(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Expected an IPv4 interface address",
+            )),
+        },
+    }
+}
+
+/// Dissolve a connected socket's association (connect to AF_UNSPEC)
+#[cfg(unix)]
+fn disconnect(fd: std::os::unix::io::RawFd) -> io::Result<()> {
+    let mut addr: libc::sockaddr = unsafe { std::mem::zeroed() };
+    addr.sa_family = libc::AF_UNSPEC as libc::sa_family_t;
+    let ret = unsafe {
+        libc::connect(
+            fd,
+            &addr,
+            std::mem::size_of::<libc::sockaddr>() as libc::socklen_t,
+        )
+    };
+    match ret {
+        0 => Ok(()),
+        _ => {
+            let error = io::Error::last_os_error();
+            // BSDs dissolve the association but still report EAFNOSUPPORT
+            if error.raw_os_error() == Some(libc::EAFNOSUPPORT) {
+                Ok(())
+            } else {
+                Err(error)
+            }
+        }
+    }
+}
+
+/// What `bind()` sets up before the address is taken
+struct BindOptions {
+    reuse_addr: bool,
+    ipv6_only: bool,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+}
+
+fn bind(addr: SocketAddr, options: &BindOptions) -> io::Result<mio::net::UdpSocket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if options.reuse_addr {
+        socket.set_reuse_address(true)?;
+    }
+    if addr.is_ipv6() {
+        socket.set_only_v6(options.ipv6_only)?;
+    }
+    if let Some(size) = options.recv_buffer_size {
+        socket.set_recv_buffer_size(size)?;
+    }
+    if let Some(size) = options.send_buffer_size {
+        socket.set_send_buffer_size(size)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    Ok(mio::net::UdpSocket::from_std(socket.into()))
+}
+
+/// Set a socket option by its JS name
+fn set_option(
+    datagram: &Datagram,
+    name: &str,
+    value: &JsValue,
+    context: &mut Context,
+) -> JsResult<io::Result<()>> {
+    let ipv6 = datagram.is_ipv6();
+    let socket = &datagram.socket;
+    let outcome = match name {
+        "broadcast" => socket.set_broadcast(value.to_boolean()),
+        "ttl" => socket.set_ttl(value.to_u32(context)?),
+        "multicastTTL" if ipv6 => {
+            SockRef::from(socket).set_multicast_hops_v6(value.to_u32(context)?)
+        }
+        "multicastTTL" => socket.set_multicast_ttl_v4(value.to_u32(context)?),
+        "multicastLoopback" if ipv6 => socket.set_multicast_loop_v6(value.to_boolean()),
+        "multicastLoopback" => socket.set_multicast_loop_v4(value.to_boolean()),
+        "multicastInterface" => {
+            let interface = string_arg(value, context)?;
+            if ipv6 {
+                interface_index(interface.as_deref())
+                    .and_then(|index| SockRef::from(socket).set_multicast_if_v6(index))
+            } else {
+                interface_v4(interface.as_deref())
+                    .and_then(|ip| SockRef::from(socket).set_multicast_if_v4(&ip))
+            }
+        }
+        "recvBufferSize" => {
+            SockRef::from(socket).set_recv_buffer_size(value.to_u32(context)? as usize)
+        }
+        "sendBufferSize" => {
+            SockRef::from(socket).set_send_buffer_size(value.to_u32(context)? as usize)
+        }
+        _ => {
+            return Err(JsNativeError::typ()
+                .with_message(format!("Unknown socket option: {}", name))
+                .into());
+        }
+    };
+    Ok(outcome)
+}
+
+/// Register the dgram natives and the `dgram` global
+pub fn register_dgram_module(context: &mut Context) -> JsResult<()> {
+    // __dgram_bind(address, port, { reuseAddr, ipv6Only, recvBufferSize,
+    //   sendBufferSize }) -> { id, address, port, family } or { error, code }
+    let bind_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let address = args
+            .get_or_undefined(0)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let port = args.get_or_undefined(1).to_u32(context)? as u16;
+        permissions::check_net(context, &address, Some(port))?;
+
+        let options = args.get_or_undefined(2).as_object();
+        let option = |name: &str, context: &mut Context| match &options {
+            Some(options) => options.get(js_string!(name), context),
+            None => Ok(JsValue::undefined()),
+        };
+        let size = |value: JsValue, context: &mut Context| -> JsResult<Option<usize>> {
+            if value.is_undefined() {
+                Ok(None)
+            } else {
+                Ok(Some(value.to_u32(context)? as usize))
+            }
+        };
+        let bind_options = BindOptions {
+            reuse_addr: option("reuseAddr", context)?.to_boolean(),
+            ipv6_only: option("ipv6Only", context)?.to_boolean(),
+            recv_buffer_size: size(option("recvBufferSize", context)?, context)?,
+            send_buffer_size: size(option("sendBufferSize", context)?, context)?,
+        };
+
+        let socket =
+            parse_ip(&address).and_then(|ip| bind(SocketAddr::new(ip, port), &bind_options));
+        let socket = match socket {
+            Ok(socket) => socket,
+            Err(e) => return error_result(&e, context),
+        };
+        // Port 0 binds an ephemeral port; report the real one
+        let local_addr = socket.local_addr().ok();
+
+        let id = SOCKET_ID.fetch_add(1, Ordering::SeqCst);
+        SOCKETS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(
+                id,
+                Datagram {
+                    socket,
+                    source: None,
+                    queued: VecDeque::new(),
+                },
+            );
+
+        let result = match local_addr {
+            Some(addr) => address_object(addr, context)?,
+            None => JsObject::with_null_proto(),
+        };
+        result.set(js_string!("id"), id as f64, false, context)?;
+        Ok(result.into())
+    });
+    context.register_global_callable(js_string!("__dgram_bind"), 3, bind_fn)?;
+
+    // __dgram_attach(id, source) - poll the socket under a loop source
+    let attach_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let source = args.get_or_undefined(1).to_u32(context)?;
+        let event_loop = event_loop::current(context)?;
+
+        let mut sockets = SOCKETS.lock().unwrap();
+        let Some(datagram) = sockets.get_or_insert_with(HashMap::new).get_mut(&id) else {
+            return Ok(JsValue::undefined());
+        };
+        event_loop
+            .register_io(
+                &mut datagram.socket,
+                source,
+                mio::Interest::READABLE | mio::Interest::WRITABLE,
+            )
+            .map_err(|e| JsNativeError::error().with_message(e.to_string()))?;
+        datagram.source = Some(source);
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__dgram_attach"), 2, attach_fn)?;
+
+    // __dgram_ready(id) -> { messages, queued, queuedBytes, error, code }
+    //
+    // Run after the socket's source fires: sends what is queued, then reads
+    // datagrams until the socket would block or the read budget runs out.
+    // Each message is `{ data, address, family, port }`.
+    let ready_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+
+        let mut sockets = SOCKETS.lock().unwrap();
+        let Some(datagram) = sockets.get_or_insert_with(HashMap::new).get_mut(&id) else {
+            return Ok(JsValue::undefined());
+        };
+
+        let mut failure = datagram.flush().err().map(|e| (e, "send"));
+
+        let messages = JsArray::new(context);
+        let mut drained = false;
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        for _ in 0..READ_BUDGET {
+            match datagram.socket.recv_from(&mut buf) {
+                Ok((n, from)) => {
+                    let data = JsUint8Array::from_iter(buf[..n].iter().copied(), context)?;
+                    let message = address_object(from, context)?;
+                    message.set(js_string!("data"), data, false, context)?;
+                    messages.push(message, context)?;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    drained = true;
+                    break;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    // An ICMP error for an earlier send (ECONNREFUSED on a
+                    // connected socket); later datagrams are still readable
+                    if failure.is_none() {
+                        failure = Some((e, "recvmsg"));
+                    }
+                }
+            }
+        }
+        // Readiness is edge-triggered: come back for what's left unread
+        if let (false, Some(source)) = (drained, datagram.source) {
+            event_loop::current(context)?.notifier(source).notify();
+        }
+
+        let result = JsObject::with_null_proto();
+        result.set(js_string!("messages"), messages, false, context)?;
+        result.set(
+            js_string!("queued"),
+            datagram.queued.len() as f64,
+            false,
+            context,
+        )?;
+        result.set(
+            js_string!("queuedBytes"),
+            datagram.queued_bytes() as f64,
+            false,
+            context,
+        )?;
+        if let Some((e, syscall)) = failure {
+            set_error(&result, &e, context)?;
+            result.set(js_string!("syscall"), js_string!(syscall), false, context)?;
+        }
+        Ok(result.into())
+    });
+    context.register_global_callable(js_string!("__dgram_ready"), 1, ready_fn)?;
+
+    // __dgram_send(id, chunk, host, address, port) -> { queued, queuedBytes,
+    // error, code }; no address sends on a connected socket. Throws when the
+    // permissions allow neither `address` nor `host` resolving to it.
+    let send_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let data = net::view_bytes(args.get_or_undefined(1), context)?;
+        let target = match string_arg(args.get_or_undefined(3), context)? {
+            Some(address) => {
+                let host = string_arg(args.get_or_undefined(2), context)?
+                    .unwrap_or_else(|| address.clone());
+                let port = args.get_or_undefined(4).to_u32(context)? as u16;
+                let target = match parse_ip(&address) {
+                    Ok(ip) => SocketAddr::new(ip, port),
+                    Err(e) => return error_result(&e, context),
+                };
+                dns::check_connect(context, &host, target)?;
+                Some(target)
+            }
+            None => None,
+        };
+
+        let outcome = with_socket(id, |datagram| {
+            datagram.queued.push_back((data, target));
+            let sent = datagram.flush();
+            Ok((sent, datagram.queued.len(), datagram.queued_bytes()))
+        });
+        match outcome {
+            Ok((sent, queued, queued_bytes)) => {
+                let result = JsObject::with_null_proto();
+                result.set(js_string!("queued"), queued as f64, false, context)?;
+                result.set(
+                    js_string!("queuedBytes"),
+                    queued_bytes as f64,
+                    false,
+                    context,
+                )?;
+                // The datagram that failed is the last one to leave the queue
+                if let Err(e) = sent {
+                    set_error(&result, &e, context)?;
+                }
+                Ok(result.into())
+            }
+            Err(e) => error_result(&e, context),
+        }
+    });
+    context.register_global_callable(js_string!("__dgram_send"), 5, send_fn)?;
+
+    // __dgram_connect(id, host, address, port) - only exchange datagrams
+    // with that peer from now on; checked like `__dgram_send`
+    let connect_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let host = args
+            .get_or_undefined(1)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let address = args
+            .get_or_undefined(2)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let port = args.get_or_undefined(3).to_u32(context)? as u16;
+        let peer = match parse_ip(&address) {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(e) => return error_result(&e, context),
+        };
+        dns::check_connect(context, &host, peer)?;
+
+        let outcome = with_socket(id, |datagram| datagram.socket.connect(peer));
+        unit_result(outcome, context)
+    });
+    context.register_global_callable(js_string!("__dgram_connect"), 4, connect_fn)?;
+
+    // __dgram_disconnect(id)
+    let disconnect_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let outcome = with_socket(id, |datagram| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+                disconnect(datagram.socket.as_raw_fd())
+            }
+            #[cfg(not(unix))]
+            {
+                let _ = datagram;
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "disconnect() is not supported on this platform",
+                ))
+            }
+        });
+        unit_result(outcome, context)
+    });
+    context.register_global_callable(js_string!("__dgram_disconnect"), 1, disconnect_fn)?;
+
+    // __dgram_address(id, remote) -> { address, family, port } or { error, code }
+    let address_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let remote = args.get_or_undefined(1).to_boolean();
+        let addr = with_socket(id, |datagram| {
+            if remote {
+                datagram.socket.peer_addr()
+            } else {
+                datagram.socket.local_addr()
+            }
+        });
+        match addr {
+            Ok(addr) => Ok(address_object(addr, context)?.into()),
+            Err(e) => error_result(&e, context),
+        }
+    });
+    context.register_global_callable(js_string!("__dgram_address"), 2, address_fn)?;
+
+    // __dgram_set_option(id, name, value) -> undefined or { error, code }
+    //
+    // broadcast, ttl, multicastTTL, multicastLoopback, multicastInterface,
+    // recvBufferSize and sendBufferSize
+    let set_option_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let name = args
+            .get_or_undefined(1)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let value = args.get_or_undefined(2).clone();
+
+        let mut sockets = SOCKETS.lock().unwrap();
+        let outcome = match sockets.get_or_insert_with(HashMap::new).get_mut(&id) {
+            Some(datagram) => set_option(datagram, &name, &value, context)?,
+            None => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Socket is not bound",
+            )),
+        };
+        drop(sockets);
+        unit_result(outcome, context)
+    });
+    context.register_global_callable(js_string!("__dgram_set_option"), 3, set_option_fn)?;
+
+    // __dgram_get_option(id, name) -> number or { error, code };
+    // recvBufferSize and sendBufferSize
+    let get_option_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let name = args
+            .get_or_undefined(1)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let value = with_socket(id, |datagram| {
+            let socket = SockRef::from(&datagram.socket);
+            match name.as_str() {
+                "recvBufferSize" => socket.recv_buffer_size(),
+                "sendBufferSize" => socket.send_buffer_size(),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown socket option: {}", name),
+                )),
+            }
+        });
+        match value {
+            Ok(value) => Ok(JsValue::from(value as f64)),
+            Err(e) => error_result(&e, context),
+        }
+    });
+    context.register_global_callable(js_string!("__dgram_get_option"), 2, get_option_fn)?;
+
+    // __dgram_membership(id, join, group, interface, source) -> undefined or
+    // { error, code }; a source makes it a source-specific membership
+    let membership_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let join = args.get_or_undefined(1).to_boolean();
+        let group = args
+            .get_or_undefined(2)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let interface = string_arg(args.get_or_undefined(3), context)?;
+        let source = string_arg(args.get_or_undefined(4), context)?;
+
+        let outcome = parse_ip(&group).and_then(|group| {
+            with_socket(id, |datagram| {
+                let socket = &datagram.socket;
+                match (group, source) {
+                    (IpAddr::V4(group), None) => {
+                        let interface = interface_v4(interface.as_deref())?;
+                        if join {
+                            socket.join_multicast_v4(&group, &interface)
+                        } else {
+                            socket.leave_multicast_v4(&group, &interface)
+                        }
+                    }
+                    (IpAddr::V6(group), None) => {
+                        let index = interface_index(interface.as_deref())?;
+                        if join {
+                            socket.join_multicast_v6(&group, index)
+                        } else {
+                            socket.leave_multicast_v6(&group, index)
+                        }
+                    }
+                    (IpAddr::V4(group), Some(source)) => {
+                        let interface = interface_v4(interface.as_deref())?;
+                        let source = match parse_ip(&source)? {
+                            IpAddr::V4(source) => source,
+                            IpAddr::V6(_) => {
+                                return Err(io::Error::new(
+                                    io::ErrorKind::InvalidInput,
+                                    "Expected an IPv4 source address",
+                                ));
+                            }
+                        };
+                        let socket = SockRef::from(socket);
+                        if join {
+                            socket.join_ssm_v4(&source, &group, &interface)
+                        } else {
+                            socket.leave_ssm_v4(&source, &group, &interface)
+                        }
+                    }
+                    (IpAddr::V6(_), Some(_)) => Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "IPv6 source-specific membership is not supported",
+                    )),
+                }
+            })
+        });
+        unit_result(outcome, context)
+    });
+    context.register_global_callable(js_string!("__dgram_membership"), 5, membership_fn)?;
+
+    // __dgram_close(id) - stop polling and close, dropping unsent datagrams
+    let close_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let removed = SOCKETS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .remove(&id);
+        if let Some(mut datagram) = removed.filter(|datagram| datagram.source.is_some()) {
+            let _ = event_loop::current(context)?.deregister_io(&mut datagram.socket);
+        }
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__dgram_close"), 1, close_fn)?;
+
+    let dgram_code = include_str!("dgram_module.js");
+    context.eval(Source::from_bytes(dgram_code.as_bytes()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::{Runtime, seen};
+
+    #[test]
+    fn test_echo() {
+        let code = r#"
+            import dgram from 'node:dgram';
+
+            const server = dgram.createSocket('udp4');
+            server.on('message', (msg, rinfo) => {
+                server.send([msg, Buffer.from('!')], rinfo.port, rinfo.address);
+            });
+            await new Promise((resolve) => server.bind(0, '127.0.0.1', resolve));
+            const { port } = server.address();
+
+            // A connected client only talks to the server
+            const client = dgram.createSocket('udp4');
+            await new Promise((resolve) => client.connect(port, '127.0.0.1', resolve));
+            const reply = new Promise((resolve) => {
+                client.once('message', (msg, rinfo) => resolve([msg, rinfo]));
+            });
+            const sent = await new Promise((resolve, reject) => {
+                client.send('ping', (err, bytes) => (err ? reject(err) : resolve(bytes)));
+            });
+            const [msg, rinfo] = await reply;
+            client.setBroadcast(true);
+
+            // Sending before bind() binds to a random port first
+            const implicit = dgram.createSocket('udp4');
+            const echoed = new Promise((resolve) => implicit.once('message', resolve));
+            implicit.send('hi', port, '127.0.0.1');
+
+            globalThis.seen = [
+                port > 0, sent, String(msg), rinfo.port === port, rinfo.size,
+                client.remoteAddress().port === port, String(await echoed),
+            ];
+            for (const socket of [server, client, implicit]) {
+                await new Promise((resolve) => socket.close(resolve));
+            }
+        "#;
+        assert_eq!(seen(code), "true,4,ping!,true,5,true,hi!");
+    }
+
+    #[test]
+    fn test_socket_errors() {
+        let code = r#"
+            import dgram from 'node:dgram';
+
+            const thrown = (f) => { try { f(); } catch (e) { return e.code; } };
+            const socket = dgram.createSocket('udp4');
+            const unbound = [
+                thrown(() => socket.address()),
+                thrown(() => socket.remoteAddress()),
+                thrown(() => socket.disconnect()),
+                thrown(() => socket.setTTL('64')),
+                thrown(() => socket.send('x', 70000, '127.0.0.1')),
+            ];
+            await new Promise((resolve) => socket.bind(0, '127.0.0.1', resolve));
+            const { port } = socket.address();
+
+            const taken = dgram.createSocket('udp4');
+            taken.bind(port, '127.0.0.1');
+            const inUse = await new Promise((resolve) => taken.once('error', resolve));
+
+            const client = dgram.createSocket('udp4');
+            await new Promise((resolve) => client.connect(port, '127.0.0.1', resolve));
+            const connected = [
+                thrown(() => client.send('x', 0, 1, port, '127.0.0.1')),
+                thrown(() => client.connect(port, '127.0.0.1')),
+                thrown(() => client.bind(0)),
+            ];
+            client.close();
+            await new Promise((resolve) => socket.close(resolve));
+
+            globalThis.seen = [
+                thrown(() => dgram.createSocket('udp5')),
+                ...unbound,
+                inUse.code + ' ' + inUse.syscall,
+                ...connected,
+                thrown(() => client.send('late', port, '127.0.0.1')),
+                thrown(() => client.close()),
+            ];
+        "#;
+        assert_eq!(
+            seen(code),
+            "ERR_SOCKET_BAD_TYPE,EBADF,ERR_SOCKET_DGRAM_NOT_CONNECTED,\
+             ERR_SOCKET_DGRAM_NOT_CONNECTED,ERR_INVALID_ARG_TYPE,ERR_SOCKET_BAD_PORT,\
+             EADDRINUSE bind,ERR_SOCKET_DGRAM_IS_CONNECTED,ERR_SOCKET_DGRAM_IS_CONNECTED,\
+             ERR_SOCKET_ALREADY_BOUND,ERR_SOCKET_DGRAM_NOT_RUNNING,ERR_SOCKET_DGRAM_NOT_RUNNING"
+        );
+    }
+
+    #[test]
+    fn test_send_permission() {
+        use crate::runtime::{Allow, Permissions, RuntimeConfig};
+
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port();
+
+        let config = RuntimeConfig {
+            permissions: Permissions {
+                net: Allow::Only(vec!["localhost".to_string(), "0.0.0.0:0".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let code = r#"
+            import dgram from 'node:dgram';
+            const outcome = (err, value) => (err ? err.code + ' ' + err.resource : value);
+            // A lookup that answers with an address localhost never resolved to
+            const lookup = (host, options, callback) => callback(null, '127.0.0.2', 4);
+            const forged = dgram.createSocket({ type: 'udp4', lookup });
+            const connecting = dgram.createSocket({ type: 'udp4', lookup });
+            const real = dgram.createSocket('udp4');
+            let bound;
+            try {
+                dgram.createSocket('udp4').bind(PORT, '127.0.0.1');
+            } catch (err) {
+                bound = outcome(err);
+            }
+            globalThis.seen = [
+                await new Promise((resolve) =>
+                    forged.send('hi', PORT, 'localhost', (err, bytes) => resolve(outcome(err, bytes)))),
+                await new Promise((resolve) =>
+                    connecting.connect(PORT, 'localhost', (err) => resolve(outcome(err, 'connected')))),
+                await new Promise((resolve) =>
+                    real.send('hi', PORT, 'localhost', (err, bytes) => resolve(outcome(err, bytes)))),
+                bound,
+            ];
+            for (const socket of [forged, connecting, real]) socket.close();
+        "#
+        .replace("PORT", &port.to_string());
+        runtime.run(&code, "main.mjs").unwrap();
+        let seen = runtime.eval("String(globalThis.seen)", "check.js").unwrap();
+        assert_eq!(
+            runtime.value_to_string(&seen),
+            format!(
+                "ERR_ACCESS_DENIED 127.0.0.2:{port},ERR_ACCESS_DENIED 127.0.0.2:{port},2,\
+                 ERR_ACCESS_DENIED 127.0.0.1:{port}"
+            )
+        );
+    }
+}
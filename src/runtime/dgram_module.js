@@ -0,0 +1,582 @@
+/**
+ * Node.js dgram
+ *
+ * A bound Socket is polled under an event loop source like a net.Socket
+ * (see dgram.rs): every time it fires, waiting datagrams are emitted as
+ * 'message' and datagrams the kernel couldn't take yet are sent, settling
+ * their `send()` callbacks in order. A socket that sends before `bind()`
+ * binds itself to a random port first, queueing those sends until it has.
+ */
+(function () {
+  "use strict";
+
+  const { EventEmitter } = globalThis.events;
+
+  // An Error shaped like Node's errno exceptions
+  function errnoException(result, syscall, address, port) {
+    const where = port !== undefined ? `${address}:${port}` : address;
+    const err = new Error(`${syscall} ${result.code}${where ? " " + where : ""}`);
+    err.code = result.code;
+    err.errno = result.code;
+    err.syscall = syscall;
+    if (address !== undefined) err.address = address;
+    if (port !== undefined) err.port = port;
+    return err;
+  }
+
+  function codedError(code, message, Type = Error) {
+    const err = new Type(message);
+    err.code = code;
+    return err;
+  }
+
+  function validatePort(port, allowZero) {
+    const number = typeof port === "string" && port.trim() !== "" ? Number(port) : port;
+    if (
+      typeof number !== "number" ||
+      !Number.isInteger(number) ||
+      number < (allowZero ? 0 : 1) ||
+      number > 65535
+    ) {
+      throw codedError(
+        "ERR_SOCKET_BAD_PORT",
+        `Port should be ${allowZero ? ">=" : ">"} 0 and < 65536. Received ${port}.`,
+        RangeError,
+      );
+    }
+    return number;
+  }
+
+  // The bytes of a send(): a string, a view, or a list of them sent as one
+  // datagram
+  function toChunk(buffer) {
+    const list = Array.isArray(buffer) ? buffer : [buffer];
+    const chunks = list.map((item) => {
+      if (typeof item === "string") return Buffer.from(item);
+      if (ArrayBuffer.isView(item)) {
+        return item instanceof Uint8Array
+          ? item
+          : new Uint8Array(item.buffer, item.byteOffset, item.byteLength);
+      }
+      throw codedError(
+        "ERR_INVALID_ARG_TYPE",
+        'The "buffer" argument must be of type string or an instance of Buffer, TypedArray, or DataView.',
+        TypeError,
+      );
+    });
+    if (chunks.length === 0) return Buffer.alloc(0);
+    return chunks.length === 1 ? chunks[0] : Buffer.concat(chunks);
+  }
+
+  function sliceBuffer(buffer, offset, length) {
+    if (typeof buffer === "string") buffer = Buffer.from(buffer);
+    if (!ArrayBuffer.isView(buffer)) return buffer;
+    offset = offset >>> 0;
+    length = length ?? buffer.byteLength - offset;
+    return new Uint8Array(buffer.buffer, buffer.byteOffset + offset, length >>> 0);
+  }
+
+  class Socket extends EventEmitter {
+    constructor(type, listener) {
+      super();
+
+      const options = typeof type === "object" && type !== null ? type : { type };
+      if (options.type !== "udp4" && options.type !== "udp6") {
+        throw codedError(
+          "ERR_SOCKET_BAD_TYPE",
+          "Bad socket type specified. Valid types are: udp4, udp6",
+          TypeError,
+        );
+      }
+
+      this.type = options.type;
+      this._options = options;
+      this._lookup = options.lookup;
+      this._id = 0;
+      this._source = null;
+      this._refed = true;
+      // "unbound", "binding" or "bound"
+      this._bindState = "unbound";
+      // "disconnected", "connecting" or "connected"
+      this._connectState = "disconnected";
+      this._closed = false;
+      // Calls waiting for an implicit or pending bind to finish
+      this._queue = [];
+      // [callback, bytes] for every datagram handed to the kernel queue,
+      // oldest first
+      this._inFlight = [];
+      this._queued = 0;
+      this._queuedBytes = 0;
+
+      if (typeof listener === "function") {
+        this.on("message", listener);
+      }
+
+      const signal = options.signal;
+      if (signal) {
+        if (signal.aborted) {
+          queueMicrotask(() => this.close());
+        } else {
+          signal.addEventListener("abort", () => {
+            if (!this._closed) this.close();
+          }, { once: true });
+        }
+      }
+    }
+
+    get _family() {
+      return this.type === "udp6" ? 6 : 4;
+    }
+
+    _healthCheck() {
+      if (this._closed) {
+        throw codedError("ERR_SOCKET_DGRAM_NOT_RUNNING", "Not running");
+      }
+    }
+
+    // Resolve `address` to an IP of this socket's family
+    _resolve(address, callback) {
+      if (!address) {
+        callback(null, this.type === "udp6" ? "::1" : "127.0.0.1");
+        return;
+      }
+      if (globalThis.net.isIP(address)) {
+        callback(null, address);
+        return;
+      }
+      const lookup = this._lookup ?? globalThis.dns.lookup;
+      lookup(address, { family: this._family }, callback);
+    }
+
+    // bind([port][, address][, cb]) or bind(options[, cb])
+    bind(...args) {
+      this._healthCheck();
+      if (this._bindState !== "unbound") {
+        throw codedError("ERR_SOCKET_ALREADY_BOUND", "Socket is already bound");
+      }
+
+      const callback = typeof args[args.length - 1] === "function" ? args.pop() : undefined;
+      let options = args[0];
+      if (typeof options !== "object" || options === null) {
+        options = { port: options, address: typeof args[1] === "string" ? args[1] : undefined };
+      }
+      const port = validatePort(options.port ?? 0, true);
+      let address = options.address;
+
+      this._bindState = "binding";
+      if (callback) {
+        this.once("listening", callback);
+      }
+
+      const bindTo = (address) => {
+        if (this._closed) return;
+        const result = __dgram_bind(address, port, {
+          reuseAddr: Boolean(this._options.reuseAddr),
+          ipv6Only: Boolean(this._options.ipv6Only),
+          recvBufferSize: this._options.recvBufferSize,
+          sendBufferSize: this._options.sendBufferSize,
+        });
+        if (result.error) {
+          this._bindState = "unbound";
+          this._queue = [];
+          setImmediate(() => this.emit("error", errnoException(result, "bind", address, port)));
+          return;
+        }
+
+        this._id = result.id;
+        this._source = __viper_loop_source(() => this._ready());
+        __viper_loop_ref(this._source, this._refed);
+        __dgram_attach(this._id, this._source);
+        this._bindState = "bound";
+
+        setImmediate(() => {
+          if (this._closed) return;
+          this.emit("listening");
+          const queue = this._queue;
+          this._queue = [];
+          for (const call of queue) call();
+        });
+      };
+
+      if (!address) {
+        bindTo(this.type === "udp6" ? "::" : "0.0.0.0");
+      } else if (globalThis.net.isIP(address)) {
+        bindTo(address);
+      } else {
+        const lookup = this._lookup ?? globalThis.dns.lookup;
+        lookup(address, { family: this._family }, (err, ip) => {
+          if (err) {
+            this._bindState = "unbound";
+            this._queue = [];
+            this.emit("error", err);
+            return;
+          }
+          bindTo(ip);
+        });
+      }
+      return this;
+    }
+
+    // Bind to a random port if needed, then run `call` once bound
+    _whenBound(call) {
+      if (this._bindState === "unbound") {
+        this.bind({ port: 0 });
+      }
+      if (this._bindState === "bound") {
+        call();
+      } else {
+        this._queue.push(call);
+      }
+    }
+
+    // send(msg[, offset, length][, port][, address][, callback])
+    send(buffer, offset, length, port, address, callback) {
+      const connected = this._connectState === "connected";
+      if (!connected) {
+        if (address || (port && typeof port !== "function")) {
+          buffer = sliceBuffer(buffer, offset, length);
+        } else {
+          callback = port;
+          port = offset;
+          address = length;
+        }
+      } else {
+        if (typeof length === "number") {
+          buffer = sliceBuffer(buffer, offset, length);
+          if (typeof port === "function") {
+            callback = port;
+            port = null;
+          }
+        } else {
+          callback = offset;
+        }
+        if (port || address) {
+          throw codedError("ERR_SOCKET_DGRAM_IS_CONNECTED", "Already connected");
+        }
+      }
+
+      const chunk = toChunk(buffer);
+      if (!connected) port = validatePort(port, false);
+      if (typeof address === "function") {
+        callback = address;
+        address = undefined;
+      } else if (address != null && typeof address !== "string") {
+        throw codedError(
+          "ERR_INVALID_ARG_TYPE",
+          'The "address" argument must be of type string.',
+          TypeError,
+        );
+      }
+      if (typeof callback !== "function") callback = undefined;
+      this._healthCheck();
+
+      this._whenBound(() => {
+        if (connected) {
+          this._send(chunk, undefined, undefined, undefined, callback);
+          return;
+        }
+        this._resolve(address, (err, ip) => {
+          if (this._closed) return;
+          if (err) {
+            if (callback) callback(err);
+            else this.emit("error", err);
+            return;
+          }
+          this._send(chunk, address || ip, ip, port, callback);
+        });
+      });
+    }
+
+    _send(chunk, host, ip, port, callback) {
+      let result;
+      try {
+        result = __dgram_send(this._id, chunk, host, ip, port);
+      } catch (err) {
+        // Not allowed to reach the address, reported like a failed lookup
+        if (callback) queueMicrotask(() => callback(err));
+        else queueMicrotask(() => this.emit("error", err));
+        return;
+      }
+      if (result.queued === undefined) {
+        const err = errnoException(result, "send", ip, port);
+        if (callback) queueMicrotask(() => callback(err));
+        return;
+      }
+      this._inFlight.push([callback, chunk.byteLength, ip, port]);
+      this._settle(result);
+    }
+
+    // Settle the callbacks of datagrams that left the queue; an error is for
+    // the last of them
+    _settle(result) {
+      this._queued = result.queued;
+      this._queuedBytes = result.queuedBytes;
+      const done = this._inFlight.splice(0, this._inFlight.length - result.queued);
+      const failed = result.error && result.syscall !== "recvmsg" ? done.pop() : undefined;
+      for (const [callback, bytes] of done) {
+        if (callback) queueMicrotask(() => callback(null, bytes));
+      }
+      if (failed) {
+        const [callback, , ip, port] = failed;
+        const err = errnoException(result, "send", ip, port);
+        if (callback) queueMicrotask(() => callback(err));
+      }
+    }
+
+    _ready() {
+      if (this._id === 0) return;
+      const result = __dgram_ready(this._id);
+      if (!result) return;
+
+      this._settle(result);
+      for (const message of result.messages) {
+        if (this._id === 0) return;
+        const data = message.data;
+        this.emit("message", Buffer.from(data.buffer, data.byteOffset, data.byteLength), {
+          address: message.address,
+          family: message.family,
+          port: message.port,
+          size: data.byteLength,
+        });
+      }
+      if (result.error && result.syscall === "recvmsg" && this._id !== 0) {
+        this.emit("error", errnoException(result, "recvmsg"));
+      }
+    }
+
+    // connect(port[, address][, callback])
+    connect(port, address, callback) {
+      port = validatePort(port, false);
+      if (typeof address === "function") {
+        callback = address;
+        address = "";
+      } else if (address === undefined) {
+        address = "";
+      }
+      this._healthCheck();
+      if (this._connectState !== "disconnected") {
+        throw codedError("ERR_SOCKET_DGRAM_IS_CONNECTED", "Already connected");
+      }
+
+      this._connectState = "connecting";
+      if (typeof callback === "function") {
+        this.once("connect", callback);
+      }
+
+      this._whenBound(() => {
+        this._resolve(address, (err, ip) => {
+          if (this._closed) return;
+          let failure = err;
+          let result;
+          if (!failure) {
+            try {
+              result = __dgram_connect(this._id, address || ip, ip, port);
+            } catch (denied) {
+              failure = denied;
+            }
+          }
+          if (failure || result) {
+            this._connectState = "disconnected";
+            const ex = failure ?? errnoException(result, "connect", ip, port);
+            queueMicrotask(() => {
+              if (typeof callback === "function") {
+                this.removeListener("connect", callback);
+                callback(ex);
+              } else {
+                this.emit("error", ex);
+              }
+            });
+            return;
+          }
+          this._connectState = "connected";
+          queueMicrotask(() => this.emit("connect"));
+        });
+      });
+    }
+
+    disconnect() {
+      this._healthCheck();
+      if (this._connectState !== "connected") {
+        throw codedError("ERR_SOCKET_DGRAM_NOT_CONNECTED", "Not connected");
+      }
+      const result = __dgram_disconnect(this._id);
+      if (result) throw errnoException(result, "connect");
+      this._connectState = "disconnected";
+    }
+
+    address() {
+      this._healthCheck();
+      const result = __dgram_address(this._id, false);
+      if (result.error) throw errnoException(result, "getsockname");
+      return result;
+    }
+
+    remoteAddress() {
+      this._healthCheck();
+      if (this._connectState !== "connected") {
+        throw codedError("ERR_SOCKET_DGRAM_NOT_CONNECTED", "Not connected");
+      }
+      const result = __dgram_address(this._id, true);
+      if (result.error) throw errnoException(result, "getpeername");
+      return result;
+    }
+
+    close(callback) {
+      if (typeof callback === "function") {
+        this.once("close", callback);
+      }
+      if (this._bindState === "binding") {
+        this._queue.push(() => this.close());
+        return this;
+      }
+      this._healthCheck();
+
+      this._closed = true;
+      this._bindState = "unbound";
+      this._connectState = "disconnected";
+      if (this._id !== 0) {
+        __dgram_close(this._id);
+        this._id = 0;
+        __viper_loop_close(this._source);
+        this._source = null;
+      }
+      this._inFlight = [];
+      this._queued = 0;
+      this._queuedBytes = 0;
+      queueMicrotask(() => this.emit("close"));
+      return this;
+    }
+
+    _setOption(name, value, syscall) {
+      this._healthCheck();
+      const result = __dgram_set_option(this._id, name, value);
+      if (result) throw errnoException(result, syscall);
+    }
+
+    setBroadcast(flag) {
+      this._setOption("broadcast", Boolean(flag), "setBroadcast");
+    }
+
+    setTTL(ttl) {
+      if (typeof ttl !== "number") {
+        throw codedError("ERR_INVALID_ARG_TYPE", 'The "ttl" argument must be of type number.', TypeError);
+      }
+      this._setOption("ttl", ttl, "setTTL");
+      return ttl;
+    }
+
+    setMulticastTTL(ttl) {
+      if (typeof ttl !== "number") {
+        throw codedError("ERR_INVALID_ARG_TYPE", 'The "ttl" argument must be of type number.', TypeError);
+      }
+      this._setOption("multicastTTL", ttl, "setMulticastTTL");
+      return ttl;
+    }
+
+    setMulticastLoopback(flag) {
+      this._setOption("multicastLoopback", Boolean(flag), "setMulticastLoopback");
+      return flag;
+    }
+
+    setMulticastInterface(interfaceAddress) {
+      if (typeof interfaceAddress !== "string") {
+        throw codedError(
+          "ERR_INVALID_ARG_TYPE",
+          'The "interfaceAddress" argument must be of type string.',
+          TypeError,
+        );
+      }
+      this._setOption("multicastInterface", interfaceAddress, "setMulticastInterface");
+    }
+
+    _membership(join, group, interfaceAddress, source, syscall) {
+      this._healthCheck();
+      const result = __dgram_membership(this._id, join, group, interfaceAddress, source);
+      if (result) throw errnoException(result, syscall);
+    }
+
+    addMembership(multicastAddress, interfaceAddress) {
+      if (!multicastAddress) {
+        throw codedError("ERR_MISSING_ARGS", 'The "multicastAddress" argument must be specified', TypeError);
+      }
+      this._membership(true, multicastAddress, interfaceAddress, undefined, "addMembership");
+    }
+
+    dropMembership(multicastAddress, interfaceAddress) {
+      if (!multicastAddress) {
+        throw codedError("ERR_MISSING_ARGS", 'The "multicastAddress" argument must be specified', TypeError);
+      }
+      this._membership(false, multicastAddress, interfaceAddress, undefined, "dropMembership");
+    }
+
+    addSourceSpecificMembership(sourceAddress, groupAddress, interfaceAddress) {
+      this._membership(true, groupAddress, interfaceAddress, sourceAddress, "addSourceSpecificMembership");
+    }
+
+    dropSourceSpecificMembership(sourceAddress, groupAddress, interfaceAddress) {
+      this._membership(false, groupAddress, interfaceAddress, sourceAddress, "dropSourceSpecificMembership");
+    }
+
+    _bufferSize(name, size) {
+      const result =
+        size === undefined
+          ? __dgram_get_option(this._id, name)
+          : __dgram_set_option(this._id, name, size);
+      if (result && result.error) {
+        const err = new Error(`Could not get or set buffer size: ${result.error}`);
+        err.code = "ERR_SOCKET_BUFFER_SIZE";
+        throw err;
+      }
+      return result;
+    }
+
+    getRecvBufferSize() {
+      return this._bufferSize("recvBufferSize");
+    }
+
+    setRecvBufferSize(size) {
+      this._bufferSize("recvBufferSize", size);
+    }
+
+    getSendBufferSize() {
+      return this._bufferSize("sendBufferSize");
+    }
+
+    setSendBufferSize(size) {
+      this._bufferSize("sendBufferSize", size);
+    }
+
+    getSendQueueSize() {
+      return this._queuedBytes;
+    }
+
+    getSendQueueCount() {
+      return this._queued;
+    }
+
+    ref() {
+      this._refed = true;
+      if (this._source !== null) __viper_loop_ref(this._source, true);
+      return this;
+    }
+
+    unref() {
+      this._refed = false;
+      if (this._source !== null) __viper_loop_ref(this._source, false);
+      return this;
+    }
+
+    [Symbol.asyncDispose]() {
+      if (this._closed) return Promise.resolve();
+      return new Promise((resolve) => {
+        this.close(() => resolve());
+      });
+    }
+  }
+
+  function createSocket(type, listener) {
+    return new Socket(type, listener);
+  }
+
+  globalThis.dgram = { createSocket, Socket };
+})();
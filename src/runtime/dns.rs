@@ -0,0 +1,929 @@
+//! DNS - the resolver behind `dns`, `net.connect` and fetch
+//!
+//! Lookups and record queries run on hickory-resolver, a pure-Rust stub
+//! resolver, as tasks on the fetch runtime; each query wakes an event loop
+//! source when it completes, so the JS thread never blocks on the network.
+//!
+//! Resolvers start from the system configuration (resolv.conf or the Windows
+//! registry) and consult `/etc/hosts` first. Each runtime has a default one
+//! behind `dns.lookup()`, the module-level functions and `net.connect()`, so
+//! `dns.setServers()` applies to them within that runtime only; every
+//! `dns.Resolver` gets a resolver of its own. A resolver belongs to the JS
+//! object holding it and is dropped with it, cancelling what it still has in
+//! flight. fetch resolves with the system configuration alone.
+//!
+//! The addresses `lookup()` returns are remembered per runtime so sockets can
+//! be held to them: [`check_connect`] lets a connection through when its
+//! address is allowed, or its host name is and resolved to that address.
+//!
+//! The `dns` module itself lives in `dns_module.js`; the natives here are:
+//! - `__viper_dns_resolver_new()` -> resolver
+//! - `__viper_dns_servers(resolver)` / `__viper_dns_set_servers(resolver, list)`
+//! - `__viper_dns_query(resolver, kind, name, source)` -> query id
+//! - `__viper_dns_result(resolver, query)` -> `{ value }`, `{ code }` or undefined
+//! - `__viper_dns_cancel(resolver)` - fail its pending queries with ECANCELLED
+
+use boa_engine::{
+    Context, JsArgs, JsData, JsNativeError, JsObject, JsResult, JsValue, NativeFunction, Source,
+    js_string, object::ObjectInitializer, object::builtins::JsArray,
+};
+use boa_gc::{Finalize, Trace};
+use hickory_resolver::TokioAsyncResolver;
+use hickory_resolver::config::{
+    LookupIpStrategy, NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig,
+    ResolverOpts,
+};
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+use hickory_resolver::proto::op::ResponseCode;
+use hickory_resolver::proto::rr::{RData, RecordType};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::task::AbortHandle;
+
+use super::event_loop::{self, SourceNotifier};
+use super::fetch;
+use super::permissions;
+
+static QUERY_COUNTER: AtomicU32 = AtomicU32::new(1);
+
+/// A resolver and the name servers it was built with
+#[derive(Clone)]
+struct ResolverEntry {
+    resolver: TokioAsyncResolver,
+    servers: Vec<SocketAddr>,
+}
+
+/// A resolver with the queries whose result has not been collected yet
+struct ResolverState {
+    entry: ResolverEntry,
+    queries: HashMap<u32, Arc<Query>>,
+}
+
+impl ResolverState {
+    /// Abort the queries still running and fail them with `code`
+    fn cancel(&mut self, code: &'static str) {
+        for job in self.queries.values() {
+            if let Some(task) = job.task.lock().unwrap().take() {
+                task.abort();
+            }
+            job.finish(Err(code));
+        }
+    }
+}
+
+impl Drop for ResolverState {
+    fn drop(&mut self) {
+        self.cancel("ECANCELLED");
+    }
+}
+
+/// A resolver held by a JS `Resolver` (or the runtime's default one)
+#[derive(Trace, Finalize, JsData)]
+struct ResolverHandle {
+    #[unsafe_ignore_trace]
+    state: RefCell<ResolverState>,
+}
+
+/// Addresses `lookup()` found for each host name, per runtime
+#[derive(Default, Trace, Finalize, JsData)]
+struct LookupSlot(#[unsafe_ignore_trace] HashMap<String, Vec<IpAddr>>);
+
+/// What a successful query produced
+enum Answer {
+    /// Addresses with their TTL (0 where the source has none, like the hosts file)
+    Addresses(Vec<(IpAddr, u32)>),
+    Names(Vec<String>),
+    Mx(Vec<(u16, String)>),
+    Txt(Vec<Vec<String>>),
+    Srv(Vec<SrvRecord>),
+    Soa(Box<SoaRecord>),
+}
+
+struct SrvRecord {
+    name: String,
+    port: u16,
+    priority: u16,
+    weight: u16,
+}
+
+struct SoaRecord {
+    nsname: String,
+    hostmaster: String,
+    serial: u32,
+    refresh: i32,
+    retry: i32,
+    expire: i32,
+    minttl: u32,
+}
+
+/// One lookup or query in flight
+struct Query {
+    /// The host name of a `lookup()`, whose addresses are remembered
+    lookup: Option<String>,
+    /// Node error code on failure (ENOTFOUND, ETIMEOUT, ...)
+    result: Mutex<Option<Result<Answer, &'static str>>>,
+    notifier: SourceNotifier,
+    task: Mutex<Option<AbortHandle>>,
+}
+
+impl Query {
+    fn finish(&self, result: Result<Answer, &'static str>) {
+        let mut slot = self.result.lock().unwrap();
+        if slot.is_none() {
+            *slot = Some(result);
+            self.notifier.notify();
+        }
+    }
+}
+
+/// Options every resolver shares: both address families, hosts file first
+fn resolver_opts(mut opts: ResolverOpts) -> ResolverOpts {
+    opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+    opts.use_hosts_file = true;
+    opts
+}
+
+/// Resolver configuration and options read from the system
+fn system_config() -> &'static (ResolverConfig, ResolverOpts) {
+    static CONFIG: OnceLock<(ResolverConfig, ResolverOpts)> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        hickory_resolver::system_conf::read_system_conf()
+            .unwrap_or_else(|_| (ResolverConfig::default(), ResolverOpts::default()))
+    })
+}
+
+fn build_resolver(config: ResolverConfig, opts: ResolverOpts) -> ResolverEntry {
+    let mut servers = Vec::new();
+    for server in config.name_servers() {
+        if !servers.contains(&server.socket_addr) {
+            servers.push(server.socket_addr);
+        }
+    }
+    // The resolver's connection tasks belong on the shared runtime
+    let _guard = fetch::runtime().enter();
+    ResolverEntry {
+        resolver: TokioAsyncResolver::tokio(config, resolver_opts(opts)),
+        servers,
+    }
+}
+
+fn system_resolver() -> ResolverEntry {
+    let (config, opts) = system_config();
+    build_resolver(config.clone(), opts.clone())
+}
+
+/// The resolver a native was handed
+fn resolver_arg(value: &JsValue) -> JsResult<JsObject> {
+    value
+        .as_object()
+        .filter(|obj| obj.downcast_ref::<ResolverHandle>().is_some())
+        .ok_or_else(|| type_error("Expected a DNS resolver"))
+}
+
+fn with_resolver<R>(resolver: &JsObject, f: impl FnOnce(&mut ResolverState) -> R) -> R {
+    let handle = resolver
+        .downcast_ref::<ResolverHandle>()
+        .expect("checked by resolver_arg");
+    f(&mut handle.state.borrow_mut())
+}
+
+/// Remember the addresses `lookup()` found for `host`
+fn record_lookup(context: &mut Context, host: &str, answer: &Answer) {
+    let Answer::Addresses(addresses) = answer else {
+        return;
+    };
+    let realm = context.realm().clone();
+    let mut host_defined = realm.host_defined_mut();
+    if !host_defined.has::<LookupSlot>() {
+        host_defined.insert_default::<LookupSlot>();
+    }
+    if let Some(slot) = host_defined.get_mut::<LookupSlot>() {
+        let ips = addresses.iter().map(|(ip, _)| *ip).collect();
+        slot.0.insert(host.to_ascii_lowercase(), ips);
+    }
+}
+
+/// Whether a `lookup()` in this runtime resolved `host` to `ip`
+fn resolved_to(context: &Context, host: &str, ip: IpAddr) -> bool {
+    context
+        .realm()
+        .host_defined()
+        .get::<LookupSlot>()
+        .and_then(|slot| slot.0.get(&host.to_ascii_lowercase()))
+        .is_some_and(|ips| ips.contains(&ip))
+}
+
+/// Check a connection to `addr` made for `host`, a name or an IP literal
+///
+/// The address must be allowed itself, or `host` must be and a `lookup()` in
+/// this runtime must have resolved it to the address; an address picked some
+/// other way can't borrow the host name's grant.
+pub(crate) fn check_connect(context: &mut Context, host: &str, addr: SocketAddr) -> JsResult<()> {
+    let ip = addr.ip().to_string();
+    if permissions::check_net(context, &ip, Some(addr.port())).is_ok() {
+        return Ok(());
+    }
+    permissions::check_net(context, host, Some(addr.port()))?;
+    let literal = host.trim_matches(['[', ']']).parse::<IpAddr>().ok();
+    if literal == Some(addr.ip()) || resolved_to(context, host, addr.ip()) {
+        return Ok(());
+    }
+    permissions::check_net(context, &ip, Some(addr.port()))
+}
+
+/// Parse a `setServers()` entry: an address, optionally with a port
+/// (`1.2.3.4`, `1.2.3.4:5353`, `::1`, `[::1]:5353`)
+fn parse_server(server: &str) -> Option<SocketAddr> {
+    if let Ok(ip) = server.parse::<IpAddr>() {
+        return Some(SocketAddr::new(ip, 53));
+    }
+    if let Ok(addr) = server.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+    let ip = server.strip_prefix('[')?.strip_suffix(']')?;
+    ip.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, 53))
+}
+
+/// Format a name server the way `getServers()` reports it
+fn format_server(addr: &SocketAddr) -> String {
+    match (addr.ip(), addr.port()) {
+        (ip, 53) => ip.to_string(),
+        (IpAddr::V4(ip), port) => format!("{}:{}", ip, port),
+        (IpAddr::V6(ip), port) => format!("[{}]:{}", ip, port),
+    }
+}
+
+/// The Node error code for a failed query
+fn error_code(error: &ResolveError) -> &'static str {
+    match error.kind() {
+        ResolveErrorKind::NoRecordsFound { response_code, .. } => match *response_code {
+            ResponseCode::NXDomain => "ENOTFOUND",
+            ResponseCode::NoError => "ENODATA",
+            ResponseCode::Refused => "EREFUSED",
+            ResponseCode::FormErr => "EFORMERR",
+            ResponseCode::NotImp => "ENOTIMP",
+            _ => "ESERVFAIL",
+        },
+        ResolveErrorKind::Timeout => "ETIMEOUT",
+        ResolveErrorKind::Io(_) | ResolveErrorKind::NoConnections => "ECONNREFUSED",
+        _ => "ESERVFAIL",
+    }
+}
+
+/// Domain names are reported without the root's trailing dot
+fn name_string(name: &hickory_resolver::Name) -> String {
+    let name = name.to_utf8();
+    name.strip_suffix('.').map(str::to_string).unwrap_or(name)
+}
+
+/// `getaddrinfo`-style lookup: hosts file, then A and AAAA
+async fn lookup(resolver: &TokioAsyncResolver, host: &str) -> Result<Answer, &'static str> {
+    let ips = resolver
+        .lookup_ip(host)
+        .await
+        .map_err(|e| match error_code(&e) {
+            // getaddrinfo has no "no data": the name has no address either way
+            "ENODATA" => "ENOTFOUND",
+            code => code,
+        })?;
+    Ok(Answer::Addresses(ips.iter().map(|ip| (ip, 0)).collect()))
+}
+
+/// A record query of `kind` ("A", "MX", ...) for `name`
+async fn query(
+    resolver: &TokioAsyncResolver,
+    kind: &str,
+    name: &str,
+) -> Result<Answer, &'static str> {
+    // dns.reverse(): a PTR query for an address asks for its in-addr.arpa name
+    if let ("PTR", Ok(ip)) = (kind, name.parse::<IpAddr>()) {
+        let names = resolver
+            .reverse_lookup(ip)
+            .await
+            .map_err(|e| error_code(&e))?;
+        return Ok(Answer::Names(
+            names.iter().map(|n| name_string(&n.0)).collect(),
+        ));
+    }
+
+    let record_type = match kind {
+        "A" => RecordType::A,
+        "AAAA" => RecordType::AAAA,
+        "CNAME" => RecordType::CNAME,
+        "MX" => RecordType::MX,
+        "NS" => RecordType::NS,
+        "PTR" => RecordType::PTR,
+        "SOA" => RecordType::SOA,
+        "SRV" => RecordType::SRV,
+        "TXT" => RecordType::TXT,
+        _ => return Err("ENOTIMP"),
+    };
+    let lookup = resolver
+        .lookup(name, record_type)
+        .await
+        .map_err(|e| error_code(&e))?;
+
+    // Only records of the asked-for type; a CNAME chain brings others along
+    let mut records = lookup
+        .records()
+        .iter()
+        .filter(|record| record.record_type() == record_type)
+        .filter_map(|record| Some((record.data()?, record.ttl())));
+    let answer = match record_type {
+        RecordType::A | RecordType::AAAA => Answer::Addresses(
+            records
+                .filter_map(|(data, ttl)| match data {
+                    RData::A(a) => Some((IpAddr::V4(a.0), ttl)),
+                    RData::AAAA(aaaa) => Some((IpAddr::V6(aaaa.0), ttl)),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        RecordType::CNAME | RecordType::NS | RecordType::PTR => Answer::Names(
+            records
+                .filter_map(|(data, _)| match data {
+                    RData::CNAME(name) => Some(name_string(&name.0)),
+                    RData::NS(name) => Some(name_string(&name.0)),
+                    RData::PTR(name) => Some(name_string(&name.0)),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        RecordType::MX => Answer::Mx(
+            records
+                .filter_map(|(data, _)| match data {
+                    RData::MX(mx) => Some((mx.preference(), name_string(mx.exchange()))),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        RecordType::TXT => Answer::Txt(
+            records
+                .filter_map(|(data, _)| match data {
+                    RData::TXT(txt) => Some(
+                        txt.iter()
+                            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+                            .collect(),
+                    ),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        RecordType::SRV => Answer::Srv(
+            records
+                .filter_map(|(data, _)| match data {
+                    RData::SRV(srv) => Some(SrvRecord {
+                        name: name_string(srv.target()),
+                        port: srv.port(),
+                        priority: srv.priority(),
+                        weight: srv.weight(),
+                    }),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => {
+            let soa = records
+                .find_map(|(data, _)| match data {
+                    RData::SOA(soa) => Some(soa),
+                    _ => None,
+                })
+                .ok_or("ENODATA")?;
+            Answer::Soa(Box::new(SoaRecord {
+                nsname: name_string(soa.mname()),
+                hostmaster: name_string(soa.rname()),
+                serial: soa.serial(),
+                refresh: soa.refresh(),
+                retry: soa.retry(),
+                expire: soa.expire(),
+                minttl: soa.minimum(),
+            }))
+        }
+    };
+    let empty = match &answer {
+        Answer::Addresses(list) => list.is_empty(),
+        Answer::Names(list) => list.is_empty(),
+        Answer::Mx(list) => list.is_empty(),
+        Answer::Txt(list) => list.is_empty(),
+        Answer::Srv(list) => list.is_empty(),
+        Answer::Soa(_) => false,
+    };
+    if empty { Err("ENODATA") } else { Ok(answer) }
+}
+
+fn answer_to_js(answer: Answer, context: &mut Context) -> JsResult<JsValue> {
+    let list = JsArray::new(context);
+    match answer {
+        Answer::Addresses(addresses) => {
+            for (ip, ttl) in addresses {
+                let family = if ip.is_ipv4() { 4 } else { 6 };
+                let entry = ObjectInitializer::new(context)
+                    .property(
+                        js_string!("address"),
+                        js_string!(ip.to_string()),
+                        Default::default(),
+                    )
+                    .property(js_string!("family"), family, Default::default())
+                    .property(js_string!("ttl"), ttl, Default::default())
+                    .build();
+                list.push(entry, context)?;
+            }
+        }
+        Answer::Names(names) => {
+            for name in names {
+                list.push(js_string!(name), context)?;
+            }
+        }
+        Answer::Mx(records) => {
+            for (priority, exchange) in records {
+                let entry = ObjectInitializer::new(context)
+                    .property(
+                        js_string!("exchange"),
+                        js_string!(exchange),
+                        Default::default(),
+                    )
+                    .property(js_string!("priority"), priority, Default::default())
+                    .build();
+                list.push(entry, context)?;
+            }
+        }
+        Answer::Txt(records) => {
+            for chunks in records {
+                let entry = JsArray::new(context);
+                for chunk in chunks {
+                    entry.push(js_string!(chunk), context)?;
+                }
+                list.push(entry, context)?;
+            }
+        }
+        Answer::Srv(records) => {
+            for srv in records {
+                let entry = ObjectInitializer::new(context)
+                    .property(js_string!("name"), js_string!(srv.name), Default::default())
+                    .property(js_string!("port"), srv.port, Default::default())
+                    .property(js_string!("priority"), srv.priority, Default::default())
+                    .property(js_string!("weight"), srv.weight, Default::default())
+                    .build();
+                list.push(entry, context)?;
+            }
+        }
+        Answer::Soa(soa) => {
+            let object = ObjectInitializer::new(context)
+                .property(
+                    js_string!("nsname"),
+                    js_string!(soa.nsname),
+                    Default::default(),
+                )
+                .property(
+                    js_string!("hostmaster"),
+                    js_string!(soa.hostmaster),
+                    Default::default(),
+                )
+                .property(js_string!("serial"), soa.serial, Default::default())
+                .property(js_string!("refresh"), soa.refresh, Default::default())
+                .property(js_string!("retry"), soa.retry, Default::default())
+                .property(js_string!("expire"), soa.expire, Default::default())
+                .property(js_string!("minttl"), soa.minttl, Default::default())
+                .build();
+            return Ok(object.into());
+        }
+    }
+    Ok(list.into())
+}
+
+/// Resolve `host` with the system configuration, for callers outside
+/// JavaScript; no runtime's `setServers()` affects it
+pub(crate) async fn lookup_host(host: &str) -> Result<Vec<IpAddr>, &'static str> {
+    static SYSTEM: OnceLock<ResolverEntry> = OnceLock::new();
+    let entry = SYSTEM.get_or_init(system_resolver);
+    match lookup(&entry.resolver, host).await? {
+        Answer::Addresses(addresses) => Ok(addresses.into_iter().map(|(ip, _)| ip).collect()),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// fetch's connections resolve host names with the system configuration
+pub(crate) struct ReqwestResolver;
+
+impl reqwest::dns::Resolve for ReqwestResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let ips = lookup_host(&host).await.map_err(|code| {
+                let message = format!("getaddrinfo {} {}", code, host);
+                Box::<dyn std::error::Error + Send + Sync>::from(message)
+            })?;
+            let addrs: reqwest::dns::Addrs =
+                Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+fn type_error(message: impl Into<String>) -> boa_engine::JsError {
+    JsNativeError::typ().with_message(message.into()).into()
+}
+
+/// Register the DNS natives and the `dns` global
+pub fn register_dns_module(context: &mut Context) -> JsResult<()> {
+    // __viper_dns_resolver_new() -> resolver, set up from the system configuration
+    let new_fn = NativeFunction::from_fn_ptr(|_this, _args, _context| {
+        let handle = ResolverHandle {
+            state: RefCell::new(ResolverState {
+                entry: system_resolver(),
+                queries: HashMap::new(),
+            }),
+        };
+        Ok(JsObject::from_proto_and_data(None, handle).into())
+    });
+    context.register_global_callable(js_string!("__viper_dns_resolver_new"), 0, new_fn)?;
+
+    // __viper_dns_servers(resolver) -> ["1.1.1.1", "[::1]:5353", ...]
+    let servers_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let resolver = resolver_arg(args.get_or_undefined(0))?;
+        let servers = with_resolver(&resolver, |state| state.entry.servers.clone());
+        let list = JsArray::new(context);
+        for server in &servers {
+            list.push(js_string!(format_server(server)), context)?;
+        }
+        Ok(list.into())
+    });
+    context.register_global_callable(js_string!("__viper_dns_servers"), 1, servers_fn)?;
+
+    // __viper_dns_set_servers(resolver, ["1.2.3.4", "[::1]:5353", ...])
+    let set_servers_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let resolver = resolver_arg(args.get_or_undefined(0))?;
+        let list = args
+            .get_or_undefined(1)
+            .as_object()
+            .and_then(|o| JsArray::from_object(o.clone()).ok())
+            .ok_or_else(|| type_error("The \"servers\" argument must be an instance of Array"))?;
+
+        let mut servers = Vec::new();
+        for i in 0..list.length(context)? {
+            let server = list
+                .get(i, context)?
+                .to_string(context)?
+                .to_std_string_escaped();
+            let addr = parse_server(&server).ok_or_else(|| {
+                JsNativeError::typ().with_message(format!("Invalid IP address: {}", server))
+            })?;
+            if !servers.contains(&addr) {
+                servers.push(addr);
+            }
+        }
+        // Queries go wherever the servers are; all of them must be reachable
+        for addr in &servers {
+            permissions::check_net(context, &addr.ip().to_string(), Some(addr.port()))?;
+        }
+
+        let mut group = NameServerConfigGroup::new();
+        for addr in &servers {
+            group.push(NameServerConfig::new(*addr, Protocol::Udp));
+            group.push(NameServerConfig::new(*addr, Protocol::Tcp));
+        }
+        let (system, opts) = system_config();
+        let config =
+            ResolverConfig::from_parts(system.domain().cloned(), system.search().to_vec(), group);
+        let entry = build_resolver(config, opts.clone());
+        with_resolver(&resolver, |state| state.entry = entry);
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_dns_set_servers"), 2, set_servers_fn)?;
+
+    // __viper_dns_query(resolver, kind, name, source) -> query id, where kind
+    // is "LOOKUP" (getaddrinfo-style) or a record type
+    let query_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let resolver = resolver_arg(args.get_or_undefined(0))?;
+        let kind = args
+            .get_or_undefined(1)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let name = args
+            .get_or_undefined(2)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let source = args.get_or_undefined(3).to_u32(context)?;
+        permissions::check_net(context, &name, None)?;
+
+        let query_id = QUERY_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let job = Arc::new(Query {
+            lookup: (kind == "LOOKUP").then(|| name.clone()),
+            result: Mutex::new(None),
+            notifier: event_loop::current(context)?.notifier(source),
+            task: Mutex::new(None),
+        });
+        let entry = with_resolver(&resolver, |state| {
+            state.queries.insert(query_id, Arc::clone(&job));
+            state.entry.clone()
+        });
+
+        let task_job = Arc::clone(&job);
+        let task = fetch::runtime().spawn(async move {
+            let result = if kind == "LOOKUP" {
+                lookup(&entry.resolver, &name).await
+            } else {
+                query(&entry.resolver, &kind, &name).await
+            };
+            task_job.finish(result);
+        });
+        *job.task.lock().unwrap() = Some(task.abort_handle());
+        Ok(JsValue::from(query_id))
+    });
+    context.register_global_callable(js_string!("__viper_dns_query"), 4, query_fn)?;
+
+    // __viper_dns_result(resolver, query) -> { value } | { code } | undefined while pending
+    let result_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let resolver = resolver_arg(args.get_or_undefined(0))?;
+        let id = args.get_or_undefined(1).to_u32(context)?;
+        let done = with_resolver(&resolver, |state| {
+            let result = state.queries.get(&id)?.result.lock().unwrap().take()?;
+            let job = state.queries.remove(&id)?;
+            Some((job, result))
+        });
+        let Some((job, result)) = done else {
+            return Ok(JsValue::undefined());
+        };
+        let object = match result {
+            Ok(answer) => {
+                if let Some(host) = &job.lookup {
+                    record_lookup(context, host, &answer);
+                }
+                let value = answer_to_js(answer, context)?;
+                ObjectInitializer::new(context)
+                    .property(js_string!("value"), value, Default::default())
+                    .build()
+            }
+            Err(code) => ObjectInitializer::new(context)
+                .property(js_string!("code"), js_string!(code), Default::default())
+                .build(),
+        };
+        Ok(object.into())
+    });
+    context.register_global_callable(js_string!("__viper_dns_result"), 2, result_fn)?;
+
+    // __viper_dns_cancel(resolver)
+    let cancel_fn = NativeFunction::from_fn_ptr(|_this, args, _context| {
+        let resolver = resolver_arg(args.get_or_undefined(0))?;
+        with_resolver(&resolver, |state| state.cancel("ECANCELLED"));
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_dns_cancel"), 1, cancel_fn)?;
+
+    let dns_code = include_str!("dns_module.js");
+    context.eval(Source::from_bytes(dns_code.as_bytes()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::{Runtime, seen};
+
+    /// Answer A, MX and TXT queries for example.test over UDP; every other
+    /// name is NXDOMAIN
+    fn spawn_dns_stub() -> u16 {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            loop {
+                let Ok((len, peer)) = socket.recv_from(&mut buf) else {
+                    return;
+                };
+                let query = &buf[..len];
+
+                // Question name labels, then qtype and qclass
+                let mut end = 12;
+                let mut labels = Vec::new();
+                while end < len && query[end] != 0 {
+                    let size = query[end] as usize;
+                    labels.push(
+                        String::from_utf8_lossy(&query[end + 1..end + 1 + size]).to_lowercase(),
+                    );
+                    end += 1 + size;
+                }
+                let qtype = u16::from_be_bytes([query[end + 1], query[end + 2]]);
+                end += 5;
+
+                let known = labels.join(".") == "example.test";
+                let rdata: Option<Vec<u8>> = match (known, qtype) {
+                    (true, 1) => Some(vec![127, 0, 0, 1]),
+                    (true, 15) => {
+                        let mut mx = vec![0, 10];
+                        for label in ["mail", "example", "test"] {
+                            mx.push(label.len() as u8);
+                            mx.extend_from_slice(label.as_bytes());
+                        }
+                        mx.push(0);
+                        Some(mx)
+                    }
+                    (true, 16) => Some([&[7u8][..], b"v=viper"].concat()),
+                    _ => None,
+                };
+
+                let mut reply = query[..2].to_vec();
+                reply.extend_from_slice(&[0x81, if known { 0x80 } else { 0x83 }]);
+                reply.extend_from_slice(&[0, 1, 0, rdata.is_some() as u8, 0, 0, 0, 0]);
+                reply.extend_from_slice(&query[12..end]);
+                if let Some(rdata) = rdata {
+                    // Pointer to the question name, type, class IN, TTL 300
+                    reply.extend_from_slice(&[0xc0, 12]);
+                    reply.extend_from_slice(&qtype.to_be_bytes());
+                    reply.extend_from_slice(&[0, 1, 0, 0, 1, 44]);
+                    reply.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+                    reply.extend_from_slice(&rdata);
+                }
+                let _ = socket.send_to(&reply, peer);
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_resolve_records() {
+        let port = spawn_dns_stub();
+        let code = r#"
+            import dns from 'node:dns';
+            import { Resolver } from 'node:dns/promises';
+
+            const resolver = new Resolver();
+            resolver.setServers(['127.0.0.1:PORT']);
+            const mx = await resolver.resolveMx('example.test');
+            const txt = await resolver.resolveTxt('example.test');
+
+            const callbacks = new dns.Resolver();
+            callbacks.setServers(['127.0.0.1:PORT']);
+            const records = await new Promise((resolve, reject) =>
+                callbacks.resolve4('example.test', { ttl: true }, (err, records) =>
+                    err ? reject(err) : resolve(records)));
+
+            globalThis.seen = [
+                (await resolver.resolve4('example.test')).join(),
+                mx[0].priority + ' ' + mx[0].exchange,
+                txt[0].join(''),
+                records[0].address + ' ' + records[0].ttl,
+                resolver.getServers().join(),
+            ];
+        "#
+        .replace("PORT", &port.to_string());
+        assert_eq!(
+            seen(&code),
+            format!("127.0.0.1,10 mail.example.test,v=viper,127.0.0.1 300,127.0.0.1:{port}")
+        );
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let port = spawn_dns_stub();
+        let code = r#"
+            import dns from 'node:dns';
+            import { Resolver } from 'node:dns/promises';
+
+            const resolver = new Resolver();
+            resolver.setServers(['127.0.0.1:PORT']);
+            const error = (e) => e.code + ' ' + (e.syscall ?? e.name);
+            const thrown = (f) => { try { f(); } catch (e) { return error(e); } };
+
+            const missing = await resolver.resolve4('missing.test').catch((e) => e);
+            const callbacks = new dns.Resolver();
+            callbacks.setServers(['127.0.0.1:PORT']);
+            const failed = await new Promise((resolve) =>
+                callbacks.resolveMx('missing.test', resolve));
+
+            globalThis.seen = [
+                error(missing) + ' ' + missing.hostname,
+                error(failed),
+                await resolver.resolve('example.test', 'CAA').catch(error),
+                await resolver.resolve('example.test', 'BOGUS').catch(error),
+                await resolver.resolve4(42).catch(error),
+                thrown(() => callbacks.resolve('example.test', 'BOGUS', () => {})),
+                thrown(() => resolver.setServers(['not an address'])),
+                thrown(() => resolver.setServers('127.0.0.1')),
+                thrown(() => dns.lookup('example.test', { family: 5 }, () => {})),
+                thrown(() => dns.lookup('example.test')),
+                thrown(() => dns.lookup(42, () => {})),
+                resolver.getServers().join(),
+            ];
+        "#
+        .replace("PORT", &port.to_string());
+        assert_eq!(
+            seen(&code),
+            format!(
+                "ENOTFOUND queryA missing.test,ENOTFOUND queryMx,ENOTIMP queryCaa,\
+                 ERR_INVALID_ARG_VALUE TypeError,ERR_INVALID_ARG_TYPE TypeError,\
+                 ERR_INVALID_ARG_VALUE TypeError,ERR_INVALID_IP_ADDRESS TypeError,\
+                 ERR_INVALID_ARG_TYPE TypeError,ERR_INVALID_ARG_VALUE TypeError,\
+                 ERR_INVALID_ARG_TYPE TypeError,ERR_INVALID_ARG_TYPE TypeError,127.0.0.1:{port}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        // A server that never answers keeps the query pending until cancelled
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = silent.local_addr().unwrap().port();
+        let code = r#"
+            import { Resolver } from 'node:dns/promises';
+
+            const resolver = new Resolver();
+            resolver.setServers(['127.0.0.1:PORT']);
+            const pending = resolver.resolve4('example.test').catch((e) => e);
+            resolver.cancel();
+            const err = await pending;
+            globalThis.seen = [err.code, err.syscall, err.hostname];
+        "#
+        .replace("PORT", &port.to_string());
+        assert_eq!(seen(&code), "ECANCELLED,queryA,example.test");
+        drop(silent);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            use std::io::Write;
+            if let Ok((mut stream, _)) = listener.accept() {
+                let _ = stream.write_all(b"hi");
+            }
+        });
+        let code = r#"
+            import dns from 'node:dns';
+            import net from 'node:net';
+
+            const literal = await new Promise((resolve) =>
+                dns.lookup('127.0.0.1', (err, address, family) => resolve(address + '/' + family)));
+            const all = await dns.promises.lookup('127.0.0.1', { all: true });
+            const empty = await dns.promises.lookup('', { family: 6 });
+
+            const socket = net.connect(PORT, 'localhost');
+            let looked = false;
+            socket.on('lookup', (err, address) => { looked = !err && net.isIP(address) > 0; });
+            const greeting = await new Promise((resolve, reject) => {
+                socket.on('data', (data) => resolve(data.toString()));
+                socket.on('error', reject);
+            });
+
+            globalThis.seen = [
+                literal,
+                all.map((entry) => entry.address + '/' + entry.family).join(' '),
+                empty.address + '/' + empty.family,
+                looked,
+                greeting,
+            ];
+        "#
+        .replace("PORT", &port.to_string());
+        assert_eq!(seen(&code), "127.0.0.1/4,127.0.0.1/4,null/6,true,hi");
+    }
+
+    #[test]
+    fn test_servers_are_per_runtime() {
+        let mut first = Runtime::new().unwrap();
+        let code = "import dns from 'node:dns'; dns.setServers(['127.0.0.1:5353']);";
+        first.run(code, "main.mjs").unwrap();
+        let mut second = Runtime::new().unwrap();
+        let code = "import dns from 'node:dns'; globalThis.servers = dns.getServers().join();";
+        second.run(code, "main.mjs").unwrap();
+
+        let servers = first.eval("dns.getServers().join()", "check.js").unwrap();
+        assert_eq!(first.value_to_string(&servers), "127.0.0.1:5353");
+        let servers = second.eval("servers", "check.js").unwrap();
+        assert_ne!(second.value_to_string(&servers), "127.0.0.1:5353");
+    }
+
+    #[test]
+    fn test_set_servers_permission() {
+        use crate::runtime::{Allow, Permissions, RuntimeConfig};
+
+        let config = RuntimeConfig {
+            permissions: Permissions {
+                net: Allow::Only(vec!["127.0.0.1:5353".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let code = r#"
+            import { Resolver } from 'node:dns/promises';
+            const resolver = new Resolver();
+            const denied = (f) => { try { f(); } catch (e) { return e.code + ' ' + e.resource; } };
+            globalThis.seen = [
+                denied(() => resolver.setServers(['127.0.0.1:5353', '10.0.0.1'])),
+                !resolver.getServers().includes('10.0.0.1'),
+                denied(() => resolver.setServers(['[::1]:5353'])),
+            ];
+            resolver.setServers(['127.0.0.1:5353']);
+            globalThis.seen.push(resolver.getServers().join());
+        "#;
+        runtime.run(code, "main.mjs").unwrap();
+        let seen = runtime.eval("String(globalThis.seen)", "check.js").unwrap();
+        assert_eq!(
+            runtime.value_to_string(&seen),
+            "ERR_ACCESS_DENIED 10.0.0.1:53,true,ERR_ACCESS_DENIED [::1]:5353,127.0.0.1:5353"
+        );
+    }
+}
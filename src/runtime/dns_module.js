@@ -0,0 +1,338 @@
+/**
+ * Node.js dns and dns/promises
+ *
+ * Every lookup and query runs off the JS thread (see dns.rs) and settles
+ * through an event loop source, which keeps the process alive until the
+ * answer arrives. The module-level functions use this runtime's default
+ * resolver, shared with `net.connect()`; each `Resolver` has its own name
+ * servers.
+ */
+(function () {
+  "use strict";
+
+  const defaultResolver = __viper_dns_resolver_new();
+  const kDefault = Symbol("viper.dns.defaultResolver");
+
+  const codes = {
+    NODATA: "ENODATA",
+    FORMERR: "EFORMERR",
+    SERVFAIL: "ESERVFAIL",
+    NOTFOUND: "ENOTFOUND",
+    NOTIMP: "ENOTIMP",
+    REFUSED: "EREFUSED",
+    BADQUERY: "EBADQUERY",
+    BADNAME: "EBADNAME",
+    BADFAMILY: "EBADFAMILY",
+    BADRESP: "EBADRESP",
+    CONNREFUSED: "ECONNREFUSED",
+    TIMEOUT: "ETIMEOUT",
+    EOF: "EOF",
+    FILE: "EFILE",
+    NOMEM: "ENOMEM",
+    DESTRUCTION: "EDESTRUCTION",
+    BADSTR: "EBADSTR",
+    BADFLAGS: "EBADFLAGS",
+    NONAME: "ENONAME",
+    BADHINTS: "EBADHINTS",
+    NOTINITIALIZED: "ENOTINITIALIZED",
+    LOADIPHLPAPI: "ELOADIPHLPAPI",
+    ADDRGETNETWORKPARAMS: "EADDRGETNETWORKPARAMS",
+    CANCELLED: "ECANCELLED",
+  };
+
+  // Record type of each resolve method
+  const resolveMethods = {
+    resolve4: "A",
+    resolve6: "AAAA",
+    resolveCname: "CNAME",
+    resolveMx: "MX",
+    resolveNs: "NS",
+    resolvePtr: "PTR",
+    resolveSoa: "SOA",
+    resolveSrv: "SRV",
+    resolveTxt: "TXT",
+  };
+
+  let defaultResultOrder = "verbatim";
+
+  function dnsError(code, syscall, hostname) {
+    const err = new Error(`${syscall} ${code} ${hostname}`);
+    err.code = code;
+    err.syscall = syscall;
+    err.hostname = hostname;
+    return err;
+  }
+
+  function invalidArgType(name, expected, value) {
+    const err = new TypeError(
+      `The "${name}" argument must be ${expected}. Received ${value === null ? "null" : typeof value}`,
+    );
+    err.code = "ERR_INVALID_ARG_TYPE";
+    return err;
+  }
+
+  function validateCallback(callback) {
+    if (typeof callback !== "function") throw invalidArgType("callback", "of type function", callback);
+  }
+
+  function validateHostname(hostname) {
+    if (typeof hostname !== "string") throw invalidArgType("hostname", "of type string", hostname);
+  }
+
+  // Run one query on `resolver`; settles with its answer or a Node DNS error
+  function query(resolver, kind, name, syscall) {
+    return new Promise((resolve, reject) => {
+      let id;
+      const source = __viper_loop_source(() => {
+        const result = __viper_dns_result(resolver, id);
+        if (result === undefined) return;
+        __viper_loop_close(source);
+        if (result.code !== undefined) reject(dnsError(result.code, syscall, name));
+        else resolve(result.value);
+      });
+      try {
+        id = __viper_dns_query(resolver, kind, name, source);
+      } catch (err) {
+        __viper_loop_close(source);
+        reject(err);
+      }
+    });
+  }
+
+  // ==========================================================================
+  // lookup
+  // ==========================================================================
+
+  function lookupOptions(options) {
+    if (typeof options === "number") options = { family: options };
+    options = options ?? {};
+    let family = options.family ?? 0;
+    if (family === "IPv4") family = 4;
+    else if (family === "IPv6") family = 6;
+    if (family !== 0 && family !== 4 && family !== 6) {
+      const err = new TypeError(`The property 'options.family' must be one of: 0, 4, 6. Received ${family}`);
+      err.code = "ERR_INVALID_ARG_VALUE";
+      throw err;
+    }
+    let order = options.order ?? defaultResultOrder;
+    if (options.verbatim !== undefined) order = options.verbatim ? "verbatim" : "ipv4first";
+    return { family, all: Boolean(options.all), order };
+  }
+
+  function sortAddresses(addresses, order) {
+    if (order === "verbatim") return addresses;
+    const first = order === "ipv6first" ? 6 : 4;
+    return [
+      ...addresses.filter((entry) => entry.family === first),
+      ...addresses.filter((entry) => entry.family !== first),
+    ];
+  }
+
+  async function lookupAddresses(hostname, options) {
+    validateHostname(hostname);
+    const { family, all, order } = lookupOptions(options);
+    if (!hostname) return all ? [] : { address: null, family: family === 6 ? 6 : 4 };
+
+    const found = await query(defaultResolver, "LOOKUP", hostname, "getaddrinfo");
+    const matching = sortAddresses(
+      found
+        .filter((entry) => family === 0 || entry.family === family)
+        .map(({ address, family }) => ({ address, family })),
+      order,
+    );
+    if (matching.length === 0) throw dnsError("ENOTFOUND", "getaddrinfo", hostname);
+    return all ? matching : matching[0];
+  }
+
+  function lookup(hostname, options, callback) {
+    if (typeof options === "function") [options, callback] = [{}, options];
+    validateCallback(callback);
+    // Bad arguments throw here rather than reaching the callback
+    validateHostname(hostname);
+    lookupOptions(options);
+    const all = Boolean(typeof options === "object" && options?.all);
+    lookupAddresses(hostname, options).then(
+      (result) => (all ? callback(null, result) : callback(null, result.address, result.family)),
+      (err) => callback(err),
+    );
+    return {};
+  }
+
+  // ==========================================================================
+  // Resolver
+  // ==========================================================================
+
+  function setServersOf(resolver, servers) {
+    if (!Array.isArray(servers)) throw invalidArgType("servers", "an instance of Array", servers);
+    try {
+      __viper_dns_set_servers(resolver, servers.map(String));
+    } catch (err) {
+      // Permission errors keep their own code
+      err.code ??= "ERR_INVALID_IP_ADDRESS";
+      throw err;
+    }
+  }
+
+  // The promise-returning methods shared by both Resolver classes
+  class ResolverBase {
+    #resolver;
+
+    constructor(options = {}) {
+      this.#resolver = options?.[kDefault] ? defaultResolver : __viper_dns_resolver_new();
+    }
+
+    cancel() {
+      __viper_dns_cancel(this.#resolver);
+    }
+
+    getServers() {
+      return __viper_dns_servers(this.#resolver);
+    }
+
+    setServers(servers) {
+      setServersOf(this.#resolver, servers);
+    }
+
+    setLocalAddress() {}
+
+    _query(rrtype, hostname, options) {
+      validateHostname(hostname);
+      const syscall = "query" + rrtype[0] + rrtype.slice(1).toLowerCase();
+      return query(this.#resolver, rrtype, hostname, syscall).then((answer) => {
+        if ((rrtype === "A" || rrtype === "AAAA") && !options?.ttl) {
+          return answer.map((entry) => entry.address);
+        }
+        if (rrtype === "A" || rrtype === "AAAA") {
+          return answer.map(({ address, ttl }) => ({ address, ttl }));
+        }
+        return answer;
+      });
+    }
+
+    _resolve(hostname, rrtype = "A", options) {
+      if (typeof rrtype !== "string") throw invalidArgType("rrtype", "of type string", rrtype);
+      const upper = rrtype.toUpperCase();
+      if (Object.values(resolveMethods).includes(upper)) return this._query(upper, hostname, options);
+      if (upper === "ANY" || upper === "CAA" || upper === "NAPTR") {
+        return Promise.reject(dnsError("ENOTIMP", "query" + upper[0] + upper.slice(1).toLowerCase(), hostname));
+      }
+      const err = new TypeError(`The argument 'rrtype' is invalid. Received '${rrtype}'`);
+      err.code = "ERR_INVALID_ARG_VALUE";
+      throw err;
+    }
+
+    _reverse(ip) {
+      if (typeof ip !== "string") throw invalidArgType("ip", "of type string", ip);
+      return query(this.#resolver, "PTR", ip, "getHostByAddr");
+    }
+  }
+
+  class Resolver extends ResolverBase {
+    resolve(hostname, rrtype, callback) {
+      if (typeof rrtype === "function") [rrtype, callback] = ["A", rrtype];
+      validateCallback(callback);
+      this._resolve(hostname, rrtype).then((answer) => callback(null, answer), callback);
+      return {};
+    }
+
+    reverse(ip, callback) {
+      validateCallback(callback);
+      this._reverse(ip).then((names) => callback(null, names), callback);
+      return {};
+    }
+  }
+
+  class PromisesResolver extends ResolverBase {
+    resolve(hostname, rrtype) {
+      try {
+        return this._resolve(hostname, rrtype);
+      } catch (err) {
+        return Promise.reject(err);
+      }
+    }
+
+    reverse(ip) {
+      try {
+        return this._reverse(ip);
+      } catch (err) {
+        return Promise.reject(err);
+      }
+    }
+  }
+
+  for (const [method, rrtype] of Object.entries(resolveMethods)) {
+    Resolver.prototype[method] = function (hostname, options, callback) {
+      if (typeof options === "function") [options, callback] = [{}, options];
+      validateCallback(callback);
+      this._query(rrtype, hostname, options).then((answer) => callback(null, answer), callback);
+      return {};
+    };
+    PromisesResolver.prototype[method] = function (hostname, options) {
+      try {
+        return this._query(rrtype, hostname, options);
+      } catch (err) {
+        return Promise.reject(err);
+      }
+    };
+  }
+  for (const rrtype of ["Any", "Caa", "Naptr"]) {
+    Resolver.prototype["resolve" + rrtype] = function (hostname, callback) {
+      validateCallback(callback);
+      queueMicrotask(() => callback(dnsError("ENOTIMP", "query" + rrtype, hostname)));
+      return {};
+    };
+    PromisesResolver.prototype["resolve" + rrtype] = (hostname) =>
+      Promise.reject(dnsError("ENOTIMP", "query" + rrtype, hostname));
+  }
+
+  // ==========================================================================
+  // Exports
+  // ==========================================================================
+
+  function setDefaultResultOrder(order) {
+    if (order !== "verbatim" && order !== "ipv4first" && order !== "ipv6first") {
+      const err = new TypeError(`The argument 'order' must be one of: 'verbatim', 'ipv4first', 'ipv6first'. Received '${order}'`);
+      err.code = "ERR_INVALID_ARG_VALUE";
+      throw err;
+    }
+    defaultResultOrder = order;
+  }
+
+  function getDefaultResultOrder() {
+    return defaultResultOrder;
+  }
+
+  // Module-level functions are the default resolver's methods
+  function bindAll(resolver) {
+    const api = {};
+    for (const name of [...Object.keys(resolveMethods), "resolveAny", "resolveCaa", "resolveNaptr"]) {
+      api[name] = resolver[name].bind(resolver);
+    }
+    for (const name of ["resolve", "reverse", "getServers", "setServers"]) {
+      api[name] = resolver[name].bind(resolver);
+    }
+    return api;
+  }
+
+  const promises = {
+    ...bindAll(new PromisesResolver({ [kDefault]: true })),
+    lookup: lookupAddresses,
+    Resolver: PromisesResolver,
+    setDefaultResultOrder,
+    getDefaultResultOrder,
+    ...codes,
+  };
+
+  globalThis.dns = {
+    ...bindAll(new Resolver({ [kDefault]: true })),
+    lookup,
+    Resolver,
+    promises,
+    setDefaultResultOrder,
+    getDefaultResultOrder,
+    ADDRCONFIG: 1024,
+    V4MAPPED: 2048,
+    ALL: 256,
+    ...codes,
+  };
+})();
@@ -1,36 +1,90 @@
-//! High-performance event loop for the Viper TypeScript runtime
+//! Event loop for the Viper TypeScript runtime
 //!
-//! This module implements a Bun-like event loop that efficiently handles:
-//! - Promise jobs (microtasks)
-//! - Timers (setTimeout/setInterval)
-//! - Async I/O operations
-//! - Generic async jobs
+//! A single loop owns everything that can keep a program alive:
+//! - Promise jobs (microtasks) and async jobs from the engine
+//! - Timers (setTimeout/setInterval) in a min-heap
+//! - Immediates (setImmediate)
+//! - Native sources: WebSocket connections, workers, message ports and child
+//!   processes that signal readiness from their own threads, and sockets
+//!   registered with the poller that become ready on this one
+//!
+//! Between turns the loop blocks in the OS poller (mio) until the next timer
+//! deadline or until a source is notified, so an idle program uses no CPU.
+//! A turn runs Node's phases in order: expired timers, ready sources (poll),
+//! then immediates (check), with a microtask checkpoint after each callback.
+//!
+//! Timers, immediates and sources can be unref'd as in libuv: they still run
+//! while the loop is alive, but don't keep it alive on their own.
+//!
+//! A callback that throws is routed through `process.on('uncaughtException')`
+//! and the loop keeps going if a listener handled it. Each microtask
+//! checkpoint ends by reporting promise rejections that are still unhandled.
+//! Resource limits are checked after every callback.
 
 use boa_engine::{
+    Context, JsArgs, JsData, JsNativeError, JsResult, JsValue, NativeFunction,
     job::{Job, JobExecutor, NativeAsyncJob, PromiseJob, TimeoutJob},
-    Context, JsResult,
+    js_string,
+    object::builtins::{JsArray, JsFunction},
 };
+use boa_gc::{Finalize, Trace};
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     cmp::Ordering,
-    collections::{BinaryHeap, VecDeque},
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    future::Future,
+    pin::Pin,
     rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context as TaskContext, Poll, Wake, Waker},
     time::{Duration, Instant},
 };
 
+use super::{limits, rejection};
+
+/// Poll token reserved for cross-thread wakeups
+const WAKER_TOKEN: mio::Token = mio::Token(0);
+
+/// Identifier of a timer, immediate or source, unique within one loop
+pub type HandleId = u32;
+
+/// An engine async job being driven by the loop
+type AsyncJobFuture<'a> = Pin<Box<dyn Future<Output = JsResult<JsValue>> + 'a>>;
+
+/// What a timer runs when it fires
+enum TimerCallback {
+    /// A JS callback from setTimeout/setInterval
+    Js {
+        function: JsFunction,
+        args: Vec<JsValue>,
+        this: JsValue,
+    },
+    /// A timeout job enqueued by the engine
+    Job(TimeoutJob),
+}
+
+struct Timer {
+    callback: TimerCallback,
+    delay: Duration,
+    repeat: bool,
+    refed: bool,
+    /// Bumped by `refresh()` so stale heap entries are skipped
+    generation: u64,
+}
+
 /// A timer entry in the priority queue
 struct TimerEntry {
     /// When this timer should fire
     deadline: Instant,
-    /// The job to execute when the timer fires
-    job: TimeoutJob,
-    /// Unique ID for ordering timers with same deadline
-    id: u64,
+    /// Insertion order, so timers with the same deadline fire in order
+    seq: u64,
+    id: HandleId,
+    generation: u64,
 }
 
 impl PartialEq for TimerEntry {
     fn eq(&self, other: &Self) -> bool {
-        self.deadline == other.deadline && self.id == other.id
+        self.deadline == other.deadline && self.seq == other.seq
     }
 }
 
@@ -45,231 +99,939 @@ impl PartialOrd for TimerEntry {
 impl Ord for TimerEntry {
     fn cmp(&self, other: &Self) -> Ordering {
         // Reverse ordering for min-heap (earliest deadline first)
-        other.deadline.cmp(&self.deadline)
-            .then_with(|| other.id.cmp(&self.id))
+        other
+            .deadline
+            .cmp(&self.deadline)
+            .then_with(|| other.seq.cmp(&self.seq))
     }
 }
 
-/// A pending async job
-#[allow(dead_code)]
-struct AsyncJobEntry {
-    job: NativeAsyncJob,
+struct Immediate {
+    id: HandleId,
+    function: JsFunction,
+    args: Vec<JsValue>,
+    this: JsValue,
+    refed: bool,
 }
 
-/// High-performance event loop inspired by Bun's architecture
-///
-/// Features:
-/// - Efficient timer management using a min-heap
-/// - Zero-allocation microtask queue processing
-/// - Proper async/await support
-/// - Non-blocking I/O readiness
+/// A native event source with a JS callback run when it is notified
+struct SourceEntry {
+    callback: JsFunction,
+    refed: bool,
+}
+
+/// State shared with other threads
+struct Shared {
+    waker: mio::Waker,
+    /// Sources notified since the last poll
+    ready: Mutex<Vec<HandleId>>,
+}
+
+impl Wake for Shared {
+    fn wake(self: Arc<Self>) {
+        let _ = self.waker.wake();
+    }
+}
+
+/// Thread-safe handle that marks a source ready and wakes its loop
+#[derive(Clone)]
+pub struct SourceNotifier {
+    shared: Arc<Shared>,
+    id: HandleId,
+}
+
+impl SourceNotifier {
+    /// Schedule the source's callback on the loop's next turn
+    pub fn notify(&self) {
+        self.shared.ready.lock().unwrap().push(self.id);
+        let _ = self.shared.waker.wake();
+    }
+}
+
+/// Why [`ViperEventLoop::run_until`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopExit {
+    /// The completion condition was met
+    Done,
+    /// Nothing ref'd is left to keep the loop alive
+    Idle,
+    /// [`ViperEventLoop::stop`] was called
+    Stopped,
+    /// The deadline passed
+    TimedOut,
+}
+
+/// The runtime's event loop and job executor
 pub struct ViperEventLoop {
     /// Queue of promise jobs (microtasks) - processed first
     microtasks: RefCell<VecDeque<PromiseJob>>,
-    /// Priority queue of timers (min-heap by deadline)
-    timers: RefCell<BinaryHeap<TimerEntry>>,
-    /// Pending async jobs
-    async_jobs: RefCell<VecDeque<AsyncJobEntry>>,
+    /// Engine async jobs (module loading) waiting to be started
+    async_jobs: RefCell<VecDeque<NativeAsyncJob>>,
     /// Generic jobs queue
     generic_jobs: RefCell<VecDeque<Job>>,
-    /// Counter for unique timer IDs
-    timer_counter: RefCell<u64>,
-    /// Whether the event loop is currently running
-    running: RefCell<bool>,
-}
-
-impl Default for ViperEventLoop {
-    fn default() -> Self {
-        Self::new()
-    }
+    timers: RefCell<HashMap<HandleId, Timer>>,
+    /// Priority queue of timer deadlines (min-heap)
+    timer_queue: RefCell<BinaryHeap<TimerEntry>>,
+    immediates: RefCell<VecDeque<Immediate>>,
+    sources: RefCell<HashMap<HandleId, SourceEntry>>,
+    next_id: Cell<HandleId>,
+    next_seq: Cell<u64>,
+    poll: RefCell<mio::Poll>,
+    events: RefCell<mio::Events>,
+    shared: Arc<Shared>,
+    stopped: Cell<bool>,
 }
 
 impl ViperEventLoop {
     /// Create a new event loop
-    pub fn new() -> Self {
-        Self {
+    pub fn new() -> std::io::Result<Self> {
+        let poll = mio::Poll::new()?;
+        let waker = mio::Waker::new(poll.registry(), WAKER_TOKEN)?;
+        Ok(Self {
             microtasks: RefCell::new(VecDeque::with_capacity(64)),
-            timers: RefCell::new(BinaryHeap::with_capacity(32)),
-            async_jobs: RefCell::new(VecDeque::with_capacity(16)),
-            generic_jobs: RefCell::new(VecDeque::with_capacity(16)),
-            timer_counter: RefCell::new(0),
-            running: RefCell::new(false),
+            async_jobs: RefCell::new(VecDeque::new()),
+            generic_jobs: RefCell::new(VecDeque::new()),
+            timers: RefCell::new(HashMap::new()),
+            timer_queue: RefCell::new(BinaryHeap::with_capacity(32)),
+            immediates: RefCell::new(VecDeque::new()),
+            sources: RefCell::new(HashMap::new()),
+            next_id: Cell::new(1),
+            next_seq: Cell::new(0),
+            poll: RefCell::new(poll),
+            events: RefCell::new(mio::Events::with_capacity(256)),
+            shared: Arc::new(Shared {
+                waker,
+                ready: Mutex::new(Vec::new()),
+            }),
+            stopped: Cell::new(false),
+        })
+    }
+
+    fn next_id(&self) -> HandleId {
+        let id = self.next_id.get();
+        self.next_id.set(id.wrapping_add(1).max(1));
+        id
+    }
+
+    // ------------------------------------------------------------------
+    // Handles
+    // ------------------------------------------------------------------
+
+    fn insert_timer(&self, callback: TimerCallback, delay: Duration, repeat: bool) -> HandleId {
+        let id = self.next_id();
+        self.timers.borrow_mut().insert(
+            id,
+            Timer {
+                callback,
+                delay,
+                repeat,
+                refed: true,
+                generation: 0,
+            },
+        );
+        self.schedule(id, Instant::now() + delay, 0);
+        id
+    }
+
+    fn schedule(&self, id: HandleId, deadline: Instant, generation: u64) {
+        let seq = self.next_seq.get();
+        self.next_seq.set(seq + 1);
+        self.timer_queue.borrow_mut().push(TimerEntry {
+            deadline,
+            seq,
+            id,
+            generation,
+        });
+    }
+
+    /// Start a JS timer; `repeat` makes it an interval
+    pub fn set_timer(
+        &self,
+        function: JsFunction,
+        args: Vec<JsValue>,
+        this: JsValue,
+        delay: Duration,
+        repeat: bool,
+    ) -> HandleId {
+        self.insert_timer(
+            TimerCallback::Js {
+                function,
+                args,
+                this,
+            },
+            delay,
+            repeat,
+        )
+    }
+
+    /// Cancel a timer
+    pub fn clear_timer(&self, id: HandleId) {
+        self.timers.borrow_mut().remove(&id);
+    }
+
+    /// Restart a timer's countdown from now
+    pub fn refresh_timer(&self, id: HandleId) {
+        let rescheduled = self.timers.borrow_mut().get_mut(&id).map(|timer| {
+            timer.generation += 1;
+            (timer.delay, timer.generation)
+        });
+        if let Some((delay, generation)) = rescheduled {
+            self.schedule(id, Instant::now() + delay, generation);
+        }
+    }
+
+    /// Queue a callback for the check phase
+    pub fn set_immediate(
+        &self,
+        function: JsFunction,
+        args: Vec<JsValue>,
+        this: JsValue,
+    ) -> HandleId {
+        let id = self.next_id();
+        self.immediates.borrow_mut().push_back(Immediate {
+            id,
+            function,
+            args,
+            this,
+            refed: true,
+        });
+        id
+    }
+
+    /// Cancel a queued immediate
+    pub fn clear_immediate(&self, id: HandleId) {
+        self.immediates
+            .borrow_mut()
+            .retain(|immediate| immediate.id != id);
+    }
+
+    /// Register a native source; `callback` runs each time it is notified
+    pub fn add_source(&self, callback: JsFunction) -> HandleId {
+        let id = self.next_id();
+        self.sources.borrow_mut().insert(
+            id,
+            SourceEntry {
+                callback,
+                refed: true,
+            },
+        );
+        id
+    }
+
+    /// Remove a source; later notifications are ignored
+    pub fn close_source(&self, id: HandleId) {
+        self.sources.borrow_mut().remove(&id);
+    }
+
+    /// Notifier that other threads use to wake `source`
+    pub fn notifier(&self, source: HandleId) -> SourceNotifier {
+        SourceNotifier {
+            shared: Arc::clone(&self.shared),
+            id: source,
         }
     }
 
-    /// Check if there's any pending work
-    pub fn has_pending_work(&self) -> bool {
+    /// Register a socket with the poller so `source` runs whenever it becomes
+    /// ready for `interest`; readiness is edge-triggered, so the callback
+    /// must read or write until the socket would block
+    pub fn register_io<S>(
+        &self,
+        io: &mut S,
+        source: HandleId,
+        interest: mio::Interest,
+    ) -> std::io::Result<()>
+    where
+        S: mio::event::Source + ?Sized,
+    {
+        self.poll
+            .borrow()
+            .registry()
+            .register(io, mio::Token(source as usize), interest)
+    }
+
+    /// Stop polling a socket registered with [`Self::register_io`]
+    pub fn deregister_io<S>(&self, io: &mut S) -> std::io::Result<()>
+    where
+        S: mio::event::Source + ?Sized,
+    {
+        self.poll.borrow().registry().deregister(io)
+    }
+
+    /// Set whether a timer, immediate or source keeps the loop alive
+    pub fn set_ref(&self, id: HandleId, refed: bool) {
+        if let Some(timer) = self.timers.borrow_mut().get_mut(&id) {
+            timer.refed = refed;
+        } else if let Some(source) = self.sources.borrow_mut().get_mut(&id) {
+            source.refed = refed;
+        } else if let Some(immediate) = self
+            .immediates
+            .borrow_mut()
+            .iter_mut()
+            .find(|immediate| immediate.id == id)
+        {
+            immediate.refed = refed;
+        }
+    }
+
+    /// Whether a live handle keeps the loop alive
+    pub fn has_ref(&self, id: HandleId) -> bool {
+        if let Some(timer) = self.timers.borrow().get(&id) {
+            return timer.refed;
+        }
+        if let Some(source) = self.sources.borrow().get(&id) {
+            return source.refed;
+        }
+        self.immediates
+            .borrow()
+            .iter()
+            .any(|immediate| immediate.id == id && immediate.refed)
+    }
+
+    /// Make the running loop return [`LoopExit::Stopped`]
+    pub fn stop(&self) {
+        self.stopped.set(true);
+        let _ = self.shared.waker.wake();
+    }
+
+    /// Drop every pending handle and job, releasing their JS callbacks
+    pub fn shutdown(&self) {
+        self.microtasks.borrow_mut().clear();
+        self.async_jobs.borrow_mut().clear();
+        self.generic_jobs.borrow_mut().clear();
+        self.timers.borrow_mut().clear();
+        self.timer_queue.borrow_mut().clear();
+        self.immediates.borrow_mut().clear();
+        self.sources.borrow_mut().clear();
+    }
+
+    /// Whether anything ref'd can still produce work
+    pub fn is_alive(&self) -> bool {
         !self.microtasks.borrow().is_empty()
-            || !self.timers.borrow().is_empty()
             || !self.async_jobs.borrow().is_empty()
             || !self.generic_jobs.borrow().is_empty()
+            || self.timers.borrow().values().any(|timer| timer.refed)
+            || self
+                .immediates
+                .borrow()
+                .iter()
+                .any(|immediate| immediate.refed)
+            || self.sources.borrow().values().any(|source| source.refed)
     }
 
-    /// Get the next timer ID
-    fn next_timer_id(&self) -> u64 {
-        let mut counter = self.timer_counter.borrow_mut();
-        *counter += 1;
-        *counter
-    }
+    // ------------------------------------------------------------------
+    // Running
+    // ------------------------------------------------------------------
 
-    /// Process all ready timers and return true if any were processed
-    fn process_timers(&self, context: &mut Context) -> JsResult<bool> {
-        let now = Instant::now();
-        let mut processed_any = false;
+    /// Run the loop until `done` returns true, nothing ref'd is left, the
+    /// loop is stopped, or `deadline` passes
+    pub fn run_until(
+        &self,
+        context: &mut Context,
+        mut done: impl FnMut(&mut Context) -> bool,
+        deadline: Option<Instant>,
+    ) -> JsResult<LoopExit> {
+        let context = RefCell::new(context);
+        let mut futures: Vec<AsyncJobFuture<'_>> = Vec::new();
+        let waker = Waker::from(Arc::clone(&self.shared));
 
         loop {
-            let should_pop = {
-                let timers = self.timers.borrow();
-                timers.peek().map(|entry| entry.deadline <= now).unwrap_or(false)
-            };
+            self.run_ready(&context, &mut futures, &waker)?;
 
-            if !should_pop {
-                break;
+            if self.stopped.get() {
+                return Ok(LoopExit::Stopped);
             }
+            // In-flight async jobs always finish first
+            if futures.is_empty() {
+                if done(&mut context.borrow_mut()) {
+                    return Ok(LoopExit::Done);
+                }
+                if !self.is_alive() {
+                    return Ok(LoopExit::Idle);
+                }
+            }
+
+            let now = Instant::now();
+            if deadline.is_some_and(|deadline| now >= deadline) {
+                return Ok(LoopExit::TimedOut);
+            }
+            let timeout = self.poll_timeout(now, deadline);
+            self.turn(&mut context.borrow_mut(), timeout)?;
+        }
+    }
+
+    /// One pass over the phases: wait up to `timeout` (`None` blocks until
+    /// woken), then run expired timers, ready sources and immediates
+    fn turn(&self, context: &mut Context, timeout: Option<Duration>) -> JsResult<()> {
+        let ready = self.wait(timeout)?;
+        self.run_timers(context)?;
+        self.run_sources(context, ready)?;
+        self.run_immediates(context)
+    }
 
-            let entry = self.timers.borrow_mut().pop();
-            if let Some(entry) = entry {
-                // Check if cancelled
-                if !entry.job.is_cancelled() {
-                    let is_recurring = entry.job.is_recurring();
-                    let timeout_ms = entry.job.timeout().as_millis() as u64;
+    /// Run microtasks, async jobs and generic jobs until none are runnable
+    fn run_ready<'a>(
+        &self,
+        context: &'a RefCell<&mut Context>,
+        futures: &mut Vec<AsyncJobFuture<'a>>,
+        waker: &Waker,
+    ) -> JsResult<()> {
+        let mut task_context = TaskContext::from_waker(waker);
+        loop {
+            self.run_microtasks(&mut context.borrow_mut())?;
 
-                    // Execute the timer callback (this consumes the job)
-                    entry.job.call(context)?;
-                    processed_any = true;
+            while let Some(job) = pop(&self.async_jobs) {
+                futures.push(Box::pin(job.call(context)));
+            }
 
-                    // Note: For recurring timers, boa_runtime handles re-scheduling internally
-                    // by enqueueing a new TimeoutJob when the callback is executed
-                    let _ = (is_recurring, timeout_ms); // Suppress unused warnings
+            let mut progressed = false;
+            let mut i = 0;
+            while i < futures.len() {
+                match futures[i].as_mut().poll(&mut task_context) {
+                    Poll::Ready(result) => {
+                        drop(futures.swap_remove(i));
+                        result?;
+                        progressed = true;
+                    }
+                    Poll::Pending => i += 1,
                 }
             }
-        }
 
-        Ok(processed_any)
-    }
+            if let Some(job) = pop(&self.generic_jobs) {
+                let context = &mut context.borrow_mut();
+                match job {
+                    Job::PromiseJob(job) => {
+                        job.call(context)?;
+                    }
+                    Job::TimeoutJob(job) => {
+                        job.call(context)?;
+                    }
+                    Job::GenericJob(job) => {
+                        job.call(context)?;
+                    }
+                    _ => {}
+                }
+                progressed = true;
+            }
 
-    /// Get time until next timer fires (for efficient sleeping)
-    pub fn time_until_next_timer(&self) -> Option<Duration> {
-        self.timers.borrow().peek().map(|entry| {
-            let now = Instant::now();
-            if entry.deadline > now {
-                entry.deadline - now
-            } else {
-                Duration::ZERO
+            if !progressed
+                && self.microtasks.borrow().is_empty()
+                && self.async_jobs.borrow().is_empty()
+            {
+                return Ok(());
             }
-        })
+        }
     }
 
-    /// Run one iteration of the event loop
-    /// Returns true if there's more work to do
-    fn run_once(&self, context: &mut Context) -> JsResult<bool> {
-        // Phase 1: Process all microtasks (highest priority)
-        while let Some(job) = self.microtasks.borrow_mut().pop_front() {
-            job.call(context)?;
+    /// Microtask checkpoint
+    fn run_microtasks(&self, context: &mut Context) -> JsResult<()> {
+        loop {
+            while let Some(job) = pop(&self.microtasks) {
+                rejection::job_boundary(context);
+                let result = job.call(context);
+                uncaught(result, context)?;
+            }
+            context.clear_kept_objects();
+            rejection::job_boundary(context);
+            // Rejection listeners may queue more microtasks
+            if !rejection::process_rejections(context)? {
+                return Ok(());
+            }
         }
+    }
 
-        // Phase 2: Process ready timers
-        self.process_timers(context)?;
-
-        // Process any microtasks generated by timer callbacks
-        while let Some(job) = self.microtasks.borrow_mut().pop_front() {
-            job.call(context)?;
+    /// How long the poller may block
+    fn poll_timeout(&self, now: Instant, deadline: Option<Instant>) -> Option<Duration> {
+        if !self.immediates.borrow().is_empty() || !self.shared.ready.lock().unwrap().is_empty() {
+            return Some(Duration::ZERO);
         }
+        [self.next_timer_deadline(), deadline]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|at| at.saturating_duration_since(now))
+    }
 
-        // Phase 3: Process one generic job
-        let generic_job = self.generic_jobs.borrow_mut().pop_front();
-        if let Some(job) = generic_job {
-            match job {
-                Job::PromiseJob(pj) => { pj.call(context)?; },
-                Job::TimeoutJob(tj) => { tj.call(context)?; },
-                Job::GenericJob(gj) => { gj.call(context)?; },
-                _ => {}
-            }
-            // Process any microtasks generated
-            while let Some(microtask) = self.microtasks.borrow_mut().pop_front() {
-                microtask.call(context)?;
+    /// Deadline of the earliest live timer, dropping cancelled entries
+    fn next_timer_deadline(&self) -> Option<Instant> {
+        let timers = self.timers.borrow();
+        let mut queue = self.timer_queue.borrow_mut();
+        while let Some(entry) = queue.peek() {
+            match timers.get(&entry.id) {
+                Some(timer) if timer.generation == entry.generation => {
+                    return Some(entry.deadline);
+                }
+                _ => {
+                    queue.pop();
+                }
             }
         }
+        None
+    }
 
-        Ok(self.has_pending_work())
+    /// Block in the poller and collect notified sources and ready sockets
+    fn wait(&self, timeout: Option<Duration>) -> JsResult<Vec<HandleId>> {
+        let mut events = self.events.borrow_mut();
+        if let Err(e) = self.poll.borrow_mut().poll(&mut events, timeout)
+            && e.kind() != std::io::ErrorKind::Interrupted
+        {
+            return Err(JsNativeError::error()
+                .with_message(format!("Event loop poll failed: {}", e))
+                .into());
+        }
+        let mut ready = std::mem::take(&mut *self.shared.ready.lock().unwrap());
+        // Sockets are registered under their source's id
+        ready.extend(
+            events
+                .iter()
+                .map(|event| event.token())
+                .filter(|token| *token != WAKER_TOKEN)
+                .map(|token| token.0 as HandleId),
+        );
+        Ok(ready)
     }
 
-    /// Run the event loop until all work is complete
-    pub fn run_to_completion(&self, context: &mut Context) -> JsResult<()> {
-        *self.running.borrow_mut() = true;
+    /// Timers phase: run every timer whose deadline has passed
+    fn run_timers(&self, context: &mut Context) -> JsResult<()> {
+        let now = Instant::now();
+        loop {
+            let entry = {
+                let mut queue = self.timer_queue.borrow_mut();
+                match queue.peek() {
+                    Some(entry) if entry.deadline <= now => queue.pop(),
+                    _ => None,
+                }
+            };
+            let Some(entry) = entry else {
+                return Ok(());
+            };
 
-        while self.has_pending_work() {
-            self.run_once(context)?;
+            let callback = {
+                let mut timers = self.timers.borrow_mut();
+                let Some(timer) = timers.get(&entry.id) else {
+                    continue;
+                };
+                if timer.generation != entry.generation {
+                    continue;
+                }
+                match (&timer.callback, timer.repeat) {
+                    (
+                        TimerCallback::Js {
+                            function,
+                            args,
+                            this,
+                        },
+                        true,
+                    ) => {
+                        // Intervals are rescheduled from the start of the callback
+                        let callback = TimerCallback::Js {
+                            function: function.clone(),
+                            args: args.clone(),
+                            this: this.clone(),
+                        };
+                        let (delay, generation) = (timer.delay, timer.generation);
+                        drop(timers);
+                        self.schedule(entry.id, now + delay, generation);
+                        callback
+                    }
+                    _ => timers.remove(&entry.id).unwrap().callback,
+                }
+            };
 
-            // If only timers are pending, sleep until the next one
-            if self.microtasks.borrow().is_empty()
-                && self.async_jobs.borrow().is_empty()
-                && self.generic_jobs.borrow().is_empty()
-                && !self.timers.borrow().is_empty()
-            {
-                if let Some(wait_time) = self.time_until_next_timer() {
-                    if wait_time > Duration::ZERO {
-                        // Sleep efficiently until next timer (max 10ms to stay responsive)
-                        std::thread::sleep(wait_time.min(Duration::from_millis(10)));
+            match callback {
+                TimerCallback::Js {
+                    function,
+                    args,
+                    this,
+                } => {
+                    let result = function.call(&this, &args, context);
+                    uncaught(result, context)?;
+                }
+                TimerCallback::Job(job) => {
+                    if !job.is_cancelled() {
+                        let result = job.call(context);
+                        uncaught(result, context)?;
                     }
                 }
             }
+            self.run_microtasks(context)?;
         }
+    }
 
-        *self.running.borrow_mut() = false;
+    /// Poll phase: run callbacks of notified sources
+    fn run_sources(&self, context: &mut Context, ready: Vec<HandleId>) -> JsResult<()> {
+        let mut seen = HashSet::new();
+        for id in ready {
+            if !seen.insert(id) {
+                continue;
+            }
+            let callback = self
+                .sources
+                .borrow()
+                .get(&id)
+                .map(|source| source.callback.clone());
+            if let Some(callback) = callback {
+                let result = callback.call(&JsValue::undefined(), &[], context);
+                uncaught(result, context)?;
+                self.run_microtasks(context)?;
+            }
+        }
         Ok(())
     }
+
+    /// Check phase: run immediates queued before this phase started
+    fn run_immediates(&self, context: &mut Context) -> JsResult<()> {
+        let Some(last) = self
+            .immediates
+            .borrow()
+            .back()
+            .map(|immediate| immediate.id)
+        else {
+            return Ok(());
+        };
+        loop {
+            let immediate = {
+                let mut immediates = self.immediates.borrow_mut();
+                match immediates.front() {
+                    Some(front) if front.id <= last => immediates.pop_front(),
+                    _ => None,
+                }
+            };
+            let Some(immediate) = immediate else {
+                return Ok(());
+            };
+            let result = immediate
+                .function
+                .call(&immediate.this, &immediate.args, context);
+            uncaught(result, context)?;
+            self.run_microtasks(context)?;
+        }
+    }
+}
+
+/// Give a callback's exception to `uncaughtException` listeners, if any
+///
+/// Also the loop's safepoint: a resource limit hit during the callback stops
+/// the loop and is never handed to listeners.
+fn uncaught<T>(result: JsResult<T>, context: &mut Context) -> JsResult<()> {
+    limits::check(context)?;
+    match result {
+        Ok(_) => Ok(()),
+        Err(error) if limits::from_js(&error, context).is_some() => Err(error),
+        Err(error) => rejection::handle_uncaught(error, context),
+    }
+}
+
+/// Pop without holding the borrow across the job it returns
+fn pop<T>(queue: &RefCell<VecDeque<T>>) -> Option<T> {
+    queue.borrow_mut().pop_front()
 }
 
 impl JobExecutor for ViperEventLoop {
-    fn enqueue_job(self: Rc<Self>, job: Job, _context: &mut Context) {
+    fn enqueue_job(self: Rc<Self>, job: Job, context: &mut Context) {
         match job {
-            Job::PromiseJob(promise_job) => {
-                self.microtasks.borrow_mut().push_back(promise_job);
-            }
-            Job::TimeoutJob(timeout_job) => {
-                let timeout_ms = timeout_job.timeout().as_millis() as u64;
-                let deadline = Instant::now() + Duration::from_millis(timeout_ms);
-                let id = self.next_timer_id();
-                self.timers.borrow_mut().push(TimerEntry {
-                    deadline,
-                    job: timeout_job,
-                    id,
-                });
+            Job::PromiseJob(job) => {
+                rejection::job_queued(context);
+                self.microtasks.borrow_mut().push_back(job);
             }
-            Job::AsyncJob(async_job) => {
-                self.async_jobs.borrow_mut().push_back(AsyncJobEntry { job: async_job });
-            }
-            Job::GenericJob(_) => {
-                self.generic_jobs.borrow_mut().push_back(job);
-            }
-            _ => {
-                // Handle any future job variants by storing as generic
-                self.generic_jobs.borrow_mut().push_back(job);
+            Job::AsyncJob(job) => self.async_jobs.borrow_mut().push_back(job),
+            Job::TimeoutJob(job) => {
+                let delay = Duration::from_millis(job.timeout().as_millis());
+                self.insert_timer(TimerCallback::Job(job), delay, false);
             }
+            job => self.generic_jobs.borrow_mut().push_back(job),
         }
     }
 
+    /// Run everything that is ready without waiting for timers or I/O;
+    /// [`ViperEventLoop::run_until`] is what keeps a program running
     fn run_jobs(self: Rc<Self>, context: &mut Context) -> JsResult<()> {
-        self.run_to_completion(context)
+        self.run_until(context, |_| true, None)?;
+        self.turn(context, Some(Duration::ZERO))?;
+        self.run_until(context, |_| true, None).map(|_| ())
     }
 }
 
+// ============================================================================
+// Per-context access
+// ============================================================================
+
+/// Stores the loop in the realm so native functions can reach it
+#[derive(Trace, Finalize, JsData)]
+struct LoopSlot(#[unsafe_ignore_trace] Rc<ViperEventLoop>);
+
+/// Make `event_loop` reachable from native functions running in `context`
+pub fn install(context: &mut Context, event_loop: Rc<ViperEventLoop>) {
+    context
+        .realm()
+        .host_defined_mut()
+        .insert(LoopSlot(event_loop));
+}
+
+/// The event loop of the context a native function runs in
+pub fn current(context: &Context) -> JsResult<Rc<ViperEventLoop>> {
+    context
+        .realm()
+        .host_defined()
+        .get::<LoopSlot>()
+        .map(|slot| Rc::clone(&slot.0))
+        .ok_or_else(|| {
+            JsNativeError::error()
+                .with_message("No event loop is installed for this context")
+                .into()
+        })
+}
+
+// ============================================================================
+// Native bindings
+// ============================================================================
+
+fn handle_arg(args: &[JsValue], index: usize, context: &mut Context) -> JsResult<HandleId> {
+    args.get_or_undefined(index).to_u32(context)
+}
+
+fn function_arg(args: &[JsValue], index: usize) -> JsResult<JsFunction> {
+    args.get_or_undefined(index)
+        .as_object()
+        .and_then(|obj| JsFunction::from_object(obj.clone()))
+        .ok_or_else(|| {
+            JsNativeError::typ()
+                .with_message("The \"callback\" argument must be of type function")
+                .into()
+        })
+}
+
+fn array_arg(args: &[JsValue], index: usize, context: &mut Context) -> JsResult<Vec<JsValue>> {
+    let Some(obj) = args.get_or_undefined(index).as_object() else {
+        return Ok(Vec::new());
+    };
+    let array = JsArray::from_object(obj.clone())?;
+    let len = array.length(context)?;
+    (0..len).map(|i| array.get(i, context)).collect()
+}
+
+/// Register the `__viper_timer_*`, `__viper_immediate_*` and `__viper_loop_*`
+/// natives that the JS timer and I/O wrappers are built on
+pub fn register_event_loop_api(context: &mut Context) -> JsResult<()> {
+    // __viper_timer_start(callback, delay, repeat, args, thisArg) -> id
+    let timer_start_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let function = function_arg(args, 0)?;
+        let delay = args.get_or_undefined(1).to_number(context)?;
+        let repeat = args.get_or_undefined(2).to_boolean();
+        let call_args = array_arg(args, 3, context)?;
+        let delay = Duration::from_millis(if delay.is_finite() && delay > 0.0 {
+            delay as u64
+        } else {
+            0
+        });
+        let this = args.get_or_undefined(4).clone();
+        let id = current(context)?.set_timer(function, call_args, this, delay, repeat);
+        Ok(JsValue::from(id))
+    });
+    context.register_global_callable(js_string!("__viper_timer_start"), 5, timer_start_fn)?;
+
+    // __viper_timer_clear(id)
+    let timer_clear_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = handle_arg(args, 0, context)?;
+        current(context)?.clear_timer(id);
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_timer_clear"), 1, timer_clear_fn)?;
+
+    // __viper_timer_refresh(id)
+    let timer_refresh_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = handle_arg(args, 0, context)?;
+        current(context)?.refresh_timer(id);
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_timer_refresh"), 1, timer_refresh_fn)?;
 
+    // __viper_immediate_start(callback, args, thisArg) -> id
+    let immediate_start_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let function = function_arg(args, 0)?;
+        let call_args = array_arg(args, 1, context)?;
+        let this = args.get_or_undefined(2).clone();
+        let id = current(context)?.set_immediate(function, call_args, this);
+        Ok(JsValue::from(id))
+    });
+    context.register_global_callable(
+        js_string!("__viper_immediate_start"),
+        3,
+        immediate_start_fn,
+    )?;
 
-/// Wrapper to use ViperEventLoop as Rc<dyn JobExecutor>
-#[allow(dead_code)]
-pub fn create_event_loop() -> Rc<ViperEventLoop> {
-    Rc::new(ViperEventLoop::new())
+    // __viper_immediate_clear(id)
+    let immediate_clear_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = handle_arg(args, 0, context)?;
+        current(context)?.clear_immediate(id);
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(
+        js_string!("__viper_immediate_clear"),
+        1,
+        immediate_clear_fn,
+    )?;
+
+    // __viper_loop_source(callback) -> id
+    let source_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let callback = function_arg(args, 0)?;
+        Ok(JsValue::from(current(context)?.add_source(callback)))
+    });
+    context.register_global_callable(js_string!("__viper_loop_source"), 1, source_fn)?;
+
+    // __viper_loop_close(id)
+    let close_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = handle_arg(args, 0, context)?;
+        current(context)?.close_source(id);
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_loop_close"), 1, close_fn)?;
+
+    // __viper_loop_ref(id, refed)
+    let ref_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = handle_arg(args, 0, context)?;
+        let refed = args.get_or_undefined(1).to_boolean();
+        current(context)?.set_ref(id, refed);
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_loop_ref"), 2, ref_fn)?;
+
+    // __viper_loop_has_ref(id) -> boolean
+    let has_ref_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = handle_arg(args, 0, context)?;
+        Ok(JsValue::from(current(context)?.has_ref(id)))
+    });
+    context.register_global_callable(js_string!("__viper_loop_has_ref"), 1, has_ref_fn)?;
+
+    // __viper_loop_stop()
+    let stop_fn = NativeFunction::from_fn_ptr(|_this, _args, context| {
+        current(context)?.stop();
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_loop_stop"), 0, stop_fn)?;
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use boa_engine::{Source, context::ContextBuilder};
+
+    fn context() -> (Context, Rc<ViperEventLoop>) {
+        let event_loop = Rc::new(ViperEventLoop::new().unwrap());
+        let mut context = ContextBuilder::default()
+            .job_executor(event_loop.clone())
+            .build()
+            .unwrap();
+        install(&mut context, event_loop.clone());
+        register_event_loop_api(&mut context).unwrap();
+        (context, event_loop)
+    }
+
+    fn eval(context: &mut Context, code: &str) -> JsValue {
+        context.eval(Source::from_bytes(code)).unwrap()
+    }
 
     #[test]
     fn test_event_loop_creation() {
-        let event_loop = ViperEventLoop::new();
-        assert!(!event_loop.has_pending_work());
+        let event_loop = ViperEventLoop::new().unwrap();
+        assert!(!event_loop.is_alive());
+    }
+
+    #[test]
+    fn test_phase_order() {
+        let (mut context, event_loop) = context();
+        eval(
+            &mut context,
+            r#"
+            globalThis.log = [];
+            __viper_timer_start(() => log.push('timeout 20'), 20, false, [], undefined);
+            __viper_timer_start(() => {
+                log.push('timeout 1');
+                Promise.resolve().then(() => log.push('microtask'));
+                __viper_immediate_start(() => log.push('immediate'), [], undefined);
+            }, 1, false, [], undefined);
+            "#,
+        );
+        let exit = event_loop.run_until(&mut context, |_| false, None).unwrap();
+        assert_eq!(exit, LoopExit::Idle);
+        let log = eval(&mut context, "log.join(',')");
+        assert_eq!(
+            log.as_string().unwrap().to_std_string_escaped(),
+            "timeout 1,microtask,immediate,timeout 20"
+        );
+    }
+
+    #[test]
+    fn test_unref_does_not_keep_loop_alive() {
+        let (mut context, event_loop) = context();
+        eval(
+            &mut context,
+            r#"
+            globalThis.ticks = 0;
+            const id = __viper_timer_start(() => ticks++, 1, true, [], undefined);
+            __viper_loop_ref(id, false);
+            "#,
+        );
+        let start = Instant::now();
+        let exit = event_loop.run_until(&mut context, |_| false, None).unwrap();
+        assert_eq!(exit, LoopExit::Idle);
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_source_wakes_from_other_thread() {
+        let (mut context, event_loop) = context();
+        let id = eval(
+            &mut context,
+            r#"
+            globalThis.woken = 0;
+            globalThis.source = __viper_loop_source(() => {
+                woken++;
+                __viper_loop_close(source);
+            });
+            source
+            "#,
+        );
+        let notifier = event_loop.notifier(id.to_u32(&mut context).unwrap());
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            notifier.notify();
+        });
+        let exit = event_loop.run_until(&mut context, |_| false, None).unwrap();
+        assert_eq!(exit, LoopExit::Idle);
+        assert_eq!(eval(&mut context, "woken").as_number(), Some(1.0));
+    }
+
+    #[test]
+    fn test_io_source_wakes_on_readiness() {
+        let (mut context, event_loop) = context();
+        let id = eval(
+            &mut context,
+            r#"
+            globalThis.woken = 0;
+            globalThis.source = __viper_loop_source(() => {
+                woken++;
+                __viper_loop_close(source);
+            });
+            source
+            "#,
+        );
+        let mut listener = mio::net::TcpListener::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        event_loop
+            .register_io(
+                &mut listener,
+                id.to_u32(&mut context).unwrap(),
+                mio::Interest::READABLE,
+            )
+            .unwrap();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            let _ = std::net::TcpStream::connect(addr);
+        });
+        let exit = event_loop.run_until(&mut context, |_| false, None).unwrap();
+        assert_eq!(exit, LoopExit::Idle);
+        assert_eq!(eval(&mut context, "woken").as_number(), Some(1.0));
     }
 }
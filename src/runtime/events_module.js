@@ -450,15 +450,30 @@
     configurable: true,
   });
 
+  // The error signal-aware APIs reject with once aborted
+  function abortError(signal) {
+    const err = new Error("The operation was aborted", { cause: signal?.reason });
+    err.name = "AbortError";
+    err.code = "ABORT_ERR";
+    return err;
+  }
+
   /**
    * Creates a Promise that is fulfilled when the EventEmitter emits the given event
    */
   function once(emitter, eventName, options = {}) {
+    if (
+      typeof emitter.addEventListener === "function" &&
+      typeof emitter.once !== "function"
+    ) {
+      return onceEventTarget(emitter, eventName, options);
+    }
+
     return new Promise((resolve, reject) => {
       const signal = options.signal;
 
       if (signal !== undefined && signal.aborted) {
-        reject(new Error("AbortError"));
+        reject(abortError(signal));
         return;
       }
 
@@ -480,7 +495,7 @@
       const abortHandler = () => {
         emitter.removeListener(eventName, eventHandler);
         emitter.removeListener("error", errorHandler);
-        reject(new Error("AbortError"));
+        reject(abortError(signal));
       };
 
       if (signal !== undefined) {
@@ -495,6 +510,37 @@
     });
   }
 
+  /**
+   * once() for an EventTarget: resolves with [event]
+   */
+  function onceEventTarget(target, eventName, options) {
+    return new Promise((resolve, reject) => {
+      const signal = options.signal;
+
+      if (signal !== undefined && signal.aborted) {
+        reject(abortError(signal));
+        return;
+      }
+
+      const abortHandler = () => {
+        target.removeEventListener(eventName, eventHandler);
+        reject(abortError(signal));
+      };
+
+      const eventHandler = (event) => {
+        if (signal !== undefined) {
+          signal.removeEventListener("abort", abortHandler);
+        }
+        resolve([event]);
+      };
+
+      if (signal !== undefined) {
+        signal.addEventListener("abort", abortHandler, { once: true });
+      }
+      target.addEventListener(eventName, eventHandler, { once: true });
+    });
+  }
+
   /**
    * Returns an AsyncIterator that iterates eventName events
    */
@@ -542,7 +588,7 @@
     };
 
     const abortHandler = () => {
-      errorHandler(new Error("AbortError"));
+      errorHandler(abortError(signal));
       closeHandler();
     };
 
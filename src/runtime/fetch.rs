@@ -0,0 +1,783 @@
+//! Non-blocking fetch() on a shared background Tokio runtime
+//!
+//! Requests run as tasks on one runtime thread shared by every context, so
+//! connections are pooled per client configuration (one client per distinct
+//! `http.Agent` setup). Each request queues its events and wakes an event loop
+//! source; response bodies are pulled chunk by chunk, so a slow reader stops
+//! the socket from being read instead of buffering the whole body.
+//!
+//! Proxies come from `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY` (either case) and
+//! extra root certificates from `NODE_EXTRA_CA_CERTS` or an agent's `ca`.
+//!
+//! The `fetch`, redirect handling and the Response built from the events live
+//! in `fetch_module.js`; the natives here are:
+//! - `__viper_fetch_start(request, source)` -> request id, once the URL passes
+//!   the net permission
+//! - `__viper_fetch_write(id, chunk | null)` - feed a streaming upload
+//! - `__viper_fetch_next(id)` -> next event or undefined
+//! - `__viper_fetch_abort(id)` - cancel the request and drop its events
+
+use boa_engine::{
+    Context, JsArgs, JsNativeError, JsObject, JsResult, JsValue, NativeFunction, Source, js_string,
+    object::ObjectInitializer,
+    object::builtins::{JsArray, JsUint8Array},
+};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::{Notify, mpsc};
+
+use super::dns;
+use super::event_loop::{self, SourceNotifier};
+use super::permissions;
+
+/// Unread body bytes a request may queue before it stops reading the socket
+const HIGH_WATER_MARK: usize = 256 * 1024;
+
+static REQUEST_COUNTER: AtomicU32 = AtomicU32::new(1);
+
+/// Requests that have not been collected or aborted yet
+static REQUESTS: Mutex<Option<HashMap<u32, Arc<FetchJob>>>> = Mutex::new(None);
+
+/// One client, and so one connection pool, per distinct configuration
+static CLIENTS: Mutex<Option<HashMap<ClientOptions, reqwest::Client>>> = Mutex::new(None);
+
+/// The runtime every request and DNS query runs on
+pub(crate) fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("viper-fetch")
+            .enable_all()
+            .build()
+            .expect("failed to start the fetch runtime")
+    })
+}
+
+/// What a request reports back to JavaScript, in order
+enum FetchEvent {
+    Head {
+        status: u16,
+        status_text: String,
+        headers: Vec<(String, String)>,
+    },
+    Chunk(Vec<u8>),
+    End,
+    Error(String),
+}
+
+/// Shared state of one in-flight request
+struct FetchJob {
+    events: Mutex<VecDeque<FetchEvent>>,
+    /// Body bytes queued but not yet taken by JavaScript
+    buffered: AtomicUsize,
+    /// Signalled when JavaScript takes a chunk
+    drained: Notify,
+    notifier: SourceNotifier,
+    /// Sender for a streaming upload, dropped to end the body
+    upload: Mutex<Option<mpsc::UnboundedSender<Vec<u8>>>>,
+    task: Mutex<Option<tokio::task::AbortHandle>>,
+}
+
+impl FetchJob {
+    fn push(&self, event: FetchEvent) {
+        if let FetchEvent::Chunk(chunk) = &event {
+            self.buffered.fetch_add(chunk.len(), Ordering::SeqCst);
+        }
+        self.events.lock().unwrap().push_back(event);
+        self.notifier.notify();
+    }
+
+    fn next(&self) -> Option<FetchEvent> {
+        let event = self.events.lock().unwrap().pop_front();
+        if let Some(FetchEvent::Chunk(chunk)) = &event {
+            self.buffered.fetch_sub(chunk.len(), Ordering::SeqCst);
+            self.drained.notify_one();
+        }
+        event
+    }
+}
+
+/// The agent options that need a client of their own
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientOptions {
+    keep_alive: bool,
+    max_free_sockets: usize,
+    ca: Vec<String>,
+    reject_unauthorized: bool,
+}
+
+/// The pooled client for `options`, built on first use
+fn client(options: &ClientOptions) -> Result<reqwest::Client, String> {
+    let mut clients = CLIENTS.lock().unwrap();
+    let clients = clients.get_or_insert_with(HashMap::new);
+    if let Some(client) = clients.get(options) {
+        return Ok(client.clone());
+    }
+
+    // Redirects are followed in JavaScript so every hop passes the net permission
+    let mut builder = reqwest::Client::builder()
+        .user_agent(format!("viper/{}", env!("CARGO_PKG_VERSION")))
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(dns::ReqwestResolver))
+        .no_proxy();
+    for proxy in env_proxies()? {
+        builder = builder.proxy(proxy);
+    }
+    builder = if options.keep_alive {
+        builder.pool_max_idle_per_host(options.max_free_sockets)
+    } else {
+        builder.pool_max_idle_per_host(0)
+    };
+
+    let mut pems = options.ca.clone();
+    if let Some(path) = std::env::var_os("NODE_EXTRA_CA_CERTS") {
+        match std::fs::read_to_string(&path) {
+            Ok(pem) => pems.push(pem),
+            Err(e) => eprintln!(
+                "Warning: Ignoring extra certs from `{}`, load failed: {}",
+                path.to_string_lossy(),
+                e
+            ),
+        }
+    }
+    for pem in &pems {
+        let certs = reqwest::Certificate::from_pem_bundle(pem.as_bytes())
+            .map_err(|e| format!("Invalid CA certificate: {}", e))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if !options.reject_unauthorized {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    let client = builder.build().map_err(|e| e.to_string())?;
+    clients.insert(options.clone(), client.clone());
+    Ok(client)
+}
+
+/// Proxies named by `HTTP_PROXY`/`HTTPS_PROXY`, skipping `NO_PROXY` hosts
+fn env_proxies() -> Result<Vec<reqwest::Proxy>, String> {
+    let var = |name: &str| {
+        std::env::var(name.to_lowercase())
+            .or_else(|_| std::env::var(name))
+            .ok()
+            .filter(|value| !value.is_empty())
+    };
+    let no_proxy = reqwest::NoProxy::from_env();
+
+    let mut proxies = Vec::new();
+    if let Some(url) = var("HTTP_PROXY") {
+        let proxy = reqwest::Proxy::http(&url).map_err(|e| format!("Invalid HTTP_PROXY: {}", e))?;
+        proxies.push(proxy.no_proxy(no_proxy.clone()));
+    }
+    if let Some(url) = var("HTTPS_PROXY") {
+        let proxy =
+            reqwest::Proxy::https(&url).map_err(|e| format!("Invalid HTTPS_PROXY: {}", e))?;
+        proxies.push(proxy.no_proxy(no_proxy));
+    }
+    Ok(proxies)
+}
+
+/// An error with its chain of causes, e.g. "error sending request: connection refused"
+fn describe(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// Send `request` and queue its response for JavaScript
+async fn run(job: Arc<FetchJob>, client: reqwest::Client, request: reqwest::Request) {
+    let mut response = match client.execute(request).await {
+        Ok(response) => response,
+        Err(e) => {
+            job.push(FetchEvent::Error(describe(&e)));
+            return;
+        }
+    };
+
+    let status = response.status();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+    job.push(FetchEvent::Head {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or("").to_string(),
+        headers,
+    });
+
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                job.push(FetchEvent::Chunk(chunk.to_vec()));
+                while job.buffered.load(Ordering::SeqCst) >= HIGH_WATER_MARK {
+                    job.drained.notified().await;
+                }
+            }
+            Ok(None) => {
+                job.push(FetchEvent::End);
+                return;
+            }
+            Err(e) => {
+                job.push(FetchEvent::Error(describe(&e)));
+                return;
+            }
+        }
+    }
+}
+
+fn get_job(id: u32) -> Option<Arc<FetchJob>> {
+    REQUESTS.lock().unwrap().as_ref()?.get(&id).cloned()
+}
+
+fn remove_job(id: u32) -> Option<Arc<FetchJob>> {
+    REQUESTS.lock().unwrap().as_mut()?.remove(&id)
+}
+
+fn type_error(message: impl Into<String>) -> boa_engine::JsError {
+    JsNativeError::typ().with_message(message.into()).into()
+}
+
+fn get_string(object: &JsObject, key: &str, context: &mut Context) -> JsResult<Option<String>> {
+    let value = object.get(js_string!(key), context)?;
+    if value.is_null_or_undefined() {
+        return Ok(None);
+    }
+    Ok(Some(value.to_string(context)?.to_std_string_escaped()))
+}
+
+fn uint8_array_bytes(value: &JsValue, context: &mut Context) -> JsResult<Vec<u8>> {
+    let array = value
+        .as_object()
+        .and_then(|obj| JsUint8Array::from_object(obj.clone()).ok())
+        .ok_or_else(|| type_error("Expected Uint8Array"))?;
+    let len = array.length(context)?;
+    let mut bytes = Vec::with_capacity(len);
+    for i in 0..len {
+        bytes.push(array.get(i, context)?.to_u32(context)? as u8);
+    }
+    Ok(bytes)
+}
+
+/// Read the agent options off a request description
+fn client_options(request: &JsObject, context: &mut Context) -> JsResult<ClientOptions> {
+    let keep_alive = request.get(js_string!("keepAlive"), context)?;
+    let max_free_sockets = request.get(js_string!("maxFreeSockets"), context)?;
+    let reject_unauthorized = request.get(js_string!("rejectUnauthorized"), context)?;
+
+    let mut ca = Vec::new();
+    let ca_value = request.get(js_string!("ca"), context)?;
+    if let Some(list) = ca_value
+        .as_object()
+        .and_then(|o| JsArray::from_object(o.clone()).ok())
+    {
+        for i in 0..list.length(context)? {
+            ca.push(
+                list.get(i, context)?
+                    .to_string(context)?
+                    .to_std_string_escaped(),
+            );
+        }
+    }
+
+    Ok(ClientOptions {
+        keep_alive: keep_alive.is_undefined() || keep_alive.to_boolean(),
+        max_free_sockets: if max_free_sockets.is_undefined() {
+            256
+        } else {
+            max_free_sockets.to_u32(context)? as usize
+        },
+        ca,
+        reject_unauthorized: reject_unauthorized.is_undefined() || reject_unauthorized.to_boolean(),
+    })
+}
+
+/// Build the reqwest request, returning the upload sender for streaming bodies
+fn build_request(
+    client: &reqwest::Client,
+    request: &JsObject,
+    context: &mut Context,
+) -> JsResult<(reqwest::Request, Option<mpsc::UnboundedSender<Vec<u8>>>)> {
+    let url = get_string(request, "url", context)?.ok_or_else(|| type_error("Missing URL"))?;
+    let url = reqwest::Url::parse(&url).map_err(|e| type_error(format!("Invalid URL: {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(type_error(format!(
+            "fetch failed: unsupported scheme {}",
+            url.scheme()
+        )));
+    }
+    // Every hop starts here, so redirects are checked too
+    permissions::check_net(
+        context,
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default(),
+    )?;
+    let method = get_string(request, "method", context)?.unwrap_or_else(|| "GET".to_string());
+    let method = reqwest::Method::from_bytes(method.as_bytes())
+        .map_err(|_| type_error(format!("Invalid method: {}", method)))?;
+
+    let mut builder = client.request(method, url);
+
+    // [[name, value], ...]
+    let headers = request.get(js_string!("headers"), context)?;
+    if let Some(list) = headers
+        .as_object()
+        .and_then(|o| JsArray::from_object(o.clone()).ok())
+    {
+        for i in 0..list.length(context)? {
+            let pair = list.get(i, context)?;
+            let pair = pair
+                .as_object()
+                .ok_or_else(|| type_error("Invalid header"))?
+                .clone();
+            let name = pair
+                .get(0, context)?
+                .to_string(context)?
+                .to_std_string_escaped();
+            let value = pair
+                .get(1, context)?
+                .to_string(context)?
+                .to_std_string_escaped();
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| type_error(format!("Invalid header name: {}", name)))?;
+            let value = reqwest::header::HeaderValue::from_str(&value)
+                .map_err(|_| type_error(format!("Invalid value for header {}", name)))?;
+            builder = builder.header(name, value);
+        }
+    }
+
+    let mut upload = None;
+    let body = request.get(js_string!("body"), context)?;
+    if request.get(js_string!("stream"), context)?.to_boolean() {
+        let (tx, rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        let chunks = futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv()
+                .await
+                .map(|chunk| (Ok::<_, std::io::Error>(chunk), rx))
+        });
+        builder = builder.body(reqwest::Body::wrap_stream(chunks));
+        upload = Some(tx);
+    } else if !body.is_null_or_undefined() {
+        builder = builder.body(uint8_array_bytes(&body, context)?);
+    }
+
+    let request = builder
+        .build()
+        .map_err(|e| type_error(format!("Invalid request: {}", e)))?;
+    Ok((request, upload))
+}
+
+/// Convert a queued event to `{ type, ... }`
+fn event_to_js(event: FetchEvent, context: &mut Context) -> JsResult<JsValue> {
+    let object = match event {
+        FetchEvent::Head {
+            status,
+            status_text,
+            headers,
+        } => {
+            let list = JsArray::new(context);
+            for (name, value) in headers {
+                let pair = JsArray::new(context);
+                pair.push(js_string!(name), context)?;
+                pair.push(js_string!(value), context)?;
+                list.push(pair, context)?;
+            }
+            ObjectInitializer::new(context)
+                .property(js_string!("type"), js_string!("head"), Default::default())
+                .property(js_string!("status"), status, Default::default())
+                .property(
+                    js_string!("statusText"),
+                    js_string!(status_text),
+                    Default::default(),
+                )
+                .property(js_string!("headers"), list, Default::default())
+                .build()
+        }
+        FetchEvent::Chunk(chunk) => {
+            let bytes = JsUint8Array::from_iter(chunk, context)?;
+            ObjectInitializer::new(context)
+                .property(js_string!("type"), js_string!("chunk"), Default::default())
+                .property(js_string!("bytes"), bytes, Default::default())
+                .build()
+        }
+        FetchEvent::End => ObjectInitializer::new(context)
+            .property(js_string!("type"), js_string!("end"), Default::default())
+            .build(),
+        FetchEvent::Error(message) => ObjectInitializer::new(context)
+            .property(js_string!("type"), js_string!("error"), Default::default())
+            .property(
+                js_string!("message"),
+                js_string!(message),
+                Default::default(),
+            )
+            .build(),
+    };
+    Ok(object.into())
+}
+
+/// Register the fetch natives and the global `fetch`
+pub fn register_fetch(context: &mut Context) -> JsResult<()> {
+    // __viper_fetch_start(request, source) -> id
+    let start_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let request = args
+            .get_or_undefined(0)
+            .as_object()
+            .ok_or_else(|| type_error("Missing request"))?
+            .clone();
+        let source = args.get_or_undefined(1).to_u32(context)?;
+
+        let options = client_options(&request, context)?;
+        let client = client(&options).map_err(type_error)?;
+        let (http_request, upload) = build_request(&client, &request, context)?;
+
+        let job = Arc::new(FetchJob {
+            events: Mutex::new(VecDeque::new()),
+            buffered: AtomicUsize::new(0),
+            drained: Notify::new(),
+            notifier: event_loop::current(context)?.notifier(source),
+            upload: Mutex::new(upload),
+            task: Mutex::new(None),
+        });
+        let id = REQUEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        REQUESTS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(id, Arc::clone(&job));
+
+        let task = runtime().spawn(run(Arc::clone(&job), client, http_request));
+        *job.task.lock().unwrap() = Some(task.abort_handle());
+        Ok(JsValue::from(id))
+    });
+    context.register_global_callable(js_string!("__viper_fetch_start"), 2, start_fn)?;
+
+    // __viper_fetch_write(id, chunk | null) - null ends the upload
+    let write_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_u32(context)?;
+        let chunk = args.get_or_undefined(1);
+        let Some(job) = get_job(id) else {
+            return Ok(JsValue::from(false));
+        };
+        let mut upload = job.upload.lock().unwrap();
+        if chunk.is_null_or_undefined() {
+            upload.take();
+            return Ok(JsValue::from(true));
+        }
+        let bytes = uint8_array_bytes(chunk, context)?;
+        Ok(JsValue::from(
+            upload.as_ref().is_some_and(|tx| tx.send(bytes).is_ok()),
+        ))
+    });
+    context.register_global_callable(js_string!("__viper_fetch_write"), 2, write_fn)?;
+
+    // __viper_fetch_next(id) -> event or undefined; the request is
+    // forgotten once its last event is taken
+    let next_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_u32(context)?;
+        let Some(event) = get_job(id).and_then(|job| job.next()) else {
+            return Ok(JsValue::undefined());
+        };
+        if matches!(event, FetchEvent::End | FetchEvent::Error(_)) {
+            remove_job(id);
+        }
+        event_to_js(event, context)
+    });
+    context.register_global_callable(js_string!("__viper_fetch_next"), 1, next_fn)?;
+
+    // __viper_fetch_abort(id)
+    let abort_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_u32(context)?;
+        if let Some(job) = remove_job(id) {
+            job.upload.lock().unwrap().take();
+            if let Some(task) = job.task.lock().unwrap().take() {
+                task.abort();
+            }
+        }
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_fetch_abort"), 1, abort_fn)?;
+
+    let fetch_code = include_str!("fetch_module.js");
+    context.eval(Source::from_bytes(fetch_code.as_bytes()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    /// Serve a few fixed routes on a local port for the fetch tests
+    #[cfg(feature = "server")]
+    fn spawn_http_server() -> u16 {
+        use bytes::Bytes;
+        use http_body_util::{BodyExt, Full};
+        use hyper::{Request, Response, body::Incoming};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            rt.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    tokio::spawn(async move {
+                        let service =
+                            hyper::service::service_fn(|req: Request<Incoming>| async move {
+                                let response = match req.uri().path() {
+                                    "/redirect" => Response::builder()
+                                        .status(302)
+                                        .header("location", "/hello")
+                                        .body(Full::new(Bytes::new())),
+                                    "/elsewhere" => Response::builder()
+                                        .status(302)
+                                        .header("location", "http://localhost/hello")
+                                        .body(Full::new(Bytes::new())),
+                                    "/temporary" => Response::builder()
+                                        .status(307)
+                                        .header("location", "/echo")
+                                        .body(Full::new(Bytes::new())),
+                                    "/loop" => Response::builder()
+                                        .status(302)
+                                        .header("location", "/loop")
+                                        .body(Full::new(Bytes::new())),
+                                    "/ftp" => Response::builder()
+                                        .status(302)
+                                        .header("location", "ftp://127.0.0.1/hello")
+                                        .body(Full::new(Bytes::new())),
+                                    "/echo" => {
+                                        let body = req.collect().await?.to_bytes();
+                                        Response::builder().body(Full::new(body))
+                                    }
+                                    "/big" => Response::builder()
+                                        .body(Full::new(Bytes::from(vec![b'a'; 1 << 20]))),
+                                    _ => Response::builder()
+                                        .header("x-test", "1")
+                                        .body(Full::new(Bytes::from("hello world"))),
+                                };
+                                Ok::<_, hyper::Error>(response.unwrap())
+                            });
+                        let io = hyper_util::rt::TokioIo::new(stream);
+                        let _ = hyper::server::conn::http1::Builder::new()
+                            .serve_connection(io, service)
+                            .await;
+                    });
+                }
+            });
+        });
+        port
+    }
+
+    /// Run `code` as a module with `base` set to the test server's URL and
+    /// read back `globalThis.seen`
+    fn seen_with(runtime: &mut Runtime, port: u16, code: &str) -> String {
+        let code = format!("const base = 'http://127.0.0.1:{port}';\n{code}");
+        runtime.run(&code, "main.mjs").unwrap();
+        let seen = runtime.eval("String(globalThis.seen)", "check.js").unwrap();
+        runtime.value_to_string(&seen)
+    }
+
+    fn seen(port: u16, code: &str) -> String {
+        seen_with(&mut Runtime::new().unwrap(), port, code)
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_fetch_follows_redirects() {
+        let port = spawn_http_server();
+        let code = r#"
+            const response = await fetch(base + '/redirect');
+            const manual = await fetch(base + '/redirect', { redirect: 'manual' });
+            globalThis.seen = [
+                response.redirected,
+                response.url === base + '/hello',
+                await response.text(),
+                manual.status,
+                manual.headers.get('location'),
+            ];
+        "#;
+        assert_eq!(seen(port, code), "true,true,hello world,302,/hello");
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_fetch_redirect_errors() {
+        let port = spawn_http_server();
+        let code = r#"
+            const fail = (url, init) => fetch(base + url, init).then(() => 'ok', (e) => e.name + ':' + e.cause.message);
+            globalThis.seen = [
+                await fail('/redirect', { redirect: 'error' }),
+                await fail('/loop'),
+                await fail('/ftp'),
+            ];
+        "#;
+        assert_eq!(
+            seen(port, code),
+            "TypeError:unexpected redirect,\
+             TypeError:redirect count exceeded,\
+             TypeError:redirect to unsupported scheme ftp:"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_fetch_streams_response_body() {
+        let port = spawn_http_server();
+        let code = r#"
+            let chunks = 0;
+            let size = 0;
+            for await (const chunk of (await fetch(base + '/big')).body) {
+                chunks++;
+                size += chunk.byteLength;
+            }
+            globalThis.seen = [chunks > 1, size];
+        "#;
+        assert_eq!(seen(port, code), "true,1048576");
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_fetch_streams_request_body() {
+        let port = spawn_http_server();
+        let code = r#"
+            const upload = new ReadableStream({
+                start(c) {
+                    c.enqueue(new TextEncoder().encode('up'));
+                    c.enqueue(new TextEncoder().encode('load'));
+                    c.close();
+                },
+            });
+            const echo = await fetch(base + '/echo', { method: 'POST', body: upload, duplex: 'half' });
+            const post = (url, body) => fetch(base + url, { method: 'POST', body, duplex: 'half' });
+            // A 302 turns the POST into a GET without a body; a 307 would need it again
+            const switched = await post('/redirect', new ReadableStream()).then((r) => r.text());
+            const resent = await post('/temporary', new ReadableStream())
+                .then(() => 'ok', (e) => e.cause.message);
+            globalThis.seen = [await echo.text(), switched, resent];
+        "#;
+        assert_eq!(
+            seen(port, code),
+            "upload,hello world,cannot follow a redirect with a streamed body"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_fetch_abort_reasons() {
+        let port = spawn_http_server();
+        let code = r#"
+            const settle = (promise) => promise.then(() => 'ok', (e) => e instanceof Error ? e.name : e);
+
+            const aborted = await settle(fetch(base, { signal: AbortSignal.abort() }));
+            const custom = await settle(fetch(base, { signal: AbortSignal.abort('why') }));
+
+            const controller = new AbortController();
+            const response = await fetch(base + '/big', { signal: controller.signal });
+            const reader = response.body.getReader();
+            await reader.read();
+            controller.abort('midway');
+            let body;
+            try { while (!(await reader.read()).done); body = 'ok'; } catch (e) { body = e; }
+
+            globalThis.seen = [aborted, custom, body];
+        "#;
+        assert_eq!(seen(port, code), "AbortError,why,midway");
+    }
+
+    #[test]
+    fn test_fetch_network_error() {
+        // A port nothing listens on once the listener is dropped
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let code = r#"
+            globalThis.seen = [await fetch(base).then(() => 'ok', (e) => e.name + ':' + e.message)];
+        "#;
+        assert_eq!(seen(port, code), "TypeError:fetch failed");
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_http_get() {
+        let port = spawn_http_server();
+        let code = r#"
+            import http from 'node:http';
+            globalThis.seen = [await new Promise((resolve) => {
+                http.get(base + '/hello', (res) => {
+                    let body = '';
+                    res.setEncoding('utf8');
+                    res.on('data', (chunk) => body += chunk);
+                    res.on('end', () => resolve(res.statusCode + ':' + res.headers['x-test'] + ':' + body));
+                });
+            })];
+        "#;
+        assert_eq!(seen(port, code), "200:1:hello world");
+    }
+
+    /// A runtime that may only reach the test server by its IP address
+    #[cfg(feature = "server")]
+    fn net_limited(port: u16) -> Runtime {
+        use crate::runtime::{Allow, Permissions, RuntimeConfig};
+
+        let config = RuntimeConfig {
+            permissions: Permissions {
+                net: Allow::Only(vec![format!("127.0.0.1:{port}")]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        Runtime::with_config(config).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_fetch_net_permission() {
+        let port = spawn_http_server();
+        let code = r#"
+            const denied = (e) => e.code + ' ' + e.resource;
+            globalThis.seen = [
+                await (await fetch(base + '/hello')).text(),
+                await fetch(base.replace('127.0.0.1', 'localhost')).then(() => 'ok', denied),
+            ];
+        "#;
+        assert_eq!(
+            seen_with(&mut net_limited(port), port, code),
+            format!("hello world,ERR_ACCESS_DENIED localhost:{port}")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_fetch_redirect_permission() {
+        let port = spawn_http_server();
+        // The redirect target is checked, not just the URL fetch() was given
+        let code = r#"
+            const denied = (e) => e.code + ' ' + e.resource;
+            globalThis.seen = [await fetch(base + '/elsewhere').then(() => 'ok', denied)];
+        "#;
+        assert_eq!(
+            seen_with(&mut net_limited(port), port, code),
+            "ERR_ACCESS_DENIED localhost:80"
+        );
+    }
+}
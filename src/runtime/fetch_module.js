@@ -0,0 +1,312 @@
+/**
+ * fetch() over the `__viper_fetch_*` natives
+ *
+ * Redirects are followed here; each hop is a new `__viper_fetch_start`,
+ * which checks its URL against the net permission. Request bodies given as a
+ * ReadableStream are uploaded as they are read, and the Response body is a
+ * byte stream pulled from the network on demand. `init.signal` cancels the
+ * request, or the body once the response has arrived; `init.dispatcher`
+ * (an `http.Agent`) picks the connection pool.
+ */
+(function () {
+  "use strict";
+
+  const REDIRECT_STATUSES = new Set([301, 302, 303, 307, 308]);
+  const MAX_REDIRECTS = 20;
+  const NORMALIZED_METHODS = new Set(["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"]);
+  const NULL_BODY_STATUSES = new Set([101, 103, 204, 205, 304]);
+
+  function networkError(message) {
+    return new TypeError("fetch failed", { cause: new Error(message) });
+  }
+
+  function toUint8Array(chunk) {
+    if (typeof chunk === "string") return new TextEncoder().encode(chunk);
+    if (chunk instanceof ArrayBuffer) return new Uint8Array(chunk);
+    return new Uint8Array(chunk.buffer, chunk.byteOffset, chunk.byteLength);
+  }
+
+  // One request on the wire; events are taken one at a time as they are wanted
+  class Exchange {
+    constructor(description) {
+      this.waiting = null;
+      this.failure = null;
+      this.done = false;
+      this.source = __viper_loop_source(() => this.wake());
+      // Only a pending next() keeps the process alive
+      __viper_loop_ref(this.source, false);
+      try {
+        this.id = __viper_fetch_start(description, this.source);
+      } catch (err) {
+        __viper_loop_close(this.source);
+        throw err;
+      }
+    }
+
+    next() {
+      if (this.failure !== null) return Promise.reject(this.failure);
+      const event = this.take();
+      if (event !== undefined) return Promise.resolve(event);
+      __viper_loop_ref(this.source, true);
+      return new Promise((resolve, reject) => {
+        this.waiting = { resolve, reject };
+      });
+    }
+
+    take() {
+      const event = __viper_fetch_next(this.id);
+      if (event !== undefined && (event.type === "end" || event.type === "error")) this.close();
+      return event;
+    }
+
+    wake() {
+      if (this.waiting === null) return;
+      const event = this.take();
+      if (event === undefined) return;
+      const { resolve } = this.waiting;
+      this.waiting = null;
+      if (!this.done) __viper_loop_ref(this.source, false);
+      resolve(event);
+    }
+
+    write(chunk) {
+      return !this.done && __viper_fetch_write(this.id, chunk);
+    }
+
+    abort(reason) {
+      if (this.done) return;
+      __viper_fetch_abort(this.id);
+      this.close();
+      this.failure = reason;
+      if (this.waiting !== null) {
+        const { reject } = this.waiting;
+        this.waiting = null;
+        reject(reason);
+      }
+    }
+
+    close() {
+      if (this.done) return;
+      this.done = true;
+      __viper_loop_close(this.source);
+    }
+  }
+
+  // Feed a streamed request body to the exchange as it is produced
+  async function pump(stream, exchange) {
+    const reader = stream.getReader();
+    try {
+      for (;;) {
+        const { value, done } = await reader.read();
+        if (done) {
+          exchange.write(null);
+          return;
+        }
+        if (!exchange.write(toUint8Array(value))) {
+          reader.cancel().catch(() => {});
+          return;
+        }
+      }
+    } catch (err) {
+      exchange.abort(err);
+    }
+  }
+
+  // Read the rest of a response nobody will see so its connection is reused
+  function drain(exchange) {
+    exchange.next().then((event) => {
+      if (event.type === "chunk") drain(exchange);
+    }, () => {});
+  }
+
+  function isRequest(input) {
+    return typeof input === "object" && input !== null && !(input instanceof URL) &&
+      typeof input.url === "string" && "method" in input;
+  }
+
+  function normalizeMethod(method) {
+    const upper = String(method).toUpperCase();
+    return NORMALIZED_METHODS.has(upper) ? upper : String(method);
+  }
+
+  function setDefault(headers, name, value) {
+    if (!headers.has(name)) headers.set(name, value);
+  }
+
+  // Bytes (or a stream) to send for `body`, filling in its content type
+  async function extractBody(body, headers) {
+    if (body === null || body === undefined) return { bytes: null, stream: null };
+    if (__viper_is_readable_stream(body)) return { bytes: null, stream: body };
+    if (typeof body === "string") {
+      setDefault(headers, "content-type", "text/plain;charset=UTF-8");
+      return { bytes: new TextEncoder().encode(body), stream: null };
+    }
+    // FormData (with a fresh multipart boundary), Blob and URLSearchParams
+    const encoded = __viper_encode_body(body);
+    if (encoded !== null) {
+      if (encoded.type) setDefault(headers, "content-type", encoded.type);
+      return { bytes: encoded.bytes, stream: null };
+    }
+    if (body instanceof ArrayBuffer || ArrayBuffer.isView(body)) {
+      return { bytes: toUint8Array(body).slice(), stream: null };
+    }
+    setDefault(headers, "content-type", "text/plain;charset=UTF-8");
+    return { bytes: new TextEncoder().encode(String(body)), stream: null };
+  }
+
+  // The pool options of an http.Agent
+  function agentOptions(agent) {
+    if (agent === undefined || agent === null) return {};
+    const options = agent.options ?? {};
+    const ca = options.ca === undefined ? [] : [].concat(options.ca).map((cert) =>
+      ArrayBuffer.isView(cert) ? new TextDecoder().decode(cert) : String(cert)
+    );
+    return {
+      keepAlive: agent.keepAlive ?? Boolean(options.keepAlive),
+      maxFreeSockets: agent.maxFreeSockets ?? options.maxFreeSockets,
+      ca,
+      rejectUnauthorized: options.rejectUnauthorized !== false,
+    };
+  }
+
+  function bodyStream(exchange, finish) {
+    return new ReadableStream({
+      type: "bytes",
+      pull(controller) {
+        return exchange.next().then(
+          (event) => {
+            if (event.type === "chunk") {
+              controller.enqueue(event.bytes);
+              return;
+            }
+            finish();
+            if (event.type === "end") {
+              controller.close();
+              const request = controller.byobRequest;
+              if (request) request.respond(0);
+            } else {
+              controller.error(new TypeError("terminated", { cause: new Error(event.message) }));
+            }
+          },
+          (reason) => {
+            finish();
+            controller.error(reason);
+          },
+        );
+      },
+      cancel(reason) {
+        finish();
+        exchange.abort(reason);
+      },
+    });
+  }
+
+  async function fetch(input, init = {}) {
+    init = init ?? {};
+    const fromRequest = isRequest(input);
+    let url = fromRequest ? input.url : String(input);
+    let method = normalizeMethod(init.method ?? (fromRequest ? input.method : "GET"));
+    const headers = new Headers(init.headers ?? (fromRequest ? input.headers : undefined));
+    const signal = init.signal ?? (fromRequest ? input.signal : undefined) ?? null;
+    const redirect = init.redirect ?? (fromRequest ? input.redirect : undefined) ?? "follow";
+    const agent = agentOptions(init.dispatcher ?? init.agent);
+
+    if (signal?.aborted) throw signal.reason;
+    if ((method === "GET" || method === "HEAD") &&
+        init.body !== undefined && init.body !== null) {
+      throw new TypeError("Request with GET/HEAD method cannot have body.");
+    }
+
+    let body = await extractBody(init.body !== undefined ? init.body : fromRequest ? input._body : null, headers);
+    setDefault(headers, "accept", "*/*");
+
+    let redirected = false;
+    for (let hops = 0; ; hops++) {
+      if (signal?.aborted) throw signal.reason;
+
+      const exchange = new Exchange({
+        url,
+        method,
+        headers: Array.from(headers.entries()),
+        body: body.bytes,
+        stream: body.stream !== null,
+        ...agent,
+      });
+      const onAbort = () => exchange.abort(signal.reason);
+      signal?.addEventListener("abort", onAbort, { once: true });
+      const finish = () => signal?.removeEventListener("abort", onAbort);
+      if (body.stream !== null) pump(body.stream, exchange);
+
+      let head;
+      try {
+        head = await exchange.next();
+      } catch (err) {
+        finish();
+        throw err;
+      }
+      if (head.type === "error") {
+        finish();
+        throw networkError(head.message);
+      }
+
+      const location = REDIRECT_STATUSES.has(head.status)
+        ? head.headers.find(([name]) => name === "location")?.[1]
+        : undefined;
+      if (location !== undefined && redirect !== "manual") {
+        finish();
+        drain(exchange);
+        if (redirect === "error") throw networkError("unexpected redirect");
+        if (hops >= MAX_REDIRECTS) throw networkError("redirect count exceeded");
+
+        const target = new URL(location, url);
+        if (target.protocol !== "http:" && target.protocol !== "https:") {
+          throw networkError(`redirect to unsupported scheme ${target.protocol}`);
+        }
+        const toGet = (head.status === 303 && method !== "GET" && method !== "HEAD") ||
+          ((head.status === 301 || head.status === 302) && method === "POST");
+        if (toGet) {
+          method = "GET";
+          body = { bytes: null, stream: null };
+          for (const name of ["content-encoding", "content-language", "content-location", "content-type", "content-length"]) {
+            headers.delete(name);
+          }
+        } else if (body.stream !== null) {
+          throw networkError("cannot follow a redirect with a streamed body");
+        }
+        if (target.origin !== new URL(url).origin) headers.delete("authorization");
+        url = target.href;
+        redirected = true;
+        continue;
+      }
+
+      let responseBody = null;
+      if (method === "HEAD" || NULL_BODY_STATUSES.has(head.status)) {
+        finish();
+        drain(exchange);
+      } else {
+        responseBody = bodyStream(exchange, finish);
+      }
+
+      const responseHeaders = new Headers();
+      for (const [name, value] of head.headers) responseHeaders.append(name, value);
+      const response = new Response(responseBody, {
+        status: head.status,
+        statusText: head.statusText,
+        headers: responseHeaders,
+      });
+      Object.defineProperties(response, {
+        url: { value: url, enumerable: true },
+        redirected: { value: redirected, enumerable: true },
+        type: { value: "basic", enumerable: true },
+      });
+      return response;
+    }
+  }
+
+  Object.defineProperty(globalThis, "fetch", {
+    value: fetch,
+    writable: true,
+    configurable: true,
+    enumerable: false,
+  });
+})();
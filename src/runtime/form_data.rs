@@ -0,0 +1,115 @@
+//! Blob, File and FormData
+//!
+//! Implementation of the File API's `Blob` and `File` and the XHR Standard's
+//! `FormData`, plus the multipart and urlencoded encoding and parsing behind
+//! `formData()` and FormData request bodies in Request, Response and fetch.
+
+use boa_engine::{Context, JsResult, Source};
+
+/// Register the Blob, File and FormData globals
+pub fn register_form_data(context: &mut Context) -> JsResult<()> {
+    let form_data_code = include_str!("form_data_module.js");
+    let source = Source::from_bytes(form_data_code.as_bytes());
+    context.eval(source)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::seen;
+
+    #[test]
+    fn test_blob() {
+        let code = r#"
+            const blob = new Blob(['hello ', new TextEncoder().encode('world')], { type: 'Text/Plain' });
+            let streamed = 0;
+            for await (const chunk of blob.stream()) streamed += chunk.byteLength;
+            globalThis.seen = [
+                blob.size,
+                blob.type,
+                await blob.slice(6).text(),
+                await blob.slice(-5, -3).text(),
+                streamed,
+                (await blob.arrayBuffer()).byteLength,
+            ];
+        "#;
+        assert_eq!(seen(code), "11,text/plain,world,wo,11,11");
+    }
+
+    #[test]
+    fn test_blob_and_file_errors() {
+        let code = r#"
+            const fail = (make) => { try { make(); return 'ok'; } catch (e) { return e.name; } };
+            globalThis.seen = [
+                fail(() => new Blob(42)),
+                fail(() => new Blob([], { endings: 'crlf' })),
+                fail(() => new File(['abc'])),
+                fail(() => new FormData({})),
+            ];
+        "#;
+        assert_eq!(seen(code), "TypeError,TypeError,TypeError,TypeError");
+    }
+
+    #[test]
+    fn test_multipart_round_trip() {
+        let code = r#"
+            const form = new FormData();
+            form.append('name', 'viper');
+            form.append('tag', 'a');
+            form.append('tag', 'b');
+            form.append('file', new File(['abc'], 'a.txt', { type: 'text/plain' }));
+            const response = new Response(form);
+            const type = response.headers.get('content-type');
+            const parsed = await response.formData();
+            const file = parsed.get('file');
+            globalThis.seen = [
+                type.startsWith('multipart/form-data; boundary='),
+                parsed.get('name'),
+                parsed.getAll('tag').join('+'),
+                file instanceof File,
+                file.name + ':' + file.type + ':' + await file.text(),
+            ];
+        "#;
+        assert_eq!(seen(code), "true,viper,a+b,true,a.txt:text/plain:abc");
+    }
+
+    #[test]
+    fn test_urlencoded_body() {
+        let code = r#"
+            const encoded = await new Request('http://localhost/', {
+                method: 'POST',
+                body: new URLSearchParams({ a: '1 2', b: 'x' }),
+            }).formData();
+            const raw = await new Response('c=%C3%A9&c=d', {
+                headers: { 'content-type': 'application/x-www-form-urlencoded; charset=utf-8' },
+            }).formData();
+            globalThis.seen = [encoded.get('a') + '|' + encoded.get('b'), raw.getAll('c').join('+')];
+        "#;
+        assert_eq!(seen(code), "1 2|x,é+d");
+    }
+
+    #[test]
+    fn test_malformed_multipart() {
+        let code = r#"
+            const parse = (body, type = 'multipart/form-data; boundary=X') =>
+                new Response(body, { headers: { 'content-type': type } })
+                    .formData()
+                    .then((form) => [...form.keys()].join('+') || 'empty', (e) => e.name);
+            const part = 'Content-Disposition: form-data; name="a"\r\n\r\n1';
+            globalThis.seen = [
+                await parse(`--X\r\n${part}\r\n--X--`),
+                await parse(`--X\r\n${part}\r\n--X--`, 'multipart/form-data'),
+                await parse(`${part}\r\n--X--`, 'multipart/form-data; boundary=Y'),
+                await parse(`--X${part}\r\n--X--`),
+                await parse(`--X\r\n${part}`),
+                await parse(`--X\r\nContent-Disposition: form-data; name="a"\r\n1\r\n--X--`),
+                await parse(`--X\r\nContent-Disposition: attachment\r\n\r\n1\r\n--X--`),
+                await parse('a=1', 'text/plain'),
+            ];
+        "#;
+        assert_eq!(
+            seen(code),
+            "a,TypeError,TypeError,TypeError,TypeError,TypeError,TypeError,TypeError"
+        );
+    }
+}
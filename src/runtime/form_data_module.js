@@ -0,0 +1,401 @@
+/**
+ * Blob, File and FormData
+ *
+ * Blobs keep their bytes in one Uint8Array under `kState`. FormData entries
+ * are strings or Files, as in the XHR Standard.
+ *
+ * Also installs the helpers Request, Response and fetch() use for these
+ * bodies: `__viper_encode_body(body)` turns a FormData, Blob or
+ * URLSearchParams into `{ bytes, type }` (multipart with a fresh boundary
+ * for FormData), `__viper_parse_form_data(bytes, contentType)` reads a
+ * `multipart/form-data` or `application/x-www-form-urlencoded` body, and
+ * `__viper_blob_bytes(blob)` gives a Blob's bytes synchronously.
+ */
+(function () {
+  "use strict";
+
+  const kState = Symbol("viper.blob.state");
+  const encoder = new TextEncoder();
+  const decoder = new TextDecoder();
+
+  // The global TextDecoder decodes the whole buffer behind a view; hand it a copy
+  function decode(bytes) {
+    return decoder.decode(bytes.slice());
+  }
+
+  // ==========================================================================
+  // Blob and File
+  // ==========================================================================
+
+  function concatBytes(chunks) {
+    let length = 0;
+    for (const chunk of chunks) length += chunk.byteLength;
+    const bytes = new Uint8Array(length);
+    let offset = 0;
+    for (const chunk of chunks) {
+      bytes.set(chunk, offset);
+      offset += chunk.byteLength;
+    }
+    return bytes;
+  }
+
+  function partBytes(part, endings) {
+    if (part instanceof Blob) return part[kState].bytes;
+    if (part instanceof ArrayBuffer) return new Uint8Array(part);
+    if (ArrayBuffer.isView(part)) {
+      return new Uint8Array(part.buffer, part.byteOffset, part.byteLength);
+    }
+    let text = String(part);
+    if (endings === "native") text = text.replace(/\r\n|\r/g, "\n");
+    return encoder.encode(text);
+  }
+
+  function normalizeType(type) {
+    type = type === undefined ? "" : String(type);
+    return /^[\x20-\x7e]*$/.test(type) ? type.toLowerCase() : "";
+  }
+
+  function relativeIndex(index, size) {
+    if (index === undefined) return undefined;
+    index = Math.trunc(Number(index)) || 0;
+    return index < 0 ? Math.max(size + index, 0) : Math.min(index, size);
+  }
+
+  class Blob {
+    constructor(blobParts = [], options = {}) {
+      if (blobParts === null || typeof blobParts !== "object" || !(Symbol.iterator in blobParts)) {
+        throw new TypeError("Failed to construct 'Blob': The provided value cannot be converted to a sequence.");
+      }
+      const endings = options?.endings ?? "transparent";
+      if (endings !== "transparent" && endings !== "native") {
+        throw new TypeError(`Failed to construct 'Blob': '${endings}' is not a valid value for endings.`);
+      }
+      const chunks = [];
+      for (const part of blobParts) chunks.push(partBytes(part, endings));
+      this[kState] = { bytes: concatBytes(chunks), type: normalizeType(options?.type) };
+    }
+
+    get size() {
+      return this[kState].bytes.byteLength;
+    }
+
+    get type() {
+      return this[kState].type;
+    }
+
+    slice(start, end, contentType) {
+      const bytes = this[kState].bytes;
+      const from = relativeIndex(start, bytes.byteLength) ?? 0;
+      const to = relativeIndex(end, bytes.byteLength) ?? bytes.byteLength;
+      const blob = new Blob([], { type: contentType });
+      blob[kState].bytes = bytes.slice(from, Math.max(from, to));
+      return blob;
+    }
+
+    stream() {
+      const bytes = this[kState].bytes;
+      let offset = 0;
+      return new ReadableStream({
+        type: "bytes",
+        pull(controller) {
+          const end = Math.min(offset + 65536, bytes.byteLength);
+          if (offset < end) controller.enqueue(bytes.slice(offset, end));
+          offset = end;
+          if (offset >= bytes.byteLength) {
+            controller.close();
+            const request = controller.byobRequest;
+            if (request) request.respond(0);
+          }
+        },
+      });
+    }
+
+    async text() {
+      return decode(this[kState].bytes);
+    }
+
+    async arrayBuffer() {
+      return this[kState].bytes.slice().buffer;
+    }
+
+    async bytes() {
+      return this[kState].bytes.slice();
+    }
+
+    get [Symbol.toStringTag]() {
+      return "Blob";
+    }
+  }
+
+  class File extends Blob {
+    constructor(fileBits, fileName, options = {}) {
+      if (arguments.length < 2) {
+        throw new TypeError("Failed to construct 'File': 2 arguments required.");
+      }
+      super(fileBits, options);
+      const lastModified = options?.lastModified;
+      this[kState].name = String(fileName);
+      this[kState].lastModified = lastModified === undefined ? Date.now() : Math.trunc(Number(lastModified));
+    }
+
+    get name() {
+      return this[kState].name;
+    }
+
+    get lastModified() {
+      return this[kState].lastModified;
+    }
+
+    get webkitRelativePath() {
+      return "";
+    }
+
+    get [Symbol.toStringTag]() {
+      return "File";
+    }
+  }
+
+  // ==========================================================================
+  // FormData
+  // ==========================================================================
+
+  // An entry value: strings stay strings, Blobs become Files
+  function entryValue(value, filename) {
+    if (!(value instanceof Blob)) return String(value);
+    if (value instanceof File && filename === undefined) return value;
+    const name = filename !== undefined ? String(filename) : value instanceof File ? value.name : "blob";
+    const file = new File([], name, {
+      type: value.type,
+      lastModified: value instanceof File ? value.lastModified : undefined,
+    });
+    file[kState].bytes = value[kState].bytes;
+    return file;
+  }
+
+  class FormData {
+    #entries = [];
+
+    constructor(form) {
+      if (form !== undefined) {
+        throw new TypeError("Failed to construct 'FormData': parameter 1 is not of type 'HTMLFormElement'.");
+      }
+    }
+
+    append(name, value, filename) {
+      this.#entries.push([String(name), entryValue(value, filename)]);
+    }
+
+    set(name, value, filename) {
+      name = String(name);
+      const entry = [name, entryValue(value, filename)];
+      const index = this.#entries.findIndex(([key]) => key === name);
+      if (index === -1) {
+        this.#entries.push(entry);
+        return;
+      }
+      this.#entries[index] = entry;
+      this.#entries = this.#entries.filter(([key], i) => i <= index || key !== name);
+    }
+
+    get(name) {
+      name = String(name);
+      const entry = this.#entries.find(([key]) => key === name);
+      return entry === undefined ? null : entry[1];
+    }
+
+    getAll(name) {
+      name = String(name);
+      return this.#entries.filter(([key]) => key === name).map(([, value]) => value);
+    }
+
+    has(name) {
+      name = String(name);
+      return this.#entries.some(([key]) => key === name);
+    }
+
+    delete(name) {
+      name = String(name);
+      this.#entries = this.#entries.filter(([key]) => key !== name);
+    }
+
+    forEach(callback, thisArg) {
+      for (const [name, value] of this) callback.call(thisArg, value, name, this);
+    }
+
+    *entries() {
+      for (let i = 0; i < this.#entries.length; i++) {
+        const [name, value] = this.#entries[i];
+        yield [name, value];
+      }
+    }
+
+    *keys() {
+      for (const [name] of this.entries()) yield name;
+    }
+
+    *values() {
+      for (const [, value] of this.entries()) yield value;
+    }
+
+    [Symbol.iterator]() {
+      return this.entries();
+    }
+
+    get [Symbol.toStringTag]() {
+      return "FormData";
+    }
+  }
+
+  // ==========================================================================
+  // multipart/form-data
+  // ==========================================================================
+
+  function escapeName(name) {
+    return name.replace(/\r\n|\r|\n/g, "\r\n").replace(/\n/g, "%0A").replace(/\r/g, "%0D").replace(/"/g, "%22");
+  }
+
+  function randomBoundary() {
+    let hex = "";
+    for (let i = 0; i < 24; i++) hex += Math.floor(Math.random() * 16).toString(16);
+    return `----ViperFormBoundary${hex}`;
+  }
+
+  function encodeMultipart(form) {
+    const boundary = randomBoundary();
+    const chunks = [];
+    for (const [name, value] of form) {
+      let head = `--${boundary}\r\nContent-Disposition: form-data; name="${escapeName(name)}"`;
+      if (typeof value === "string") {
+        chunks.push(encoder.encode(`${head}\r\n\r\n${value.replace(/\r\n|\r|\n/g, "\r\n")}\r\n`));
+        continue;
+      }
+      head += `; filename="${escapeName(value.name)}"\r\nContent-Type: ${value.type || "application/octet-stream"}\r\n\r\n`;
+      chunks.push(encoder.encode(head), value[kState].bytes, encoder.encode("\r\n"));
+    }
+    chunks.push(encoder.encode(`--${boundary}--\r\n`));
+    return { bytes: concatBytes(chunks), type: `multipart/form-data; boundary=${boundary}` };
+  }
+
+  function indexOfBytes(haystack, needle, from) {
+    const first = needle[0];
+    const last = haystack.byteLength - needle.byteLength;
+    for (let i = haystack.indexOf(first, from); i !== -1 && i <= last; i = haystack.indexOf(first, i + 1)) {
+      let match = true;
+      for (let j = 1; j < needle.byteLength; j++) {
+        if (haystack[i + j] !== needle[j]) {
+          match = false;
+          break;
+        }
+      }
+      if (match) return i;
+    }
+    return -1;
+  }
+
+  // The value of `key` in a header like `form-data; name="a"; filename="b"`
+  function headerParam(header, key) {
+    const pattern = new RegExp(`;\\s*${key}\\s*=\\s*(?:"((?:[^"\\\\]|\\\\.)*)"|([^;\\s]*))`, "i");
+    const match = pattern.exec(header);
+    if (!match) return undefined;
+    return match[1] !== undefined ? match[1].replace(/\\(.)/g, "$1") : match[2];
+  }
+
+  function parseError() {
+    return new TypeError("Could not parse content as FormData.");
+  }
+
+  function parseMultipart(bytes, boundary) {
+    const form = new FormData();
+    const delimiter = encoder.encode(`--${boundary}`);
+    const separator = encoder.encode("\r\n\r\n");
+    const nextDelimiter = encoder.encode(`\r\n--${boundary}`);
+
+    let position = indexOfBytes(bytes, delimiter, 0);
+    if (position === -1) throw parseError();
+    position += delimiter.byteLength;
+
+    for (;;) {
+      // "--" after a delimiter closes the body
+      if (bytes[position] === 0x2d && bytes[position + 1] === 0x2d) return form;
+      if (bytes[position] !== 0x0d || bytes[position + 1] !== 0x0a) throw parseError();
+      position += 2;
+
+      const headerEnd = indexOfBytes(bytes, separator, position);
+      if (headerEnd === -1) throw parseError();
+      const headers = {};
+      for (const line of decode(bytes.subarray(position, headerEnd)).split("\r\n")) {
+        const colon = line.indexOf(":");
+        if (colon > 0) headers[line.slice(0, colon).trim().toLowerCase()] = line.slice(colon + 1).trim();
+      }
+
+      const bodyStart = headerEnd + separator.byteLength;
+      const bodyEnd = indexOfBytes(bytes, nextDelimiter, bodyStart);
+      if (bodyEnd === -1) throw parseError();
+      const body = bytes.subarray(bodyStart, bodyEnd);
+
+      const disposition = headers["content-disposition"] ?? "";
+      const name = headerParam(disposition, "name");
+      if (!/^\s*form-data\s*(;|$)/i.test(disposition) || name === undefined) throw parseError();
+      const filename = headerParam(disposition, "filename");
+      if (filename === undefined) {
+        form.append(name, decode(body));
+      } else {
+        const file = new File([], filename, { type: headers["content-type"] ?? "application/octet-stream" });
+        file[kState].bytes = body.slice();
+        form.append(name, file);
+      }
+      position = bodyEnd + nextDelimiter.byteLength;
+    }
+  }
+
+  function parseFormData(bytes, contentType) {
+    const mime = String(contentType ?? "").split(";")[0].trim().toLowerCase();
+    if (mime === "multipart/form-data") {
+      const boundary = headerParam(String(contentType), "boundary");
+      if (!boundary) throw parseError();
+      return parseMultipart(bytes, boundary);
+    }
+    if (mime === "application/x-www-form-urlencoded") {
+      const form = new FormData();
+      // The native URLSearchParams has no Symbol.iterator
+      new URLSearchParams(decode(bytes)).forEach((value, name) => form.append(name, value));
+      return form;
+    }
+    throw parseError();
+  }
+
+  // Bytes and content type of a body that knows its own encoding, else null
+  function encodeBody(body) {
+    if (body instanceof FormData) return encodeMultipart(body);
+    if (body instanceof Blob) return { bytes: body[kState].bytes.slice(), type: body.type || null };
+    if (typeof URLSearchParams === "function" && body instanceof URLSearchParams) {
+      return {
+        bytes: encoder.encode(body.toString()),
+        type: "application/x-www-form-urlencoded;charset=UTF-8",
+      };
+    }
+    return null;
+  }
+
+  function blobBytes(blob) {
+    if (!(blob instanceof Blob)) throw new TypeError("Expected a Blob");
+    return blob[kState].bytes;
+  }
+
+  // ==========================================================================
+  // Exports
+  // ==========================================================================
+
+  for (const [name, value] of Object.entries({ Blob, File, FormData })) {
+    Object.defineProperty(globalThis, name, {
+      value,
+      writable: true,
+      configurable: true,
+      enumerable: false,
+    });
+  }
+
+  globalThis.__viper_encode_body = encodeBody;
+  globalThis.__viper_parse_form_data = parseFormData;
+  globalThis.__viper_blob_bytes = blobBytes;
+})();
@@ -0,0 +1,208 @@
+//! Host hooks of a runtime: rejection tracking and `import.meta`
+//!
+//! Boa takes a single `HostHooks` implementation per context. [`RuntimeHooks`]
+//! forwards promise rejection operations to the [`RejectionTracker`].
+//!
+//! `import.meta` is a module loader hook in Boa; the runtime's loader calls
+//! [`init_import_meta`] for every ES module, which fills in:
+//! - `url` - the module's `file://` URL
+//! - `filename` and `dirname` - its absolute path and directory
+//! - `main` - whether it is the entry module of the runtime
+//! - `resolve(specifier)` - the URL `import specifier` would load, resolved
+//!   synchronously with the module loader's [`ModuleResolver`]
+//! - `env` - the same object as `process.env`
+//!
+//! Modules without a path (built-ins, `host:` modules) only get `main` and
+//! `env`.
+
+use boa_engine::{
+    Context, JsNativeError, JsObject, JsResult, JsString, JsValue, Module, NativeFunction,
+    builtins::promise::OperationType, context::HostHooks, js_string,
+};
+use boa_gc::{Finalize, Trace};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use super::rejection::RejectionTracker;
+use super::{builtins, url};
+use crate::resolver::ModuleResolver;
+
+/// The `HostHooks` of every runtime context
+pub struct RuntimeHooks {
+    rejections: Rc<RejectionTracker>,
+}
+
+impl RuntimeHooks {
+    pub fn new(rejections: Rc<RejectionTracker>) -> Self {
+        Self { rejections }
+    }
+}
+
+impl HostHooks for RuntimeHooks {
+    fn promise_rejection_tracker(
+        &self,
+        promise: &JsObject,
+        operation: OperationType,
+        context: &mut Context,
+    ) {
+        self.rejections
+            .promise_rejection_tracker(promise, operation, context);
+    }
+}
+
+/// Populate `import_meta` for `module`; `main` is the entry module's path
+pub(crate) fn init_import_meta(
+    meta: &JsObject,
+    module: &Module,
+    resolver: &Rc<ModuleResolver>,
+    main: Option<&Path>,
+    context: &mut Context,
+) -> JsResult<()> {
+    let path = module.path().map(Path::to_path_buf);
+    let main = path.is_some() && main == path.as_deref();
+
+    if let Some(path) = path {
+        let filename = path.to_string_lossy().to_string();
+        let dirname = path
+            .parent()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let file_url = url::path_to_file_url_impl(&filename);
+
+        let resolve = NativeFunction::from_copy_closure_with_captures(
+            |_this, args, referrer, context| {
+                let specifier = args
+                    .first()
+                    .cloned()
+                    .unwrap_or_default()
+                    .to_string(context)?
+                    .to_std_string_escaped();
+                referrer.resolve(&specifier).map(JsValue::from)
+            },
+            Referrer {
+                path,
+                resolver: Rc::clone(resolver),
+            },
+        )
+        .to_js_function(context.realm());
+
+        meta.create_data_property_or_throw(js_string!("url"), js_string!(file_url), context)?;
+        meta.create_data_property_or_throw(js_string!("filename"), js_string!(filename), context)?;
+        meta.create_data_property_or_throw(js_string!("dirname"), js_string!(dirname), context)?;
+        meta.create_data_property_or_throw(js_string!("resolve"), resolve, context)?;
+    }
+
+    meta.create_data_property_or_throw(js_string!("main"), main, context)?;
+
+    let process = context
+        .global_object()
+        .get(js_string!("process"), context)?;
+    let env = match process.as_object() {
+        Some(process) => process.get(js_string!("env"), context)?,
+        None => JsValue::undefined(),
+    };
+    meta.create_data_property_or_throw(js_string!("env"), env, context)?;
+    Ok(())
+}
+
+/// The module `import.meta.resolve` resolves against
+#[derive(Trace, Finalize)]
+struct Referrer {
+    #[unsafe_ignore_trace]
+    path: PathBuf,
+    #[unsafe_ignore_trace]
+    resolver: Rc<ModuleResolver>,
+}
+
+impl Referrer {
+    fn resolve(&self, specifier: &str) -> JsResult<JsString> {
+        match builtins::resolve(specifier) {
+            Ok(Some(name)) => return Ok(js_string!(format!("node:{}", name))),
+            Ok(None) => {}
+            Err(message) => return Err(JsNativeError::typ().with_message(message).into()),
+        }
+        if specifier.starts_with("host:") {
+            return Ok(js_string!(specifier));
+        }
+
+        let resolved = self.resolver.resolve(specifier, &self.path).map_err(|e| {
+            JsNativeError::typ().with_message(format!(
+                "Cannot resolve '{}' from '{}': {}",
+                specifier,
+                self.path.display(),
+                e
+            ))
+        })?;
+        Ok(js_string!(url::path_to_file_url_impl(
+            &resolved.to_string_lossy()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::{Runtime, seen};
+
+    #[test]
+    fn test_import_meta_paths() {
+        let code = r#"
+            globalThis.seen = [
+                import.meta.url === 'file://' + import.meta.filename,
+                import.meta.filename === import.meta.dirname + '/main.mjs',
+                new URL('./data.json', import.meta.url).pathname === import.meta.dirname + '/data.json',
+            ];
+        "#;
+        assert_eq!(seen(code), "true,true,true");
+    }
+
+    #[test]
+    fn test_import_meta_main() {
+        let dir = std::env::temp_dir().join(format!("viper-meta-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("dep.mjs"), "export const main = import.meta.main;").unwrap();
+
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            import { main } from './dep.mjs';
+            globalThis.seen = [import.meta.main, main];
+        "#;
+        let result = runtime.run(code, &dir.join("main.mjs").to_string_lossy());
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+        let result = runtime.eval("seen.join(',')", "check.js").unwrap();
+        assert_eq!(runtime.value_to_string(&result), "true,false");
+    }
+
+    #[test]
+    fn test_import_meta_resolve() {
+        let code = r#"
+            globalThis.seen = [
+                import.meta.resolve('./Cargo.toml') === 'file://' + import.meta.dirname + '/Cargo.toml',
+                import.meta.resolve('fs'),
+                import.meta.resolve('host:app'),
+            ];
+        "#;
+        assert_eq!(seen(code), "true,node:fs,host:app");
+    }
+
+    #[test]
+    fn test_import_meta_resolve_errors() {
+        let code = r#"
+            const fail = (specifier) => {
+                try { import.meta.resolve(specifier); } catch (e) { return e.name; }
+            };
+            globalThis.seen = [fail('no-such-package-anywhere'), fail('node:nope')];
+        "#;
+        assert_eq!(seen(code), "TypeError,TypeError");
+    }
+
+    #[test]
+    fn test_import_meta_env() {
+        assert_eq!(
+            seen("globalThis.seen = [import.meta.env === process.env];"),
+            "true"
+        );
+    }
+}
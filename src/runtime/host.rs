@@ -0,0 +1,431 @@
+//! Embedding API for host applications
+//!
+//! A Rust program embedding Viper can expose its own functionality to scripts
+//! and call back into them:
+//! - [`HostModule`] groups functions and values that scripts import as
+//!   `host:<name>` (or `require('host:<name>')`)
+//! - Functions take and return serde types; arguments are deserialized from the
+//!   JavaScript argument list and the result is serialized back
+//! - [`Runtime::call_export`] calls a function exported by the main module and
+//!   drives the event loop until the promise it returns settles
+//!
+//! Registrations belong to one [`Runtime`]; other runtimes, including workers,
+//! don't see them.
+//!
+//! ```no_run
+//! use viper::runtime::{HostError, HostModule, Runtime};
+//!
+//! let mut runtime = Runtime::new()?;
+//! runtime.register_module(
+//!     HostModule::new("math")
+//!         .function("add", |(a, b): (f64, f64)| Ok(a + b))
+//!         .function("sqrt", |x: f64| {
+//!             if x < 0.0 {
+//!                 return Err(HostError::new("negative input"));
+//!             }
+//!             Ok(x.sqrt())
+//!         }),
+//! )?;
+//! runtime.run(
+//!     "import { add } from 'host:math'; export async function total(xs) { return xs.reduce(add, 0); }",
+//!     "plugin.mjs",
+//! )?;
+//! let total: f64 = runtime.call_export("total", (vec![1.0, 2.0, 3.0],))?;
+//! # Ok::<(), viper::runtime::RuntimeError>(())
+//! ```
+
+use boa_engine::{
+    Context, JsError, JsNativeError, JsObject, JsResult, JsValue, NativeFunction,
+    builtins::promise::PromiseState, js_string, object::ObjectInitializer,
+    object::builtins::JsPromise, property::Attribute,
+};
+use boa_gc::{Finalize, Trace};
+use serde::{Serialize, de::DeserializeOwned};
+use std::rc::Rc;
+use thiserror::Error;
+
+use super::event_loop::LoopExit;
+use super::{Runtime, RuntimeError, RuntimeResult, rejection};
+
+/// Global object holding the exports of every registered host module
+const HOST_MODULES: &str = "__viper_host_modules";
+
+/// Error returned by a host function; scripts see it as a thrown `Error`
+#[derive(Error, Debug)]
+#[error("{0}")]
+pub struct HostError(String);
+
+impl HostError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+/// Result type for host functions
+pub type HostResult<T> = Result<T, HostError>;
+
+type HostFn = Rc<dyn Fn(&[JsValue], &mut Context) -> JsResult<JsValue>>;
+
+/// Keeps a host closure alive inside a native function
+#[derive(Trace, Finalize)]
+struct HostFnSlot(#[unsafe_ignore_trace] HostFn);
+
+enum HostExport {
+    Function(HostFn),
+    Value(serde_json::Value),
+}
+
+/// A native module scripts can import as `host:<name>`
+pub struct HostModule {
+    name: String,
+    exports: Vec<(String, HostExport)>,
+}
+
+impl HostModule {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            exports: Vec::new(),
+        }
+    }
+
+    /// Export a function with serde-converted arguments and result
+    ///
+    /// Arguments deserialize from the argument list, so use a tuple for
+    /// several arguments; a function taking one argument may also take it
+    /// directly. Closures must not hold on to JavaScript values.
+    pub fn function<A, R, F>(mut self, name: impl Into<String>, f: F) -> Self
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        F: Fn(A) -> HostResult<R> + 'static,
+    {
+        self.exports
+            .push((name.into(), HostExport::Function(typed(f))));
+        self
+    }
+
+    /// Export a function working on raw engine values
+    pub fn raw_function<F>(mut self, name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(&[JsValue], &mut Context) -> JsResult<JsValue> + 'static,
+    {
+        self.exports
+            .push((name.into(), HostExport::Function(Rc::new(f))));
+        self
+    }
+
+    /// Export a constant value
+    pub fn value(mut self, name: impl Into<String>, value: serde_json::Value) -> Self {
+        self.exports.push((name.into(), HostExport::Value(value)));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn export_names(&self) -> impl Iterator<Item = &str> {
+        self.exports.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Build the object holding the module's exports
+    fn build(&self, context: &mut Context) -> JsResult<JsObject> {
+        let mut exports = Vec::with_capacity(self.exports.len());
+        for (name, export) in &self.exports {
+            let value = match export {
+                HostExport::Function(f) => native(f.clone()).to_js_function(context.realm()).into(),
+                HostExport::Value(value) => JsValue::from_json(value, context)?,
+            };
+            exports.push((name, value));
+        }
+
+        let mut object = ObjectInitializer::new(context);
+        for (name, value) in exports {
+            object.property(js_string!(name.as_str()), value, Attribute::all());
+        }
+        Ok(object.build())
+    }
+}
+
+/// Wrap a serde-typed closure as an engine-level host function
+fn typed<A, R, F>(f: F) -> HostFn
+where
+    A: DeserializeOwned,
+    R: Serialize,
+    F: Fn(A) -> HostResult<R> + 'static,
+{
+    Rc::new(move |args, context| {
+        let args = deserialize_args(args_to_json(args, context)?)
+            .map_err(|e| JsNativeError::typ().with_message(format!("Invalid arguments: {}", e)))?;
+        let result = f(args).map_err(|e| JsNativeError::error().with_message(e.to_string()))?;
+        let result = serde_json::to_value(result).map_err(|e| {
+            JsNativeError::typ().with_message(format!("Invalid return value: {}", e))
+        })?;
+        JsValue::from_json(&result, context)
+    })
+}
+
+fn native(f: HostFn) -> NativeFunction {
+    NativeFunction::from_copy_closure_with_captures(
+        |_this, args, slot, context| (slot.0)(args, context),
+        HostFnSlot(f),
+    )
+}
+
+/// The argument list as a JSON array; `undefined` becomes `null`
+fn args_to_json(args: &[JsValue], context: &mut Context) -> JsResult<serde_json::Value> {
+    let mut values = Vec::with_capacity(args.len());
+    for arg in args {
+        values.push(arg.to_json(context)?.unwrap_or(serde_json::Value::Null));
+    }
+    Ok(serde_json::Value::Array(values))
+}
+
+/// Deserialize an argument list as a tuple, falling back to a single
+/// argument (or `()` for none). Extra trailing arguments are ignored, as they
+/// are for JavaScript functions, so callbacks like `reduce` work.
+fn deserialize_args<A: DeserializeOwned>(args: serde_json::Value) -> serde_json::Result<A> {
+    let error = match A::deserialize(&args) {
+        Ok(args) => return Ok(args),
+        Err(error) => error,
+    };
+    let serde_json::Value::Array(values) = args else {
+        return Err(error);
+    };
+    if let Some(first) = values.first()
+        && let Ok(args) = A::deserialize(first)
+    {
+        return Ok(args);
+    }
+    for len in (1..values.len()).rev() {
+        let prefix = serde_json::Value::Array(values[..len].to_vec());
+        if let Ok(args) = A::deserialize(&prefix) {
+            return Ok(args);
+        }
+    }
+    A::deserialize(serde_json::Value::Null).map_err(|_| error)
+}
+
+/// Serialize call arguments: tuples and arrays spread, `()` passes nothing
+fn args_from_serde<A: Serialize>(args: A, context: &mut Context) -> RuntimeResult<Vec<JsValue>> {
+    match serde_json::to_value(args)? {
+        serde_json::Value::Null => Ok(Vec::new()),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(|value| JsValue::from_json(value, context))
+            .collect::<JsResult<_>>()
+            .map_err(|e| RuntimeError::JsError(e.to_string())),
+        value => Ok(vec![
+            JsValue::from_json(&value, context)
+                .map_err(|e| RuntimeError::JsError(e.to_string()))?,
+        ]),
+    }
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        && name != "default"
+}
+
+/// ES module source re-exporting a registered host module
+pub(crate) fn module_source(name: &str, exports: &[String]) -> String {
+    let key = serde_json::to_string(name).unwrap_or_default();
+    let mut code = format!(
+        "const m = globalThis.{}[{}];\nexport default m;\n",
+        HOST_MODULES, key
+    );
+    for export in exports {
+        code.push_str(&format!("export const {0} = m.{0};\n", export));
+    }
+    code
+}
+
+impl Runtime {
+    /// Make `module` importable as `host:<name>` in this runtime
+    pub fn register_module(&mut self, module: HostModule) -> RuntimeResult<()> {
+        if let Some(name) = module.export_names().find(|name| !is_identifier(name)) {
+            return Err(RuntimeError::ModuleError(format!(
+                "host:{} export '{}' is not a valid identifier",
+                module.name, name
+            )));
+        }
+
+        let exports = module
+            .build(&mut self.context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        let global = self.context.global_object();
+        let registry = global
+            .get(js_string!(HOST_MODULES), &mut self.context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        let registry = match registry.as_object() {
+            Some(registry) => registry.clone(),
+            None => {
+                let registry = JsObject::with_null_proto();
+                global
+                    .set(
+                        js_string!(HOST_MODULES),
+                        registry.clone(),
+                        false,
+                        &mut self.context,
+                    )
+                    .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+                registry
+            }
+        };
+        registry
+            .set(
+                js_string!(module.name.as_str()),
+                exports,
+                false,
+                &mut self.context,
+            )
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        self.module_loader.register_host_module(
+            module.name.clone(),
+            module.export_names().map(String::from).collect(),
+        );
+        Ok(())
+    }
+
+    /// Define a global function with serde-converted arguments and result
+    pub fn register_function<A, R, F>(&mut self, name: &str, f: F) -> RuntimeResult<()>
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        F: Fn(A) -> HostResult<R> + 'static,
+    {
+        let function = native(typed(f)).to_js_function(self.context.realm());
+        self.context
+            .global_object()
+            .set(js_string!(name), function, false, &mut self.context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Call a function exported by the main module and convert its result
+    ///
+    /// `args` is spread when it serializes to an array (use a tuple for
+    /// several arguments). A returned promise is awaited by running the
+    /// event loop until it settles.
+    pub fn call_export<A, R>(&mut self, name: &str, args: A) -> RuntimeResult<R>
+    where
+        A: Serialize,
+        R: DeserializeOwned,
+    {
+        let Some(module) = self.main_module.clone() else {
+            return Err(RuntimeError::ModuleError(
+                "No module has been evaluated yet".to_string(),
+            ));
+        };
+        let namespace = module.namespace(&mut self.context);
+        let function = namespace
+            .get(js_string!(name), &mut self.context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        let Some(function) = function.as_callable() else {
+            return Err(RuntimeError::ModuleError(format!(
+                "Module has no exported function '{}'",
+                name
+            )));
+        };
+
+        let args = args_from_serde(args, &mut self.context)?;
+        let result = function
+            .call(&JsValue::undefined(), &args, &mut self.context)
+            .map_err(|e| self.uncaught_error(e))?;
+        let result = self.settle(name, result)?;
+
+        let json = result
+            .to_json(&mut self.context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?
+            .unwrap_or(serde_json::Value::Null);
+        Ok(serde_json::from_value(json)?)
+    }
+
+    /// Run the event loop until `value`, if it is a promise, settles
+    fn settle(&mut self, name: &str, value: JsValue) -> RuntimeResult<JsValue> {
+        let Some(promise) = value
+            .as_object()
+            .and_then(|object| JsPromise::from_object(object.clone()).ok())
+        else {
+            return Ok(value);
+        };
+
+        let deadline = self.deadline();
+        let exit = self.event_loop.run_until(
+            &mut self.context,
+            |_| !matches!(promise.state(), PromiseState::Pending),
+            deadline,
+        );
+        let exit = exit.map_err(|e| self.uncaught_error(e))?;
+
+        match promise.state() {
+            PromiseState::Fulfilled(value) => Ok(value),
+            PromiseState::Rejected(reason) => {
+                let error = JsError::from_opaque(reason);
+                Err(RuntimeError::JsError(rejection::describe(
+                    &error,
+                    &mut self.context,
+                )))
+            }
+            PromiseState::Pending if exit == LoopExit::TimedOut => Err(self.timeout_error()),
+            PromiseState::Pending => Err(RuntimeError::JsError(format!(
+                "Promise returned by '{}' never settled",
+                name
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_module_import_and_call_export() {
+        let mut runtime = Runtime::new().unwrap();
+        runtime
+            .register_module(
+                HostModule::new("math")
+                    .function("add", |(a, b): (f64, f64)| Ok(a + b))
+                    .function("fail", |_: ()| -> HostResult<()> {
+                        Err(HostError::new("nope"))
+                    })
+                    .value("answer", serde_json::json!(42)),
+            )
+            .unwrap();
+        let code = r#"
+            import { add, fail, answer } from 'host:math';
+            export async function total(xs) {
+                await null;
+                return xs.reduce(add, answer);
+            }
+            export function caught() {
+                try { fail(); } catch (e) { return e.message; }
+            }
+        "#;
+        runtime.run(code, "plugin.mjs").unwrap();
+
+        let total: f64 = runtime.call_export("total", (vec![1, 2, 3],)).unwrap();
+        assert_eq!(total, 48.0);
+        let message: String = runtime.call_export("caught", ()).unwrap();
+        assert_eq!(message, "nope");
+    }
+
+    #[test]
+    fn test_registrations_are_per_runtime() {
+        let mut first = Runtime::new().unwrap();
+        first
+            .register_function("greet", |name: String| Ok(format!("hi {}", name)))
+            .unwrap();
+        let result = first.eval("greet('viper')", "test.js").unwrap();
+        assert_eq!(first.value_to_string(&result), "hi viper");
+
+        let mut second = Runtime::new().unwrap();
+        let result = second.eval("typeof greet", "test.js").unwrap();
+        assert_eq!(second.value_to_string(&result), "undefined");
+    }
+}
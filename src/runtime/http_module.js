@@ -148,8 +148,29 @@
       return this;
     }
 
+    setEncoding(encoding) {
+      this._encoding = encoding;
+      return this;
+    }
+
+    pause() {
+      this._paused = true;
+      return this;
+    }
+
+    resume() {
+      this._paused = false;
+      if (this._flow) this._flow();
+      return this;
+    }
+
+    isPaused() {
+      return Boolean(this._paused);
+    }
+
     destroy(error) {
       if (this._timeout) clearTimeout(this._timeout);
+      if (this._reader) this._reader.cancel(error).catch(() => {});
       if (error) this.emit("error", error);
       this.emit("close");
       return this;
@@ -237,7 +258,8 @@
         chunk = new TextEncoder().encode(chunk);
       }
 
-      this._chunks.push(chunk);
+      if (this._send) this._send(chunk);
+      else this._chunks.push(chunk);
 
       if (callback) setTimeout(callback, 0);
       return true;
@@ -312,11 +334,14 @@
   }
 
   // ClientRequest class
+  // Sent through fetch() on the request's agent. A body written before end()
+  // is streamed as it is written; end() alone sends it in one piece.
   class ClientRequest {
     constructor(options, callback) {
       if (typeof options === "string") {
         options = new URL(options);
-      } else if (options instanceof URL) {
+      }
+      if (options instanceof URL) {
         options = {
           protocol: options.protocol,
           hostname: options.hostname,
@@ -331,85 +356,130 @@
       this.port = options.port || (options.protocol === "https:" ? 443 : 80);
       this.protocol = options.protocol || "http:";
       this.headers = options.headers || {};
+      this.agent =
+        options.agent === false
+          ? new Agent({ keepAlive: false })
+          : options.agent || globalAgent;
+      this.signal = options.signal;
       this.aborted = false;
+      this.destroyed = false;
       this.finished = false;
       this.socket = null;
       this.connection = null;
       this.reusedSocket = false;
       this._chunks = [];
+      this._started = false;
+      this._upload = null;
+      this._controller = null;
 
       addEventEmitter(this);
 
       if (callback) {
         this.once("response", callback);
       }
-
-      // Auto-start request
-      setTimeout(() => this._executeRequest(), 0);
     }
 
-    async _executeRequest() {
-      try {
-        // Build URL
-        const url = `${this.protocol}//${this.host}:${this.port}${this.path}`;
-
-        // Build body
-        let body = undefined;
-        if (this._chunks.length > 0) {
-          const totalLength = this._chunks.reduce(
-            (acc, chunk) => acc + chunk.length,
-            0,
-          );
-          const combined = new Uint8Array(totalLength);
-          let offset = 0;
-          for (const chunk of this._chunks) {
-            combined.set(chunk, offset);
-            offset += chunk.length;
-          }
-          body = combined;
-        }
+    _start(streaming) {
+      this._started = true;
+      const url = `${this.protocol}//${this.host}:${this.port}${this.path}`;
 
-        // Make fetch request
-        const response = await fetch(url, {
-          method: this.method,
-          headers: this.headers,
-          body: body,
-        });
-
-        // Create IncomingMessage
-        const headers = {};
-        const rawHeaders = [];
-        response.headers.forEach((value, key) => {
-          headers[key] = value;
-          rawHeaders.push(key, value);
-        });
-
-        const msg = new IncomingMessage({
-          statusCode: response.status,
-          statusMessage: response.statusText,
-          headers: headers,
-          rawHeaders: rawHeaders,
-          httpVersion: "1.1",
+      let body;
+      if (streaming) {
+        body = new ReadableStream({
+          start: (controller) => {
+            this._upload = controller;
+          },
         });
+      } else if (this._chunks.length > 0) {
+        const totalLength = this._chunks.reduce(
+          (acc, chunk) => acc + chunk.length,
+          0,
+        );
+        body = new Uint8Array(totalLength);
+        let offset = 0;
+        for (const chunk of this._chunks) {
+          body.set(chunk, offset);
+          offset += chunk.length;
+        }
+      }
 
-        // Emit response
-        this.emit("response", msg);
+      this._controller = new AbortController();
+      const signal = this.signal
+        ? AbortSignal.any([this._controller.signal, this.signal])
+        : this._controller.signal;
+
+      fetch(url, {
+        method: this.method,
+        headers: this.headers,
+        body,
+        duplex: "half",
+        redirect: "manual",
+        dispatcher: this.agent,
+        signal,
+      }).then(
+        (response) => this._onResponse(response),
+        (error) => {
+          if (!this.aborted) this.emit("error", error);
+        },
+      );
+    }
 
-        // Read body
-        const text = await response.text();
-        msg._body = text;
+    _onResponse(response) {
+      const headers = {};
+      const rawHeaders = [];
+      response.headers.forEach((value, key) => {
+        headers[key] = value;
+        rawHeaders.push(key, value);
+      });
 
-        // Emit data and end
-        setTimeout(() => {
-          if (text) {
-            msg.emit("data", new TextEncoder().encode(text));
-          }
-          msg.complete = true;
-          msg.emit("end");
-        }, 0);
-      } catch (error) {
-        this.emit("error", error);
-      }
+      const msg = new IncomingMessage({
+        statusCode: response.status,
+        statusMessage: response.statusText,
+        headers: headers,
+        rawHeaders: rawHeaders,
+        httpVersion: "1.1",
+      });
+      this.res = msg;
+      this.emit("response", msg);
+
+      // Hand the body over chunk by chunk, stopping while the reader is paused
+      const reader = response.body ? response.body.getReader() : null;
+      const finish = () => {
+        msg.complete = true;
+        msg.emit("end");
+        msg.emit("close");
+        this.emit("close");
+      };
+      msg._flow = () => {
+        if (msg._reading || msg._paused) return;
+        if (!reader) {
+          finish();
+          return;
+        }
+        msg._reading = true;
+        reader.read().then(
+          ({ value, done }) => {
+            msg._reading = false;
+            if (done) {
+              finish();
+              return;
+            }
+            const chunk = globalThis.Buffer ? Buffer.from(value) : value;
+            msg.emit(
+              "data",
+              msg._encoding ? chunk.toString(msg._encoding) : chunk,
+            );
+            msg._flow();
+          },
+          (error) => {
+            msg._reading = false;
+            msg.emit("error", error);
+            msg.emit("close");
+          },
+        );
+      };
+      msg._reader = reader;
+      msg._flow();
     }
 
     write(chunk, encoding, callback) {
@@ -437,8 +507,16 @@
         encoding = "utf8";
       }
 
-      if (data !== undefined) {
-        this.write(data, encoding);
+      if (typeof data === "string") {
+        data = new TextEncoder().encode(data);
+      }
+
+      if (this._started) {
+        if (data !== undefined) this._upload.enqueue(data);
+        this._upload.close();
+      } else {
+        if (data !== undefined) this._chunks.push(data);
+        this._start(false);
       }
 
       this.finished = true;
@@ -452,11 +530,23 @@
     }
 
     abort() {
+      if (this.aborted) return;
       this.aborted = true;
+      this._controller?.abort();
       this.emit("abort");
       this.emit("close");
     }
 
+    destroy(error) {
+      if (this.destroyed) return this;
+      this.destroyed = true;
+      this.aborted = true;
+      this._controller?.abort(error);
+      if (error) this.emit("error", error);
+      this.emit("close");
+      return this;
+    }
+
     setTimeout(timeout, callback) {
       if (callback) this.once("timeout", callback);
       return this;
@@ -704,6 +794,283 @@
     }
   }
 
+  // HTTP/1.1 over a connected duplex: parse requests off `socket`, emit
+  // them on the server (`this`) as 'request' and write the responses back,
+  // one at a time and keeping the connection alive between them. This is
+  // how https.Server serves the sockets tls.Server hands it.
+  function connectionListener(socket) {
+    const server = this;
+    const maxHeaderSize = server.maxHeaderSize ?? http.maxHeaderSize;
+    let buffer = Buffer.alloc(0);
+    let phase = "head";
+    let exchange = null;
+    let idleTimer = null;
+    // Between requests, so closing the server may drop the connection
+    socket._httpIdle = true;
+
+    const reject = (status) => {
+      phase = "closed";
+      socket.end(`HTTP/1.1 ${status} ${STATUS_CODES[status]}\r\nConnection: close\r\n\r\n`);
+    };
+
+    const parseHead = () => {
+      const end = buffer.indexOf("\r\n\r\n");
+      if (end === -1) {
+        if (buffer.length > maxHeaderSize) reject(431);
+        return false;
+      }
+      if (end > maxHeaderSize) {
+        reject(431);
+        return false;
+      }
+      const lines = buffer.toString("latin1", 0, end).split("\r\n");
+      buffer = buffer.slice(end + 4);
+
+      const match = /^([A-Z]+) (\S+) HTTP\/(\d)\.(\d)$/.exec(lines[0]);
+      if (!match) {
+        if (server.listenerCount?.("clientError") > 0) {
+          const err = new Error("Parse Error: Invalid method encountered");
+          err.code = "HPE_INVALID_METHOD";
+          phase = "closed";
+          server.emit("clientError", err, socket);
+        } else {
+          reject(400);
+        }
+        return false;
+      }
+
+      const headers = {};
+      const rawHeaders = [];
+      for (const line of lines.slice(1)) {
+        const colon = line.indexOf(":");
+        if (colon <= 0) continue;
+        const name = line.slice(0, colon).trim();
+        const value = line.slice(colon + 1).trim();
+        const key = name.toLowerCase();
+        rawHeaders.push(name, value);
+        if (key === "set-cookie") (headers[key] ??= []).push(value);
+        else headers[key] = key in headers ? `${headers[key]}, ${value}` : value;
+      }
+
+      const httpVersion = `${match[3]}.${match[4]}`;
+      const connection = (headers.connection ?? "").toLowerCase();
+      const req = new IncomingMessage({
+        method: match[1],
+        url: match[2],
+        headers,
+        rawHeaders,
+        httpVersion,
+        socket,
+      });
+      const res = new ServerResponse(req);
+      exchange = {
+        req,
+        res,
+        chunks: [],
+        bodyDone: false,
+        remaining: 0,
+        chunked: /\bchunked\b/i.test(headers["transfer-encoding"] ?? ""),
+        keepAlive: httpVersion === "1.1" ? connection !== "close" : connection === "keep-alive",
+      };
+      if (!exchange.chunked) exchange.remaining = parseInt(headers["content-length"] ?? "0", 10) || 0;
+      socket._httpIdle = false;
+      serve(exchange);
+      phase = "body";
+      return true;
+    };
+
+    // Moves body bytes from `buffer` to the request; true once it is all in
+    const parseBody = () => {
+      const current = exchange;
+      if (!current.chunked) {
+        const take = Math.min(current.remaining, buffer.length);
+        if (take > 0) {
+          current.chunks.push(buffer.slice(0, take));
+          buffer = buffer.slice(take);
+          current.remaining -= take;
+        }
+        return current.remaining === 0;
+      }
+      for (;;) {
+        if (current.remaining > 0) {
+          const take = Math.min(current.remaining, buffer.length);
+          current.chunks.push(buffer.slice(0, take));
+          buffer = buffer.slice(take);
+          current.remaining -= take;
+          if (current.remaining > 0) return false;
+          current.trailing = true;
+        }
+        if (current.trailing) {
+          if (buffer.length < 2) return false;
+          buffer = buffer.slice(2);
+          current.trailing = false;
+        }
+        const eol = buffer.indexOf("\r\n");
+        if (eol === -1) return false;
+        if (current.last) {
+          // Trailers end at an empty line
+          buffer = buffer.slice(eol + 2);
+          if (eol === 0) return true;
+          continue;
+        }
+        const size = parseInt(buffer.toString("latin1", 0, eol).split(";")[0], 16);
+        if (Number.isNaN(size)) {
+          reject(400);
+          return false;
+        }
+        buffer = buffer.slice(eol + 2);
+        if (size === 0) current.last = true;
+        else current.remaining = size;
+      }
+    };
+
+    const parse = () => {
+      while (phase === "head" || phase === "body") {
+        if (phase === "head") {
+          if (buffer.length === 0 || !parseHead()) return;
+          clearTimeout(idleTimer);
+        }
+        if (phase === "body") {
+          const complete = parseBody();
+          exchange.req._flow();
+          if (!complete) return;
+          exchange.bodyDone = true;
+          exchange.req._flow();
+          phase = "respond";
+        }
+      }
+    };
+
+    // Wire one request to its response, then carry on with the next
+    const serve = (current) => {
+      const { req, res } = current;
+      let started = false;
+      req._flow = () => {
+        if (!started || req._paused) return;
+        while (current.chunks.length > 0) {
+          const chunk = current.chunks.shift();
+          req.emit("data", req._encoding ? chunk.toString(req._encoding) : chunk);
+          if (req._paused) return;
+        }
+        if (current.bodyDone && !req.complete) {
+          req.complete = true;
+          req.emit("end");
+        }
+      };
+      // Listeners are added after 'request', so the body follows later
+      setImmediate(() => {
+        started = true;
+        req._flow();
+      });
+
+      let pending = [];
+      let ended = false;
+      let scheduled = false;
+      let headSent = false;
+      let chunked = false;
+      let noBody = false;
+      const flush = () => {
+        scheduled = false;
+        if (socket.destroyed) return;
+        if (!headSent) {
+          headSent = true;
+          const status = res.statusCode;
+          noBody = req.method === "HEAD" || status === 204 || status === 304 || status < 200;
+          if (!res.headersSent) res.writeHead(status);
+          const headers = res._headers;
+          if (!("content-length" in headers) && !noBody) {
+            if (ended) {
+              headers["content-length"] = String(pending.reduce((sum, chunk) => sum + chunk.length, 0));
+            } else if (req.httpVersion === "1.1") {
+              headers["transfer-encoding"] = "chunked";
+              chunked = true;
+            } else {
+              current.keepAlive = false;
+            }
+          }
+          if (String(headers.connection ?? "").toLowerCase() === "close") current.keepAlive = false;
+          if (server.listening === false) current.keepAlive = false;
+          headers.connection ??= current.keepAlive ? "keep-alive" : "close";
+          if (res.sendDate) headers.date ??= new Date().toUTCString();
+
+          let head = `HTTP/1.1 ${status} ${res.statusMessage || STATUS_CODES[status] || "Unknown"}\r\n`;
+          for (const [name, value] of Object.entries(headers)) {
+            for (const item of [].concat(value)) head += `${name}: ${item}\r\n`;
+          }
+          socket.write(Buffer.from(head + "\r\n", "latin1"));
+        }
+        if (!noBody) {
+          for (const chunk of pending) {
+            if (chunk.length === 0) continue;
+            if (chunked) socket.write(`${chunk.length.toString(16)}\r\n`);
+            socket.write(chunk);
+            if (chunked) socket.write("\r\n");
+          }
+        }
+        pending = [];
+        if (ended) {
+          if (chunked) socket.write("0\r\n\r\n");
+          finish();
+        }
+      };
+      const schedule = () => {
+        if (!scheduled) {
+          scheduled = true;
+          queueMicrotask(flush);
+        }
+      };
+      res._send = (chunk) => {
+        pending.push(chunk);
+        schedule();
+      };
+      res._nativeEnd = () => {
+        ended = true;
+        schedule();
+      };
+
+      const finish = () => {
+        exchange = null;
+        if (!current.keepAlive || !current.bodyDone || server.listening === false) {
+          phase = "closed";
+          socket.end();
+          return;
+        }
+        phase = "head";
+        socket._httpIdle = true;
+        if (server.keepAliveTimeout > 0) {
+          idleTimer = setTimeout(() => socket.destroy(), server.keepAliveTimeout);
+          idleTimer.unref?.();
+        }
+        parse();
+      };
+
+      if (/^100-continue$/i.test(req.headers.expect ?? "")) {
+        if (server.listenerCount?.("checkContinue") > 0) {
+          res.writeContinue = () => socket.write("HTTP/1.1 100 Continue\r\n\r\n");
+          server.emit("checkContinue", req, res);
+          return;
+        }
+        socket.write("HTTP/1.1 100 Continue\r\n\r\n");
+      }
+      server.emit("request", req, res);
+    };
+
+    socket.on("data", (data) => {
+      buffer = buffer.length === 0 ? data : Buffer.concat([buffer, data]);
+      parse();
+    });
+    socket.on("error", () => {});
+    socket.on("close", () => {
+      clearTimeout(idleTimer);
+      if (exchange && !exchange.req.complete) exchange.req.emit("aborted");
+      if (exchange) {
+        exchange.req.emit("close");
+        exchange.res.emit("close");
+      }
+      phase = "closed";
+    });
+  }
+
   // Global agent
   const globalAgent = new Agent({
     keepAlive: true,
@@ -721,6 +1088,7 @@
     ServerResponse: ServerResponse,
     ClientRequest: ClientRequest,
     OutgoingMessage: ServerResponse, // Alias
+    _connectionListener: connectionListener,
 
     createServer(options, requestListener) {
       return new Server(options, requestListener);
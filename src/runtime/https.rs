@@ -0,0 +1,130 @@
+//! HTTPS module - Node.js compatible https
+//!
+//! - https.request() / https.get() - fetch-backed requests, with `ca` and
+//!   `rejectUnauthorized` from the request or its Agent
+//! - https.Agent / https.globalAgent
+//! - https.createServer() - HTTP/1.1 on `tls.Server` connections
+
+use boa_engine::{Context, JsResult, Source};
+
+/// Register the https module
+pub fn register_https_module(context: &mut Context) -> JsResult<()> {
+    let https_code = include_str!("https_module.js");
+    let source = Source::from_bytes(https_code.as_bytes());
+    context.eval(source)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+    use crate::runtime::tls::test_certificates;
+
+    /// An https server answering with the method, path, body and whether
+    /// the connection was encrypted, on `port`, with `ca` in scope
+    fn server_code(code: &str) -> String {
+        let (ca, cert, key) = test_certificates();
+        format!(
+            "const [ca, cert, key] = [{ca:?}, {cert:?}, {key:?}];\n{}{code}",
+            r#"
+            import https from 'node:https';
+
+            const server = https.createServer({ key, cert }, (req, res) => {
+                let body = '';
+                req.on('data', (data) => { body += data; });
+                req.on('end', () => {
+                    res.setHeader('content-type', 'text/plain');
+                    res.end(`${req.method} ${req.url} ${body} ${req.socket.encrypted}`);
+                });
+            });
+            await new Promise((resolve) => server.listen(0, '127.0.0.1', resolve));
+            const { port } = server.address();
+            const send = (options, body) => new Promise((resolve, reject) => {
+                const req = https.request({ hostname: '127.0.0.1', port, ...options }, (res) => {
+                    let text = '';
+                    res.on('data', (data) => { text += data; });
+                    res.on('end', () => resolve(`${res.statusCode} ${text}`));
+                });
+                req.on('error', reject);
+                req.end(body);
+            });
+        "#
+        )
+    }
+
+    fn seen(code: &str) -> String {
+        crate::runtime::seen(&server_code(code))
+    }
+
+    #[test]
+    fn test_server_and_request() {
+        let code = r#"
+            const res = await new Promise((resolve, reject) => {
+                const req = https.request({ hostname: '127.0.0.1', port, path: '/echo?x=1', method: 'POST', ca }, resolve);
+                req.on('error', reject);
+                req.end('payload');
+            });
+            let text = '';
+            res.on('data', (data) => { text += data; });
+            await new Promise((resolve) => res.on('end', resolve));
+            const agent = new https.Agent({ ca });
+            const viaAgent = await send({ path: '/agent', agent });
+            await new Promise((resolve) => server.close(resolve));
+
+            globalThis.seen = [res.statusCode, res.headers['content-type'], text, viaAgent];
+        "#;
+        assert_eq!(
+            seen(code),
+            "200,text/plain,POST /echo?x=1 payload true,200 GET /agent  true"
+        );
+    }
+
+    #[test]
+    fn test_request_errors() {
+        let code = r#"
+            // The cause carries the TLS or socket error, worded by the platform
+            const failure = (options, word) => send(options).catch((err) =>
+                err.name + ' ' + err.message + ' ' + err.cause.message.toLowerCase().includes(word));
+            globalThis.seen = [
+                await failure({ path: '/untrusted' }, 'certificate'),
+                await failure({ path: '/refused', port: 1, ca }, 'refused'),
+                await send({ path: '/insecure', rejectUnauthorized: false }),
+                await send({ path: '/agent', agent: new https.Agent({ rejectUnauthorized: false }) }),
+                await send({ hostname: 'localhost', path: '/name', ca }),
+            ];
+            await new Promise((resolve) => server.close(resolve));
+        "#;
+        assert_eq!(
+            seen(code),
+            "TypeError fetch failed true,TypeError fetch failed true,\
+             200 GET /insecure  true,200 GET /agent  true,200 GET /name  true"
+        );
+    }
+
+    #[test]
+    fn test_request_permission() {
+        use crate::runtime::{Allow, Permissions, RuntimeConfig};
+
+        let config = RuntimeConfig {
+            permissions: Permissions {
+                net: Allow::Only(vec!["127.0.0.1".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let code = r#"
+            const denied = await send({ hostname: 'localhost', path: '/denied', ca })
+                .catch((err) => err.code + ' ' + (err.resource === `localhost:${port}`));
+            const allowed = await send({ path: '/allowed', ca });
+            await new Promise((resolve) => server.close(resolve));
+            globalThis.seen = [denied, allowed];
+        "#;
+        runtime.run(&server_code(code), "main.mjs").unwrap();
+        let seen = runtime.eval("String(globalThis.seen)", "check.js").unwrap();
+        assert_eq!(
+            runtime.value_to_string(&seen),
+            "ERR_ACCESS_DENIED true,200 GET /allowed  true"
+        );
+    }
+}
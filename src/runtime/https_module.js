@@ -0,0 +1,138 @@
+/**
+ * Node.js https
+ *
+ * Requests go through fetch like http's, on an Agent whose TLS options
+ * (`ca`, `rejectUnauthorized`) configure its connection pool; passing those
+ * options to `request()` itself gets the request an agent of its own. A
+ * Server is a tls.Server speaking HTTP/1.1 on every secure connection.
+ */
+(function () {
+  "use strict";
+
+  const http = globalThis.http;
+  const tls = globalThis.tls;
+
+  const TLS_OPTIONS = ["ca", "cert", "key", "pfx", "passphrase", "rejectUnauthorized", "servername"];
+
+  class Agent extends http.Agent {
+    constructor(options = {}) {
+      super(options);
+      this.defaultPort = 443;
+      this.protocol = "https:";
+    }
+
+    getName(options = {}) {
+      return super.getName({ ...options, port: options.port || this.defaultPort });
+    }
+  }
+
+  const globalAgent = new Agent({ keepAlive: true, timeout: 5000 });
+
+  // request(url[, options][, callback]) or request(options[, callback])
+  function normalizeArgs(input, options, callback) {
+    if (typeof options === "function") {
+      callback = options;
+      options = undefined;
+    }
+    let merged;
+    if (typeof input === "string" || input instanceof URL) {
+      const url = new URL(input);
+      merged = {
+        protocol: url.protocol,
+        hostname: url.hostname,
+        port: url.port,
+        path: url.pathname + url.search,
+        ...options,
+      };
+    } else {
+      merged = { ...input };
+    }
+    merged.protocol ??= "https:";
+
+    if (merged.agent === false) {
+      merged.agent = new Agent({ ...merged, keepAlive: false });
+    } else if (merged.agent === undefined) {
+      const ownTls = TLS_OPTIONS.some((key) => merged[key] !== undefined);
+      merged.agent = ownTls ? new Agent(merged) : globalAgent;
+    }
+    return [merged, callback];
+  }
+
+  function request(input, options, callback) {
+    return new http.ClientRequest(...normalizeArgs(input, options, callback));
+  }
+
+  function get(input, options, callback) {
+    const [merged, listener] = normalizeArgs(input, options, callback);
+    merged.method = "GET";
+    const req = new http.ClientRequest(merged, listener);
+    req.end();
+    return req;
+  }
+
+  class Server extends tls.Server {
+    constructor(options, requestListener) {
+      if (typeof options === "function") {
+        requestListener = options;
+        options = {};
+      }
+      options = { ...options };
+      options.ALPNProtocols ??= ["http/1.1"];
+      super(options);
+      this.timeout = 0;
+      this.keepAliveTimeout = 5000;
+      this.headersTimeout = 60000;
+      this.requestTimeout = 300000;
+      this.maxHeadersCount = null;
+      this.maxRequestsPerSocket = 0;
+      this.maxHeaderSize = options.maxHeaderSize ?? http.maxHeaderSize;
+      this._httpSockets = new Set();
+
+      this.on("secureConnection", (socket) => {
+        this._httpSockets.add(socket);
+        socket.once("close", () => this._httpSockets.delete(socket));
+        http._connectionListener.call(this, socket);
+      });
+      if (requestListener) this.on("request", requestListener);
+    }
+
+    close(callback) {
+      super.close(callback);
+      this.closeIdleConnections();
+      return this;
+    }
+
+    closeAllConnections() {
+      for (const socket of this._httpSockets) socket.destroy();
+    }
+
+    closeIdleConnections() {
+      for (const socket of this._httpSockets) {
+        if (socket._httpIdle) socket.destroy();
+      }
+    }
+
+    setTimeout(msecs, callback) {
+      if (typeof msecs === "function") {
+        callback = msecs;
+        msecs = 0;
+      }
+      this.timeout = msecs;
+      if (callback) this.on("timeout", callback);
+      return this;
+    }
+  }
+
+  function createServer(options, requestListener) {
+    return new Server(options, requestListener);
+  }
+
+  globalThis.https = {
+    Agent,
+    Server,
+    globalAgent,
+    createServer,
+    request,
+    get,
+  };
+})();
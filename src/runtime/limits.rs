@@ -0,0 +1,511 @@
+//! Resource limits for untrusted scripts
+//!
+//! Three budgets can be set per runtime through [`ResourceLimits`]:
+//! - Loop iterations, enforced by the engine itself: a function whose loops
+//!   run more iterations than allowed throws an error scripts can't catch.
+//! - Execution time, enforced by a watchdog thread that raises an interrupt
+//!   flag once the deadline passes. Boa can't be stopped from another thread,
+//!   so the flag is honoured at safepoints: every [`SLICE`] instructions of a
+//!   script run by [`Runtime::run`](super::Runtime::run), between event loop
+//!   callbacks, and at the top of every loop iteration and function call of
+//!   the scripts and modules the runtime loads (see [`with_safepoints`]).
+//!   Code built at run time with `eval` or `new Function` has none.
+//! - Heap size, measured as the bytes the runtime's thread holds through
+//!   [`CountingAllocator`] and checked at the same safepoints after a forced
+//!   collection. The binary installs the allocator; embedders that want a
+//!   heap ceiling must install it as their `#[global_allocator]` too.
+//!
+//! A runtime that hit a limit stops with
+//! [`RuntimeError::ResourceLimit`](super::RuntimeError::ResourceLimit) and
+//! should be dropped.
+
+use boa_engine::{
+    Context, JsData, JsError, JsNativeError, JsResult, JsValue, NativeFunction, js_string,
+};
+use boa_gc::{Finalize, Trace};
+use oxc_allocator::Allocator;
+use oxc_ast::{
+    AstKind,
+    ast::{FunctionBody, Statement},
+};
+use oxc_parser::{ParseOptions, Parser};
+use oxc_semantic::SemanticBuilder;
+use oxc_span::{GetSpan, SourceType, Span};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Instructions a script runs between two safepoints
+pub const SLICE: u32 = 4096;
+
+/// Budgets for a single runtime; `None` means unlimited
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Loop iterations a single function call may run
+    pub max_loop_iterations: Option<u64>,
+    /// Wall-clock time after which running JavaScript is interrupted
+    pub max_execution_time: Option<Duration>,
+    /// Bytes the runtime's thread may hold
+    pub max_heap_size: Option<usize>,
+}
+
+impl ResourceLimits {
+    /// Whether scripts must stop at safepoints to enforce these limits
+    pub fn needs_safepoints(&self) -> bool {
+        self.max_execution_time.is_some() || self.max_heap_size.is_some()
+    }
+}
+
+/// The budget a runtime ran out of
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ResourceLimit {
+    #[error("loop iteration limit of {0} exceeded")]
+    LoopIterations(u64),
+
+    #[error("execution time limit of {0:?} exceeded")]
+    ExecutionTime(Duration),
+
+    #[error("heap limit of {0} bytes exceeded")]
+    HeapSize(usize),
+}
+
+// ============================================================================
+// Heap accounting
+// ============================================================================
+
+thread_local! {
+    /// Bytes allocated and not yet freed by this thread
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Global allocator that counts live bytes per thread, for `max_heap_size`
+///
+/// Memory freed by another thread than the one that allocated it is credited
+/// to the freeing thread, so the count is an estimate.
+pub struct CountingAllocator;
+
+fn account(delta: isize) {
+    // Fails once the thread-local is destroyed during thread exit
+    let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() + delta));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            account(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            account(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        account(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            account(new_size as isize - layout.size() as isize);
+        }
+        new
+    }
+}
+
+/// Live bytes of the current thread as counted by [`CountingAllocator`]
+fn allocated() -> isize {
+    ALLOCATED.try_with(Cell::get).unwrap_or(0)
+}
+
+// ============================================================================
+// Enforcement
+// ============================================================================
+
+/// Limits of one runtime and whether one of them was hit
+pub struct LimitState {
+    limits: ResourceLimits,
+    /// Raised by the watchdog when `max_execution_time` passes
+    interrupt: Arc<AtomicBool>,
+    /// Dropping it tells the watchdog to exit
+    _watchdog: Option<mpsc::Sender<()>>,
+    /// Heap in use before the runtime was created
+    baseline: isize,
+    /// The limit that stopped the runtime
+    tripped: RefCell<Option<ResourceLimit>>,
+}
+
+impl LimitState {
+    /// Start enforcing `limits`; the deadline counts from now
+    pub fn new(limits: ResourceLimits, baseline: isize) -> Self {
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let watchdog = limits.max_execution_time.map(|timeout| {
+            let (cancel, cancelled) = mpsc::channel::<()>();
+            let flag = Arc::clone(&interrupt);
+            thread::spawn(move || {
+                if let Err(mpsc::RecvTimeoutError::Timeout) = cancelled.recv_timeout(timeout) {
+                    flag.store(true, Ordering::SeqCst);
+                }
+            });
+            cancel
+        });
+        Self {
+            limits,
+            interrupt,
+            _watchdog: watchdog,
+            baseline,
+            tripped: RefCell::new(None),
+        }
+    }
+
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// The limit hit so far, checking the deadline and heap now
+    pub fn exceeded(&self) -> Option<ResourceLimit> {
+        if let Some(limit) = self.tripped.borrow().clone() {
+            return Some(limit);
+        }
+        let limit = self.check_deadline().or_else(|| self.check_heap())?;
+        *self.tripped.borrow_mut() = Some(limit.clone());
+        Some(limit)
+    }
+
+    fn check_deadline(&self) -> Option<ResourceLimit> {
+        let timeout = self.limits.max_execution_time?;
+        self.interrupt
+            .load(Ordering::SeqCst)
+            .then_some(ResourceLimit::ExecutionTime(timeout))
+    }
+
+    fn check_heap(&self) -> Option<ResourceLimit> {
+        let max = self.limits.max_heap_size?;
+        let over = || allocated() - self.baseline > max as isize;
+        if !over() {
+            return None;
+        }
+        // Garbage doesn't count against the ceiling
+        boa_gc::force_collect();
+        over().then_some(ResourceLimit::HeapSize(max))
+    }
+
+    /// Record a loop budget error thrown by the engine
+    fn trip_loop(&self, error: &JsError) -> Option<ResourceLimit> {
+        let max = self.limits.max_loop_iterations?;
+        let native = error.as_native()?;
+        if !native.to_string().contains("loop iteration") {
+            return None;
+        }
+        let limit = ResourceLimit::LoopIterations(max);
+        *self.tripped.borrow_mut() = Some(limit.clone());
+        Some(limit)
+    }
+}
+
+/// Heap in use by the current thread, the baseline for a new runtime
+pub fn heap_baseline() -> isize {
+    allocated()
+}
+
+// ============================================================================
+// Per-context access
+// ============================================================================
+
+#[derive(Trace, Finalize, JsData)]
+struct LimitSlot(#[unsafe_ignore_trace] Rc<LimitState>);
+
+/// Apply `state` to `context`: the engine's loop budget and the safepoint checks
+pub fn install(context: &mut Context, state: Rc<LimitState>) -> JsResult<()> {
+    if let Some(max) = state.limits.max_loop_iterations {
+        context.runtime_limits_mut().set_loop_iteration_limit(max);
+    }
+    if state.limits.needs_safepoints() {
+        let safepoint = NativeFunction::from_fn_ptr(|_this, _args, context| {
+            check(context)?;
+            Ok(JsValue::undefined())
+        });
+        context.register_global_callable(js_string!(SAFEPOINT), 0, safepoint)?;
+    }
+    context.realm().host_defined_mut().insert(LimitSlot(state));
+    Ok(())
+}
+
+fn current(context: &Context) -> Option<Rc<LimitState>> {
+    context
+        .realm()
+        .host_defined()
+        .get::<LimitSlot>()
+        .map(|slot| Rc::clone(&slot.0))
+}
+
+/// Safepoint: fail with an error scripts can't catch once a limit is hit
+pub fn check(context: &mut Context) -> JsResult<()> {
+    match current(context).and_then(|state| state.exceeded()) {
+        Some(limit) => Err(JsNativeError::runtime_limit()
+            .with_message(limit.to_string())
+            .into()),
+        None => Ok(()),
+    }
+}
+
+/// The limit behind `error`, if it was raised by one
+pub fn from_js(error: &JsError, context: &Context) -> Option<ResourceLimit> {
+    let state = current(context)?;
+    let tripped = state.tripped.borrow().clone();
+    tripped.or_else(|| state.trip_loop(error))
+}
+
+// ============================================================================
+// Safepoints in scripts
+// ============================================================================
+
+/// The native a script calls at its safepoints
+const SAFEPOINT: &str = "__viper_safepoint";
+
+/// `code` with a safepoint at the top of every loop iteration and function
+/// call, when `context` enforces an execution time or heap limit
+///
+/// `module` parses `code` as a module, otherwise as a script body that may
+/// `return` (CommonJS). Code that doesn't parse is returned unchanged for
+/// Boa to report.
+pub fn with_safepoints(context: &Context, code: String, module: bool) -> String {
+    if !current(context).is_some_and(|state| state.limits.needs_safepoints()) {
+        return code;
+    }
+    instrument(&code, module).unwrap_or(code)
+}
+
+fn instrument(code: &str, module: bool) -> Option<String> {
+    let allocator = Allocator::default();
+    let source_type = if module {
+        SourceType::mjs()
+    } else {
+        SourceType::cjs()
+    };
+    let options = ParseOptions {
+        allow_return_outside_function: !module,
+        ..ParseOptions::default()
+    };
+    let parsed = Parser::new(&allocator, code, source_type.with_jsx(true))
+        .with_options(options)
+        .parse();
+    if !parsed.errors.is_empty() {
+        return None;
+    }
+    let semantic = SemanticBuilder::new().build(&parsed.program).semantic;
+
+    let mut inserts = Inserts::default();
+    for node in semantic.nodes().iter() {
+        let body = match node.kind() {
+            AstKind::ForStatement(it) => &it.body,
+            AstKind::ForInStatement(it) => &it.body,
+            AstKind::ForOfStatement(it) => &it.body,
+            AstKind::WhileStatement(it) => &it.body,
+            AstKind::DoWhileStatement(it) => &it.body,
+            AstKind::Function(it) => {
+                if let Some(body) = &it.body {
+                    inserts.prologue(body);
+                }
+                continue;
+            }
+            AstKind::ArrowFunctionExpression(it) => {
+                match it.get_expression() {
+                    Some(expression) => {
+                        inserts.wrap(expression.span(), format!("({SAFEPOINT}(), "), ")")
+                    }
+                    None => inserts.prologue(&it.body),
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        match body {
+            Statement::BlockStatement(block) => {
+                inserts.open(block.span.start + 1, format!("{SAFEPOINT}();"));
+            }
+            body => inserts.wrap(body.span(), format!("{{ {SAFEPOINT}(); "), " }"),
+        }
+    }
+    Some(inserts.apply(code))
+}
+
+/// Text to insert into a script, recorded while visiting its nodes outside in
+#[derive(Default)]
+struct Inserts {
+    /// (offset, closes a node, order among texts at that offset, text)
+    texts: Vec<(u32, bool, isize, String)>,
+}
+
+impl Inserts {
+    /// Text that starts something at `offset`; outer nodes open first
+    fn open(&mut self, offset: u32, text: String) {
+        let order = self.texts.len() as isize;
+        self.texts.push((offset, false, order, text));
+    }
+
+    /// Surround `span`; a node ending where another does closes first
+    fn wrap(&mut self, span: Span, open: String, close: &str) {
+        self.open(span.start, open);
+        let order = -(self.texts.len() as isize);
+        self.texts.push((span.end, true, order, close.to_string()));
+    }
+
+    /// A safepoint as the first statement of `body`, after its directives
+    fn prologue(&mut self, body: &FunctionBody) {
+        match body.directives.last() {
+            // The directive may end without a semicolon
+            Some(directive) => self.open(directive.span.end, format!(";{SAFEPOINT}();")),
+            None => self.open(body.span.start + 1, format!("{SAFEPOINT}();")),
+        }
+    }
+
+    fn apply(mut self, code: &str) -> String {
+        // Closing texts go before opening ones at the same offset
+        self.texts
+            .sort_by_key(|(offset, closes, order, _)| (*offset, !*closes, *order));
+        let mut out = String::with_capacity(code.len() + self.texts.len() * 24);
+        let mut copied = 0;
+        for (offset, _, _, text) in &self.texts {
+            let offset = *offset as usize;
+            out.push_str(&code[copied..offset]);
+            out.push_str(text);
+            copied = offset;
+        }
+        out.push_str(&code[copied..]);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{Runtime, RuntimeConfig, RuntimeError, RuntimeResult};
+
+    fn limited(limits: ResourceLimits) -> Runtime {
+        let config = RuntimeConfig {
+            resource_limits: limits,
+            ..Default::default()
+        };
+        Runtime::with_config(config).unwrap()
+    }
+
+    /// A runtime that may run JavaScript for 50ms
+    fn time_limited() -> Runtime {
+        limited(ResourceLimits {
+            max_execution_time: Some(Duration::from_millis(50)),
+            ..Default::default()
+        })
+    }
+
+    fn out_of_time(result: RuntimeResult<JsValue>) -> bool {
+        matches!(
+            result,
+            Err(RuntimeError::ResourceLimit(ResourceLimit::ExecutionTime(_)))
+        )
+    }
+
+    #[test]
+    fn test_loop_iterations() {
+        let mut runtime = limited(ResourceLimits {
+            max_loop_iterations: Some(1000),
+            ..Default::default()
+        });
+        let result = runtime.run("try { while (true) {} } catch (e) {}", "test.js");
+        assert!(matches!(
+            result,
+            Err(RuntimeError::ResourceLimit(ResourceLimit::LoopIterations(
+                1000
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_execution_time_in_script() {
+        let result = time_limited().run("while (true) {}", "test.js");
+        assert!(out_of_time(result));
+    }
+
+    #[test]
+    fn test_execution_time_in_module() {
+        let result = time_limited().run("export const x = 1; while (true) {}", "test.mjs");
+        assert!(out_of_time(result));
+    }
+
+    #[test]
+    fn test_execution_time_in_timer() {
+        let code = "setTimeout(() => { try { for (;;) {} } catch {} }, 0);";
+        assert!(out_of_time(time_limited().run(code, "timer.js")));
+    }
+
+    #[test]
+    fn test_execution_time_in_promise_callback() {
+        let code = "Promise.resolve().then(() => { while (true) {} });";
+        assert!(out_of_time(time_limited().run(code, "promise.js")));
+    }
+
+    #[test]
+    fn test_limits_bypass_uncaught_exception() {
+        let code = r#"
+            process.on('uncaughtException', () => {});
+            setTimeout(() => { while (true) {} }, 0);
+        "#;
+        assert!(out_of_time(time_limited().run(code, "handled.js")));
+    }
+
+    #[test]
+    fn test_instrument_loops() {
+        let code = instrument("for (;;) { a(); }\nwhile (x) y();", false).unwrap();
+        assert_eq!(
+            code,
+            "for (;;) {__viper_safepoint(); a(); }\nwhile (x) { __viper_safepoint(); y(); }"
+        );
+
+        let code = instrument("for (;;) for (;;) x;", false).unwrap();
+        assert_eq!(
+            code,
+            "for (;;) { __viper_safepoint(); for (;;) { __viper_safepoint(); x; } }"
+        );
+    }
+
+    #[test]
+    fn test_instrument_functions() {
+        let code = instrument("function f() { 'use strict'\n return 1 }", false).unwrap();
+        assert_eq!(
+            code,
+            "function f() { 'use strict';__viper_safepoint();\n return 1 }"
+        );
+
+        let code = instrument("const f = () => ({});", true).unwrap();
+        assert_eq!(code, "const f = () => (__viper_safepoint(), ({}));");
+    }
+
+    #[test]
+    fn test_instrument_top_level_return() {
+        // CommonJS bodies may return at the top level
+        let code = "if (done) return;\nclass A { m() {} }";
+        let instrumented = instrument(code, false).unwrap();
+        assert_eq!(
+            instrumented,
+            "if (done) return;\nclass A { m() {__viper_safepoint();} }"
+        );
+        assert_eq!(instrument("if (done) return;", true), None);
+    }
+}
@@ -14,7 +14,7 @@
 //! - ES Modules with TypeScript transpilation
 
 use boa_engine::{
-    Context, JsError, JsResult, JsString, JsValue, Source,
+    Context, JsError, JsNativeError, JsObject, JsResult, JsValue, NativeFunction, Script, Source,
     builtins::promise::PromiseState,
     context::ContextBuilder,
     js_string,
@@ -24,62 +24,59 @@ use boa_gc::{Finalize, Trace};
 use boa_runtime::{
     ConsoleState, Logger,
     extensions::{
-        ConsoleExtension, EncodingExtension, FetchExtension, MicrotaskExtension,
-        StructuredCloneExtension, TimeoutExtension, UrlExtension,
+        ConsoleExtension, EncodingExtension, MicrotaskExtension, StructuredCloneExtension,
+        UrlExtension,
     },
-    fetch::BlockingReqwestFetcher,
     register_extensions,
 };
 use std::{
     cell::RefCell,
+    collections::HashMap,
+    future::Future,
     path::{Path, PathBuf},
+    pin::pin,
     rc::Rc,
-    sync::atomic::{AtomicU32, Ordering},
+    task::{Context as TaskContext, Poll, Waker},
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
-/// Global counter for pending timers (setTimeout/setInterval)
-/// This allows the event loop to know when to keep running
-static PENDING_TIMER_COUNT: AtomicU32 = AtomicU32::new(0);
-
-/// Increment pending timer count
-pub fn increment_pending_timers() {
-    PENDING_TIMER_COUNT.fetch_add(1, Ordering::SeqCst);
-}
-
-/// Decrement pending timer count
-pub fn decrement_pending_timers() {
-    PENDING_TIMER_COUNT.fetch_sub(1, Ordering::SeqCst);
-}
-
-/// Check if there are pending timers
-pub fn has_pending_timers() -> bool {
-    PENDING_TIMER_COUNT.load(Ordering::SeqCst) > 0
-}
-
-/// Reset pending timer count (for new runtime instances)
-fn reset_pending_timers() {
-    PENDING_TIMER_COUNT.store(0, Ordering::SeqCst);
-}
-
+mod abort;
 mod assert;
+mod assets;
 mod buffer;
+mod builtins;
+mod cjs;
 mod crypto;
+mod dgram;
+mod dns;
 mod event_loop;
 mod events;
+mod fetch;
+mod form_data;
+mod hooks;
+mod host;
 mod http;
+mod https;
+mod limits;
 mod net;
 mod os;
 mod path;
+pub(crate) mod permissions;
 mod process;
 mod querystring;
+mod readline;
+mod rejection;
 mod server_api;
 mod spawn;
+mod startup;
 mod stream;
 mod string_decoder;
+mod tls;
 mod tty;
 mod url;
 mod util;
+mod web_streams;
 mod websocket;
 pub mod worker;
 mod zlib;
@@ -87,7 +84,18 @@ mod zlib;
 use crate::fs;
 use crate::resolver::ModuleResolver;
 use crate::transpiler::{Transpiler, TranspilerConfig};
-use event_loop::ViperEventLoop;
+use assets::AssetKind;
+use cjs::ModuleFormat;
+use event_loop::{LoopExit, ViperEventLoop};
+use hooks::RuntimeHooks;
+pub use host::{HostError, HostModule, HostResult};
+use limits::LimitState;
+pub use limits::{CountingAllocator, ResourceLimit, ResourceLimits};
+pub use permissions::{Allow, PermissionDenied, PermissionKind, Permissions};
+use rejection::RejectionTracker;
+pub use rejection::UnhandledRejections;
+pub use startup::StartupTiming;
+pub use tty::restore_terminal;
 
 /// Errors that can occur during runtime execution
 #[derive(Error, Debug)]
@@ -104,6 +112,28 @@ pub enum RuntimeError {
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    #[error("Execution timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error(
+        "Detected unsettled top-level await in {0}: the event loop is empty but the module's promise is still pending"
+    )]
+    UnsettledTopLevelAwait(String),
+
+    #[error("Value conversion error: {0}")]
+    Conversion(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    PermissionDenied(#[from] PermissionDenied),
+
+    /// A budget from [`RuntimeConfig::resource_limits`] ran out
+    #[error("Resource limit reached: {0}")]
+    ResourceLimit(#[from] ResourceLimit),
+
+    /// An exception or rejection no listener handled, with its stack
+    #[error("Uncaught {0}")]
+    Uncaught(String),
 }
 
 /// Result type for runtime operations
@@ -140,7 +170,13 @@ impl Logger for ViperLogger {
 pub struct TypeScriptModuleLoader {
     base_path: PathBuf,
     transpiler: Transpiler,
-    resolver: ModuleResolver,
+    resolver: Rc<ModuleResolver>,
+    /// Export names of modules registered as `host:<name>`
+    host_modules: RefCell<HashMap<String, Vec<String>>>,
+    /// JSON, text, bytes and WebAssembly modules by resolved path
+    assets: RefCell<HashMap<(PathBuf, AssetKind), Module>>,
+    /// Path of the entry module, for `import.meta.main`
+    main: RefCell<Option<PathBuf>>,
 }
 
 impl TypeScriptModuleLoader {
@@ -149,692 +185,57 @@ impl TypeScriptModuleLoader {
         Self {
             base_path: base.clone(),
             transpiler: Transpiler::new(),
-            resolver: ModuleResolver::new(&base),
+            resolver: Rc::new(ModuleResolver::new(&base)),
+            host_modules: RefCell::new(HashMap::new()),
+            assets: RefCell::new(HashMap::new()),
+            main: RefCell::new(None),
         }
     }
 
-    /// Get built-in module code for Node.js compatible modules
-    fn get_builtin_module(specifier: &str) -> Option<String> {
-        match specifier {
-            "path" | "node:path" => Some(
-                r#"
-                const p = globalThis.path;
-                export default p;
-                export const {
-                    sep, delimiter, join, resolve, normalize, dirname,
-                    basename, extname, isAbsolute, relative, parse,
-                    format, toNamespacedPath, matchesGlob, posix, win32
-                } = p;
-                "#
-                .to_string(),
-            ),
-            "http" | "node:http" => Some(
-                r#"
-                const h = globalThis.http;
-                export default h;
-                export const {
-                    Server, Agent, ClientRequest, IncomingMessage, ServerResponse,
-                    OutgoingMessage, METHODS, STATUS_CODES, createServer, request,
-                    get, globalAgent, maxHeaderSize
-                } = h;
-                "#
-                .to_string(),
-            ),
-            "events" | "node:events" => Some(
-                r#"
-                const e = globalThis.events;
-                export default e.EventEmitter;
-                export const EventEmitter = e.EventEmitter;
-                export const once = e.once;
-                export const on = e.on;
-                export const getEventListeners = e.getEventListeners;
-                export const getMaxListeners = e.getMaxListeners;
-                export const setMaxListeners = e.setMaxListeners;
-                export const listenerCount = e.listenerCount;
-                export const addAbortListener = e.addAbortListener;
-                export const errorMonitor = e.errorMonitor;
-                export const captureRejectionSymbol = e.captureRejectionSymbol;
-                export const captureRejections = e.captureRejections;
-                export const defaultMaxListeners = e.defaultMaxListeners;
-                "#
-                .to_string(),
-            ),
-            "buffer" | "node:buffer" => Some(
-                r#"
-                const b = globalThis.buffer;
-                export default b;
-                export const Buffer = globalThis.Buffer;
-                export const constants = b.constants;
-                export const kMaxLength = b.kMaxLength;
-                export const INSPECT_MAX_BYTES = b.INSPECT_MAX_BYTES;
-                export const SlowBuffer = globalThis.Buffer;
-                export const Blob = globalThis.Blob;
-                export const File = globalThis.File;
-                export const atob = globalThis.atob;
-                export const btoa = globalThis.btoa;
-                export const transcode = function(source, fromEnc, toEnc) {
-                    return Buffer.from(source.toString(fromEnc), toEnc);
-                };
-                export const isUtf8 = function(input) {
-                    try {
-                        const str = input.toString('utf8');
-                        return Buffer.from(str, 'utf8').equals(input);
-                    } catch { return false; }
-                };
-                export const isAscii = function(input) {
-                    for (let i = 0; i < input.length; i++) {
-                        if (input[i] > 127) return false;
-                    }
-                    return true;
-                };
-                "#
-                .to_string(),
-            ),
-            "stream" | "node:stream" => Some(
-                r#"
-                const s = globalThis.stream;
-                export default s;
-                export const Stream = s.Stream;
-                export const Readable = s.Readable;
-                export const Writable = s.Writable;
-                export const Duplex = s.Duplex;
-                export const Transform = s.Transform;
-                export const PassThrough = s.PassThrough;
-                export const pipeline = s.pipeline;
-                export const finished = s.finished;
-                export const addAbortSignal = s.addAbortSignal;
-                export const promises = s.promises;
-                "#
-                .to_string(),
-            ),
-            "fs" | "node:fs" => Some(
-                r#"
-                const f = globalThis.fs;
-                export default f;
-                export const readFileSync = f.readFileSync;
-                export const writeFileSync = f.writeFileSync;
-                export const appendFileSync = f.appendFileSync;
-                export const existsSync = f.existsSync;
-                export const statSync = f.statSync;
-                export const lstatSync = f.lstatSync;
-                export const readdirSync = f.readdirSync;
-                export const mkdirSync = f.mkdirSync;
-                export const rmdirSync = f.rmdirSync;
-                export const rmSync = f.rmSync;
-                export const unlinkSync = f.unlinkSync;
-                export const renameSync = f.renameSync;
-                export const copyFileSync = f.copyFileSync;
-                export const chmodSync = f.chmodSync;
-                export const realpathSync = f.realpathSync;
-                export const accessSync = f.accessSync;
-                export const truncateSync = f.truncateSync;
-                export const openSync = f.openSync;
-                export const closeSync = f.closeSync;
-                export const readSync = f.readSync;
-                export const writeSync = f.writeSync;
-                export const readFile = f.readFile;
-                export const writeFile = f.writeFile;
-                export const appendFile = f.appendFile;
-                export const exists = f.exists;
-                export const stat = f.stat;
-                export const lstat = f.lstat;
-                export const readdir = f.readdir;
-                export const mkdir = f.mkdir;
-                export const rmdir = f.rmdir;
-                export const rm = f.rm;
-                export const unlink = f.unlink;
-                export const rename = f.rename;
-                export const copyFile = f.copyFile;
-                export const chmod = f.chmod;
-                export const realpath = f.realpath;
-                export const access = f.access;
-                export const truncate = f.truncate;
-                export const promises = f.promises;
-                export const constants = f.constants;
-                export const Dirent = f.Dirent;
-                export const Stats = f.Stats;
-                "#
-                .to_string(),
-            ),
-            "fs/promises" | "node:fs/promises" => Some(
-                r#"
-                const p = globalThis.fs.promises;
-                export default p;
-                export const readFile = p.readFile;
-                export const writeFile = p.writeFile;
-                export const appendFile = p.appendFile;
-                export const stat = p.stat;
-                export const lstat = p.lstat;
-                export const readdir = p.readdir;
-                export const mkdir = p.mkdir;
-                export const rmdir = p.rmdir;
-                export const rm = p.rm;
-                export const unlink = p.unlink;
-                export const rename = p.rename;
-                export const copyFile = p.copyFile;
-                export const chmod = p.chmod;
-                export const realpath = p.realpath;
-                export const access = p.access;
-                export const truncate = p.truncate;
-                "#
-                .to_string(),
-            ),
-            "util" | "node:util" => Some(
-                r#"
-                const u = globalThis.util;
-                export default u;
-                export const promisify = u.promisify;
-                export const callbackify = u.callbackify;
-                export const format = u.format;
-                export const formatWithOptions = u.formatWithOptions;
-                export const inspect = u.inspect;
-                export const deprecate = u.deprecate;
-                export const isDeepStrictEqual = u.isDeepStrictEqual;
-                export const inherits = u.inherits;
-                export const debuglog = u.debuglog;
-                export const getSystemErrorName = u.getSystemErrorName;
-                export const getSystemErrorMap = u.getSystemErrorMap;
-                export const types = u.types;
-                "#
-                .to_string(),
-            ),
-            "net" | "node:net" => Some(
-                r#"
-                const n = globalThis.net;
-                export default n;
-                export const Socket = n.Socket;
-                export const Server = n.Server;
-                export const BlockList = n.BlockList;
-                export const SocketAddress = n.SocketAddress;
-                export const createServer = n.createServer;
-                export const createConnection = n.createConnection;
-                export const connect = n.connect;
-                export const isIP = n.isIP;
-                export const isIPv4 = n.isIPv4;
-                export const isIPv6 = n.isIPv6;
-                "#
-                .to_string(),
-            ),
-            "tty" | "node:tty" => Some(
-                r#"
-                const t = globalThis.tty;
-                export default t;
-                export const isatty = t.isatty;
-                export const ReadStream = t.ReadStream;
-                export const WriteStream = t.WriteStream;
-                "#
-                .to_string(),
-            ),
-            "url" | "node:url" => Some(
-                r#"
-                const u = globalThis.url;
-                export default u;
-                export const URL = globalThis.URL;
-                export const URLSearchParams = globalThis.URLSearchParams;
-                export const parse = u.parse;
-                export const format = u.format;
-                export const resolve = u.resolve;
-                export const domainToASCII = u.domainToASCII;
-                export const domainToUnicode = u.domainToUnicode;
-                export const fileURLToPath = u.fileURLToPath;
-                export const pathToFileURL = u.pathToFileURL;
-                export const urlToHttpOptions = u.urlToHttpOptions;
-                export const Url = u.Url;
-                "#
-                .to_string(),
-            ),
-            "querystring" | "node:querystring" => Some(
-                r#"
-                const qs = globalThis.querystring;
-                export default qs;
-                export const parse = qs.parse;
-                export const stringify = qs.stringify;
-                export const escape = qs.escape;
-                export const unescape = qs.unescape;
-                export const decode = qs.decode;
-                export const encode = qs.encode;
-                "#
-                .to_string(),
-            ),
-            "string_decoder" | "node:string_decoder" => Some(
-                r#"
-                const sd = globalThis.string_decoder;
-                export default sd;
-                export const StringDecoder = sd.StringDecoder;
-                "#
-                .to_string(),
-            ),
-            "assert" | "node:assert" => Some(
-                r#"
-                const a = globalThis.assert;
-                export default a;
-                export const AssertionError = a.AssertionError;
-                export const ok = a.ok;
-                export const equal = a.equal;
-                export const notEqual = a.notEqual;
-                export const strictEqual = a.strictEqual;
-                export const notStrictEqual = a.notStrictEqual;
-                export const deepEqual = a.deepEqual;
-                export const notDeepEqual = a.notDeepEqual;
-                export const deepStrictEqual = a.deepStrictEqual;
-                export const notDeepStrictEqual = a.notDeepStrictEqual;
-                export const fail = a.fail;
-                export const throws = a.throws;
-                export const doesNotThrow = a.doesNotThrow;
-                export const rejects = a.rejects;
-                export const doesNotReject = a.doesNotReject;
-                export const match = a.match;
-                export const doesNotMatch = a.doesNotMatch;
-                export const ifError = a.ifError;
-                export const strict = a.strict;
-                "#
-                .to_string(),
-            ),
-            // Add more built-in modules here as they're implemented
-            _ => None,
-        }
+    /// The resolver imports go through, shared with `import.meta.resolve`
+    pub(crate) fn resolver(&self) -> Rc<ModuleResolver> {
+        Rc::clone(&self.resolver)
     }
-}
 
-impl TypeScriptModuleLoader {
-    /// Check if code is CommonJS (has module.exports or require())
-    fn is_commonjs(code: &str) -> bool {
-        // Simple heuristic: check for CommonJS patterns
-        // Avoid false positives from comments
-        for line in code.lines() {
-            let trimmed = line.trim();
-            // Skip comments
-            if trimmed.starts_with("//") || trimmed.starts_with("/*") || trimmed.starts_with("*") {
-                continue;
-            }
-            // Check for CommonJS patterns
-            if trimmed.contains("module.exports")
-                || trimmed.contains("exports.")
-                || (trimmed.contains("require(") && !trimmed.contains("import"))
-            {
-                return true;
-            }
-            // Check for ESM patterns (if found, it's not CJS)
-            if trimmed.starts_with("import ") || trimmed.starts_with("export ") {
-                return false;
-            }
-        }
-        false
+    /// Mark the module at `path` as the entry module
+    pub(crate) fn set_main(&self, path: &Path) {
+        *self.main.borrow_mut() = Some(path.to_path_buf());
     }
 
-    /// Recursively bundle a CommonJS module and all its dependencies
-    fn bundle_commonjs(&self, entry_path: &Path) -> Result<String, String> {
-        use std::collections::{HashMap, HashSet};
-
-        // Maps: module_id -> (code, require_map)
-        // require_map: original_specifier -> resolved_module_id
-        let mut modules: HashMap<String, (String, HashMap<String, String>)> = HashMap::new();
-        let mut visited: HashSet<String> = HashSet::new();
-
-        // Recursively collect all required modules
-        self.collect_cjs_modules(entry_path, &mut modules, &mut visited)?;
-
-        let entry_id = entry_path.to_string_lossy().replace('\\', "/");
-
-        // Build the bundle with a require runtime
-        let mut bundle = String::from(
-            r#"
-// CommonJS Runtime
-const __cjs_modules__ = {};
-const __cjs_cache__ = {};
-
-function __cjs_require__(id) {
-    if (__cjs_cache__[id]) {
-        return __cjs_cache__[id].exports;
+    /// Make `host:<name>` resolvable; the exports live in the runtime's globals
+    pub(crate) fn register_host_module(&self, name: String, exports: Vec<String>) {
+        self.host_modules.borrow_mut().insert(name, exports);
     }
 
-    const module = { exports: {} };
-    __cjs_cache__[id] = module;
-
-    const moduleFunc = __cjs_modules__[id];
-    if (!moduleFunc) {
-        throw new Error(`Cannot find module '${id}'`);
-    }
-
-    moduleFunc(module.exports, module, __cjs_require__, id);
-    return module.exports;
-}
-
-"#,
-        );
-
-        // Add each module as a function
-        for (id, (code, require_map)) in &modules {
-            // Handle JSON files specially - they don't need the require wrapper
-            if id.ends_with(".json") {
-                bundle.push_str(&format!(
-                    r#"__cjs_modules__["{}"] = function(exports, module, __parent_require__, __filename) {{
-module.exports = {};
-}};
-
-"#,
-                    id, code
-                ));
-                continue;
-            }
-
-            // Build a local require function that maps specifiers to resolved IDs
-            let mut require_mappings = String::from("const __require_map__ = {\n");
-            for (spec, resolved_id) in require_map {
-                require_mappings.push_str(&format!(
-                    "  \"{}\": \"{}\",\n",
-                    spec.replace('\\', "\\\\").replace('"', "\\\""),
-                    resolved_id
-                ));
-            }
-            require_mappings.push_str("};\n");
-
-            bundle.push_str(&format!(
-                r#"__cjs_modules__["{}"] = function(exports, module, __parent_require__, __filename) {{
-const __dirname = __filename.substring(0, __filename.lastIndexOf('/'));
-{}
-function require(specifier) {{
-    // Check built-in modules first
-    if (specifier === 'buffer' || specifier === 'node:buffer') {{
-        // Create a callable Buffer constructor wrapper
-        const _Buffer = globalThis.Buffer;
-        function Buffer(arg, encodingOrOffset, length) {{
-            if (!(this instanceof Buffer)) {{
-                return Buffer.from(arg, encodingOrOffset, length);
-            }}
-            return Buffer.from(arg, encodingOrOffset, length);
-        }}
-        Buffer.prototype = Object.create(Uint8Array.prototype);
-        Buffer.prototype.constructor = Buffer;
-        Buffer.from = _Buffer.from;
-        Buffer.alloc = _Buffer.alloc;
-        Buffer.allocUnsafe = _Buffer.allocUnsafe || _Buffer.alloc;
-        Buffer.allocUnsafeSlow = _Buffer.allocUnsafeSlow || _Buffer.alloc;
-        Buffer.concat = _Buffer.concat;
-        Buffer.byteLength = _Buffer.byteLength;
-        Buffer.compare = _Buffer.compare;
-        Buffer.isBuffer = _Buffer.isBuffer;
-        Buffer.isEncoding = _Buffer.isEncoding;
-        Buffer.poolSize = 8192;
-        return {{ Buffer: Buffer, constants: globalThis.buffer?.constants || {{}}, kMaxLength: 2147483647, SlowBuffer: Buffer }};
-    }}
-    if (specifier === 'path' || specifier === 'node:path') {{
-        return globalThis.path;
-    }}
-    if (specifier === 'events' || specifier === 'node:events') {{
-        return globalThis.events;
-    }}
-    if (specifier === 'http' || specifier === 'node:http') {{
-        return globalThis.http;
-    }}
-    if (specifier === 'fs' || specifier === 'node:fs') {{
-        return globalThis.fs;
-    }}
-    if (specifier === 'fs/promises' || specifier === 'node:fs/promises') {{
-        return globalThis.fs.promises;
-    }}
-    if (specifier === 'process' || specifier === 'node:process') {{
-        return globalThis.process;
-    }}
-    if (specifier === 'util' || specifier === 'node:util') {{
-        return globalThis.util;
-    }}
-    if (specifier === 'stream' || specifier === 'node:stream') {{
-        return globalThis.stream;
-    }}
-    if (specifier === 'crypto' || specifier === 'node:crypto') {{
-        return globalThis.crypto;
-    }}
-    if (specifier === 'tty' || specifier === 'node:tty') {{
-        return {{
-            isatty: (fd) => false,
-            ReadStream: class ReadStream {{}},
-            WriteStream: class WriteStream {{
-                constructor() {{ this.isTTY = false; this.columns = 80; this.rows = 24; }}
-                getColorDepth() {{ return 1; }}
-                hasColors() {{ return false; }}
-            }}
-        }};
-    }}
-    if (specifier === 'os' || specifier === 'node:os') {{
-        return {{
-            platform: () => globalThis.process?.platform || 'unknown',
-            arch: () => globalThis.process?.arch || 'unknown',
-            cpus: () => [],
-            totalmem: () => 0,
-            freemem: () => 0,
-            homedir: () => globalThis.process?.env?.HOME || globalThis.process?.env?.USERPROFILE || '',
-            tmpdir: () => globalThis.process?.env?.TMPDIR || globalThis.process?.env?.TEMP || '/tmp',
-            hostname: () => 'localhost',
-            type: () => 'Unknown',
-            release: () => '0.0.0',
-            EOL: globalThis.process?.platform === 'win32' ? '\\r\\n' : '\\n'
-        }};
-    }}
-    if (specifier === 'zlib' || specifier === 'node:zlib') {{
-        return {{
-            createGzip: () => new (globalThis.stream?.Transform || class{{}})(),
-            createGunzip: () => new (globalThis.stream?.Transform || class{{}})(),
-            createDeflate: () => new (globalThis.stream?.Transform || class{{}})(),
-            createInflate: () => new (globalThis.stream?.Transform || class{{}})(),
-            gzip: (buf, cb) => cb(null, buf),
-            gunzip: (buf, cb) => cb(null, buf),
-            deflate: (buf, cb) => cb(null, buf),
-            inflate: (buf, cb) => cb(null, buf)
-        }};
-    }}
-    if (specifier === 'string_decoder' || specifier === 'node:string_decoder') {{
-        return {{
-            StringDecoder: class StringDecoder {{
-                constructor(encoding = 'utf8') {{ this.encoding = encoding; }}
-                write(buffer) {{ return buffer.toString(this.encoding); }}
-                end(buffer) {{ return buffer ? buffer.toString(this.encoding) : ''; }}
-            }}
-        }};
-    }}
-    if (specifier === 'net' || specifier === 'node:net') {{
-        return globalThis.net;
-    }}
-    if (specifier === 'url' || specifier === 'node:url') {{
-        return {{
-            URL: globalThis.URL,
-            URLSearchParams: globalThis.URLSearchParams,
-            parse: (urlStr) => {{
-                try {{
-                    const u = new URL(urlStr);
-                    return {{ protocol: u.protocol, hostname: u.hostname, host: u.host, port: u.port, pathname: u.pathname, search: u.search, hash: u.hash, href: u.href, path: u.pathname + u.search }};
-                }} catch(e) {{ return null; }}
-            }},
-            format: (urlObj) => {{
-                if (typeof urlObj === 'string') return urlObj;
-                return urlObj.href || '';
-            }},
-            resolve: (from, to) => new URL(to, from).href
-        }};
-    }}
-    if (specifier === 'querystring' || specifier === 'node:querystring') {{
-        return {{
-            parse: (str) => {{
-                const result = {{}};
-                if (!str || typeof str !== 'string') return result;
-                str = str.replace(/^\\?/, '');
-                const pairs = str.split('&');
-                for (const pair of pairs) {{
-                    if (!pair) continue;
-                    const idx = pair.indexOf('=');
-                    const key = idx >= 0 ? decodeURIComponent(pair.slice(0, idx)) : decodeURIComponent(pair);
-                    const value = idx >= 0 ? decodeURIComponent(pair.slice(idx + 1)) : '';
-                    if (result[key] !== undefined) {{
-                        if (Array.isArray(result[key])) result[key].push(value);
-                        else result[key] = [result[key], value];
-                    }} else {{
-                        result[key] = value;
-                    }}
-                }}
-                return result;
-            }},
-            stringify: (obj) => {{
-                const pairs = [];
-                for (const key in obj) {{
-                    const value = obj[key];
-                    if (Array.isArray(value)) {{
-                        for (const v of value) pairs.push(encodeURIComponent(key) + '=' + encodeURIComponent(v));
-                    }} else {{
-                        pairs.push(encodeURIComponent(key) + '=' + encodeURIComponent(value));
-                    }}
-                }}
-                return pairs.join('&');
-            }},
-            escape: (str) => encodeURIComponent(str),
-            unescape: (str) => decodeURIComponent(str)
-        }};
-    }}
-    if (specifier === 'assert' || specifier === 'node:assert') {{
-        const AssertionError = class extends Error {{ constructor(msg) {{ super(msg); this.name = 'AssertionError'; }} }};
-        const ok = (value, message) => {{ if (!value) throw new AssertionError(message || 'Assertion failed'); }};
-        ok.ok = ok;
-        ok.strictEqual = (a, b, message) => {{ if (a !== b) throw new AssertionError(message || `Expected ${{a}} === ${{b}}`); }};
-        ok.notStrictEqual = (a, b, message) => {{ if (a === b) throw new AssertionError(message || `Expected ${{a}} !== ${{b}}`); }};
-        ok.deepStrictEqual = (a, b, message) => {{ if (JSON.stringify(a) !== JSON.stringify(b)) throw new AssertionError(message || 'Deep equality failed'); }};
-        ok.throws = (fn, message) => {{ try {{ fn(); throw new AssertionError(message || 'Expected function to throw'); }} catch(e) {{}} }};
-        ok.AssertionError = AssertionError;
-        return ok;
-    }}
-    if (specifier === 'timers' || specifier === 'node:timers') {{
-        return {{
-            setTimeout: globalThis.setTimeout,
-            clearTimeout: globalThis.clearTimeout,
-            setInterval: globalThis.setInterval,
-            clearInterval: globalThis.clearInterval,
-            setImmediate: globalThis.setImmediate || ((fn) => setTimeout(fn, 0)),
-            clearImmediate: globalThis.clearImmediate || globalThis.clearTimeout
-        }};
-    }}
-    if (specifier === 'constants' || specifier === 'node:constants') {{
-        return {{}};
-    }}
-    if (specifier === 'punycode' || specifier === 'node:punycode') {{
-        return {{
-            encode: (str) => str,
-            decode: (str) => str,
-            toASCII: (str) => str,
-            toUnicode: (str) => str
-        }};
-    }}
-    const resolved = __require_map__[specifier];
-    if (resolved) {{
-        return __parent_require__(resolved);
-    }}
-    throw new Error(`Cannot find module '${{specifier}}'`);
-}}
-{}
-}};
-
-"#,
-                id, require_mappings, code
-            ));
-        }
-
-        // Execute entry and export
-        bundle.push_str(&format!(
-            r#"
-// Execute entry module and export
-const __entry_exports__ = __cjs_require__("{}");
-export default __entry_exports__;
-export {{ __entry_exports__ as module }};
-"#,
-            entry_id
-        ));
-
-        Ok(bundle)
-    }
-
-    /// Recursively collect all CommonJS modules with their require mappings
-    fn collect_cjs_modules(
+    /// Load a module that isn't JavaScript, once per path and kind
+    fn load_asset(
         &self,
-        file_path: &Path,
-        modules: &mut std::collections::HashMap<
-            String,
-            (String, std::collections::HashMap<String, String>),
-        >,
-        visited: &mut std::collections::HashSet<String>,
-    ) -> Result<(), String> {
-        let file_id = file_path.to_string_lossy().replace('\\', "/");
-
-        if visited.contains(&file_id) {
-            return Ok(());
+        path: PathBuf,
+        kind: AssetKind,
+        context: &RefCell<&mut Context>,
+    ) -> JsResult<Module> {
+        let key = (path, kind);
+        if let Some(module) = self.assets.borrow().get(&key) {
+            return Ok(module.clone());
         }
-        visited.insert(file_id.clone());
-
-        let code = std::fs::read_to_string(file_path)
-            .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
-
-        // Find all require() calls and resolve them
-        let requires = Self::find_requires(&code);
-        let mut require_map: std::collections::HashMap<String, String> =
-            std::collections::HashMap::new();
-
-        for req in requires {
-            // Resolve the require path using CommonJS resolver (prefers "require" condition)
-            if let Ok(resolved) = self.resolver.resolve_cjs(&req, file_path) {
-                // Skip TypeScript declaration files (.d.ts) - they're not runtime code
-                let resolved_str = resolved.to_string_lossy();
-                if resolved_str.ends_with(".d.ts")
-                    || resolved_str.ends_with(".d.mts")
-                    || resolved_str.ends_with(".d.cts")
-                {
-                    continue;
-                }
 
-                let resolved_id = resolved_str.replace('\\', "/");
-                require_map.insert(req.clone(), resolved_id);
-                self.collect_cjs_modules(&resolved, modules, visited)?;
+        let mut ctx = context.borrow_mut();
+        if kind == AssetKind::Wasm {
+            let global = ctx.global_object();
+            if !global.has_property(js_string!("WebAssembly"), &mut ctx)? {
+                return Err(JsError::from_opaque(JsValue::from(js_string!(format!(
+                    "Cannot import '{}': WebAssembly is not available in this runtime",
+                    key.0.display()
+                )))));
             }
-            // If resolution fails, it might be a built-in - we'll handle at runtime
         }
 
-        modules.insert(file_id, (code, require_map));
-        Ok(())
-    }
-
-    /// Find all require() calls in code (simple regex-like matching)
-    fn find_requires(code: &str) -> Vec<String> {
-        let mut requires = Vec::new();
-
-        // Simple pattern matching for require('...') or require("...")
-        let mut chars = code.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            if c == 'r' {
-                // Check for "require("
-                let rest: String = std::iter::once(c).chain(chars.clone().take(7)).collect();
-                if rest.starts_with("require(") {
-                    // Skip "require("
-                    for _ in 0..7 {
-                        chars.next();
-                    }
-
-                    // Get the quote character
-                    if let Some(quote) = chars.next() {
-                        if quote == '\'' || quote == '"' {
-                            // Collect until closing quote
-                            let mut path = String::new();
-                            for ch in chars.by_ref() {
-                                if ch == quote {
-                                    break;
-                                }
-                                path.push(ch);
-                            }
-                            if !path.is_empty() {
-                                requires.push(path);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        requires
+        let code = assets::module_source(&key.0, kind)
+            .map_err(|e| JsError::from_opaque(JsValue::from(js_string!(e))))?;
+        let source = Source::from_bytes(code.as_bytes()).with_path(&key.0);
+        let module = Module::parse(source, None, &mut ctx)?;
+        self.assets.borrow_mut().insert(key, module.clone());
+        Ok(module)
     }
 }
 
@@ -848,11 +249,35 @@ impl ModuleLoader for TypeScriptModuleLoader {
         let specifier_str = specifier.to_std_string_escaped();
 
         async move {
+            // Import attributes travel in the specifier
+            let (specifier_str, asset) = assets::split_specifier(&specifier_str)
+                .map(|(specifier, asset)| (specifier.to_string(), asset))
+                .map_err(|message| JsError::from(JsNativeError::typ().with_message(message)))?;
+
             // Check for built-in modules first
-            if let Some(builtin_code) = Self::get_builtin_module(&specifier_str) {
+            let builtin = builtins::resolve(&specifier_str)
+                .map_err(|message| JsError::from(JsNativeError::error().with_message(message)))?;
+            if let Some(name) = builtin {
                 let mut ctx = context.borrow_mut();
-                let source = Source::from_bytes(builtin_code.as_bytes());
-                return Module::parse(source, None, &mut *ctx);
+                let code = builtins::esm_source(name, &mut ctx)?;
+                return Module::parse(Source::from_bytes(code.as_bytes()), None, &mut ctx);
+            }
+
+            // Then modules registered by the embedding application
+            if let Some(name) = specifier_str.strip_prefix("host:") {
+                let code = self
+                    .host_modules
+                    .borrow()
+                    .get(name)
+                    .map(|exports| host::module_source(name, exports))
+                    .ok_or_else(|| {
+                        JsError::from_opaque(JsValue::from(js_string!(format!(
+                            "Cannot find host module '{}'",
+                            specifier_str
+                        ))))
+                    })?;
+                let mut ctx = context.borrow_mut();
+                return Module::parse(Source::from_bytes(code.as_bytes()), None, &mut ctx);
             }
 
             // Get the referrer path using Boa's built-in path() method
@@ -900,6 +325,14 @@ impl ModuleLoader for TypeScriptModuleLoader {
                 }
             }
 
+            // Modules are files like any other the script reads
+            permissions::check_read(&mut context.borrow_mut(), &resolved_path.to_string_lossy())?;
+
+            // JSON, text, bytes and WebAssembly modules
+            if let Some(kind) = asset.or_else(|| AssetKind::from_path(&resolved_path)) {
+                return self.load_asset(resolved_path, kind, context);
+            }
+
             // Read the resolved file
             let source_code = std::fs::read_to_string(&resolved_path).map_err(|e| {
                 JsError::from_opaque(JsValue::from(js_string!(format!(
@@ -916,7 +349,7 @@ impl ModuleLoader for TypeScriptModuleLoader {
                 .unwrap_or("");
 
             // Transpile TypeScript/TSX files
-            let js_code = if matches!(extension, "ts" | "tsx" | "mts") {
+            let js_code = if matches!(extension, "ts" | "tsx" | "mts" | "cts") {
                 let filename = resolved_path
                     .file_name()
                     .and_then(|n| n.to_str())
@@ -930,22 +363,43 @@ impl ModuleLoader for TypeScriptModuleLoader {
                 source_code
             };
 
-            // Check if this is a CommonJS module and bundle it for ESM compatibility
-            let esm_code = if Self::is_commonjs(&js_code) {
-                self.bundle_commonjs(&resolved_path)
-                    .map_err(|e| JsError::from_opaque(JsValue::from(js_string!(e))))?
-            } else {
-                js_code
+            // CommonJS modules run through require(), with their statically
+            // detected named exports re-exported
+            let esm_code = match cjs::module_format(&self.resolver, &resolved_path, &js_code) {
+                ModuleFormat::CommonJs => {
+                    let names = cjs::export_names(&self.resolver, &resolved_path, &js_code);
+                    cjs::esm_wrapper(&resolved_path, &names)
+                }
+                ModuleFormat::Esm => assets::rewrite_import_attributes(&js_code),
             };
 
             // Parse and load the module with its path for proper referrer tracking
             // Star exports (export * from "...") are now handled natively by Boa
             // with proper path tracking via Source::with_path()
-            let source = Source::from_bytes(esm_code.as_bytes()).with_path(&resolved_path);
             let mut ctx = context.borrow_mut();
+            let esm_code = limits::with_safepoints(&ctx, esm_code, true);
+            let source = Source::from_bytes(esm_code.as_bytes()).with_path(&resolved_path);
             Module::parse(source, None, &mut *ctx)
         }
     }
+
+    fn init_import_meta(
+        self: Rc<Self>,
+        import_meta: &JsObject,
+        module: &Module,
+        context: &mut Context,
+    ) {
+        // `import.meta` is created on first access and can't report errors;
+        // the properties set before a failure stay
+        let main = self.main.borrow().clone();
+        let _ = hooks::init_import_meta(
+            import_meta,
+            module,
+            &self.resolver,
+            main.as_deref(),
+            context,
+        );
+    }
 }
 
 /// Configuration for the Viper runtime
@@ -955,10 +409,17 @@ pub struct RuntimeConfig {
     pub base_path: PathBuf,
     /// Transpiler configuration
     pub transpiler_config: TranspilerConfig,
-    /// Whether to use the high-performance event loop
-    pub use_event_loop: bool,
     /// Command-line arguments (for process.argv)
     pub args: Vec<String>,
+    /// Fail with [`RuntimeError::Timeout`] once execution has run this long;
+    /// `None` runs until the event loop is empty
+    pub max_runtime: Option<Duration>,
+    /// How rejections still unhandled after a microtask checkpoint are reported
+    pub unhandled_rejections: UnhandledRejections,
+    /// Capabilities scripts get; everything is allowed by default
+    pub permissions: Permissions,
+    /// Loop, execution time and heap budgets for untrusted scripts
+    pub resource_limits: ResourceLimits,
 }
 
 impl Default for RuntimeConfig {
@@ -966,8 +427,11 @@ impl Default for RuntimeConfig {
         Self {
             base_path: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
             transpiler_config: TranspilerConfig::default(),
-            use_event_loop: true,
             args: std::env::args().collect(),
+            max_runtime: None,
+            unhandled_rejections: UnhandledRejections::default(),
+            permissions: Permissions::default(),
+            resource_limits: ResourceLimits::default(),
         }
     }
 }
@@ -988,8 +452,16 @@ pub struct Runtime {
     transpiler: Transpiler,
     #[allow(dead_code)]
     config: RuntimeConfig,
-    #[allow(dead_code)]
-    event_loop: Option<Rc<ViperEventLoop>>,
+    event_loop: Rc<ViperEventLoop>,
+    rejections: Rc<RejectionTracker>,
+    limits: Rc<LimitState>,
+    module_loader: Rc<TypeScriptModuleLoader>,
+    /// The last module passed to `execute_module`, for `call_export`
+    main_module: Option<Module>,
+    /// When the runtime was created, for `max_runtime`
+    started: Instant,
+    /// Time spent in each step of `with_config`
+    startup: StartupTiming,
 }
 
 impl Runtime {
@@ -1000,31 +472,33 @@ impl Runtime {
 
     /// Create a new runtime with custom configuration
     pub fn with_config(config: RuntimeConfig) -> RuntimeResult<Self> {
-        // Create the high-performance event loop
-        let event_loop = if config.use_event_loop {
-            Some(Rc::new(ViperEventLoop::new()))
-        } else {
-            None
-        };
+        let mut timing = StartupTiming::new();
+        // Heap use is measured from here, and the execution time deadline starts now
+        let limits = Rc::new(LimitState::new(
+            config.resource_limits.clone(),
+            limits::heap_baseline(),
+        ));
+        // The event loop drives promise jobs, timers and native I/O sources
+        let event_loop = Rc::new(ViperEventLoop::new()?);
+        // Promise rejections are reported at the loop's microtask checkpoints
+        let rejections = Rc::new(RejectionTracker::new(config.unhandled_rejections));
 
         // Create module loader
         let module_loader = Rc::new(TypeScriptModuleLoader::new(&config.base_path));
+        // Rejection tracking; import.meta is filled in by the module loader
+        let hooks = Rc::new(RuntimeHooks::new(rejections.clone()));
 
-        // Build the context with module loader
-        // Note: We don't set the event loop as job_executor when using modules
-        // because it causes RefCell borrow conflicts in async module loading
-        let builder = ContextBuilder::default().module_loader(module_loader);
-
-        // Only use event loop for non-module code
-        // For module code, Boa handles its own job queue
-        if let Some(ref el) = event_loop {
-            // We'll manually use the event loop instead of setting it as executor
-            let _ = el;
-        }
-
-        let mut context = builder
+        let mut context = ContextBuilder::default()
+            .module_loader(module_loader.clone())
+            .job_executor(event_loop.clone())
+            .host_hooks(hooks)
             .build()
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        event_loop::install(&mut context, event_loop.clone());
+        rejection::install(&mut context, rejections.clone());
+        permissions::install(&mut context, config.permissions.clone().resolved());
+        limits::install(&mut context, limits.clone())
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
         // Increase runtime limits to match Node.js/V8 defaults
         // This handles large module graphs (e.g., date-fns has 245 re-exports)
@@ -1032,25 +506,39 @@ impl Runtime {
         context
             .runtime_limits_mut()
             .set_stack_size_limit(1024 * 1024); // 1MB
+        timing.mark("context");
 
         // Register all boa_runtime extensions using tuple syntax
-        // This gives us: console, setTimeout/setInterval, URL, TextEncoder/TextDecoder,
-        // structuredClone, queueMicrotask, and fetch
+        // This gives us: console, URL, TextEncoder/TextDecoder, structuredClone
+        // and queueMicrotask. Timers are provided by our event loop.
         register_extensions(
             (
                 ConsoleExtension(ViperLogger),
-                TimeoutExtension,
                 UrlExtension,
                 EncodingExtension,
                 StructuredCloneExtension,
                 MicrotaskExtension,
-                FetchExtension(BlockingReqwestFetcher::default()),
             ),
             None,
             &mut context,
         )
         .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
+        // Register fetch() on the background HTTP client
+        fetch::register_fetch(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        // Register EventTarget/AbortController/DOMException
+        abort::register_abort(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        // Register ReadableStream/WritableStream/TransformStream
+        web_streams::register_web_streams(&mut context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        // Register Blob/File/FormData
+        form_data::register_form_data(&mut context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        timing.mark("web APIs");
+
         // Add global 'global' object (like Node.js)
         let global = context.global_object();
         context
@@ -1061,8 +549,9 @@ impl Runtime {
         // Add Viper-specific globals
         Self::register_viper_globals(&mut context)?;
 
-        // Wrap setTimeout/setInterval to track pending timers
-        Self::wrap_timer_functions(&mut context)?;
+        // Register timers (setTimeout/setInterval/setImmediate) on the event loop
+        Self::register_timers(&mut context)?;
+        timing.mark("globals, JSX and timers");
 
         // Register ultra-fast file system API (Node.js compatible)
         fs::fast::register_fs_module(&mut context)
@@ -1075,9 +564,11 @@ impl Runtime {
         // Register process object (with command-line args)
         process::register_process(&mut context, &config.args)
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        timing.mark("fs, server and process");
 
-        // Register crypto API
-        crypto::register_crypto(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        // Register crypto API, initialized on first use
+        startup::register_lazy(&mut context, "crypto", crypto::register_crypto)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
         // Register spawn/exec APIs
         spawn::register_spawn(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
@@ -1087,6 +578,7 @@ impl Runtime {
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
         websocket::register_websocket_helpers(&mut context)
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        timing.mark("spawn and WebSocket");
 
         // Register path module (Node.js compatible)
         path::register_path(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
@@ -1114,37 +606,76 @@ impl Runtime {
         // Register TTY module (Node.js compatible, native Rust performance)
         tty::register_tty_module(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
+        // Register url module (Node.js compatible URL utilities extending WHATWG URL API)
+        url::register_url_module(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        // Register string_decoder module (Node.js compatible string decoding)
+        string_decoder::register_string_decoder_module(&mut context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        timing.mark("core node modules");
+
+        // Modules below are initialized on first require/import or access
+
         // Register net module (Node.js compatible TCP networking, native Rust performance)
-        net::register_net_module(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        startup::register_lazy(&mut context, "net", net::register_net_module)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
         // Register os module (Node.js compatible, native Rust performance)
-        os::register_os_module(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        startup::register_lazy(&mut context, "os", os::register_os_module)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
         // Register zlib module (Node.js compatible compression, using zlib-rs for max performance)
-        zlib::register_zlib_module(&mut context)
+        startup::register_lazy(&mut context, "zlib", zlib::register_zlib_module)
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
         // Register querystring module (Node.js compatible URL query string utilities)
-        querystring::register_querystring_module(&mut context)
+        startup::register_lazy(
+            &mut context,
+            "querystring",
+            querystring::register_querystring_module,
+        )
+        .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        // Register dns module (Node.js compatible, pure-Rust resolver off the JS thread)
+        startup::register_lazy(&mut context, "dns", dns::register_dns_module)
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
-        // Register url module (Node.js compatible URL utilities extending WHATWG URL API)
-        url::register_url_module(&mut context).map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        // Register tls module (Node.js compatible, rustls sessions over event-loop sockets)
+        startup::register_lazy(&mut context, "tls", tls::register_tls_module)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
-        // Register string_decoder module (Node.js compatible string decoding)
-        string_decoder::register_string_decoder_module(&mut context)
+        // Register https module (Node.js compatible, on top of tls and fetch)
+        startup::register_lazy(&mut context, "https", https::register_https_module)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        // Register dgram module (Node.js compatible UDP sockets on the event loop)
+        startup::register_lazy(&mut context, "dgram", dgram::register_dgram_module)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+
+        // Register readline module (Node.js compatible line input and editing)
+        startup::register_lazy(&mut context, "readline", readline::register_readline_module)
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
         // Register assert module (Node.js compatible assertions)
-        assert::register_assert_module(&mut context)
+        startup::register_lazy(&mut context, "assert", assert::register_assert_module)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        timing.mark("lazy module stubs");
+
+        // Register the node: builtin table shared by require() and import
+        builtins::register_builtins(&mut context)
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
         // Register global require() function for CommonJS compatibility
+        cjs::register_cjs(&mut context, module_loader.resolver())
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
         Self::register_require_function(&mut context)?;
+        timing.mark("require");
 
         // Register Worker API (high-performance Web Workers)
         worker::register_worker_api(&mut context)
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+        timing.mark("workers");
 
         let transpiler = Transpiler::with_config(config.transpiler_config.clone());
 
@@ -1153,6 +684,12 @@ impl Runtime {
             transpiler,
             config,
             event_loop,
+            rejections,
+            limits,
+            module_loader,
+            main_module: None,
+            started: Instant::now(),
+            startup: timing,
         })
     }
 
@@ -1186,32 +723,91 @@ impl Runtime {
         Ok(())
     }
 
-    /// Wrap setTimeout/setInterval to track pending timers for the event loop
-    fn wrap_timer_functions(context: &mut Context) -> RuntimeResult<()> {
-        // Reset timer count for this runtime instance
-        reset_pending_timers();
+    /// Register setTimeout/setInterval/setImmediate and process.nextTick on top of
+    /// the event loop natives
+    fn register_timers(context: &mut Context) -> RuntimeResult<()> {
+        event_loop::register_event_loop_api(context)
+            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
 
-        let wrapper_code = r#"
+        let timers_code = r#"
             (function() {
-                // Store original functions
-                const _origSetTimeout = globalThis.setTimeout;
-                const _origSetInterval = globalThis.setInterval;
-                const _origClearTimeout = globalThis.clearTimeout;
-                const _origClearInterval = globalThis.clearInterval;
+                const TIMEOUT_MAX = 2147483647;
+
+                function validateCallback(callback) {
+                    if (typeof callback !== 'function') {
+                        const err = new TypeError('The "callback" argument must be of type function. Received ' +
+                            (callback === null ? 'null' : typeof callback));
+                        err.code = 'ERR_INVALID_ARG_TYPE';
+                        throw err;
+                    }
+                }
+
+                // Node.js Timeout object returned by setTimeout/setInterval
+                class Timeout {
+                    #id;
+                    constructor(callback, delay, args, repeat) {
+                        delay = Number(delay);
+                        if (!(delay >= 1 && delay <= TIMEOUT_MAX)) delay = 1;
+                        this._idleTimeout = delay;
+                        this._repeat = repeat ? delay : null;
+                        this.#id = __viper_timer_start(callback, delay, repeat, args, this);
+                    }
+                    ref() { __viper_loop_ref(this.#id, true); return this; }
+                    unref() { __viper_loop_ref(this.#id, false); return this; }
+                    hasRef() { return __viper_loop_has_ref(this.#id); }
+                    refresh() { __viper_timer_refresh(this.#id); return this; }
+                    close() { __viper_timer_clear(this.#id); return this; }
+                    [Symbol.toPrimitive]() { return this.#id; }
+                }
+
+                // Node.js Immediate object returned by setImmediate
+                class Immediate {
+                    #id;
+                    constructor(callback, args) {
+                        this.#id = __viper_immediate_start(callback, args, this);
+                    }
+                    ref() { __viper_loop_ref(this.#id, true); return this; }
+                    unref() { __viper_loop_ref(this.#id, false); return this; }
+                    hasRef() { return __viper_loop_has_ref(this.#id); }
+                    [Symbol.toPrimitive]() { return this.#id; }
+                }
+
+                globalThis.setTimeout = function setTimeout(callback, delay, ...args) {
+                    validateCallback(callback);
+                    return new Timeout(callback, delay, args, false);
+                };
+
+                globalThis.setInterval = function setInterval(callback, delay, ...args) {
+                    validateCallback(callback);
+                    return new Timeout(callback, delay, args, true);
+                };
+
+                // Timers can be cleared by object or by their primitive id
+                function clearTimer(timer) {
+                    if (timer == null) return;
+                    if (timer instanceof Timeout) {
+                        timer.close();
+                    } else if (typeof timer === 'number' || typeof timer === 'string') {
+                        __viper_timer_clear(Number(timer));
+                    }
+                }
+                globalThis.clearTimeout = clearTimer;
+                globalThis.clearInterval = clearTimer;
 
-                // Track active timers
-                const activeTimers = new Set();
-                const activeIntervals = new Set();
+                globalThis.setImmediate = function setImmediate(callback, ...args) {
+                    validateCallback(callback);
+                    return new Immediate(callback, args);
+                };
+
+                globalThis.clearImmediate = function clearImmediate(immediate) {
+                    if (immediate == null) return;
+                    __viper_immediate_clear(Number(immediate));
+                };
 
                 // nextTick queue (highest priority - runs before I/O)
                 const nextTickQueue = [];
                 let processingNextTick = false;
 
-                // setImmediate queue (runs after I/O, in check phase)
-                const immediateQueue = [];
-                let immediateId = 0;
-                const immediateCallbacks = new Map();
-
                 // Process nextTick queue
                 function processNextTicks() {
                     if (processingNextTick) return;
@@ -1234,104 +830,10 @@ impl Runtime {
                     // Use queueMicrotask to process before next macrotask
                     queueMicrotask(processNextTicks);
                 };
-
-                // setImmediate - runs in check phase (after I/O)
-                globalThis.setImmediate = function(callback, ...args) {
-                    const id = ++immediateId;
-                    immediateCallbacks.set(id, { callback, args });
-                    __viper_timer_increment();
-                    // Use setTimeout(0) to schedule in macrotask queue
-                    _origSetTimeout(() => {
-                        const entry = immediateCallbacks.get(id);
-                        if (entry) {
-                            immediateCallbacks.delete(id);
-                            __viper_timer_decrement();
-                            entry.callback.apply(null, entry.args);
-                        }
-                    }, 0);
-                    return id;
-                };
-
-                globalThis.clearImmediate = function(id) {
-                    if (immediateCallbacks.has(id)) {
-                        immediateCallbacks.delete(id);
-                        __viper_timer_decrement();
-                    }
-                };
-
-                // Wrap setTimeout
-                globalThis.setTimeout = function(callback, delay, ...args) {
-                    __viper_timer_increment();
-                    const id = _origSetTimeout(function() {
-                        activeTimers.delete(id);
-                        __viper_timer_decrement();
-                        callback.apply(this, args);
-                    }, delay);
-                    activeTimers.add(id);
-                    return id;
-                };
-
-                // Wrap setInterval
-                globalThis.setInterval = function(callback, delay, ...args) {
-                    __viper_timer_increment();
-                    const id = _origSetInterval(function() {
-                        callback.apply(this, args);
-                    }, delay);
-                    activeIntervals.add(id);
-                    return id;
-                };
-
-                // Wrap clearTimeout
-                globalThis.clearTimeout = function(id) {
-                    if (activeTimers.has(id)) {
-                        activeTimers.delete(id);
-                        __viper_timer_decrement();
-                    }
-                    return _origClearTimeout(id);
-                };
-
-                // Wrap clearInterval
-                globalThis.clearInterval = function(id) {
-                    if (activeIntervals.has(id)) {
-                        activeIntervals.delete(id);
-                        __viper_timer_decrement();
-                    }
-                    return _origClearInterval(id);
-                };
             })();
         "#;
 
-        // Register native timer tracking functions
-        let increment_fn = boa_engine::NativeFunction::from_fn_ptr(|_this, _args, _context| {
-            increment_pending_timers();
-            Ok(JsValue::undefined())
-        });
-        context
-            .global_object()
-            .set(
-                js_string!("__viper_timer_increment"),
-                increment_fn.to_js_function(context.realm()),
-                false,
-                context,
-            )
-            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
-
-        let decrement_fn = boa_engine::NativeFunction::from_fn_ptr(|_this, _args, _context| {
-            decrement_pending_timers();
-            Ok(JsValue::undefined())
-        });
-        context
-            .global_object()
-            .set(
-                js_string!("__viper_timer_decrement"),
-                decrement_fn.to_js_function(context.realm()),
-                false,
-                context,
-            )
-            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
-
-        // Execute wrapper code
-        let source = Source::from_bytes(wrapper_code.as_bytes());
+        let source = Source::from_bytes(timers_code.as_bytes());
         context
             .eval(source)
             .map_err(|e| RuntimeError::JsError(e.to_string()))?;
@@ -1377,199 +879,6 @@ impl Runtime {
             // Module cache
             globalThis.__moduleCache = {};
 
-            // Create a proper Buffer constructor wrapper for Node.js compatibility
-            // safer-buffer and other modules expect Buffer to be a constructor with a prototype
-            const BufferConstructor = (function() {
-                const _Buffer = globalThis.Buffer;
-
-                // Create constructor function
-                function Buffer(arg, encodingOrOffset, length) {
-                    if (!(this instanceof Buffer)) {
-                        return Buffer.from(arg, encodingOrOffset, length);
-                    }
-                    // When called as constructor, delegate to from
-                    const buf = _Buffer.from(arg, encodingOrOffset, length);
-                    Object.setPrototypeOf(buf, Buffer.prototype);
-                    return buf;
-                }
-
-                // Create prototype
-                Buffer.prototype = Object.create(Uint8Array.prototype);
-                Buffer.prototype.constructor = Buffer;
-
-                // Copy static methods from _Buffer
-                Buffer.alloc = _Buffer.alloc;
-                Buffer.allocUnsafe = _Buffer.allocUnsafe;
-                Buffer.allocUnsafeSlow = _Buffer.allocUnsafeSlow;
-                Buffer.from = function(value, encodingOrOffset, length) {
-                    const buf = _Buffer.from(value, encodingOrOffset, length);
-                    // Don't change prototype - it adds overhead
-                    return buf;
-                };
-                Buffer.concat = _Buffer.concat;
-                Buffer.byteLength = _Buffer.byteLength;
-                Buffer.compare = _Buffer.compare;
-                Buffer.isBuffer = _Buffer.isBuffer;
-                Buffer.isEncoding = _Buffer.isEncoding;
-
-                // Node.js Buffer constants
-                Buffer.poolSize = 8192;
-                Buffer.kMaxLength = 2147483647;
-
-                return Buffer;
-            })();
-
-            // Built-in modules map
-            const builtinModules = {
-                'buffer': () => ({ Buffer: BufferConstructor, constants: globalThis.buffer?.constants, kMaxLength: globalThis.buffer?.kMaxLength, SlowBuffer: BufferConstructor }),
-                'path': () => globalThis.path,
-                'events': () => globalThis.events,
-                'http': () => globalThis.http,
-                'fs': () => globalThis.fs,
-                'fs/promises': () => globalThis.fs?.promises,
-                'process': () => globalThis.process,
-                'crypto': () => globalThis.crypto,
-                'util': () => globalThis.util,
-                'stream': () => globalThis.stream,
-                'url': () => ({
-                    URL: globalThis.URL,
-                    parse: (urlStr) => {
-                        try {
-                            const u = new URL(urlStr);
-                            return { protocol: u.protocol, hostname: u.hostname, port: u.port, pathname: u.pathname, search: u.search, hash: u.hash, href: u.href };
-                        } catch(e) { return null; }
-                    },
-                    format: (urlObj) => urlObj.href || '',
-                }),
-                'querystring': () => ({
-                    parse: (str) => {
-                        const result = {};
-                        if (!str || typeof str !== 'string') return result;
-                        str = str.replace(/^\?/, '');
-                        const pairs = str.split('&');
-                        for (const pair of pairs) {
-                            if (!pair) continue;
-                            const idx = pair.indexOf('=');
-                            const key = idx >= 0 ? decodeURIComponent(pair.slice(0, idx)) : decodeURIComponent(pair);
-                            const value = idx >= 0 ? decodeURIComponent(pair.slice(idx + 1)) : '';
-                            if (result[key] !== undefined) {
-                                if (Array.isArray(result[key])) result[key].push(value);
-                                else result[key] = [result[key], value];
-                            } else {
-                                result[key] = value;
-                            }
-                        }
-                        return result;
-                    },
-                    stringify: (obj) => {
-                        const pairs = [];
-                        for (const key in obj) {
-                            const value = obj[key];
-                            if (Array.isArray(value)) {
-                                for (const v of value) pairs.push(encodeURIComponent(key) + '=' + encodeURIComponent(v));
-                            } else {
-                                pairs.push(encodeURIComponent(key) + '=' + encodeURIComponent(value));
-                            }
-                        }
-                        return pairs.join('&');
-                    },
-                    encode: function(obj) { return this.stringify(obj); },
-                    decode: function(str) { return this.parse(str); },
-                    escape: (str) => encodeURIComponent(str),
-                    unescape: (str) => decodeURIComponent(str),
-                }),
-                'string_decoder': () => ({
-                    StringDecoder: class StringDecoder {
-                        constructor(encoding = 'utf8') { this.encoding = encoding; }
-                        write(buffer) { return buffer.toString(this.encoding); }
-                        end(buffer) { return buffer ? buffer.toString(this.encoding) : ''; }
-                    }
-                }),
-                'assert': () => ({
-                    ok: (value, message) => { if (!value) throw new Error(message || 'Assertion failed'); },
-                    strictEqual: (a, b, message) => { if (a !== b) throw new Error(message || `Expected ${a} === ${b}`); },
-                    deepStrictEqual: (a, b, message) => { if (JSON.stringify(a) !== JSON.stringify(b)) throw new Error(message || 'Deep equality failed'); },
-                    notStrictEqual: (a, b, message) => { if (a === b) throw new Error(message || `Expected ${a} !== ${b}`); },
-                    throws: (fn, message) => { try { fn(); throw new Error(message || 'Expected function to throw'); } catch(e) {} },
-                }),
-                'os': () => ({
-                    platform: () => globalThis.process?.platform || 'unknown',
-                    arch: () => globalThis.process?.arch || 'unknown',
-                    cpus: () => [],
-                    totalmem: () => 0,
-                    freemem: () => 0,
-                    homedir: () => globalThis.process?.env?.HOME || globalThis.process?.env?.USERPROFILE || '',
-                    tmpdir: () => globalThis.process?.env?.TMPDIR || globalThis.process?.env?.TEMP || '/tmp',
-                    hostname: () => 'localhost',
-                    type: () => 'Unknown',
-                    release: () => '0.0.0',
-                    uptime: () => 0,
-                    loadavg: () => [0, 0, 0],
-                    networkInterfaces: () => ({}),
-                    EOL: globalThis.process?.platform === 'win32' ? '\r\n' : '\n',
-                }),
-                'timers': () => ({
-                    setTimeout: globalThis.setTimeout,
-                    clearTimeout: globalThis.clearTimeout,
-                    setInterval: globalThis.setInterval,
-                    clearInterval: globalThis.clearInterval,
-                    setImmediate: globalThis.setImmediate || ((fn) => setTimeout(fn, 0)),
-                    clearImmediate: globalThis.clearImmediate || clearTimeout,
-                }),
-                'tty': () => ({
-                    isatty: (fd) => false,
-                    ReadStream: class ReadStream {},
-                    WriteStream: class WriteStream {
-                        constructor() { this.isTTY = false; this.columns = 80; this.rows = 24; }
-                        getColorDepth() { return 1; }
-                        hasColors() { return false; }
-                    },
-                }),
-                'net': () => ({
-                    Socket: class Socket extends globalThis.events?.EventEmitter {
-                        constructor() { super(); this.writable = true; this.readable = true; }
-                        connect() { return this; }
-                        write() { return true; }
-                        end() {}
-                        destroy() {}
-                        setEncoding() {}
-                        setNoDelay() {}
-                        setKeepAlive() {}
-                        setTimeout() {}
-                    },
-                    Server: class Server extends globalThis.events?.EventEmitter {
-                        constructor() { super(); }
-                        listen() { return this; }
-                        close() {}
-                        address() { return { port: 0, family: 'IPv4', address: '0.0.0.0' }; }
-                    },
-                    createServer: (options, connectionListener) => new (builtinModules['net']()).Server(),
-                    createConnection: () => new (builtinModules['net']()).Socket(),
-                    connect: () => new (builtinModules['net']()).Socket(),
-                    isIP: (input) => { try { return input.includes(':') ? 6 : (input.match(/^\d+\.\d+\.\d+\.\d+$/) ? 4 : 0); } catch(e) { return 0; } },
-                    isIPv4: (input) => builtinModules['net']().isIP(input) === 4,
-                    isIPv6: (input) => builtinModules['net']().isIP(input) === 6,
-                }),
-                'zlib': () => ({
-                    createGzip: () => new (globalThis.stream?.Transform || class{})(),
-                    createGunzip: () => new (globalThis.stream?.Transform || class{})(),
-                    createDeflate: () => new (globalThis.stream?.Transform || class{})(),
-                    createInflate: () => new (globalThis.stream?.Transform || class{})(),
-                    gzip: (buf, cb) => cb(null, buf),
-                    gunzip: (buf, cb) => cb(null, buf),
-                    deflate: (buf, cb) => cb(null, buf),
-                    inflate: (buf, cb) => cb(null, buf),
-                }),
-            };
-
-            // Add node: prefix versions
-            for (const key of Object.keys(builtinModules)) {
-                builtinModules['node:' + key] = builtinModules[key];
-            }
-
-            // Path utilities for require resolution
-            const isWindows = globalThis.process?.platform === 'win32';
-
             function dirname(p) {
                 const normalized = p.replace(/\\/g, '/');
                 const lastSlash = normalized.lastIndexOf('/');
@@ -1580,218 +889,77 @@ impl Runtime {
                 return normalized.slice(0, lastSlash);
             }
 
-            function join(...parts) {
-                const joined = parts.map(p => p.replace(/\\/g, '/')).join('/');
-                // Clean up multiple slashes but preserve Windows drive letters
-                return joined.replace(/([^:])\/+/g, '$1/');
+            // Resolve with the module resolver's CommonJS conditions
+            function resolveModule(specifier, fromDir) {
+                if (__viper_is_builtin(specifier)) return specifier;
+                const resolved = __viper_cjs_resolve(specifier, fromDir);
+                if (resolved === null) {
+                    const error = new Error(`Cannot find module '${specifier}' from '${fromDir}'`);
+                    error.code = 'MODULE_NOT_FOUND';
+                    throw error;
+                }
+                return resolved;
             }
 
-            function resolve(from, to) {
-                // Normalize slashes
-                from = from.replace(/\\/g, '/');
-                to = to.replace(/\\/g, '/');
-
-                // If 'to' is absolute, return it
-                if (to.startsWith('/')) return to;
-                if (to.length >= 2 && to[1] === ':') return to; // Windows absolute path
-
-                // Handle relative paths
-                const fromParts = from.split('/').filter(p => p !== '');
-                const toParts = to.split('/');
-
-                for (const part of toParts) {
-                    if (part === '..') {
-                        // Don't pop the drive letter on Windows
-                        if (fromParts.length > 1 || (fromParts.length === 1 && !fromParts[0].includes(':'))) {
-                            fromParts.pop();
-                        }
-                    } else if (part !== '.' && part !== '') {
-                        fromParts.push(part);
-                    }
+            // Evaluate a resolved module once; also what ESM imports of CJS call
+            function loadModule(resolvedPath) {
+                if (globalThis.__moduleCache[resolvedPath]) {
+                    return globalThis.__moduleCache[resolvedPath].exports;
                 }
 
-                // Reconstruct path
-                const result = fromParts.join('/');
-                // If original from started with / and result doesn't have drive letter, add /
-                if (from.startsWith('/') && !result.match(/^[A-Za-z]:/)) {
-                    return '/' + result;
-                }
-                return result;
-            }
+                // Read (and transpile) the file; throws ERR_REQUIRE_ESM for ES modules
+                const code = __viper_cjs_source(resolvedPath);
 
-            // Try to read a file
-            function tryReadFile(filepath) {
-                try {
-                    return globalThis.fs.readFileSync(filepath, 'utf8');
-                } catch (e) {
-                    return null;
+                // Handle JSON files
+                if (resolvedPath.endsWith('.json')) {
+                    const exports = JSON.parse(code);
+                    globalThis.__moduleCache[resolvedPath] = { exports, loaded: true };
+                    return exports;
                 }
-            }
 
-            // Check if file exists
-            function fileExists(filepath) {
-                try {
-                    return globalThis.fs.existsSync(filepath);
-                } catch (e) {
-                    return false;
-                }
-            }
+                // Create module object
+                const module = { exports: {}, id: resolvedPath, filename: resolvedPath, loaded: false };
+                globalThis.__moduleCache[resolvedPath] = module;
 
-            // Find package.json main entry
-            function getPackageMain(pkgDir) {
-                const pkgJsonPath = join(pkgDir, 'package.json');
-                const content = tryReadFile(pkgJsonPath);
-                if (!content) return null;
+                // Create require for this module's directory
+                const moduleDir = dirname(resolvedPath);
+                const moduleRequire = createRequire(moduleDir);
+                module.require = moduleRequire;
 
+                // Wrap and execute
+                const wrapper = `(function(exports, require, module, __filename, __dirname) { ${code} \n})`;
                 try {
-                    const pkg = JSON.parse(content);
-                    // Try various fields in order of priority
-                    return pkg.main || pkg.module || 'index.js';
+                    const fn = eval(wrapper);
+                    fn.call(module.exports, module.exports, moduleRequire, module, resolvedPath, moduleDir);
                 } catch (e) {
-                    return null;
+                    delete globalThis.__moduleCache[resolvedPath];
+                    throw e;
                 }
-            }
 
-            // Check if path is absolute (Unix or Windows)
-            function isAbsolutePath(p) {
-                if (p.startsWith('/')) return true;
-                // Windows absolute path like C:/ or C:\
-                if (p.length >= 2 && p[1] === ':') return true;
-                return false;
-            }
-
-            // Resolve module path
-            function resolveModule(specifier, fromDir) {
-                // Relative or absolute paths
-                if (specifier.startsWith('./') || specifier.startsWith('../') || isAbsolutePath(specifier)) {
-                    const resolved = isAbsolutePath(specifier) ? specifier : resolve(fromDir, specifier);
-
-                    // Try exact path
-                    if (fileExists(resolved)) return resolved;
-
-                    // Try with extensions
-                    for (const ext of ['.js', '.mjs', '.cjs', '.json', '.ts']) {
-                        if (fileExists(resolved + ext)) return resolved + ext;
-                    }
-
-                    // Try as directory with index
-                    for (const ext of ['.js', '.mjs', '.cjs', '.ts']) {
-                        if (fileExists(join(resolved, 'index' + ext))) return join(resolved, 'index' + ext);
-                    }
-
-                    // Try package.json main
-                    const main = getPackageMain(resolved);
-                    if (main) {
-                        const mainPath = join(resolved, main);
-                        if (fileExists(mainPath)) return mainPath;
-                        for (const ext of ['.js', '.mjs', '.cjs']) {
-                            if (fileExists(mainPath + ext)) return mainPath + ext;
-                        }
-                    }
-
-                    return null;
-                }
-
-                // Node modules resolution - check if at root
-                function isRootDir(dir) {
-                    if (!dir || dir === '/' || dir === '.') return true;
-                    // Windows root like C:/ or C:
-                    if (dir.match(/^[A-Za-z]:[\\/]?$/)) return true;
-                    return false;
-                }
-
-                let currentDir = fromDir;
-                let prevDir = null;
-                while (currentDir && !isRootDir(currentDir) && currentDir !== prevDir) {
-                    const nodeModulesDir = join(currentDir, 'node_modules');
-                    const pkgDir = join(nodeModulesDir, specifier);
-
-                    // Check if package exists
-                    if (fileExists(pkgDir)) {
-                        // It's a directory - find main entry
-                        const main = getPackageMain(pkgDir) || 'index.js';
-                        const mainPath = join(pkgDir, main);
-
-                        if (fileExists(mainPath)) return mainPath;
-
-                        // Try with extensions
-                        for (const ext of ['.js', '.mjs', '.cjs']) {
-                            if (fileExists(mainPath + ext)) return mainPath + ext;
-                        }
-
-                        // Try index files
-                        for (const ext of ['.js', '.mjs', '.cjs']) {
-                            if (fileExists(join(pkgDir, 'index' + ext))) return join(pkgDir, 'index' + ext);
-                        }
-                    }
-
-                    // Check for file directly in node_modules
-                    for (const ext of ['', '.js', '.mjs', '.cjs', '.json']) {
-                        const filePath = join(nodeModulesDir, specifier + ext);
-                        if (fileExists(filePath)) return filePath;
-                    }
-
-                    // Go up one directory
-                    prevDir = currentDir;
-                    currentDir = dirname(currentDir);
-                }
-
-                return null;
+                module.loaded = true;
+                return module.exports;
             }
 
             // Create require function for a given directory
             function createRequire(fromDir) {
                 function require(specifier) {
                     // Check built-in modules first
-                    if (builtinModules[specifier]) {
-                        return builtinModules[specifier]();
-                    }
-
-                    // Resolve module path
-                    const resolvedPath = resolveModule(specifier, fromDir);
-                    if (!resolvedPath) {
-                        throw new Error(`Cannot find module '${specifier}' from '${fromDir}'`);
-                    }
-
-                    // Check cache
-                    if (globalThis.__moduleCache[resolvedPath]) {
-                        return globalThis.__moduleCache[resolvedPath].exports;
+                    const builtin = __viper_builtin(specifier);
+                    if (builtin !== undefined) {
+                        return builtin;
                     }
 
-                    // Read file
-                    const code = tryReadFile(resolvedPath);
-                    if (code === null) {
-                        throw new Error(`Cannot read module '${resolvedPath}'`);
-                    }
-
-                    // Handle JSON files
-                    if (resolvedPath.endsWith('.json')) {
-                        const exports = JSON.parse(code);
-                        globalThis.__moduleCache[resolvedPath] = { exports };
-                        return exports;
-                    }
-
-                    // Create module object
-                    const module = { exports: {}, id: resolvedPath, filename: resolvedPath };
-                    globalThis.__moduleCache[resolvedPath] = module;
-
-                    // Create require for this module's directory
-                    const moduleDir = dirname(resolvedPath);
-                    const moduleRequire = createRequire(moduleDir);
-                    moduleRequire.resolve = (id) => resolveModule(id, moduleDir);
-                    moduleRequire.cache = globalThis.__moduleCache;
-
-                    // Wrap and execute
-                    const wrapper = `(function(exports, require, module, __filename, __dirname) { ${code} \n})`;
-                    try {
-                        const fn = eval(wrapper);
-                        fn(module.exports, moduleRequire, module, resolvedPath, moduleDir);
-                    } catch (e) {
-                        delete globalThis.__moduleCache[resolvedPath];
-                        throw e;
+                    // Modules registered by the embedding application
+                    if (specifier.startsWith('host:')) {
+                        const hostModule = globalThis.__viper_host_modules?.[specifier.slice(5)];
+                        if (!hostModule) {
+                            throw new Error(`Cannot find host module '${specifier}'`);
+                        }
+                        return hostModule;
                     }
 
-                    return module.exports;
+                    // Resolved when called, so computed and conditional requires work
+                    return loadModule(resolveModule(specifier, fromDir));
                 }
 
                 require.resolve = (id) => resolveModule(id, fromDir);
@@ -1800,11 +968,15 @@ impl Runtime {
                 return require;
             }
 
+            // Used by ES module imports of CommonJS modules
+            globalThis.__viper_require_cjs = loadModule;
+
+            // Used by module.createRequire()
+            globalThis.__viper_create_require = createRequire;
+
             // Set up global require from cwd
             const cwd = globalThis.process?.cwd?.() || '.';
             globalThis.require = createRequire(cwd);
-            globalThis.require.resolve = (id) => resolveModule(id, cwd);
-            globalThis.require.cache = globalThis.__moduleCache;
 
             // Also support module.exports pattern for simple scripts
             globalThis.module = { exports: {} };
@@ -1962,13 +1134,15 @@ impl Runtime {
         };
 
         // Evaluate the JavaScript code
-        let source = Source::from_bytes(js_code.as_bytes());
-        let result = self.context.eval(source);
+        let result = self.evaluate(&js_code);
 
         // Run any pending jobs using the event loop
         let _ = self.context.run_jobs();
 
-        result.map_err(|e| RuntimeError::JsError(e.to_string()))
+        result.map_err(|e| match limits::from_js(&e, &self.context) {
+            Some(limit) => RuntimeError::ResourceLimit(limit),
+            None => RuntimeError::JsError(e.to_string()),
+        })
     }
 
     /// Execute a TypeScript file
@@ -2085,59 +1259,115 @@ impl Runtime {
             js_code
         );
 
-        // Evaluate the JavaScript code
-        let source = Source::from_bytes(wrapped_code.as_bytes());
-        let result = self.context.eval(source);
+        // Evaluate the JavaScript code; a throw is fatal unless an
+        // `uncaughtException` listener handles it
+        let result = match self.evaluate(&wrapped_code) {
+            Ok(value) => value,
+            Err(e) => {
+                self.handle_uncaught(e)?;
+                JsValue::undefined()
+            }
+        };
 
         // Run the event loop to completion, including waiting for workers
         self.run_event_loop()?;
 
-        result.map_err(|e| RuntimeError::JsError(e.to_string()))
+        Ok(result)
     }
 
     /// Run the event loop until all work is complete (including workers, timers, promises)
     ///
-    /// This implements a proper event loop that:
-    /// 1. Runs all immediate jobs (promises, microtasks)
-    /// 2. Waits for timers to fire and runs their callbacks
-    /// 3. Keeps running while there are active workers
+    /// The loop blocks in the OS poller between turns and returns once nothing
+    /// ref'd (timers, immediates, workers, sockets) is left to keep it alive.
     fn run_event_loop(&mut self) -> RuntimeResult<()> {
-        use std::time::{Duration, Instant};
+        let deadline = self.deadline();
+        let exit = self
+            .event_loop
+            .run_until(&mut self.context, |_| false, deadline);
+        let exit = exit.map_err(|e| self.uncaught_error(e))?;
+
+        if exit == LoopExit::TimedOut {
+            return Err(self.timeout_error());
+        }
+        Ok(())
+    }
 
-        let start_time = Instant::now();
-        let max_runtime = Duration::from_secs(300); // 5 minute max runtime safety limit
+    /// Evaluate a script, yielding to limit checks every [`limits::SLICE`]
+    /// instructions and at its safepoints when an execution time or heap
+    /// budget is set
+    fn evaluate(&mut self, code: &str) -> JsResult<JsValue> {
+        if !self.limits.limits().needs_safepoints() {
+            return self.context.eval(Source::from_bytes(code.as_bytes()));
+        }
 
+        let code = limits::with_safepoints(&self.context, code.to_string(), false);
+        let script = Script::parse(Source::from_bytes(code.as_bytes()), None, &mut self.context)?;
+        let limits = Rc::clone(&self.limits);
+        let mut evaluation =
+            pin!(script.evaluate_async_with_budget(&mut self.context, limits::SLICE));
+        let mut task = TaskContext::from_waker(Waker::noop());
         loop {
-            // Safety: don't run forever
-            if start_time.elapsed() > max_runtime {
-                break;
+            if let Poll::Ready(result) = evaluation.as_mut().poll(&mut task) {
+                return result;
             }
+            if let Some(limit) = limits.exceeded() {
+                return Err(JsNativeError::runtime_limit()
+                    .with_message(limit.to_string())
+                    .into());
+            }
+        }
+    }
 
-            // Run pending jobs - this processes promises and ready timers
-            self.context
-                .run_jobs()
-                .map_err(|e| RuntimeError::JsError(e.to_string()))?;
-
-            // Check if we have active workers or pending timers
-            let has_workers = worker::has_active_workers();
-            let has_timers = has_pending_timers();
+    /// Route an exception from the main script through `uncaughtException`
+    fn handle_uncaught(&mut self, error: JsError) -> RuntimeResult<()> {
+        if limits::from_js(&error, &self.context).is_some() {
+            return Err(self.uncaught_error(error));
+        }
+        let result = rejection::handle_uncaught(error, &mut self.context);
+        result.map_err(|e| self.uncaught_error(e))
+    }
 
-            // If we have workers or pending timers, keep running
-            if has_workers || has_timers {
-                std::thread::sleep(Duration::from_millis(1));
-                continue;
-            }
+    /// Fatal error for an exception nothing handled, printed with its stack
+    fn uncaught_error(&mut self, error: JsError) -> RuntimeError {
+        if let Some(limit) = limits::from_js(&error, &self.context) {
+            return RuntimeError::ResourceLimit(limit);
+        }
+        if let Some(denied) = PermissionDenied::from_js(&error, &mut self.context) {
+            return RuntimeError::PermissionDenied(denied);
+        }
+        RuntimeError::Uncaught(rejection::describe(&error, &mut self.context))
+    }
 
-            // No workers and no pending timers - we're done
-            break;
+    /// Exit code requested through `process.exitCode`, 0 if unset
+    pub fn exit_code(&mut self) -> i32 {
+        let Ok(process) = self
+            .context
+            .global_object()
+            .get(js_string!("process"), &mut self.context)
+        else {
+            return 0;
+        };
+        let Some(process) = process.as_object() else {
+            return 0;
+        };
+        match process.get(js_string!("exitCode"), &mut self.context) {
+            Ok(code) if !code.is_undefined() => code.to_i32(&mut self.context).unwrap_or(1),
+            _ => 0,
         }
+    }
 
-        // Final cleanup run
-        self.context
-            .run_jobs()
-            .map_err(|e| RuntimeError::JsError(e.to_string()))?;
+    /// How long creating this runtime took, step by step
+    pub fn startup_timing(&self) -> &StartupTiming {
+        &self.startup
+    }
 
-        Ok(())
+    /// Deadline derived from `RuntimeConfig::max_runtime`, if any
+    fn deadline(&self) -> Option<Instant> {
+        self.config.max_runtime.map(|max| self.started + max)
+    }
+
+    fn timeout_error(&self) -> RuntimeError {
+        RuntimeError::Timeout(self.config.max_runtime.unwrap_or_default())
     }
 
     /// Run a TypeScript file with full event loop support
@@ -2154,8 +1384,6 @@ impl Runtime {
     /// Execute TypeScript code as a module (supports top-level await)
     #[allow(dead_code)]
     pub fn execute_module(&mut self, code: &str, filename: &str) -> RuntimeResult<JsValue> {
-        use std::time::{Duration, Instant};
-
         // Transpile if TypeScript
         let is_typescript = filename.ends_with(".ts") || filename.ends_with(".tsx");
         let js_code = if is_typescript {
@@ -2163,86 +1391,58 @@ impl Runtime {
         } else {
             code.to_string()
         };
+        let js_code = assets::rewrite_import_attributes(&js_code);
+        let js_code = limits::with_safepoints(&self.context, js_code, true);
 
-        // Parse as module
-        let source = Source::from_bytes(js_code.as_bytes());
+        // Parse as module; its path anchors relative imports and import.meta
+        let path = self.config.base_path.join(filename);
+        let source = Source::from_bytes(js_code.as_bytes()).with_path(&path);
         let module = Module::parse(source, None, &mut self.context)
             .map_err(|e| RuntimeError::ModuleError(e.to_string()))?;
+        self.module_loader.set_main(&path);
+        self.main_module = Some(module.clone());
 
         // Load and evaluate the module
         let promise = module.load_link_evaluate(&mut self.context);
+        // Evaluation errors are reported below, not as unhandled rejections
+        let ignore = NativeFunction::from_fn_ptr(|_, _, _| Ok(JsValue::undefined()));
+        let _ = promise.then(
+            None,
+            Some(ignore.to_js_function(self.context.realm())),
+            &mut self.context,
+        );
 
-        // Run the event loop until the module promise resolves
+        // Run the event loop until the module promise settles
         // This handles top-level await properly
-        let start_time = Instant::now();
-        let max_runtime = Duration::from_secs(300); // 5 minute max
+        let deadline = self.deadline();
+        let exit = self.event_loop.run_until(
+            &mut self.context,
+            |_| !matches!(promise.state(), PromiseState::Pending),
+            deadline,
+        );
+        let exit = exit.map_err(|e| self.uncaught_error(e))?;
 
-        loop {
-            // Safety check
-            if start_time.elapsed() > max_runtime {
-                return Err(RuntimeError::ModuleError(
-                    "Module execution timed out".to_string(),
-                ));
+        match promise.state() {
+            PromiseState::Fulfilled(_) => {
+                // Module executed successfully, now run event loop for workers/timers
+                self.run_event_loop()?;
+                Ok(JsValue::undefined())
             }
-
-            // Run pending jobs
-            self.context
-                .run_jobs()
-                .map_err(|e| RuntimeError::JsError(e.to_string()))?;
-
-            // Check promise state
-            match promise.state() {
-                PromiseState::Fulfilled(_) => {
-                    // Module executed successfully, now run event loop for workers/timers
-                    self.run_event_loop()?;
-                    return Ok(JsValue::undefined());
-                }
-                PromiseState::Rejected(err) => {
-                    let err_str: JsString = err
-                        .to_string(&mut self.context)
-                        .unwrap_or_else(|_| js_string!("Unknown error"));
-                    return Err(RuntimeError::ModuleError(err_str.to_std_string_escaped()));
-                }
-                PromiseState::Pending => {
-                    // Still pending - check if we have timers or workers keeping us alive
-                    let has_workers = worker::has_active_workers();
-                    let has_timers = has_pending_timers();
-
-                    if has_workers || has_timers {
-                        // Keep running, there's async work to do
-                        std::thread::sleep(Duration::from_millis(1));
-                        continue;
-                    }
-
-                    // No timers or workers, but promise is pending
-                    // Give it a bit more time for microtasks to complete
-                    std::thread::sleep(Duration::from_millis(1));
-
-                    // Run jobs again
-                    self.context
-                        .run_jobs()
-                        .map_err(|e| RuntimeError::JsError(e.to_string()))?;
-
-                    // Check again
-                    match promise.state() {
-                        PromiseState::Fulfilled(_) => {
-                            self.run_event_loop()?;
-                            return Ok(JsValue::undefined());
-                        }
-                        PromiseState::Rejected(err) => {
-                            let err_str: JsString = err
-                                .to_string(&mut self.context)
-                                .unwrap_or_else(|_| js_string!("Unknown error"));
-                            return Err(RuntimeError::ModuleError(err_str.to_std_string_escaped()));
-                        }
-                        PromiseState::Pending => {
-                            // If still pending with no work, it might be waiting for
-                            // something that won't happen. Continue for a bit.
-                            continue;
-                        }
-                    }
-                }
+            PromiseState::Rejected(err) => {
+                // A throw during evaluation, same as for a script
+                self.handle_uncaught(JsError::from_opaque(err))?;
+                self.run_event_loop()?;
+                Ok(JsValue::undefined())
             }
+            PromiseState::Pending => match exit {
+                LoopExit::TimedOut => Err(self.timeout_error()),
+                // A worker was terminated or closed itself mid-evaluation
+                LoopExit::Stopped => Ok(JsValue::undefined()),
+                // Nothing left in the loop could ever settle the module
+                LoopExit::Idle | LoopExit::Done => {
+                    Err(RuntimeError::UnsettledTopLevelAwait(filename.to_string()))
+                }
+            },
         }
     }
 
@@ -2273,6 +1473,26 @@ impl Default for Runtime {
     }
 }
 
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // Pending callbacks hold JS objects; release them before the context goes away
+        self.event_loop.shutdown();
+        self.rejections.clear();
+    }
+}
+
+#[cfg(test)]
+pub(crate) use tls::test_certificates;
+
+/// Run `code` as the module `main.mjs` and read back `globalThis.seen`
+#[cfg(test)]
+pub(crate) fn seen(code: &str) -> String {
+    let mut runtime = Runtime::new().unwrap();
+    runtime.run(code, "main.mjs").unwrap();
+    let seen = runtime.eval("String(globalThis.seen)", "check.js").unwrap();
+    runtime.value_to_string(&seen)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2321,6 +1541,231 @@ mod tests {
         // Promise should be resolved after run
     }
 
+    #[test]
+    fn test_timer_ordering() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            globalThis.order = [];
+            setTimeout(() => order.push('timeout'), 5);
+            setImmediate(() => order.push('immediate'));
+            process.nextTick(() => order.push('tick'));
+            setInterval(() => order.push('interval'), 1000).unref();
+        "#;
+        runtime.run(code, "test.js").unwrap();
+        let result = runtime.eval("order.join(',')", "check.js").unwrap();
+        assert_eq!(runtime.value_to_string(&result), "tick,immediate,timeout");
+    }
+
+    #[test]
+    fn test_max_runtime() {
+        let config = RuntimeConfig {
+            max_runtime: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let result = runtime.run("setInterval(() => {}, 10);", "test.js");
+        assert!(matches!(result, Err(RuntimeError::Timeout(_))));
+    }
+
+    #[test]
+    fn test_unhandled_rejection_is_fatal() {
+        let mut runtime = Runtime::new().unwrap();
+        let result = runtime.run("Promise.reject(new Error('boom'));", "test.js");
+        assert!(matches!(result, Err(RuntimeError::Uncaught(msg)) if msg.contains("boom")));
+    }
+
+    #[test]
+    fn test_rejection_of_awaited_promise_is_handled() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            const later = (value) => new Promise((_, reject) => setTimeout(() => reject(value), 1));
+            const caught = await later(new Error('caught')).catch((err) => err.message);
+            let awaited;
+            try { await later(5); } catch (value) { awaited = value; }
+            const passed = await later('passed').then(() => 'fulfilled').catch((value) => value);
+            globalThis.seen = [caught, awaited, passed];
+        "#;
+        runtime.run(code, "main.mjs").unwrap();
+        let result = runtime.eval("String(seen)", "check.js").unwrap();
+        assert_eq!(runtime.value_to_string(&result), "caught,5,passed");
+
+        let result = runtime.run(
+            "new Promise((_, reject) => setTimeout(reject, 1, 'lost'));",
+            "test.js",
+        );
+        assert!(matches!(result, Err(RuntimeError::Uncaught(msg)) if msg.contains("lost")));
+    }
+
+    #[test]
+    fn test_rejection_is_tracked_per_promise() {
+        let unrelated = [
+            ("Promise.reject(); await undefined;", "main.mjs"),
+            (
+                "Promise.reject(); Promise.resolve().then(() => {});",
+                "test.js",
+            ),
+            (
+                "Promise.reject(0); Promise.resolve(0).then(() => {});",
+                "test.js",
+            ),
+            (
+                "const e = new Error(); Promise.reject(e); Promise.resolve(e).then(() => {});",
+                "test.js",
+            ),
+            (
+                "Promise.resolve().then(() => {}); Promise.reject();",
+                "test.js",
+            ),
+            (
+                "Promise.resolve(0).then(() => {}); Promise.reject(0);",
+                "test.js",
+            ),
+        ];
+        for (code, filename) in unrelated {
+            let mut runtime = Runtime::new().unwrap();
+            let result = runtime.run(code, filename);
+            assert!(
+                matches!(result, Err(RuntimeError::Uncaught(_))),
+                "{code}: {:?}",
+                result.map(|_| ())
+            );
+        }
+
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            let reject;
+            const p = new Promise((_, r) => (reject = r));
+            p.catch(() => {});
+            reject();
+            Promise.reject(1).then(() => {}, () => {});
+        "#;
+        assert!(runtime.run(code, "test.js").is_ok());
+    }
+
+    #[test]
+    fn test_rejection_events() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            globalThis.seen = [];
+            process.on('unhandledRejection', (reason) => seen.push('unhandled:' + reason));
+            process.on('rejectionHandled', () => seen.push('handled'));
+            process.on('uncaughtException', (err, origin) => seen.push(origin + ':' + err.message));
+            const p = Promise.reject('late');
+            setTimeout(() => p.catch(() => {}), 1);
+            setTimeout(() => { throw new Error('timer'); }, 5);
+        "#;
+        runtime.run(code, "test.js").unwrap();
+        let result = runtime.eval("seen.join(',')", "check.js").unwrap();
+        assert_eq!(
+            runtime.value_to_string(&result),
+            "unhandled:late,handled,uncaughtException:timer"
+        );
+    }
+
+    #[test]
+    fn test_unhandled_rejections_warn_mode() {
+        let config = RuntimeConfig {
+            unhandled_rejections: UnhandledRejections::Warn,
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        assert!(
+            runtime
+                .run("Promise.reject(new Error('ignored'));", "test.js")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_permission_denied() {
+        let config = RuntimeConfig {
+            permissions: Permissions::deny_all(),
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let result = runtime.run("fs.readFileSync('Cargo.toml', 'utf8');", "test.js");
+        match result {
+            Err(RuntimeError::PermissionDenied(denied)) => {
+                assert_eq!(denied.kind, PermissionKind::Read)
+            }
+            other => panic!("expected PermissionDenied, got {:?}", other.map(|_| ())),
+        }
+
+        let mut runtime = Runtime::new().unwrap();
+        assert!(
+            runtime
+                .run("fs.readFileSync('Cargo.toml', 'utf8');", "test.js")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_unsettled_top_level_await() {
+        let mut runtime = Runtime::new().unwrap();
+        let result = runtime.run("await new Promise(() => {});", "test.mjs");
+        assert!(matches!(
+            result,
+            Err(RuntimeError::UnsettledTopLevelAwait(_))
+        ));
+    }
+
+    #[test]
+    fn test_module_read_permission() {
+        let dir = std::env::temp_dir().join(format!("viper-module-perm-{}", std::process::id()));
+        let allowed = dir.join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::write(allowed.join("lib.mjs"), "export const value = 1;").unwrap();
+        std::fs::write(dir.join("secret.json"), r#"{ "token": "x" }"#).unwrap();
+        std::fs::write(dir.join("secret.mjs"), "export const value = 2;").unwrap();
+
+        let config = RuntimeConfig {
+            permissions: Permissions {
+                read: Allow::Only(vec![allowed.clone()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let code = r#"
+            const denied = (e) => e.code;
+            globalThis.lib = (await import('./lib.mjs')).value;
+            globalThis.json = await import('../secret.json').then(() => 'ok', denied);
+            globalThis.module = await import('../secret.mjs').then(() => 'ok', denied);
+        "#;
+        let result = runtime.run(code, &allowed.join("main.mjs").to_string_lossy());
+        let seen = runtime.eval("[lib, json, module].join()", "check.js");
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+        assert_eq!(
+            runtime.value_to_string(&seen.unwrap()),
+            "1,ERR_ACCESS_DENIED,ERR_ACCESS_DENIED"
+        );
+    }
+
+    #[test]
+    fn test_static_import_read_permission() {
+        let dir = std::env::temp_dir().join(format!("viper-static-perm-{}", std::process::id()));
+        let allowed = dir.join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::write(dir.join("secret.mjs"), "export const value = 2;").unwrap();
+
+        let config = RuntimeConfig {
+            permissions: Permissions {
+                read: Allow::Only(vec![allowed.clone()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let code = "import { value } from '../secret.mjs'; globalThis.value = value;";
+        let result = runtime.run(code, &allowed.join("main.mjs").to_string_lossy());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(
+            matches!(result, Err(RuntimeError::PermissionDenied(_))),
+            "{result:?}"
+        );
+    }
+
     #[test]
     fn test_url_api() {
         let mut runtime = Runtime::new().unwrap();
@@ -1,21 +1,40 @@
-//! Ultra-fast Net module - Native Rust implementation
+//! Net module - Node.js compatible TCP and IPC sockets
 //!
-//! Provides Node.js compatible net module with native Rust/Tokio performance.
-//! Uses tokio for async TCP operations with zero-copy where possible.
+//! Sockets and servers are non-blocking mio handles registered with the
+//! event loop's poller under a loop source, so an idle connection costs
+//! nothing until the kernel reports it ready. The JS side then drains it:
+//! `__net_socket_ready` finishes a pending connect, flushes queued writes and
+//! reads until the socket would block, handing each chunk over as a
+//! Uint8Array that becomes a Buffer without another copy.
 //!
-//! This is a CRITICAL module for Express.js and any networking applications.
+//! `net.Socket` is a Duplex. Bytes the kernel won't take yet stay queued
+//! here; a write's callback runs once its bytes have left the queue, which
+//! is what drives `write()`'s return value and `'drain'`. Paths (Unix domain
+//! sockets) work wherever a host and port do.
 
 use boa_engine::{
     Context, JsArgs, JsNativeError, JsObject, JsResult, JsValue, NativeFunction, Source, js_string,
-    object::ObjectInitializer, property::Attribute,
+    object::ObjectInitializer,
+    object::builtins::{JsArray, JsArrayBuffer, JsUint8Array},
+    property::Attribute,
 };
 use std::collections::HashMap;
-use std::io::Write;
-use std::net::{Ipv4Addr, Ipv6Addr, TcpListener, TcpStream};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
 use std::str::FromStr;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+
+use super::dns;
+use super::event_loop::{self, HandleId};
+use super::permissions;
+
+/// Size of each chunk read off a socket
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks read per readiness callback, so one busy socket can't starve the
+/// loop; the source is notified again to read the rest on the next turn
+const READ_BUDGET: usize = 16;
 
 /// Global connection ID counter
 static CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
@@ -58,13 +77,291 @@ fn is_ipv6(input: &str) -> bool {
     Ipv6Addr::from_str(input).is_ok()
 }
 
-/// Active TCP servers - stored globally for event loop integration
-type ServerMap = Arc<Mutex<HashMap<u64, Arc<Mutex<TcpListener>>>>>;
+/// A connected (or connecting) stream socket
+enum Stream {
+    Tcp(mio::net::TcpStream),
+    #[cfg(unix)]
+    Unix(mio::net::UnixStream),
+}
+
+impl Stream {
+    fn io(&mut self) -> &mut dyn mio::event::Source {
+        match self {
+            Stream::Tcp(stream) => stream,
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream,
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown(how),
+        }
+    }
+
+    /// Whether a non-blocking connect has finished; its failure is the error
+    fn connected(&self) -> io::Result<bool> {
+        let (error, peer) = match self {
+            Stream::Tcp(stream) => (stream.take_error()?, stream.peer_addr().map(|_| ())),
+            #[cfg(unix)]
+            Stream::Unix(stream) => (stream.take_error()?, stream.peer_addr().map(|_| ())),
+        };
+        if let Some(error) = error {
+            return Err(error);
+        }
+        match peer {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotConnected => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// A socket and the bytes it still has to send
+struct Connection {
+    stream: Stream,
+    /// Loop source notified when the socket is ready, once attached
+    source: Option<HandleId>,
+    connecting: bool,
+    /// Bytes write() accepted that the kernel hasn't taken yet
+    queued: Vec<u8>,
+    /// end() was called: shut down writing once `queued` is flushed
+    ending: bool,
+    write_shut: bool,
+}
+
+impl Connection {
+    fn new(stream: Stream, connecting: bool) -> Self {
+        Self {
+            stream,
+            source: None,
+            connecting,
+            queued: Vec::new(),
+            ending: false,
+            write_shut: false,
+        }
+    }
+
+    /// Write as much of the queue as the kernel takes, then shut down
+    /// writing if end() is waiting on it
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.queued.is_empty() {
+            match self.stream.write(&self.queued) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.queued.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if self.ending && !self.write_shut {
+            self.write_shut = true;
+            let _ = self.stream.shutdown(Shutdown::Write);
+        }
+        Ok(())
+    }
+}
+
+/// A listening socket
+enum Listener {
+    Tcp(mio::net::TcpListener),
+    /// The socket file is removed again when the server closes
+    #[cfg(unix)]
+    Unix(mio::net::UnixListener, std::path::PathBuf),
+}
 
-lazy_static::lazy_static! {
-    static ref SERVERS: ServerMap = Arc::new(Mutex::new(HashMap::new()));
-    static ref SOCKETS: Arc<Mutex<HashMap<u64, Arc<Mutex<TcpStream>>>>> =
-        Arc::new(Mutex::new(HashMap::new()));
+impl Listener {
+    fn io(&mut self) -> &mut dyn mio::event::Source {
+        match self {
+            Listener::Tcp(listener) => listener,
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => listener,
+        }
+    }
+
+    fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().map(|(stream, _)| Stream::Tcp(stream)),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => {
+                listener.accept().map(|(stream, _)| Stream::Unix(stream))
+            }
+        }
+    }
+}
+
+/// Open sockets by id
+static SOCKETS: Mutex<Option<HashMap<u64, Connection>>> = Mutex::new(None);
+
+/// Listening servers by id
+static SERVERS: Mutex<Option<HashMap<u64, Listener>>> = Mutex::new(None);
+
+fn insert_socket(connection: Connection) -> u64 {
+    let id = next_connection_id();
+    SOCKETS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(id, connection);
+    id
+}
+
+/// The Node error code for a failed socket call
+pub(crate) fn error_code(error: &io::Error) -> &'static str {
+    match error.kind() {
+        io::ErrorKind::ConnectionRefused => "ECONNREFUSED",
+        io::ErrorKind::ConnectionReset => "ECONNRESET",
+        io::ErrorKind::ConnectionAborted => "ECONNABORTED",
+        io::ErrorKind::BrokenPipe | io::ErrorKind::WriteZero => "EPIPE",
+        io::ErrorKind::TimedOut => "ETIMEDOUT",
+        io::ErrorKind::AddrInUse => "EADDRINUSE",
+        io::ErrorKind::AddrNotAvailable => "EADDRNOTAVAIL",
+        io::ErrorKind::NotFound => "ENOENT",
+        io::ErrorKind::PermissionDenied => "EACCES",
+        io::ErrorKind::NotConnected => "ENOTCONN",
+        io::ErrorKind::HostUnreachable => "EHOSTUNREACH",
+        io::ErrorKind::NetworkUnreachable => "ENETUNREACH",
+        io::ErrorKind::InvalidInput => "EINVAL",
+        io::ErrorKind::Unsupported => "ENOTSUP",
+        _ => "EIO",
+    }
+}
+
+/// `{ error, code }` for a failed socket call
+fn error_result(error: &io::Error, context: &mut Context) -> JsValue {
+    ObjectInitializer::new(context)
+        .property(
+            js_string!("error"),
+            js_string!(error.to_string()),
+            Attribute::all(),
+        )
+        .property(
+            js_string!("code"),
+            js_string!(error_code(error)),
+            Attribute::all(),
+        )
+        .build()
+        .into()
+}
+
+fn closed_error() -> io::Error {
+    io::Error::new(io::ErrorKind::NotConnected, "Socket is closed")
+}
+
+/// Set `{prefix}Address`, `{prefix}Port` and `{prefix}Family` on `target`
+fn set_address(
+    target: &JsObject,
+    prefix: &str,
+    addr: SocketAddr,
+    context: &mut Context,
+) -> JsResult<()> {
+    let family = if addr.is_ipv4() { "IPv4" } else { "IPv6" };
+    target.set(
+        js_string!(format!("{}Address", prefix)),
+        js_string!(addr.ip().to_string()),
+        false,
+        context,
+    )?;
+    target.set(
+        js_string!(format!("{}Port", prefix)),
+        addr.port(),
+        false,
+        context,
+    )?;
+    target.set(
+        js_string!(format!("{}Family", prefix)),
+        js_string!(family),
+        false,
+        context,
+    )?;
+    Ok(())
+}
+
+/// The bytes a Uint8Array (or Buffer) views
+pub(crate) fn view_bytes(value: &JsValue, context: &mut Context) -> JsResult<Vec<u8>> {
+    let array = value
+        .as_object()
+        .and_then(|obj| JsUint8Array::from_object(obj.clone()).ok())
+        .ok_or_else(|| JsNativeError::typ().with_message("Expected a Uint8Array"))?;
+    let offset = array.byte_offset(context)?;
+    let length = array.byte_length(context)?;
+    let buffer = array.buffer(context)?;
+    let buffer = buffer
+        .as_object()
+        .and_then(|obj| JsArrayBuffer::from_object(obj.clone()).ok())
+        .ok_or_else(|| JsNativeError::typ().with_message("Expected an ArrayBuffer"))?;
+    let data = buffer
+        .data()
+        .ok_or_else(|| JsNativeError::typ().with_message("ArrayBuffer is detached"))?;
+    Ok(data[offset..offset + length].to_vec())
+}
+
+/// Turn on SO_KEEPALIVE, with the first probe after `delay_secs` of idling
+#[cfg(unix)]
+fn set_keep_alive(fd: std::os::unix::io::RawFd, enable: bool, delay_secs: u32) {
+    let on = enable as libc::c_int;
+    let size = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_KEEPALIVE,
+            &on as *const _ as *const libc::c_void,
+            size,
+        );
+        if enable && delay_secs > 0 {
+            let delay = delay_secs as libc::c_int;
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            let option = libc::TCP_KEEPIDLE;
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            let option = libc::TCP_KEEPALIVE;
+            libc::setsockopt(
+                fd,
+                libc::IPPROTO_TCP,
+                option,
+                &delay as *const _ as *const libc::c_void,
+                size,
+            );
+        }
+    }
+}
+
+/// Set SO_LINGER to 0 so closing sends RST instead of FIN
+#[cfg(unix)]
+fn set_linger_zero(fd: std::os::unix::io::RawFd) {
+    let linger = libc::linger {
+        l_onoff: 1,
+        l_linger: 0,
+    };
+    unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_LINGER,
+            &linger as *const _ as *const libc::c_void,
+            std::mem::size_of::<libc::linger>() as libc::socklen_t,
+        );
+    }
 }
 
 /// Register the net module
@@ -120,6 +417,7 @@ pub fn register_net_module(context: &mut Context) -> JsResult<()> {
         .function(is_ipv4_fn, js_string!("isIPv4"), 1)
         .function(is_ipv6_fn, js_string!("isIPv6"), 1)
         .property(js_string!("Socket"), socket_class.clone(), Attribute::all())
+        .property(js_string!("Stream"), socket_class, Attribute::all())
         .property(js_string!("Server"), server_class, Attribute::all())
         .property(js_string!("BlockList"), block_list_class, Attribute::all())
         .property(
@@ -156,32 +454,69 @@ fn create_socket_class(context: &mut Context) -> JsResult<JsObject> {
 
     let socket_code = r#"
         (function() {
-            const EventEmitter = globalThis.events?.EventEmitter || class {
-                constructor() { this._events = {}; this._maxListeners = 10; }
-                on(e, fn) { if (!this._events[e]) this._events[e] = []; this._events[e].push(fn); return this; }
-                once(e, fn) { const w = (...a) => { this.off(e, w); fn.apply(this, a); }; return this.on(e, w); }
-                off(e, fn) { if (this._events[e]) this._events[e] = this._events[e].filter(f => f !== fn); return this; }
-                emit(e, ...a) { if (this._events[e]) this._events[e].slice().forEach(fn => fn.apply(this, a)); return this._events[e]?.length > 0; }
-                removeAllListeners(e) { if (e) delete this._events[e]; else this._events = {}; return this; }
-                listeners(e) { return this._events[e] || []; }
-                listenerCount(e) { return this._events[e]?.length || 0; }
-            };
-
-            class Socket extends EventEmitter {
+            const { Duplex } = globalThis.stream;
+
+            // An Error shaped like Node's errno exceptions
+            function errnoException(result, syscall, address, port) {
+                const where = port !== undefined ? `${address}:${port}` : address;
+                const err = new Error(`${syscall} ${result.code}${where ? ' ' + where : ''}`);
+                err.code = result.code;
+                err.syscall = syscall;
+                if (address !== undefined) err.address = address;
+                if (port !== undefined) err.port = port;
+                return err;
+            }
+
+            // connect(options[, cb]), connect(port[, host][, cb]) or connect(path[, cb])
+            function normalizeConnectArgs(args) {
+                let options = args[0];
+                let callback = args[args.length - 1];
+                if (typeof callback !== 'function') callback = undefined;
+                if (typeof options === 'string' && !/^\d+$/.test(options)) {
+                    options = { path: options };
+                } else if (typeof options !== 'object' || options === null) {
+                    options = { port: options, host: typeof args[1] === 'string' ? args[1] : undefined };
+                }
+                return [options, callback];
+            }
+
+            function validatePort(port) {
+                const number = Number(port ?? 0);
+                if (!Number.isInteger(number) || number < 0 || number > 65535) {
+                    const err = new RangeError(`Port should be >= 0 and < 65536. Received ${port}.`);
+                    err.code = 'ERR_SOCKET_BAD_PORT';
+                    throw err;
+                }
+                return number;
+            }
+
+            class Socket extends Duplex {
                 constructor(options = {}) {
-                    super();
+                    super(options);
                     this._id = 0;
-                    this._options = options;
-                    this._connected = false;
+                    this._source = null;
                     this._connecting = false;
-                    this._destroyed = false;
-                    this._ended = false;
-                    this._pending = true;
-                    this._readableState = { flowing: null };
-                    this._writableState = {};
-
-                    // Socket properties
-                    this.bufferSize = 0;
+                    this._path = undefined;
+                    this._refed = true;
+                    // Whether the readable side wants more data
+                    this._reading = false;
+                    this._readEnded = false;
+                    this._writeDone = false;
+                    // Writes and end() made before the connection is up
+                    this._pendingWrites = [];
+                    this._pendingFinal = null;
+                    // Total bytes handed to the native queue, how many of them
+                    // are still queued, and the callbacks waiting on them
+                    this._written = 0;
+                    this._queued = 0;
+                    this._writeCallbacks = [];
+                    this._finalCallback = null;
+                    this._timer = null;
+                    this._noDelay = undefined;
+                    this._keepAlive = undefined;
+                    this._candidates = null;
+
+                    this.allowHalfOpen = Boolean(options.allowHalfOpen);
                     this.bytesRead = 0;
                     this.bytesWritten = 0;
                     this.localAddress = undefined;
@@ -191,23 +526,25 @@ fn create_socket_class(context: &mut Context) -> JsResult<JsObject> {
                     this.remotePort = undefined;
                     this.remoteFamily = undefined;
                     this.timeout = undefined;
-                    this.allowHalfOpen = options.allowHalfOpen || false;
+                    this.server = null;
 
-                    // Readable/Writable state
-                    this.readable = true;
-                    this.writable = true;
+                    this.once('end', () => {
+                        this._readEnded = true;
+                        if (!this.allowHalfOpen) this.end();
+                        this._maybeClose();
+                    });
                 }
 
                 get connecting() {
                     return this._connecting;
                 }
 
-                get destroyed() {
-                    return this._destroyed;
+                get pending() {
+                    return this._id === 0 || this._connecting;
                 }
 
-                get pending() {
-                    return this._pending;
+                get bufferSize() {
+                    return this.writableLength;
                 }
 
                 get readyState() {
@@ -219,7 +556,8 @@ fn create_socket_class(context: &mut Context) -> JsResult<JsObject> {
                 }
 
                 address() {
-                    if (!this._connected) return {};
+                    if (this._path !== undefined) return this._path;
+                    if (this._id === 0 || this._connecting) return {};
                     return {
                         address: this.localAddress,
                         family: this.localFamily,
@@ -227,291 +565,352 @@ fn create_socket_class(context: &mut Context) -> JsResult<JsObject> {
                     };
                 }
 
-                connect(options, connectListener) {
-                    // Handle overloaded signatures
-                    if (typeof options === 'number') {
-                        // connect(port[, host][, connectListener])
-                        const port = options;
-                        let host = 'localhost';
-                        if (typeof arguments[1] === 'string') {
-                            host = arguments[1];
-                            if (typeof arguments[2] === 'function') {
-                                connectListener = arguments[2];
-                            }
-                        } else if (typeof arguments[1] === 'function') {
-                            connectListener = arguments[1];
-                        }
-                        options = { port, host };
-                    } else if (typeof options === 'string') {
-                        // connect(path[, connectListener])
-                        const path = options;
-                        if (typeof arguments[1] === 'function') {
-                            connectListener = arguments[1];
-                        }
-                        options = { path };
-                    }
-
+                connect(...args) {
+                    const [options, connectListener] = normalizeConnectArgs(args);
                     if (connectListener) {
                         this.once('connect', connectListener);
                     }
-
+                    if (options.timeout) {
+                        this.setTimeout(options.timeout);
+                    }
                     this._connecting = true;
-                    this._pending = true;
 
-                    // Use native connect
-                    const host = options.host || 'localhost';
-                    const port = options.port || 0;
-
-                    try {
-                        const result = __net_socket_connect(host, port, options.timeout || 0);
+                    if (options.path !== undefined) {
+                        this._path = String(options.path);
+                        const result = __net_socket_connect_path(this._path);
                         if (result.error) {
-                            this._connecting = false;
-                            setImmediate(() => {
-                                const err = new Error(result.error);
-                                err.code = 'ECONNREFUSED';
-                                this.emit('error', err);
-                            });
-                            return this;
+                            this._fail(errnoException(result, 'connect', this._path));
+                        } else {
+                            this._id = result.id;
+                            this._attach();
                         }
+                        return this;
+                    }
 
-                        this._id = result.id;
-                        this._connected = true;
-                        this._connecting = false;
-                        this._pending = false;
-                        this.localAddress = result.localAddress;
-                        this.localPort = result.localPort;
-                        this.localFamily = result.localFamily;
-                        this.remoteAddress = result.remoteAddress;
-                        this.remotePort = result.remotePort;
-                        this.remoteFamily = result.remoteFamily;
-
-                        // Start the read loop
-                        this._startReadLoop();
-
-                        setImmediate(() => {
-                            this.emit('connect');
-                            this.emit('ready');
+                    const host = options.host || 'localhost';
+                    const port = validatePort(options.port);
+
+                    // IP literals connect directly; names go through dns.lookup
+                    // (or options.lookup) on the shared resolver first
+                    if (globalThis.net.isIP(host)) {
+                        this._connectTo(host, port, [host]);
+                        return this;
+                    }
+                    const lookup = options.lookup ?? globalThis.dns.lookup;
+                    try {
+                        lookup(host, { family: options.family, all: true }, (err, addresses) => {
+                            if (this.destroyed) return;
+                            const first = addresses?.[0];
+                            this.emit('lookup', err, first?.address, first?.family, host);
+                            if (err) {
+                                this._fail(err);
+                                return;
+                            }
+                            this._connectTo(host, port, addresses.map((entry) => entry.address));
                         });
                     } catch (err) {
-                        this._connecting = false;
-                        setImmediate(() => this.emit('error', err));
+                        this._fail(err);
                     }
 
                     return this;
                 }
 
-                _startReadLoop() {
-                    if (this._destroyed || !this._connected) return;
-
-                    const poll = () => {
-                        if (this._destroyed || this._id === 0) return;
-                        if (this._readableState.flowing === false) {
-                            // Paused - check again later
-                            setTimeout(poll, 50);
-                            return;
+                // Try each address in turn until one accepts the connection
+                _connectTo(host, port, addresses) {
+                    let failure;
+                    while (addresses.length > 0) {
+                        const address = addresses.shift();
+                        let result;
+                        try {
+                            result = __net_socket_connect(host, address, port);
+                        } catch (err) {
+                            // Not allowed to reach this address; try the next
+                            failure = err;
+                            continue;
                         }
-
-                        const result = __net_socket_read(this._id);
-
                         if (result.error) {
-                            this.emit('error', new Error(result.error));
-                            this.destroy();
-                            return;
+                            failure = errnoException(result, 'connect', address, port);
+                            continue;
                         }
+                        this._id = result.id;
+                        this._candidates = { host, port, address, addresses };
+                        this._attach();
+                        return;
+                    }
+                    this._fail(failure);
+                }
 
-                        if (result.eof) {
-                            this.readable = false;
-                            this.emit('end');
-                            if (!this.allowHalfOpen) {
-                                this.destroy();
-                            }
-                            return;
-                        }
-
-                        if (result.data && result.bytesRead > 0) {
-                            this.bytesRead += result.bytesRead;
-                            // Convert array to Buffer
-                            const buf = Buffer.from(result.data);
-                            this.emit('data', buf);
-                        }
-
-                        // Continue polling if not destroyed
-                        if (!this._destroyed && this._id > 0) {
-                            setImmediate(poll);
-                        }
-                    };
-
-                    setImmediate(poll);
+                _fail(err) {
+                    this._connecting = false;
+                    queueMicrotask(() => this.destroy(err));
                 }
 
-                write(data, encoding, callback) {
-                    if (typeof encoding === 'function') {
-                        callback = encoding;
-                        encoding = 'utf8';
+                _attach() {
+                    if (this._source === null) {
+                        this._source = __viper_loop_source(() => this._ready());
+                        __viper_loop_ref(this._source, this._refed);
                     }
-                    encoding = encoding || 'utf8';
+                    __net_socket_attach(this._id, this._source);
+                }
 
-                    if (this._destroyed || !this.writable) {
-                        const err = new Error('Socket is not writable');
-                        if (callback) callback(err);
-                        return false;
-                    }
+                // Adopt a connection accepted by a server
+                _accept(id, reading) {
+                    this._id = id;
+                    this._attach();
+                    Object.assign(this, __net_socket_address(id));
+                    this._reading = reading;
+                    this._applyOptions();
+                }
 
-                    if (!this._connected) {
-                        // Buffer the write until connected
-                        this.once('connect', () => this.write(data, encoding, callback));
-                        return false;
-                    }
+                _ready() {
+                    if (this._id === 0) return;
+                    const result = __net_socket_ready(this._id, this._reading);
 
-                    try {
-                        let bytes;
-                        if (typeof data === 'string') {
-                            bytes = new TextEncoder().encode(data);
-                        } else if (data instanceof Uint8Array) {
-                            bytes = data;
-                        } else if (Buffer.isBuffer(data)) {
-                            bytes = new Uint8Array(data);
+                    if (result.error && this._connecting) {
+                        // Refused: move on to the next address, if any
+                        const { host, port, address, addresses } = this._candidates ?? {};
+                        __net_socket_destroy(this._id, false);
+                        this._id = 0;
+                        if (addresses?.length > 0) {
+                            this._connectTo(host, port, addresses);
                         } else {
-                            bytes = new TextEncoder().encode(String(data));
+                            this._fail(errnoException(result, 'connect', address ?? this._path, port));
                         }
-
-                        const result = __net_socket_write(this._id, bytes);
-                        if (result.error) {
-                            const err = new Error(result.error);
-                            this.emit('error', err);
-                            if (callback) callback(err);
-                            return false;
+                        return;
+                    }
+                    if (result.connected) {
+                        const wasReading = this._reading;
+                        this._onConnect();
+                        // Data that arrived with the connect is read right
+                        // away; when already reading, it came with the result
+                        if (!wasReading) {
+                            this._ready();
+                            return;
                         }
+                    }
 
-                        this.bytesWritten += result.bytesWritten;
-                        if (callback) callback();
-                        return true;
-                    } catch (err) {
-                        this.emit('error', err);
-                        if (callback) callback(err);
-                        return false;
+                    for (const chunk of result.chunks ?? []) {
+                        this.bytesRead += chunk.byteLength;
+                        this._reading = this.push(Buffer.from(chunk.buffer, chunk.byteOffset, chunk.byteLength));
+                        this._touch();
+                    }
+                    if (result.queued !== undefined) {
+                        this._queued = result.queued;
+                        this._settleWrites();
+                    }
+                    if (result.error) {
+                        this.destroy(errnoException(result, 'read'));
+                        return;
+                    }
+                    if (result.eof) {
+                        this._reading = false;
+                        this.push(null);
                     }
                 }
 
-                end(data, encoding, callback) {
-                    if (typeof data === 'function') {
-                        callback = data;
-                        data = undefined;
-                    } else if (typeof encoding === 'function') {
-                        callback = encoding;
-                        encoding = undefined;
+                _onConnect() {
+                    this._connecting = false;
+                    this._candidates = null;
+                    Object.assign(this, __net_socket_address(this._id));
+                    this._applyOptions();
+                    this._touch();
+                    // Start reading, as Node does, so EOF is seen even
+                    // before anyone listens for data
+                    this._reading = true;
+
+                    const writes = this._pendingWrites;
+                    this._pendingWrites = [];
+                    for (const [chunk, encoding, callback] of writes) {
+                        this._write(chunk, encoding, callback);
                     }
-
-                    if (data !== undefined) {
-                        this.write(data, encoding);
+                    if (this._pendingFinal) {
+                        const callback = this._pendingFinal;
+                        this._pendingFinal = null;
+                        this._final(callback);
                     }
 
-                    this.writable = false;
-                    this._ended = true;
+                    this.emit('connect');
+                    this.emit('ready');
+                }
 
-                    if (this._id > 0) {
-                        __net_socket_end(this._id);
+                _applyOptions() {
+                    if (this._noDelay !== undefined) {
+                        __net_socket_set_no_delay(this._id, this._noDelay);
                     }
+                    if (this._keepAlive !== undefined) {
+                        __net_socket_set_keep_alive(this._id, ...this._keepAlive);
+                    }
+                }
 
-                    setImmediate(() => {
-                        this.emit('end');
-                        if (callback) callback();
-                        if (!this.allowHalfOpen) {
-                            this.destroy();
-                        }
-                    });
-
-                    return this;
+                _read() {
+                    this._reading = true;
+                    if (this._id !== 0 && !this._connecting) {
+                        this._ready();
+                    }
                 }
 
-                destroy(error) {
-                    if (this._destroyed) return this;
+                _write(chunk, encoding, callback) {
+                    if (this._connecting) {
+                        this._pendingWrites.push([chunk, encoding, callback]);
+                        return;
+                    }
+                    if (this._id === 0) {
+                        const err = new Error('This socket is closed');
+                        err.code = 'ERR_SOCKET_CLOSED';
+                        callback(err);
+                        return;
+                    }
+                    const result = __net_socket_write(this._id, chunk);
+                    if (result.error) {
+                        callback(errnoException(result, 'write'));
+                        return;
+                    }
+                    this._written += chunk.length;
+                    this.bytesWritten += chunk.length;
+                    this._queued = result.queued;
+                    this._writeCallbacks.push([this._written, callback]);
+                    this._touch();
+                    // Callbacks never run inside write(), so 'drain' follows
+                    // a write() that returned false even when nothing queued
+                    queueMicrotask(() => this._settleWrites());
+                }
 
-                    this._destroyed = true;
-                    this.readable = false;
-                    this.writable = false;
+                _final(callback) {
+                    if (this._connecting) {
+                        this._pendingFinal = callback;
+                        return;
+                    }
+                    const done = () => {
+                        this._writeDone = true;
+                        callback();
+                        this._maybeClose();
+                    };
+                    if (this._id === 0) {
+                        done();
+                        return;
+                    }
+                    this._finalCallback = done;
+                    this._queued = __net_socket_end(this._id).queued;
+                    queueMicrotask(() => this._settleWrites());
+                }
 
-                    if (this._id > 0) {
-                        __net_socket_destroy(this._id);
-                        this._id = 0;
+                // Run the callbacks of writes whose bytes have left the queue
+                _settleWrites() {
+                    const flushed = this._written - this._queued;
+                    while (this._writeCallbacks.length > 0 && this._writeCallbacks[0][0] <= flushed) {
+                        this._writeCallbacks.shift()[1]();
                     }
+                    if (this._queued === 0 && this._finalCallback) {
+                        const callback = this._finalCallback;
+                        this._finalCallback = null;
+                        callback();
+                    }
+                }
 
-                    setImmediate(() => {
-                        if (error) {
-                            this.emit('error', error);
-                        }
-                        this.emit('close', !!error);
-                    });
+                // Both sides are done: close after this tick's 'end'/'finish' listeners
+                _maybeClose() {
+                    if (this._readEnded && this._writeDone && !this.destroyed) {
+                        queueMicrotask(() => this.destroy());
+                    }
+                }
 
-                    return this;
+                _touch() {
+                    if (this._timer) this._timer.refresh();
                 }
 
-                destroySoon() {
-                    if (this.writable) {
-                        this.end();
+                _close(reset) {
+                    if (this._id !== 0) {
+                        __net_socket_destroy(this._id, reset);
+                        this._id = 0;
                     }
-                    if (!this._writableState.finished) {
-                        this.once('finish', () => this.destroy());
-                    } else {
-                        this.destroy();
+                    if (this._source !== null) {
+                        __viper_loop_close(this._source);
+                        this._source = null;
                     }
                 }
 
-                pause() {
-                    this._readableState.flowing = false;
-                    return this;
+                _destroy(err, callback) {
+                    this._connecting = false;
+                    this._reading = false;
+                    if (this._timer) {
+                        clearTimeout(this._timer);
+                        this._timer = null;
+                    }
+                    this._close(false);
+                    this._pendingWrites = [];
+                    this._writeCallbacks = [];
+                    this._finalCallback = null;
+                    callback(err);
                 }
 
-                resume() {
-                    this._readableState.flowing = true;
-                    return this;
+                destroy(err) {
+                    if (err && !this.destroyed) this._hadError = true;
+                    return super.destroy(err);
+                }
+
+                // 'close' says whether the socket went down with an error
+                emit(event, ...args) {
+                    if (event === 'close' && args.length === 0) {
+                        return super.emit('close', this._hadError === true);
+                    }
+                    return super.emit(event, ...args);
                 }
 
                 setTimeout(timeout, callback) {
                     this.timeout = timeout;
-                    if (callback) {
-                        this.once('timeout', callback);
+                    if (this._timer) {
+                        clearTimeout(this._timer);
+                        this._timer = null;
                     }
-                    if (this._id > 0 && timeout > 0) {
-                        __net_socket_set_timeout(this._id, timeout);
+                    if (timeout > 0) {
+                        this._timer = setTimeout(() => this.emit('timeout'), timeout);
+                        // The socket itself decides whether the loop stays alive
+                        this._timer.unref();
+                    }
+                    if (callback) {
+                        if (timeout > 0) this.once('timeout', callback);
+                        else this.off('timeout', callback);
                     }
                     return this;
                 }
 
                 setNoDelay(noDelay = true) {
-                    if (this._id > 0) {
-                        __net_socket_set_no_delay(this._id, noDelay);
+                    this._noDelay = Boolean(noDelay);
+                    if (this._id !== 0 && !this._connecting) {
+                        __net_socket_set_no_delay(this._id, this._noDelay);
                     }
                     return this;
                 }
 
                 setKeepAlive(enable = false, initialDelay = 0) {
-                    if (this._id > 0) {
-                        __net_socket_set_keep_alive(this._id, enable, initialDelay);
+                    this._keepAlive = [Boolean(enable), Math.max(0, Math.floor(initialDelay))];
+                    if (this._id !== 0 && !this._connecting) {
+                        __net_socket_set_keep_alive(this._id, ...this._keepAlive);
                     }
                     return this;
                 }
 
-                setEncoding(encoding) {
-                    this._encoding = encoding;
-                    return this;
-                }
-
                 ref() {
-                    // Keep event loop alive
+                    this._refed = true;
+                    if (this._source !== null) __viper_loop_ref(this._source, true);
                     return this;
                 }
 
                 unref() {
-                    // Allow event loop to exit
+                    this._refed = false;
+                    if (this._source !== null) __viper_loop_ref(this._source, false);
                     return this;
                 }
 
+                destroySoon() {
+                    if (this.writable) this.end();
+                    if (this.writableFinished) this.destroy();
+                    else this.once('finish', () => this.destroy());
+                }
+
                 resetAndDestroy() {
-                    // Send RST packet and destroy
-                    if (this._id > 0) {
-                        __net_socket_reset(this._id);
-                    }
+                    // Send RST instead of FIN
+                    this._close(true);
                     return this.destroy();
                 }
             }
@@ -531,255 +930,282 @@ fn create_socket_class(context: &mut Context) -> JsResult<JsObject> {
 
 /// Register native socket operations
 fn register_native_socket_ops(context: &mut Context) -> JsResult<()> {
-    // __net_socket_connect(host, port, timeout) -> { id, localAddress, ... } or { error }
+    // __net_socket_connect(host, address, port) -> { id } or { error, code }
+    //
+    // Starts a non-blocking connect to `address`, one of the IPs `host`
+    // resolved to; the socket reports the outcome once attached. Throws when
+    // the permissions allow neither the address nor `host` resolving to it.
     let connect_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let host = args
             .get_or_undefined(0)
             .to_string(context)?
             .to_std_string_escaped();
-        let port = args.get_or_undefined(1).to_u32(context)? as u16;
-        let timeout_ms = args.get_or_undefined(2).to_u32(context).unwrap_or(0);
-
-        // Create socket address
-        let addr_str = format!("{}:{}", host, port);
-
-        // Connect with optional timeout
-        let stream_result = if timeout_ms > 0 {
-            // First resolve the address
-            match std::net::ToSocketAddrs::to_socket_addrs(&addr_str) {
-                Ok(mut addrs) => {
-                    if let Some(addr) = addrs.next() {
-                        let stream = TcpStream::connect_timeout(
-                            &addr,
-                            Duration::from_millis(timeout_ms as u64),
-                        );
-                        stream
-                    } else {
-                        Err(std::io::Error::new(
-                            std::io::ErrorKind::NotFound,
-                            "Could not resolve address",
-                        ))
-                    }
-                }
-                Err(e) => Err(e),
+        let address = args
+            .get_or_undefined(1)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let port = args.get_or_undefined(2).to_u32(context)? as u16;
+
+        let ip = match address.trim_matches(['[', ']']).parse::<IpAddr>() {
+            Ok(ip) => ip,
+            Err(_) => {
+                let error = io::Error::new(io::ErrorKind::InvalidInput, "Invalid IP address");
+                return Ok(error_result(&error, context));
             }
-        } else {
-            TcpStream::connect(&addr_str)
         };
-
-        match stream_result {
+        let addr = SocketAddr::new(ip, port);
+        dns::check_connect(context, &host, addr)?;
+        match mio::net::TcpStream::connect(addr) {
             Ok(stream) => {
-                // Set non-blocking for async operations
-                let _ = stream.set_nonblocking(true);
-
-                let id = next_connection_id();
-                let local_addr = stream.local_addr().ok();
-                let peer_addr = stream.peer_addr().ok();
-
-                // Store the stream
-                SOCKETS
-                    .lock()
-                    .unwrap()
-                    .insert(id, Arc::new(Mutex::new(stream)));
-
-                // Build result object
+                let id = insert_socket(Connection::new(Stream::Tcp(stream), true));
                 let result = JsObject::with_null_proto();
-                result.set(js_string!("id"), JsValue::from(id as f64), false, context)?;
-
-                if let Some(addr) = local_addr {
-                    result.set(
-                        js_string!("localAddress"),
-                        JsValue::from(js_string!(addr.ip().to_string())),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("localPort"),
-                        JsValue::from(addr.port()),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("localFamily"),
-                        JsValue::from(js_string!(if addr.is_ipv4() { "IPv4" } else { "IPv6" })),
-                        false,
-                        context,
-                    )?;
-                }
-
-                if let Some(addr) = peer_addr {
-                    result.set(
-                        js_string!("remoteAddress"),
-                        JsValue::from(js_string!(addr.ip().to_string())),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("remotePort"),
-                        JsValue::from(addr.port()),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("remoteFamily"),
-                        JsValue::from(js_string!(if addr.is_ipv4() { "IPv4" } else { "IPv6" })),
-                        false,
-                        context,
-                    )?;
-                }
-
-                Ok(JsValue::from(result))
+                result.set(js_string!("id"), id as f64, false, context)?;
+                Ok(result.into())
             }
-            Err(e) => {
+            Err(e) => Ok(error_result(&e, context)),
+        }
+    });
+    context.register_global_callable(js_string!("__net_socket_connect"), 3, connect_fn)?;
+
+    // __net_socket_connect_path(path) -> { id } or { error, code }
+    let connect_path_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let path = args
+            .get_or_undefined(0)
+            .to_string(context)?
+            .to_std_string_escaped();
+        permissions::check_read(context, &path)?;
+        permissions::check_write(context, &path)?;
+
+        #[cfg(unix)]
+        let stream = mio::net::UnixStream::connect(&path).map(Stream::Unix);
+        #[cfg(not(unix))]
+        let stream: io::Result<Stream> = Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "IPC paths are not supported on this platform",
+        ));
+        match stream {
+            Ok(stream) => {
+                let id = insert_socket(Connection::new(stream, true));
                 let result = JsObject::with_null_proto();
-                result.set(
-                    js_string!("error"),
-                    JsValue::from(js_string!(e.to_string())),
-                    false,
-                    context,
-                )?;
-                Ok(JsValue::from(result))
+                result.set(js_string!("id"), id as f64, false, context)?;
+                Ok(result.into())
             }
+            Err(e) => Ok(error_result(&e, context)),
         }
     });
+    context.register_global_callable(
+        js_string!("__net_socket_connect_path"),
+        1,
+        connect_path_fn,
+    )?;
 
-    // __net_socket_write(id, data) -> { bytesWritten } or { error }
-    let write_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+    // __net_socket_attach(id, source) - poll the socket under a loop source
+    let attach_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args.get_or_undefined(0).to_number(context)? as u64;
-        let data = args.get_or_undefined(1);
-
-        // Get the data as bytes - use array-like access for Buffer/TypedArray
-        let bytes: Vec<u8> = if let Some(obj) = data.as_object() {
-            // Use array-like access (works for Buffer, Uint8Array, etc.)
-            let len = obj
-                .get(js_string!("length"), context)?
-                .to_number(context)
-                .unwrap_or(0.0) as usize;
-            let mut result = Vec::with_capacity(len);
-            for i in 0..len {
-                if let Ok(val) = obj.get(i as u32, context) {
-                    if let Some(byte) = val.as_number() {
-                        result.push(byte as u8);
-                    }
-                }
-            }
-            result
-        } else {
-            data.to_string(context)?
-                .to_std_string_escaped()
-                .into_bytes()
+        let source = args.get_or_undefined(1).to_u32(context)?;
+        let event_loop = event_loop::current(context)?;
+
+        let mut sockets = SOCKETS.lock().unwrap();
+        let Some(connection) = sockets.get_or_insert_with(HashMap::new).get_mut(&id) else {
+            return Ok(JsValue::undefined());
         };
+        event_loop
+            .register_io(
+                connection.stream.io(),
+                source,
+                mio::Interest::READABLE | mio::Interest::WRITABLE,
+            )
+            .map_err(|e| JsNativeError::error().with_message(e.to_string()))?;
+        connection.source = Some(source);
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__net_socket_attach"), 2, attach_fn)?;
+
+    // __net_socket_ready(id, reading) -> { connected, chunks, eof, queued, error, code }
+    //
+    // Run after the socket's source fires: finishes a pending connect,
+    // flushes queued writes and, while JS wants data, reads until the socket
+    // would block or the read budget runs out.
+    let ready_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let reading = args.get_or_undefined(1).to_boolean();
 
-        let sockets = SOCKETS.lock().unwrap();
-        if let Some(stream_arc) = sockets.get(&id) {
-            let mut stream = stream_arc.lock().unwrap();
-            match stream.write_all(&bytes) {
-                Ok(()) => {
-                    let _ = stream.flush();
-                    let result = JsObject::with_null_proto();
-                    result.set(
-                        js_string!("bytesWritten"),
-                        JsValue::from(bytes.len() as f64),
-                        false,
-                        context,
-                    )?;
-                    Ok(JsValue::from(result))
+        let mut sockets = SOCKETS.lock().unwrap();
+        let Some(connection) = sockets.get_or_insert_with(HashMap::new).get_mut(&id) else {
+            return Ok(error_result(&closed_error(), context));
+        };
+
+        let result = JsObject::with_null_proto();
+        if connection.connecting {
+            match connection.stream.connected() {
+                Ok(true) => {
+                    connection.connecting = false;
+                    result.set(js_string!("connected"), true, false, context)?;
                 }
-                Err(e) => {
-                    let result = JsObject::with_null_proto();
-                    result.set(
-                        js_string!("error"),
-                        JsValue::from(js_string!(e.to_string())),
-                        false,
-                        context,
-                    )?;
-                    Ok(JsValue::from(result))
+                Ok(false) => return Ok(result.into()),
+                Err(e) => return Ok(error_result(&e, context)),
+            }
+        }
+
+        let mut failure = connection.flush().err();
+
+        let chunks = JsArray::new(context);
+        let mut eof = false;
+        let mut budget_left = true;
+        if reading && failure.is_none() {
+            let mut buf = vec![0u8; CHUNK_SIZE];
+            budget_left = false;
+            for _ in 0..READ_BUDGET {
+                match connection.stream.read(&mut buf) {
+                    Ok(0) => {
+                        eof = true;
+                        budget_left = true;
+                        break;
+                    }
+                    Ok(n) => {
+                        let chunk = JsUint8Array::from_iter(buf[..n].iter().copied(), context)?;
+                        chunks.push(chunk, context)?;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        budget_left = true;
+                        break;
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        failure = Some(e);
+                        budget_left = true;
+                        break;
+                    }
                 }
             }
-        } else {
-            let result = JsObject::with_null_proto();
+        }
+        // Readiness is edge-triggered: come back for what's left unread
+        if let (false, Some(source)) = (budget_left, connection.source) {
+            event_loop::current(context)?.notifier(source).notify();
+        }
+
+        result.set(js_string!("chunks"), chunks, false, context)?;
+        result.set(js_string!("eof"), eof, false, context)?;
+        result.set(
+            js_string!("queued"),
+            connection.queued.len() as f64,
+            false,
+            context,
+        )?;
+        if let Some(e) = failure {
             result.set(
                 js_string!("error"),
-                JsValue::from(js_string!("Socket not found")),
+                js_string!(e.to_string()),
+                false,
+                context,
+            )?;
+            result.set(
+                js_string!("code"),
+                js_string!(error_code(&e)),
                 false,
                 context,
             )?;
-            Ok(JsValue::from(result))
         }
+        Ok(result.into())
     });
+    context.register_global_callable(js_string!("__net_socket_ready"), 2, ready_fn)?;
 
-    // __net_socket_end(id)
-    let end_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+    // __net_socket_write(id, chunk) -> { queued } or { error, code }
+    let write_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args.get_or_undefined(0).to_number(context)? as u64;
-        let sockets = SOCKETS.lock().unwrap();
-        if let Some(stream_arc) = sockets.get(&id) {
-            let mut stream = stream_arc.lock().unwrap();
-            let _ = stream.shutdown(std::net::Shutdown::Write);
+        let bytes = view_bytes(args.get_or_undefined(1), context)?;
+
+        let mut sockets = SOCKETS.lock().unwrap();
+        let Some(connection) = sockets.get_or_insert_with(HashMap::new).get_mut(&id) else {
+            return Ok(error_result(&closed_error(), context));
+        };
+        connection.queued.extend_from_slice(&bytes);
+        if let Err(e) = connection.flush() {
+            return Ok(error_result(&e, context));
         }
-        Ok(JsValue::undefined())
+        let result = JsObject::with_null_proto();
+        result.set(
+            js_string!("queued"),
+            connection.queued.len() as f64,
+            false,
+            context,
+        )?;
+        Ok(result.into())
     });
+    context.register_global_callable(js_string!("__net_socket_write"), 2, write_fn)?;
 
-    // __net_socket_destroy(id)
-    let destroy_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+    // __net_socket_end(id) -> { queued } - half-close once the queue is flushed
+    let end_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args.get_or_undefined(0).to_number(context)? as u64;
+
         let mut sockets = SOCKETS.lock().unwrap();
-        if let Some(stream_arc) = sockets.remove(&id) {
-            if let Ok(mut stream) = stream_arc.lock() {
-                let _ = stream.shutdown(std::net::Shutdown::Both);
+        let queued = match sockets.get_or_insert_with(HashMap::new).get_mut(&id) {
+            Some(connection) => {
+                connection.ending = true;
+                let _ = connection.flush();
+                connection.queued.len()
             }
-        }
-        Ok(JsValue::undefined())
+            None => 0,
+        };
+        let result = JsObject::with_null_proto();
+        result.set(js_string!("queued"), queued as f64, false, context)?;
+        Ok(result.into())
     });
+    context.register_global_callable(js_string!("__net_socket_end"), 1, end_fn)?;
 
-    // __net_socket_reset(id) - send RST
-    let reset_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+    // __net_socket_destroy(id, reset) - stop polling and close; `reset`
+    // sends RST instead of FIN
+    let destroy_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args.get_or_undefined(0).to_number(context)? as u64;
-        let mut sockets = SOCKETS.lock().unwrap();
-        if let Some(stream_arc) = sockets.remove(&id) {
-            if let Ok(stream) = stream_arc.lock() {
-                // Set SO_LINGER to 0 to send RST on close
-                #[cfg(unix)]
-                {
-                    use std::os::unix::io::AsRawFd;
-                    unsafe {
-                        let linger = libc::linger {
-                            l_onoff: 1,
-                            l_linger: 0,
-                        };
-                        libc::setsockopt(
-                            stream.as_raw_fd(),
-                            libc::SOL_SOCKET,
-                            libc::SO_LINGER,
-                            &linger as *const _ as *const libc::c_void,
-                            std::mem::size_of::<libc::linger>() as libc::socklen_t,
-                        );
-                    }
+        let reset = args.get_or_undefined(1).to_boolean();
+
+        let removed = SOCKETS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .remove(&id);
+        if let Some(mut connection) = removed {
+            if connection.source.is_some() {
+                let _ = event_loop::current(context)?.deregister_io(connection.stream.io());
+            }
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+                if let (true, Stream::Tcp(stream)) = (reset, &connection.stream) {
+                    set_linger_zero(stream.as_raw_fd());
                 }
-                drop(stream);
             }
+            #[cfg(not(unix))]
+            let _ = reset;
         }
         Ok(JsValue::undefined())
     });
+    context.register_global_callable(js_string!("__net_socket_destroy"), 2, destroy_fn)?;
 
-    // __net_socket_set_timeout(id, timeout)
-    let set_timeout_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+    // __net_socket_address(id) -> { localAddress, localPort, ..., remoteFamily }
+    let address_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args.get_or_undefined(0).to_number(context)? as u64;
-        let timeout_ms = args.get_or_undefined(1).to_u32(context)?;
 
-        let sockets = SOCKETS.lock().unwrap();
-        if let Some(stream_arc) = sockets.get(&id) {
-            if let Ok(stream) = stream_arc.lock() {
-                let timeout = if timeout_ms > 0 {
-                    Some(Duration::from_millis(timeout_ms as u64))
-                } else {
-                    None
-                };
-                let _ = stream.set_read_timeout(timeout);
-                let _ = stream.set_write_timeout(timeout);
-            }
+        let result = JsObject::with_null_proto();
+        let addresses = match SOCKETS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .get(&id)
+            .map(|connection| &connection.stream)
+        {
+            Some(Stream::Tcp(stream)) => (stream.local_addr().ok(), stream.peer_addr().ok()),
+            _ => (None, None),
+        };
+        if let Some(addr) = addresses.0 {
+            set_address(&result, "local", addr, context)?;
         }
-        Ok(JsValue::undefined())
+        if let Some(addr) = addresses.1 {
+            set_address(&result, "remote", addr, context)?;
+        }
+        Ok(result.into())
     });
+    context.register_global_callable(js_string!("__net_socket_address"), 1, address_fn)?;
 
     // __net_socket_set_no_delay(id, noDelay)
     let set_no_delay_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
@@ -787,155 +1213,47 @@ fn register_native_socket_ops(context: &mut Context) -> JsResult<()> {
         let no_delay = args.get_or_undefined(1).to_boolean();
 
         let sockets = SOCKETS.lock().unwrap();
-        if let Some(stream_arc) = sockets.get(&id) {
-            if let Ok(stream) = stream_arc.lock() {
-                let _ = stream.set_nodelay(no_delay);
-            }
+        if let Some(Connection {
+            stream: Stream::Tcp(stream),
+            ..
+        }) = sockets.as_ref().and_then(|sockets| sockets.get(&id))
+        {
+            let _ = stream.set_nodelay(no_delay);
         }
         Ok(JsValue::undefined())
     });
+    context.register_global_callable(
+        js_string!("__net_socket_set_no_delay"),
+        2,
+        set_no_delay_fn,
+    )?;
 
-    // __net_socket_set_keep_alive(id, enable, delay)
+    // __net_socket_set_keep_alive(id, enable, initialDelayMs)
     let set_keep_alive_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args.get_or_undefined(0).to_number(context)? as u64;
-        let _enable = args.get_or_undefined(1).to_boolean();
-        let _delay = args.get_or_undefined(2).to_u32(context).unwrap_or(0);
-
-        // Note: Rust std doesn't expose keep-alive with delay directly
-        // Would need socket2 crate or platform-specific code
-        let _ = id;
-        Ok(JsValue::undefined())
-    });
-
-    // __net_socket_read(id) -> { data: Uint8Array, bytesRead } or { error } or { eof: true }
-    let read_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
-        use std::io::Read;
-
-        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let enable = args.get_or_undefined(1).to_boolean();
+        let delay_ms = args.get_or_undefined(2).to_u32(context).unwrap_or(0);
 
         let sockets = SOCKETS.lock().unwrap();
-        if let Some(stream_arc) = sockets.get(&id) {
-            let mut stream = stream_arc.lock().unwrap();
-
-            // Read up to 64KB at a time
-            let mut buffer = vec![0u8; 65536];
-
-            match stream.read(&mut buffer) {
-                Ok(0) => {
-                    // EOF
-                    let result = JsObject::with_null_proto();
-                    result.set(js_string!("eof"), JsValue::from(true), false, context)?;
-                    Ok(JsValue::from(result))
-                }
-                Ok(n) => {
-                    // Got data - create a Uint8Array
-                    buffer.truncate(n);
-
-                    // Create array with the bytes
-                    let arr = boa_engine::object::builtins::JsArray::new(context);
-                    for (i, byte) in buffer.iter().enumerate() {
-                        arr.set(i as u32, JsValue::from(*byte as i32), false, context)?;
-                    }
-
-                    let result = JsObject::with_null_proto();
-                    result.set(js_string!("data"), JsValue::from(arr), false, context)?;
-                    result.set(
-                        js_string!("bytesRead"),
-                        JsValue::from(n as f64),
-                        false,
-                        context,
-                    )?;
-                    Ok(JsValue::from(result))
-                }
-                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // No data available (non-blocking)
-                    let result = JsObject::with_null_proto();
-                    result.set(
-                        js_string!("wouldBlock"),
-                        JsValue::from(true),
-                        false,
-                        context,
-                    )?;
-                    Ok(JsValue::from(result))
-                }
-                Err(e) => {
-                    let result = JsObject::with_null_proto();
-                    result.set(
-                        js_string!("error"),
-                        JsValue::from(js_string!(e.to_string())),
-                        false,
-                        context,
-                    )?;
-                    Ok(JsValue::from(result))
-                }
+        if let Some(Connection {
+            stream: Stream::Tcp(stream),
+            ..
+        }) = sockets.as_ref().and_then(|sockets| sockets.get(&id))
+        {
+            #[cfg(unix)]
+            {
+                use std::os::unix::io::AsRawFd;
+                set_keep_alive(stream.as_raw_fd(), enable, delay_ms / 1000);
             }
-        } else {
-            let result = JsObject::with_null_proto();
-            result.set(
-                js_string!("error"),
-                JsValue::from(js_string!("Socket not found")),
-                false,
-                context,
-            )?;
-            Ok(JsValue::from(result))
+            #[cfg(not(unix))]
+            let _ = (stream, enable, delay_ms);
         }
+        Ok(JsValue::undefined())
     });
-
-    // Register all native functions
-    let global = context.global_object();
-    global.set(
-        js_string!("__net_socket_connect"),
-        connect_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_socket_write"),
-        write_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_socket_end"),
-        end_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_socket_destroy"),
-        destroy_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_socket_reset"),
-        reset_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_socket_set_timeout"),
-        set_timeout_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_socket_set_no_delay"),
-        set_no_delay_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
+    context.register_global_callable(
         js_string!("__net_socket_set_keep_alive"),
-        set_keep_alive_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_socket_read"),
-        read_fn.to_js_function(context.realm()),
-        false,
-        context,
+        3,
+        set_keep_alive_fn,
     )?;
 
     Ok(())
@@ -948,18 +1266,17 @@ fn create_server_class(context: &mut Context) -> JsResult<JsObject> {
 
     let server_code = r#"
         (function() {
-            const EventEmitter = globalThis.events?.EventEmitter || class {
-                constructor() { this._events = {}; this._maxListeners = 10; }
-                on(e, fn) { if (!this._events[e]) this._events[e] = []; this._events[e].push(fn); return this; }
-                once(e, fn) { const w = (...a) => { this.off(e, w); fn.apply(this, a); }; return this.on(e, w); }
-                off(e, fn) { if (this._events[e]) this._events[e] = this._events[e].filter(f => f !== fn); return this; }
-                emit(e, ...a) { if (this._events[e]) this._events[e].slice().forEach(fn => fn.apply(this, a)); return this._events[e]?.length > 0; }
-                removeAllListeners(e) { if (e) delete this._events[e]; else this._events = {}; return this; }
-                listeners(e) { return this._events[e] || []; }
-                listenerCount(e) { return this._events[e]?.length || 0; }
-            };
-
-            const Socket = globalThis.net?.Socket || class {};
+            const { EventEmitter } = globalThis.events;
+
+            function listenError(result, address, port) {
+                const where = port !== undefined ? `${address}:${port}` : address;
+                const err = new Error(`listen ${result.code}: ${result.error} ${where}`);
+                err.code = result.code;
+                err.syscall = 'listen';
+                err.address = address;
+                if (port !== undefined) err.port = port;
+                return err;
+            }
 
             class Server extends EventEmitter {
                 constructor(options, connectionListener) {
@@ -969,14 +1286,20 @@ fn create_server_class(context: &mut Context) -> JsResult<JsObject> {
                         connectionListener = options;
                         options = {};
                     }
+                    options = options || {};
 
-                    this._options = options || {};
+                    this._options = options;
                     this._id = 0;
+                    this._source = null;
+                    this._refed = true;
                     this._listening = false;
                     this._connections = 0;
                     this._address = null;
+                    // close() was called and 'close' waits for connections
+                    this._closing = false;
+                    this.allowHalfOpen = Boolean(options.allowHalfOpen);
+                    this.pauseOnConnect = Boolean(options.pauseOnConnect);
                     this.maxConnections = undefined;
-                    this.dropMaxConnection = false;
 
                     if (connectionListener) {
                         this.on('connection', connectionListener);
@@ -998,140 +1321,127 @@ fn create_server_class(context: &mut Context) -> JsResult<JsObject> {
                     return this;
                 }
 
-                listen(port, host, backlog, callback) {
-                    // Handle overloaded signatures
-                    if (typeof port === 'object' && port !== null) {
-                        // listen(options[, callback])
-                        const options = port;
-                        callback = host;
-                        port = options.port;
-                        host = options.host || '0.0.0.0';
-                        backlog = options.backlog;
-                    } else if (typeof host === 'function') {
-                        callback = host;
-                        host = '0.0.0.0';
-                        backlog = undefined;
-                    } else if (typeof backlog === 'function') {
-                        callback = backlog;
-                        backlog = undefined;
+                // listen(port[, host][, backlog][, cb]), listen(path[, backlog][, cb]),
+                // listen(options[, cb]) or listen([cb])
+                listen(...args) {
+                    const callback = typeof args[args.length - 1] === 'function' ? args.pop() : undefined;
+                    let options = args[0];
+                    if (typeof options === 'string' && !/^\d+$/.test(options)) {
+                        options = { path: options };
+                    } else if (typeof options !== 'object' || options === null) {
+                        options = { port: options, host: typeof args[1] === 'string' ? args[1] : undefined };
                     }
 
-                    host = host || '0.0.0.0';
-                    port = port || 0;
-                    backlog = backlog || 511;
-
                     if (callback) {
                         this.once('listening', callback);
                     }
 
-                    try {
-                        const result = __net_server_listen(host, port, backlog);
-                        if (result.error) {
-                            setImmediate(() => {
-                                const err = new Error(result.error);
-                                err.code = 'EADDRINUSE';
-                                this.emit('error', err);
-                            });
-                            return this;
-                        }
-
-                        this._id = result.id;
-                        this._listening = true;
-                        this._address = {
-                            address: result.address,
-                            family: result.family,
-                            port: result.port
-                        };
-
-                        // Start accepting connections
-                        this._acceptLoop();
-
-                        setImmediate(() => this.emit('listening'));
-                    } catch (err) {
-                        setImmediate(() => this.emit('error', err));
+                    if (options.path !== undefined) {
+                        const path = String(options.path);
+                        this._bind(__net_server_listen_path(path), path);
+                        return this;
                     }
 
+                    const port = Number(options.port ?? 0);
+                    if (!Number.isInteger(port) || port < 0 || port > 65535) {
+                        const err = new RangeError(`options.port should be >= 0 and < 65536. Received ${options.port}.`);
+                        err.code = 'ERR_SOCKET_BAD_PORT';
+                        throw err;
+                    }
+                    const host = options.host || '0.0.0.0';
+                    if (globalThis.net.isIP(host)) {
+                        this._bind(__net_server_listen(host, port), host, port);
+                        return this;
+                    }
+                    globalThis.dns.lookup(host, (err, address) => {
+                        if (err) {
+                            this.emit('error', err);
+                            return;
+                        }
+                        this._bind(__net_server_listen(address, port), address, port);
+                    });
                     return this;
                 }
 
-                _acceptLoop() {
-                    if (!this._listening || this._id === 0) return;
-
-                    // Check max connections
-                    if (this.maxConnections !== undefined && this._connections >= this.maxConnections) {
-                        if (this.dropMaxConnection) {
-                            // Drop new connections
-                            return;
-                        }
+                _bind(result, address, port) {
+                    if (result.error) {
+                        setImmediate(() => this.emit('error', listenError(result, address, port)));
+                        return;
                     }
 
-                    // Use setImmediate for non-blocking accept
-                    const checkConnection = () => {
-                        if (!this._listening || this._id === 0) return;
-
-                        const result = __net_server_accept(this._id);
-                        if (result.hasConnection) {
-                            this._connections++;
-
-                            // Create a Socket for this connection
-                            const socket = new (globalThis.net?.Socket || Socket)();
-                            socket._id = result.socketId;
-                            socket._connected = true;
-                            socket._connecting = false;
-                            socket._pending = false;
-                            socket.localAddress = result.localAddress;
-                            socket.localPort = result.localPort;
-                            socket.localFamily = result.localFamily;
-                            socket.remoteAddress = result.remoteAddress;
-                            socket.remotePort = result.remotePort;
-                            socket.remoteFamily = result.remoteFamily;
-
-                            // Track disconnection
-                            socket.on('close', () => {
-                                this._connections--;
-                            });
-
-                            // Start reading data from this socket
-                            socket._startReadLoop();
-
-                            this.emit('connection', socket);
-                        }
-
-                        // Continue accept loop
-                        setImmediate(checkConnection, 10);
+                    this._id = result.id;
+                    this._address = result.path ?? {
+                        address: result.address,
+                        family: result.family,
+                        port: result.port
                     };
+                    this._source = __viper_loop_source(() => this._accept());
+                    __viper_loop_ref(this._source, this._refed);
+                    __net_server_attach(this._id, this._source);
+                    this._listening = true;
 
-                    setImmediate(checkConnection, 0);
+                    setImmediate(() => this.emit('listening'));
                 }
 
-                close(callback) {
-                    if (!this._listening) {
-                        if (callback) {
-                            setImmediate(() => callback(new Error('Server is not running')));
+                _accept() {
+                    if (this._id === 0) return;
+                    for (const id of __net_server_accept(this._id)) {
+                        if (this.maxConnections !== undefined && this._connections >= this.maxConnections) {
+                            __net_socket_destroy(id, false);
+                            continue;
                         }
-                        return this;
+
+                        const socket = new globalThis.net.Socket({ allowHalfOpen: this.allowHalfOpen });
+                        socket.server = this;
+                        socket._accept(id, !this.pauseOnConnect);
+                        this._connections++;
+                        socket.once('close', () => {
+                            this._connections--;
+                            this._emitCloseIfDrained();
+                        });
+                        this.emit('connection', socket);
                     }
+                }
 
-                    this._listening = false;
+                _emitCloseIfDrained() {
+                    if (!this._closing || this._connections > 0) return;
+                    this._closing = false;
+                    setImmediate(() => this.emit('close'));
+                }
 
-                    if (this._id > 0) {
-                        __net_server_close(this._id);
-                        this._id = 0;
+                close(callback) {
+                    if (callback) {
+                        if (!this._listening) {
+                            const err = new Error('Server is not running.');
+                            err.code = 'ERR_SERVER_NOT_RUNNING';
+                            setImmediate(() => callback(err));
+                        } else {
+                            this.once('close', () => callback());
+                        }
                     }
+                    if (!this._listening) return this;
 
-                    setImmediate(() => {
-                        this.emit('close');
-                        if (callback) callback();
-                    });
+                    this._listening = false;
+                    __net_server_close(this._id);
+                    this._id = 0;
+                    __viper_loop_close(this._source);
+                    this._source = null;
 
+                    // 'close' waits for the open connections to end
+                    this._closing = true;
+                    this._emitCloseIfDrained();
                     return this;
                 }
 
                 ref() {
+                    this._refed = true;
+                    if (this._source !== null) __viper_loop_ref(this._source, true);
                     return this;
                 }
 
                 unref() {
+                    this._refed = false;
+                    if (this._source !== null) __viper_loop_ref(this._source, false);
                     return this;
                 }
 
@@ -1157,214 +1467,158 @@ fn create_server_class(context: &mut Context) -> JsResult<JsObject> {
 
 /// Register native server operations
 fn register_native_server_ops(context: &mut Context) -> JsResult<()> {
-    // __net_server_listen(host, port, backlog) -> { id, address, port, family } or { error }
+    // __net_server_listen(host, port) -> { id, address, port, family } or { error, code }
     let listen_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let host = args
             .get_or_undefined(0)
             .to_string(context)?
             .to_std_string_escaped();
         let port = args.get_or_undefined(1).to_u32(context)? as u16;
-        let _backlog = args.get_or_undefined(2).to_u32(context).unwrap_or(511);
-
-        let addr_str = format!("{}:{}", host, port);
-
-        match TcpListener::bind(&addr_str) {
-            Ok(listener) => {
-                // Set non-blocking for accept
-                let _ = listener.set_nonblocking(true);
+        permissions::check_net(context, &host, Some(port))?;
+
+        let listener = host
+            .trim_matches(['[', ']'])
+            .parse::<IpAddr>()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid IP address"))
+            .and_then(|ip| std::net::TcpListener::bind(SocketAddr::new(ip, port)))
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                Ok(mio::net::TcpListener::from_std(listener))
+            });
+        let listener = match listener {
+            Ok(listener) => listener,
+            Err(e) => return Ok(error_result(&e, context)),
+        };
 
-                let id = next_connection_id();
-                let local_addr = listener.local_addr().ok();
+        // Port 0 binds an ephemeral port; report the real one
+        let local_addr = listener.local_addr().ok();
+        let id = next_connection_id();
+        SERVERS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(id, Listener::Tcp(listener));
+
+        let result = JsObject::with_null_proto();
+        result.set(js_string!("id"), id as f64, false, context)?;
+        if let Some(addr) = local_addr {
+            result.set(
+                js_string!("address"),
+                js_string!(addr.ip().to_string()),
+                false,
+                context,
+            )?;
+            result.set(js_string!("port"), addr.port(), false, context)?;
+            result.set(
+                js_string!("family"),
+                js_string!(if addr.is_ipv4() { "IPv4" } else { "IPv6" }),
+                false,
+                context,
+            )?;
+        }
+        Ok(result.into())
+    });
+    context.register_global_callable(js_string!("__net_server_listen"), 2, listen_fn)?;
 
-                SERVERS
-                    .lock()
-                    .unwrap()
-                    .insert(id, Arc::new(Mutex::new(listener)));
+    // __net_server_listen_path(path) -> { id, path } or { error, code }
+    let listen_path_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let path = args
+            .get_or_undefined(0)
+            .to_string(context)?
+            .to_std_string_escaped();
+        permissions::check_read(context, &path)?;
+        permissions::check_write(context, &path)?;
+
+        #[cfg(unix)]
+        let listener = mio::net::UnixListener::bind(&path)
+            .map(|listener| Listener::Unix(listener, std::path::PathBuf::from(&path)));
+        #[cfg(not(unix))]
+        let listener: io::Result<Listener> = Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "IPC paths are not supported on this platform",
+        ));
+        let listener = match listener {
+            Ok(listener) => listener,
+            Err(e) => return Ok(error_result(&e, context)),
+        };
 
-                let result = JsObject::with_null_proto();
-                result.set(js_string!("id"), JsValue::from(id as f64), false, context)?;
-
-                if let Some(addr) = local_addr {
-                    result.set(
-                        js_string!("address"),
-                        JsValue::from(js_string!(addr.ip().to_string())),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("port"),
-                        JsValue::from(addr.port()),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("family"),
-                        JsValue::from(js_string!(if addr.is_ipv4() { "IPv4" } else { "IPv6" })),
-                        false,
-                        context,
-                    )?;
-                }
+        let id = next_connection_id();
+        SERVERS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(id, listener);
+
+        let result = JsObject::with_null_proto();
+        result.set(js_string!("id"), id as f64, false, context)?;
+        result.set(js_string!("path"), js_string!(path), false, context)?;
+        Ok(result.into())
+    });
+    context.register_global_callable(js_string!("__net_server_listen_path"), 1, listen_path_fn)?;
 
-                Ok(JsValue::from(result))
-            }
-            Err(e) => {
-                let result = JsObject::with_null_proto();
-                result.set(
-                    js_string!("error"),
-                    JsValue::from(js_string!(e.to_string())),
-                    false,
-                    context,
-                )?;
-                Ok(JsValue::from(result))
-            }
+    // __net_server_attach(id, source) - poll the listener under a loop source
+    let attach_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args.get_or_undefined(0).to_number(context)? as u64;
+        let source = args.get_or_undefined(1).to_u32(context)?;
+        let event_loop = event_loop::current(context)?;
+
+        let mut servers = SERVERS.lock().unwrap();
+        if let Some(listener) = servers.get_or_insert_with(HashMap::new).get_mut(&id) {
+            event_loop
+                .register_io(listener.io(), source, mio::Interest::READABLE)
+                .map_err(|e| JsNativeError::error().with_message(e.to_string()))?;
         }
+        Ok(JsValue::undefined())
     });
+    context.register_global_callable(js_string!("__net_server_attach"), 2, attach_fn)?;
 
-    // __net_server_accept(id) -> { hasConnection, socketId, ... } or { hasConnection: false }
+    // __net_server_accept(id) -> [socketId, ...], every connection waiting
     let accept_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args.get_or_undefined(0).to_number(context)? as u64;
 
-        let servers = SERVERS.lock().unwrap();
-        if let Some(listener_arc) = servers.get(&id) {
-            let listener = listener_arc.lock().unwrap();
-
-            // Non-blocking accept
-            match listener.accept() {
-                Ok((stream, peer_addr)) => {
-                    let _ = stream.set_nonblocking(true);
-
-                    let socket_id = next_connection_id();
-                    let local_addr = stream.local_addr().ok();
-
-                    drop(listener); // Release lock before modifying SOCKETS
-                    drop(servers);
-
-                    SOCKETS
-                        .lock()
-                        .unwrap()
-                        .insert(socket_id, Arc::new(Mutex::new(stream)));
-
-                    let result = JsObject::with_null_proto();
-                    result.set(
-                        js_string!("hasConnection"),
-                        JsValue::from(true),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("socketId"),
-                        JsValue::from(socket_id as f64),
-                        false,
-                        context,
-                    )?;
-
-                    result.set(
-                        js_string!("remoteAddress"),
-                        JsValue::from(js_string!(peer_addr.ip().to_string())),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("remotePort"),
-                        JsValue::from(peer_addr.port()),
-                        false,
-                        context,
-                    )?;
-                    result.set(
-                        js_string!("remoteFamily"),
-                        JsValue::from(js_string!(if peer_addr.is_ipv4() {
-                            "IPv4"
-                        } else {
-                            "IPv6"
-                        })),
-                        false,
-                        context,
-                    )?;
-
-                    if let Some(addr) = local_addr {
-                        result.set(
-                            js_string!("localAddress"),
-                            JsValue::from(js_string!(addr.ip().to_string())),
-                            false,
-                            context,
-                        )?;
-                        result.set(
-                            js_string!("localPort"),
-                            JsValue::from(addr.port()),
-                            false,
-                            context,
-                        )?;
-                        result.set(
-                            js_string!("localFamily"),
-                            JsValue::from(js_string!(if addr.is_ipv4() { "IPv4" } else { "IPv6" })),
-                            false,
-                            context,
-                        )?;
-                    }
-
-                    Ok(JsValue::from(result))
-                }
-                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // No connection pending
-                    let result = JsObject::with_null_proto();
-                    result.set(
-                        js_string!("hasConnection"),
-                        JsValue::from(false),
-                        false,
-                        context,
-                    )?;
-                    Ok(JsValue::from(result))
-                }
-                Err(_e) => {
-                    let result = JsObject::with_null_proto();
-                    result.set(
-                        js_string!("hasConnection"),
-                        JsValue::from(false),
-                        false,
-                        context,
-                    )?;
-                    Ok(JsValue::from(result))
+        let mut accepted = Vec::new();
+        if let Some(listener) = SERVERS.lock().unwrap().as_ref().and_then(|s| s.get(&id)) {
+            loop {
+                match listener.accept() {
+                    Ok(stream) => accepted.push(stream),
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    // WouldBlock, or a failure (EMFILE, ...) that leaves the
+                    // connection for a later turn
+                    Err(_) => break,
                 }
             }
-        } else {
-            let result = JsObject::with_null_proto();
-            result.set(
-                js_string!("hasConnection"),
-                JsValue::from(false),
-                false,
-                context,
-            )?;
-            Ok(JsValue::from(result))
         }
+
+        let ids = JsArray::new(context);
+        for stream in accepted {
+            let socket_id = insert_socket(Connection::new(stream, false));
+            ids.push(socket_id as f64, context)?;
+        }
+        Ok(ids.into())
     });
+    context.register_global_callable(js_string!("__net_server_accept"), 1, accept_fn)?;
 
-    // __net_server_close(id)
+    // __net_server_close(id) - stop listening, removing a socket file
     let close_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args.get_or_undefined(0).to_number(context)? as u64;
-        SERVERS.lock().unwrap().remove(&id);
+
+        let removed = SERVERS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .remove(&id);
+        if let Some(mut listener) = removed {
+            let _ = event_loop::current(context)?.deregister_io(listener.io());
+            #[cfg(unix)]
+            {
+                if let Listener::Unix(_, path) = &listener {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
         Ok(JsValue::undefined())
     });
-
-    // Register all native functions
-    let global = context.global_object();
-    global.set(
-        js_string!("__net_server_listen"),
-        listen_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_server_accept"),
-        accept_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
-    global.set(
-        js_string!("__net_server_close"),
-        close_fn.to_js_function(context.realm()),
-        false,
-        context,
-    )?;
+    context.register_global_callable(js_string!("__net_server_close"), 1, close_fn)?;
 
     Ok(())
 }
@@ -1386,28 +1640,13 @@ fn create_create_server_function(context: &mut Context) -> JsResult<JsValue> {
 /// Create net.createConnection function
 fn create_create_connection_function(context: &mut Context) -> JsResult<JsValue> {
     let code = r#"
-        (function createConnection(options, connectListener) {
+        (function createConnection(...args) {
             const Socket = globalThis.net?.Socket;
             if (!Socket) throw new Error('net.Socket not available');
 
-            const socket = new Socket();
-
-            if (typeof options === 'number') {
-                // createConnection(port[, host][, callback])
-                const port = options;
-                let host = 'localhost';
-                if (typeof arguments[1] === 'string') {
-                    host = arguments[1];
-                    if (typeof arguments[2] === 'function') {
-                        connectListener = arguments[2];
-                    }
-                } else if (typeof arguments[1] === 'function') {
-                    connectListener = arguments[1];
-                }
-                return socket.connect({ port, host }, connectListener);
-            }
-
-            return socket.connect(options, connectListener);
+            const options = typeof args[0] === 'object' && args[0] !== null ? args[0] : {};
+            const socket = new Socket(options);
+            return socket.connect(...args);
         })
     "#;
 
@@ -1537,3 +1776,187 @@ fn create_socket_address_class(context: &mut Context) -> JsResult<JsObject> {
             .into()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::{Runtime, seen};
+
+    #[test]
+    fn test_echo_backpressure() {
+        let code = r#"
+            import net from 'node:net';
+
+            const server = net.createServer((socket) => socket.pipe(socket));
+            await new Promise((resolve) => server.listen(0, '127.0.0.1', resolve));
+            const { port } = server.address();
+
+            const client = net.connect(port, '127.0.0.1');
+            await new Promise((resolve) => client.once('connect', resolve));
+            let echoed = 0;
+            client.on('data', (data) => {
+                echoed += data.length;
+                if (echoed === 256 * 1024) client.end();
+            });
+            const accepted = client.write(Buffer.alloc(256 * 1024, 97));
+            await new Promise((resolve) => client.once('drain', resolve));
+            await new Promise((resolve) => client.once('close', resolve));
+            await new Promise((resolve) => server.close(resolve));
+
+            globalThis.seen = [port > 0, accepted, echoed, client.bytesWritten];
+        "#;
+        assert_eq!(seen(code), "true,false,262144,262144");
+    }
+
+    #[test]
+    fn test_socket_errors() {
+        let code = r#"
+            import net from 'node:net';
+
+            const thrown = (f) => { try { f(); } catch (e) { return e.code + ' ' + e.name; } };
+            const server = net.createServer();
+            await new Promise((resolve) => server.listen(0, '127.0.0.1', resolve));
+            const { port } = server.address();
+
+            const taken = net.createServer().listen(port, '127.0.0.1');
+            const inUse = await new Promise((resolve) => taken.once('error', resolve));
+            await new Promise((resolve) => server.close(resolve));
+            const notRunning = await new Promise((resolve) => server.close(resolve));
+
+            const refused = net.connect(port, '127.0.0.1');
+            const closed = new Promise((resolve) => refused.once('close', resolve));
+            const err = await new Promise((resolve) => refused.once('error', resolve));
+            const hadError = await closed;
+
+            globalThis.seen = [
+                inUse.code + ' ' + inUse.syscall,
+                notRunning.code,
+                err.code + ' ' + err.syscall + ' ' + (err.port === port),
+                refused.destroyed,
+                hadError,
+                thrown(() => net.connect(70000, '127.0.0.1')),
+                thrown(() => net.createServer().listen(-1)),
+            ];
+        "#;
+        assert_eq!(
+            seen(code),
+            "EADDRINUSE listen,ERR_SERVER_NOT_RUNNING,ECONNREFUSED connect true,true,\
+             true,ERR_SOCKET_BAD_PORT RangeError,ERR_SOCKET_BAD_PORT RangeError"
+        );
+    }
+
+    #[test]
+    fn test_destroy_reason() {
+        let code = r#"
+            import net from 'node:net';
+
+            const peer = new Promise((resolve) => {
+                const server = net.createServer((socket) => {
+                    socket.on('error', () => {});
+                    socket.on('close', () => { server.close(); resolve('peer closed'); });
+                    socket.resume();
+                });
+                server.listen(0, '127.0.0.1', () => globalThis.connect(server.address().port));
+            });
+
+            const client = await new Promise((resolve) => {
+                globalThis.connect = (port) => {
+                    const socket = net.connect(port, '127.0.0.1', () => resolve(socket));
+                };
+            });
+            const events = [];
+            client.on('error', (err) => events.push('error ' + err.message));
+            client.on('close', (hadError) => events.push('close ' + hadError));
+            client.destroy(new Error('why'));
+            events.push(await peer);
+
+            globalThis.seen = [events.join(' / '), client.destroyed];
+        "#;
+        assert_eq!(seen(code), "error why / close true / peer closed,true");
+    }
+
+    #[test]
+    fn test_connect_permission() {
+        use crate::runtime::{Allow, Permissions, RuntimeConfig};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || for _ in listener.incoming() {});
+
+        let config = RuntimeConfig {
+            permissions: Permissions {
+                net: Allow::Only(vec!["localhost".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let code = r#"
+            import net from 'node:net';
+            const denied = (err) => err.code + ' ' + err.resource;
+            const attempt = (options) => new Promise((resolve) => {
+                const socket = net.connect(options);
+                socket.on('connect', () => { socket.destroy(); resolve('connected'); });
+                socket.on('error', (err) => resolve(denied(err)));
+            });
+            // A lookup that answers with an address localhost never resolved to
+            const forged = (host, options, callback) =>
+                callback(null, [{ address: '127.0.0.2', family: 4 }]);
+            let listened;
+            try {
+                net.createServer().listen(0, '127.0.0.1');
+            } catch (err) {
+                listened = denied(err);
+            }
+            globalThis.seen = [
+                await attempt({ port: PORT, host: 'localhost', lookup: forged }),
+                await attempt({ port: PORT, host: 'localhost' }),
+                await attempt({ port: PORT, host: '127.0.0.1' }),
+                listened,
+            ];
+        "#
+        .replace("PORT", &port.to_string());
+        runtime.run(&code, "main.mjs").unwrap();
+        let seen = runtime.eval("String(globalThis.seen)", "check.js").unwrap();
+        assert_eq!(
+            runtime.value_to_string(&seen),
+            format!(
+                "ERR_ACCESS_DENIED 127.0.0.2:{port},connected,\
+                 ERR_ACCESS_DENIED 127.0.0.1:{port},ERR_ACCESS_DENIED 127.0.0.1:0"
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ipc() {
+        let path = std::env::temp_dir().join(format!("viper-ipc-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let code = r#"
+            import net from 'node:net';
+
+            const path = PATH;
+            const server = net.createServer({ allowHalfOpen: true }, (socket) => {
+                let text = '';
+                socket.on('data', (data) => { text += data; });
+                socket.on('end', () => socket.end(text.toUpperCase()));
+            });
+            await new Promise((resolve) => server.listen(path, resolve));
+
+            const client = net.connect(path, () => client.end('ping'));
+            let reply = '';
+            client.on('data', (data) => { reply += data; });
+            await new Promise((resolve) => client.once('close', resolve));
+            const address = server.address();
+            await new Promise((resolve) => server.close(resolve));
+
+            const missing = net.connect(path);
+            const err = await new Promise((resolve) => missing.once('error', resolve));
+
+            globalThis.seen = [address === path, reply, err.code];
+        "#
+        .replace("PATH", &format!("{:?}", path.to_string_lossy()));
+        assert_eq!(seen(&code), "true,PING,ENOENT");
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,478 @@
+//! Capability-based permissions
+//!
+//! Every [`Runtime`](super::Runtime) carries a [`Permissions`] set that native
+//! functions consult before touching the outside world:
+//! - `read`/`write`: paths for `fs`, `Viper.readDir` and friends
+//! - `net`: hosts for sockets, servers, WebSocket and fetch
+//! - `env`: variable names for `process.env`
+//! - `run`: commands for `spawn`/`exec` and `process.kill`
+//! - `worker`: creating Workers (which inherit the parent's permissions)
+//!
+//! The default allows everything. A sandbox starts from
+//! [`Permissions::deny_all`] and grants capabilities one by one, which is
+//! what the `--allow-*` and `--deny-all` CLI flags build.
+//!
+//! A denial throws an `Error` with `code: 'ERR_ACCESS_DENIED'`, `permission`
+//! and `resource` properties; if nothing catches it the runtime returns
+//! [`RuntimeError::PermissionDenied`](super::RuntimeError::PermissionDenied).
+
+use boa_engine::{Context, JsData, JsError, JsNativeError, JsResult, js_string};
+use boa_gc::{Finalize, Trace};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// Error code carried by denials on the JavaScript side
+const ERR_ACCESS_DENIED: &str = "ERR_ACCESS_DENIED";
+
+/// Which resources of one kind are accessible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Allow<T> {
+    All,
+    /// Only these; an empty list denies everything
+    Only(Vec<T>),
+}
+
+impl<T> Allow<T> {
+    pub fn none() -> Self {
+        Self::Only(Vec::new())
+    }
+
+    fn permits(&self, matches: impl Fn(&T) -> bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(grants) => grants.iter().any(matches),
+        }
+    }
+}
+
+/// Capabilities granted to a runtime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permissions {
+    /// Files and directories (recursively) that may be read
+    pub read: Allow<PathBuf>,
+    /// Files and directories (recursively) that may be written
+    pub write: Allow<PathBuf>,
+    /// Hosts that may be reached or listened on, as `host` or `host:port`
+    pub net: Allow<String>,
+    /// Environment variables that may be read or changed
+    pub env: Allow<String>,
+    /// Commands that may be run; shell commands require `Allow::All`
+    pub run: Allow<String>,
+    /// Whether Workers may be created
+    pub worker: bool,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self::allow_all()
+    }
+}
+
+impl Permissions {
+    pub fn allow_all() -> Self {
+        Self {
+            read: Allow::All,
+            write: Allow::All,
+            net: Allow::All,
+            env: Allow::All,
+            run: Allow::All,
+            worker: true,
+        }
+    }
+
+    pub fn deny_all() -> Self {
+        Self {
+            read: Allow::none(),
+            write: Allow::none(),
+            net: Allow::none(),
+            env: Allow::none(),
+            run: Allow::none(),
+            worker: false,
+        }
+    }
+
+    /// Make path grants absolute so a later `process.chdir` can't widen them
+    pub fn resolved(mut self) -> Self {
+        for allow in [&mut self.read, &mut self.write] {
+            if let Allow::Only(paths) = allow {
+                for path in paths.iter_mut() {
+                    *path = canonical(path);
+                }
+            }
+        }
+        self
+    }
+
+    pub fn check_read(&self, path: &Path) -> Result<(), PermissionDenied> {
+        check_path(&self.read, PermissionKind::Read, path)
+    }
+
+    pub fn check_write(&self, path: &Path) -> Result<(), PermissionDenied> {
+        check_path(&self.write, PermissionKind::Write, path)
+    }
+
+    pub fn check_net(&self, host: &str, port: Option<u16>) -> Result<(), PermissionDenied> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let allowed = self.net.permits(|grant| {
+            let (grant_host, grant_port) = split_host_port(grant);
+            grant_host.eq_ignore_ascii_case(host) && (grant_port.is_none() || grant_port == port)
+        });
+        match port {
+            _ if allowed => Ok(()),
+            // Bracket IPv6 hosts so the resource reads like a grant
+            Some(port) if host.contains(':') => Err(PermissionDenied::new(
+                PermissionKind::Net,
+                format!("[{}]:{}", host, port),
+            )),
+            Some(port) => Err(PermissionDenied::new(
+                PermissionKind::Net,
+                format!("{}:{}", host, port),
+            )),
+            None => Err(PermissionDenied::new(PermissionKind::Net, host)),
+        }
+    }
+
+    /// Check the host of a URL; URLs without one (`data:`, `blob:`) need nothing
+    pub fn check_url(&self, url: &str) -> Result<(), PermissionDenied> {
+        match url_host(url) {
+            Some((host, port)) => self.check_net(&host, port),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_env(&self, name: &str) -> Result<(), PermissionDenied> {
+        if self.env.permits(|grant| grant == name) {
+            Ok(())
+        } else {
+            Err(PermissionDenied::new(PermissionKind::Env, name))
+        }
+    }
+
+    pub fn check_run(&self, command: &str) -> Result<(), PermissionDenied> {
+        if self.run.permits(|grant| grant == command) {
+            Ok(())
+        } else {
+            Err(PermissionDenied::new(PermissionKind::Run, command))
+        }
+    }
+
+    /// Shell commands can run anything, so they need unrestricted `run`
+    pub fn check_shell(&self, command: &str) -> Result<(), PermissionDenied> {
+        if self.run == Allow::All {
+            Ok(())
+        } else {
+            Err(PermissionDenied::new(PermissionKind::Run, command))
+        }
+    }
+
+    pub fn check_worker(&self, script: &str) -> Result<(), PermissionDenied> {
+        if self.worker {
+            Ok(())
+        } else {
+            Err(PermissionDenied::new(PermissionKind::Worker, script))
+        }
+    }
+}
+
+/// Capability a denied operation needed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionKind {
+    Read,
+    Write,
+    Net,
+    Env,
+    Run,
+    Worker,
+}
+
+impl PermissionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Net => "net",
+            Self::Env => "env",
+            Self::Run => "run",
+            Self::Worker => "worker",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "read" => Self::Read,
+            "write" => Self::Write,
+            "net" => Self::Net,
+            "env" => Self::Env,
+            "run" => Self::Run,
+            "worker" => Self::Worker,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for PermissionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An operation needed a capability the runtime wasn't granted
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Requires {kind} access to \"{resource}\", run again with --allow-{kind}")]
+pub struct PermissionDenied {
+    pub kind: PermissionKind,
+    pub resource: String,
+}
+
+impl PermissionDenied {
+    pub fn new(kind: PermissionKind, resource: impl Into<String>) -> Self {
+        Self {
+            kind,
+            resource: resource.into(),
+        }
+    }
+
+    /// The `Error` object scripts see
+    fn into_js(self, context: &mut Context) -> JsError {
+        let error = JsError::from(JsNativeError::error().with_message(self.to_string()));
+        let value = error.to_opaque(context);
+        if let Some(object) = value.as_object() {
+            let _ = object.set(
+                js_string!("code"),
+                js_string!(ERR_ACCESS_DENIED),
+                false,
+                context,
+            );
+            let _ = object.set(
+                js_string!("permission"),
+                js_string!(self.kind.as_str()),
+                false,
+                context,
+            );
+            let _ = object.set(
+                js_string!("resource"),
+                js_string!(self.resource),
+                false,
+                context,
+            );
+        }
+        JsError::from_opaque(value)
+    }
+
+    /// Recover a denial from an error thrown in JavaScript
+    pub(crate) fn from_js(error: &JsError, context: &mut Context) -> Option<Self> {
+        let value = error.to_opaque(context);
+        let object = value.as_object()?;
+        let mut property = |name| {
+            object
+                .get(name, context)
+                .ok()?
+                .as_string()
+                .map(|s| s.to_std_string_escaped())
+        };
+        if property(js_string!("code"))? != ERR_ACCESS_DENIED {
+            return None;
+        }
+        let kind = PermissionKind::parse(&property(js_string!("permission"))?)?;
+        let resource = property(js_string!("resource"))?;
+        Some(Self { kind, resource })
+    }
+}
+
+fn check_path(
+    allow: &Allow<PathBuf>,
+    kind: PermissionKind,
+    path: &Path,
+) -> Result<(), PermissionDenied> {
+    if *allow == Allow::All {
+        return Ok(());
+    }
+    let path = canonical(path);
+    if allow.permits(|grant| path.starts_with(grant)) {
+        Ok(())
+    } else {
+        Err(PermissionDenied::new(kind, path.to_string_lossy()))
+    }
+}
+
+/// Absolute path with `.`/`..` removed and symlinks resolved as far as the
+/// path exists, so links and `..` can't escape a grant
+fn canonical(path: &Path) -> PathBuf {
+    let absolute = normalize(&std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()));
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(mut real) = existing.canonicalize() {
+            real.extend(rest.iter().rev());
+            return real;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return absolute,
+        }
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Split `host:port`, `[v6]:port` or a bare host
+fn split_host_port(value: &str) -> (&str, Option<u16>) {
+    if let Some((host, after)) = value
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+    {
+        return (host, after.strip_prefix(':').and_then(|p| p.parse().ok()));
+    }
+    match value.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => match port.parse() {
+            Ok(port) => (host, Some(port)),
+            Err(_) => (value, None),
+        },
+        _ => (value, None),
+    }
+}
+
+/// Host and port of an absolute URL with an authority
+fn url_host(url: &str) -> Option<(String, Option<u16>)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let (host, port) = split_host_port(authority);
+    let port = port.or(match scheme.to_ascii_lowercase().as_str() {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        _ => None,
+    });
+    Some((host.to_string(), port))
+}
+
+// ============================================================================
+// Per-context access
+// ============================================================================
+
+/// Stores the permissions in the realm so native functions can check them
+#[derive(Trace, Finalize, JsData)]
+struct PermissionsSlot(#[unsafe_ignore_trace] Permissions);
+
+/// Apply `permissions` to native functions running in `context`
+pub fn install(context: &mut Context, permissions: Permissions) {
+    context
+        .realm()
+        .host_defined_mut()
+        .insert(PermissionsSlot(permissions));
+}
+
+/// The permissions of `context`; contexts without any allow everything
+pub fn current(context: &Context) -> Permissions {
+    context
+        .realm()
+        .host_defined()
+        .get::<PermissionsSlot>()
+        .map(|slot| slot.0.clone())
+        .unwrap_or_default()
+}
+
+fn check(
+    context: &mut Context,
+    check: impl FnOnce(&Permissions) -> Result<(), PermissionDenied>,
+) -> JsResult<()> {
+    let result = match context.realm().host_defined().get::<PermissionsSlot>() {
+        Some(slot) => check(&slot.0),
+        None => Ok(()),
+    };
+    result.map_err(|denied| denied.into_js(context))
+}
+
+pub fn check_read(context: &mut Context, path: &str) -> JsResult<()> {
+    check(context, |p| p.check_read(Path::new(path)))
+}
+
+pub fn check_write(context: &mut Context, path: &str) -> JsResult<()> {
+    check(context, |p| p.check_write(Path::new(path)))
+}
+
+pub fn check_net(context: &mut Context, host: &str, port: Option<u16>) -> JsResult<()> {
+    check(context, |p| p.check_net(host, port))
+}
+
+pub fn check_url(context: &mut Context, url: &str) -> JsResult<()> {
+    check(context, |p| p.check_url(url))
+}
+
+pub fn check_env(context: &mut Context, name: &str) -> JsResult<()> {
+    check(context, |p| p.check_env(name))
+}
+
+pub fn check_run(context: &mut Context, command: &str, shell: bool) -> JsResult<()> {
+    if shell {
+        check(context, |p| p.check_shell(command))
+    } else {
+        check(context, |p| p.check_run(command))
+    }
+}
+
+pub fn check_worker(context: &mut Context, script: &str) -> JsResult<()> {
+    check(context, |p| p.check_worker(script))
+}
+
+/// Whether `process.env` may list the variable, for enumeration
+pub fn env_visible(context: &Context, name: &str) -> bool {
+    match context.realm().host_defined().get::<PermissionsSlot>() {
+        Some(slot) => slot.0.check_env(name).is_ok(),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_grants() {
+        let dir = std::env::temp_dir().join("viper-permissions-test");
+        let permissions = Permissions {
+            read: Allow::Only(vec![dir.clone()]),
+            ..Permissions::deny_all()
+        }
+        .resolved();
+
+        assert!(permissions.check_read(&dir.join("data/file.txt")).is_ok());
+        assert!(permissions.check_read(&dir.join("../secret")).is_err());
+        assert!(permissions.check_write(&dir.join("file.txt")).is_err());
+    }
+
+    #[test]
+    fn test_net_grants() {
+        let permissions = Permissions {
+            net: Allow::Only(vec!["api.example.com".into(), "localhost:8080".into()]),
+            ..Permissions::deny_all()
+        };
+
+        assert!(permissions.check_url("https://api.example.com/v1").is_ok());
+        assert!(permissions.check_net("localhost", Some(8080)).is_ok());
+        assert!(permissions.check_net("localhost", Some(9000)).is_err());
+        assert!(permissions.check_url("wss://evil.example.com").is_err());
+        assert!(permissions.check_url("data:text/plain,hi").is_ok());
+
+        let denied = permissions.check_net("::1", Some(5353)).unwrap_err();
+        assert_eq!(denied.resource, "[::1]:5353");
+    }
+}
@@ -16,6 +16,7 @@
 //! - process.kill(pid, signal)
 //! - process.stdout, process.stderr, process.stdin
 //! - Event emitter: on, once, off, emit for exit, beforeExit, uncaughtException, etc.
+//! - unhandledRejection/rejectionHandled and uncaughtException reporting for the event loop
 
 use boa_engine::{
     Context, JsNativeError, JsResult, JsValue, NativeFunction, Source, js_string,
@@ -24,6 +25,8 @@ use boa_engine::{
 use std::sync::OnceLock;
 use std::time::Instant;
 
+use super::permissions;
+
 /// Global start time for process.uptime() - thread-safe
 static PROCESS_START_TIME: OnceLock<Instant> = OnceLock::new();
 
@@ -315,12 +318,16 @@ pub fn register_process(context: &mut Context, args: &[String]) -> JsResult<()>
                     columns: 80,
                     rows: 24,
                 },
-                stdin: {
-                    fd: 0,
-                    isTTY: __viper_isatty(0),
-                    resume: () => process.stdin,
-                    pause: () => process.stdin,
-                    setEncoding: () => process.stdin,
+                // A tty.ReadStream over fd 0, created on first use
+                get stdin() {
+                    const stdin = new tty.ReadStream(0);
+                    Object.defineProperty(process, 'stdin', {
+                        value: stdin,
+                        writable: true,
+                        enumerable: true,
+                        configurable: true,
+                    });
+                    return stdin;
                 },
 
                 // Debug port
@@ -445,20 +452,11 @@ pub fn register_process(context: &mut Context, args: &[String]) -> JsResult<()>
 
                 // Get builtin module
                 getBuiltinModule: (id) => {
-                    const builtins = {
-                        'fs': globalThis.fs,
-                        'path': globalThis.path,
-                        'events': globalThis.events,
-                        'buffer': globalThis.buffer,
-                        'stream': globalThis.stream,
-                        'util': globalThis.util,
-                        'crypto': globalThis.crypto,
-                        'http': globalThis.http,
-                        'net': globalThis.net,
-                        'tty': globalThis.tty,
-                    };
-                    const key = id.replace(/^node:/, '');
-                    return builtins[key];
+                    try {
+                        return __viper_builtin(String(id));
+                    } catch {
+                        return undefined;
+                    }
                 },
 
                 // Constrained memory (stub)
@@ -466,6 +464,110 @@ pub fn register_process(context: &mut Context, args: &[String]) -> JsResult<()>
                 availableMemory: () => __viper_available_memory(),
             };
 
+            // Uncaught exceptions: called by the event loop with errors that
+            // escaped a callback; returns whether anything handled the error
+            function handleUncaught(err, origin) {
+                emit('uncaughtExceptionMonitor', err, origin);
+                if (typeof process._uncaughtExceptionCallback === 'function') {
+                    process._uncaughtExceptionCallback(err);
+                    return true;
+                }
+                if (listenerCount('uncaughtException') === 0) return false;
+                emit('uncaughtException', err, origin);
+                return true;
+            }
+            globalThis.__viper_handle_uncaught = handleUncaught;
+
+            // Unhandled rejections: called after each microtask checkpoint
+            // with [promise, reason] pairs that are still unhandled and with
+            // promises that got a handler late
+            const reportedRejections = new WeakMap();
+            let lastRejectionId = 0;
+
+            function showReason(reason) {
+                if (typeof reason === 'string') return reason;
+                try {
+                    return globalThis.util?.inspect ? globalThis.util.inspect(reason) : String(reason);
+                } catch {
+                    return Object.prototype.toString.call(reason);
+                }
+            }
+
+            function unhandledRejectionError(reason) {
+                if (reason instanceof Error) return reason;
+                const err = new Error(
+                    'This error originated either by throwing inside of an async function without a catch block, ' +
+                    'or by rejecting a promise which was not handled with .catch(). ' +
+                    `The promise rejected with the reason "${showReason(reason)}".`
+                );
+                err.name = 'UnhandledPromiseRejection';
+                err.code = 'ERR_UNHANDLED_REJECTION';
+                return err;
+            }
+
+            function rejectionWarning(message) {
+                __viper_stderr_write(`(viper:${process.pid}) ${message}\n`);
+            }
+
+            function warnUnhandled(reason, id) {
+                const shown = reason instanceof Error ? (reason.stack || String(reason)) : showReason(reason);
+                rejectionWarning(`UnhandledPromiseRejectionWarning: ${shown}`);
+                rejectionWarning(
+                    'UnhandledPromiseRejectionWarning: Unhandled promise rejection. This error originated either by ' +
+                    'throwing inside of an async function without a catch block, or by rejecting a promise which was ' +
+                    'not handled with .catch(). To terminate the process on unhandled promise rejection, use the CLI ' +
+                    `flag \`--unhandled-rejections=strict\`. (rejection id: ${id})`
+                );
+            }
+
+            globalThis.__viper_process_rejections = function(unhandled, handled, mode) {
+                for (const promise of handled) {
+                    const id = reportedRejections.get(promise);
+                    if (id === undefined) continue;
+                    reportedRejections.delete(promise);
+                    if (mode === 'warn' || mode === 'warn-with-error-code') {
+                        rejectionWarning(`PromiseRejectionHandledWarning: Promise rejection was handled asynchronously (rejection id: ${id})`);
+                    }
+                    emit('rejectionHandled', promise);
+                }
+
+                for (const [promise, reason] of unhandled) {
+                    const id = ++lastRejectionId;
+                    reportedRejections.set(promise, id);
+                    const listened = listenerCount('unhandledRejection') > 0;
+                    switch (mode) {
+                        case 'strict': {
+                            const err = unhandledRejectionError(reason);
+                            if (!handleUncaught(err, 'unhandledRejection')) throw err;
+                            emit('unhandledRejection', reason, promise);
+                            break;
+                        }
+                        case 'warn':
+                            emit('unhandledRejection', reason, promise);
+                            warnUnhandled(reason, id);
+                            break;
+                        case 'warn-with-error-code':
+                            if (listened) {
+                                emit('unhandledRejection', reason, promise);
+                            } else {
+                                warnUnhandled(reason, id);
+                                process.exitCode = 1;
+                            }
+                            break;
+                        case 'none':
+                            emit('unhandledRejection', reason, promise);
+                            break;
+                        default:
+                            if (listened) {
+                                emit('unhandledRejection', reason, promise);
+                            } else {
+                                const err = unhandledRejectionError(reason);
+                                if (!handleUncaught(err, 'unhandledRejection')) throw err;
+                            }
+                    }
+                }
+            };
+
             // Make process global
             globalThis.process = process;
 
@@ -547,6 +649,7 @@ fn register_native_process_functions(context: &mut Context) -> JsResult<()> {
             .map(|v| v.to_i32(context))
             .transpose()?
             .unwrap_or(0);
+        super::tty::restore_terminal();
         std::process::exit(code);
     });
     global.set(
@@ -614,6 +717,7 @@ fn register_native_process_functions(context: &mut Context) -> JsResult<()> {
             .transpose()?
             .map(|s| s.to_std_string_escaped())
             .unwrap_or_default();
+        permissions::check_env(context, &key)?;
 
         match std::env::var(&key) {
             Ok(val) => Ok(JsValue::from(js_string!(val))),
@@ -640,6 +744,7 @@ fn register_native_process_functions(context: &mut Context) -> JsResult<()> {
             .transpose()?
             .map(|s| s.to_std_string_escaped())
             .unwrap_or_default();
+        permissions::check_env(context, &key)?;
 
         unsafe {
             std::env::set_var(&key, &value);
@@ -660,6 +765,7 @@ fn register_native_process_functions(context: &mut Context) -> JsResult<()> {
             .transpose()?
             .map(|s| s.to_std_string_escaped())
             .unwrap_or_default();
+        permissions::check_env(context, &key)?;
 
         unsafe {
             std::env::remove_var(&key);
@@ -675,7 +781,11 @@ fn register_native_process_functions(context: &mut Context) -> JsResult<()> {
 
     let env_all_fn = NativeFunction::from_fn_ptr(|_this, _args, context| {
         let obj = ObjectInitializer::new(context).build();
+        // Only variables the env permission grants are listed
         for (key, value) in std::env::vars() {
+            if !permissions::env_visible(context, &key) {
+                continue;
+            }
             obj.set(
                 js_string!(key),
                 JsValue::from(js_string!(value)),
@@ -1117,6 +1227,8 @@ fn register_native_process_functions(context: &mut Context) -> JsResult<()> {
             .get(0)
             .ok_or_else(|| JsNativeError::typ().with_message("pid argument required"))?
             .to_i32(context)?;
+        // Signalling arbitrary processes needs unrestricted run access
+        permissions::check_run(context, &format!("kill {}", pid), true)?;
 
         let signal: i32 = if let Some(v) = args.get(1) {
             if v.is_number() {
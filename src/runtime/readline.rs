@@ -0,0 +1,149 @@
+//! Readline module - Node.js compatible readline and readline/promises
+//!
+//! - readline.createInterface() - lines from any readable, with `question()`,
+//!   'line' events and `for await` iteration
+//! - Terminal interfaces put the input in raw mode and edit the line
+//!   themselves (cursor keys, word deletion, history, tab completion)
+//! - readline.emitKeypressEvents() - 'keypress' events from raw input
+//! - readline.clearLine(), cursorTo(), moveCursor(), clearScreenDown()
+//! - readline/promises with `Interface` and `Readline`
+
+use boa_engine::{Context, JsResult, Source};
+
+/// Register the readline module
+pub fn register_readline_module(context: &mut Context) -> JsResult<()> {
+    let readline_code = include_str!("readline_module.js");
+    let source = Source::from_bytes(readline_code.as_bytes());
+    context.eval(source)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::seen;
+
+    /// A terminal interface over a PassThrough, recording what it writes
+    const TERMINAL: &str = r#"
+        import { createInterface } from 'node:readline/promises';
+        import { PassThrough } from 'node:stream';
+
+        let written = '';
+        const output = { isTTY: true, write: (data) => { written += data; return true; } };
+        const keys = new PassThrough();
+        const tty = createInterface({ input: keys, output, terminal: true });
+    "#;
+
+    #[test]
+    fn test_line_events() {
+        let code = r#"
+            import readline from 'node:readline';
+            import { PassThrough } from 'node:stream';
+
+            const input = new PassThrough();
+            const rl = readline.createInterface({ input, terminal: false });
+            const lines = [];
+            rl.on('line', (line) => lines.push(line));
+            let closed = 0;
+            rl.on('close', () => closed++);
+            input.write('one\r\ntw');
+            input.write('o\n');
+            await new Promise((resolve) => setTimeout(resolve, 10));
+            rl.close();
+            rl.close();
+            globalThis.seen = [lines.join('|'), closed];
+        "#;
+        assert_eq!(seen(code), "one|two,1");
+    }
+
+    #[test]
+    fn test_question_line_editing() {
+        let code = format!(
+            "{TERMINAL}{}",
+            r#"
+            const answer = tty.question('name? ');
+            keys.write('vipr\x1b[De\x1b[F!\r');
+            globalThis.seen = [await answer, written.startsWith('\x1b[1Gname? ')];
+            tty.close();
+        "#
+        );
+        assert_eq!(seen(&code), "viper!,true");
+    }
+
+    #[test]
+    fn test_history_recall() {
+        let code = format!(
+            "{TERMINAL}{}",
+            r#"
+            const first = new Promise((resolve) => tty.once('line', resolve));
+            keys.write('viper\r');
+            await first;
+            const recalled = new Promise((resolve) => tty.once('line', resolve));
+            keys.write('\x1b[A\r');
+            // Stream data arrives on a microtask; closing first would drop it
+            globalThis.seen = [await recalled, tty.history.length];
+            tty.close();
+        "#
+        );
+        assert_eq!(seen(&code), "viper,1");
+    }
+
+    #[test]
+    fn test_async_iteration() {
+        let code = r#"
+            import readline from 'node:readline';
+            import { Readable } from 'node:stream';
+            const piped = readline.createInterface({ input: Readable.from(['a\nb', '\nc']) });
+            const iterated = [];
+            for await (const line of piped) iterated.push(line);
+            globalThis.seen = [iterated.join('|')];
+        "#;
+        assert_eq!(seen(code), "a|b|c");
+    }
+
+    #[test]
+    fn test_question_errors() {
+        let code = format!(
+            "{TERMINAL}{}",
+            r#"
+            const settle = (promise) => promise.then(() => 'ok', (e) => e.name + ':' + (e.code ?? '') + ':' + e.cause);
+            const aborted = await settle(tty.question('a? ', { signal: AbortSignal.abort('early') }));
+            const controller = new AbortController();
+            const pending = settle(tty.question('b? ', { signal: controller.signal }));
+            controller.abort('late');
+            const late = await pending;
+            tty.close();
+            const closed = await settle(tty.question('c? '));
+            globalThis.seen = [aborted, late, closed];
+        "#
+        );
+        assert_eq!(
+            seen(&code),
+            "AbortError:ABORT_ERR:early,AbortError:ABORT_ERR:late,Error:ERR_USE_AFTER_CLOSE:undefined"
+        );
+    }
+
+    #[test]
+    fn test_cursor_helpers() {
+        let code = r#"
+            import readline from 'node:readline';
+            import { Readline } from 'node:readline/promises';
+
+            let written = '';
+            const output = { write: (data, callback) => { written += data; callback?.(); return true; } };
+            readline.cursorTo(output, 2);
+            readline.moveCursor(output, -1, 1);
+            readline.clearLine(output, 0);
+            const direct = written;
+
+            written = '';
+            const queued = new Readline(output).cursorTo(0, 0).clearScreenDown();
+            const before = written;
+            await queued.commit();
+            globalThis.seen = [JSON.stringify(direct), before === '', JSON.stringify(written)];
+        "#;
+        assert_eq!(
+            seen(code),
+            r#""\u001b[3G\u001b[1D\u001b[1B\u001b[2K",true,"\u001b[1;1H\u001b[0J""#
+        );
+    }
+}
@@ -0,0 +1,769 @@
+/**
+ * Node.js readline and readline/promises
+ *
+ * `Interface` reads lines from any stream emitting 'data'. When it is a
+ * terminal interface (by default, when the output is a TTY) it puts the
+ * input in raw mode, decodes keypresses and edits the line itself: cursor
+ * movement, word and line deletion, history and tab completion.
+ */
+(function () {
+  "use strict";
+
+  const EventEmitter = globalThis.EventEmitter;
+  const { StringDecoder } = globalThis.string_decoder;
+
+  const kDecoder = Symbol("viper.readline.keypressDecoder");
+  const kHistorySize = 30;
+
+  // ==========================================================================
+  // Keypress decoding
+  // ==========================================================================
+
+  // Final byte of a CSI or SS3 sequence and the key it names
+  const letterKeys = {
+    A: "up", B: "down", C: "right", D: "left", E: "clear", F: "end", H: "home",
+    P: "f1", Q: "f2", R: "f3", S: "f4", Z: "tab",
+  };
+
+  // Number of a `CSI <n> ~` sequence and the key it names
+  const tildeKeys = {
+    1: "home", 2: "insert", 3: "delete", 4: "end", 5: "pageup", 6: "pagedown", 7: "home", 8: "end",
+    11: "f1", 12: "f2", 13: "f3", 14: "f4", 15: "f5", 17: "f6", 18: "f7", 19: "f8",
+    20: "f9", 21: "f10", 23: "f11", 24: "f12",
+  };
+
+  function makeKey(sequence, name, ctrl = false, meta = false, shift = false) {
+    return { sequence, name, ctrl, meta, shift };
+  }
+
+  // The key of a control or printable character
+  function charKey(ch, meta) {
+    const sequence = meta ? "\x1b" + ch : ch;
+    if (ch === "\r") return makeKey(sequence, "return", false, meta);
+    if (ch === "\n") return makeKey(sequence, "enter", false, meta);
+    if (ch === "\t") return makeKey(sequence, "tab", false, meta);
+    if (ch === "\b" || ch === "\x7f") return makeKey(sequence, "backspace", false, meta);
+    if (ch === "\x1b") return makeKey(sequence, "escape", false, meta);
+    if (ch === " ") return makeKey(sequence, "space", false, meta);
+    const code = ch.charCodeAt(0);
+    if (code < 0x20) {
+      return makeKey(sequence, String.fromCharCode(code + 0x60), true, meta);
+    }
+    if (/^[a-z0-9]$/.test(ch)) return makeKey(sequence, ch, false, meta);
+    if (/^[A-Z]$/.test(ch)) return makeKey(sequence, ch.toLowerCase(), false, meta, true);
+    return makeKey(sequence, undefined, false, meta);
+  }
+
+  // Split `text` into [string, key] pairs; an escape sequence cut off at the
+  // end is returned as `rest` to be completed by the next chunk
+  function decodeKeys(text) {
+    const keys = [];
+    let i = 0;
+    while (i < text.length) {
+      if (text[i] !== "\x1b" || i + 1 === text.length) {
+        const ch = String.fromCodePoint(text.codePointAt(i));
+        keys.push([ch, charKey(ch, false)]);
+        i += ch.length;
+        continue;
+      }
+
+      const next = text[i + 1];
+      if (next !== "[" && next !== "O") {
+        // Meta (Alt) held down with a character
+        const ch = String.fromCodePoint(text.codePointAt(i + 1));
+        keys.push([undefined, charKey(ch, true)]);
+        i += 1 + ch.length;
+        continue;
+      }
+
+      // CSI: ESC [ params final, or SS3: ESC O final
+      const match = /^\x1b(\[|O)([\d;]*)([~A-Za-z])/.exec(text.slice(i));
+      if (match === null) {
+        if (/^\x1b(\[|O)[\d;]*$/.test(text.slice(i))) return { keys, rest: text.slice(i) };
+        keys.push([undefined, makeKey(text.slice(i, i + 2), undefined, false, true)]);
+        i += 2;
+        continue;
+      }
+      const [sequence, , params, final] = match;
+      const [first, modifier] = params.split(";").map(Number);
+      const name = final === "~" ? tildeKeys[first] : letterKeys[final];
+      // xterm modifier parameter: 1 + (shift | alt << 1 | ctrl << 2)
+      const flags = (modifier || 1) - 1;
+      const key = makeKey(sequence, name, Boolean(flags & 4), Boolean(flags & 2), Boolean(flags & 1));
+      if (final === "Z") key.shift = true;
+      keys.push([undefined, key]);
+      i += sequence.length;
+    }
+    return { keys, rest: "" };
+  }
+
+  // Make `stream` emit 'keypress' (string, key) for what it reads
+  function emitKeypressEvents(stream) {
+    if (stream[kDecoder]) return;
+    const decoder = new StringDecoder("utf8");
+    let pending = "";
+    stream[kDecoder] = decoder;
+
+    function onData(chunk) {
+      if (stream.listenerCount("keypress") === 0) return;
+      const text = pending + (typeof chunk === "string" ? chunk : decoder.write(chunk));
+      const { keys, rest } = decodeKeys(text);
+      pending = rest;
+      for (const [string, key] of keys) stream.emit("keypress", string, key);
+    }
+
+    function onNewListener(event) {
+      if (event === "keypress") {
+        stream.on("data", onData);
+        stream.removeListener("newListener", onNewListener);
+      }
+    }
+
+    if (stream.listenerCount("keypress") > 0) stream.on("data", onData);
+    else stream.on("newListener", onNewListener);
+  }
+
+  // ==========================================================================
+  // Cursor helpers
+  // ==========================================================================
+
+  function writeTo(stream, data, callback) {
+    if (stream === null || stream === undefined) {
+      if (typeof callback === "function") queueMicrotask(callback);
+      return true;
+    }
+    return stream.write(data, callback);
+  }
+
+  function clearLine(stream, dir, callback) {
+    return writeTo(stream, dir < 0 ? "\x1b[1K" : dir > 0 ? "\x1b[0K" : "\x1b[2K", callback);
+  }
+
+  function clearScreenDown(stream, callback) {
+    return writeTo(stream, "\x1b[0J", callback);
+  }
+
+  function cursorToCode(x, y) {
+    return y === undefined || y === null ? `\x1b[${x + 1}G` : `\x1b[${y + 1};${x + 1}H`;
+  }
+
+  function cursorTo(stream, x, y, callback) {
+    if (typeof y === "function") [y, callback] = [undefined, y];
+    if (!Number.isFinite(x)) return writeTo(stream, "", callback);
+    return writeTo(stream, cursorToCode(x, y), callback);
+  }
+
+  function moveCursorCode(dx, dy) {
+    let data = "";
+    if (dx) data += dx < 0 ? `\x1b[${-dx}D` : `\x1b[${dx}C`;
+    if (dy) data += dy < 0 ? `\x1b[${-dy}A` : `\x1b[${dy}B`;
+    return data;
+  }
+
+  function moveCursor(stream, dx, dy, callback) {
+    return writeTo(stream, moveCursorCode(dx, dy), callback);
+  }
+
+  // ==========================================================================
+  // Interface
+  // ==========================================================================
+
+  function useAfterClose() {
+    const err = new Error("readline was closed");
+    err.code = "ERR_USE_AFTER_CLOSE";
+    return err;
+  }
+
+  function abortError(signal) {
+    const err = new Error("The operation was aborted", { cause: signal.reason });
+    err.name = "AbortError";
+    err.code = "ABORT_ERR";
+    return err;
+  }
+
+  // Start of the word before `cursor` and end of the word after it
+  function wordLeft(line, cursor) {
+    const match = /\S+\s*$/.exec(line.slice(0, cursor));
+    return match === null ? 0 : match.index;
+  }
+
+  function wordRight(line, cursor) {
+    const match = /^\s*\S+/.exec(line.slice(cursor));
+    return match === null ? line.length : cursor + match[0].length;
+  }
+
+  function commonPrefix(strings) {
+    if (strings.length === 0) return "";
+    let prefix = strings[0];
+    for (const s of strings) {
+      while (!s.startsWith(prefix)) prefix = prefix.slice(0, -1);
+    }
+    return prefix;
+  }
+
+  class Interface extends EventEmitter {
+    #closed = false;
+    #paused = false;
+    #prompt = "> ";
+    #buffer = "";
+    #sawReturn = false;
+    #decoder;
+    #question = null;
+    #savedPrompt = null;
+    #sawReturnAt = 0;
+    #lines = [];
+    #waiting = [];
+    #historyIndex = -1;
+    #savedLine = "";
+    #setRaw = false;
+    #onData;
+    #onEnd;
+    #onKeypress;
+
+    constructor(input, output, completer, terminal) {
+      super();
+      this.#decoder = new StringDecoder("utf8");
+      let options = {};
+      if (input && typeof input.on !== "function" && typeof input === "object") {
+        options = input;
+        ({ input, output, completer, terminal } = options);
+      }
+      this.input = input;
+      this.output = output ?? null;
+      this.completer = typeof completer === "function" ? completer : null;
+      this.terminal = terminal === undefined ? Boolean(this.output?.isTTY) : Boolean(terminal);
+      this.crlfDelay = Math.max(100, Number(options.crlfDelay) || 100);
+      this.historySize = options.historySize ?? kHistorySize;
+      this.removeHistoryDuplicates = Boolean(options.removeHistoryDuplicates);
+      this.history = this.terminal ? [...(options.history ?? [])] : [];
+      this.line = "";
+      this.cursor = 0;
+      if (options.prompt !== undefined) this.#prompt = String(options.prompt);
+
+      if (options.signal) {
+        if (options.signal.aborted) {
+          queueMicrotask(() => this.close());
+        } else {
+          options.signal.addEventListener("abort", () => this.close(), { once: true });
+        }
+      }
+
+      this.#onEnd = () => {
+        if (this.#buffer) this.#onLine(this.#buffer);
+        this.#buffer = "";
+        this.close();
+      };
+      input?.on?.("end", this.#onEnd);
+
+      if (this.terminal) {
+        emitKeypressEvents(input, this);
+        this.#onKeypress = (string, key) => this.#ttyWrite(string, key);
+        input.on("keypress", this.#onKeypress);
+        if (typeof input.setRawMode === "function" && !input.isRaw) {
+          input.setRawMode(true);
+          this.#setRaw = true;
+        }
+      } else {
+        this.#onData = (chunk) => this.#normalWrite(chunk);
+        input?.on?.("data", this.#onData);
+      }
+      input?.resume?.();
+    }
+
+    get closed() {
+      return this.#closed;
+    }
+
+    // Lines from a non-terminal stream; "\r\n" split across chunks counts once
+    #normalWrite(chunk) {
+      let text = typeof chunk === "string" ? chunk : this.#decoder.write(chunk);
+      if (this.#sawReturn && text.startsWith("\n")) text = text.slice(1);
+      this.#sawReturn = false;
+      this.#buffer += text;
+      let match;
+      while ((match = /\r\n|\n|\r/.exec(this.#buffer)) !== null) {
+        const line = this.#buffer.slice(0, match.index);
+        this.#buffer = this.#buffer.slice(match.index + match[0].length);
+        if (match[0] === "\r" && this.#buffer === "") this.#sawReturn = true;
+        this.#onLine(line);
+        if (this.#closed) return;
+      }
+    }
+
+    #onLine(line) {
+      if (this.#question !== null) {
+        const callback = this.#question;
+        this.#question = null;
+        callback(line);
+        return;
+      }
+      const waiter = this.#waiting.shift();
+      if (waiter) waiter({ value: line, done: false });
+      else if (this.listenerCount("line") === 0) this.#lines.push(line);
+      this.emit("line", line);
+    }
+
+    setPrompt(prompt) {
+      this.#prompt = String(prompt);
+      this.#savedPrompt = null;
+    }
+
+    getPrompt() {
+      return this.#prompt;
+    }
+
+    prompt(preserveCursor) {
+      if (this.#closed) throw useAfterClose();
+      if (this.#paused) this.resume();
+      if (this.terminal) {
+        if (!preserveCursor) this.cursor = 0;
+        this.#refreshLine();
+      } else {
+        this.output?.write?.(this.#prompt);
+      }
+    }
+
+    question(query, options, callback) {
+      if (typeof options === "function") [options, callback] = [{}, options];
+      if (this.#closed) throw useAfterClose();
+      const signal = options?.signal;
+      if (signal?.aborted) return;
+      if (this.#question !== null) {
+        this.prompt();
+        return;
+      }
+      if (signal) {
+        const onAbort = () => {
+          this.#question = null;
+          this.#restorePrompt();
+        };
+        signal.addEventListener("abort", onAbort, { once: true });
+        const inner = callback;
+        callback = (answer) => {
+          signal.removeEventListener("abort", onAbort);
+          inner(answer);
+        };
+      }
+      const previous = this.#prompt;
+      this.#prompt = String(query);
+      this.#savedPrompt = previous;
+      this.#question = (answer) => {
+        this.#restorePrompt();
+        callback?.(answer);
+      };
+      this.prompt();
+    }
+
+    #restorePrompt() {
+      if (this.#savedPrompt !== null) {
+        this.#prompt = this.#savedPrompt;
+        this.#savedPrompt = null;
+      }
+    }
+
+    getCursorPos() {
+      const columns = this.output?.columns || Infinity;
+      const offset = this.#prompt.length + this.cursor;
+      return { rows: Math.floor(offset / columns), cols: offset % columns };
+    }
+
+    write(data, key) {
+      if (this.#closed) throw useAfterClose();
+      if (this.#paused) this.resume();
+      if (this.terminal) {
+        if (key !== undefined && key !== null) {
+          this.#ttyWrite(data, key);
+        } else if (data !== undefined && data !== null) {
+          for (const [string, k] of decodeKeys(String(data)).keys) this.#ttyWrite(string, k);
+        }
+      } else if (data !== undefined && data !== null) {
+        this.#normalWrite(String(data));
+      }
+    }
+
+    pause() {
+      if (this.#paused) return this;
+      this.input?.pause?.();
+      this.#paused = true;
+      this.emit("pause");
+      return this;
+    }
+
+    resume() {
+      if (!this.#paused) return this;
+      this.input?.resume?.();
+      this.#paused = false;
+      this.emit("resume");
+      return this;
+    }
+
+    close() {
+      if (this.#closed) return;
+      this.pause();
+      if (this.#setRaw) {
+        this.input.setRawMode(false);
+        this.#setRaw = false;
+      }
+      if (this.#onData) this.input?.removeListener?.("data", this.#onData);
+      if (this.#onKeypress) this.input?.removeListener?.("keypress", this.#onKeypress);
+      this.input?.removeListener?.("end", this.#onEnd);
+      this.#closed = true;
+      for (const waiter of this.#waiting.splice(0)) waiter({ value: undefined, done: true });
+      this.emit("close");
+    }
+
+    [Symbol.asyncIterator]() {
+      return {
+        next: () => {
+          if (this.#lines.length) {
+            return Promise.resolve({ value: this.#lines.shift(), done: false });
+          }
+          if (this.#closed) return Promise.resolve({ value: undefined, done: true });
+          return new Promise((resolve) => this.#waiting.push(resolve));
+        },
+        return: () => {
+          this.close();
+          return Promise.resolve({ value: undefined, done: true });
+        },
+        [Symbol.asyncIterator]() {
+          return this;
+        },
+      };
+    }
+
+    // ------------------------------------------------------------------------
+    // Line editing
+    // ------------------------------------------------------------------------
+
+    #write(data) {
+      this.output?.write?.(data);
+    }
+
+    #refreshLine() {
+      if (this.output === null) return;
+      this.#write(cursorToCode(0) + this.#prompt + this.line + "\x1b[0J");
+      this.#write(cursorToCode(this.#prompt.length + this.cursor));
+    }
+
+    #insert(text) {
+      const atEnd = this.cursor === this.line.length;
+      this.line = this.line.slice(0, this.cursor) + text + this.line.slice(this.cursor);
+      this.cursor += text.length;
+      if (atEnd) this.#write(text);
+      else this.#refreshLine();
+    }
+
+    #setLine(line, cursor = line.length) {
+      this.line = line;
+      this.cursor = cursor;
+      this.#refreshLine();
+    }
+
+    #moveTo(cursor) {
+      cursor = Math.max(0, Math.min(this.line.length, cursor));
+      if (cursor === this.cursor) return;
+      this.#write(moveCursorCode(cursor - this.cursor, 0));
+      this.cursor = cursor;
+    }
+
+    #deleteRange(start, end) {
+      if (start >= end) return;
+      this.line = this.line.slice(0, start) + this.line.slice(end);
+      this.cursor = start;
+      this.#refreshLine();
+    }
+
+    #addHistory(line) {
+      if (line.trim() === "" || this.historySize === 0) return;
+      if (this.removeHistoryDuplicates) {
+        const index = this.history.indexOf(line);
+        if (index !== -1) this.history.splice(index, 1);
+      } else if (this.history[0] === line) {
+        return;
+      }
+      this.history.unshift(line);
+      if (this.history.length > this.historySize) this.history.length = this.historySize;
+      this.emit("history", this.history);
+    }
+
+    #historyMove(step) {
+      const index = this.#historyIndex + step;
+      if (index < -1 || index >= this.history.length) return;
+      if (this.#historyIndex === -1) this.#savedLine = this.line;
+      this.#historyIndex = index;
+      this.#setLine(index === -1 ? this.#savedLine : this.history[index]);
+    }
+
+    #submit() {
+      const line = this.line;
+      this.#write("\r\n");
+      this.line = "";
+      this.cursor = 0;
+      this.#historyIndex = -1;
+      this.#addHistory(line);
+      this.#onLine(line);
+    }
+
+    #complete() {
+      const before = this.line.slice(0, this.cursor);
+      const done = (err, result) => {
+        if (err || !result || this.#closed) return;
+        const [completions, sub] = result;
+        const matches = completions.filter((c) => c.startsWith(sub));
+        if (matches.length === 0) return;
+        const prefix = commonPrefix(matches);
+        if (prefix.length > sub.length) {
+          this.#insert(prefix.slice(sub.length));
+        } else if (matches.length > 1) {
+          this.#write("\r\n" + matches.join("  ") + "\r\n");
+          this.#refreshLine();
+        }
+      };
+      if (this.completer.length === 2) {
+        this.completer(before, done);
+        return;
+      }
+      try {
+        const result = this.completer(before);
+        if (result && typeof result.then === "function") result.then((r) => done(null, r), done);
+        else done(null, result);
+      } catch (err) {
+        done(err);
+      }
+    }
+
+    #ttyWrite(string, key = {}) {
+      if (this.#closed) return;
+      if (key.ctrl && !key.meta) {
+        switch (key.name) {
+          case "c":
+            if (this.listenerCount("SIGINT") > 0) {
+              this.emit("SIGINT");
+            } else {
+              this.#write("\r\n");
+              this.close();
+            }
+            return;
+          case "d":
+            if (this.line.length === 0) {
+              this.#write("\r\n");
+              this.close();
+            } else {
+              this.#deleteRange(this.cursor, this.cursor + 1);
+            }
+            return;
+          case "h":
+            this.#deleteRange(this.cursor - 1, this.cursor);
+            return;
+          case "u":
+            this.#deleteRange(0, this.cursor);
+            return;
+          case "k":
+            this.#deleteRange(this.cursor, this.line.length);
+            return;
+          case "w":
+          case "backspace":
+            this.#deleteRange(wordLeft(this.line, this.cursor), this.cursor);
+            return;
+          case "delete":
+            this.#deleteRange(this.cursor, wordRight(this.line, this.cursor));
+            return;
+          case "a":
+            this.#moveTo(0);
+            return;
+          case "e":
+            this.#moveTo(this.line.length);
+            return;
+          case "b":
+            this.#moveTo(this.cursor - 1);
+            return;
+          case "f":
+            this.#moveTo(this.cursor + 1);
+            return;
+          case "left":
+            this.#moveTo(wordLeft(this.line, this.cursor));
+            return;
+          case "right":
+            this.#moveTo(wordRight(this.line, this.cursor));
+            return;
+          case "l":
+            this.#write("\x1b[1;1H\x1b[0J");
+            this.#refreshLine();
+            return;
+          case "p":
+            this.#historyMove(1);
+            return;
+          case "n":
+            this.#historyMove(-1);
+            return;
+          case "z":
+            if (this.listenerCount("SIGTSTP") > 0) this.emit("SIGTSTP");
+            return;
+          case "m":
+          case "j":
+            this.#submit();
+            return;
+          case "i":
+            break;
+          default:
+            return;
+        }
+      }
+      if (key.meta) {
+        switch (key.name) {
+          case "b":
+          case "left":
+            this.#moveTo(wordLeft(this.line, this.cursor));
+            return;
+          case "f":
+          case "right":
+            this.#moveTo(wordRight(this.line, this.cursor));
+            return;
+          case "d":
+          case "delete":
+            this.#deleteRange(this.cursor, wordRight(this.line, this.cursor));
+            return;
+          case "backspace":
+            this.#deleteRange(wordLeft(this.line, this.cursor), this.cursor);
+            return;
+          default:
+            return;
+        }
+      }
+      switch (key.name) {
+        case "return":
+          this.#sawReturnAt = Date.now();
+          this.#submit();
+          return;
+        case "enter":
+          // The "\n" of a "\r\n" pair ends no second line
+          if (Date.now() - this.#sawReturnAt > this.crlfDelay) this.#submit();
+          this.#sawReturnAt = 0;
+          return;
+        case "backspace":
+          this.#deleteRange(this.cursor - 1, this.cursor);
+          return;
+        case "delete":
+          this.#deleteRange(this.cursor, this.cursor + 1);
+          return;
+        case "left":
+          this.#moveTo(this.cursor - 1);
+          return;
+        case "right":
+          this.#moveTo(this.cursor + 1);
+          return;
+        case "home":
+          this.#moveTo(0);
+          return;
+        case "end":
+          this.#moveTo(this.line.length);
+          return;
+        case "up":
+          this.#historyMove(1);
+          return;
+        case "down":
+          this.#historyMove(-1);
+          return;
+        case "tab":
+          if (this.completer !== null) {
+            this.#complete();
+            return;
+          }
+          break;
+        default:
+          break;
+      }
+      if (typeof string === "string" && string.length > 0 && (string === "\t" || string >= " ")) {
+        // Pasted text arrives as one key per character; a newline submits
+        this.#insert(string);
+      }
+    }
+  }
+
+  // ==========================================================================
+  // readline/promises
+  // ==========================================================================
+
+  class PromisesInterface extends Interface {
+    question(query, options = {}) {
+      return new Promise((resolve, reject) => {
+        const signal = options?.signal;
+        if (signal?.aborted) {
+          reject(abortError(signal));
+          return;
+        }
+        if (signal) {
+          signal.addEventListener("abort", () => reject(abortError(signal)), { once: true });
+        }
+        super.question(query, options, resolve);
+      });
+    }
+  }
+
+  // Cursor actions queued until commit()
+  class Readline {
+    #stream;
+    #autoCommit;
+    #pending = [];
+
+    constructor(stream, options = {}) {
+      this.#stream = stream;
+      this.#autoCommit = Boolean(options?.autoCommit);
+    }
+
+    #queue(data) {
+      if (this.#autoCommit) this.#stream.write(data);
+      else this.#pending.push(data);
+      return this;
+    }
+
+    clearLine(dir) {
+      return this.#queue(dir < 0 ? "\x1b[1K" : dir > 0 ? "\x1b[0K" : "\x1b[2K");
+    }
+
+    clearScreenDown() {
+      return this.#queue("\x1b[0J");
+    }
+
+    cursorTo(x, y) {
+      return this.#queue(cursorToCode(x, y));
+    }
+
+    moveCursor(dx, dy) {
+      return this.#queue(moveCursorCode(dx, dy));
+    }
+
+    commit() {
+      const data = this.#pending.join("");
+      this.#pending = [];
+      return new Promise((resolve) => {
+        if (data === "") resolve();
+        else this.#stream.write(data, () => resolve());
+      });
+    }
+
+    rollback() {
+      this.#pending = [];
+      return this;
+    }
+  }
+
+  const promises = {
+    Interface: PromisesInterface,
+    Readline,
+    createInterface: (input, output, completer, terminal) =>
+      new PromisesInterface(input, output, completer, terminal),
+  };
+
+  globalThis.readline = {
+    Interface,
+    createInterface: (input, output, completer, terminal) =>
+      new Interface(input, output, completer, terminal),
+    emitKeypressEvents,
+    clearLine,
+    clearScreenDown,
+    cursorTo,
+    moveCursor,
+    promises,
+  };
+})();
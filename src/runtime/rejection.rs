@@ -0,0 +1,280 @@
+//! Promise rejection tracking and uncaught exceptions
+//!
+//! Boa reports every rejection without a handler, and every handler added to
+//! such a promise later, through `HostHooks::promise_rejection_tracker`. The
+//! tracker only queues them; at the end of each microtask checkpoint the
+//! event loop hands the queue to `process`, which emits `unhandledRejection`
+//! and `rejectionHandled` the way Node.js does and applies the
+//! `--unhandled-rejections` mode.
+//!
+//! Boa 0.21 only marks a promise handled when `then` is called after it
+//! settled, so a pending promise that was awaited or given a handler is still
+//! reported when it rejects. Rejecting such a promise queues its reaction
+//! jobs right before the report, with no JavaScript running in between; the
+//! event loop tells the tracker where each promise job was queued, and a
+//! rejection reported from that same place had reactions.
+//!
+//! Exceptions that escape a timer, immediate or source callback go through
+//! [`handle_uncaught`], which emits `uncaughtExceptionMonitor` and
+//! `uncaughtException`. Without a listener the error stays fatal.
+
+use boa_engine::{
+    Context, JsData, JsError, JsObject, JsResult, JsValue,
+    ast::Position,
+    builtins::promise::{OperationType, PromiseState},
+    context::HostHooks,
+    js_string,
+    object::builtins::{JsArray, JsPromise},
+};
+use boa_gc::{Finalize, Trace};
+use std::{cell::RefCell, fmt, rc::Rc, str::FromStr};
+
+/// What to do with a rejection that is still unhandled after a checkpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnhandledRejections {
+    /// Emit `unhandledRejection`; without a listener raise it as an uncaught exception
+    #[default]
+    Throw,
+    /// Raise as an uncaught exception, then emit `unhandledRejection`
+    Strict,
+    /// Emit `unhandledRejection` and always print a warning
+    Warn,
+    /// Like `Warn` when nothing listens, and set the exit code to 1
+    WarnWithErrorCode,
+    /// Only emit `unhandledRejection`
+    None,
+}
+
+impl UnhandledRejections {
+    /// Name as accepted by `--unhandled-rejections`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Throw => "throw",
+            Self::Strict => "strict",
+            Self::Warn => "warn",
+            Self::WarnWithErrorCode => "warn-with-error-code",
+            Self::None => "none",
+        }
+    }
+}
+
+impl fmt::Display for UnhandledRejections {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for UnhandledRejections {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "throw" => Ok(Self::Throw),
+            "strict" => Ok(Self::Strict),
+            "warn" => Ok(Self::Warn),
+            "warn-with-error-code" => Ok(Self::WarnWithErrorCode),
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "invalid unhandled rejections mode '{}' (expected strict, throw, warn, warn-with-error-code or none)",
+                s
+            )),
+        }
+    }
+}
+
+/// Host hooks that queue promise rejection operations for the next checkpoint
+#[derive(Default)]
+pub struct RejectionTracker {
+    mode: UnhandledRejections,
+    /// Rejected without a handler since the last checkpoint
+    unhandled: RefCell<Vec<JsObject>>,
+    /// Given a handler after a checkpoint may already have reported them
+    handled: RefCell<Vec<JsObject>>,
+    /// Where the last promise job was queued, until a job runs or a
+    /// rejection claims it
+    queued: RefCell<Option<Site>>,
+}
+
+/// Call depth and source position the engine was at
+type Site = (usize, Option<Position>);
+
+fn site(context: &Context) -> Site {
+    let frames: Vec<_> = context.stack_trace().collect();
+    let position = frames.first().and_then(|frame| frame.position().position);
+    (frames.len(), position)
+}
+
+impl RejectionTracker {
+    pub fn new(mode: UnhandledRejections) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Drop queued promises so they don't outlive the context
+    pub fn clear(&self) {
+        self.unhandled.borrow_mut().clear();
+        self.handled.borrow_mut().clear();
+        self.queued.borrow_mut().take();
+    }
+}
+
+impl HostHooks for RejectionTracker {
+    fn promise_rejection_tracker(
+        &self,
+        promise: &JsObject,
+        operation: OperationType,
+        context: &mut Context,
+    ) {
+        match operation {
+            OperationType::Reject => {
+                // Its reaction jobs were just queued: it was awaited or had a handler
+                if self.queued.borrow_mut().take() == Some(site(context)) {
+                    return;
+                }
+                self.unhandled.borrow_mut().push(promise.clone());
+            }
+            OperationType::Handle => {
+                let mut unhandled = self.unhandled.borrow_mut();
+                // Handled within the same checkpoint: nothing to report
+                match unhandled.iter().position(|p| JsObject::equals(p, promise)) {
+                    Some(index) => {
+                        unhandled.remove(index);
+                    }
+                    None => self.handled.borrow_mut().push(promise.clone()),
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Per-context access
+// ============================================================================
+
+/// Stores the tracker in the realm so the event loop can reach it
+#[derive(Trace, Finalize, JsData)]
+struct TrackerSlot(#[unsafe_ignore_trace] Rc<RejectionTracker>);
+
+/// Make `tracker` reachable from the event loop running `context`
+pub fn install(context: &mut Context, tracker: Rc<RejectionTracker>) {
+    context
+        .realm()
+        .host_defined_mut()
+        .insert(TrackerSlot(tracker));
+}
+
+/// A promise job was queued; a rejection reported from the same place next
+/// is the one that queued it
+pub fn job_queued(context: &Context) {
+    if let Some(tracker) = current(context) {
+        *tracker.queued.borrow_mut() = Some(site(context));
+    }
+}
+
+/// A job or callback is about to run; jobs queued so far belong to no
+/// rejection that follows
+pub fn job_boundary(context: &Context) {
+    if let Some(tracker) = current(context) {
+        tracker.queued.borrow_mut().take();
+    }
+}
+
+fn current(context: &Context) -> Option<Rc<RejectionTracker>> {
+    context
+        .realm()
+        .host_defined()
+        .get::<TrackerSlot>()
+        .map(|slot| Rc::clone(&slot.0))
+}
+
+// ============================================================================
+// Reporting
+// ============================================================================
+
+/// Report rejections queued since the last checkpoint to `process`
+///
+/// Returns whether any JavaScript ran, in which case new microtasks may be
+/// queued. An error means the rejection was fatal under the current mode.
+pub fn process_rejections(context: &mut Context) -> JsResult<bool> {
+    let Some(tracker) = current(context) else {
+        return Ok(false);
+    };
+    let unhandled = std::mem::take(&mut *tracker.unhandled.borrow_mut());
+    let handled = std::mem::take(&mut *tracker.handled.borrow_mut());
+    if unhandled.is_empty() && handled.is_empty() {
+        return Ok(false);
+    }
+
+    let report = context
+        .global_object()
+        .get(js_string!("__viper_process_rejections"), context)?;
+    let Some(report) = report.as_callable() else {
+        return Ok(false);
+    };
+
+    let rejections = JsArray::new(context);
+    for promise in unhandled {
+        let Ok(js_promise) = JsPromise::from_object(promise.clone()) else {
+            continue;
+        };
+        if let PromiseState::Rejected(reason) = js_promise.state() {
+            let pair = JsArray::from_iter([promise.into(), reason], context);
+            rejections.push(pair, context)?;
+        }
+    }
+    let handled = JsArray::from_iter(handled.into_iter().map(JsValue::from), context);
+
+    report.call(
+        &JsValue::undefined(),
+        &[
+            rejections.into(),
+            handled.into(),
+            js_string!(tracker.mode.as_str()).into(),
+        ],
+        context,
+    )?;
+    Ok(true)
+}
+
+/// Give an exception that escaped to the event loop to `uncaughtException`
+/// listeners; the error comes back if nothing handled it
+pub fn handle_uncaught(error: JsError, context: &mut Context) -> JsResult<()> {
+    let handler = context
+        .global_object()
+        .get(js_string!("__viper_handle_uncaught"), context)?;
+    let Some(handler) = handler.as_callable() else {
+        return Err(error);
+    };
+    let value = error.to_opaque(context);
+    let handled = handler.call(
+        &JsValue::undefined(),
+        &[value, js_string!("uncaughtException").into()],
+        context,
+    )?;
+    if handled.to_boolean() {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// Text printed for a fatal error: the stack of Error objects, otherwise the
+/// thrown value
+pub fn describe(error: &JsError, context: &mut Context) -> String {
+    let value = error.to_opaque(context);
+    if let Some(object) = value.as_object()
+        && let Ok(stack) = object.get(js_string!("stack"), context)
+        && let Some(stack) = stack.as_string()
+    {
+        let stack = stack.to_std_string_escaped();
+        if !stack.is_empty() {
+            return stack;
+        }
+    }
+    value
+        .to_string(context)
+        .map(|s| s.to_std_string_escaped())
+        .unwrap_or_else(|_| error.to_string())
+}
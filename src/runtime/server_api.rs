@@ -21,6 +21,13 @@ use boa_engine::{
 use std::cell::RefCell;
 use std::rc::Rc;
 
+#[cfg(feature = "server")]
+use super::permissions;
+#[cfg(feature = "server")]
+use boa_engine::object::builtins::{JsArrayBuffer, JsUint8Array};
+#[cfg(feature = "server")]
+use boa_engine::{JsObject, object::FunctionObjectBuilder};
+
 /// Register the Viper namespace with serve(), Router, and file APIs
 #[cfg(feature = "server")]
 pub fn register_server_api(context: &mut Context) -> JsResult<()> {
@@ -108,14 +115,13 @@ pub fn register_server_api(context: &mut Context) -> JsResult<()> {
     Ok(())
 }
 
-/// Placeholder when server feature is disabled
+/// Without the server feature only the Web API classes fetch() needs exist
 #[cfg(not(feature = "server"))]
-pub fn register_server_api(_context: &mut Context) -> JsResult<()> {
-    Ok(())
+pub fn register_server_api(context: &mut Context) -> JsResult<()> {
+    register_web_apis(context)
 }
 
 /// Register Web API classes (Request, Response, Headers)
-#[cfg(feature = "server")]
 fn register_web_apis(context: &mut Context) -> JsResult<()> {
     let classes = r#"
         // Headers class
@@ -175,6 +181,14 @@ fn register_web_apis(context: &mut Context) -> JsResult<()> {
                     this.headers = new Headers(options.headers || {});
                     this._body = options.body || null;
                 }
+                // FormData, Blob and URLSearchParams bodies bring their content type
+                const encoded = __viper_encode_body(this._body);
+                if (encoded !== null) {
+                    this._body = encoded.bytes;
+                    if (encoded.type && !this.headers.has('content-type')) this.headers.set('content-type', encoded.type);
+                }
+                // Aborts when the client disconnects before the response is sent
+                this.signal = options.signal ?? input?.signal ?? new AbortController().signal;
                 // Parse URL for params (set by router)
                 this.params = options.params || {};
                 this.query = this._parseQuery();
@@ -193,12 +207,24 @@ fn register_web_apis(context: &mut Context) -> JsResult<()> {
                 return query;
             }
 
-            get body() { return this._body; }
+            get body() {
+                if (this._body === null || this._body === undefined) return null;
+                if (this._stream === undefined) this._stream = __viper_body_stream(this._body);
+                return this._stream;
+            }
 
             async text() {
                 if (this._body === null) return '';
                 if (typeof this._body === 'string') return this._body;
-                return String(this._body);
+                return new TextDecoder().decode(await __viper_body_bytes(this._body));
+            }
+
+            async arrayBuffer() {
+                return (await this.bytes()).buffer;
+            }
+
+            async bytes() {
+                return (await __viper_body_bytes(this._body)).slice();
             }
 
             async json() {
@@ -207,13 +233,11 @@ fn register_web_apis(context: &mut Context) -> JsResult<()> {
             }
 
             async formData() {
-                const text = await this.text();
-                const data = new Map();
-                for (const pair of text.split('&')) {
-                    const [key, value] = pair.split('=');
-                    if (key) data.set(decodeURIComponent(key), decodeURIComponent(value || ''));
-                }
-                return data;
+                return __viper_parse_form_data(await this.bytes(), this.headers.get('content-type'));
+            }
+
+            async blob() {
+                return new Blob([await this.bytes()], { type: this.headers.get('content-type') ?? '' });
             }
 
             clone() {
@@ -235,9 +259,14 @@ fn register_web_apis(context: &mut Context) -> JsResult<()> {
                 this.headers = new Headers(options.headers || {});
                 this.ok = this.status >= 200 && this.status < 300;
 
-                // Auto-detect content type
-                if (body !== null && !this.headers.has('content-type')) {
-                    if (typeof body === 'object' && !(body instanceof ArrayBuffer) && !ArrayBuffer.isView(body)) {
+                // FormData, Blob and URLSearchParams bodies bring their content type
+                const encoded = __viper_encode_body(body);
+                if (encoded !== null) {
+                    this._body = encoded.bytes;
+                    if (encoded.type && !this.headers.has('content-type')) this.headers.set('content-type', encoded.type);
+                } else if (body !== null && !this.headers.has('content-type')) {
+                    // Auto-detect content type
+                    if (typeof body === 'object' && !(body instanceof ArrayBuffer) && !ArrayBuffer.isView(body) && !__viper_is_readable_stream(body)) {
                         this.headers.set('content-type', 'application/json');
                         this._body = JSON.stringify(body);
                     } else if (typeof body === 'string') {
@@ -250,12 +279,24 @@ fn register_web_apis(context: &mut Context) -> JsResult<()> {
                 }
             }
 
-            get body() { return this._body; }
+            get body() {
+                if (this._body === null || this._body === undefined) return null;
+                if (this._stream === undefined) this._stream = __viper_body_stream(this._body);
+                return this._stream;
+            }
 
             async text() {
                 if (this._body === null) return '';
                 if (typeof this._body === 'string') return this._body;
-                return String(this._body);
+                return new TextDecoder().decode(await __viper_body_bytes(this._body));
+            }
+
+            async arrayBuffer() {
+                return (await this.bytes()).buffer;
+            }
+
+            async bytes() {
+                return (await __viper_body_bytes(this._body)).slice();
             }
 
             async json() {
@@ -263,12 +304,30 @@ fn register_web_apis(context: &mut Context) -> JsResult<()> {
                 return JSON.parse(text);
             }
 
+            async formData() {
+                return __viper_parse_form_data(await this.bytes(), this.headers.get('content-type'));
+            }
+
+            async blob() {
+                return new Blob([await this.bytes()], { type: this.headers.get('content-type') ?? '' });
+            }
+
             clone() {
-                return new Response(this._body, {
+                let body = this._body;
+                // A streamed body is split so both copies can read it
+                if (__viper_is_readable_stream(body)) {
+                    [this._body, body] = body.tee();
+                    this._stream = undefined;
+                }
+                const copy = new Response(body, {
                     status: this.status,
                     statusText: this.statusText,
                     headers: new Headers(this.headers)
                 });
+                for (const key of ['url', 'redirected', 'type']) {
+                    if (key in this) Object.defineProperty(copy, key, { value: this[key], enumerable: true });
+                }
+                return copy;
             }
 
             static json(data, options = {}) {
@@ -538,6 +597,7 @@ fn register_native_helpers(context: &mut Context) -> JsResult<()> {
             .transpose()?
             .map(|s| s.to_std_string_escaped())
             .unwrap_or_default();
+        permissions::check_write(context, &path)?;
         let recursive = args.get(1).map(|v| v.to_boolean()).unwrap_or(false);
 
         let result = if recursive {
@@ -568,6 +628,7 @@ fn register_native_helpers(context: &mut Context) -> JsResult<()> {
             .transpose()?
             .map(|s| s.to_std_string_escaped())
             .unwrap_or_default();
+        permissions::check_write(context, &path)?;
         let recursive = args.get(1).map(|v| v.to_boolean()).unwrap_or(false);
 
         let metadata = std::fs::metadata(&path);
@@ -605,6 +666,7 @@ fn register_native_helpers(context: &mut Context) -> JsResult<()> {
             .transpose()?
             .map(|s| s.to_std_string_escaped())
             .unwrap_or_else(|| ".".to_string());
+        permissions::check_read(context, &path)?;
 
         match std::fs::read_dir(&path) {
             Ok(entries) => {
@@ -635,6 +697,7 @@ fn register_native_helpers(context: &mut Context) -> JsResult<()> {
             .transpose()?
             .map(|s| s.to_std_string_escaped())
             .unwrap_or_default();
+        permissions::check_read(context, &path)?;
 
         match std::fs::metadata(&path) {
             Ok(m) => {
@@ -707,6 +770,7 @@ fn serve_function(_this: &JsValue, args: &[JsValue], context: &mut Context) -> J
             .map(|s| s.to_std_string_escaped())
             .unwrap_or_else(|_| "127.0.0.1".to_string())
     };
+    permissions::check_net(context, &hostname, Some(port))?;
 
     // Get the fetch handler (can be a function or router.fetch)
     let fetch_handler = options_obj.get(js_string!("fetch"), context)?;
@@ -761,9 +825,6 @@ fn serve_function(_this: &JsValue, args: &[JsValue], context: &mut Context) -> J
 /// Call the JavaScript fetch handler
 #[cfg(feature = "server")]
 fn call_js_handler(context: &mut Context, req: &JsRequest) -> Result<JsResponse, String> {
-    use boa_engine::builtins::promise::PromiseState;
-    use boa_engine::object::builtins::JsPromise;
-
     let fetch_fn = context
         .global_object()
         .get(js_string!("__viper_fetch_handler"), context)
@@ -773,61 +834,162 @@ fn call_js_handler(context: &mut Context, req: &JsRequest) -> Result<JsResponse,
         .as_object()
         .ok_or_else(|| "Fetch handler not found".to_string())?;
 
-    let request_obj = create_js_request(context, req)?;
+    let controller = construct_global(context, "AbortController", &[])?;
+    let signal = controller
+        .get(js_string!("signal"), context)
+        .map_err(|e| e.to_string())?;
+    let request_obj = create_js_request(context, req, signal)?;
 
     let result = fetch_obj
         .call(&JsValue::undefined(), &[request_obj], context)
         .map_err(|e| e.to_string())?;
 
-    // Check if result is a Promise by trying to convert it
-    if let Some(promise_obj) = result.as_object() {
-        // Try to convert to JsPromise - if successful, it's a promise
-        if let Ok(promise) = JsPromise::from_object(promise_obj.clone()) {
-            // Check if promise is already fulfilled (fast path - most common case)
-            if let PromiseState::Fulfilled(ref value) = promise.state() {
-                return extract_js_response(value, context);
-            }
-
-            // Promise is pending - run the event loop until it settles
-            // This follows the same pattern as deno_core's approach:
-            // run jobs until the promise resolves or we exhaust pending jobs
-            let max_iterations = 10;
-            for _ in 0..max_iterations {
-                // Run pending microtasks/jobs
-                if context.run_jobs().is_err() {
-                    break; // No more jobs to run
-                }
+    // Abort request.signal if the client goes away while the handler awaits
+    let _disconnect = if is_pending(&result) {
+        Some(DisconnectWatch::new(context, req, controller)?)
+    } else {
+        None
+    };
 
-                // Check state after running jobs
-                match promise.state() {
-                    PromiseState::Fulfilled(ref value) => {
-                        return extract_js_response(value, context);
-                    }
-                    PromiseState::Rejected(ref err) => {
-                        let err_str = err
-                            .to_string(context)
-                            .map(|s| s.to_std_string_escaped())
-                            .unwrap_or_else(|_| "Unknown error".to_string());
-                        return Err(format!("Promise rejected: {}", err_str));
-                    }
-                    PromiseState::Pending => {
-                        // Continue loop
-                    }
+    let response = settle(
+        result,
+        "Promise did not settle - handler must call res.end()",
+        context,
+    )?;
+    extract_js_response(&response, context)
+}
+
+/// `new globalThis[name](...args)`
+#[cfg(feature = "server")]
+fn construct_global(
+    context: &mut Context,
+    name: &str,
+    args: &[JsValue],
+) -> Result<JsObject, String> {
+    let ctor = context
+        .global_object()
+        .get(js_string!(name), context)
+        .map_err(|e| e.to_string())?;
+    let ctor = ctor
+        .as_object()
+        .ok_or_else(|| format!("{} constructor not found", name))?;
+    ctor.construct(args, Some(&ctor), context)
+        .map_err(|e| e.to_string())
+}
+
+/// Whether `value` is a promise that has not settled yet
+#[cfg(feature = "server")]
+fn is_pending(value: &JsValue) -> bool {
+    use boa_engine::builtins::promise::PromiseState;
+    use boa_engine::object::builtins::JsPromise;
+
+    value
+        .as_object()
+        .and_then(|obj| JsPromise::from_object(obj.clone()).ok())
+        .is_some_and(|promise| matches!(promise.state(), PromiseState::Pending))
+}
+
+/// Aborts a request's controller from the event loop once its client hangs up
+///
+/// The loop source is unref'd so it never keeps a handler waiting on its own;
+/// dropping the watch stops the helper thread and closes the source.
+#[cfg(feature = "server")]
+struct DisconnectWatch {
+    event_loop: Rc<super::event_loop::ViperEventLoop>,
+    source: super::event_loop::HandleId,
+    _watch: hyper_server::CloseWatch,
+}
+
+#[cfg(feature = "server")]
+impl DisconnectWatch {
+    fn new(context: &mut Context, req: &JsRequest, controller: JsObject) -> Result<Self, String> {
+        let abort = NativeFunction::from_copy_closure_with_captures(
+            |_this, _args, controller, context| {
+                let abort = controller.get(js_string!("abort"), context)?;
+                if let Some(abort) = abort.as_callable() {
+                    abort.call(&JsValue::from(controller.clone()), &[], context)?;
                 }
-            }
+                Ok(JsValue::undefined())
+            },
+            controller,
+        );
+        let callback = FunctionObjectBuilder::new(context.realm(), abort).build();
+
+        let event_loop = super::event_loop::current(context).map_err(|e| e.to_string())?;
+        let source = event_loop.add_source(callback);
+        event_loop.set_ref(source, false);
+        let notifier = event_loop.notifier(source);
+        let watch = req.connection.watch(move || notifier.notify());
+        Ok(Self {
+            event_loop,
+            source,
+            _watch: watch,
+        })
+    }
+}
 
-            // Promise still pending after exhausting jobs
-            return Err("Promise did not settle - handler must call res.end()".to_string());
-        }
+#[cfg(feature = "server")]
+impl Drop for DisconnectWatch {
+    fn drop(&mut self) {
+        self.event_loop.close_source(self.source);
     }
+}
+
+/// The value of `value`, or what it fulfills with if it is a promise
+///
+/// `pending` is the error when the event loop runs dry first.
+#[cfg(feature = "server")]
+fn settle(value: JsValue, pending: &str, context: &mut Context) -> Result<JsValue, String> {
+    use boa_engine::builtins::promise::PromiseState;
+    use boa_engine::object::builtins::JsPromise;
 
-    // Not a promise, extract directly
-    extract_js_response(&result, context)
+    // Check if value is a Promise by trying to convert it
+    let Some(promise) = value
+        .as_object()
+        .and_then(|obj| JsPromise::from_object(obj.clone()).ok())
+    else {
+        return Ok(value);
+    };
+
+    // Check if promise is already fulfilled (fast path - most common case)
+    if let PromiseState::Fulfilled(value) = promise.state() {
+        return Ok(value);
+    }
+
+    // Promise is pending - run the event loop until it settles, so handlers
+    // and body streams can await timers, workers and other I/O
+    if let PromiseState::Pending = promise.state() {
+        let event_loop = super::event_loop::current(context).map_err(|e| e.to_string())?;
+        event_loop
+            .run_until(
+                context,
+                |_| !matches!(promise.state(), PromiseState::Pending),
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+    }
+
+    match promise.state() {
+        PromiseState::Fulfilled(value) => Ok(value),
+        PromiseState::Rejected(err) => {
+            let err_str = err
+                .to_string(context)
+                .map(|s| s.to_std_string_escaped())
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            Err(format!("Promise rejected: {}", err_str))
+        }
+        // Nothing left in the loop that could settle the promise
+        PromiseState::Pending => Err(pending.to_string()),
+    }
 }
 
 /// Create a JS Request object
 #[cfg(feature = "server")]
-fn create_js_request(context: &mut Context, req: &JsRequest) -> Result<JsValue, String> {
+fn create_js_request(
+    context: &mut Context,
+    req: &JsRequest,
+    signal: JsValue,
+) -> Result<JsValue, String> {
     let request_ctor = context
         .global_object()
         .get(js_string!("Request"), context)
@@ -843,17 +1005,13 @@ fn create_js_request(context: &mut Context, req: &JsRequest) -> Result<JsValue,
             JsValue::from(js_string!(req.method.clone())),
             Default::default(),
         )
+        .property(js_string!("signal"), signal, Default::default())
         .build();
 
     if let Some(body) = &req.body {
-        let body_str = String::from_utf8_lossy(body);
+        let bytes = bytes_to_uint8_array(body, context).map_err(|e| e.to_string())?;
         options
-            .set(
-                js_string!("body"),
-                JsValue::from(js_string!(body_str.to_string())),
-                false,
-                context,
-            )
+            .set(js_string!("body"), bytes, false, context)
             .map_err(|e| e.to_string())?;
     }
 
@@ -939,11 +1097,7 @@ fn extract_js_response(value: &JsValue, context: &mut Context) -> Result<JsRespo
         if let Some(s) = body_val.as_string() {
             bytes::Bytes::from(s.to_std_string_escaped())
         } else if !body_val.is_null_or_undefined() {
-            let s = body_val
-                .to_string(context)
-                .map_err(|e| e.to_string())?
-                .to_std_string_escaped();
-            bytes::Bytes::from(s)
+            bytes::Bytes::from(body_bytes(body_val, context)?)
         } else {
             bytes::Bytes::new()
         }
@@ -957,3 +1111,42 @@ fn extract_js_response(value: &JsValue, context: &mut Context) -> Result<JsRespo
         body,
     })
 }
+
+/// All bytes of a response body, reading it to the end if it is a stream
+#[cfg(feature = "server")]
+fn body_bytes(body: JsValue, context: &mut Context) -> Result<Vec<u8>, String> {
+    let read = context
+        .global_object()
+        .get(js_string!("__viper_body_bytes"), context)
+        .map_err(|e| e.to_string())?;
+    let read = read
+        .as_callable()
+        .ok_or_else(|| "Body reader not registered".to_string())?;
+    let bytes = read
+        .call(&JsValue::undefined(), &[body], context)
+        .map_err(|e| e.to_string())?;
+    let bytes = settle(bytes, "Response body stream did not finish", context)?;
+
+    let array = bytes
+        .as_object()
+        .and_then(|obj| JsUint8Array::from_object(obj.clone()).ok())
+        .ok_or_else(|| "Response body is not a byte stream".to_string())?;
+    let len = array.length(context).map_err(|e| e.to_string())?;
+    let mut out = Vec::with_capacity(len);
+    for i in 0..len {
+        let byte = array.get(i, context).map_err(|e| e.to_string())?;
+        out.push(byte.to_u32(context).unwrap_or(0) as u8);
+    }
+    Ok(out)
+}
+
+/// A Uint8Array holding a copy of `bytes`
+#[cfg(feature = "server")]
+fn bytes_to_uint8_array(bytes: &[u8], context: &mut Context) -> JsResult<JsValue> {
+    let array_buffer = JsArrayBuffer::new(bytes.len(), context)?;
+    let uint8_array = JsUint8Array::from_array_buffer(array_buffer, context)?;
+    for (i, byte) in bytes.iter().enumerate() {
+        uint8_array.set(i, JsValue::from(*byte as u32), false, context)?;
+    }
+    Ok(uint8_array.into())
+}
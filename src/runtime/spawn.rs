@@ -4,130 +4,192 @@
 //! - Viper.spawn(command, args?, options?) - Spawn a child process
 //! - Viper.exec(command) - Execute a shell command and return output
 //! - Viper.$ - Tagged template literal for shell commands (Bun-style)
+//!
+//! Viper.spawn and Viper.exec run the child on a helper thread and resolve
+//! through an event loop source, so the loop keeps running while they wait.
+//! A `signal` option kills the child when it aborts.
 
 use boa_engine::{
-    Context, JsNativeError, JsResult, JsValue, NativeFunction, Source, js_string,
+    Context, JsArgs, JsNativeError, JsResult, JsValue, NativeFunction, Source, js_string,
     object::ObjectInitializer, object::builtins::JsUint8Array,
 };
-use std::process::{Command, Stdio};
-
-/// Register the spawn APIs
-pub fn register_spawn(context: &mut Context) -> JsResult<()> {
-    // Viper.spawn(command, args?, options?)
-    let spawn_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
-        let command = args
-            .get(0)
-            .ok_or_else(|| JsNativeError::typ().with_message("spawn requires a command"))?
-            .to_string(context)?
-            .to_std_string_escaped();
-
-        // Parse arguments
-        let cmd_args: Vec<String> = if let Some(args_val) = args.get(1) {
-            if let Some(arr) = args_val.as_object() {
-                let mut result = Vec::new();
-                if let Ok(len_val) = arr.get(js_string!("length"), context) {
-                    if let Some(len) = len_val.as_number() {
-                        for i in 0..(len as usize) {
-                            if let Ok(item) = arr.get(i, context) {
-                                if let Ok(s) = item.to_string(context) {
-                                    result.push(s.to_std_string_escaped());
-                                }
-                            }
-                        }
+use std::collections::HashMap;
+use std::process::{Command, Output, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::{event_loop, permissions};
+
+/// Counter for background spawn jobs
+static SPAWN_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Finished background spawns waiting to be collected by JS
+static SPAWN_RESULTS: Mutex<Option<HashMap<u32, std::io::Result<Output>>>> = Mutex::new(None);
+
+/// Process ids of background spawns that are still running
+static SPAWN_PIDS: Mutex<Option<HashMap<u32, u32>>> = Mutex::new(None);
+
+/// Build a command from `(command, args?, options?)` JS arguments
+fn build_command(args: &[JsValue], context: &mut Context) -> JsResult<Command> {
+    let command = args
+        .first()
+        .ok_or_else(|| JsNativeError::typ().with_message("spawn requires a command"))?
+        .to_string(context)?
+        .to_std_string_escaped();
+
+    // Parse arguments
+    let cmd_args: Vec<String> = if let Some(args_val) = args.get(1) {
+        if let Some(arr) = args_val.as_object() {
+            let mut result = Vec::new();
+            if let Ok(len_val) = arr.get(js_string!("length"), context)
+                && let Some(len) = len_val.as_number()
+            {
+                for i in 0..(len as usize) {
+                    if let Ok(item) = arr.get(i, context)
+                        && let Ok(s) = item.to_string(context)
+                    {
+                        result.push(s.to_std_string_escaped());
                     }
                 }
-                result
-            } else {
-                Vec::new()
             }
+            result
         } else {
             Vec::new()
-        };
-
-        // Parse options
-        let options = args.get(2).and_then(|v| v.as_object());
-
-        let cwd = options
-            .as_ref()
-            .and_then(|o| o.get(js_string!("cwd"), context).ok())
-            .and_then(|v| {
-                if v.is_undefined() || v.is_null() {
-                    None
-                } else {
-                    v.to_string(context).ok().map(|s| s.to_std_string_escaped())
-                }
-            });
-
-        let shell = options
-            .as_ref()
-            .and_then(|o| o.get(js_string!("shell"), context).ok())
-            .map(|v| v.to_boolean())
-            .unwrap_or(false);
-
-        // Build the command
-        let mut cmd = if shell {
-            if cfg!(target_os = "windows") {
-                let mut c = Command::new("cmd");
-                c.arg("/C").arg(&command);
-                for arg in &cmd_args {
-                    c.arg(arg);
-                }
-                c
+        }
+    } else {
+        Vec::new()
+    };
+
+    // Parse options
+    let options = args.get(2).and_then(|v| v.as_object());
+
+    let cwd = options
+        .as_ref()
+        .and_then(|o| o.get(js_string!("cwd"), context).ok())
+        .and_then(|v| {
+            if v.is_undefined() || v.is_null() {
+                None
             } else {
-                let mut c = Command::new("sh");
-                let full_cmd = if cmd_args.is_empty() {
-                    command.clone()
-                } else {
-                    format!("{} {}", command, cmd_args.join(" "))
-                };
-                c.arg("-c").arg(&full_cmd);
-                c
+                v.to_string(context).ok().map(|s| s.to_std_string_escaped())
+            }
+        });
+
+    let shell = options
+        .as_ref()
+        .and_then(|o| o.get(js_string!("shell"), context).ok())
+        .map(|v| v.to_boolean())
+        .unwrap_or(false);
+    permissions::check_run(context, &command, shell)?;
+
+    // Build the command
+    let mut cmd = if shell {
+        if cfg!(target_os = "windows") {
+            let mut c = Command::new("cmd");
+            c.arg("/C").arg(&command);
+            for arg in &cmd_args {
+                c.arg(arg);
             }
+            c
         } else {
-            let mut c = Command::new(&command);
-            c.args(&cmd_args);
+            let mut c = Command::new("sh");
+            let full_cmd = if cmd_args.is_empty() {
+                command.clone()
+            } else {
+                format!("{} {}", command, cmd_args.join(" "))
+            };
+            c.arg("-c").arg(&full_cmd);
             c
-        };
-
-        // Set working directory
-        if let Some(dir) = cwd {
-            cmd.current_dir(dir);
         }
+    } else {
+        let mut c = Command::new(&command);
+        c.args(&cmd_args);
+        c
+    };
 
-        // Note: Custom env is handled in JavaScript wrapper for simplicity
+    // Set working directory
+    if let Some(dir) = cwd {
+        cmd.current_dir(dir);
+    }
 
-        // Capture output
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
+    // Note: Custom env is handled in JavaScript wrapper for simplicity
 
-        // Execute
-        let output = cmd.output().map_err(|e| {
-            JsNativeError::error().with_message(format!("Failed to spawn process: {}", e))
-        })?;
+    // Capture output
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
 
-        // Create result object
-        let result = ObjectInitializer::new(context)
-            .property(
-                js_string!("exitCode"),
-                JsValue::from(output.status.code().unwrap_or(-1)),
-                Default::default(),
-            )
-            .property(
-                js_string!("success"),
-                JsValue::from(output.status.success()),
-                Default::default(),
-            )
-            .build();
+    Ok(cmd)
+}
 
-        // Add stdout as Uint8Array
-        let stdout_array = JsUint8Array::from_iter(output.stdout.clone(), context)?;
-        result.set(js_string!("stdout"), stdout_array, false, context)?;
+/// Convert process output into `{ exitCode, success, stdout, stderr }` with byte arrays
+fn output_to_object(output: Output, context: &mut Context) -> JsResult<JsValue> {
+    // Create result object
+    let result = ObjectInitializer::new(context)
+        .property(
+            js_string!("exitCode"),
+            JsValue::from(output.status.code().unwrap_or(-1)),
+            Default::default(),
+        )
+        .property(
+            js_string!("success"),
+            JsValue::from(output.status.success()),
+            Default::default(),
+        )
+        .build();
+
+    // Add stdout as Uint8Array
+    let stdout_array = JsUint8Array::from_iter(output.stdout.clone(), context)?;
+    result.set(js_string!("stdout"), stdout_array, false, context)?;
+
+    // Add stderr as Uint8Array
+    let stderr_array = JsUint8Array::from_iter(output.stderr.clone(), context)?;
+    result.set(js_string!("stderr"), stderr_array, false, context)?;
+
+    Ok(JsValue::from(result))
+}
 
-        // Add stderr as Uint8Array
-        let stderr_array = JsUint8Array::from_iter(output.stderr.clone(), context)?;
-        result.set(js_string!("stderr"), stderr_array, false, context)?;
+fn spawn_error(e: std::io::Error) -> boa_engine::JsError {
+    JsNativeError::error()
+        .with_message(format!("Failed to spawn process: {}", e))
+        .into()
+}
 
-        Ok(JsValue::from(result))
+/// Send `signal` to process `pid`; returns whether it was delivered
+fn kill_process(pid: u32, signal: i32) -> bool {
+    #[cfg(unix)]
+    {
+        unsafe { libc::kill(pid as libc::pid_t, signal) == 0 }
+    }
+
+    #[cfg(windows)]
+    {
+        // Windows has no signals; every signal terminates the process
+        let _ = signal;
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, PROCESS_TERMINATE, TerminateProcess,
+        };
+
+        unsafe {
+            let handle = OpenProcess(PROCESS_TERMINATE, 0, pid);
+            if handle.is_null() {
+                return false;
+            }
+            let killed = TerminateProcess(handle, 1) != 0;
+            CloseHandle(handle);
+            killed
+        }
+    }
+}
+
+/// Register the spawn APIs
+pub fn register_spawn(context: &mut Context) -> JsResult<()> {
+    // __viper_spawn(command, args?, options?) - blocking
+    let spawn_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let output = build_command(args, context)?
+            .output()
+            .map_err(spawn_error)?;
+        output_to_object(output, context)
     });
     context.global_object().set(
         js_string!("__viper_spawn"),
@@ -136,6 +198,71 @@ pub fn register_spawn(context: &mut Context) -> JsResult<()> {
         context,
     )?;
 
+    // __viper_spawn_start(command, args, options, source_id) -> job id
+    // Runs the command on a helper thread and notifies the source when it exits
+    let spawn_start_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let mut cmd = build_command(args, context)?;
+        let source = args
+            .get(3)
+            .ok_or_else(|| JsNativeError::typ().with_message("Missing source ID"))?
+            .to_u32(context)?;
+        let notifier = event_loop::current(context)?.notifier(source);
+        let child = cmd.spawn().map_err(spawn_error)?;
+        let job_id = SPAWN_COUNTER.fetch_add(1, Ordering::SeqCst);
+        SPAWN_PIDS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(job_id, child.id());
+
+        std::thread::spawn(move || {
+            let output = child.wait_with_output();
+            if let Some(pids) = SPAWN_PIDS.lock().unwrap().as_mut() {
+                pids.remove(&job_id);
+            }
+            SPAWN_RESULTS
+                .lock()
+                .unwrap()
+                .get_or_insert_with(HashMap::new)
+                .insert(job_id, output);
+            notifier.notify();
+        });
+
+        Ok(JsValue::from(job_id))
+    });
+    context.register_global_callable(js_string!("__viper_spawn_start"), 4, spawn_start_fn)?;
+
+    // __viper_spawn_result(job_id) -> result object, once the job's source fired
+    let spawn_result_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let job_id = args
+            .first()
+            .ok_or_else(|| JsNativeError::typ().with_message("Missing job ID"))?
+            .to_u32(context)?;
+        let output = SPAWN_RESULTS
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(|results| results.remove(&job_id))
+            .ok_or_else(|| JsNativeError::error().with_message("Spawn job has not finished"))?;
+        output_to_object(output.map_err(spawn_error)?, context)
+    });
+    context.register_global_callable(js_string!("__viper_spawn_result"), 1, spawn_result_fn)?;
+
+    // __viper_spawn_kill(job_id, signal) -> whether the job was still running
+    let spawn_kill_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let job_id = args.get_or_undefined(0).to_u32(context)?;
+        let signal = args.get_or_undefined(1).to_i32(context)?;
+        let pid = SPAWN_PIDS
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|pids| pids.get(&job_id).copied());
+        Ok(JsValue::from(
+            pid.is_some_and(|pid| kill_process(pid, signal)),
+        ))
+    });
+    context.register_global_callable(js_string!("__viper_spawn_kill"), 2, spawn_kill_fn)?;
+
     // Viper.exec(command) - Simple shell execution
     let exec_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let command = args
@@ -143,6 +270,7 @@ pub fn register_spawn(context: &mut Context) -> JsResult<()> {
             .ok_or_else(|| JsNativeError::typ().with_message("exec requires a command"))?
             .to_string(context)?
             .to_std_string_escaped();
+        permissions::check_run(context, &command, true)?;
 
         let output = if cfg!(target_os = "windows") {
             Command::new("cmd").arg("/C").arg(&command).output()
@@ -204,9 +332,47 @@ pub fn register_spawn(context: &mut Context) -> JsResult<()> {
 
         // Add spawn to Viper namespace
         {
+            const abortError = (signal) => {
+                const err = new Error('The operation was aborted', { cause: signal.reason });
+                err.name = 'AbortError';
+                err.code = 'ABORT_ERR';
+                return err;
+            };
+
+            // Run a command off-thread; resolves with the raw result object.
+            // Aborting options.signal kills the child and rejects.
+            const spawnAsync = (command, args, options) => new Promise((resolve, reject) => {
+                const signal = options.signal;
+                if (signal?.aborted) {
+                    reject(abortError(signal));
+                    return;
+                }
+                let job;
+                const onAbort = () => {
+                    __viper_spawn_kill(job, 15);
+                    reject(abortError(signal));
+                };
+                const source = __viper_loop_source(() => {
+                    __viper_loop_close(source);
+                    signal?.removeEventListener('abort', onAbort);
+                    try {
+                        resolve(__viper_spawn_result(job));
+                    } catch (e) {
+                        reject(e);
+                    }
+                });
+                try {
+                    job = __viper_spawn_start(command, args, options, source);
+                    signal?.addEventListener('abort', onAbort, { once: true });
+                } catch (e) {
+                    __viper_loop_close(source);
+                    reject(e);
+                }
+            });
+
             // Viper.spawn(command, args?, options?) - Returns a Promise
             Viper.spawn = async (command, args, options) => {
-                const result = __viper_spawn(command, args || [], options || {});
+                const result = await spawnAsync(command, args || [], options || {});
 
                 // Decode stdout/stderr to strings
                 const decoder = new TextDecoder();
@@ -225,7 +391,7 @@ pub fn register_spawn(context: &mut Context) -> JsResult<()> {
 
             // Viper.exec(command) - Simple shell execution returning strings (async)
             Viper.exec = async (command) => {
-                const result = __viper_exec(command);
+                const result = await Viper.spawn(command, [], { shell: true });
                 return {
                     exitCode: result.exitCode,
                     success: result.success,
@@ -0,0 +1,203 @@
+//! Runtime startup: lazily initialized builtins and a timing breakdown
+//!
+//! Boa can neither snapshot a context nor serialize compiled bytecode, and a
+//! parsed script belongs to the context whose interner it used, so every
+//! runtime (and every Worker) has to evaluate its polyfills itself. What can
+//! be saved is work for modules a program never touches: heavy builtins are
+//! installed as accessors on the global object that run their registration
+//! the first time `require`, `import` or plain code reads them.
+//!
+//! `Script::evaluate` runs on the caller's environment stack, so a polyfill
+//! evaluated straight from a getter that module or function code triggered
+//! would resolve its bindings against the wrong scopes. Registrations run
+//! through a function created at startup, whose only environment is the
+//! global one.
+
+use boa_engine::{
+    Context, JsData, JsNativeError, JsResult, JsValue, NativeFunction, Source, js_string,
+    object::builtins::JsFunction, property::PropertyDescriptor,
+};
+use boa_gc::{Finalize, Gc, GcRefCell, Trace};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// How long each step of `Runtime::with_config` took
+#[derive(Debug, Clone)]
+pub struct StartupTiming {
+    phases: Vec<(&'static str, Duration)>,
+    last: Instant,
+}
+
+impl StartupTiming {
+    pub fn new() -> Self {
+        Self {
+            phases: Vec::new(),
+            last: Instant::now(),
+        }
+    }
+
+    /// Record the time since the previous mark as `phase`
+    pub fn mark(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.last));
+        self.last = now;
+    }
+
+    /// Steps in the order they ran
+    pub fn phases(&self) -> &[(&'static str, Duration)] {
+        &self.phases
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, took)| *took).sum()
+    }
+}
+
+impl Default for StartupTiming {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for StartupTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .phases
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        for (name, took) in &self.phases {
+            writeln!(f, "{:<width$}  {:>8.3} ms", name, millis(*took))?;
+        }
+        write!(f, "{:<width$}  {:>8.3} ms", "total", millis(self.total()))
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Define `globalThis[name]` so that `init` runs on first access
+///
+/// `init` must assign the global itself, as the eager `register_*` functions
+/// do. The property stays an accessor over a stored value: Boa 0.21 caches a
+/// property read under the shape the object has after the getter ran, so a
+/// getter that swapped itself for a data property would leave that call site
+/// calling the module object on its next read.
+pub fn register_lazy(
+    context: &mut Context,
+    name: &'static str,
+    init: fn(&mut Context) -> JsResult<()>,
+) -> JsResult<()> {
+    let trampoline = trampoline(context)?;
+    let value = Gc::new(GcRefCell::new(None::<JsValue>));
+    let getter = NativeFunction::from_copy_closure_with_captures(
+        move |_this, _args, (trampoline, value), context| {
+            if let Some(value) = value.borrow().clone() {
+                return Ok(value);
+            }
+            // Reads from within `init` see `undefined` rather than recursing
+            *value.borrow_mut() = Some(JsValue::undefined());
+            let realm = context.realm().clone();
+            let init = NativeFunction::from_copy_closure(move |_this, _args, context| {
+                init(context)?;
+                Ok(JsValue::undefined())
+            })
+            .to_js_function(&realm);
+            trampoline
+                .0
+                .call(&JsValue::undefined(), &[init.into()], context)?;
+            Ok(value.borrow().clone().unwrap_or_default())
+        },
+        (trampoline, value.clone()),
+    );
+    let setter = NativeFunction::from_copy_closure_with_captures(
+        move |_this, args, value, _context| {
+            *value.borrow_mut() = Some(args.first().cloned().unwrap_or_default());
+            Ok(JsValue::undefined())
+        },
+        value,
+    );
+
+    let realm = context.realm().clone();
+    context.global_object().define_property_or_throw(
+        js_string!(name),
+        PropertyDescriptor::builder()
+            .get(getter.to_js_function(&realm))
+            .set(setter.to_js_function(&realm))
+            .enumerable(false)
+            .configurable(true),
+        context,
+    )?;
+    Ok(())
+}
+
+/// `(init) => init()`, evaluated while only the global environment is active
+#[derive(Clone, Trace, Finalize, JsData)]
+struct Trampoline(JsFunction);
+
+fn trampoline(context: &mut Context) -> JsResult<Trampoline> {
+    if let Some(trampoline) = context.realm().host_defined().get::<Trampoline>() {
+        return Ok(trampoline.clone());
+    }
+    let function = context
+        .eval(Source::from_bytes("(init) => init()"))?
+        .as_object()
+        .and_then(|object| JsFunction::from_object(object.clone()))
+        .ok_or_else(|| JsNativeError::typ().with_message("the trampoline is not a function"))?;
+    let trampoline = Trampoline(function);
+    context
+        .realm()
+        .host_defined_mut()
+        .insert(trampoline.clone());
+    Ok(trampoline)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_startup_timing() {
+        let runtime = Runtime::new().unwrap();
+        assert!(!runtime.startup_timing().phases().is_empty());
+    }
+
+    #[test]
+    fn test_lazy_builtins() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = "typeof require('zlib').gzipSync + ' ' + typeof crypto.randomUUID";
+        let result = runtime.eval(code, "test.js").unwrap();
+        assert_eq!(
+            result.as_string().unwrap().to_std_string_escaped(),
+            "function function"
+        );
+    }
+
+    #[test]
+    fn test_lazy_builtin_read_twice() {
+        let mut runtime = Runtime::new().unwrap();
+        // The same property read first initializes `os`, then finds it
+        let code = r#"
+            const platform = () => globalThis.os.platform();
+            platform() === platform()
+        "#;
+        let result = runtime.eval(code, "test.js").unwrap();
+        assert_eq!(result.as_boolean(), Some(true));
+    }
+
+    #[test]
+    fn test_lazy_builtin_assignment() {
+        let mut runtime = Runtime::new().unwrap();
+        // Scripts may replace a builtin before anything initialized it
+        let code = "globalThis.os = 'mine'; os + ' ' + globalThis.os";
+        let result = runtime.eval(code, "test.js").unwrap();
+        assert_eq!(
+            result.as_string().unwrap().to_std_string_escaped(),
+            "mine mine"
+        );
+    }
+}
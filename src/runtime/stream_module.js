@@ -115,6 +115,7 @@
         ended: false,
         endEmitted: false,
         reading: false,
+        flowScheduled: false,
         paused: true,
         pipes: [],
         decoder: null,
@@ -188,6 +189,8 @@
           this._readableState.endEmitted = true;
           this.readableEnded = true;
           queueMicrotask(() => this.emit("end"));
+        } else if (this._readableState.flowing) {
+          this._scheduleFlow();
         }
         return false;
       }
@@ -205,7 +208,11 @@
 
       this.emit("readable");
 
-      // Don't emit data here - _flow() will handle it when in flowing mode
+      // Don't emit data here - _flow() will handle it when in flowing mode,
+      // including for chunks pushed asynchronously after _read() returned
+      if (this._readableState.flowing) {
+        this._scheduleFlow();
+      }
 
       return this.readableLength < this.readableHighWaterMark;
     }
@@ -245,14 +252,18 @@
       return this;
     }
 
+    _scheduleFlow() {
+      if (this._readableState.flowScheduled) return;
+      this._readableState.flowScheduled = true;
+      queueMicrotask(() => {
+        this._readableState.flowScheduled = false;
+        this._flow();
+      });
+    }
+
     _flow() {
       if (!this._readableState.flowing) return;
 
-      // If buffer is empty, call _read to get more data
-      if (this._buffer.length === 0 && !this[kEnded]) {
-        this._read(this.readableHighWaterMark);
-      }
-
       // Emit buffered data
       while (this._readableState.flowing && this._buffer.length > 0) {
         const chunk = this._buffer.shift();
@@ -273,6 +284,14 @@
         this._readableState.endEmitted = true;
         this.readableEnded = true;
         this.emit("end");
+      } else if (
+        this._readableState.flowing &&
+        this._buffer.length === 0 &&
+        !this[kEnded]
+      ) {
+        // Buffer drained - call _read to get more data; what it pushes
+        // schedules the next _flow()
+        this._read(this.readableHighWaterMark);
       }
     }
 
@@ -450,6 +469,77 @@
 
       return readable;
     }
+
+    // Wrap this stream in a WHATWG ReadableStream
+    static toWeb(readable, options = {}) {
+      const objectMode = readable.readableObjectMode;
+      let done = false;
+      return new ReadableStream(
+        {
+          start(controller) {
+            readable.on("data", (chunk) => {
+              if (done) return;
+              controller.enqueue(objectMode ? chunk : new Uint8Array(chunk));
+              if (controller.desiredSize <= 0) readable.pause();
+            });
+            readable.once("end", () => {
+              if (done) return;
+              done = true;
+              controller.close();
+            });
+            readable.once("error", (err) => {
+              if (done) return;
+              done = true;
+              controller.error(err);
+            });
+          },
+          pull() {
+            readable.resume();
+          },
+          cancel(reason) {
+            done = true;
+            readable.destroy(reason);
+          },
+        },
+        options.strategy,
+      );
+    }
+
+    // Create a Readable that reads from a WHATWG ReadableStream
+    static fromWeb(readableStream, options = {}) {
+      const reader = readableStream.getReader();
+      let reading = false;
+      const readable = new Readable({
+        ...options,
+        read() {
+          if (reading) return;
+          reading = true;
+          reader.read().then(
+            ({ value, done }) => {
+              reading = false;
+              if (done) {
+                readable.push(null);
+              } else if (readable.readableObjectMode || typeof value === "string") {
+                readable.push(value);
+              } else {
+                readable.push(Buffer.from(value));
+              }
+            },
+            (err) => {
+              reading = false;
+              readable.destroy(err);
+            },
+          );
+        },
+        destroy(err, callback) {
+          reader.cancel(err).then(
+            () => callback(err),
+            () => callback(err),
+          );
+        },
+      });
+      return readable;
+    }
   }
 
   /**
@@ -566,13 +656,13 @@
         return this;
       }
 
-      this._writableState.ended = true;
-      this.writableEnded = true;
-
       if (chunk !== null && chunk !== undefined) {
         this.write(chunk, encoding);
       }
 
+      this._writableState.ended = true;
+      this.writableEnded = true;
+
       if (callback) {
         this.once("finish", callback);
       }
@@ -657,6 +747,43 @@
     get destroyed() {
       return this[kDestroyed];
     }
+
+    // Wrap this stream in a WHATWG WritableStream
+    static toWeb(writable) {
+      return new WritableStream({
+        write(chunk) {
+          return new Promise((resolve, reject) => {
+            writable.write(chunk, (err) => (err ? reject(err) : resolve()));
+          });
+        },
+        close() {
+          return new Promise((resolve) => writable.end(resolve));
+        },
+        abort(reason) {
+          writable.destroy(reason);
+        },
+      });
+    }
+
+    // Create a Writable that writes to a WHATWG WritableStream
+    static fromWeb(writableStream, options = {}) {
+      const writer = writableStream.getWriter();
+      return new Writable({
+        ...options,
+        write(chunk, encoding, callback) {
+          writer.write(chunk).then(() => callback(), callback);
+        },
+        final(callback) {
+          writer.close().then(() => callback(), callback);
+        },
+        destroy(err, callback) {
+          writer.abort(err).then(
+            () => callback(err),
+            () => callback(err),
+          );
+        },
+      });
+    }
   }
 
   /**
@@ -966,13 +1093,19 @@
    * addAbortSignal - Add abort signal support to stream
    */
   function addAbortSignal(signal, stream) {
+    const abortError = () => {
+      const err = new Error("The operation was aborted", { cause: signal.reason });
+      err.name = "AbortError";
+      err.code = "ABORT_ERR";
+      return err;
+    };
     if (signal.aborted) {
-      stream.destroy(new Error("AbortError"));
+      stream.destroy(abortError());
     } else {
       signal.addEventListener(
         "abort",
         () => {
-          stream.destroy(new Error("AbortError"));
+          stream.destroy(abortError());
         },
         { once: true },
       );
@@ -0,0 +1,1443 @@
+//! TLS - the sessions behind `tls` and `https`
+//!
+//! A session never touches the network itself. Its `TLSSocket` feeds it the
+//! ciphertext the transport (a `net.Socket`, or any duplex) received and
+//! writes out whatever it produces, so readiness stays with the transport on
+//! the event loop and a plain connection can be upgraded in place, the way
+//! STARTTLS protocols do.
+//!
+//! Server sessions stop after the ClientHello: `tls_module.js` picks the
+//! secure context (SNI) and protocol (ALPN) for it, then the handshake goes
+//! on with `__viper_tls_accept`. Certificates are verified against the `ca`
+//! option or the bundled Mozilla roots plus `NODE_EXTRA_CA_CERTS`; with
+//! `rejectUnauthorized: false` a failure is recorded as the socket's
+//! `authorizationError` instead of aborting the handshake.
+//!
+//! The natives, all taking and returning plain values or handles:
+//! - `__viper_tls_context_new(options)` -> secure context
+//! - `__viper_tls_client_new(options)` / `__viper_tls_server_new()` -> session
+//! - `__viper_tls_feed`, `__viper_tls_accept`, `__viper_tls_write`,
+//!   `__viper_tls_end` -> `{ output, chunks, secure, eof, hello, error, code }`
+//! - `__viper_tls_info`, `__viper_tls_certificates`,
+//!   `__viper_tls_export_keying_material`, `__viper_tls_destroy`
+
+use boa_engine::{
+    Context, JsArgs, JsData, JsError, JsNativeError, JsObject, JsResult, JsValue, NativeFunction,
+    Source, js_string,
+    object::ObjectInitializer,
+    object::builtins::{JsArray, JsUint8Array},
+    property::Attribute,
+};
+use boa_gc::{Finalize, Trace};
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::server::{Accepted, Acceptor, WebPkiClientVerifier};
+use rustls::{
+    ClientConfig, ClientConnection, Connection, DigitallySignedStruct, DistinguishedName,
+    ProtocolVersion, RootCertStore, ServerConfig, SignatureScheme, SupportedProtocolVersion,
+};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::io::{Read, Write};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, OnceLock};
+use x509_parser::prelude::{ASN1Time, GeneralName, X509Name};
+
+use super::net;
+
+/// Largest plaintext read at once (one TLS record)
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Key, certificate chain, trusted CAs and protocol range of a secure context
+struct SecureContext {
+    chain: Vec<CertificateDer<'static>>,
+    key: Option<PrivateKeyDer<'static>>,
+    /// Trust anchors from `ca`; the bundled roots when absent
+    roots: Option<Arc<RootCertStore>>,
+    /// The `ca` certificates themselves, which complete a peer's chain
+    ca: Vec<CertificateDer<'static>>,
+    versions: Vec<&'static SupportedProtocolVersion>,
+}
+
+impl SecureContext {
+    /// Build from PEM strings and `minVersion`/`maxVersion` names
+    fn new(
+        keys: &[String],
+        certs: &[String],
+        ca: Option<&[String]>,
+        min_version: &str,
+        max_version: &str,
+    ) -> Result<Self, String> {
+        let mut chain = Vec::new();
+        for pem in certs {
+            let before = chain.len();
+            for cert in CertificateDer::pem_slice_iter(pem.as_bytes()) {
+                chain.push(cert.map_err(|e| format!("Invalid certificate: {}", e))?);
+            }
+            if chain.len() == before {
+                return Err("Invalid certificate: no certificates found".to_string());
+            }
+        }
+        let key = match keys.first() {
+            Some(pem) => Some(
+                PrivateKeyDer::from_pem_slice(pem.as_bytes())
+                    .map_err(|e| format!("Invalid private key: {}", e))?,
+            ),
+            None => None,
+        };
+        let mut authorities = Vec::new();
+        let roots = match ca {
+            Some(ca) => {
+                let mut store = RootCertStore::empty();
+                for pem in ca {
+                    let before = authorities.len();
+                    for cert in CertificateDer::pem_slice_iter(pem.as_bytes()) {
+                        let cert = cert.map_err(|e| format!("Invalid CA certificate: {}", e))?;
+                        authorities.push(cert.clone());
+                        store
+                            .add(cert)
+                            .map_err(|e| format!("Invalid CA certificate: {}", e))?;
+                    }
+                    if authorities.len() == before {
+                        return Err("Invalid CA certificate: no certificates found".to_string());
+                    }
+                }
+                Some(Arc::new(store))
+            }
+            None => None,
+        };
+
+        // rustls speaks TLS 1.2 and 1.3; older minimums mean 1.2
+        let rank = |name: &str| match name {
+            "TLSv1.3" => 3,
+            _ => 2,
+        };
+        let versions: Vec<_> = [(2, &rustls::version::TLS12), (3, &rustls::version::TLS13)]
+            .into_iter()
+            .filter(|(rank_of, _)| *rank_of >= rank(min_version) && *rank_of <= rank(max_version))
+            .map(|(_, version)| version)
+            .collect();
+        if versions.is_empty() {
+            return Err(format!(
+                "No TLS version between {} and {}",
+                min_version, max_version
+            ));
+        }
+        Ok(Self {
+            chain,
+            key,
+            roots,
+            ca: authorities,
+            versions,
+        })
+    }
+
+    fn roots(&self) -> Arc<RootCertStore> {
+        self.roots.clone().unwrap_or_else(default_roots)
+    }
+
+    fn client_config(
+        &self,
+        reject_unauthorized: bool,
+        alpn: Vec<Vec<u8>>,
+        verdict: Arc<Verdict>,
+    ) -> Result<ClientConfig, rustls::Error> {
+        let inner = WebPkiServerVerifier::builder_with_provider(self.roots(), provider())
+            .build()
+            .map_err(|e| rustls::Error::General(e.to_string()))?;
+        let verifier = Arc::new(ServerVerifier {
+            inner,
+            reject_unauthorized,
+            verdict,
+        });
+        let builder = ClientConfig::builder_with_provider(provider())
+            .with_protocol_versions(&self.versions)?
+            .dangerous()
+            .with_custom_certificate_verifier(verifier);
+        let mut config = match &self.key {
+            Some(key) if !self.chain.is_empty() => {
+                builder.with_client_auth_cert(self.chain.clone(), key.clone_key())?
+            }
+            _ => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = alpn;
+        Ok(config)
+    }
+
+    fn server_config(
+        &self,
+        request_cert: bool,
+        reject_unauthorized: bool,
+        alpn: Vec<Vec<u8>>,
+        verdict: Arc<Verdict>,
+    ) -> Result<ServerConfig, rustls::Error> {
+        let key = self
+            .key
+            .as_ref()
+            .ok_or_else(|| rustls::Error::General("Missing private key".to_string()))?;
+        let builder = ServerConfig::builder_with_provider(provider())
+            .with_protocol_versions(&self.versions)?;
+        let builder = if request_cert {
+            let inner = WebPkiClientVerifier::builder_with_provider(self.roots(), provider())
+                .allow_unauthenticated()
+                .build()
+                .map_err(|e| rustls::Error::General(e.to_string()))?;
+            builder.with_client_cert_verifier(Arc::new(ClientVerifier {
+                inner,
+                reject_unauthorized,
+                verdict,
+            }))
+        } else {
+            builder.with_no_client_auth()
+        };
+        let mut config = builder.with_single_cert(self.chain.clone(), key.clone_key())?;
+        config.alpn_protocols = alpn;
+        Ok(config)
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    static PROVIDER: OnceLock<Arc<CryptoProvider>> = OnceLock::new();
+    PROVIDER
+        .get_or_init(|| Arc::new(rustls::crypto::ring::default_provider()))
+        .clone()
+}
+
+/// The bundled Mozilla roots plus `NODE_EXTRA_CA_CERTS`
+fn default_roots() -> Arc<RootCertStore> {
+    static ROOTS: OnceLock<Arc<RootCertStore>> = OnceLock::new();
+    ROOTS
+        .get_or_init(|| {
+            let mut store = RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            if let Some(pem) =
+                std::env::var_os("NODE_EXTRA_CA_CERTS").and_then(|path| std::fs::read(path).ok())
+            {
+                for cert in CertificateDer::pem_slice_iter(&pem).flatten() {
+                    let _ = store.add(cert);
+                }
+            }
+            Arc::new(store)
+        })
+        .clone()
+}
+
+/// Why the peer's certificate failed verification, when that was allowed
+#[derive(Debug, Default)]
+struct Verdict(Mutex<Option<rustls::Error>>);
+
+impl Verdict {
+    fn error(&self) -> Option<rustls::Error> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Verifies server certificates, recording instead of failing the
+/// handshake when `rejectUnauthorized` is false
+#[derive(Debug)]
+struct ServerVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    reject_unauthorized: bool,
+    verdict: Arc<Verdict>,
+}
+
+impl ServerCertVerifier for ServerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        ) {
+            Err(e) if !self.reject_unauthorized => {
+                *self.verdict.0.lock().unwrap() = Some(e);
+                Ok(ServerCertVerified::assertion())
+            }
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Verifies client certificates for `requestCert` servers; they are only
+/// required when `rejectUnauthorized` is true
+#[derive(Debug)]
+struct ClientVerifier {
+    inner: Arc<dyn ClientCertVerifier>,
+    reject_unauthorized: bool,
+    verdict: Arc<Verdict>,
+}
+
+impl ClientCertVerifier for ClientVerifier {
+    fn client_auth_mandatory(&self) -> bool {
+        self.reject_unauthorized
+    }
+
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        self.inner.root_hint_subjects()
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        match self
+            .inner
+            .verify_client_cert(end_entity, intermediates, now)
+        {
+            Err(e) if !self.reject_unauthorized => {
+                *self.verdict.0.lock().unwrap() = Some(e);
+                Ok(ClientCertVerified::assertion())
+            }
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+enum State {
+    /// Server side, reading the ClientHello
+    Accepting(Acceptor),
+    /// Server side, waiting for JavaScript to pick a context for the hello
+    Hello(Accepted),
+    Open(Connection),
+    Closed,
+}
+
+/// One end of a TLS connection, fed and drained by its TLSSocket
+struct Session {
+    state: State,
+    /// Ciphertext that arrived while the ClientHello was waiting
+    backlog: Vec<u8>,
+    verdict: Arc<Verdict>,
+    /// Whether the finished handshake has been reported
+    secure: bool,
+    /// Our own certificate chain, for `getCertificate()`
+    chain: Vec<CertificateDer<'static>>,
+    /// Trusted CA certificates the peer may have left out of its chain
+    issuers: Vec<CertificateDer<'static>>,
+    servername: Option<String>,
+}
+
+/// What feeding, writing to or ending a session produced
+#[derive(Default)]
+struct Progress {
+    /// Ciphertext for the transport
+    output: Vec<u8>,
+    /// Plaintext for the reader
+    chunks: Vec<Vec<u8>>,
+    /// The handshake finished just now
+    secure: bool,
+    /// The peer sent close_notify
+    eof: bool,
+    /// A server's ClientHello: server name and offered ALPN protocols
+    hello: Option<(Option<String>, Vec<String>)>,
+    error: Option<rustls::Error>,
+}
+
+impl Session {
+    fn client(
+        context: &SecureContext,
+        servername: &str,
+        reject_unauthorized: bool,
+        alpn: Vec<Vec<u8>>,
+    ) -> Result<Self, rustls::Error> {
+        let verdict = Arc::new(Verdict::default());
+        let config = context.client_config(reject_unauthorized, alpn, verdict.clone())?;
+        let name = ServerName::try_from(servername.to_string())
+            .map_err(|_| rustls::Error::General(format!("Invalid server name: {}", servername)))?;
+        let connection = ClientConnection::new(Arc::new(config), name)?;
+        Ok(Self {
+            state: State::Open(connection.into()),
+            backlog: Vec::new(),
+            verdict,
+            secure: false,
+            chain: context.chain.clone(),
+            issuers: context.ca.clone(),
+            servername: Some(servername.to_string()),
+        })
+    }
+
+    fn server() -> Self {
+        Self {
+            state: State::Accepting(Acceptor::default()),
+            backlog: Vec::new(),
+            verdict: Arc::new(Verdict::default()),
+            secure: false,
+            chain: Vec::new(),
+            issuers: Vec::new(),
+            servername: None,
+        }
+    }
+
+    /// Take in ciphertext from the transport
+    fn feed(&mut self, mut input: &[u8]) -> Progress {
+        let mut progress = Progress::default();
+        match std::mem::replace(&mut self.state, State::Closed) {
+            State::Accepting(mut acceptor) => {
+                while !input.is_empty() {
+                    if let Err(e) = acceptor.read_tls(&mut input) {
+                        progress.error = Some(rustls::Error::General(e.to_string()));
+                        return progress;
+                    }
+                }
+                match acceptor.accept() {
+                    Ok(None) => self.state = State::Accepting(acceptor),
+                    Ok(Some(accepted)) => {
+                        let hello = accepted.client_hello();
+                        let alpn = hello
+                            .alpn()
+                            .map(|protocols| {
+                                protocols
+                                    .map(|protocol| String::from_utf8_lossy(protocol).into_owned())
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        self.servername = hello.server_name().map(str::to_string);
+                        progress.hello = Some((self.servername.clone(), alpn));
+                        self.state = State::Hello(accepted);
+                    }
+                    Err((e, mut alert)) => {
+                        let _ = alert.write_all(&mut progress.output);
+                        progress.error = Some(e);
+                    }
+                }
+            }
+            State::Hello(accepted) => {
+                self.backlog.extend_from_slice(input);
+                self.state = State::Hello(accepted);
+            }
+            State::Open(mut connection) => {
+                while !input.is_empty() && progress.error.is_none() {
+                    if let Err(e) = connection.read_tls(&mut input) {
+                        progress.error = Some(rustls::Error::General(e.to_string()));
+                    } else if let Err(e) = connection.process_new_packets() {
+                        progress.error = Some(e);
+                    } else {
+                        Self::read_plaintext(&mut connection, &mut progress);
+                    }
+                }
+                if progress.error.is_none() {
+                    if let Err(e) = connection.process_new_packets() {
+                        progress.error = Some(e);
+                    }
+                    Self::read_plaintext(&mut connection, &mut progress);
+                }
+                self.drain(&mut connection, &mut progress);
+                if progress.error.is_none() {
+                    self.state = State::Open(connection);
+                }
+            }
+            State::Closed => {}
+        }
+        progress
+    }
+
+    /// Continue a server handshake with the context picked for the ClientHello
+    fn accept(
+        &mut self,
+        context: &SecureContext,
+        request_cert: bool,
+        reject_unauthorized: bool,
+        alpn: Vec<Vec<u8>>,
+    ) -> Progress {
+        let State::Hello(accepted) = std::mem::replace(&mut self.state, State::Closed) else {
+            return Progress {
+                error: Some(rustls::Error::General(
+                    "No ClientHello to accept".to_string(),
+                )),
+                ..Progress::default()
+            };
+        };
+        let config = match context.server_config(
+            request_cert,
+            reject_unauthorized,
+            alpn,
+            self.verdict.clone(),
+        ) {
+            Ok(config) => config,
+            Err(e) => {
+                return Progress {
+                    error: Some(e),
+                    ..Progress::default()
+                };
+            }
+        };
+        match accepted.into_connection(Arc::new(config)) {
+            Ok(connection) => {
+                self.chain = context.chain.clone();
+                self.issuers = context.ca.clone();
+                self.state = State::Open(connection.into());
+                let backlog = std::mem::take(&mut self.backlog);
+                self.feed(&backlog)
+            }
+            Err((e, mut alert)) => {
+                let mut progress = Progress::default();
+                let _ = alert.write_all(&mut progress.output);
+                progress.error = Some(e);
+                progress
+            }
+        }
+    }
+
+    /// Encrypt plaintext for the transport
+    fn write(&mut self, mut data: &[u8]) -> Progress {
+        let mut progress = Progress::default();
+        let State::Open(connection) = &mut self.state else {
+            progress.error = Some(rustls::Error::General("TLS session is closed".to_string()));
+            return progress;
+        };
+        while !data.is_empty() {
+            match connection.writer().write(data) {
+                Ok(0) => break,
+                Ok(n) => data = &data[n..],
+                Err(e) => {
+                    progress.error = Some(rustls::Error::General(e.to_string()));
+                    break;
+                }
+            }
+            while connection.wants_write() {
+                if connection.write_tls(&mut progress.output).is_err() {
+                    break;
+                }
+            }
+        }
+        progress
+    }
+
+    /// Send close_notify
+    fn end(&mut self) -> Progress {
+        let mut progress = Progress::default();
+        if let State::Open(connection) = &mut self.state {
+            connection.send_close_notify();
+            while connection.wants_write() {
+                if connection.write_tls(&mut progress.output).is_err() {
+                    break;
+                }
+            }
+        }
+        progress
+    }
+
+    fn read_plaintext(connection: &mut Connection, progress: &mut Progress) {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            match connection.reader().read(&mut buf) {
+                Ok(0) => {
+                    progress.eof = true;
+                    break;
+                }
+                Ok(n) => progress.chunks.push(buf[..n].to_vec()),
+                // WouldBlock: nothing more until the next record
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Report a finished handshake and collect the ciphertext to send
+    fn drain(&mut self, connection: &mut Connection, progress: &mut Progress) {
+        if !self.secure && !connection.is_handshaking() && progress.error.is_none() {
+            self.secure = true;
+            progress.secure = true;
+        }
+        while connection.wants_write() {
+            if connection.write_tls(&mut progress.output).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn connection(&self) -> Option<&Connection> {
+        match &self.state {
+            State::Open(connection) => Some(connection),
+            _ => None,
+        }
+    }
+}
+
+/// Node's code for a TLS failure
+fn error_code(error: &rustls::Error) -> &'static str {
+    use rustls::{AlertDescription, CertificateError, Error};
+    match error {
+        Error::InvalidCertificate(certificate) => match certificate {
+            CertificateError::UnknownIssuer => "UNABLE_TO_GET_ISSUER_CERT_LOCALLY",
+            CertificateError::Expired | CertificateError::ExpiredContext { .. } => {
+                "CERT_HAS_EXPIRED"
+            }
+            CertificateError::NotValidYet | CertificateError::NotValidYetContext { .. } => {
+                "CERT_NOT_YET_VALID"
+            }
+            CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. } => {
+                "ERR_TLS_CERT_ALTNAME_INVALID"
+            }
+            CertificateError::Revoked => "CERT_REVOKED",
+            CertificateError::BadSignature => "CERT_SIGNATURE_FAILURE",
+            _ => "UNABLE_TO_VERIFY_LEAF_SIGNATURE",
+        },
+        Error::NoCertificatesPresented => "ERR_SSL_PEER_DID_NOT_RETURN_A_CERTIFICATE",
+        Error::NoApplicationProtocol
+        | Error::AlertReceived(AlertDescription::NoApplicationProtocol) => {
+            "ERR_SSL_TLSV1_ALERT_NO_APPLICATION_PROTOCOL"
+        }
+        Error::AlertReceived(AlertDescription::UnknownCA) => "ERR_SSL_TLSV1_ALERT_UNKNOWN_CA",
+        Error::AlertReceived(AlertDescription::BadCertificate) => {
+            "ERR_SSL_SSLV3_ALERT_BAD_CERTIFICATE"
+        }
+        Error::AlertReceived(AlertDescription::CertificateRequired) => {
+            "ERR_SSL_TLSV13_ALERT_CERTIFICATE_REQUIRED"
+        }
+        Error::AlertReceived(AlertDescription::HandshakeFailure) => {
+            "ERR_SSL_SSLV3_ALERT_HANDSHAKE_FAILURE"
+        }
+        Error::AlertReceived(AlertDescription::ProtocolVersion) => {
+            "ERR_SSL_TLSV1_ALERT_PROTOCOL_VERSION"
+        }
+        Error::InvalidMessage(_) => "ERR_SSL_WRONG_VERSION_NUMBER",
+        _ => "ERR_SSL_PROTOCOL_ERROR",
+    }
+}
+
+/// Fields of a certificate, as `getPeerCertificate()` shows them
+struct CertificateInfo {
+    subject: Vec<(&'static str, String)>,
+    issuer: Vec<(&'static str, String)>,
+    subjectaltname: Option<String>,
+    ca: bool,
+    valid_from: String,
+    valid_to: String,
+    serial_number: String,
+    fingerprint: String,
+    fingerprint256: String,
+    fingerprint512: String,
+    raw: Vec<u8>,
+}
+
+/// Append the certificates from `issuers` that signed the end of `chain`, up
+/// to a self-signed one, as OpenSSL's verified chain would have them
+fn complete_chain(chain: &mut Vec<CertificateDer<'static>>, issuers: &[CertificateDer<'static>]) {
+    let names = |der: &[u8]| {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+        Some((
+            cert.subject().as_raw().to_vec(),
+            cert.issuer().as_raw().to_vec(),
+        ))
+    };
+    while let Some((subject, issuer)) = chain.last().and_then(|last| names(last)) {
+        if subject == issuer {
+            break;
+        }
+        let next = issuers.iter().find(|candidate| {
+            !chain.contains(candidate)
+                && names(candidate).is_some_and(|(subject, _)| subject == issuer)
+        });
+        match next {
+            Some(cert) => chain.push(cert.clone()),
+            None => break,
+        }
+    }
+}
+
+impl CertificateInfo {
+    fn parse(der: &[u8]) -> Option<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+        let subjectaltname = cert
+            .subject_alternative_name()
+            .ok()
+            .flatten()
+            .map(|extension| {
+                extension
+                    .value
+                    .general_names
+                    .iter()
+                    .filter_map(|name| match name {
+                        GeneralName::DNSName(dns) => Some(format!("DNS:{}", dns)),
+                        GeneralName::RFC822Name(email) => Some(format!("email:{}", email)),
+                        GeneralName::URI(uri) => Some(format!("URI:{}", uri)),
+                        GeneralName::IPAddress(ip) => {
+                            ip_address(ip).map(|ip| format!("IP Address:{}", ip))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+        Some(Self {
+            subject: name_fields(cert.subject()),
+            issuer: name_fields(cert.issuer()),
+            subjectaltname,
+            ca: cert.is_ca(),
+            valid_from: format_time(&cert.validity().not_before),
+            valid_to: format_time(&cert.validity().not_after),
+            serial_number: hex::encode_upper(cert.raw_serial()),
+            fingerprint: fingerprint(&Sha1::digest(der)),
+            fingerprint256: fingerprint(&Sha256::digest(der)),
+            fingerprint512: fingerprint(&Sha512::digest(der)),
+            raw: der.to_vec(),
+        })
+    }
+}
+
+fn name_fields(name: &X509Name) -> Vec<(&'static str, String)> {
+    let fields = [
+        ("C", name.iter_country().collect::<Vec<_>>()),
+        ("ST", name.iter_state_or_province().collect()),
+        ("L", name.iter_locality().collect()),
+        ("O", name.iter_organization().collect()),
+        ("OU", name.iter_organizational_unit().collect()),
+        ("CN", name.iter_common_name().collect()),
+    ];
+    fields
+        .into_iter()
+        .filter_map(|(key, values)| {
+            let value = values.first()?.as_str().ok()?;
+            Some((key, value.to_string()))
+        })
+        .collect()
+}
+
+fn ip_address(bytes: &[u8]) -> Option<IpAddr> {
+    match bytes.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => Some(IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => None,
+    }
+}
+
+/// `Jan  2 03:04:05 2025 GMT`, as OpenSSL prints times
+fn format_time(time: &ASN1Time) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let time = time.to_datetime();
+    format!(
+        "{} {:>2} {:02}:{:02}:{:02} {} GMT",
+        MONTHS[time.month() as usize - 1],
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+        time.year()
+    )
+}
+
+/// `AB:CD:...`
+fn fingerprint(digest: &[u8]) -> String {
+    digest
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// A secure context held by a JS `SecureContext`
+#[derive(Trace, Finalize, JsData)]
+struct ContextHandle {
+    #[unsafe_ignore_trace]
+    context: Arc<SecureContext>,
+}
+
+/// A session held by a JS `TLSSocket`
+#[derive(Trace, Finalize, JsData)]
+struct SessionHandle {
+    #[unsafe_ignore_trace]
+    session: Arc<Mutex<Session>>,
+}
+
+fn context_arg(value: &JsValue) -> JsResult<Arc<SecureContext>> {
+    value
+        .as_object()
+        .and_then(|obj| {
+            obj.downcast_ref::<ContextHandle>()
+                .map(|handle| handle.context.clone())
+        })
+        .ok_or_else(|| {
+            JsNativeError::typ()
+                .with_message("Expected a secure context")
+                .into()
+        })
+}
+
+fn session_arg(value: &JsValue) -> JsResult<Arc<Mutex<Session>>> {
+    value
+        .as_object()
+        .and_then(|obj| {
+            obj.downcast_ref::<SessionHandle>()
+                .map(|handle| handle.session.clone())
+        })
+        .ok_or_else(|| {
+            JsNativeError::typ()
+                .with_message("Expected a TLS session")
+                .into()
+        })
+}
+
+fn get(options: &JsValue, key: &str, context: &mut Context) -> JsResult<JsValue> {
+    match options.as_object() {
+        Some(obj) => obj.get(js_string!(key), context),
+        None => Ok(JsValue::undefined()),
+    }
+}
+
+/// `undefined` or an array of strings
+fn string_list(value: &JsValue, context: &mut Context) -> JsResult<Option<Vec<String>>> {
+    let Some(array) = value
+        .as_object()
+        .and_then(|obj| JsArray::from_object(obj.clone()).ok())
+    else {
+        return Ok(None);
+    };
+    let mut list = Vec::new();
+    for i in 0..array.length(context)? {
+        list.push(
+            array
+                .get(i, context)?
+                .to_string(context)?
+                .to_std_string_escaped(),
+        );
+    }
+    Ok(Some(list))
+}
+
+fn alpn_list(options: &JsValue, context: &mut Context) -> JsResult<Vec<Vec<u8>>> {
+    let protocols = get(options, "ALPNProtocols", context)?;
+    Ok(string_list(&protocols, context)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(String::into_bytes)
+        .collect())
+}
+
+fn bytes_value(bytes: Vec<u8>, context: &mut Context) -> JsResult<JsValue> {
+    Ok(JsUint8Array::from_iter(bytes, context)?.into())
+}
+
+fn tls_error(error: impl ToString) -> JsError {
+    JsNativeError::error()
+        .with_message(error.to_string())
+        .into()
+}
+
+/// `{ output, chunks, secure, eof, hello, error, code }`
+fn progress_to_js(progress: Progress, context: &mut Context) -> JsResult<JsValue> {
+    let result = JsObject::with_null_proto();
+    if !progress.output.is_empty() {
+        let output = bytes_value(progress.output, context)?;
+        result.set(js_string!("output"), output, false, context)?;
+    }
+    let chunks = JsArray::new(context);
+    for chunk in progress.chunks {
+        chunks.push(bytes_value(chunk, context)?, context)?;
+    }
+    result.set(js_string!("chunks"), chunks, false, context)?;
+    result.set(js_string!("secure"), progress.secure, false, context)?;
+    result.set(js_string!("eof"), progress.eof, false, context)?;
+    if let Some((servername, protocols)) = progress.hello {
+        let list = JsArray::new(context);
+        for protocol in protocols {
+            list.push(js_string!(protocol), context)?;
+        }
+        let servername = match servername {
+            Some(name) => JsValue::from(js_string!(name)),
+            None => JsValue::undefined(),
+        };
+        let hello = ObjectInitializer::new(context)
+            .property(js_string!("servername"), servername, Default::default())
+            .property(js_string!("protocols"), list, Default::default())
+            .build();
+        result.set(js_string!("hello"), hello, false, context)?;
+    }
+    if let Some(error) = progress.error {
+        result.set(
+            js_string!("code"),
+            js_string!(error_code(&error)),
+            false,
+            context,
+        )?;
+        result.set(
+            js_string!("error"),
+            js_string!(error.to_string()),
+            false,
+            context,
+        )?;
+    }
+    Ok(result.into())
+}
+
+fn name_to_js(fields: Vec<(&'static str, String)>, context: &mut Context) -> JsResult<JsObject> {
+    let object = JsObject::with_null_proto();
+    for (key, value) in fields {
+        object.set(js_string!(key), js_string!(value), false, context)?;
+    }
+    Ok(object)
+}
+
+fn certificate_to_js(info: CertificateInfo, context: &mut Context) -> JsResult<JsValue> {
+    let subject = name_to_js(info.subject, context)?;
+    let issuer = name_to_js(info.issuer, context)?;
+    let raw = bytes_value(info.raw, context)?;
+    let mut object = ObjectInitializer::new(context);
+    object
+        .property(js_string!("subject"), subject, Attribute::all())
+        .property(js_string!("issuer"), issuer, Attribute::all());
+    if let Some(names) = info.subjectaltname {
+        object.property(
+            js_string!("subjectaltname"),
+            js_string!(names),
+            Attribute::all(),
+        );
+    }
+    let object = object
+        .property(js_string!("ca"), info.ca, Attribute::all())
+        .property(
+            js_string!("valid_from"),
+            js_string!(info.valid_from),
+            Attribute::all(),
+        )
+        .property(
+            js_string!("valid_to"),
+            js_string!(info.valid_to),
+            Attribute::all(),
+        )
+        .property(
+            js_string!("serialNumber"),
+            js_string!(info.serial_number),
+            Attribute::all(),
+        )
+        .property(
+            js_string!("fingerprint"),
+            js_string!(info.fingerprint),
+            Attribute::all(),
+        )
+        .property(
+            js_string!("fingerprint256"),
+            js_string!(info.fingerprint256),
+            Attribute::all(),
+        )
+        .property(
+            js_string!("fingerprint512"),
+            js_string!(info.fingerprint512),
+            Attribute::all(),
+        )
+        .property(js_string!("raw"), raw, Attribute::all())
+        .build();
+    Ok(object.into())
+}
+
+/// Register the TLS natives and the `tls` global
+pub fn register_tls_module(context: &mut Context) -> JsResult<()> {
+    // __viper_tls_context_new({ key, cert, ca, minVersion, maxVersion }) -> context
+    //
+    // key, cert and ca are arrays of PEM strings; a missing ca means the
+    // bundled roots.
+    let context_new_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let options = args.get_or_undefined(0);
+        let key = get(options, "key", context)?;
+        let keys = string_list(&key, context)?.unwrap_or_default();
+        let cert = get(options, "cert", context)?;
+        let certs = string_list(&cert, context)?.unwrap_or_default();
+        let ca = get(options, "ca", context)?;
+        let ca = string_list(&ca, context)?;
+        let min_version = get(options, "minVersion", context)?;
+        let min_version = if min_version.is_undefined() {
+            "TLSv1.2".to_string()
+        } else {
+            min_version.to_string(context)?.to_std_string_escaped()
+        };
+        let max_version = get(options, "maxVersion", context)?;
+        let max_version = if max_version.is_undefined() {
+            "TLSv1.3".to_string()
+        } else {
+            max_version.to_string(context)?.to_std_string_escaped()
+        };
+
+        let secure_context =
+            SecureContext::new(&keys, &certs, ca.as_deref(), &min_version, &max_version)
+                .map_err(tls_error)?;
+        let handle = ContextHandle {
+            context: Arc::new(secure_context),
+        };
+        Ok(JsObject::from_proto_and_data(None, handle).into())
+    });
+    context.register_global_callable(js_string!("__viper_tls_context_new"), 1, context_new_fn)?;
+
+    // __viper_tls_client_new({ context, servername, rejectUnauthorized, ALPNProtocols }) -> session
+    let client_new_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let options = args.get_or_undefined(0);
+        let secure_context = context_arg(&get(options, "context", context)?)?;
+        let servername = get(options, "servername", context)?
+            .to_string(context)?
+            .to_std_string_escaped();
+        let reject_unauthorized = get(options, "rejectUnauthorized", context)?.to_boolean();
+        let alpn = alpn_list(options, context)?;
+
+        let session = Session::client(&secure_context, &servername, reject_unauthorized, alpn)
+            .map_err(tls_error)?;
+        let handle = SessionHandle {
+            session: Arc::new(Mutex::new(session)),
+        };
+        Ok(JsObject::from_proto_and_data(None, handle).into())
+    });
+    context.register_global_callable(js_string!("__viper_tls_client_new"), 1, client_new_fn)?;
+
+    // __viper_tls_server_new() -> session, reading the ClientHello
+    let server_new_fn = NativeFunction::from_fn_ptr(|_this, _args, _context| {
+        let handle = SessionHandle {
+            session: Arc::new(Mutex::new(Session::server())),
+        };
+        Ok(JsObject::from_proto_and_data(None, handle).into())
+    });
+    context.register_global_callable(js_string!("__viper_tls_server_new"), 0, server_new_fn)?;
+
+    // __viper_tls_accept(session, { context, requestCert, rejectUnauthorized, ALPNProtocols })
+    let accept_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let session = session_arg(args.get_or_undefined(0))?;
+        let options = args.get_or_undefined(1);
+        let secure_context = context_arg(&get(options, "context", context)?)?;
+        let request_cert = get(options, "requestCert", context)?.to_boolean();
+        let reject_unauthorized = get(options, "rejectUnauthorized", context)?.to_boolean();
+        let alpn = alpn_list(options, context)?;
+
+        let progress = session.lock().unwrap().accept(
+            &secure_context,
+            request_cert,
+            reject_unauthorized,
+            alpn,
+        );
+        progress_to_js(progress, context)
+    });
+    context.register_global_callable(js_string!("__viper_tls_accept"), 2, accept_fn)?;
+
+    // __viper_tls_feed(session[, ciphertext]) - without data, just collect
+    // what the session wants to send (a client's hello)
+    let feed_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let session = session_arg(args.get_or_undefined(0))?;
+        let input = match args.get_or_undefined(1) {
+            value if value.is_undefined() => Vec::new(),
+            value => net::view_bytes(value, context)?,
+        };
+        let progress = session.lock().unwrap().feed(&input);
+        progress_to_js(progress, context)
+    });
+    context.register_global_callable(js_string!("__viper_tls_feed"), 2, feed_fn)?;
+
+    // __viper_tls_write(session, plaintext)
+    let write_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let session = session_arg(args.get_or_undefined(0))?;
+        let data = net::view_bytes(args.get_or_undefined(1), context)?;
+        let progress = session.lock().unwrap().write(&data);
+        progress_to_js(progress, context)
+    });
+    context.register_global_callable(js_string!("__viper_tls_write"), 2, write_fn)?;
+
+    // __viper_tls_end(session) - close_notify
+    let end_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let session = session_arg(args.get_or_undefined(0))?;
+        let progress = session.lock().unwrap().end();
+        progress_to_js(progress, context)
+    });
+    context.register_global_callable(js_string!("__viper_tls_end"), 1, end_fn)?;
+
+    // __viper_tls_destroy(session) - drop the connection state
+    let destroy_fn = NativeFunction::from_fn_ptr(|_this, args, _context| {
+        let session = session_arg(args.get_or_undefined(0))?;
+        session.lock().unwrap().state = State::Closed;
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_tls_destroy"), 1, destroy_fn)?;
+
+    // __viper_tls_info(session) -> { protocol, cipher, alpnProtocol, servername,
+    //   peerCertificate, authorizationError, authorizationMessage }
+    let info_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let session = session_arg(args.get_or_undefined(0))?;
+        let session = session.lock().unwrap();
+        let result = JsObject::with_null_proto();
+        if let Some(connection) = session.connection() {
+            let protocol = match connection.protocol_version() {
+                Some(ProtocolVersion::TLSv1_3) => JsValue::from(js_string!("TLSv1.3")),
+                Some(ProtocolVersion::TLSv1_2) => JsValue::from(js_string!("TLSv1.2")),
+                _ => JsValue::null(),
+            };
+            result.set(js_string!("protocol"), protocol, false, context)?;
+            // OpenSSL names TLS 1.3 suites without the "13"
+            if let Some(name) = connection
+                .negotiated_cipher_suite()
+                .and_then(|suite| suite.suite().as_str())
+            {
+                let name = name.replacen("TLS13_", "TLS_", 1);
+                result.set(js_string!("cipher"), js_string!(name), false, context)?;
+            }
+            let alpn = match connection.alpn_protocol() {
+                Some(protocol) => {
+                    JsValue::from(js_string!(String::from_utf8_lossy(protocol).into_owned()))
+                }
+                None => JsValue::from(false),
+            };
+            result.set(js_string!("alpnProtocol"), alpn, false, context)?;
+            let has_peer_certificate = connection
+                .peer_certificates()
+                .is_some_and(|certs| !certs.is_empty());
+            result.set(
+                js_string!("peerCertificate"),
+                has_peer_certificate,
+                false,
+                context,
+            )?;
+        }
+        if let Some(name) = &session.servername {
+            result.set(
+                js_string!("servername"),
+                js_string!(name.clone()),
+                false,
+                context,
+            )?;
+        }
+        if let Some(error) = session.verdict.error() {
+            result.set(
+                js_string!("authorizationError"),
+                js_string!(error_code(&error)),
+                false,
+                context,
+            )?;
+            result.set(
+                js_string!("authorizationMessage"),
+                js_string!(error.to_string()),
+                false,
+                context,
+            )?;
+        }
+        Ok(result.into())
+    });
+    context.register_global_callable(js_string!("__viper_tls_info"), 1, info_fn)?;
+
+    // __viper_tls_certificates(session, peer) -> [certificate, ...], leaf first
+    let certificates_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let session = session_arg(args.get_or_undefined(0))?;
+        let peer = args.get_or_undefined(1).to_boolean();
+        let chain: Vec<CertificateDer<'static>> = {
+            let session = session.lock().unwrap();
+            if peer {
+                let mut chain = session
+                    .connection()
+                    .and_then(|connection| connection.peer_certificates())
+                    .map(|certs| certs.to_vec())
+                    .unwrap_or_default();
+                complete_chain(&mut chain, &session.issuers);
+                chain
+            } else {
+                session.chain.clone()
+            }
+        };
+        let list = JsArray::new(context);
+        for cert in chain {
+            if let Some(info) = CertificateInfo::parse(&cert) {
+                list.push(certificate_to_js(info, context)?, context)?;
+            }
+        }
+        Ok(list.into())
+    });
+    context.register_global_callable(js_string!("__viper_tls_certificates"), 2, certificates_fn)?;
+
+    // __viper_tls_export_keying_material(session, length, label[, context])
+    let export_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let session = session_arg(args.get_or_undefined(0))?;
+        let length = args.get_or_undefined(1).to_u32(context)? as usize;
+        let label = args
+            .get_or_undefined(2)
+            .to_string(context)?
+            .to_std_string_escaped();
+        let extra = match args.get_or_undefined(3) {
+            value if value.is_undefined() => None,
+            value => Some(net::view_bytes(value, context)?),
+        };
+        let material = {
+            let session = session.lock().unwrap();
+            let connection = session
+                .connection()
+                .ok_or_else(|| tls_error("TLS session is not established"))?;
+            connection
+                .export_keying_material(vec![0u8; length], label.as_bytes(), extra.as_deref())
+                .map_err(tls_error)?
+        };
+        bytes_value(material, context)
+    });
+    context.register_global_callable(
+        js_string!("__viper_tls_export_keying_material"),
+        4,
+        export_fn,
+    )?;
+
+    let tls_code = include_str!("tls_module.js");
+    context.eval(Source::from_bytes(tls_code.as_bytes()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+/// A CA and a `localhost`/`127.0.0.1` certificate signed by it, as PEM
+/// strings: (ca, cert, key)
+pub(crate) fn test_certificates() -> (String, String, String) {
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+    let ca_key = KeyPair::generate().unwrap();
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, "Viper Test CA");
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+
+    let key = KeyPair::generate().unwrap();
+    let mut params =
+        CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]).unwrap();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "localhost");
+    let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+    (ca.pem(), cert.pem(), key.serialize_pem())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_certificates;
+    use crate::runtime::Runtime;
+
+    /// Run `code` with `ca`, `cert` and `key` in scope
+    fn seen(code: &str) -> String {
+        let (ca, cert, key) = test_certificates();
+        crate::runtime::seen(&format!(
+            "const [ca, cert, key] = [{ca:?}, {cert:?}, {key:?}];\n{code}"
+        ))
+    }
+
+    /// An echo-with-servername server on `port`
+    const SERVER: &str = r#"
+        import net from 'node:net';
+        import tls from 'node:tls';
+
+        const server = tls.createServer({ key, cert, ALPNProtocols: ['h2', 'http/1.1'] }, (socket) => {
+            socket.on('error', () => {});
+            socket.on('data', (data) => socket.end(`${socket.servername}:${data}`));
+        });
+        await new Promise((resolve) => server.listen(0, '127.0.0.1', resolve));
+        const { port } = server.address();
+        const failure = (options) => new Promise((resolve) => {
+            const socket = tls.connect({ port, host: '127.0.0.1', ...options });
+            socket.on('secureConnect', () => { socket.destroy(); resolve('connected'); });
+            socket.on('error', (err) => resolve(err.code));
+        });
+    "#;
+
+    #[test]
+    fn test_secure_connection() {
+        let code = format!(
+            "{SERVER}{}",
+            r#"
+            const client = tls.connect({ port, host: '127.0.0.1', servername: 'localhost', ca, ALPNProtocols: ['http/1.1'] });
+            await new Promise((resolve) => client.once('secureConnect', resolve));
+            const peer = client.getPeerCertificate(true);
+            let reply = '';
+            client.on('data', (data) => { reply += data; });
+            client.write('ping');
+            await new Promise((resolve) => client.once('close', resolve));
+            await new Promise((resolve) => server.close(resolve));
+
+            globalThis.seen = [
+                client.authorized, client.alpnProtocol, client.getProtocol(),
+                peer.subject.CN, peer.issuerCertificate.subject.CN, peer.subjectaltname, reply,
+            ].join('|');
+        "#
+        );
+        assert_eq!(
+            seen(&code),
+            "true|http/1.1|TLSv1.3|localhost|Viper Test CA|DNS:localhost, IP Address:127.0.0.1|\
+             localhost:ping"
+        );
+    }
+
+    #[test]
+    fn test_verification_errors() {
+        let code = format!(
+            "{SERVER}{}",
+            r#"
+            const insecure = tls.connect({ port, host: '127.0.0.1', rejectUnauthorized: false });
+            await new Promise((resolve) => insecure.once('secureConnect', resolve));
+            insecure.end();
+            await new Promise((resolve) => insecure.once('close', resolve));
+
+            const plain = net.createServer((socket) => socket.resume().end('not tls\r\n\r\n'));
+            await new Promise((resolve) => plain.listen(0, '127.0.0.1', resolve));
+            const garbled = await new Promise((resolve) => {
+                const socket = tls.connect({ port: plain.address().port, host: '127.0.0.1', ca });
+                socket.on('error', (err) => resolve(err.code));
+            });
+            await new Promise((resolve) => plain.close(resolve));
+
+            // A client that hangs up before its hello
+            const early = new Promise((resolve) => server.once('tlsClientError', resolve));
+            net.connect(port, '127.0.0.1', function () { this.end(); });
+            const hungUp = (await early).code;
+
+            globalThis.seen = [
+                insecure.authorized,
+                insecure.authorizationError,
+                await failure({}),
+                await failure({ ca, servername: 'example.com' }),
+                garbled,
+                hungUp,
+            ];
+            await new Promise((resolve) => server.close(resolve));
+        "#
+        );
+        assert_eq!(
+            seen(&code),
+            "false,UNABLE_TO_GET_ISSUER_CERT_LOCALLY,UNABLE_TO_GET_ISSUER_CERT_LOCALLY,\
+             ERR_TLS_CERT_ALTNAME_INVALID,ERR_SSL_WRONG_VERSION_NUMBER,ECONNRESET"
+        );
+    }
+
+    #[test]
+    fn test_context_errors() {
+        let code = r#"
+            import tls from 'node:tls';
+
+            const thrown = (f) => { try { f(); return 'created'; } catch (e) { return e.code ?? e.message; } };
+            const versions = { minVersion: 'TLSv1.3', maxVersion: 'TLSv1.2' };
+            globalThis.seen = [
+                thrown(() => tls.createSecureContext({ key, cert, ca })),
+                thrown(() => tls.createSecureContext({ pfx: Buffer.alloc(4) })),
+                thrown(() => tls.createSecureContext({ key: 'not a key', cert })),
+                thrown(() => tls.createSecureContext({ key, cert: 'not a cert' })),
+                thrown(() => tls.createSecureContext({ ca: 'not a ca' })),
+                thrown(() => tls.createSecureContext(versions)),
+            ].join('|');
+        "#;
+        assert_eq!(
+            seen(code),
+            "created|ERR_FEATURE_UNAVAILABLE_ON_PLATFORM|Invalid private key: no items found|\
+             Invalid certificate: no certificates found|\
+             Invalid CA certificate: no certificates found|\
+             No TLS version between TLSv1.3 and TLSv1.2"
+        );
+    }
+
+    #[test]
+    fn test_starttls() {
+        let code = r#"
+            import net from 'node:net';
+            import tls from 'node:tls';
+
+            const secureContext = tls.createSecureContext({ key, cert });
+            const server = net.createServer((socket) => {
+                socket.once('data', () => {
+                    socket.write('220 go ahead\n');
+                    const secure = new tls.TLSSocket(socket, { isServer: true, secureContext });
+                    secure.on('data', (data) => secure.end(`secret ${data}`));
+                });
+            });
+            await new Promise((resolve) => server.listen(0, '127.0.0.1', resolve));
+
+            const socket = net.connect(server.address().port, '127.0.0.1', () => socket.write('STARTTLS\n'));
+            const banner = await new Promise((resolve) => socket.once('data', resolve));
+            const secure = tls.connect({ socket, servername: 'localhost', ca }, () => secure.write('hello'));
+            let reply = '';
+            secure.on('data', (data) => { reply += data; });
+            await new Promise((resolve) => secure.once('close', resolve));
+            await new Promise((resolve) => server.close(resolve));
+
+            globalThis.seen = [String(banner).trim(), secure.encrypted, secure.authorized, reply];
+        "#;
+        assert_eq!(seen(code), "220 go ahead,true,true,secret hello");
+    }
+
+    #[test]
+    fn test_connect_permission() {
+        use crate::runtime::{Allow, Permissions, RuntimeConfig};
+
+        let config = RuntimeConfig {
+            permissions: Permissions {
+                net: Allow::Only(vec!["127.0.0.1:1".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut runtime = Runtime::with_config(config).unwrap();
+        let code = r#"
+            import tls from 'node:tls';
+            const denied = (err) => err.code + ' ' + err.resource;
+            let listened;
+            try {
+                tls.createServer({}).listen(0, '127.0.0.1');
+            } catch (err) {
+                listened = denied(err);
+            }
+            const connected = await new Promise((resolve) => {
+                const socket = tls.connect({ port: 443, host: '127.0.0.1' });
+                socket.on('secureConnect', () => resolve('connected'));
+                socket.on('error', (err) => resolve(denied(err)));
+            });
+            globalThis.seen = [listened, connected];
+        "#;
+        runtime.run(code, "main.mjs").unwrap();
+        let seen = runtime.eval("String(globalThis.seen)", "check.js").unwrap();
+        assert_eq!(
+            runtime.value_to_string(&seen),
+            "ERR_ACCESS_DENIED 127.0.0.1:0,ERR_ACCESS_DENIED 127.0.0.1:443"
+        );
+    }
+}
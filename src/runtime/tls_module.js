@@ -0,0 +1,581 @@
+/**
+ * Node.js tls
+ *
+ * A TLSSocket is a net.Socket whose bytes pass through a TLS session (see
+ * tls.rs) on their way to and from another stream, its transport.
+ * `tls.connect()` opens the transport itself; `new tls.TLSSocket(socket)`
+ * takes one that is already connected, which is how STARTTLS upgrades a
+ * plain connection. `tls.Server` is a net.Server wrapping every connection
+ * it accepts, picking the secure context (SNI) and protocol (ALPN) once the
+ * ClientHello has arrived.
+ */
+(function () {
+  "use strict";
+
+  const net = globalThis.net;
+
+  const CIPHERS = [
+    "tls_aes_256_gcm_sha384",
+    "tls_aes_128_gcm_sha256",
+    "tls_chacha20_poly1305_sha256",
+    "ecdhe-ecdsa-aes256-gcm-sha384",
+    "ecdhe-ecdsa-aes128-gcm-sha256",
+    "ecdhe-ecdsa-chacha20-poly1305",
+    "ecdhe-rsa-aes256-gcm-sha384",
+    "ecdhe-rsa-aes128-gcm-sha256",
+    "ecdhe-rsa-chacha20-poly1305",
+  ];
+
+  function tlsError(result) {
+    const err = new Error(result.error);
+    err.code = result.code;
+    return err;
+  }
+
+  function disconnectedError() {
+    const err = new Error("Client network socket disconnected before secure TLS connection was established");
+    err.code = "ECONNRESET";
+    return err;
+  }
+
+  // The global TextDecoder decodes the whole buffer behind a view; hand it a copy
+  function decode(view) {
+    return new TextDecoder().decode(new Uint8Array(view.buffer, view.byteOffset, view.byteLength).slice());
+  }
+
+  // A PEM option (string, Buffer, { pem } or an array of them) as strings
+  function pemList(value) {
+    if (value === undefined || value === null) return undefined;
+    return [].concat(value).map((entry) => {
+      if (entry !== null && typeof entry === "object" && !ArrayBuffer.isView(entry)) entry = entry.pem;
+      return ArrayBuffer.isView(entry) ? decode(entry) : String(entry);
+    });
+  }
+
+  // ALPNProtocols as an array of strings; a Buffer is in wire format
+  function alpnList(value) {
+    if (value === undefined || value === null) return [];
+    if (ArrayBuffer.isView(value) && !Array.isArray(value)) {
+      const bytes = new Uint8Array(value.buffer, value.byteOffset, value.byteLength);
+      const protocols = [];
+      for (let i = 0; i < bytes.length; i += bytes[i] + 1) {
+        protocols.push(decode(bytes.subarray(i + 1, i + 1 + bytes[i])));
+      }
+      return protocols;
+    }
+    return [...value].map((protocol) =>
+      ArrayBuffer.isView(protocol) ? decode(protocol) : String(protocol)
+    );
+  }
+
+  class SecureContext {
+    constructor(options = {}) {
+      if (options.pfx !== undefined) {
+        const err = new Error("PFX/PKCS#12 bundles are not supported; pass key and cert as PEM");
+        err.code = "ERR_FEATURE_UNAVAILABLE_ON_PLATFORM";
+        throw err;
+      }
+      this.context = __viper_tls_context_new({
+        key: pemList(options.key),
+        cert: pemList(options.cert),
+        ca: pemList(options.ca),
+        minVersion: options.minVersion ?? tls.DEFAULT_MIN_VERSION,
+        maxVersion: options.maxVersion ?? tls.DEFAULT_MAX_VERSION,
+      });
+    }
+  }
+
+  function createSecureContext(options = {}) {
+    return new SecureContext(options);
+  }
+
+  // `*.example.com` matches exactly one label
+  function matchName(host, pattern) {
+    host = host.toLowerCase().replace(/\.$/, "");
+    pattern = pattern.toLowerCase().replace(/\.$/, "");
+    if (!pattern.startsWith("*.")) return host === pattern;
+    const rest = pattern.slice(2);
+    const dot = host.indexOf(".");
+    return rest.includes(".") && dot > 0 && host.slice(dot + 1) === rest;
+  }
+
+  function checkServerIdentity(hostname, cert) {
+    const altNames = (cert.subjectaltname ?? "").split(", ").filter(Boolean);
+    const dnsNames = altNames.filter((name) => name.startsWith("DNS:")).map((name) => name.slice(4));
+    const ips = altNames.filter((name) => name.startsWith("IP Address:")).map((name) => name.slice(11));
+
+    let valid;
+    let reason;
+    if (net.isIP(hostname)) {
+      valid = ips.includes(hostname);
+      reason = `IP: ${hostname} is not in the cert's list: ${ips.join(", ")}`;
+    } else if (altNames.length > 0) {
+      valid = dnsNames.some((name) => matchName(hostname, name));
+      reason = `Host: ${hostname}. is not in the cert's altnames: ${altNames.join(", ")}`;
+    } else {
+      const cn = cert.subject?.CN;
+      valid = cn !== undefined && matchName(hostname, cn);
+      reason = `Host: ${hostname}. is not cert's CN: ${cn}`;
+    }
+    if (valid) return undefined;
+
+    const err = new Error(`Hostname/IP does not match certificate's altnames: ${reason}`);
+    err.code = "ERR_TLS_CERT_ALTNAME_INVALID";
+    err.reason = reason;
+    err.host = hostname;
+    err.cert = cert;
+    return err;
+  }
+
+  function toCertificate(cert) {
+    cert.raw = Buffer.from(cert.raw.buffer, cert.raw.byteOffset, cert.raw.byteLength);
+    return cert;
+  }
+
+  class TLSSocket extends net.Socket {
+    constructor(socket, options = {}) {
+      options = options ?? {};
+      super({ allowHalfOpen: options.allowHalfOpen });
+      this.encrypted = true;
+      this.authorized = false;
+      this.authorizationError = null;
+      this.alpnProtocol = false;
+      this.servername = undefined;
+      this._tlsOptions = options;
+      this._isServer = Boolean(options.isServer);
+      this._server = options.server ?? null;
+      this._session = null;
+      this._secure = false;
+      this._tlsEnded = false;
+      this._info = null;
+
+      const transport = socket ?? new net.Socket({ allowHalfOpen: options.allowHalfOpen });
+      this._parent = transport;
+      // Its end follows ours, after close_notify, not the peer's FIN
+      transport.allowHalfOpen = true;
+      transport.on("data", (data) => this._feed(data));
+      // An end before the handshake finished would otherwise leave the
+      // half-open transport waiting forever
+      transport.on("end", () => {
+        if (this._secure) this._onTlsEnd();
+        else this._fail(disconnectedError());
+      });
+      transport.on("error", (err) => this._fail(err));
+      transport.on("close", () => {
+        if (this.destroyed) return;
+        if (this._secure) this.destroy();
+        else this._fail(disconnectedError());
+      });
+      transport.on("lookup", (...args) => this.emit("lookup", ...args));
+
+      // Start once the transport is connected, right away for one that is
+      if (transport.connecting || (transport instanceof net.Socket && transport.pending)) {
+        this._connecting = true;
+        transport.once("connect", () => this._start(true));
+      } else {
+        this._start(false);
+      }
+    }
+
+    get pending() {
+      return !this._secure;
+    }
+
+    connect(...args) {
+      this._parent.connect(...args);
+      return this;
+    }
+
+    _start(connected) {
+      if (this.destroyed) return;
+      this._connecting = false;
+      const transport = this._parent;
+      for (const key of ["localAddress", "localPort", "localFamily", "remoteAddress", "remotePort", "remoteFamily"]) {
+        this[key] = transport[key];
+      }
+      const options = this._tlsOptions;
+
+      try {
+        if (this._isServer) {
+          this._session = __viper_tls_server_new();
+        } else {
+          const context = options.secureContext ?? createSecureContext(options);
+          this.servername = options.servername || options.host || "localhost";
+          this._session = __viper_tls_client_new({
+            context: context.context,
+            servername: this.servername,
+            rejectUnauthorized: options.rejectUnauthorized !== false,
+            ALPNProtocols: alpnList(options.ALPNProtocols),
+          });
+        }
+      } catch (err) {
+        queueMicrotask(() => this.destroy(err));
+        return;
+      }
+
+      if (connected) this.emit("connect");
+      // A client's hello goes out now
+      if (!this._isServer) this._handle(__viper_tls_feed(this._session));
+    }
+
+    _feed(data) {
+      if (this._session === null || this.destroyed) return;
+      this._touch();
+      this._handle(__viper_tls_feed(this._session, data));
+    }
+
+    // Send what the session produced and deliver what it decrypted
+    _handle(result) {
+      if (result.output) {
+        this._parent.write(Buffer.from(result.output.buffer));
+      }
+      if (result.error) {
+        this._fail(tlsError(result));
+        return;
+      }
+      if (result.hello) this._onHello(result.hello);
+      if (result.secure) this._onSecure();
+      for (const chunk of result.chunks) {
+        this.bytesRead += chunk.byteLength;
+        if (!this.push(Buffer.from(chunk.buffer, chunk.byteOffset, chunk.byteLength))) {
+          this._parent.pause();
+        }
+      }
+      if (result.eof) this._onTlsEnd();
+    }
+
+    // Handshake failures on a server's socket go to 'tlsClientError'
+    _fail(err) {
+      if (this.destroyed) return;
+      if (this._isServer && !this._secure && this._server) {
+        this._server.emit("tlsClientError", err, this);
+        this.destroy();
+      } else {
+        this.destroy(err);
+      }
+    }
+
+    // Pick the context and protocol for a ClientHello, then go on
+    _onHello({ servername, protocols }) {
+      this.servername = servername ?? false;
+      const options = this._tlsOptions;
+      const proceed = (err, context) => {
+        if (this.destroyed) return;
+        if (err) {
+          this._fail(err);
+          return;
+        }
+        let alpn = alpnList(options.ALPNProtocols);
+        if (typeof options.ALPNCallback === "function") {
+          const chosen = options.ALPNCallback({ servername, protocols });
+          alpn = chosen !== undefined && protocols.includes(chosen) ? [chosen] : [];
+        }
+        this._handle(__viper_tls_accept(this._session, {
+          context: (context ?? options.secureContext).context,
+          requestCert: Boolean(options.requestCert),
+          rejectUnauthorized: options.rejectUnauthorized !== false,
+          ALPNProtocols: alpn,
+        }));
+      };
+
+      if (servername && typeof options.SNICallback === "function") {
+        try {
+          options.SNICallback(servername, proceed);
+        } catch (err) {
+          proceed(err);
+        }
+      } else {
+        proceed(null, this._server?._contextFor(servername));
+      }
+    }
+
+    _onSecure() {
+      this._secure = true;
+      const info = __viper_tls_info(this._session);
+      const options = this._tlsOptions;
+      this._info = info;
+      this.alpnProtocol = info.alpnProtocol ?? false;
+      if (info.authorizationError) {
+        this.authorizationError = info.authorizationError;
+      } else {
+        this.authorized = this._isServer ? Boolean(info.peerCertificate) : true;
+      }
+
+      if (!this._isServer && this.authorized && typeof options.checkServerIdentity === "function") {
+        const err = options.checkServerIdentity(this.servername, this.getPeerCertificate());
+        if (err) {
+          if (options.rejectUnauthorized !== false) {
+            this.destroy(err);
+            return;
+          }
+          this.authorized = false;
+          this.authorizationError = err.code;
+        }
+      }
+
+      const writes = this._pendingWrites;
+      this._pendingWrites = [];
+      for (const [chunk, encoding, callback] of writes) {
+        this._write(chunk, encoding, callback);
+      }
+      if (this._pendingFinal) {
+        const callback = this._pendingFinal;
+        this._pendingFinal = null;
+        this._final(callback);
+      }
+
+      this.emit(this._isServer ? "secure" : "secureConnect");
+    }
+
+    _onTlsEnd() {
+      if (this._tlsEnded) return;
+      this._tlsEnded = true;
+      this.push(null);
+    }
+
+    _read() {
+      if (this._parent.isPaused?.()) this._parent.resume();
+    }
+
+    _write(chunk, encoding, callback) {
+      if (!this._secure) {
+        this._pendingWrites.push([chunk, encoding, callback]);
+        return;
+      }
+      const result = __viper_tls_write(this._session, chunk);
+      if (result.error) {
+        callback(tlsError(result));
+        return;
+      }
+      this.bytesWritten += chunk.length;
+      this._touch();
+      // Done once the transport has taken the ciphertext, so its
+      // backpressure is ours too
+      if (result.output) {
+        this._parent.write(Buffer.from(result.output.buffer), (err) => callback(err));
+      } else {
+        queueMicrotask(() => callback());
+      }
+    }
+
+    _final(callback) {
+      if (!this._secure) {
+        this._pendingFinal = callback;
+        return;
+      }
+      const result = __viper_tls_end(this._session);
+      if (result.output) {
+        this._parent.write(Buffer.from(result.output.buffer));
+      }
+      this._parent.end(() => {
+        this._writeDone = true;
+        callback();
+        this._maybeClose();
+      });
+    }
+
+    _destroy(err, callback) {
+      if (this._session !== null) __viper_tls_destroy(this._session);
+      this._pendingWrites = [];
+      this._pendingFinal = null;
+      if (!this._parent.destroyed) this._parent.destroy();
+      super._destroy(err, callback);
+    }
+
+    address() {
+      return this._parent.address();
+    }
+
+    setNoDelay(noDelay = true) {
+      this._parent.setNoDelay?.(noDelay);
+      return this;
+    }
+
+    setKeepAlive(enable = false, initialDelay = 0) {
+      this._parent.setKeepAlive?.(enable, initialDelay);
+      return this;
+    }
+
+    ref() {
+      this._parent.ref?.();
+      return this;
+    }
+
+    unref() {
+      this._parent.unref?.();
+      return this;
+    }
+
+    getPeerCertificate(detailed = false) {
+      if (this._session === null) return null;
+      const chain = __viper_tls_certificates(this._session, true).map(toCertificate);
+      if (chain.length === 0) return {};
+      if (detailed) {
+        chain.forEach((cert, i) => {
+          const selfSigned = JSON.stringify(cert.subject) === JSON.stringify(cert.issuer);
+          cert.issuerCertificate = chain[i + 1] ?? (selfSigned ? cert : undefined);
+        });
+      }
+      return chain[0];
+    }
+
+    getCertificate() {
+      if (this._session === null) return null;
+      const chain = __viper_tls_certificates(this._session, false).map(toCertificate);
+      return chain[0] ?? {};
+    }
+
+    getProtocol() {
+      return this._info?.protocol ?? null;
+    }
+
+    getCipher() {
+      if (!this._info?.cipher) return undefined;
+      const name = this._info.cipher;
+      return { name, standardName: name, version: this._info.protocol };
+    }
+
+    getSession() {
+      return undefined;
+    }
+
+    isSessionReused() {
+      return false;
+    }
+
+    getEphemeralKeyInfo() {
+      return {};
+    }
+
+    exportKeyingMaterial(length, label, context) {
+      const material = __viper_tls_export_keying_material(this._session, length, label, context);
+      return Buffer.from(material.buffer);
+    }
+
+    renegotiate(options, callback) {
+      const err = new Error("TLS session renegotiation is not supported");
+      err.code = "ERR_TLS_RENEGOTIATION_DISABLED";
+      if (callback) queueMicrotask(() => callback(err));
+      return false;
+    }
+
+    disableRenegotiation() {}
+
+    setMaxSendFragment() {
+      return true;
+    }
+
+    enableTrace() {}
+  }
+
+  class Server extends net.Server {
+    constructor(options, secureConnectionListener) {
+      if (typeof options === "function") {
+        secureConnectionListener = options;
+        options = {};
+      }
+      options = options ?? {};
+      super({ allowHalfOpen: options.allowHalfOpen, pauseOnConnect: options.pauseOnConnect });
+      this._tlsOptions = options;
+      this._contexts = [];
+      this.setSecureContext(options);
+
+      this.on("connection", (socket) => this._wrap(socket));
+      if (secureConnectionListener) {
+        this.on("secureConnection", secureConnectionListener);
+      }
+    }
+
+    _wrap(transport) {
+      const options = this._tlsOptions;
+      const socket = new TLSSocket(transport, {
+        isServer: true,
+        server: this,
+        secureContext: this._secureContext,
+        SNICallback: options.SNICallback,
+        ALPNProtocols: options.ALPNProtocols,
+        ALPNCallback: options.ALPNCallback,
+        requestCert: options.requestCert,
+        rejectUnauthorized: options.rejectUnauthorized,
+        allowHalfOpen: this.allowHalfOpen,
+      });
+      socket.once("secure", () => this.emit("secureConnection", socket));
+    }
+
+    _contextFor(servername) {
+      if (servername) {
+        for (const [pattern, context] of this._contexts) {
+          if (pattern.test(servername)) return context;
+        }
+      }
+      return this._secureContext;
+    }
+
+    addContext(hostname, context) {
+      const pattern = new RegExp(
+        "^" + hostname.replace(/[.+?^${}()|[\]\\]/g, "\\$&").replace(/\*/g, "[^.]+") + "$",
+        "i",
+      );
+      const secureContext = context instanceof SecureContext ? context : createSecureContext(context);
+      this._contexts.push([pattern, secureContext]);
+    }
+
+    setSecureContext(options) {
+      this._secureContext = options.secureContext ?? createSecureContext(options);
+    }
+
+    getTicketKeys() {
+      return Buffer.alloc(48);
+    }
+
+    setTicketKeys() {}
+  }
+
+  function createServer(options, secureConnectionListener) {
+    return new Server(options, secureConnectionListener);
+  }
+
+  // connect(options[, cb]), connect(port[, host][, options][, cb]) or
+  // connect(path[, options][, cb])
+  function normalizeConnectArgs(args) {
+    const callback = typeof args[args.length - 1] === "function" ? args.pop() : undefined;
+    let options;
+    if (typeof args[0] === "object" && args[0] !== null) {
+      options = { ...args[0] };
+    } else if (typeof args[0] === "string" && !/^\d+$/.test(args[0])) {
+      options = { ...(args[1] ?? {}), path: args[0] };
+    } else {
+      const extra = args.find((arg, i) => i > 0 && typeof arg === "object" && arg !== null);
+      options = { ...extra, port: args[0] };
+      if (typeof args[1] === "string") options.host = args[1];
+    }
+    return [options, callback];
+  }
+
+  function connect(...args) {
+    const [options, callback] = normalizeConnectArgs(args);
+    const socket = new TLSSocket(options.socket, { ...options, isServer: false });
+    if (callback) socket.once("secureConnect", callback);
+    if (!options.socket) socket._parent.connect(options);
+    return socket;
+  }
+
+  const tls = {
+    connect,
+    createServer,
+    createSecureContext,
+    checkServerIdentity,
+    getCiphers: () => [...CIPHERS],
+    Server,
+    TLSSocket,
+    SecureContext,
+    DEFAULT_MIN_VERSION: "TLSv1.2",
+    DEFAULT_MAX_VERSION: "TLSv1.3",
+    DEFAULT_ECDH_CURVE: "auto",
+    DEFAULT_CIPHERS: CIPHERS.slice(0, 3).map((name) => name.toUpperCase()).join(":"),
+    CLIENT_RENEG_LIMIT: 3,
+    CLIENT_RENEG_WINDOW: 600,
+  };
+
+  globalThis.tls = tls;
+})();
@@ -2,11 +2,20 @@
 //!
 //! Provides Node.js compatible tty module with native performance.
 //! Uses direct system calls for maximum speed.
+//!
+//! `tty.ReadStream` is also what `process.stdin` is: a Readable fed by a
+//! thread that reads fd 0 and wakes the event loop when bytes arrive, with
+//! `setRawMode()` switching the terminal to raw input.
 
 use boa_engine::{
     Context, JsArgs, JsNativeError, JsObject, JsResult, JsValue, NativeFunction, js_string,
-    object::ObjectInitializer, property::Attribute,
+    object::ObjectInitializer, object::builtins::JsUint8Array, property::Attribute,
 };
+use std::collections::VecDeque;
+use std::io::Read;
+use std::sync::{Condvar, Mutex};
+
+use super::event_loop::{self, SourceNotifier};
 
 #[cfg(unix)]
 use std::os::unix::io::RawFd;
@@ -83,6 +92,204 @@ fn get_terminal_size() -> Option<(u16, u16)> {
     }
 }
 
+/// Bytes the reader thread holds before it waits for JS to catch up
+const STDIN_HIGH_WATER_MARK: usize = 1 << 20;
+
+/// Input read from fd 0 that JS hasn't taken yet
+struct StdinQueue {
+    /// Chunks in arrival order; `None` marks end of input
+    chunks: VecDeque<Option<Vec<u8>>>,
+    buffered: usize,
+    /// Source of the runtime currently reading stdin
+    notifier: Option<SourceNotifier>,
+    started: bool,
+}
+
+static STDIN: Mutex<StdinQueue> = Mutex::new(StdinQueue {
+    chunks: VecDeque::new(),
+    buffered: 0,
+    notifier: None,
+    started: false,
+});
+
+/// Signalled when JS takes a chunk off a full queue
+static STDIN_DRAINED: Condvar = Condvar::new();
+
+/// Read fd 0 until end of input, queueing what arrives
+///
+/// There is one reader for the whole process; a blocking read can't be
+/// interrupted, so it runs until stdin closes.
+fn spawn_stdin_reader() {
+    let _ = std::thread::Builder::new()
+        .name("viper-stdin".into())
+        .spawn(|| {
+            let mut stdin = std::io::stdin();
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let chunk = match stdin.read(&mut buf) {
+                    Ok(0) => None,
+                    Ok(n) => Some(buf[..n].to_vec()),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(_) => None,
+                };
+                let eof = chunk.is_none();
+
+                let mut queue = STDIN.lock().unwrap();
+                queue.buffered += chunk.as_ref().map_or(0, Vec::len);
+                queue.chunks.push_back(chunk);
+                if let Some(notifier) = &queue.notifier {
+                    notifier.notify();
+                }
+                if eof {
+                    return;
+                }
+                while queue.buffered >= STDIN_HIGH_WATER_MARK {
+                    queue = STDIN_DRAINED.wait(queue).unwrap();
+                }
+            }
+        });
+}
+
+/// Terminal and its settings from before the first `setRawMode(true)`
+#[cfg(unix)]
+static SAVED_MODE: Mutex<Option<(i32, libc::termios)>> = Mutex::new(None);
+
+/// Switch `fd` between raw input (no echo, no line buffering, no signal
+/// keys) and the mode it had before
+#[cfg(unix)]
+fn set_raw_mode(fd: i32, raw: bool) -> std::io::Result<()> {
+    let mut saved = SAVED_MODE.lock().unwrap();
+    let (fd, mode) = if raw {
+        let mut mode: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut mode) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if saved.is_none() {
+            *saved = Some((fd, mode));
+        }
+        // The same flags as libuv's UV_TTY_MODE_RAW; output keeps its processing
+        mode.c_iflag &= !(libc::BRKINT | libc::ICRNL | libc::INPCK | libc::ISTRIP | libc::IXON);
+        mode.c_cflag |= libc::CS8;
+        mode.c_lflag &= !(libc::ECHO | libc::ICANON | libc::IEXTEN | libc::ISIG);
+        mode.c_cc[libc::VMIN] = 1;
+        mode.c_cc[libc::VTIME] = 0;
+        (fd, mode)
+    } else {
+        match saved.take() {
+            Some(saved) => saved,
+            None => return Ok(()),
+        }
+    };
+    if unsafe { libc::tcsetattr(fd, libc::TCSADRAIN, &mode) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Console input mode from before the first `setRawMode(true)`
+#[cfg(windows)]
+static SAVED_MODE: Mutex<Option<u32>> = Mutex::new(None);
+
+#[cfg(windows)]
+fn set_raw_mode(fd: i32, raw: bool) -> std::io::Result<()> {
+    use windows_sys::Win32::System::Console::{
+        ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT,
+        ENABLE_VIRTUAL_TERMINAL_INPUT, GetConsoleMode, GetStdHandle, STD_INPUT_HANDLE,
+        SetConsoleMode,
+    };
+
+    if fd != 0 {
+        return Err(std::io::Error::from(std::io::ErrorKind::Unsupported));
+    }
+    let handle = unsafe { GetStdHandle(STD_INPUT_HANDLE) };
+    let mut saved = SAVED_MODE.lock().unwrap();
+    let mode = if raw {
+        let mut mode: u32 = 0;
+        if unsafe { GetConsoleMode(handle, &mut mode) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if saved.is_none() {
+            *saved = Some(mode);
+        }
+        (mode & !(ENABLE_ECHO_INPUT | ENABLE_LINE_INPUT | ENABLE_PROCESSED_INPUT))
+            | ENABLE_VIRTUAL_TERMINAL_INPUT
+    } else {
+        match saved.take() {
+            Some(mode) => mode,
+            None => return Ok(()),
+        }
+    };
+    if unsafe { SetConsoleMode(handle, mode) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Leave the terminal in the mode the first `setRawMode(true)` found it in
+///
+/// `process.exit()` and the CLI call this on their way out, since a process
+/// that ends while stdin is raw would otherwise leave the shell without echo.
+pub fn restore_terminal() {
+    let _ = set_raw_mode(0, false);
+}
+
+/// Register the stdin and raw mode natives behind `tty.ReadStream`
+fn register_stdin_natives(context: &mut Context) -> JsResult<()> {
+    // __viper_stdin_start(source) - deliver stdin to this loop source
+    let start_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let source = args.get_or_undefined(0).to_u32(context)?;
+        let notifier = event_loop::current(context)?.notifier(source);
+        let mut queue = STDIN.lock().unwrap();
+        if !queue.chunks.is_empty() {
+            notifier.notify();
+        }
+        queue.notifier = Some(notifier);
+        if !queue.started {
+            queue.started = true;
+            spawn_stdin_reader();
+        }
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_stdin_start"), 1, start_fn)?;
+
+    // __viper_stdin_read() -> Uint8Array, null at end of input, or undefined
+    // when nothing has arrived yet
+    let read_fn = NativeFunction::from_fn_ptr(|_this, _args, context| {
+        let chunk = {
+            let mut queue = STDIN.lock().unwrap();
+            let Some(chunk) = queue.chunks.pop_front() else {
+                return Ok(JsValue::undefined());
+            };
+            match chunk {
+                Some(bytes) => {
+                    queue.buffered -= bytes.len();
+                    STDIN_DRAINED.notify_one();
+                    bytes
+                }
+                None => {
+                    // Later readers see the end too
+                    queue.chunks.push_front(None);
+                    return Ok(JsValue::null());
+                }
+            }
+        };
+        Ok(JsUint8Array::from_iter(chunk, context)?.into())
+    });
+    context.register_global_callable(js_string!("__viper_stdin_read"), 0, read_fn)?;
+
+    // __viper_tty_set_raw_mode(fd, raw)
+    let raw_mode_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let fd = args.get_or_undefined(0).to_i32(context)?;
+        let raw = args.get_or_undefined(1).to_boolean();
+        set_raw_mode(fd, raw)
+            .map_err(|e| JsNativeError::error().with_message(format!("setRawMode EIO: {}", e)))?;
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_tty_set_raw_mode"), 2, raw_mode_fn)?;
+
+    Ok(())
+}
+
 /// Register the tty module
 pub fn register_tty_module(context: &mut Context) -> JsResult<()> {
     // tty.isatty(fd) - Native Rust, blazing fast
@@ -100,6 +307,8 @@ pub fn register_tty_module(context: &mut Context) -> JsResult<()> {
     // Get terminal size for WriteStream
     let (columns, rows) = get_terminal_size().unwrap_or((80, 24));
 
+    register_stdin_natives(context)?;
+
     // Create ReadStream class
     let read_stream_class = create_read_stream_class(context)?;
 
@@ -131,54 +340,86 @@ pub fn register_tty_module(context: &mut Context) -> JsResult<()> {
 
 /// Create the ReadStream class
 fn create_read_stream_class(context: &mut Context) -> JsResult<JsObject> {
-    // ReadStream constructor
+    // ReadStream: a Readable over fd 0, woken by a loop source that is only
+    // ref'd while someone is waiting for input
     let read_stream_code = r#"
         (function() {
-            class ReadStream {
+            const { Readable } = globalThis.stream;
+
+            class ReadStream extends Readable {
                 constructor(fd, options) {
+                    super(options);
                     this.fd = fd || 0;
                     this.isRaw = false;
-                    this.isTTY = true;
-                    this._events = {};
+                    this.isTTY = __tty_isatty(this.fd);
+                    this._source = null;
+                    this._wanted = false;
+                    this._restoreMode = () => this.setRawMode(false);
                 }
 
                 setRawMode(mode) {
-                    this.isRaw = !!mode;
+                    mode = !!mode;
+                    if (mode === this.isRaw) return this;
+                    __viper_tty_set_raw_mode(this.fd, mode);
+                    this.isRaw = mode;
+                    // Leave the terminal as it was found
+                    if (mode) process.on('exit', this._restoreMode);
+                    else process.off('exit', this._restoreMode);
                     return this;
                 }
 
-                on(event, fn) {
-                    if (!this._events[event]) this._events[event] = [];
-                    this._events[event].push(fn);
-                    return this;
+                _read() {
+                    if (this._source === null) {
+                        this._source = __viper_loop_source(() => this._drain());
+                        __viper_stdin_start(this._source);
+                    }
+                    this._wanted = true;
+                    this._updateRef();
+                    this._drain();
                 }
 
-                once(event, fn) {
-                    const wrapper = (...args) => {
-                        this.off(event, wrapper);
-                        fn.apply(this, args);
-                    };
-                    return this.on(event, wrapper);
+                _drain() {
+                    if (this._source === null) return;
+                    let chunk;
+                    while (this._wanted && (chunk = __viper_stdin_read()) !== undefined) {
+                        if (chunk === null) {
+                            this._closeSource();
+                            this.push(null);
+                            return;
+                        }
+                        this._wanted = this.push(Buffer.from(chunk.buffer, chunk.byteOffset, chunk.byteLength));
+                    }
+                    this._updateRef();
                 }
 
-                off(event, fn) {
-                    if (this._events[event]) {
-                        this._events[event] = this._events[event].filter(f => f !== fn);
-                    }
+                _updateRef() {
+                    if (this._source === null) return;
+                    __viper_loop_ref(this._source, this._wanted && !this.isPaused());
+                }
+
+                _closeSource() {
+                    if (this._source === null) return;
+                    __viper_loop_close(this._source);
+                    this._source = null;
+                }
+
+                pause() {
+                    super.pause();
+                    this._updateRef();
                     return this;
                 }
 
-                emit(event, ...args) {
-                    if (this._events[event]) {
-                        this._events[event].forEach(fn => fn.apply(this, args));
-                    }
+                resume() {
+                    super.resume();
+                    this._updateRef();
                     return this;
                 }
 
-                read() { return null; }
-                pause() { return this; }
-                resume() { return this; }
-                destroy() { return this; }
+                _destroy(err, callback) {
+                    this._closeSource();
+                    if (this.isRaw) this.setRawMode(false);
+                    callback(err);
+                }
             }
 
             return ReadStream;
@@ -255,16 +496,13 @@ fn create_write_stream_class(context: &mut Context, columns: u16, rows: u16) ->
 
                 clearLine(dir, callback) {{
                     // ANSI escape codes for clearing line
-                    const codes = {{ '-1': '\\x1b[1K', '0': '\\x1b[2K', '1': '\\x1b[0K' }};
-                    // In real implementation, would write to stream
-                    if (callback) setTimeout(callback, 0);
-                    return true;
+                    const code = dir < 0 ? '\x1b[1K' : dir > 0 ? '\x1b[0K' : '\x1b[2K';
+                    return this.write(code, callback);
                 }}
 
                 clearScreenDown(callback) {{
                     // ANSI: clear from cursor to end of screen
-                    if (callback) setTimeout(callback, 0);
-                    return true;
+                    return this.write('\x1b[0J', callback);
                 }}
 
                 cursorTo(x, y, callback) {{
@@ -272,13 +510,15 @@ fn create_write_stream_class(context: &mut Context, columns: u16, rows: u16) ->
                         callback = y;
                         y = undefined;
                     }}
-                    if (callback) setTimeout(callback, 0);
-                    return true;
+                    const code = y === undefined ? `\x1b[${{x + 1}}G` : `\x1b[${{y + 1}};${{x + 1}}H`;
+                    return this.write(code, callback);
                 }}
 
                 moveCursor(dx, dy, callback) {{
-                    if (callback) setTimeout(callback, 0);
-                    return true;
+                    let code = '';
+                    if (dx) code += dx < 0 ? `\x1b[${{-dx}}D` : `\x1b[${{dx}}C`;
+                    if (dy) code += dy < 0 ? `\x1b[${{-dy}}A` : `\x1b[${{dy}}B`;
+                    return this.write(code, callback);
                 }}
 
                 on(event, fn) {{
@@ -309,7 +549,13 @@ fn create_write_stream_class(context: &mut Context, columns: u16, rows: u16) ->
                     return this;
                 }}
 
-                write(data) {{ return true; }}
+                write(data, encoding, callback) {{
+                    if (typeof encoding === 'function') callback = encoding;
+                    if (this.fd === 2) __viper_stderr_write(String(data));
+                    else __viper_stdout_write(String(data));
+                    if (callback) setTimeout(callback, 0);
+                    return true;
+                }}
                 end() {{}}
                 destroy() {{}}
             }}
@@ -341,3 +587,56 @@ fn create_write_stream_class(context: &mut Context, columns: u16, rows: u16) ->
             .into()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(unix)]
+    use super::*;
+    use crate::runtime::Runtime;
+
+    #[test]
+    fn test_process_stdin() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            const { Readable } = require('node:stream');
+            const { ReadStream } = require('node:tty');
+            [process.stdin instanceof Readable, process.stdin instanceof ReadStream, typeof process.stdin.setRawMode].join()
+        "#;
+        let result = runtime.eval(code, "test.js").unwrap();
+        assert_eq!(runtime.value_to_string(&result), "true,true,function");
+    }
+
+    /// The local flags of the terminal behind `fd`
+    #[cfg(unix)]
+    fn local_flags(fd: i32) -> libc::tcflag_t {
+        let mut mode: libc::termios = unsafe { std::mem::zeroed() };
+        assert_eq!(unsafe { libc::tcgetattr(fd, &mut mode) }, 0);
+        mode.c_lflag
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_restore_terminal() {
+        // The terminal side of a fresh pseudo-terminal echoes like a shell's
+        let master = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+        assert!(master >= 0);
+        assert_eq!(unsafe { libc::grantpt(master) }, 0);
+        assert_eq!(unsafe { libc::unlockpt(master) }, 0);
+        let name = unsafe { libc::ptsname(master) };
+        let fd = unsafe { libc::open(name, libc::O_RDWR | libc::O_NOCTTY) };
+        assert!(fd >= 0);
+        let cooked = local_flags(fd);
+        assert_ne!(cooked & libc::ECHO, 0);
+
+        set_raw_mode(fd, true).unwrap();
+        assert_eq!(local_flags(fd) & (libc::ECHO | libc::ICANON), 0);
+        restore_terminal();
+        assert_eq!(local_flags(fd), cooked);
+        assert!(SAVED_MODE.lock().unwrap().is_none());
+
+        unsafe {
+            libc::close(fd);
+            libc::close(master);
+        }
+    }
+}
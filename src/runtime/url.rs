@@ -148,33 +148,29 @@ impl UrlSearchParamsData {
     }
 }
 
-/// URL decode (percent-decode) a string
+/// URL decode (percent-decode) a string; escaped bytes are read as UTF-8
 fn url_decode(s: &str) -> String {
-    let mut result = String::with_capacity(s.len());
-    let mut bytes = s.bytes().peekable();
-
-    while let Some(b) = bytes.next() {
-        if b == b'%' {
-            let hex: Vec<u8> = bytes.by_ref().take(2).collect();
-            if hex.len() == 2 {
-                let hex_str = String::from_utf8_lossy(&hex);
-                if let Ok(byte) = u8::from_str_radix(&hex_str, 16) {
-                    result.push(byte as char);
-                    continue;
-                }
-            }
-            result.push('%');
-            for h in hex {
-                result.push(h as char);
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
             }
-        } else if b == b'+' {
-            result.push(' ');
-        } else {
-            result.push(b as char);
+            (b'+', _) => decoded.push(b' '),
+            (b, _) => decoded.push(b),
         }
+        i += 1;
     }
-
-    result
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
 /// URL encode a string for use in query strings
@@ -1058,11 +1054,11 @@ fn parse_query_to_object(query: &str, context: &mut Context) -> JsResult<JsValue
         }
 
         let (key, value) = if let Some(eq_idx) = pair.find('=') {
-            let k = percent_decode(&pair[..eq_idx]);
-            let v = percent_decode(&pair[eq_idx + 1..]);
+            let k = url_decode(&pair[..eq_idx]);
+            let v = url_decode(&pair[eq_idx + 1..]);
             (k, v)
         } else {
-            (percent_decode(pair), String::new())
+            (url_decode(pair), String::new())
         };
 
         // Check if key already exists
@@ -1095,32 +1091,6 @@ fn parse_query_to_object(query: &str, context: &mut Context) -> JsResult<JsValue
     Ok(JsValue::from(result))
 }
 
-/// Percent-decode a string
-fn percent_decode(s: &str) -> String {
-    let mut result = String::with_capacity(s.len());
-    let mut chars = s.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        if c == '%' {
-            let hex: String = chars.by_ref().take(2).collect();
-            if hex.len() == 2 {
-                if let Ok(byte) = u8::from_str_radix(&hex, 16) {
-                    result.push(byte as char);
-                    continue;
-                }
-            }
-            result.push('%');
-            result.push_str(&hex);
-        } else if c == '+' {
-            result.push(' ');
-        } else {
-            result.push(c);
-        }
-    }
-
-    result
-}
-
 /// url.format(urlObject)
 /// Format a URL object into a URL string
 fn url_format(_this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
@@ -1460,7 +1430,7 @@ fn file_url_to_path_impl(url: &str) -> JsResult<String> {
     };
 
     // Percent-decode the path
-    let decoded = percent_decode(path_part);
+    let decoded = url_decode(path_part);
 
     // Handle Windows paths
     #[cfg(windows)]
@@ -1506,7 +1476,7 @@ fn path_to_file_url(_this: &JsValue, args: &[JsValue], context: &mut Context) ->
 }
 
 /// Implementation of path to file URL conversion
-fn path_to_file_url_impl(path: &str) -> String {
+pub(crate) fn path_to_file_url_impl(path: &str) -> String {
     let mut url = String::from("file://");
 
     #[cfg(windows)]
@@ -1546,8 +1516,10 @@ fn path_to_file_url_impl(path: &str) -> String {
 
     #[cfg(not(windows))]
     {
-        // Unix path
-        url.push('/');
+        // Unix path; absolute paths bring their own leading slash
+        if !path.starts_with('/') {
+            url.push('/');
+        }
         for c in path.chars() {
             match c {
                 '#' => url.push_str("%23"),
@@ -0,0 +1,152 @@
+//! Web Streams API - WHATWG streams
+//!
+//! Implementation of the Streams Standard including:
+//! - ReadableStream - default and byte streams, BYOB readers, tee, pipes
+//! - WritableStream - sinks with backpressure and abort signals
+//! - TransformStream - pipe-through transforms
+//! - ByteLengthQueuingStrategy and CountQueuingStrategy
+//! - TextEncoderStream and TextDecoderStream
+//!
+//! Also installs the helpers fetch, the server, fs and the `stream` module
+//! use to expose bodies and files as streams.
+
+use boa_engine::{Context, JsResult, Source};
+
+/// Register the Web Streams globals
+pub fn register_web_streams(context: &mut Context) -> JsResult<()> {
+    let web_streams_code = include_str!("web_streams_module.js");
+    let source = Source::from_bytes(web_streams_code.as_bytes());
+    context.eval(source)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::{Runtime, seen};
+
+    #[test]
+    fn test_byob_reader() {
+        let code = r#"
+            const bytes = new ReadableStream({
+                type: 'bytes',
+                start(c) { c.enqueue(new Uint8Array([1, 2, 3, 4, 5])); c.close(); },
+            });
+            const reader = bytes.getReader({ mode: 'byob' });
+            const first = await reader.read(new Uint8Array(3));
+            const second = await reader.read(new Uint8Array(3));
+            const done = await reader.read(new Uint8Array(3));
+            globalThis.seen = [first.value.join(''), second.value.join(''), done.done];
+        "#;
+        assert_eq!(seen(code), "123,45,true");
+    }
+
+    #[test]
+    fn test_tee_and_text_decoder_stream() {
+        let code = r#"
+            const source = new ReadableStream({
+                start(c) {
+                    c.enqueue(new Uint8Array([0xe2, 0x82]));
+                    c.enqueue(new Uint8Array([0xac]));
+                    c.close();
+                },
+            });
+            const [left, right] = source.tee();
+            let text = '';
+            for await (const chunk of left.pipeThrough(new TextDecoderStream())) text += chunk;
+            let count = 0;
+            for await (const _ of right) count++;
+            globalThis.seen = [text, count];
+        "#;
+        assert_eq!(seen(code), "€,2");
+    }
+
+    #[test]
+    fn test_node_stream_bridges() {
+        let code = r#"
+            import { Readable } from 'node:stream';
+            import { ReadableStream as WebReadable } from 'node:stream/web';
+
+            const web = Readable.toWeb(Readable.from(['hello', ' ', 'world']));
+            let text = '';
+            for await (const chunk of web.pipeThrough(new TextDecoderStream())) text += chunk;
+
+            const node = Readable.fromWeb(new ReadableStream({
+                start(c) { c.enqueue('a'); c.enqueue('b'); c.close(); },
+            }), { objectMode: true });
+            let chunks = '';
+            for await (const chunk of node) chunks += chunk;
+            globalThis.seen = [text, chunks, WebReadable === ReadableStream];
+        "#;
+        assert_eq!(seen(code), "hello world,ab,true");
+    }
+
+    #[test]
+    fn test_file_stream_to_web() {
+        let dir = std::env::temp_dir().join(format!("viper-streams-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("data.txt"), "hello world").unwrap();
+
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            import { Readable } from 'node:stream';
+            import fs from 'node:fs';
+
+            const path = import.meta.dirname + '/data.txt';
+            const web = Readable.toWeb(fs.createReadStream(path, { highWaterMark: 4 }));
+            let chunks = 0;
+            let file = '';
+            for await (const chunk of web.pipeThrough(new TextDecoderStream())) {
+                chunks++;
+                file += chunk;
+            }
+            globalThis.seen = [file, chunks];
+        "#;
+        let result = runtime.run(code, &dir.join("main.mjs").to_string_lossy());
+        let seen = runtime.eval("String(globalThis.seen)", "check.js");
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+        assert_eq!(runtime.value_to_string(&seen.unwrap()), "hello world,3");
+    }
+
+    #[test]
+    fn test_errored_stream() {
+        let code = r#"
+            let controller;
+            const stream = new ReadableStream({ start(c) { controller = c; } });
+            const reader = stream.getReader();
+            controller.error(new Error('broken'));
+            const read = await reader.read().then(() => 'ok', (e) => e.message);
+
+            let locked;
+            try { stream.getReader(); } catch (e) { locked = e.name; }
+
+            const failing = new ReadableStream({ pull() { throw new RangeError('pull failed'); } });
+            let aborted;
+            const sink = new WritableStream({ abort(reason) { aborted = reason.name; } });
+            const piped = await failing.pipeTo(sink).then(() => 'ok', (e) => e.name);
+            globalThis.seen = [read, locked, piped, aborted];
+        "#;
+        assert_eq!(seen(code), "broken,TypeError,RangeError,RangeError");
+    }
+
+    #[test]
+    fn test_abort_reasons() {
+        let code = r#"
+            let received;
+            const writable = new WritableStream({ abort(reason) { received = reason; } });
+            await writable.getWriter().abort('stopped');
+
+            const controller = new AbortController();
+            let cancelled;
+            const readable = new ReadableStream({
+                pull(c) { c.enqueue('x'); },
+                cancel(reason) { cancelled = reason; },
+            }, { highWaterMark: 0 });
+            const piping = readable.pipeTo(new WritableStream(), { signal: controller.signal });
+            controller.abort('enough');
+            const piped = await piping.then(() => 'ok', (e) => e);
+            globalThis.seen = [received, piped, cancelled];
+        "#;
+        assert_eq!(seen(code), "stopped,enough,enough");
+    }
+}
@@ -0,0 +1,2900 @@
+/**
+ * WHATWG Streams
+ *
+ * ReadableStream (default and byte streams, BYOB readers, tee, pipes and
+ * async iteration), WritableStream, TransformStream, the queuing strategies
+ * and TextEncoderStream/TextDecoderStream, following the algorithms of the
+ * Streams Standard. Internal slots live in one object per instance under
+ * `kState`; the public classes only validate arguments and unwrap it.
+ *
+ * Also installs the helpers the rest of the runtime uses to surface bodies
+ * as streams: `__viper_body_stream`, `__viper_body_bytes` and
+ * `__viper_file_stream`.
+ */
+(function () {
+  "use strict";
+
+  const kState = Symbol("viper.streams.state");
+
+  // ==========================================================================
+  // Helpers
+  // ==========================================================================
+
+  function deferred() {
+    const d = { state: "pending" };
+    d.promise = new Promise((resolve, reject) => {
+      d.resolve = (value) => {
+        if (d.state !== "pending") return;
+        d.state = "fulfilled";
+        resolve(value);
+      };
+      d.reject = (reason) => {
+        if (d.state !== "pending") return;
+        d.state = "rejected";
+        reject(reason);
+      };
+    });
+    return d;
+  }
+
+  function resolvedDeferred(value) {
+    const d = deferred();
+    d.resolve(value);
+    return d;
+  }
+
+  function rejectedDeferred(reason) {
+    const d = deferred();
+    d.reject(reason);
+    markHandled(d.promise);
+    return d;
+  }
+
+  function resolved(value) {
+    return Promise.resolve(value);
+  }
+
+  function rejected(reason) {
+    return Promise.reject(reason);
+  }
+
+  // Internal rejections nobody observes must not count as unhandled
+  function markHandled(promise) {
+    promise.then(undefined, () => {});
+  }
+
+  function promiseCall(fn, thisArg, ...args) {
+    try {
+      return resolved(fn.apply(thisArg, args));
+    } catch (e) {
+      return rejected(e);
+    }
+  }
+
+  function method(dict, name) {
+    const fn = dict[name];
+    if (fn === undefined) return undefined;
+    if (typeof fn !== "function") {
+      throw new TypeError(`${name} must be a function`);
+    }
+    return fn;
+  }
+
+  function internal(object, kind, name) {
+    const state = object !== null && typeof object === "object" ? object[kState] : undefined;
+    if (state === undefined || state.kind !== kind) {
+      throw new TypeError(`Illegal invocation: not a ${name}`);
+    }
+    return state;
+  }
+
+  function illegalConstructor() {
+    throw new TypeError("Illegal constructor");
+  }
+
+  function abortError() {
+    if (typeof DOMException === "function") {
+      return new DOMException("This operation was aborted", "AbortError");
+    }
+    const error = new Error("This operation was aborted");
+    error.name = "AbortError";
+    return error;
+  }
+
+  function isDetached(buffer) {
+    return buffer.detached === true;
+  }
+
+  // Moves the bytes to a new buffer; copies where transfer() is missing
+  function transferArrayBuffer(buffer) {
+    if (isDetached(buffer)) {
+      throw new TypeError("The ArrayBuffer is detached");
+    }
+    return typeof buffer.transfer === "function" ? buffer.transfer() : buffer.slice(0);
+  }
+
+  function toUint8Array(chunk) {
+    if (chunk instanceof ArrayBuffer) return new Uint8Array(chunk);
+    if (ArrayBuffer.isView(chunk)) {
+      return new Uint8Array(chunk.buffer, chunk.byteOffset, chunk.byteLength);
+    }
+    throw new TypeError("The chunk must be an ArrayBuffer or ArrayBufferView");
+  }
+
+  function concatBytes(chunks, length) {
+    const bytes = new Uint8Array(length);
+    let offset = 0;
+    for (const chunk of chunks) {
+      bytes.set(chunk, offset);
+      offset += chunk.byteLength;
+    }
+    return bytes;
+  }
+
+  // ==========================================================================
+  // Queues and strategies
+  // ==========================================================================
+
+  function dequeueValue(container) {
+    const pair = container.queue.shift();
+    container.queueTotalSize = Math.max(0, container.queueTotalSize - pair.size);
+    return pair.value;
+  }
+
+  function enqueueValueWithSize(container, value, size) {
+    size = Number(size);
+    if (!(size >= 0) || size === Infinity) {
+      throw new RangeError("The size of a chunk must be a finite, non-negative number");
+    }
+    container.queue.push({ value, size });
+    container.queueTotalSize += size;
+  }
+
+  function peekQueueValue(container) {
+    return container.queue[0].value;
+  }
+
+  function resetQueue(container) {
+    container.queue = [];
+    container.queueTotalSize = 0;
+  }
+
+  function extractHighWaterMark(strategy, defaultHWM) {
+    const hwm = strategy === undefined || strategy === null ? undefined : strategy.highWaterMark;
+    if (hwm === undefined) return defaultHWM;
+    const value = Number(hwm);
+    if (Number.isNaN(value) || value < 0) {
+      throw new RangeError("highWaterMark must be a non-negative number");
+    }
+    return value;
+  }
+
+  function extractSizeAlgorithm(strategy) {
+    const size = strategy === undefined || strategy === null ? undefined : strategy.size;
+    if (size === undefined) return () => 1;
+    if (typeof size !== "function") {
+      throw new TypeError("size must be a function");
+    }
+    return (chunk) => size(chunk);
+  }
+
+  const byteLengthSize = function size(chunk) {
+    return chunk.byteLength;
+  };
+
+  const countSize = function size() {
+    return 1;
+  };
+
+  class ByteLengthQueuingStrategy {
+    constructor(init) {
+      if (init === null || typeof init !== "object" || init.highWaterMark === undefined) {
+        throw new TypeError("ByteLengthQueuingStrategy requires a highWaterMark");
+      }
+      this[kState] = { kind: "byte-length-strategy", highWaterMark: Number(init.highWaterMark) };
+    }
+
+    get highWaterMark() {
+      return internal(this, "byte-length-strategy", "ByteLengthQueuingStrategy").highWaterMark;
+    }
+
+    get size() {
+      internal(this, "byte-length-strategy", "ByteLengthQueuingStrategy");
+      return byteLengthSize;
+    }
+  }
+
+  class CountQueuingStrategy {
+    constructor(init) {
+      if (init === null || typeof init !== "object" || init.highWaterMark === undefined) {
+        throw new TypeError("CountQueuingStrategy requires a highWaterMark");
+      }
+      this[kState] = { kind: "count-strategy", highWaterMark: Number(init.highWaterMark) };
+    }
+
+    get highWaterMark() {
+      return internal(this, "count-strategy", "CountQueuingStrategy").highWaterMark;
+    }
+
+    get size() {
+      internal(this, "count-strategy", "CountQueuingStrategy");
+      return countSize;
+    }
+  }
+
+  // ==========================================================================
+  // ReadableStream
+  // ==========================================================================
+
+  function initializeReadableStream(object) {
+    const stream = {
+      kind: "readable",
+      object,
+      state: "readable",
+      reader: undefined,
+      storedError: undefined,
+      disturbed: false,
+      controller: undefined,
+    };
+    object[kState] = stream;
+    return stream;
+  }
+
+  function createReadableStream(startAlgorithm, pullAlgorithm, cancelAlgorithm, hwm = 1, size = () => 1) {
+    const object = Object.create(ReadableStream.prototype);
+    const stream = initializeReadableStream(object);
+    const controller = Object.create(ReadableStreamDefaultController.prototype);
+    setUpDefaultController(stream, controller, startAlgorithm, pullAlgorithm, cancelAlgorithm, hwm, size);
+    return object;
+  }
+
+  function createReadableByteStream(startAlgorithm, pullAlgorithm, cancelAlgorithm) {
+    const object = Object.create(ReadableStream.prototype);
+    const stream = initializeReadableStream(object);
+    const controller = Object.create(ReadableByteStreamController.prototype);
+    setUpByteController(stream, controller, startAlgorithm, pullAlgorithm, cancelAlgorithm, 0, undefined);
+    return object;
+  }
+
+  function isLocked(stream) {
+    return stream.reader !== undefined;
+  }
+
+  function hasDefaultReader(stream) {
+    return stream.reader !== undefined && stream.reader.kind === "default-reader";
+  }
+
+  function hasBYOBReader(stream) {
+    return stream.reader !== undefined && stream.reader.kind === "byob-reader";
+  }
+
+  function readableStreamCancel(stream, reason) {
+    stream.disturbed = true;
+    if (stream.state === "closed") return resolved(undefined);
+    if (stream.state === "errored") return rejected(stream.storedError);
+    readableStreamClose(stream);
+    const reader = stream.reader;
+    if (reader !== undefined && reader.kind === "byob-reader") {
+      const requests = reader.readIntoRequests;
+      reader.readIntoRequests = [];
+      for (const request of requests) request.close(undefined);
+    }
+    return stream.controller.cancelSteps(reason).then(() => undefined);
+  }
+
+  function readableStreamClose(stream) {
+    stream.state = "closed";
+    const reader = stream.reader;
+    if (reader === undefined) return;
+    reader.closed.resolve(undefined);
+    if (reader.kind === "default-reader") {
+      const requests = reader.readRequests;
+      reader.readRequests = [];
+      for (const request of requests) request.close();
+    }
+  }
+
+  function readableStreamError(stream, error) {
+    stream.state = "errored";
+    stream.storedError = error;
+    const reader = stream.reader;
+    if (reader === undefined) return;
+    reader.closed.reject(error);
+    markHandled(reader.closed.promise);
+    if (reader.kind === "default-reader") {
+      errorReadRequests(reader, error);
+    } else {
+      errorReadIntoRequests(reader, error);
+    }
+  }
+
+  function fulfillReadRequest(stream, chunk, done) {
+    const request = stream.reader.readRequests.shift();
+    if (done) request.close();
+    else request.chunk(chunk);
+  }
+
+  function fulfillReadIntoRequest(stream, chunk, done) {
+    const request = stream.reader.readIntoRequests.shift();
+    if (done) request.close(chunk);
+    else request.chunk(chunk);
+  }
+
+  function numReadRequests(stream) {
+    return stream.reader.readRequests.length;
+  }
+
+  function numReadIntoRequests(stream) {
+    return stream.reader.readIntoRequests.length;
+  }
+
+  // --------------------------------------------------------------------------
+  // Readers
+  // --------------------------------------------------------------------------
+
+  function readerGenericInitialize(reader, stream) {
+    reader.stream = stream;
+    stream.reader = reader;
+    if (stream.state === "readable") {
+      reader.closed = deferred();
+    } else if (stream.state === "closed") {
+      reader.closed = resolvedDeferred(undefined);
+    } else {
+      reader.closed = rejectedDeferred(stream.storedError);
+    }
+  }
+
+  function readerGenericRelease(reader) {
+    const stream = reader.stream;
+    const error = new TypeError("The reader was released");
+    if (stream.state === "readable") {
+      reader.closed.reject(error);
+      markHandled(reader.closed.promise);
+    } else {
+      reader.closed = rejectedDeferred(error);
+    }
+    stream.controller.releaseSteps();
+    stream.reader = undefined;
+    reader.stream = undefined;
+  }
+
+  function errorReadRequests(reader, error) {
+    const requests = reader.readRequests;
+    reader.readRequests = [];
+    for (const request of requests) request.error(error);
+  }
+
+  function errorReadIntoRequests(reader, error) {
+    const requests = reader.readIntoRequests;
+    reader.readIntoRequests = [];
+    for (const request of requests) request.error(error);
+  }
+
+  function acquireDefaultReader(stream) {
+    if (isLocked(stream)) throw new TypeError("The ReadableStream is locked");
+    const object = Object.create(ReadableStreamDefaultReader.prototype);
+    const reader = { kind: "default-reader", object, stream: undefined, closed: undefined, readRequests: [] };
+    object[kState] = reader;
+    readerGenericInitialize(reader, stream);
+    return reader;
+  }
+
+  function acquireBYOBReader(stream) {
+    if (isLocked(stream)) throw new TypeError("The ReadableStream is locked");
+    if (stream.controller.kind !== "byte-controller") {
+      throw new TypeError("A BYOB reader can only be used with a byte stream");
+    }
+    const object = Object.create(ReadableStreamBYOBReader.prototype);
+    const reader = { kind: "byob-reader", object, stream: undefined, closed: undefined, readIntoRequests: [] };
+    object[kState] = reader;
+    readerGenericInitialize(reader, stream);
+    return reader;
+  }
+
+  function defaultReaderRead(reader, request) {
+    const stream = reader.stream;
+    stream.disturbed = true;
+    if (stream.state === "closed") request.close();
+    else if (stream.state === "errored") request.error(stream.storedError);
+    else stream.controller.pullSteps(request);
+  }
+
+  function defaultReaderRelease(reader) {
+    readerGenericRelease(reader);
+    errorReadRequests(reader, new TypeError("The reader was released"));
+  }
+
+  function byobReaderRead(reader, view, min, request) {
+    const stream = reader.stream;
+    stream.disturbed = true;
+    if (stream.state === "errored") request.error(stream.storedError);
+    else byteControllerPullInto(stream.controller, view, min, request);
+  }
+
+  function byobReaderRelease(reader) {
+    readerGenericRelease(reader);
+    errorReadIntoRequests(reader, new TypeError("The reader was released"));
+  }
+
+  function readRequestFor(d) {
+    return {
+      chunk: (value) => d.resolve({ value, done: false }),
+      close: () => d.resolve({ value: undefined, done: true }),
+      error: (e) => d.reject(e),
+    };
+  }
+
+  class ReadableStreamDefaultReader {
+    constructor(stream) {
+      const reader = acquireDefaultReader(internal(stream, "readable", "ReadableStream"));
+      reader.object = this;
+      this[kState] = reader;
+    }
+
+    get closed() {
+      return internal(this, "default-reader", "ReadableStreamDefaultReader").closed.promise;
+    }
+
+    read() {
+      const reader = internal(this, "default-reader", "ReadableStreamDefaultReader");
+      if (reader.stream === undefined) {
+        return rejected(new TypeError("The reader has no stream"));
+      }
+      const d = deferred();
+      defaultReaderRead(reader, readRequestFor(d));
+      return d.promise;
+    }
+
+    releaseLock() {
+      const reader = internal(this, "default-reader", "ReadableStreamDefaultReader");
+      if (reader.stream !== undefined) defaultReaderRelease(reader);
+    }
+
+    cancel(reason) {
+      const reader = internal(this, "default-reader", "ReadableStreamDefaultReader");
+      if (reader.stream === undefined) {
+        return rejected(new TypeError("The reader has no stream"));
+      }
+      return readableStreamCancel(reader.stream, reason);
+    }
+  }
+
+  class ReadableStreamBYOBReader {
+    constructor(stream) {
+      const reader = acquireBYOBReader(internal(stream, "readable", "ReadableStream"));
+      reader.object = this;
+      this[kState] = reader;
+    }
+
+    get closed() {
+      return internal(this, "byob-reader", "ReadableStreamBYOBReader").closed.promise;
+    }
+
+    read(view, options = {}) {
+      const reader = internal(this, "byob-reader", "ReadableStreamBYOBReader");
+      if (!ArrayBuffer.isView(view)) {
+        return rejected(new TypeError("view must be an ArrayBufferView"));
+      }
+      if (view.byteLength === 0 || view.buffer.byteLength === 0) {
+        return rejected(new TypeError("view must not be empty"));
+      }
+      if (isDetached(view.buffer)) {
+        return rejected(new TypeError("view's buffer is detached"));
+      }
+      const min = options === null || options === undefined || options.min === undefined ? 1 : Number(options.min);
+      if (!(min > 0) || !Number.isInteger(min)) {
+        return rejected(new TypeError("min must be a positive integer"));
+      }
+      const length = view instanceof DataView ? view.byteLength : view.length;
+      if (min > length) {
+        return rejected(new RangeError("min must not exceed the view's length"));
+      }
+      if (reader.stream === undefined) {
+        return rejected(new TypeError("The reader has no stream"));
+      }
+      const d = deferred();
+      byobReaderRead(reader, view, min, {
+        chunk: (value) => d.resolve({ value, done: false }),
+        close: (value) => d.resolve({ value, done: true }),
+        error: (e) => d.reject(e),
+      });
+      return d.promise;
+    }
+
+    releaseLock() {
+      const reader = internal(this, "byob-reader", "ReadableStreamBYOBReader");
+      if (reader.stream !== undefined) byobReaderRelease(reader);
+    }
+
+    cancel(reason) {
+      const reader = internal(this, "byob-reader", "ReadableStreamBYOBReader");
+      if (reader.stream === undefined) {
+        return rejected(new TypeError("The reader has no stream"));
+      }
+      return readableStreamCancel(reader.stream, reason);
+    }
+  }
+
+  // --------------------------------------------------------------------------
+  // Default controller
+  // --------------------------------------------------------------------------
+
+  function setUpDefaultController(stream, object, startAlgorithm, pullAlgorithm, cancelAlgorithm, hwm, size) {
+    const controller = {
+      kind: "default-controller",
+      object,
+      stream,
+      queue: [],
+      queueTotalSize: 0,
+      started: false,
+      closeRequested: false,
+      pullAgain: false,
+      pulling: false,
+      strategyHWM: hwm,
+      strategySizeAlgorithm: size,
+      pullAlgorithm,
+      cancelAlgorithm,
+      cancelSteps(reason) {
+        resetQueue(controller);
+        const result = controller.cancelAlgorithm(reason);
+        defaultControllerClearAlgorithms(controller);
+        return result;
+      },
+      pullSteps(request) {
+        if (controller.queue.length > 0) {
+          const chunk = dequeueValue(controller);
+          if (controller.closeRequested && controller.queue.length === 0) {
+            defaultControllerClearAlgorithms(controller);
+            readableStreamClose(stream);
+          } else {
+            defaultControllerCallPullIfNeeded(controller);
+          }
+          request.chunk(chunk);
+        } else {
+          stream.reader.readRequests.push(request);
+          defaultControllerCallPullIfNeeded(controller);
+        }
+      },
+      releaseSteps() {},
+    };
+    object[kState] = controller;
+    stream.controller = controller;
+
+    const startResult = startAlgorithm();
+    resolved(startResult).then(
+      () => {
+        controller.started = true;
+        defaultControllerCallPullIfNeeded(controller);
+      },
+      (e) => defaultControllerError(controller, e),
+    );
+  }
+
+  function setUpDefaultControllerFromSource(stream, source, hwm, size) {
+    const object = Object.create(ReadableStreamDefaultController.prototype);
+    const start = method(source, "start");
+    const pull = method(source, "pull");
+    const cancel = method(source, "cancel");
+    setUpDefaultController(
+      stream,
+      object,
+      () => (start ? start.call(source, object) : undefined),
+      () => (pull ? promiseCall(pull, source, object) : resolved(undefined)),
+      (reason) => (cancel ? promiseCall(cancel, source, reason) : resolved(undefined)),
+      hwm,
+      size,
+    );
+  }
+
+  function defaultControllerCallPullIfNeeded(controller) {
+    if (!defaultControllerShouldCallPull(controller)) return;
+    if (controller.pulling) {
+      controller.pullAgain = true;
+      return;
+    }
+    controller.pulling = true;
+    controller.pullAlgorithm().then(
+      () => {
+        controller.pulling = false;
+        if (controller.pullAgain) {
+          controller.pullAgain = false;
+          defaultControllerCallPullIfNeeded(controller);
+        }
+      },
+      (e) => defaultControllerError(controller, e),
+    );
+  }
+
+  function defaultControllerShouldCallPull(controller) {
+    const stream = controller.stream;
+    if (!defaultControllerCanCloseOrEnqueue(controller)) return false;
+    if (!controller.started) return false;
+    if (isLocked(stream) && numReadRequests(stream) > 0) return true;
+    return defaultControllerGetDesiredSize(controller) > 0;
+  }
+
+  function defaultControllerClearAlgorithms(controller) {
+    controller.pullAlgorithm = undefined;
+    controller.cancelAlgorithm = undefined;
+    controller.strategySizeAlgorithm = undefined;
+  }
+
+  function defaultControllerCanCloseOrEnqueue(controller) {
+    return !controller.closeRequested && controller.stream.state === "readable";
+  }
+
+  function defaultControllerGetDesiredSize(controller) {
+    const state = controller.stream.state;
+    if (state === "errored") return null;
+    if (state === "closed") return 0;
+    return controller.strategyHWM - controller.queueTotalSize;
+  }
+
+  function defaultControllerHasBackpressure(controller) {
+    return !defaultControllerShouldCallPull(controller);
+  }
+
+  function defaultControllerClose(controller) {
+    if (!defaultControllerCanCloseOrEnqueue(controller)) return;
+    controller.closeRequested = true;
+    if (controller.queue.length === 0) {
+      defaultControllerClearAlgorithms(controller);
+      readableStreamClose(controller.stream);
+    }
+  }
+
+  function defaultControllerEnqueue(controller, chunk) {
+    if (!defaultControllerCanCloseOrEnqueue(controller)) return;
+    const stream = controller.stream;
+    if (isLocked(stream) && numReadRequests(stream) > 0) {
+      fulfillReadRequest(stream, chunk, false);
+    } else {
+      try {
+        const size = controller.strategySizeAlgorithm(chunk);
+        enqueueValueWithSize(controller, chunk, size);
+      } catch (e) {
+        defaultControllerError(controller, e);
+        throw e;
+      }
+    }
+    defaultControllerCallPullIfNeeded(controller);
+  }
+
+  function defaultControllerError(controller, error) {
+    if (controller.stream.state !== "readable") return;
+    resetQueue(controller);
+    defaultControllerClearAlgorithms(controller);
+    readableStreamError(controller.stream, error);
+  }
+
+  class ReadableStreamDefaultController {
+    constructor() {
+      illegalConstructor();
+    }
+
+    get desiredSize() {
+      return defaultControllerGetDesiredSize(
+        internal(this, "default-controller", "ReadableStreamDefaultController"),
+      );
+    }
+
+    close() {
+      const controller = internal(this, "default-controller", "ReadableStreamDefaultController");
+      if (!defaultControllerCanCloseOrEnqueue(controller)) {
+        throw new TypeError("The stream is not in a state that permits close");
+      }
+      defaultControllerClose(controller);
+    }
+
+    enqueue(chunk) {
+      const controller = internal(this, "default-controller", "ReadableStreamDefaultController");
+      if (!defaultControllerCanCloseOrEnqueue(controller)) {
+        throw new TypeError("The stream is not in a state that permits enqueue");
+      }
+      defaultControllerEnqueue(controller, chunk);
+    }
+
+    error(e) {
+      defaultControllerError(internal(this, "default-controller", "ReadableStreamDefaultController"), e);
+    }
+  }
+
+  // --------------------------------------------------------------------------
+  // Byte stream controller
+  // --------------------------------------------------------------------------
+
+  function setUpByteController(stream, object, startAlgorithm, pullAlgorithm, cancelAlgorithm, hwm, autoAllocateChunkSize) {
+    const controller = {
+      kind: "byte-controller",
+      object,
+      stream,
+      queue: [],
+      queueTotalSize: 0,
+      pendingPullIntos: [],
+      byobRequest: null,
+      autoAllocateChunkSize,
+      started: false,
+      closeRequested: false,
+      pullAgain: false,
+      pulling: false,
+      strategyHWM: hwm,
+      pullAlgorithm,
+      cancelAlgorithm,
+      cancelSteps(reason) {
+        byteControllerClearPendingPullIntos(controller);
+        resetQueue(controller);
+        const result = controller.cancelAlgorithm(reason);
+        byteControllerClearAlgorithms(controller);
+        return result;
+      },
+      pullSteps(request) {
+        if (controller.queueTotalSize > 0) {
+          byteControllerFillReadRequestFromQueue(controller, request);
+          return;
+        }
+        const size = controller.autoAllocateChunkSize;
+        if (size !== undefined) {
+          let buffer;
+          try {
+            buffer = new ArrayBuffer(size);
+          } catch (e) {
+            request.error(e);
+            return;
+          }
+          controller.pendingPullIntos.push({
+            buffer,
+            bufferByteLength: size,
+            byteOffset: 0,
+            byteLength: size,
+            bytesFilled: 0,
+            minimumFill: 1,
+            elementSize: 1,
+            viewConstructor: Uint8Array,
+            readerType: "default",
+          });
+        }
+        stream.reader.readRequests.push(request);
+        byteControllerCallPullIfNeeded(controller);
+      },
+      releaseSteps() {
+        if (controller.pendingPullIntos.length > 0) {
+          const first = controller.pendingPullIntos[0];
+          first.readerType = "none";
+          controller.pendingPullIntos = [first];
+        }
+      },
+    };
+    object[kState] = controller;
+    stream.controller = controller;
+
+    const startResult = startAlgorithm();
+    resolved(startResult).then(
+      () => {
+        controller.started = true;
+        byteControllerCallPullIfNeeded(controller);
+      },
+      (e) => byteControllerError(controller, e),
+    );
+  }
+
+  function setUpByteControllerFromSource(stream, source, hwm) {
+    const object = Object.create(ReadableByteStreamController.prototype);
+    const start = method(source, "start");
+    const pull = method(source, "pull");
+    const cancel = method(source, "cancel");
+    let autoAllocateChunkSize = source.autoAllocateChunkSize;
+    if (autoAllocateChunkSize !== undefined) {
+      autoAllocateChunkSize = Number(autoAllocateChunkSize);
+      if (!Number.isInteger(autoAllocateChunkSize) || autoAllocateChunkSize <= 0) {
+        throw new TypeError("autoAllocateChunkSize must be a positive integer");
+      }
+    }
+    setUpByteController(
+      stream,
+      object,
+      () => (start ? start.call(source, object) : undefined),
+      () => (pull ? promiseCall(pull, source, object) : resolved(undefined)),
+      (reason) => (cancel ? promiseCall(cancel, source, reason) : resolved(undefined)),
+      hwm,
+      autoAllocateChunkSize,
+    );
+  }
+
+  function byteControllerCallPullIfNeeded(controller) {
+    if (!byteControllerShouldCallPull(controller)) return;
+    if (controller.pulling) {
+      controller.pullAgain = true;
+      return;
+    }
+    controller.pulling = true;
+    controller.pullAlgorithm().then(
+      () => {
+        controller.pulling = false;
+        if (controller.pullAgain) {
+          controller.pullAgain = false;
+          byteControllerCallPullIfNeeded(controller);
+        }
+      },
+      (e) => byteControllerError(controller, e),
+    );
+  }
+
+  function byteControllerShouldCallPull(controller) {
+    const stream = controller.stream;
+    if (stream.state !== "readable" || controller.closeRequested || !controller.started) {
+      return false;
+    }
+    if (hasDefaultReader(stream) && numReadRequests(stream) > 0) return true;
+    if (hasBYOBReader(stream) && numReadIntoRequests(stream) > 0) return true;
+    return byteControllerGetDesiredSize(controller) > 0;
+  }
+
+  function byteControllerGetDesiredSize(controller) {
+    const state = controller.stream.state;
+    if (state === "errored") return null;
+    if (state === "closed") return 0;
+    return controller.strategyHWM - controller.queueTotalSize;
+  }
+
+  function byteControllerClearAlgorithms(controller) {
+    controller.pullAlgorithm = undefined;
+    controller.cancelAlgorithm = undefined;
+  }
+
+  function byteControllerClearPendingPullIntos(controller) {
+    byteControllerInvalidateBYOBRequest(controller);
+    controller.pendingPullIntos = [];
+  }
+
+  function byteControllerInvalidateBYOBRequest(controller) {
+    if (controller.byobRequest === null) return;
+    const request = controller.byobRequest[kState];
+    request.controller = undefined;
+    request.view = null;
+    controller.byobRequest = null;
+  }
+
+  function byteControllerClose(controller) {
+    const stream = controller.stream;
+    if (controller.closeRequested || stream.state !== "readable") return;
+    if (controller.queueTotalSize > 0) {
+      controller.closeRequested = true;
+      return;
+    }
+    if (controller.pendingPullIntos.length > 0) {
+      const first = controller.pendingPullIntos[0];
+      if (first.bytesFilled % first.elementSize !== 0) {
+        const e = new TypeError("Insufficient bytes to fill elements in the given buffer");
+        byteControllerError(controller, e);
+        throw e;
+      }
+    }
+    byteControllerClearAlgorithms(controller);
+    readableStreamClose(stream);
+  }
+
+  function byteControllerEnqueue(controller, chunk) {
+    const stream = controller.stream;
+    if (controller.closeRequested || stream.state !== "readable") return;
+    const { buffer, byteOffset, byteLength } = chunk;
+    if (isDetached(buffer)) throw new TypeError("The chunk's buffer is detached");
+    const transferred = transferArrayBuffer(buffer);
+
+    if (controller.pendingPullIntos.length > 0) {
+      const first = controller.pendingPullIntos[0];
+      if (isDetached(first.buffer)) {
+        throw new TypeError("The BYOB request's buffer has been detached");
+      }
+      byteControllerInvalidateBYOBRequest(controller);
+      first.buffer = transferArrayBuffer(first.buffer);
+      if (first.readerType === "none") {
+        byteControllerEnqueueDetachedPullIntoToQueue(controller, first);
+      }
+    }
+
+    if (hasDefaultReader(stream)) {
+      byteControllerProcessReadRequestsUsingQueue(controller);
+      if (numReadRequests(stream) === 0) {
+        byteControllerEnqueueChunkToQueue(controller, transferred, byteOffset, byteLength);
+      } else {
+        if (controller.pendingPullIntos.length > 0) {
+          controller.pendingPullIntos.shift();
+        }
+        fulfillReadRequest(stream, new Uint8Array(transferred, byteOffset, byteLength), false);
+      }
+    } else if (hasBYOBReader(stream)) {
+      byteControllerEnqueueChunkToQueue(controller, transferred, byteOffset, byteLength);
+      const filled = byteControllerProcessPullIntosUsingQueue(controller);
+      for (const descriptor of filled) {
+        byteControllerCommitPullIntoDescriptor(stream, descriptor);
+      }
+    } else {
+      byteControllerEnqueueChunkToQueue(controller, transferred, byteOffset, byteLength);
+    }
+    byteControllerCallPullIfNeeded(controller);
+  }
+
+  function byteControllerEnqueueChunkToQueue(controller, buffer, byteOffset, byteLength) {
+    controller.queue.push({ buffer, byteOffset, byteLength });
+    controller.queueTotalSize += byteLength;
+  }
+
+  function byteControllerEnqueueClonedChunkToQueue(controller, buffer, byteOffset, byteLength) {
+    let clone;
+    try {
+      clone = buffer.slice(byteOffset, byteOffset + byteLength);
+    } catch (e) {
+      byteControllerError(controller, e);
+      throw e;
+    }
+    byteControllerEnqueueChunkToQueue(controller, clone, 0, byteLength);
+  }
+
+  function byteControllerEnqueueDetachedPullIntoToQueue(controller, descriptor) {
+    if (descriptor.bytesFilled > 0) {
+      byteControllerEnqueueClonedChunkToQueue(
+        controller,
+        descriptor.buffer,
+        descriptor.byteOffset,
+        descriptor.bytesFilled,
+      );
+    }
+    controller.pendingPullIntos.shift();
+  }
+
+  function byteControllerError(controller, error) {
+    const stream = controller.stream;
+    if (stream.state !== "readable") return;
+    byteControllerClearPendingPullIntos(controller);
+    resetQueue(controller);
+    byteControllerClearAlgorithms(controller);
+    readableStreamError(stream, error);
+  }
+
+  function byteControllerFillPullIntoDescriptorFromQueue(controller, descriptor) {
+    const maxBytesToCopy = Math.min(
+      controller.queueTotalSize,
+      descriptor.byteLength - descriptor.bytesFilled,
+    );
+    const maxBytesFilled = descriptor.bytesFilled + maxBytesToCopy;
+    let remaining = maxBytesToCopy;
+    let ready = false;
+    const maxAlignedBytes = maxBytesFilled - (maxBytesFilled % descriptor.elementSize);
+    if (maxAlignedBytes >= descriptor.minimumFill) {
+      remaining = maxAlignedBytes - descriptor.bytesFilled;
+      ready = true;
+    }
+    while (remaining > 0) {
+      const head = controller.queue[0];
+      const bytesToCopy = Math.min(remaining, head.byteLength);
+      const destStart = descriptor.byteOffset + descriptor.bytesFilled;
+      new Uint8Array(descriptor.buffer, destStart, bytesToCopy).set(
+        new Uint8Array(head.buffer, head.byteOffset, bytesToCopy),
+      );
+      if (head.byteLength === bytesToCopy) {
+        controller.queue.shift();
+      } else {
+        head.byteOffset += bytesToCopy;
+        head.byteLength -= bytesToCopy;
+      }
+      controller.queueTotalSize -= bytesToCopy;
+      descriptor.bytesFilled += bytesToCopy;
+      remaining -= bytesToCopy;
+    }
+    return ready;
+  }
+
+  function byteControllerFillReadRequestFromQueue(controller, request) {
+    const entry = controller.queue.shift();
+    controller.queueTotalSize -= entry.byteLength;
+    byteControllerHandleQueueDrain(controller);
+    request.chunk(new Uint8Array(entry.buffer, entry.byteOffset, entry.byteLength));
+  }
+
+  function byteControllerHandleQueueDrain(controller) {
+    if (controller.queueTotalSize === 0 && controller.closeRequested) {
+      byteControllerClearAlgorithms(controller);
+      readableStreamClose(controller.stream);
+    } else {
+      byteControllerCallPullIfNeeded(controller);
+    }
+  }
+
+  function byteControllerProcessPullIntosUsingQueue(controller) {
+    const filled = [];
+    while (controller.pendingPullIntos.length > 0 && controller.queueTotalSize > 0) {
+      const descriptor = controller.pendingPullIntos[0];
+      if (byteControllerFillPullIntoDescriptorFromQueue(controller, descriptor)) {
+        controller.pendingPullIntos.shift();
+        filled.push(descriptor);
+      }
+    }
+    return filled;
+  }
+
+  function byteControllerProcessReadRequestsUsingQueue(controller) {
+    const reader = controller.stream.reader;
+    while (reader.readRequests.length > 0 && controller.queueTotalSize > 0) {
+      byteControllerFillReadRequestFromQueue(controller, reader.readRequests.shift());
+    }
+  }
+
+  function byteControllerConvertPullIntoDescriptor(descriptor) {
+    const buffer = transferArrayBuffer(descriptor.buffer);
+    return new descriptor.viewConstructor(
+      buffer,
+      descriptor.byteOffset,
+      descriptor.bytesFilled / descriptor.elementSize,
+    );
+  }
+
+  function byteControllerCommitPullIntoDescriptor(stream, descriptor) {
+    const done = stream.state === "closed";
+    const view = byteControllerConvertPullIntoDescriptor(descriptor);
+    if (descriptor.readerType === "default") {
+      fulfillReadRequest(stream, view, done);
+    } else {
+      fulfillReadIntoRequest(stream, view, done);
+    }
+  }
+
+  function byteControllerPullInto(controller, view, min, request) {
+    const stream = controller.stream;
+    let elementSize = 1;
+    let viewConstructor = DataView;
+    if (!(view instanceof DataView)) {
+      elementSize = view.constructor.BYTES_PER_ELEMENT;
+      viewConstructor = view.constructor;
+    }
+    let buffer;
+    try {
+      buffer = transferArrayBuffer(view.buffer);
+    } catch (e) {
+      request.error(e);
+      return;
+    }
+    const descriptor = {
+      buffer,
+      bufferByteLength: buffer.byteLength,
+      byteOffset: view.byteOffset,
+      byteLength: view.byteLength,
+      bytesFilled: 0,
+      minimumFill: min * elementSize,
+      elementSize,
+      viewConstructor,
+      readerType: "byob",
+    };
+
+    if (controller.pendingPullIntos.length > 0) {
+      controller.pendingPullIntos.push(descriptor);
+      stream.reader.readIntoRequests.push(request);
+      return;
+    }
+    if (stream.state === "closed") {
+      request.close(new viewConstructor(descriptor.buffer, descriptor.byteOffset, 0));
+      return;
+    }
+    if (controller.queueTotalSize > 0) {
+      if (byteControllerFillPullIntoDescriptorFromQueue(controller, descriptor)) {
+        const filledView = byteControllerConvertPullIntoDescriptor(descriptor);
+        byteControllerHandleQueueDrain(controller);
+        request.chunk(filledView);
+        return;
+      }
+      if (controller.closeRequested) {
+        const e = new TypeError("Insufficient bytes to fill elements in the given buffer");
+        byteControllerError(controller, e);
+        request.error(e);
+        return;
+      }
+    }
+    controller.pendingPullIntos.push(descriptor);
+    stream.reader.readIntoRequests.push(request);
+    byteControllerCallPullIfNeeded(controller);
+  }
+
+  function byteControllerGetBYOBRequest(controller) {
+    if (controller.byobRequest === null && controller.pendingPullIntos.length > 0) {
+      const first = controller.pendingPullIntos[0];
+      const view = new Uint8Array(
+        first.buffer,
+        first.byteOffset + first.bytesFilled,
+        first.byteLength - first.bytesFilled,
+      );
+      const request = Object.create(ReadableStreamBYOBRequest.prototype);
+      request[kState] = { kind: "byob-request", controller, view };
+      controller.byobRequest = request;
+    }
+    return controller.byobRequest;
+  }
+
+  function byteControllerRespond(controller, bytesWritten) {
+    const first = controller.pendingPullIntos[0];
+    if (controller.stream.state === "closed") {
+      if (bytesWritten !== 0) {
+        throw new TypeError("bytesWritten must be 0 once the stream is closed");
+      }
+    } else {
+      if (bytesWritten === 0) {
+        throw new TypeError("bytesWritten must be greater than 0");
+      }
+      if (first.bytesFilled + bytesWritten > first.byteLength) {
+        throw new RangeError("bytesWritten out of range");
+      }
+    }
+    first.buffer = transferArrayBuffer(first.buffer);
+    byteControllerRespondInternal(controller, bytesWritten);
+  }
+
+  function byteControllerRespondWithNewView(controller, view) {
+    const first = controller.pendingPullIntos[0];
+    if (controller.stream.state === "closed") {
+      if (view.byteLength !== 0) {
+        throw new TypeError("The view must be empty once the stream is closed");
+      }
+    } else if (view.byteLength === 0) {
+      throw new TypeError("The view must not be empty");
+    }
+    if (first.byteOffset + first.bytesFilled !== view.byteOffset) {
+      throw new RangeError("The view does not start where the BYOB request's view did");
+    }
+    if (first.bufferByteLength !== view.buffer.byteLength) {
+      throw new RangeError("The view's buffer has a different size than the BYOB request's");
+    }
+    if (first.bytesFilled + view.byteLength > first.byteLength) {
+      throw new RangeError("The view is larger than the BYOB request's view");
+    }
+    const viewByteLength = view.byteLength;
+    first.buffer = transferArrayBuffer(view.buffer);
+    byteControllerRespondInternal(controller, viewByteLength);
+  }
+
+  function byteControllerRespondInternal(controller, bytesWritten) {
+    const first = controller.pendingPullIntos[0];
+    const stream = controller.stream;
+    byteControllerInvalidateBYOBRequest(controller);
+    if (stream.state === "closed") {
+      if (first.readerType === "none") controller.pendingPullIntos.shift();
+      if (hasBYOBReader(stream)) {
+        const filled = [];
+        while (filled.length < numReadIntoRequests(stream)) {
+          filled.push(controller.pendingPullIntos.shift());
+        }
+        for (const descriptor of filled) {
+          byteControllerCommitPullIntoDescriptor(stream, descriptor);
+        }
+      }
+    } else {
+      byteControllerRespondInReadableState(controller, bytesWritten, first);
+    }
+    byteControllerCallPullIfNeeded(controller);
+  }
+
+  function byteControllerRespondInReadableState(controller, bytesWritten, descriptor) {
+    const stream = controller.stream;
+    descriptor.bytesFilled += bytesWritten;
+    if (descriptor.readerType === "none") {
+      byteControllerEnqueueDetachedPullIntoToQueue(controller, descriptor);
+      for (const filled of byteControllerProcessPullIntosUsingQueue(controller)) {
+        byteControllerCommitPullIntoDescriptor(stream, filled);
+      }
+      return;
+    }
+    if (descriptor.bytesFilled < descriptor.minimumFill) return;
+    controller.pendingPullIntos.shift();
+    const remainder = descriptor.bytesFilled % descriptor.elementSize;
+    if (remainder > 0) {
+      const end = descriptor.byteOffset + descriptor.bytesFilled;
+      byteControllerEnqueueClonedChunkToQueue(controller, descriptor.buffer, end - remainder, remainder);
+    }
+    descriptor.bytesFilled -= remainder;
+    const filled = byteControllerProcessPullIntosUsingQueue(controller);
+    byteControllerCommitPullIntoDescriptor(stream, descriptor);
+    for (const other of filled) {
+      byteControllerCommitPullIntoDescriptor(stream, other);
+    }
+  }
+
+  class ReadableByteStreamController {
+    constructor() {
+      illegalConstructor();
+    }
+
+    get byobRequest() {
+      return byteControllerGetBYOBRequest(
+        internal(this, "byte-controller", "ReadableByteStreamController"),
+      );
+    }
+
+    get desiredSize() {
+      return byteControllerGetDesiredSize(
+        internal(this, "byte-controller", "ReadableByteStreamController"),
+      );
+    }
+
+    close() {
+      const controller = internal(this, "byte-controller", "ReadableByteStreamController");
+      if (controller.closeRequested) {
+        throw new TypeError("close() was already called");
+      }
+      if (controller.stream.state !== "readable") {
+        throw new TypeError("The stream is not readable");
+      }
+      byteControllerClose(controller);
+    }
+
+    enqueue(chunk) {
+      const controller = internal(this, "byte-controller", "ReadableByteStreamController");
+      if (!ArrayBuffer.isView(chunk)) {
+        throw new TypeError("chunk must be an ArrayBufferView");
+      }
+      if (chunk.byteLength === 0 || chunk.buffer.byteLength === 0) {
+        throw new TypeError("chunk must not be empty");
+      }
+      if (controller.closeRequested) {
+        throw new TypeError("The stream is closing");
+      }
+      if (controller.stream.state !== "readable") {
+        throw new TypeError("The stream is not readable");
+      }
+      byteControllerEnqueue(controller, chunk);
+    }
+
+    error(e) {
+      byteControllerError(internal(this, "byte-controller", "ReadableByteStreamController"), e);
+    }
+  }
+
+  class ReadableStreamBYOBRequest {
+    constructor() {
+      illegalConstructor();
+    }
+
+    get view() {
+      return internal(this, "byob-request", "ReadableStreamBYOBRequest").view;
+    }
+
+    respond(bytesWritten) {
+      const request = internal(this, "byob-request", "ReadableStreamBYOBRequest");
+      if (request.controller === undefined) {
+        throw new TypeError("This BYOB request has been invalidated");
+      }
+      if (isDetached(request.view.buffer)) {
+        throw new TypeError("The view's buffer is detached");
+      }
+      byteControllerRespond(request.controller, Number(bytesWritten));
+    }
+
+    respondWithNewView(view) {
+      const request = internal(this, "byob-request", "ReadableStreamBYOBRequest");
+      if (!ArrayBuffer.isView(view)) {
+        throw new TypeError("view must be an ArrayBufferView");
+      }
+      if (request.controller === undefined) {
+        throw new TypeError("This BYOB request has been invalidated");
+      }
+      if (isDetached(view.buffer)) {
+        throw new TypeError("The view's buffer is detached");
+      }
+      byteControllerRespondWithNewView(request.controller, view);
+    }
+  }
+
+  // --------------------------------------------------------------------------
+  // Tee
+  // --------------------------------------------------------------------------
+
+  // Byte streams tee into two byte streams; the second branch gets copies
+  function readableStreamTee(stream, cloneForBranch2) {
+    const bytes = stream.controller.kind === "byte-controller";
+    const reader = acquireDefaultReader(stream);
+    let reading = false;
+    let readAgain = false;
+    let canceled1 = false;
+    let canceled2 = false;
+    let reason1;
+    let reason2;
+    let branch1;
+    let branch2;
+    const cancelPromise = deferred();
+
+    const enqueue = (branch, chunk) => {
+      const controller = branch[kState].controller;
+      if (bytes) byteControllerEnqueue(controller, chunk);
+      else defaultControllerEnqueue(controller, chunk);
+    };
+    const close = (branch) => {
+      const controller = branch[kState].controller;
+      if (bytes) {
+        byteControllerClose(controller);
+        if (controller.pendingPullIntos.length > 0) byteControllerRespond(controller, 0);
+      } else {
+        defaultControllerClose(controller);
+      }
+    };
+    const error = (branch, e) => {
+      const controller = branch[kState].controller;
+      if (bytes) byteControllerError(controller, e);
+      else defaultControllerError(controller, e);
+    };
+
+    function pullAlgorithm() {
+      if (reading) {
+        readAgain = true;
+        return resolved(undefined);
+      }
+      reading = true;
+      defaultReaderRead(reader, {
+        chunk: (chunk) => {
+          queueMicrotask(() => {
+            readAgain = false;
+            let chunk2 = chunk;
+            if (!canceled2 && bytes) {
+              chunk2 = new Uint8Array(chunk);
+            } else if (!canceled2 && cloneForBranch2) {
+              try {
+                chunk2 = structuredClone(chunk);
+              } catch (e) {
+                error(branch1, e);
+                error(branch2, e);
+                cancelPromise.resolve(readableStreamCancel(stream, e));
+                return;
+              }
+            }
+            if (!canceled1) enqueue(branch1, chunk);
+            if (!canceled2) enqueue(branch2, chunk2);
+            reading = false;
+            if (readAgain) pullAlgorithm();
+          });
+        },
+        close: () => {
+          reading = false;
+          if (!canceled1) close(branch1);
+          if (!canceled2) close(branch2);
+          if (!canceled1 || !canceled2) cancelPromise.resolve(undefined);
+        },
+        error: () => {
+          reading = false;
+        },
+      });
+      return resolved(undefined);
+    }
+
+    function cancel1(reason) {
+      canceled1 = true;
+      reason1 = reason;
+      if (canceled2) {
+        cancelPromise.resolve(readableStreamCancel(stream, [reason1, reason2]));
+      }
+      return cancelPromise.promise;
+    }
+
+    function cancel2(reason) {
+      canceled2 = true;
+      reason2 = reason;
+      if (canceled1) {
+        cancelPromise.resolve(readableStreamCancel(stream, [reason1, reason2]));
+      }
+      return cancelPromise.promise;
+    }
+
+    const start = () => undefined;
+    if (bytes) {
+      branch1 = createReadableByteStream(start, pullAlgorithm, cancel1);
+      branch2 = createReadableByteStream(start, pullAlgorithm, cancel2);
+    } else {
+      branch1 = createReadableStream(start, pullAlgorithm, cancel1);
+      branch2 = createReadableStream(start, pullAlgorithm, cancel2);
+    }
+
+    reader.closed.promise.then(undefined, (e) => {
+      error(branch1, e);
+      error(branch2, e);
+      if (!canceled1 || !canceled2) cancelPromise.resolve(undefined);
+    });
+
+    return [branch1, branch2];
+  }
+
+  // --------------------------------------------------------------------------
+  // Pipes
+  // --------------------------------------------------------------------------
+
+  function readableStreamPipeTo(source, dest, preventClose, preventAbort, preventCancel, signal) {
+    const reader = acquireDefaultReader(source);
+    const writer = acquireWriter(dest);
+    source.disturbed = true;
+    let shuttingDown = false;
+    let currentWrite = resolved(undefined);
+    const result = deferred();
+    let abortAlgorithm;
+
+    if (signal !== undefined) {
+      abortAlgorithm = () => {
+        const error = signal.reason !== undefined ? signal.reason : abortError();
+        const actions = [];
+        if (!preventAbort) {
+          actions.push(() =>
+            dest.state === "writable" ? writableStreamAbort(dest, error) : resolved(undefined),
+          );
+        }
+        if (!preventCancel) {
+          actions.push(() =>
+            source.state === "readable" ? readableStreamCancel(source, error) : resolved(undefined),
+          );
+        }
+        shutdownWithAction(() => Promise.all(actions.map((action) => action())), true, error);
+      };
+      if (signal.aborted) {
+        abortAlgorithm();
+        return result.promise;
+      }
+      signal.addEventListener("abort", abortAlgorithm);
+    }
+
+    function pipeStep() {
+      if (shuttingDown) return resolved(true);
+      return writer.ready.promise.then(
+        () =>
+          new Promise((resolveRead, rejectRead) => {
+            defaultReaderRead(reader, {
+              chunk: (chunk) => {
+                currentWrite = writerWrite(writer, chunk).then(undefined, () => {});
+                resolveRead(false);
+              },
+              close: () => resolveRead(true),
+              error: rejectRead,
+            });
+          }),
+      );
+    }
+
+    function pipeLoop() {
+      return new Promise((resolveLoop, rejectLoop) => {
+        function next(done) {
+          if (done) resolveLoop();
+          else pipeStep().then(next, rejectLoop);
+        }
+        next(false);
+      });
+    }
+
+    function waitForWritesToFinish() {
+      const oldCurrentWrite = currentWrite;
+      return currentWrite.then(() =>
+        oldCurrentWrite !== currentWrite ? waitForWritesToFinish() : undefined,
+      );
+    }
+
+    function isOrBecomesErrored(stream, promise, action) {
+      if (stream.state === "errored") action(stream.storedError);
+      else promise.then(undefined, action);
+    }
+
+    function isOrBecomesClosed(stream, promise, action) {
+      if (stream.state === "closed") action();
+      else promise.then(action, () => {});
+    }
+
+    function shutdownWithAction(action, originalIsError, originalError) {
+      if (shuttingDown) return;
+      shuttingDown = true;
+      const doTheRest = () => {
+        action().then(
+          () => finalize(originalIsError, originalError),
+          (newError) => finalize(true, newError),
+        );
+      };
+      if (dest.state === "writable" && !writableStreamCloseQueuedOrInFlight(dest)) {
+        waitForWritesToFinish().then(doTheRest);
+      } else {
+        doTheRest();
+      }
+    }
+
+    function shutdown(isError, error) {
+      if (shuttingDown) return;
+      shuttingDown = true;
+      if (dest.state === "writable" && !writableStreamCloseQueuedOrInFlight(dest)) {
+        waitForWritesToFinish().then(() => finalize(isError, error));
+      } else {
+        finalize(isError, error);
+      }
+    }
+
+    function finalize(isError, error) {
+      writerRelease(writer);
+      defaultReaderRelease(reader);
+      if (signal !== undefined) signal.removeEventListener("abort", abortAlgorithm);
+      if (isError) result.reject(error);
+      else result.resolve(undefined);
+    }
+
+    isOrBecomesErrored(source, reader.closed.promise, (storedError) => {
+      if (!preventAbort) {
+        shutdownWithAction(() => writableStreamAbort(dest, storedError), true, storedError);
+      } else {
+        shutdown(true, storedError);
+      }
+    });
+
+    isOrBecomesErrored(dest, writer.closed.promise, (storedError) => {
+      if (!preventCancel) {
+        shutdownWithAction(() => readableStreamCancel(source, storedError), true, storedError);
+      } else {
+        shutdown(true, storedError);
+      }
+    });
+
+    isOrBecomesClosed(source, reader.closed.promise, () => {
+      if (!preventClose) {
+        shutdownWithAction(() => writerCloseWithErrorPropagation(writer));
+      } else {
+        shutdown();
+      }
+    });
+
+    if (writableStreamCloseQueuedOrInFlight(dest) || dest.state === "closed") {
+      const destClosed = new TypeError(
+        "The destination writable stream closed before all data could be piped to it",
+      );
+      if (!preventCancel) {
+        shutdownWithAction(() => readableStreamCancel(source, destClosed), true, destClosed);
+      } else {
+        shutdown(true, destClosed);
+      }
+    }
+
+    markHandled(pipeLoop());
+    return result.promise;
+  }
+
+  // --------------------------------------------------------------------------
+  // Async iteration
+  // --------------------------------------------------------------------------
+
+  const AsyncIteratorPrototype = Object.getPrototypeOf(
+    Object.getPrototypeOf(async function* () {}).prototype,
+  );
+
+  const ReadableStreamAsyncIteratorPrototype = Object.create(AsyncIteratorPrototype, {
+    next: {
+      value: function next() {
+        const iterator = this[kState];
+        const step = () => {
+          const reader = iterator.reader;
+          if (reader.stream === undefined) {
+            return resolved({ value: undefined, done: true });
+          }
+          const d = deferred();
+          defaultReaderRead(reader, {
+            chunk: (value) => d.resolve({ value, done: false }),
+            close: () => {
+              defaultReaderRelease(reader);
+              d.resolve({ value: undefined, done: true });
+            },
+            error: (e) => {
+              defaultReaderRelease(reader);
+              d.reject(e);
+            },
+          });
+          return d.promise;
+        };
+        iterator.ongoing = iterator.ongoing ? iterator.ongoing.then(step, step) : step();
+        return iterator.ongoing;
+      },
+      writable: true,
+      configurable: true,
+    },
+    return: {
+      value: function (value) {
+        const iterator = this[kState];
+        const step = () => {
+          const reader = iterator.reader;
+          if (reader.stream === undefined) {
+            return resolved({ value, done: true });
+          }
+          if (!iterator.preventCancel) {
+            const result = readableStreamCancel(reader.stream, value);
+            defaultReaderRelease(reader);
+            return result.then(() => ({ value, done: true }));
+          }
+          defaultReaderRelease(reader);
+          return resolved({ value, done: true });
+        };
+        iterator.ongoing = iterator.ongoing ? iterator.ongoing.then(step, step) : step();
+        return iterator.ongoing;
+      },
+      writable: true,
+      configurable: true,
+    },
+  });
+
+  function getAsyncIterator(iterable) {
+    const asyncMethod = iterable[Symbol.asyncIterator];
+    if (typeof asyncMethod === "function") {
+      return asyncMethod.call(iterable);
+    }
+    const syncMethod = iterable[Symbol.iterator];
+    if (typeof syncMethod !== "function") {
+      throw new TypeError("The value is not iterable");
+    }
+    const iterator = syncMethod.call(iterable);
+    return {
+      next() {
+        const result = iterator.next();
+        return resolved(result.value).then((value) => ({ value, done: result.done }));
+      },
+      return(value) {
+        return typeof iterator.return === "function"
+          ? resolved(iterator.return(value))
+          : resolved({ value, done: true });
+      },
+    };
+  }
+
+  // --------------------------------------------------------------------------
+  // Public class
+  // --------------------------------------------------------------------------
+
+  class ReadableStream {
+    constructor(underlyingSource = undefined, strategy = {}) {
+      if (underlyingSource === null) {
+        throw new TypeError("The underlying source cannot be null");
+      }
+      const source = underlyingSource === undefined ? {} : underlyingSource;
+      const stream = initializeReadableStream(this);
+      const type = source.type;
+      if (type !== undefined && String(type) === "bytes") {
+        if (strategy !== null && strategy !== undefined && strategy.size !== undefined) {
+          throw new RangeError("The strategy for a byte stream cannot have a size function");
+        }
+        setUpByteControllerFromSource(stream, source, extractHighWaterMark(strategy, 0));
+      } else if (type !== undefined) {
+        throw new TypeError(`Invalid underlying source type: ${type}`);
+      } else {
+        setUpDefaultControllerFromSource(
+          stream,
+          source,
+          extractHighWaterMark(strategy, 1),
+          extractSizeAlgorithm(strategy),
+        );
+      }
+    }
+
+    static from(asyncIterable) {
+      const iterator = getAsyncIterator(asyncIterable);
+      let stream;
+      const pull = () =>
+        resolved(iterator.next()).then((result) => {
+          if (result === null || typeof result !== "object") {
+            throw new TypeError("The iterator's next() must return an object");
+          }
+          const controller = stream[kState].controller;
+          if (result.done) defaultControllerClose(controller);
+          else defaultControllerEnqueue(controller, result.value);
+        });
+      const cancel = (reason) => {
+        const returnMethod = iterator.return;
+        if (typeof returnMethod !== "function") return resolved(undefined);
+        return promiseCall(returnMethod, iterator, reason).then((result) => {
+          if (result === null || typeof result !== "object") {
+            throw new TypeError("The iterator's return() must return an object");
+          }
+        });
+      };
+      stream = createReadableStream(() => undefined, pull, cancel, 0);
+      return stream;
+    }
+
+    get locked() {
+      return isLocked(internal(this, "readable", "ReadableStream"));
+    }
+
+    cancel(reason) {
+      const stream = internal(this, "readable", "ReadableStream");
+      if (isLocked(stream)) {
+        return rejected(new TypeError("Cannot cancel a locked ReadableStream"));
+      }
+      return readableStreamCancel(stream, reason);
+    }
+
+    getReader(options = {}) {
+      const stream = internal(this, "readable", "ReadableStream");
+      const mode = options === null || options === undefined ? undefined : options.mode;
+      if (mode === undefined) return acquireDefaultReader(stream).object;
+      if (String(mode) === "byob") return acquireBYOBReader(stream).object;
+      throw new TypeError(`Invalid reader mode: ${mode}`);
+    }
+
+    pipeThrough(transform, options = {}) {
+      const stream = internal(this, "readable", "ReadableStream");
+      const readable = transform && transform.readable;
+      const writable = transform && transform.writable;
+      const dest = internal(writable, "writable", "WritableStream");
+      internal(readable, "readable", "ReadableStream");
+      if (isLocked(stream)) throw new TypeError("The ReadableStream is locked");
+      if (dest.writer !== undefined) throw new TypeError("The WritableStream is locked");
+      const { preventClose, preventAbort, preventCancel, signal } = options || {};
+      const promise = readableStreamPipeTo(
+        stream,
+        dest,
+        Boolean(preventClose),
+        Boolean(preventAbort),
+        Boolean(preventCancel),
+        signal,
+      );
+      markHandled(promise);
+      return readable;
+    }
+
+    pipeTo(destination, options = {}) {
+      let stream;
+      let dest;
+      try {
+        stream = internal(this, "readable", "ReadableStream");
+        dest = internal(destination, "writable", "WritableStream");
+      } catch (e) {
+        return rejected(e);
+      }
+      if (isLocked(stream)) {
+        return rejected(new TypeError("The ReadableStream is locked"));
+      }
+      if (dest.writer !== undefined) {
+        return rejected(new TypeError("The WritableStream is locked"));
+      }
+      const { preventClose, preventAbort, preventCancel, signal } = options || {};
+      return readableStreamPipeTo(
+        stream,
+        dest,
+        Boolean(preventClose),
+        Boolean(preventAbort),
+        Boolean(preventCancel),
+        signal,
+      );
+    }
+
+    tee() {
+      return readableStreamTee(internal(this, "readable", "ReadableStream"), false);
+    }
+
+    values(options = {}) {
+      const stream = internal(this, "readable", "ReadableStream");
+      const reader = acquireDefaultReader(stream);
+      const iterator = Object.create(ReadableStreamAsyncIteratorPrototype);
+      iterator[kState] = {
+        reader,
+        preventCancel: Boolean(options && options.preventCancel),
+        ongoing: undefined,
+      };
+      return iterator;
+    }
+
+    [Symbol.asyncIterator](options) {
+      return this.values(options);
+    }
+  }
+
+  Object.defineProperty(ReadableStream.prototype, Symbol.toStringTag, {
+    value: "ReadableStream",
+    configurable: true,
+  });
+
+  // ==========================================================================
+  // WritableStream
+  // ==========================================================================
+
+  const closeSentinel = {};
+
+  function initializeWritableStream(object) {
+    const stream = {
+      kind: "writable",
+      object,
+      state: "writable",
+      storedError: undefined,
+      writer: undefined,
+      controller: undefined,
+      inFlightWriteRequest: undefined,
+      closeRequest: undefined,
+      inFlightCloseRequest: undefined,
+      pendingAbortRequest: undefined,
+      writeRequests: [],
+      backpressure: false,
+    };
+    object[kState] = stream;
+    return stream;
+  }
+
+  function createWritableStream(startAlgorithm, writeAlgorithm, closeAlgorithm, abortAlgorithm, hwm, size) {
+    const object = Object.create(WritableStream.prototype);
+    const stream = initializeWritableStream(object);
+    const controller = Object.create(WritableStreamDefaultController.prototype);
+    setUpWritableController(stream, controller, startAlgorithm, writeAlgorithm, closeAlgorithm, abortAlgorithm, hwm, size);
+    return object;
+  }
+
+  function writableStreamAbort(stream, reason) {
+    if (stream.state === "closed" || stream.state === "errored") return resolved(undefined);
+    if (stream.controller.abortController !== undefined) {
+      stream.controller.abortController.abort(reason);
+    }
+    const state = stream.state;
+    if (state === "closed" || state === "errored") return resolved(undefined);
+    if (stream.pendingAbortRequest !== undefined) {
+      return stream.pendingAbortRequest.promise.promise;
+    }
+    let wasAlreadyErroring = false;
+    if (state === "erroring") {
+      wasAlreadyErroring = true;
+      reason = undefined;
+    }
+    const promise = deferred();
+    stream.pendingAbortRequest = { promise, reason, wasAlreadyErroring };
+    if (!wasAlreadyErroring) writableStreamStartErroring(stream, reason);
+    return promise.promise;
+  }
+
+  function writableStreamClose(stream) {
+    const state = stream.state;
+    if (state === "closed" || state === "errored") {
+      return rejected(new TypeError("The stream is closed or errored"));
+    }
+    const promise = deferred();
+    stream.closeRequest = promise;
+    const writer = stream.writer;
+    if (writer !== undefined && stream.backpressure && state === "writable") {
+      writer.ready.resolve(undefined);
+    }
+    enqueueValueWithSize(stream.controller, closeSentinel, 0);
+    writableControllerAdvanceQueueIfNeeded(stream.controller);
+    return promise.promise;
+  }
+
+  function writableStreamDealWithRejection(stream, error) {
+    if (stream.state === "writable") {
+      writableStreamStartErroring(stream, error);
+      return;
+    }
+    writableStreamFinishErroring(stream);
+  }
+
+  function writableStreamStartErroring(stream, reason) {
+    const controller = stream.controller;
+    stream.state = "erroring";
+    stream.storedError = reason;
+    if (stream.writer !== undefined) writerEnsureReadyPromiseRejected(stream.writer, reason);
+    if (!writableStreamHasOperationMarkedInFlight(stream) && controller.started) {
+      writableStreamFinishErroring(stream);
+    }
+  }
+
+  function writableStreamFinishErroring(stream) {
+    stream.state = "errored";
+    resetQueue(stream.controller);
+    const storedError = stream.storedError;
+    for (const request of stream.writeRequests) request.reject(storedError);
+    stream.writeRequests = [];
+    const abortRequest = stream.pendingAbortRequest;
+    if (abortRequest === undefined) {
+      writableStreamRejectCloseAndClosedPromiseIfNeeded(stream);
+      return;
+    }
+    stream.pendingAbortRequest = undefined;
+    if (abortRequest.wasAlreadyErroring) {
+      abortRequest.promise.reject(storedError);
+      writableStreamRejectCloseAndClosedPromiseIfNeeded(stream);
+      return;
+    }
+    const controller = stream.controller;
+    const promise = controller.abortAlgorithm
+      ? controller.abortAlgorithm(abortRequest.reason)
+      : resolved(undefined);
+    writableControllerClearAlgorithms(controller);
+    promise.then(
+      () => {
+        abortRequest.promise.resolve(undefined);
+        writableStreamRejectCloseAndClosedPromiseIfNeeded(stream);
+      },
+      (reason) => {
+        abortRequest.promise.reject(reason);
+        writableStreamRejectCloseAndClosedPromiseIfNeeded(stream);
+      },
+    );
+  }
+
+  function writableStreamFinishInFlightWrite(stream) {
+    stream.inFlightWriteRequest.resolve(undefined);
+    stream.inFlightWriteRequest = undefined;
+  }
+
+  function writableStreamFinishInFlightWriteWithError(stream, error) {
+    stream.inFlightWriteRequest.reject(error);
+    stream.inFlightWriteRequest = undefined;
+    writableStreamDealWithRejection(stream, error);
+  }
+
+  function writableStreamFinishInFlightClose(stream) {
+    stream.inFlightCloseRequest.resolve(undefined);
+    stream.inFlightCloseRequest = undefined;
+    if (stream.state === "erroring") {
+      stream.storedError = undefined;
+      if (stream.pendingAbortRequest !== undefined) {
+        stream.pendingAbortRequest.promise.resolve(undefined);
+        stream.pendingAbortRequest = undefined;
+      }
+    }
+    stream.state = "closed";
+    if (stream.writer !== undefined) stream.writer.closed.resolve(undefined);
+  }
+
+  function writableStreamFinishInFlightCloseWithError(stream, error) {
+    stream.inFlightCloseRequest.reject(error);
+    stream.inFlightCloseRequest = undefined;
+    if (stream.pendingAbortRequest !== undefined) {
+      stream.pendingAbortRequest.promise.reject(error);
+      stream.pendingAbortRequest = undefined;
+    }
+    writableStreamDealWithRejection(stream, error);
+  }
+
+  function writableStreamCloseQueuedOrInFlight(stream) {
+    return stream.closeRequest !== undefined || stream.inFlightCloseRequest !== undefined;
+  }
+
+  function writableStreamHasOperationMarkedInFlight(stream) {
+    return stream.inFlightWriteRequest !== undefined || stream.inFlightCloseRequest !== undefined;
+  }
+
+  function writableStreamRejectCloseAndClosedPromiseIfNeeded(stream) {
+    if (stream.closeRequest !== undefined) {
+      stream.closeRequest.reject(stream.storedError);
+      stream.closeRequest = undefined;
+    }
+    const writer = stream.writer;
+    if (writer !== undefined) {
+      writer.closed.reject(stream.storedError);
+      markHandled(writer.closed.promise);
+    }
+  }
+
+  function writableStreamUpdateBackpressure(stream, backpressure) {
+    const writer = stream.writer;
+    if (writer !== undefined && backpressure !== stream.backpressure) {
+      if (backpressure) writer.ready = deferred();
+      else writer.ready.resolve(undefined);
+    }
+    stream.backpressure = backpressure;
+  }
+
+  // --------------------------------------------------------------------------
+  // Writer
+  // --------------------------------------------------------------------------
+
+  function acquireWriter(stream) {
+    if (stream.writer !== undefined) throw new TypeError("The WritableStream is locked");
+    const object = Object.create(WritableStreamDefaultWriter.prototype);
+    const writer = { kind: "writer", object, stream, ready: undefined, closed: undefined };
+    object[kState] = writer;
+    stream.writer = writer;
+    const state = stream.state;
+    if (state === "writable") {
+      writer.ready =
+        !writableStreamCloseQueuedOrInFlight(stream) && stream.backpressure
+          ? deferred()
+          : resolvedDeferred(undefined);
+      writer.closed = deferred();
+    } else if (state === "erroring") {
+      writer.ready = rejectedDeferred(stream.storedError);
+      writer.closed = deferred();
+    } else if (state === "closed") {
+      writer.ready = resolvedDeferred(undefined);
+      writer.closed = resolvedDeferred(undefined);
+    } else {
+      writer.ready = rejectedDeferred(stream.storedError);
+      writer.closed = rejectedDeferred(stream.storedError);
+    }
+    return writer;
+  }
+
+  function writerGetDesiredSize(writer) {
+    const stream = writer.stream;
+    if (stream.state === "errored" || stream.state === "erroring") return null;
+    if (stream.state === "closed") return 0;
+    return writableControllerGetDesiredSize(stream.controller);
+  }
+
+  function writerEnsureReadyPromiseRejected(writer, error) {
+    if (writer.ready.state === "pending") writer.ready.reject(error);
+    else writer.ready = rejectedDeferred(error);
+    markHandled(writer.ready.promise);
+  }
+
+  function writerEnsureClosedPromiseRejected(writer, error) {
+    if (writer.closed.state === "pending") writer.closed.reject(error);
+    else writer.closed = rejectedDeferred(error);
+    markHandled(writer.closed.promise);
+  }
+
+  function writerRelease(writer) {
+    const stream = writer.stream;
+    const error = new TypeError("The writer was released");
+    writerEnsureReadyPromiseRejected(writer, error);
+    writerEnsureClosedPromiseRejected(writer, error);
+    stream.writer = undefined;
+    writer.stream = undefined;
+  }
+
+  function writerWrite(writer, chunk) {
+    const stream = writer.stream;
+    const controller = stream.controller;
+    const chunkSize = writableControllerGetChunkSize(controller, chunk);
+    if (stream !== writer.stream) {
+      return rejected(new TypeError("The writer was released"));
+    }
+    const state = stream.state;
+    if (state === "errored") return rejected(stream.storedError);
+    if (writableStreamCloseQueuedOrInFlight(stream) || state === "closed") {
+      return rejected(new TypeError("The stream is closing or closed"));
+    }
+    if (state === "erroring") return rejected(stream.storedError);
+    const promise = deferred();
+    stream.writeRequests.push(promise);
+    writableControllerWrite(controller, chunk, chunkSize);
+    return promise.promise;
+  }
+
+  function writerCloseWithErrorPropagation(writer) {
+    const stream = writer.stream;
+    const state = stream.state;
+    if (writableStreamCloseQueuedOrInFlight(stream) || state === "closed") {
+      return resolved(undefined);
+    }
+    if (state === "errored") return rejected(stream.storedError);
+    return writableStreamClose(stream);
+  }
+
+  class WritableStreamDefaultWriter {
+    constructor(stream) {
+      const writer = acquireWriter(internal(stream, "writable", "WritableStream"));
+      writer.object = this;
+      this[kState] = writer;
+    }
+
+    get closed() {
+      return internal(this, "writer", "WritableStreamDefaultWriter").closed.promise;
+    }
+
+    get desiredSize() {
+      const writer = internal(this, "writer", "WritableStreamDefaultWriter");
+      if (writer.stream === undefined) throw new TypeError("The writer has no stream");
+      return writerGetDesiredSize(writer);
+    }
+
+    get ready() {
+      return internal(this, "writer", "WritableStreamDefaultWriter").ready.promise;
+    }
+
+    abort(reason) {
+      const writer = internal(this, "writer", "WritableStreamDefaultWriter");
+      if (writer.stream === undefined) {
+        return rejected(new TypeError("The writer has no stream"));
+      }
+      return writableStreamAbort(writer.stream, reason);
+    }
+
+    close() {
+      const writer = internal(this, "writer", "WritableStreamDefaultWriter");
+      const stream = writer.stream;
+      if (stream === undefined) return rejected(new TypeError("The writer has no stream"));
+      if (writableStreamCloseQueuedOrInFlight(stream)) {
+        return rejected(new TypeError("The stream is already closing"));
+      }
+      return writableStreamClose(stream);
+    }
+
+    releaseLock() {
+      const writer = internal(this, "writer", "WritableStreamDefaultWriter");
+      if (writer.stream !== undefined) writerRelease(writer);
+    }
+
+    write(chunk) {
+      const writer = internal(this, "writer", "WritableStreamDefaultWriter");
+      if (writer.stream === undefined) {
+        return rejected(new TypeError("The writer has no stream"));
+      }
+      return writerWrite(writer, chunk);
+    }
+  }
+
+  // --------------------------------------------------------------------------
+  // Writable controller
+  // --------------------------------------------------------------------------
+
+  function setUpWritableController(stream, object, startAlgorithm, writeAlgorithm, closeAlgorithm, abortAlgorithm, hwm, size) {
+    const controller = {
+      kind: "writable-controller",
+      object,
+      stream,
+      queue: [],
+      queueTotalSize: 0,
+      started: false,
+      strategyHWM: hwm,
+      strategySizeAlgorithm: size,
+      writeAlgorithm,
+      closeAlgorithm,
+      abortAlgorithm,
+      abortController: typeof AbortController === "function" ? new AbortController() : undefined,
+    };
+    object[kState] = controller;
+    stream.controller = controller;
+    writableStreamUpdateBackpressure(stream, writableControllerGetBackpressure(controller));
+
+    const startResult = startAlgorithm();
+    resolved(startResult).then(
+      () => {
+        controller.started = true;
+        writableControllerAdvanceQueueIfNeeded(controller);
+      },
+      (r) => {
+        controller.started = true;
+        writableStreamDealWithRejection(stream, r);
+      },
+    );
+  }
+
+  function setUpWritableControllerFromSink(stream, sink, hwm, size) {
+    const object = Object.create(WritableStreamDefaultController.prototype);
+    const start = method(sink, "start");
+    const write = method(sink, "write");
+    const close = method(sink, "close");
+    const abort = method(sink, "abort");
+    setUpWritableController(
+      stream,
+      object,
+      () => (start ? start.call(sink, object) : undefined),
+      (chunk) => (write ? promiseCall(write, sink, chunk, object) : resolved(undefined)),
+      () => (close ? promiseCall(close, sink) : resolved(undefined)),
+      (reason) => (abort ? promiseCall(abort, sink, reason) : resolved(undefined)),
+      hwm,
+      size,
+    );
+  }
+
+  function writableControllerClearAlgorithms(controller) {
+    controller.writeAlgorithm = undefined;
+    controller.closeAlgorithm = undefined;
+    controller.abortAlgorithm = undefined;
+    controller.strategySizeAlgorithm = undefined;
+  }
+
+  function writableControllerGetChunkSize(controller, chunk) {
+    if (controller.strategySizeAlgorithm === undefined) return 1;
+    try {
+      return controller.strategySizeAlgorithm(chunk);
+    } catch (e) {
+      writableControllerErrorIfNeeded(controller, e);
+      return 1;
+    }
+  }
+
+  function writableControllerGetDesiredSize(controller) {
+    return controller.strategyHWM - controller.queueTotalSize;
+  }
+
+  function writableControllerGetBackpressure(controller) {
+    return writableControllerGetDesiredSize(controller) <= 0;
+  }
+
+  function writableControllerWrite(controller, chunk, chunkSize) {
+    try {
+      enqueueValueWithSize(controller, chunk, chunkSize);
+    } catch (e) {
+      writableControllerErrorIfNeeded(controller, e);
+      return;
+    }
+    const stream = controller.stream;
+    if (!writableStreamCloseQueuedOrInFlight(stream) && stream.state === "writable") {
+      writableStreamUpdateBackpressure(stream, writableControllerGetBackpressure(controller));
+    }
+    writableControllerAdvanceQueueIfNeeded(controller);
+  }
+
+  function writableControllerAdvanceQueueIfNeeded(controller) {
+    const stream = controller.stream;
+    if (!controller.started || stream.inFlightWriteRequest !== undefined) return;
+    const state = stream.state;
+    if (state === "closed" || state === "errored") return;
+    if (state === "erroring") {
+      writableStreamFinishErroring(stream);
+      return;
+    }
+    if (controller.queue.length === 0) return;
+    const value = peekQueueValue(controller);
+    if (value === closeSentinel) writableControllerProcessClose(controller);
+    else writableControllerProcessWrite(controller, value);
+  }
+
+  function writableControllerErrorIfNeeded(controller, error) {
+    if (controller.stream.state === "writable") writableControllerError(controller, error);
+  }
+
+  function writableControllerError(controller, error) {
+    writableControllerClearAlgorithms(controller);
+    writableStreamStartErroring(controller.stream, error);
+  }
+
+  function writableControllerProcessClose(controller) {
+    const stream = controller.stream;
+    stream.inFlightCloseRequest = stream.closeRequest;
+    stream.closeRequest = undefined;
+    dequeueValue(controller);
+    const sinkClosePromise = controller.closeAlgorithm();
+    writableControllerClearAlgorithms(controller);
+    sinkClosePromise.then(
+      () => writableStreamFinishInFlightClose(stream),
+      (reason) => writableStreamFinishInFlightCloseWithError(stream, reason),
+    );
+  }
+
+  function writableControllerProcessWrite(controller, chunk) {
+    const stream = controller.stream;
+    stream.inFlightWriteRequest = stream.writeRequests.shift();
+    controller.writeAlgorithm(chunk).then(
+      () => {
+        writableStreamFinishInFlightWrite(stream);
+        const state = stream.state;
+        dequeueValue(controller);
+        if (!writableStreamCloseQueuedOrInFlight(stream) && state === "writable") {
+          writableStreamUpdateBackpressure(stream, writableControllerGetBackpressure(controller));
+        }
+        writableControllerAdvanceQueueIfNeeded(controller);
+      },
+      (reason) => {
+        if (stream.state === "writable") writableControllerClearAlgorithms(controller);
+        writableStreamFinishInFlightWriteWithError(stream, reason);
+      },
+    );
+  }
+
+  class WritableStreamDefaultController {
+    constructor() {
+      illegalConstructor();
+    }
+
+    get signal() {
+      const controller = internal(this, "writable-controller", "WritableStreamDefaultController");
+      return controller.abortController !== undefined ? controller.abortController.signal : undefined;
+    }
+
+    error(e) {
+      const controller = internal(this, "writable-controller", "WritableStreamDefaultController");
+      if (controller.stream.state !== "writable") return;
+      writableControllerError(controller, e);
+    }
+  }
+
+  class WritableStream {
+    constructor(underlyingSink = undefined, strategy = {}) {
+      if (underlyingSink === null) {
+        throw new TypeError("The underlying sink cannot be null");
+      }
+      const sink = underlyingSink === undefined ? {} : underlyingSink;
+      if (sink.type !== undefined) {
+        throw new RangeError("Invalid underlying sink type");
+      }
+      const stream = initializeWritableStream(this);
+      setUpWritableControllerFromSink(
+        stream,
+        sink,
+        extractHighWaterMark(strategy, 1),
+        extractSizeAlgorithm(strategy),
+      );
+    }
+
+    get locked() {
+      return internal(this, "writable", "WritableStream").writer !== undefined;
+    }
+
+    abort(reason) {
+      const stream = internal(this, "writable", "WritableStream");
+      if (stream.writer !== undefined) {
+        return rejected(new TypeError("Cannot abort a locked WritableStream"));
+      }
+      return writableStreamAbort(stream, reason);
+    }
+
+    close() {
+      const stream = internal(this, "writable", "WritableStream");
+      if (stream.writer !== undefined) {
+        return rejected(new TypeError("Cannot close a locked WritableStream"));
+      }
+      if (writableStreamCloseQueuedOrInFlight(stream)) {
+        return rejected(new TypeError("The stream is already closing"));
+      }
+      return writableStreamClose(stream);
+    }
+
+    getWriter() {
+      return acquireWriter(internal(this, "writable", "WritableStream")).object;
+    }
+  }
+
+  Object.defineProperty(WritableStream.prototype, Symbol.toStringTag, {
+    value: "WritableStream",
+    configurable: true,
+  });
+
+  // ==========================================================================
+  // TransformStream
+  // ==========================================================================
+
+  function initializeTransformStream(object, startPromise, writableHWM, writableSize, readableHWM, readableSize) {
+    const stream = {
+      kind: "transform",
+      object,
+      readable: undefined,
+      writable: undefined,
+      backpressure: undefined,
+      backpressureChangePromise: undefined,
+      controller: undefined,
+    };
+    object[kState] = stream;
+    const startAlgorithm = () => startPromise;
+    stream.writable = createWritableStream(
+      startAlgorithm,
+      (chunk) => transformSinkWrite(stream, chunk),
+      () => transformSinkClose(stream),
+      (reason) => transformSinkAbort(stream, reason),
+      writableHWM,
+      writableSize,
+    );
+    stream.readable = createReadableStream(
+      startAlgorithm,
+      () => transformSourcePull(stream),
+      (reason) => transformSourceCancel(stream, reason),
+      readableHWM,
+      readableSize,
+    );
+    transformSetBackpressure(stream, true);
+    return stream;
+  }
+
+  function readableControllerOf(stream) {
+    return stream.readable[kState].controller;
+  }
+
+  function writableControllerOf(stream) {
+    return stream.writable[kState].controller;
+  }
+
+  function transformError(stream, e) {
+    defaultControllerError(readableControllerOf(stream), e);
+    transformErrorWritableAndUnblockWrite(stream, e);
+  }
+
+  function transformErrorWritableAndUnblockWrite(stream, e) {
+    transformControllerClearAlgorithms(stream.controller);
+    writableControllerErrorIfNeeded(writableControllerOf(stream), e);
+    if (stream.backpressure) transformSetBackpressure(stream, false);
+  }
+
+  function transformSetBackpressure(stream, backpressure) {
+    if (stream.backpressureChangePromise !== undefined) {
+      stream.backpressureChangePromise.resolve(undefined);
+    }
+    stream.backpressureChangePromise = deferred();
+    stream.backpressure = backpressure;
+  }
+
+  function setUpTransformController(stream, object, transformAlgorithm, flushAlgorithm, cancelAlgorithm) {
+    const controller = {
+      kind: "transform-controller",
+      object,
+      stream,
+      transformAlgorithm,
+      flushAlgorithm,
+      cancelAlgorithm,
+      finishPromise: undefined,
+    };
+    object[kState] = controller;
+    stream.controller = controller;
+  }
+
+  function setUpTransformControllerFromTransformer(stream, transformer) {
+    const object = Object.create(TransformStreamDefaultController.prototype);
+    const transform = method(transformer, "transform");
+    const flush = method(transformer, "flush");
+    const cancel = method(transformer, "cancel");
+    setUpTransformController(
+      stream,
+      object,
+      transform
+        ? (chunk) => promiseCall(transform, transformer, chunk, object)
+        : (chunk) => {
+            try {
+              transformControllerEnqueue(stream.controller, chunk);
+              return resolved(undefined);
+            } catch (e) {
+              return rejected(e);
+            }
+          },
+      () => (flush ? promiseCall(flush, transformer, object) : resolved(undefined)),
+      (reason) => (cancel ? promiseCall(cancel, transformer, reason) : resolved(undefined)),
+    );
+    return object;
+  }
+
+  function transformControllerClearAlgorithms(controller) {
+    controller.transformAlgorithm = undefined;
+    controller.flushAlgorithm = undefined;
+    controller.cancelAlgorithm = undefined;
+  }
+
+  function transformControllerEnqueue(controller, chunk) {
+    const stream = controller.stream;
+    const readableController = readableControllerOf(stream);
+    if (!defaultControllerCanCloseOrEnqueue(readableController)) {
+      throw new TypeError("The readable side cannot be enqueued to");
+    }
+    try {
+      defaultControllerEnqueue(readableController, chunk);
+    } catch (e) {
+      transformErrorWritableAndUnblockWrite(stream, e);
+      throw stream.readable[kState].storedError;
+    }
+    const backpressure = defaultControllerHasBackpressure(readableController);
+    if (backpressure !== stream.backpressure) transformSetBackpressure(stream, true);
+  }
+
+  function transformControllerTerminate(controller) {
+    const stream = controller.stream;
+    defaultControllerClose(readableControllerOf(stream));
+    transformErrorWritableAndUnblockWrite(stream, new TypeError("The TransformStream was terminated"));
+  }
+
+  function transformControllerPerformTransform(controller, chunk) {
+    return controller.transformAlgorithm(chunk).then(undefined, (r) => {
+      transformError(controller.stream, r);
+      throw r;
+    });
+  }
+
+  function transformSinkWrite(stream, chunk) {
+    const controller = stream.controller;
+    if (stream.backpressure) {
+      return stream.backpressureChangePromise.promise.then(() => {
+        const writable = stream.writable[kState];
+        if (writable.state === "erroring") throw writable.storedError;
+        return transformControllerPerformTransform(controller, chunk);
+      });
+    }
+    return transformControllerPerformTransform(controller, chunk);
+  }
+
+  function transformSinkAbort(stream, reason) {
+    const controller = stream.controller;
+    if (controller.finishPromise !== undefined) return controller.finishPromise.promise;
+    const readable = stream.readable[kState];
+    controller.finishPromise = deferred();
+    const cancelPromise = controller.cancelAlgorithm(reason);
+    transformControllerClearAlgorithms(controller);
+    cancelPromise.then(
+      () => {
+        if (readable.state === "errored") {
+          controller.finishPromise.reject(readable.storedError);
+        } else {
+          defaultControllerError(readable.controller, reason);
+          controller.finishPromise.resolve(undefined);
+        }
+      },
+      (r) => {
+        defaultControllerError(readable.controller, r);
+        controller.finishPromise.reject(r);
+      },
+    );
+    return controller.finishPromise.promise;
+  }
+
+  function transformSinkClose(stream) {
+    const controller = stream.controller;
+    if (controller.finishPromise !== undefined) return controller.finishPromise.promise;
+    const readable = stream.readable[kState];
+    controller.finishPromise = deferred();
+    const flushPromise = controller.flushAlgorithm();
+    transformControllerClearAlgorithms(controller);
+    flushPromise.then(
+      () => {
+        if (readable.state === "errored") {
+          controller.finishPromise.reject(readable.storedError);
+        } else {
+          defaultControllerClose(readable.controller);
+          controller.finishPromise.resolve(undefined);
+        }
+      },
+      (r) => {
+        defaultControllerError(readable.controller, r);
+        controller.finishPromise.reject(r);
+      },
+    );
+    return controller.finishPromise.promise;
+  }
+
+  function transformSourcePull(stream) {
+    transformSetBackpressure(stream, false);
+    return stream.backpressureChangePromise.promise;
+  }
+
+  function transformSourceCancel(stream, reason) {
+    const controller = stream.controller;
+    if (controller.finishPromise !== undefined) return controller.finishPromise.promise;
+    const writable = stream.writable[kState];
+    controller.finishPromise = deferred();
+    const cancelPromise = controller.cancelAlgorithm(reason);
+    transformControllerClearAlgorithms(controller);
+    const unblock = () => {
+      if (stream.backpressure) transformSetBackpressure(stream, false);
+    };
+    cancelPromise.then(
+      () => {
+        if (writable.state === "errored") {
+          controller.finishPromise.reject(writable.storedError);
+        } else {
+          writableControllerErrorIfNeeded(writable.controller, reason);
+          unblock();
+          controller.finishPromise.resolve(undefined);
+        }
+      },
+      (r) => {
+        writableControllerErrorIfNeeded(writable.controller, r);
+        unblock();
+        controller.finishPromise.reject(r);
+      },
+    );
+    return controller.finishPromise.promise;
+  }
+
+  class TransformStreamDefaultController {
+    constructor() {
+      illegalConstructor();
+    }
+
+    get desiredSize() {
+      const controller = internal(this, "transform-controller", "TransformStreamDefaultController");
+      return defaultControllerGetDesiredSize(readableControllerOf(controller.stream));
+    }
+
+    enqueue(chunk) {
+      transformControllerEnqueue(
+        internal(this, "transform-controller", "TransformStreamDefaultController"),
+        chunk,
+      );
+    }
+
+    error(reason) {
+      const controller = internal(this, "transform-controller", "TransformStreamDefaultController");
+      transformError(controller.stream, reason);
+    }
+
+    terminate() {
+      transformControllerTerminate(
+        internal(this, "transform-controller", "TransformStreamDefaultController"),
+      );
+    }
+  }
+
+  class TransformStream {
+    constructor(transformer = undefined, writableStrategy = {}, readableStrategy = {}) {
+      if (transformer === null) {
+        throw new TypeError("The transformer cannot be null");
+      }
+      const dict = transformer === undefined ? {} : transformer;
+      if (dict.readableType !== undefined) throw new RangeError("Invalid readableType");
+      if (dict.writableType !== undefined) throw new RangeError("Invalid writableType");
+      const readableHWM = extractHighWaterMark(readableStrategy, 0);
+      const readableSize = extractSizeAlgorithm(readableStrategy);
+      const writableHWM = extractHighWaterMark(writableStrategy, 1);
+      const writableSize = extractSizeAlgorithm(writableStrategy);
+      const startPromise = deferred();
+      const stream = initializeTransformStream(
+        this,
+        startPromise.promise,
+        writableHWM,
+        writableSize,
+        readableHWM,
+        readableSize,
+      );
+      const controller = setUpTransformControllerFromTransformer(stream, dict);
+      const start = method(dict, "start");
+      startPromise.resolve(start ? start.call(dict, controller) : undefined);
+    }
+
+    get readable() {
+      return internal(this, "transform", "TransformStream").readable;
+    }
+
+    get writable() {
+      return internal(this, "transform", "TransformStream").writable;
+    }
+  }
+
+  Object.defineProperty(TransformStream.prototype, Symbol.toStringTag, {
+    value: "TransformStream",
+    configurable: true,
+  });
+
+  // ==========================================================================
+  // Encoding streams
+  // ==========================================================================
+
+  class TextEncoderStream {
+    constructor() {
+      const encoder = new TextEncoder();
+      let pendingHighSurrogate = null;
+      const transform = new TransformStream({
+        transform(chunk, controller) {
+          let string = String(chunk);
+          if (pendingHighSurrogate !== null) {
+            string = pendingHighSurrogate + string;
+            pendingHighSurrogate = null;
+          }
+          const last = string.charCodeAt(string.length - 1);
+          if (last >= 0xd800 && last <= 0xdbff) {
+            pendingHighSurrogate = string[string.length - 1];
+            string = string.slice(0, -1);
+          }
+          if (string.length > 0) controller.enqueue(encoder.encode(string));
+        },
+        flush(controller) {
+          if (pendingHighSurrogate !== null) {
+            controller.enqueue(new Uint8Array([0xef, 0xbf, 0xbd]));
+          }
+        },
+      });
+      this[kState] = { kind: "text-encoder-stream", transform };
+    }
+
+    get encoding() {
+      internal(this, "text-encoder-stream", "TextEncoderStream");
+      return "utf-8";
+    }
+
+    get readable() {
+      return internal(this, "text-encoder-stream", "TextEncoderStream").transform.readable;
+    }
+
+    get writable() {
+      return internal(this, "text-encoder-stream", "TextEncoderStream").transform.writable;
+    }
+  }
+
+  // Length of the incomplete UTF-8 sequence at the end of `bytes`
+  function incompleteUtf8Tail(bytes) {
+    const length = bytes.length;
+    for (let back = 1; back <= Math.min(3, length); back++) {
+      const byte = bytes[length - back];
+      if ((byte & 0xc0) !== 0x80) {
+        const needed = byte >= 0xf0 ? 4 : byte >= 0xe0 ? 3 : byte >= 0xc0 ? 2 : 1;
+        return needed > back ? back : 0;
+      }
+    }
+    return 0;
+  }
+
+  // Boa leaks an environment from base class constructors that combine default
+  // parameters with closures, so the transform is built outside the constructor
+  function createTextDecoderStreamState(label, options) {
+    const fatal = Boolean(options && options.fatal);
+    const ignoreBOM = Boolean(options && options.ignoreBOM);
+    const decoder = new TextDecoder(label, { fatal, ignoreBOM });
+    const utf8 = decoder.encoding === "utf-8";
+    // UTF-8 is split on sequence boundaries here, so chunks decode on their own
+    const chunkDecoder = utf8 ? new TextDecoder("utf-8", { fatal, ignoreBOM: true }) : decoder;
+    let pending = new Uint8Array(0);
+    let start = true;
+
+    const decodeUtf8 = (bytes) => {
+      if (start && bytes.length > 0) {
+        if (!ignoreBOM && bytes.length >= 3 && bytes[0] === 0xef && bytes[1] === 0xbb && bytes[2] === 0xbf) {
+          bytes = bytes.subarray(3);
+        } else if (!ignoreBOM && bytes.length < 3 && bytes[0] === 0xef) {
+          return "";
+        }
+        start = false;
+      }
+      return bytes.length > 0 ? chunkDecoder.decode(bytes) : "";
+    };
+
+    const transform = new TransformStream({
+      transform(chunk, controller) {
+        const bytes = toUint8Array(chunk);
+        let text;
+        if (utf8) {
+          const combined = concatBytes([pending, bytes], pending.length + bytes.length);
+          let tail = incompleteUtf8Tail(combined);
+          if (start && !ignoreBOM && combined.length < 3 && combined[0] === 0xef) {
+            tail = combined.length;
+          }
+          pending = combined.slice(combined.length - tail);
+          text = decodeUtf8(combined.subarray(0, combined.length - tail));
+        } else {
+          text = decoder.decode(bytes, { stream: true });
+        }
+        if (text.length > 0) controller.enqueue(text);
+      },
+      flush(controller) {
+        let text;
+        if (utf8) {
+          start = start && pending.length >= 3;
+          text = pending.length > 0 ? chunkDecoder.decode(pending) : "";
+          pending = new Uint8Array(0);
+        } else {
+          text = decoder.decode();
+        }
+        if (text.length > 0) controller.enqueue(text);
+      },
+    });
+    return { kind: "text-decoder-stream", transform, decoder, fatal, ignoreBOM };
+  }
+
+  class TextDecoderStream {
+    constructor(label = "utf-8", options = {}) {
+      this[kState] = createTextDecoderStreamState(label, options);
+    }
+
+    get encoding() {
+      return internal(this, "text-decoder-stream", "TextDecoderStream").decoder.encoding;
+    }
+
+    get fatal() {
+      return internal(this, "text-decoder-stream", "TextDecoderStream").fatal;
+    }
+
+    get ignoreBOM() {
+      return internal(this, "text-decoder-stream", "TextDecoderStream").ignoreBOM;
+    }
+
+    get readable() {
+      return internal(this, "text-decoder-stream", "TextDecoderStream").transform.readable;
+    }
+
+    get writable() {
+      return internal(this, "text-decoder-stream", "TextDecoderStream").transform.writable;
+    }
+  }
+
+  // ==========================================================================
+  // Bodies
+  // ==========================================================================
+
+  function isReadableStream(value) {
+    return value !== null && typeof value === "object" && value[kState] !== undefined && value[kState].kind === "readable";
+  }
+
+  // A byte stream over a string, buffer or view; streams pass through
+  function bodyStream(body) {
+    if (body === null || body === undefined) return null;
+    if (isReadableStream(body)) return body;
+    let bytes;
+    if (typeof body === "string") {
+      bytes = new TextEncoder().encode(body);
+    } else if (body instanceof ArrayBuffer || ArrayBuffer.isView(body)) {
+      bytes = toUint8Array(body).slice();
+    } else {
+      bytes = new TextEncoder().encode(String(body));
+    }
+    return new ReadableStream({
+      type: "bytes",
+      start(controller) {
+        if (bytes.byteLength > 0) controller.enqueue(bytes);
+        controller.close();
+      },
+    });
+  }
+
+  // All bytes of a body; a promise only when the body is a stream
+  function bodyBytes(body) {
+    if (body === null || body === undefined) return new Uint8Array(0);
+    if (typeof body === "string") return new TextEncoder().encode(body);
+    if (body instanceof ArrayBuffer || ArrayBuffer.isView(body)) return toUint8Array(body);
+    if (!isReadableStream(body)) return new TextEncoder().encode(String(body));
+
+    const reader = acquireDefaultReader(body[kState]);
+    const chunks = [];
+    let length = 0;
+    const next = () => {
+      const d = deferred();
+      defaultReaderRead(reader, readRequestFor(d));
+      return d.promise.then(({ value, done }) => {
+        if (done) {
+          defaultReaderRelease(reader);
+          return concatBytes(chunks, length);
+        }
+        const chunk = typeof value === "string" ? new TextEncoder().encode(value) : toUint8Array(value);
+        chunks.push(chunk);
+        length += chunk.byteLength;
+        return next();
+      });
+    };
+    return next();
+  }
+
+  // A byte stream reading a file (or an open fd) in chunks
+  function fileStream(pathOrFd, options = {}) {
+    const fs = globalThis.fs;
+    const chunkSize = Number(options.highWaterMark) || 65536;
+    const end = options.end === undefined ? Infinity : Number(options.end);
+    const autoClose = typeof pathOrFd !== "number" || options.autoClose === true;
+    let position = options.start === undefined ? 0 : Number(options.start);
+    let fd = null;
+
+    const close = () => {
+      if (fd !== null && autoClose) fs.closeSync(fd);
+      fd = null;
+    };
+
+    return new ReadableStream(
+      {
+        type: "bytes",
+        autoAllocateChunkSize: chunkSize,
+        start() {
+          fd = typeof pathOrFd === "number" ? pathOrFd : fs.openSync(String(pathOrFd), "r");
+        },
+        pull(controller) {
+          const request = controller.byobRequest;
+          const view = request ? request.view : new Uint8Array(chunkSize);
+          const wanted = Math.min(view.byteLength, end + 1 - position);
+          const target = new Uint8Array(view.buffer, view.byteOffset, view.byteLength);
+          const bytesRead = wanted > 0 ? fs.readSync(fd, target, 0, wanted, position) : 0;
+          if (bytesRead === 0) {
+            close();
+            controller.close();
+            if (request) request.respond(0);
+            return;
+          }
+          position += bytesRead;
+          if (request) request.respond(bytesRead);
+          else controller.enqueue(view.subarray(0, bytesRead));
+        },
+        cancel() {
+          close();
+        },
+      },
+      { highWaterMark: 0 },
+    );
+  }
+
+  // ==========================================================================
+  // Exports
+  // ==========================================================================
+
+  const exports = {
+    ReadableStream,
+    ReadableStreamDefaultReader,
+    ReadableStreamBYOBReader,
+    ReadableStreamBYOBRequest,
+    ReadableStreamDefaultController,
+    ReadableByteStreamController,
+    WritableStream,
+    WritableStreamDefaultWriter,
+    WritableStreamDefaultController,
+    TransformStream,
+    TransformStreamDefaultController,
+    ByteLengthQueuingStrategy,
+    CountQueuingStrategy,
+    TextEncoderStream,
+    TextDecoderStream,
+  };
+  for (const [name, value] of Object.entries(exports)) {
+    Object.defineProperty(globalThis, name, {
+      value,
+      writable: true,
+      configurable: true,
+      enumerable: false,
+    });
+  }
+
+  globalThis.__viper_is_readable_stream = isReadableStream;
+  globalThis.__viper_body_stream = bodyStream;
+  globalThis.__viper_body_bytes = bodyBytes;
+  globalThis.__viper_file_stream = fileStream;
+})();
@@ -27,6 +27,9 @@ use tokio::runtime::Runtime as TokioRuntime;
 use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
+use super::event_loop::{self, SourceNotifier};
+use super::permissions;
+
 // ============================================================================
 // Constants
 // ============================================================================
@@ -59,6 +62,8 @@ pub enum WsEvent {
 struct EventQueue {
     events: parking_lot::Mutex<VecDeque<WsEvent>>,
     has_events: AtomicBool,
+    /// Wakes the JS thread's event loop when events arrive
+    notifier: parking_lot::Mutex<Option<SourceNotifier>>,
 }
 
 impl EventQueue {
@@ -66,6 +71,7 @@ impl EventQueue {
         Self {
             events: parking_lot::Mutex::new(VecDeque::with_capacity(64)),
             has_events: AtomicBool::new(false),
+            notifier: parking_lot::Mutex::new(None),
         }
     }
 
@@ -74,6 +80,18 @@ impl EventQueue {
         let mut queue = self.events.lock();
         queue.push_back(event);
         self.has_events.store(true, Ordering::Release);
+        drop(queue);
+        if let Some(notifier) = self.notifier.lock().as_ref() {
+            notifier.notify();
+        }
+    }
+
+    /// Wake `notifier` on new events, including any already queued
+    fn watch(&self, notifier: SourceNotifier) {
+        if self.has_events.load(Ordering::Acquire) {
+            notifier.notify();
+        }
+        *self.notifier.lock() = Some(notifier);
     }
 
     /// Drain all events (called from JS thread)
@@ -336,6 +354,11 @@ impl WsConnection {
     pub fn drain_events(&self) -> Vec<WsEvent> {
         self.events.drain()
     }
+
+    /// Deliver events to an event loop source
+    pub fn watch(&self, notifier: SourceNotifier) {
+        self.events.watch(notifier);
+    }
 }
 
 // ============================================================================
@@ -391,6 +414,7 @@ pub fn register_websocket(context: &mut Context) -> JsResult<()> {
             static CLOSED = 3;
 
             #wsId = null;
+            #source = null;
             #eventsFired = { open: false };
             #binaryType = "arraybuffer";
 
@@ -522,71 +546,67 @@ pub fn register_websocket(context: &mut Context) -> JsResult<()> {
             }
 
             #startEventLoop() {
-                const poll = () => {
-                    if (this.#wsId === null) return;
-
-                    const state = this.readyState;
-
-                    // Process all pending events
-                    const events = __viper_ws_poll_events(this.#wsId);
-
-                    for (const event of events) {
-                        switch (event.type) {
-                            case 'open':
-                                if (!this.#eventsFired.open) {
-                                    this.#eventsFired.open = true;
-                                    this.protocol = event.protocol || "";
-                                    if (this.onopen) {
-                                        this.onopen({ type: 'open', target: this });
-                                    }
-                                }
-                                break;
+                // The connection thread wakes this source whenever events arrive
+                this.#source = __viper_loop_source(() => this.#dispatchEvents());
+                __viper_ws_watch(this.#wsId, this.#source);
+            }
 
-                            case 'message':
-                                if (this.onmessage) {
-                                    this.onmessage({
-                                        type: 'message',
-                                        data: event.data,
-                                        origin: this.url,
-                                        lastEventId: '',
-                                        source: null,
-                                        ports: [],
-                                        target: this
-                                    });
-                                }
-                                break;
+            #dispatchEvents() {
+                if (this.#wsId === null) return;
 
-                            case 'error':
-                                if (this.onerror) {
-                                    this.onerror({ type: 'error', message: event.message, target: this });
+                // Process all pending events
+                const events = __viper_ws_poll_events(this.#wsId);
+
+                for (const event of events) {
+                    switch (event.type) {
+                        case 'open':
+                            if (!this.#eventsFired.open) {
+                                this.#eventsFired.open = true;
+                                this.protocol = event.protocol || "";
+                                if (this.onopen) {
+                                    this.onopen({ type: 'open', target: this });
                                 }
-                                break;
+                            }
+                            break;
 
-                            case 'close':
-                                if (this.onclose) {
-                                    this.onclose({
-                                        type: 'close',
-                                        code: event.code,
-                                        reason: event.reason,
-                                        wasClean: event.wasClean,
-                                        target: this
-                                    });
-                                }
-                                // Clean up connection
-                                __viper_ws_cleanup(this.#wsId);
-                                this.#wsId = null;
-                                return; // Stop polling
-                        }
-                    }
+                        case 'message':
+                            if (this.onmessage) {
+                                this.onmessage({
+                                    type: 'message',
+                                    data: event.data,
+                                    origin: this.url,
+                                    lastEventId: '',
+                                    source: null,
+                                    ports: [],
+                                    target: this
+                                });
+                            }
+                            break;
 
-                    // Continue polling if not closed
-                    if (state !== WebSocket.CLOSED) {
-                        setTimeout(poll, 1); // 1ms for ultra-low latency
-                    }
-                };
+                        case 'error':
+                            if (this.onerror) {
+                                this.onerror({ type: 'error', message: event.message, target: this });
+                            }
+                            break;
 
-                // Start polling on next tick
-                setTimeout(poll, 0);
+                        case 'close':
+                            if (this.onclose) {
+                                this.onclose({
+                                    type: 'close',
+                                    code: event.code,
+                                    reason: event.reason,
+                                    wasClean: event.wasClean,
+                                    target: this
+                                });
+                            }
+                            // Clean up connection
+                            __viper_ws_cleanup(this.#wsId);
+                            __viper_loop_close(this.#source);
+                            this.#wsId = null;
+                            this.#source = null;
+                            return;
+                    }
+                }
             }
         };
     "#;
@@ -606,6 +626,7 @@ pub fn register_websocket_helpers(context: &mut Context) -> JsResult<()> {
             .ok_or_else(|| JsNativeError::typ().with_message("Missing URL"))?
             .to_string(context)?
             .to_std_string_escaped();
+        permissions::check_url(context, &url)?;
 
         match WsConnection::new(url) {
             Ok(conn) => {
@@ -813,6 +834,25 @@ pub fn register_websocket_helpers(context: &mut Context) -> JsResult<()> {
     });
     context.register_global_callable(js_string!("__viper_ws_poll_events"), 1, poll_events_fn)?;
 
+    // __viper_ws_watch - Wake an event loop source when events arrive
+    let watch_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let id = args
+            .first()
+            .ok_or_else(|| JsNativeError::typ().with_message("Missing WebSocket ID"))?
+            .to_u32(context)?;
+        let source = args
+            .get(1)
+            .ok_or_else(|| JsNativeError::typ().with_message("Missing source ID"))?
+            .to_u32(context)?;
+
+        if let Some(conn) = get_connection(id) {
+            conn.watch(event_loop::current(context)?.notifier(source));
+        }
+
+        Ok(JsValue::undefined())
+    });
+    context.register_global_callable(js_string!("__viper_ws_watch"), 2, watch_fn)?;
+
     // __viper_ws_set_binary_type - Set binary type preference
     let set_binary_type_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let id = args
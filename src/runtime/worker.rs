@@ -6,6 +6,7 @@
 //! - Proper thread isolation with separate JS contexts
 //! - blob: URL support for inline workers
 //! - preload module support
+//! - resourceLimits (heap, loop iteration and execution time budgets)
 //!
 //! Key features:
 //! - new Worker(url, options) - Create worker from file or blob URL
@@ -13,18 +14,24 @@
 //! - worker.terminate() - Forcefully terminate worker
 //! - worker.ref() / worker.unref() - Control process lifetime
 //! - Events: open, message, error, close
+//!
+//! Each side registers an event loop source for its inbox; senders notify the
+//! receiving loop instead of the receiver polling on a timer.
 
 use boa_engine::{
-    Context, JsNativeError, JsResult, JsValue, NativeFunction, Source, js_string,
+    Context, JsNativeError, JsObject, JsResult, JsValue, NativeFunction, Source, js_string,
     object::builtins::JsArray, property::Attribute,
 };
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crate::runtime::{Runtime, RuntimeConfig};
+use super::event_loop::{self, SourceNotifier};
+use super::permissions::{self, Permissions};
+use crate::runtime::{ResourceLimits, Runtime, RuntimeConfig, RuntimeError};
 
 // ============================================================================
 // Worker Message Types - Optimized for performance
@@ -119,6 +126,8 @@ pub struct MessagePortHandle {
     is_open: AtomicU32,
     /// Whether this port has been transferred to a worker
     is_transferred: AtomicU32,
+    /// Wakes the event loop of the thread listening on this port
+    notifier: Mutex<Option<SourceNotifier>>,
 }
 
 /// Message sent through a MessagePort
@@ -140,6 +149,7 @@ impl MessagePortHandle {
             inbox: Mutex::new(Vec::with_capacity(16)),
             is_open: AtomicU32::new(1),
             is_transferred: AtomicU32::new(0),
+            notifier: Mutex::new(None),
         })
     }
 
@@ -164,12 +174,27 @@ impl MessagePortHandle {
         // Get the paired port and add message to its inbox
         if let Some(paired) = get_port_handle(self.paired_port_id) {
             if paired.is_open() {
-                let mut inbox = paired.inbox.lock().unwrap();
-                inbox.push(msg);
+                paired.inbox.lock().unwrap().push(msg);
+                paired.notify();
             }
         }
     }
 
+    /// Deliver future messages to the event loop source behind `notifier`
+    fn watch(&self, notifier: SourceNotifier) {
+        let pending = !self.inbox.lock().unwrap().is_empty();
+        if pending {
+            notifier.notify();
+        }
+        *self.notifier.lock().unwrap() = Some(notifier);
+    }
+
+    fn notify(&self) {
+        if let Some(notifier) = self.notifier.lock().unwrap().as_ref() {
+            notifier.notify();
+        }
+    }
+
     /// Receive all pending messages
     fn receive(&self) -> Vec<PortMessage> {
         let mut inbox = self.inbox.lock().unwrap();
@@ -223,33 +248,6 @@ pub fn has_active_ports() -> bool {
     false
 }
 
-/// Check if there are any active (ref'd) workers
-pub fn has_active_workers() -> bool {
-    let workers = match WORKERS.lock() {
-        Ok(w) => w,
-        Err(_) => return false,
-    };
-
-    if let Some(ref map) = *workers {
-        for handle in map.values() {
-            // Check if worker is running and ref'd
-            let state = handle.get_state();
-            if state == WorkerState::Running || state == WorkerState::Starting {
-                if handle.ref_count.load(Ordering::SeqCst) > 0 {
-                    return true;
-                }
-            }
-        }
-    }
-    false
-}
-
-/// Poll all workers and return any pending messages for the main thread
-/// Returns true if any workers are still active
-pub fn poll_workers() -> bool {
-    has_active_workers()
-}
-
 /// Handle to a worker thread
 pub struct WorkerHandle {
     /// Unique worker ID (same as threadId)
@@ -260,10 +258,10 @@ pub struct WorkerHandle {
     inbox: Mutex<Vec<WorkerMessage>>,
     /// Messages from worker to main thread
     outbox: Mutex<Vec<WorkerMessage>>,
-    /// Condition variable for inbox
-    inbox_condvar: Condvar,
-    /// Whether this worker keeps the process alive
-    ref_count: AtomicU32,
+    /// Wakes the main thread's event loop when the outbox fills
+    main_notifier: Mutex<Option<SourceNotifier>>,
+    /// Wakes the worker's event loop when the inbox fills
+    worker_notifier: Mutex<Option<SourceNotifier>>,
     /// Thread handle (for joining)
     thread_handle: Mutex<Option<JoinHandle<()>>>,
     /// Worker script URL
@@ -279,8 +277,8 @@ impl WorkerHandle {
             state: AtomicU32::new(WorkerState::Starting as u32),
             inbox: Mutex::new(Vec::with_capacity(64)),
             outbox: Mutex::new(Vec::with_capacity(64)),
-            inbox_condvar: Condvar::new(),
-            ref_count: AtomicU32::new(1), // ref'd by default
+            main_notifier: Mutex::new(None),
+            worker_notifier: Mutex::new(None),
             thread_handle: Mutex::new(None),
             url,
             smol,
@@ -297,9 +295,10 @@ impl WorkerHandle {
 
     /// Send message to worker (from main thread)
     fn send_to_worker(&self, msg: WorkerMessage) {
-        let mut inbox = self.inbox.lock().unwrap();
-        inbox.push(msg);
-        self.inbox_condvar.notify_one();
+        self.inbox.lock().unwrap().push(msg);
+        if let Some(notifier) = self.worker_notifier.lock().unwrap().as_ref() {
+            notifier.notify();
+        }
     }
 
     /// Receive messages from worker (on main thread)
@@ -310,8 +309,28 @@ impl WorkerHandle {
 
     /// Send message to main thread (from worker)
     fn send_to_main(&self, msg: WorkerMessage) {
-        let mut outbox = self.outbox.lock().unwrap();
-        outbox.push(msg);
+        self.outbox.lock().unwrap().push(msg);
+        if let Some(notifier) = self.main_notifier.lock().unwrap().as_ref() {
+            notifier.notify();
+        }
+    }
+
+    /// Wake `notifier` for messages from the worker, including any already queued
+    fn watch_outbox(&self, notifier: SourceNotifier) {
+        let pending = !self.outbox.lock().unwrap().is_empty();
+        if pending {
+            notifier.notify();
+        }
+        *self.main_notifier.lock().unwrap() = Some(notifier);
+    }
+
+    /// Wake `notifier` for messages from the main thread, including any already queued
+    fn watch_inbox(&self, notifier: SourceNotifier) {
+        let pending = !self.inbox.lock().unwrap().is_empty();
+        if pending {
+            notifier.notify();
+        }
+        *self.worker_notifier.lock().unwrap() = Some(notifier);
     }
 
     /// Receive messages from main thread (on worker), non-blocking
@@ -324,14 +343,6 @@ impl WorkerHandle {
         self.set_state(WorkerState::Closing);
         self.send_to_worker(WorkerMessage::Terminate);
     }
-
-    fn add_ref(&self) {
-        self.ref_count.fetch_add(1, Ordering::SeqCst);
-    }
-
-    fn remove_ref(&self) {
-        self.ref_count.fetch_sub(1, Ordering::SeqCst);
-    }
 }
 
 // ============================================================================
@@ -343,6 +354,8 @@ fn spawn_worker(
     handle: Arc<WorkerHandle>,
     script_path: PathBuf,
     preload: Vec<String>,
+    permissions: Permissions,
+    resource_limits: ResourceLimits,
 ) -> JoinHandle<()> {
     let worker_id = handle.id;
 
@@ -350,8 +363,9 @@ fn spawn_worker(
         // Create isolated runtime for this worker
         let config = RuntimeConfig {
             base_path: script_path.parent().unwrap_or(&script_path).to_path_buf(),
-            use_event_loop: true,
             args: vec![], // Workers don't inherit args
+            permissions,
+            resource_limits,
             ..Default::default()
         };
 
@@ -420,144 +434,8 @@ fn spawn_worker(
             }
         };
 
-        let filename = script_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("worker.ts");
-
-        // Signal ready
-        if let Some(h) = get_worker_handle(worker_id) {
-            h.set_state(WorkerState::Running);
-            h.send_to_main(WorkerMessage::Ready);
-        }
-
-        // Execute the worker script using eval() instead of run()
-        // We use eval() because run() calls run_event_loop() which would block
-        // waiting for workers. The worker's own message loop handles that below.
-        if let Err(e) = runtime.eval(&script_code, filename) {
-            if let Some(h) = get_worker_handle(worker_id) {
-                h.send_to_main(WorkerMessage::Error(format!("Worker script error: {}", e)));
-            }
-        }
-
-        // Run any pending jobs from script execution (e.g., console.log)
-        let _ = runtime.context_mut().run_jobs();
-
-        // Worker event loop - keep running while there are message handlers
-        loop {
-            let should_break = if let Some(h) = get_worker_handle(worker_id) {
-                h.get_state() == WorkerState::Closing
-            } else {
-                true
-            };
-
-            if should_break {
-                break;
-            }
-
-            // Process incoming messages
-            let messages = if let Some(h) = get_worker_handle(worker_id) {
-                h.receive_from_main()
-            } else {
-                break;
-            };
-
-            let mut processed_any = false;
-            for msg in messages {
-                match msg {
-                    WorkerMessage::Terminate => {
-                        if let Some(h) = get_worker_handle(worker_id) {
-                            h.set_state(WorkerState::Closing);
-                        }
-                        break;
-                    }
-                    WorkerMessage::String(s) => {
-                        processed_any = true;
-                        // Dispatch to self.onmessage
-                        let dispatch_code = format!(
-                            r#"
-                            if (typeof self !== 'undefined' && self.onmessage) {{
-                                self.onmessage({{ data: "{}" }});
-                            }}
-                            "#,
-                            s.replace('\\', "\\\\")
-                                .replace('"', "\\\"")
-                                .replace('\n', "\\n")
-                        );
-                        let _ = runtime.eval(&dispatch_code, "worker-dispatch.js");
-                    }
-                    WorkerMessage::SimpleObject(props) => {
-                        processed_any = true;
-                        // Build object literal
-                        let mut obj_str = String::from("{");
-                        for (i, (key, value)) in props.iter().enumerate() {
-                            if i > 0 {
-                                obj_str.push_str(", ");
-                            }
-                            obj_str.push_str(&format!("\"{}\": ", key.replace('"', "\\\"")));
-                            match value {
-                                SimpleValue::Undefined => obj_str.push_str("undefined"),
-                                SimpleValue::Null => obj_str.push_str("null"),
-                                SimpleValue::Boolean(b) => {
-                                    obj_str.push_str(if *b { "true" } else { "false" })
-                                }
-                                SimpleValue::Number(n) => obj_str.push_str(&n.to_string()),
-                                SimpleValue::String(s) => {
-                                    obj_str.push('"');
-                                    obj_str.push_str(
-                                        &s.replace('\\', "\\\\")
-                                            .replace('"', "\\\"")
-                                            .replace('\n', "\\n"),
-                                    );
-                                    obj_str.push('"');
-                                }
-                            }
-                        }
-                        obj_str.push('}');
-
-                        let dispatch_code = format!(
-                            r#"
-                            if (typeof self !== 'undefined' && self.onmessage) {{
-                                self.onmessage({{ data: {} }});
-                            }}
-                            "#,
-                            obj_str
-                        );
-                        let _ = runtime.eval(&dispatch_code, "worker-dispatch.js");
-                    }
-                    WorkerMessage::StructuredClone(data) => {
-                        processed_any = true;
-                        // For now, treat as JSON
-                        if let Ok(json_str) = String::from_utf8(data) {
-                            let dispatch_code = format!(
-                                r#"
-                                if (typeof self !== 'undefined' && self.onmessage) {{
-                                    self.onmessage({{ data: JSON.parse('{}') }});
-                                }}
-                                "#,
-                                json_str.replace('\\', "\\\\").replace('\'', "\\'")
-                            );
-                            let _ = runtime.eval(&dispatch_code, "worker-dispatch.js");
-                        }
-                    }
-                    _ => {}
-                }
-            }
-
-            // Run any pending jobs (like console.log from message handlers)
-            if processed_any {
-                let _ = runtime.context_mut().run_jobs();
-            }
-
-            // Small sleep to avoid busy-waiting
-            std::thread::sleep(std::time::Duration::from_millis(1));
-        }
-
-        // Cleanup
-        if let Some(h) = get_worker_handle(worker_id) {
-            h.set_state(WorkerState::Closed);
-            h.send_to_main(WorkerMessage::Close(0));
-        }
+        let filename = script_path.to_string_lossy();
+        run_worker_script(worker_id, runtime, &script_code, &filename);
     })
 }
 
@@ -566,14 +444,17 @@ fn spawn_worker_from_blob(
     handle: Arc<WorkerHandle>,
     code: String,
     content_type: String,
+    permissions: Permissions,
+    resource_limits: ResourceLimits,
 ) -> JoinHandle<()> {
     let worker_id = handle.id;
 
     thread::spawn(move || {
         let config = RuntimeConfig {
             base_path: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
-            use_event_loop: true,
             args: vec![],
+            permissions,
+            resource_limits,
             ..Default::default()
         };
 
@@ -610,109 +491,88 @@ fn spawn_worker_from_blob(
             "worker.js"
         };
 
-        // Signal ready
-        if let Some(h) = get_worker_handle(worker_id) {
-            h.set_state(WorkerState::Running);
-            h.send_to_main(WorkerMessage::Ready);
-        }
-
-        // Execute the worker script using eval() instead of run()
-        if let Err(e) = runtime.eval(&code, filename) {
-            if let Some(h) = get_worker_handle(worker_id) {
-                h.send_to_main(WorkerMessage::Error(format!("Worker script error: {}", e)));
-            }
-        }
-
-        // Run any pending jobs from script execution
-        let _ = runtime.context_mut().run_jobs();
+        run_worker_script(worker_id, runtime, &code, filename);
+    })
+}
 
-        // Worker event loop
-        loop {
-            let should_break = if let Some(h) = get_worker_handle(worker_id) {
-                h.get_state() == WorkerState::Closing
-            } else {
-                true
-            };
+/// Run a worker's script on its own event loop until it is terminated or
+/// calls `self.close()`, then report the close to the main thread
+fn run_worker_script(worker_id: u32, mut runtime: Runtime, code: &str, filename: &str) {
+    // Signal ready
+    if let Some(h) = get_worker_handle(worker_id) {
+        h.set_state(WorkerState::Running);
+        h.send_to_main(WorkerMessage::Ready);
+    }
 
-            if should_break {
-                break;
-            }
+    // The inbox source keeps the worker's loop alive until it is stopped
+    let result = runtime
+        .eval(WORKER_INBOX_LISTENER, "worker-inbox.js")
+        .and_then(|_| runtime.run(code, filename));
+    // A worker stopped by its resourceLimits exits with code 1, as in Node.js
+    let exit_code = match &result {
+        Err(RuntimeError::ResourceLimit(_)) => 1,
+        _ => 0,
+    };
+    if let Err(e) = result
+        && let Some(h) = get_worker_handle(worker_id)
+    {
+        h.send_to_main(WorkerMessage::Error(format!("Worker script error: {}", e)));
+    }
 
-            let messages = if let Some(h) = get_worker_handle(worker_id) {
-                h.receive_from_main()
-            } else {
-                break;
-            };
+    // Cleanup
+    if let Some(h) = get_worker_handle(worker_id) {
+        h.set_state(WorkerState::Closed);
+        h.send_to_main(WorkerMessage::Close(exit_code));
+    }
+}
 
-            for msg in messages {
-                match msg {
-                    WorkerMessage::Terminate => {
-                        if let Some(h) = get_worker_handle(worker_id) {
-                            h.set_state(WorkerState::Closing);
-                        }
-                        break;
-                    }
-                    WorkerMessage::String(s) => {
-                        let dispatch_code = format!(
-                            r#"
-                            if (typeof self !== 'undefined' && self.onmessage) {{
-                                self.onmessage({{ data: "{}" }});
-                            }}
-                            "#,
-                            s.replace('\\', "\\\\")
-                                .replace('"', "\\\"")
-                                .replace('\n', "\\n")
-                        );
-                        let _ = runtime.eval(&dispatch_code, "worker-dispatch.js");
-                    }
-                    WorkerMessage::SimpleObject(props) => {
-                        let mut obj_str = String::from("{");
-                        for (i, (key, value)) in props.iter().enumerate() {
-                            if i > 0 {
-                                obj_str.push_str(", ");
-                            }
-                            obj_str.push_str(&format!("\"{}\": ", key.replace('"', "\\\"")));
-                            match value {
-                                SimpleValue::Undefined => obj_str.push_str("undefined"),
-                                SimpleValue::Null => obj_str.push_str("null"),
-                                SimpleValue::Boolean(b) => {
-                                    obj_str.push_str(if *b { "true" } else { "false" })
-                                }
-                                SimpleValue::Number(n) => obj_str.push_str(&n.to_string()),
-                                SimpleValue::String(s) => {
-                                    obj_str.push('"');
-                                    obj_str.push_str(
-                                        &s.replace('\\', "\\\\")
-                                            .replace('"', "\\\"")
-                                            .replace('\n', "\\n"),
-                                    );
-                                    obj_str.push('"');
-                                }
-                            }
-                        }
-                        obj_str.push('}');
-
-                        let dispatch_code = format!(
-                            r#"
-                            if (typeof self !== 'undefined' && self.onmessage) {{
-                                self.onmessage({{ data: {} }});
-                            }}
-                            "#,
-                            obj_str
-                        );
-                        let _ = runtime.eval(&dispatch_code, "worker-dispatch.js");
-                    }
-                    _ => {}
+/// Dispatches messages from the main thread to `self.onmessage`
+const WORKER_INBOX_LISTENER: &str = r#"
+    (function() {
+        const inbox = __viper_loop_source(() => {
+            for (const event of __viper_worker_inbox()) {
+                if (typeof self.onmessage === 'function') {
+                    self.onmessage(event);
                 }
             }
-
-            std::thread::sleep(std::time::Duration::from_millis(1));
+        });
+        __viper_worker_watch_inbox(inbox);
+    })();
+"#;
+
+/// Read Node's `resourceLimits` option
+///
+/// The young and old generation sizes add up to the heap ceiling; Boa has no
+/// equivalent of `stackSizeMb` or `codeRangeSizeMb`, so they are ignored.
+/// `maxLoopIterations` and `maxExecutionTimeMs` are Viper extensions.
+fn parse_resource_limits(limits: &JsObject, context: &mut Context) -> JsResult<ResourceLimits> {
+    let number = |name: &str, context: &mut Context| -> JsResult<Option<f64>> {
+        let value = limits.get(js_string!(name), context)?;
+        if value.is_undefined() {
+            return Ok(None);
         }
-
-        if let Some(h) = get_worker_handle(worker_id) {
-            h.set_state(WorkerState::Closed);
-            h.send_to_main(WorkerMessage::Close(0));
+        let number = value.to_number(context)?;
+        if !number.is_finite() || number <= 0.0 {
+            return Err(JsNativeError::range()
+                .with_message(format!("resourceLimits.{} must be a positive number", name))
+                .into());
         }
+        Ok(Some(number))
+    };
+
+    let old_generation = number("maxOldGenerationSizeMb", context)?;
+    let young_generation = number("maxYoungGenerationSizeMb", context)?;
+    let loop_iterations = number("maxLoopIterations", context)?;
+    let execution_time = number("maxExecutionTimeMs", context)?;
+
+    let heap_mb = match (old_generation, young_generation) {
+        (None, None) => None,
+        (old, young) => Some(old.unwrap_or(0.0) + young.unwrap_or(0.0)),
+    };
+    Ok(ResourceLimits {
+        max_loop_iterations: loop_iterations.map(|n| n as u64),
+        max_execution_time: execution_time.map(|ms| Duration::from_secs_f64(ms / 1000.0)),
+        max_heap_size: heap_mb.map(|mb| (mb * 1024.0 * 1024.0) as usize),
     })
 }
 
@@ -722,6 +582,99 @@ fn get_worker_handle(id: u32) -> Option<Arc<WorkerHandle>> {
     workers.as_ref()?.get(&id).cloned()
 }
 
+/// Convert queued worker messages into `{ type, data | message | code }` event objects
+fn messages_to_events(messages: Vec<WorkerMessage>, context: &mut Context) -> JsResult<JsArray> {
+    let arr = JsArray::new(context);
+    for msg in messages {
+        match msg {
+            WorkerMessage::Ready => {
+                let obj = boa_engine::object::ObjectInitializer::new(context)
+                    .property(js_string!("type"), js_string!("open"), Attribute::all())
+                    .build();
+                arr.push(obj, context)?;
+            }
+            WorkerMessage::String(s) => {
+                let obj = boa_engine::object::ObjectInitializer::new(context)
+                    .property(js_string!("type"), js_string!("message"), Attribute::all())
+                    .property(js_string!("data"), js_string!(s), Attribute::all())
+                    .build();
+                arr.push(obj, context)?;
+            }
+            WorkerMessage::SimpleObject(props) => {
+                // Build data object by constructing JSON and parsing
+                let mut obj_str = String::from("{");
+                for (i, (key, value)) in props.iter().enumerate() {
+                    if i > 0 {
+                        obj_str.push_str(", ");
+                    }
+                    obj_str.push_str(&format!(
+                        "\"{}\": ",
+                        key.replace('\\', "\\\\").replace('"', "\\\"")
+                    ));
+                    match value {
+                        SimpleValue::Undefined => obj_str.push_str("undefined"),
+                        SimpleValue::Null => obj_str.push_str("null"),
+                        SimpleValue::Boolean(b) => {
+                            obj_str.push_str(if *b { "true" } else { "false" })
+                        }
+                        SimpleValue::Number(n) => obj_str.push_str(&n.to_string()),
+                        SimpleValue::String(s) => {
+                            obj_str.push('"');
+                            obj_str.push_str(
+                                &s.replace('\\', "\\\\")
+                                    .replace('"', "\\\"")
+                                    .replace('\n', "\\n"),
+                            );
+                            obj_str.push('"');
+                        }
+                    }
+                }
+                obj_str.push('}');
+
+                // Parse the constructed object
+                let parse_code = format!("({})", obj_str);
+                if let Ok(data_obj) = context.eval(Source::from_bytes(parse_code.as_bytes())) {
+                    let obj = boa_engine::object::ObjectInitializer::new(context)
+                        .property(js_string!("type"), js_string!("message"), Attribute::all())
+                        .property(js_string!("data"), data_obj, Attribute::all())
+                        .build();
+                    arr.push(obj, context)?;
+                }
+            }
+            WorkerMessage::StructuredClone(data) => {
+                if let Ok(json_str) = String::from_utf8(data) {
+                    // Parse JSON back to JS object
+                    let parse_code = format!("({})", json_str);
+                    if let Ok(parsed) = context.eval(Source::from_bytes(parse_code.as_bytes())) {
+                        let obj = boa_engine::object::ObjectInitializer::new(context)
+                            .property(js_string!("type"), js_string!("message"), Attribute::all())
+                            .property(js_string!("data"), parsed, Attribute::all())
+                            .build();
+                        arr.push(obj, context)?;
+                    }
+                }
+            }
+            WorkerMessage::Error(e) => {
+                let obj = boa_engine::object::ObjectInitializer::new(context)
+                    .property(js_string!("type"), js_string!("error"), Attribute::all())
+                    .property(js_string!("message"), js_string!(e), Attribute::all())
+                    .build();
+                arr.push(obj, context)?;
+            }
+            WorkerMessage::Close(code) => {
+                let obj = boa_engine::object::ObjectInitializer::new(context)
+                    .property(js_string!("type"), js_string!("close"), Attribute::all())
+                    .property(js_string!("code"), JsValue::from(code), Attribute::all())
+                    .build();
+                arr.push(obj, context)?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(arr)
+}
+
 /// Register worker-specific globals (self, postMessage, etc.)
 fn register_worker_globals(context: &mut Context, worker_id: u32) -> JsResult<()> {
     // Register __viper_worker_post_message (worker -> main)
@@ -784,6 +737,53 @@ fn register_worker_globals(context: &mut Context, worker_id: u32) -> JsResult<()
         post_message_fn,
     )?;
 
+    // __viper_worker_inbox() -> messages from the main thread as events
+    let inbox_fn = NativeFunction::from_fn_ptr(|_this, _args, context| {
+        let worker_id = context
+            .global_object()
+            .get(js_string!("__viper_worker_id"), context)?
+            .to_u32(context)?;
+
+        let mut messages = get_worker_handle(worker_id)
+            .map(|handle| handle.receive_from_main())
+            .unwrap_or_default();
+        if messages
+            .iter()
+            .any(|msg| matches!(msg, WorkerMessage::Terminate))
+        {
+            messages.clear();
+            event_loop::current(context)?.stop();
+        }
+
+        Ok(messages_to_events(messages, context)?.into())
+    });
+
+    context.register_global_callable(js_string!("__viper_worker_inbox"), 0, inbox_fn)?;
+
+    // __viper_worker_watch_inbox(source_id) - wake the source on main thread messages
+    let watch_inbox_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let worker_id = context
+            .global_object()
+            .get(js_string!("__viper_worker_id"), context)?
+            .to_u32(context)?;
+        let source = args
+            .first()
+            .ok_or_else(|| JsNativeError::typ().with_message("Missing source ID"))?
+            .to_u32(context)?;
+
+        if let Some(handle) = get_worker_handle(worker_id) {
+            handle.watch_inbox(event_loop::current(context)?.notifier(source));
+        }
+
+        Ok(JsValue::undefined())
+    });
+
+    context.register_global_callable(
+        js_string!("__viper_worker_watch_inbox"),
+        1,
+        watch_inbox_fn,
+    )?;
+
     // Store worker_id in global
     context.global_object().set(
         js_string!("__viper_worker_id"),
@@ -812,7 +812,7 @@ fn register_worker_globals(context: &mut Context, worker_id: u32) -> JsResult<()
 
         // Close the worker from within
         self.close = function() {
-            // Signal close
+            __viper_loop_stop();
         };
 
         // importScripts (legacy, but useful)
@@ -922,11 +922,14 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
             .ok_or_else(|| JsNativeError::typ().with_message("Missing URL"))?
             .to_string(context)?
             .to_std_string_escaped();
+        permissions::check_worker(context, &url)?;
+        // Workers run with the permissions of the runtime creating them
+        let permissions = permissions::current(context);
 
         // Parse options
         let mut preload: Vec<String> = Vec::new();
         let mut smol = false;
-        let mut _ref = true;
+        let mut resource_limits = ResourceLimits::default();
 
         if let Some(opts) = args.get(1).and_then(|v| v.as_object()) {
             if let Ok(preload_val) = opts.get(js_string!("preload"), context) {
@@ -949,8 +952,9 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
             if let Ok(smol_val) = opts.get(js_string!("smol"), context) {
                 smol = smol_val.as_boolean().unwrap_or(false);
             }
-            if let Ok(ref_val) = opts.get(js_string!("ref"), context) {
-                _ref = ref_val.as_boolean().unwrap_or(true);
+            let limits_val = opts.get(js_string!("resourceLimits"), context)?;
+            if let Some(limits) = limits_val.as_object() {
+                resource_limits = parse_resource_limits(&limits, context)?;
             }
         }
 
@@ -974,7 +978,13 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
         // Handle blob: URLs
         let thread_handle = if url.starts_with("blob:") {
             if let Some((content, content_type)) = get_blob_content(&url) {
-                spawn_worker_from_blob(Arc::clone(&handle), content, content_type)
+                spawn_worker_from_blob(
+                    Arc::clone(&handle),
+                    content,
+                    content_type,
+                    permissions,
+                    resource_limits,
+                )
             } else {
                 return Err(JsNativeError::typ()
                     .with_message(format!("Blob URL not found: {}", url))
@@ -992,6 +1002,11 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
                 PathBuf::from(&url)
             };
 
+            permissions::check_read(context, &script_path.to_string_lossy())?;
+            for path in &preload {
+                permissions::check_read(context, path)?;
+            }
+
             // Verify file exists before spawning (like Bun)
             if !script_path.exists() {
                 return Err(JsNativeError::typ()
@@ -1002,16 +1017,18 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
                     .into());
             }
 
-            spawn_worker(Arc::clone(&handle), script_path, preload)
+            spawn_worker(
+                Arc::clone(&handle),
+                script_path,
+                preload,
+                permissions,
+                resource_limits,
+            )
         };
 
         // Store thread handle
         *handle.thread_handle.lock().unwrap() = Some(thread_handle);
 
-        if !_ref {
-            handle.remove_ref();
-        }
-
         Ok(JsValue::from(worker_id))
     });
 
@@ -1077,154 +1094,37 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
             .ok_or_else(|| JsNativeError::typ().with_message("Missing worker ID"))?
             .to_u32(context)?;
 
-        let workers = WORKERS.lock().unwrap();
-        let handle = workers.as_ref().and_then(|m| m.get(&worker_id));
-
-        let arr = JsArray::new(context);
-
-        if let Some(handle) = handle {
-            let messages = handle.receive_from_worker();
-            for msg in messages {
-                match msg {
-                    WorkerMessage::Ready => {
-                        let obj = boa_engine::object::ObjectInitializer::new(context)
-                            .property(js_string!("type"), js_string!("open"), Attribute::all())
-                            .build();
-                        arr.push(obj, context)?;
-                    }
-                    WorkerMessage::String(s) => {
-                        let obj = boa_engine::object::ObjectInitializer::new(context)
-                            .property(js_string!("type"), js_string!("message"), Attribute::all())
-                            .property(js_string!("data"), js_string!(s), Attribute::all())
-                            .build();
-                        arr.push(obj, context)?;
-                    }
-                    WorkerMessage::SimpleObject(props) => {
-                        // Build data object by constructing JSON and parsing
-                        let mut obj_str = String::from("{");
-                        for (i, (key, value)) in props.iter().enumerate() {
-                            if i > 0 {
-                                obj_str.push_str(", ");
-                            }
-                            obj_str.push_str(&format!(
-                                "\"{}\": ",
-                                key.replace('\\', "\\\\").replace('"', "\\\"")
-                            ));
-                            match value {
-                                SimpleValue::Undefined => obj_str.push_str("undefined"),
-                                SimpleValue::Null => obj_str.push_str("null"),
-                                SimpleValue::Boolean(b) => {
-                                    obj_str.push_str(if *b { "true" } else { "false" })
-                                }
-                                SimpleValue::Number(n) => obj_str.push_str(&n.to_string()),
-                                SimpleValue::String(s) => {
-                                    obj_str.push('"');
-                                    obj_str.push_str(
-                                        &s.replace('\\', "\\\\")
-                                            .replace('"', "\\\"")
-                                            .replace('\n', "\\n"),
-                                    );
-                                    obj_str.push('"');
-                                }
-                            }
-                        }
-                        obj_str.push('}');
+        let messages = get_worker_handle(worker_id)
+            .map(|handle| handle.receive_from_worker())
+            .unwrap_or_default();
 
-                        // Parse the constructed object
-                        let parse_code = format!("({})", obj_str);
-                        if let Ok(data_obj) =
-                            context.eval(Source::from_bytes(parse_code.as_bytes()))
-                        {
-                            let obj = boa_engine::object::ObjectInitializer::new(context)
-                                .property(
-                                    js_string!("type"),
-                                    js_string!("message"),
-                                    Attribute::all(),
-                                )
-                                .property(js_string!("data"), data_obj, Attribute::all())
-                                .build();
-                            arr.push(obj, context)?;
-                        }
-                    }
-                    WorkerMessage::StructuredClone(data) => {
-                        if let Ok(json_str) = String::from_utf8(data) {
-                            // Parse JSON back to JS object
-                            let parse_code = format!("({})", json_str);
-                            if let Ok(parsed) =
-                                context.eval(Source::from_bytes(parse_code.as_bytes()))
-                            {
-                                let obj = boa_engine::object::ObjectInitializer::new(context)
-                                    .property(
-                                        js_string!("type"),
-                                        js_string!("message"),
-                                        Attribute::all(),
-                                    )
-                                    .property(js_string!("data"), parsed, Attribute::all())
-                                    .build();
-                                arr.push(obj, context)?;
-                            }
-                        }
-                    }
-                    WorkerMessage::Error(e) => {
-                        let obj = boa_engine::object::ObjectInitializer::new(context)
-                            .property(js_string!("type"), js_string!("error"), Attribute::all())
-                            .property(js_string!("message"), js_string!(e), Attribute::all())
-                            .build();
-                        arr.push(obj, context)?;
-                    }
-                    WorkerMessage::Close(code) => {
-                        let obj = boa_engine::object::ObjectInitializer::new(context)
-                            .property(js_string!("type"), js_string!("close"), Attribute::all())
-                            .property(js_string!("code"), JsValue::from(code), Attribute::all())
-                            .build();
-                        arr.push(obj, context)?;
-                    }
-                    _ => {}
-                }
-            }
-        }
-
-        Ok(arr.into())
+        Ok(messages_to_events(messages, context)?.into())
     });
 
     context.register_global_callable(js_string!("__viper_worker_receive"), 1, receive_fn)?;
 
-    // __viper_worker_terminate(worker_id)
-    let terminate_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+    // __viper_worker_watch(worker_id, source_id) - wake the source on worker messages
+    let watch_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let worker_id = args
             .get(0)
             .ok_or_else(|| JsNativeError::typ().with_message("Missing worker ID"))?
             .to_u32(context)?;
-
-        let workers = WORKERS.lock().unwrap();
-        if let Some(handle) = workers.as_ref().and_then(|m| m.get(&worker_id)) {
-            handle.terminate();
-        }
-
-        Ok(JsValue::undefined())
-    });
-
-    context.register_global_callable(js_string!("__viper_worker_terminate"), 1, terminate_fn)?;
-
-    // __viper_worker_ref(worker_id)
-    let ref_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
-        let worker_id = args
-            .get(0)
-            .ok_or_else(|| JsNativeError::typ().with_message("Missing worker ID"))?
+        let source = args
+            .get(1)
+            .ok_or_else(|| JsNativeError::typ().with_message("Missing source ID"))?
             .to_u32(context)?;
 
-        let workers = WORKERS.lock().unwrap();
-        if let Some(handle) = workers.as_ref().and_then(|m| m.get(&worker_id)) {
-            handle.add_ref();
+        if let Some(handle) = get_worker_handle(worker_id) {
+            handle.watch_outbox(event_loop::current(context)?.notifier(source));
         }
 
         Ok(JsValue::undefined())
     });
 
-    context.register_global_callable(js_string!("__viper_worker_ref"), 1, ref_fn)?;
+    context.register_global_callable(js_string!("__viper_worker_watch"), 2, watch_fn)?;
 
-    // __viper_worker_unref(worker_id)
-    let unref_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+    // __viper_worker_terminate(worker_id)
+    let terminate_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
         let worker_id = args
             .get(0)
             .ok_or_else(|| JsNativeError::typ().with_message("Missing worker ID"))?
@@ -1232,13 +1132,13 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
 
         let workers = WORKERS.lock().unwrap();
         if let Some(handle) = workers.as_ref().and_then(|m| m.get(&worker_id)) {
-            handle.remove_ref();
+            handle.terminate();
         }
 
         Ok(JsValue::undefined())
     });
 
-    context.register_global_callable(js_string!("__viper_worker_unref"), 1, unref_fn)?;
+    context.register_global_callable(js_string!("__viper_worker_terminate"), 1, terminate_fn)?;
 
     // __viper_worker_get_state(worker_id) -> state
     let get_state_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
@@ -1468,18 +1368,40 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
 
     // __viper_port_start(port_id) - starts the port (enables message receiving)
     let port_start_fn = NativeFunction::from_fn_ptr(|_this, _args, _context| {
-        // Port is always "started" in our implementation since we use polling
+        // Ports queue messages from creation; start() only attaches a loop source
         Ok(JsValue::undefined())
     });
 
     context.register_global_callable(js_string!("__viper_port_start"), 1, port_start_fn)?;
 
+    // __viper_port_watch(port_id, source_id) - wake the source when messages arrive
+    let port_watch_fn = NativeFunction::from_fn_ptr(|_this, args, context| {
+        let port_id = args
+            .first()
+            .ok_or_else(|| JsNativeError::typ().with_message("Missing port ID"))?
+            .to_u32(context)?;
+        let source = args
+            .get(1)
+            .ok_or_else(|| JsNativeError::typ().with_message("Missing source ID"))?
+            .to_u32(context)?;
+
+        if let Some(handle) = get_port_handle(port_id) {
+            handle.watch(event_loop::current(context)?.notifier(source));
+        }
+
+        Ok(JsValue::undefined())
+    });
+
+    context.register_global_callable(js_string!("__viper_port_watch"), 2, port_watch_fn)?;
+
     // Register JavaScript Worker class
     let worker_class = r#"
         // Worker class implementation
         globalThis.Worker = class Worker {
             #workerId = null;
+            #source = null;
             #isOpen = false;
+            #resourceLimits = {};
 
             onopen = null;
             onmessage = null;
@@ -1498,11 +1420,16 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
                 }
 
                 this.url = url;
+                this.#resourceLimits = { ...(options.resourceLimits || {}) };
 
                 // Create the worker
                 try {
                     this.#workerId = __viper_worker_create(url, options);
-                    this.#startPolling();
+                    this.#source = __viper_loop_source(() => this.#drain());
+                    __viper_worker_watch(this.#workerId, this.#source);
+                    if (options.ref === false) {
+                        this.unref();
+                    }
                 } catch (e) {
                     if (this.onerror) {
                         queueMicrotask(() => {
@@ -1517,6 +1444,11 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
                 return this.#workerId;
             }
 
+            // Limits the worker runs with; empty once it has stopped, as in Node.js
+            get resourceLimits() {
+                return this.#workerId === null ? {} : { ...this.#resourceLimits };
+            }
+
             postMessage(data, transfer) {
                 if (this.#workerId === null) {
                     throw new Error('Worker has been terminated');
@@ -1527,20 +1459,20 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
             terminate() {
                 if (this.#workerId !== null) {
                     __viper_worker_terminate(this.#workerId);
-                    this.#workerId = null;
+                    this.#detach();
                 }
             }
 
             ref() {
-                if (this.#workerId !== null) {
-                    __viper_worker_ref(this.#workerId);
+                if (this.#source !== null) {
+                    __viper_loop_ref(this.#source, true);
                 }
                 return this;
             }
 
             unref() {
-                if (this.#workerId !== null) {
-                    __viper_worker_unref(this.#workerId);
+                if (this.#source !== null) {
+                    __viper_loop_ref(this.#source, false);
                 }
                 return this;
             }
@@ -1559,69 +1491,69 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
                 else if (type === 'close' && this.onclose === listener) this.onclose = null;
             }
 
-            #startPolling() {
-                const poll = () => {
-                    if (this.#workerId === null) {
-                        return;
-                    }
+            // Stop listening; the worker no longer keeps the process alive
+            #detach() {
+                if (this.#source !== null) {
+                    __viper_loop_close(this.#source);
+                    this.#source = null;
+                }
+                this.#workerId = null;
+            }
 
-                    try {
-                        const events = __viper_worker_receive(this.#workerId);
-                        for (const event of events) {
-                            switch (event.type) {
-                                case 'open':
-                                    this.#isOpen = true;
-                                    if (this.onopen) {
-                                        this.onopen({ type: 'open' });
-                                    }
-                                    break;
-                                case 'message':
-                                    if (this.onmessage) {
-                                        this.onmessage({
-                                            type: 'message',
-                                            data: event.data,
-                                            origin: '',
-                                            lastEventId: '',
-                                            source: null,
-                                            ports: []
-                                        });
-                                    }
-                                    break;
-                                case 'error':
-                                    if (this.onerror) {
-                                        this.onerror({
-                                            type: 'error',
-                                            message: event.message,
-                                            error: new Error(event.message)
-                                        });
-                                    }
-                                    break;
-                                case 'close':
-                                    if (this.onclose) {
-                                        this.onclose({
-                                            type: 'close',
-                                            code: event.code,
-                                            wasClean: true
-                                        });
-                                    }
-                                    this.#workerId = null;
-                                    return; // Stop polling
-                            }
-                        }
-                    } catch (e) {
-                        if (this.onerror) {
-                            this.onerror({ type: 'error', message: e.message, error: e });
+            // Dispatch events queued by the worker thread
+            #drain() {
+                if (this.#workerId === null) {
+                    return;
+                }
+
+                try {
+                    const events = __viper_worker_receive(this.#workerId);
+                    for (const event of events) {
+                        switch (event.type) {
+                            case 'open':
+                                this.#isOpen = true;
+                                if (this.onopen) {
+                                    this.onopen({ type: 'open' });
+                                }
+                                break;
+                            case 'message':
+                                if (this.onmessage) {
+                                    this.onmessage({
+                                        type: 'message',
+                                        data: event.data,
+                                        origin: '',
+                                        lastEventId: '',
+                                        source: null,
+                                        ports: []
+                                    });
+                                }
+                                break;
+                            case 'error':
+                                if (this.onerror) {
+                                    this.onerror({
+                                        type: 'error',
+                                        message: event.message,
+                                        error: new Error(event.message)
+                                    });
+                                }
+                                break;
+                            case 'close':
+                                if (this.onclose) {
+                                    this.onclose({
+                                        type: 'close',
+                                        code: event.code,
+                                        wasClean: true
+                                    });
+                                }
+                                this.#detach();
+                                return;
                         }
                     }
-
-                    // Continue polling with minimal delay
-                    if (this.#workerId !== null) {
-                        setTimeout(poll, 1);
+                } catch (e) {
+                    if (this.onerror) {
+                        this.onerror({ type: 'error', message: e.message, error: e });
                     }
-                };
-
-                // Start polling immediately
-                setTimeout(poll, 0);
+                }
             }
         };
 
@@ -1632,13 +1564,8 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
                 if (blob instanceof Blob) {
                     // Read blob content synchronously (for workers)
                     const type = blob.type || 'application/javascript';
-
-                    // Convert blob to string using stored content
-                    if (blob._content !== undefined) {
-                        return __viper_create_blob_url(blob._content, type);
-                    }
-
-                    return __viper_create_blob_url('[blob content]', type);
+                    const content = new TextDecoder().decode(__viper_blob_bytes(blob));
+                    return __viper_create_blob_url(content, type);
                 }
                 if (originalCreateObjectURL) {
                     return originalCreateObjectURL.call(this, blob);
@@ -1653,65 +1580,6 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
             };
         }
 
-        // Blob class for createObjectURL support
-        if (typeof globalThis.Blob === 'undefined') {
-            globalThis.Blob = class Blob {
-                constructor(parts, options) {
-                    this.type = options?.type || '';
-                    this.size = 0;
-                    this._content = '';
-                    if (parts && parts.length > 0) {
-                        this._content = parts.map(p => {
-                            if (typeof p === 'string') return p;
-                            if (p instanceof ArrayBuffer) return new TextDecoder().decode(p);
-                            if (p instanceof Uint8Array) return new TextDecoder().decode(p);
-                            return String(p);
-                        }).join('');
-                        this.size = this._content.length;
-                    }
-                }
-
-                async text() {
-                    return this._content;
-                }
-
-                async arrayBuffer() {
-                    return new TextEncoder().encode(this._content).buffer;
-                }
-
-                slice(start, end, contentType) {
-                    const sliced = this._content.slice(start, end);
-                    return new Blob([sliced], { type: contentType || this.type });
-                }
-            };
-        } else {
-            // Enhance existing Blob to store content
-            const OriginalBlob = globalThis.Blob;
-            globalThis.Blob = class Blob extends OriginalBlob {
-                constructor(parts, options) {
-                    super(parts, options);
-                    // Store content for createObjectURL
-                    if (parts && parts.length > 0) {
-                        this._content = parts.map(p => {
-                            if (typeof p === 'string') return p;
-                            if (p instanceof ArrayBuffer) return new TextDecoder().decode(p);
-                            if (p instanceof Uint8Array) return new TextDecoder().decode(p);
-                            return String(p);
-                        }).join('');
-                    }
-                }
-            };
-        }
-
-        // File class for blob URLs with filenames
-        globalThis.File = globalThis.File || class File extends Blob {
-            constructor(parts, name, options) {
-                super(parts, options);
-                this.name = name;
-                this.lastModified = options?.lastModified || Date.now();
-            }
-        };
-
         // Viper.isMainThread
         globalThis.Viper = globalThis.Viper || {};
         globalThis.Viper.isMainThread = true;
@@ -1815,7 +1683,7 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
             #portId = null;
             #started = false;
             #closed = false;
-            #pollInterval = null;
+            #source = null;
 
             onmessage = null;
             onmessageerror = null;
@@ -1873,47 +1741,63 @@ pub fn register_worker_api(context: &mut Context) -> JsResult<()> {
                 if (this.#started || this.#closed) return;
                 this.#started = true;
 
-                // Start polling for messages
-                const poll = () => {
-                    if (this.#closed) return;
-
-                    try {
-                        const messages = __viper_port_receive(this.#portId);
-                        for (const msg of messages) {
-                            const event = new MessageEvent('message', {
-                                data: msg.data,
-                                ports: (msg.ports || []).map(id => {
-                                    const port = new MessagePort(id);
-                                    return port;
-                                })
-                            });
-
-                            if (this.onmessage) {
-                                this.onmessage(event);
-                            }
-                            this.dispatchEvent(event);
-                        }
-                    } catch (e) {
-                        const errorEvent = new MessageEvent('messageerror', { data: e });
-                        if (this.onmessageerror) {
-                            this.onmessageerror(errorEvent);
+                // Dispatch messages whenever the other end posts
+                this.#source = __viper_loop_source(() => this.#drain());
+                __viper_port_watch(this.#portId, this.#source);
+            }
+
+            #drain() {
+                if (this.#closed) return;
+
+                try {
+                    const messages = __viper_port_receive(this.#portId);
+                    for (const msg of messages) {
+                        const event = new MessageEvent('message', {
+                            data: msg.data,
+                            ports: (msg.ports || []).map(id => {
+                                const port = new MessagePort(id);
+                                return port;
+                            })
+                        });
+
+                        if (this.onmessage) {
+                            this.onmessage(event);
                         }
-                        this.dispatchEvent(errorEvent);
+                        this.dispatchEvent(event);
                     }
-
-                    if (!this.#closed) {
-                        this.#pollInterval = setTimeout(poll, 1);
+                } catch (e) {
+                    const errorEvent = new MessageEvent('messageerror', { data: e });
+                    if (this.onmessageerror) {
+                        this.onmessageerror(errorEvent);
                     }
-                };
+                    this.dispatchEvent(errorEvent);
+                }
+            }
+
+            ref() {
+                if (this.#source !== null) {
+                    __viper_loop_ref(this.#source, true);
+                }
+                return this;
+            }
+
+            unref() {
+                if (this.#source !== null) {
+                    __viper_loop_ref(this.#source, false);
+                }
+                return this;
+            }
 
-                setTimeout(poll, 0);
+            hasRef() {
+                return this.#source !== null && __viper_loop_has_ref(this.#source);
             }
 
             close() {
                 if (this.#closed) return;
                 this.#closed = true;
-                if (this.#pollInterval) {
-                    clearTimeout(this.#pollInterval);
+                if (this.#source !== null) {
+                    __viper_loop_close(this.#source);
+                    this.#source = null;
                 }
                 if (this.#portId !== null) {
                     __viper_port_close(this.#portId);
@@ -2024,6 +1908,45 @@ mod tests {
         assert_eq!(props.len(), 4);
     }
 
+    #[test]
+    fn test_resource_limits_exit_code() {
+        let path = std::env::temp_dir().join(format!("viper-spin-{}.js", std::process::id()));
+        std::fs::write(&path, "setTimeout(() => { while (true) {} }, 0);").unwrap();
+        let mut runtime = Runtime::new().unwrap();
+        let code = format!(
+            r#"
+            const worker = new Worker({path:?}, {{ resourceLimits: {{ maxExecutionTimeMs: 200 }} }});
+            globalThis.code = await new Promise((resolve) => {{
+                worker.onclose = (event) => resolve(event.code);
+            }});
+        "#,
+            path = path.to_string_lossy()
+        );
+        let result = runtime.run(&code, "main.mjs");
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+        let code = runtime.eval("code", "check.js").unwrap();
+        assert_eq!(code.as_number(), Some(1.0));
+    }
+
+    #[test]
+    fn test_resource_limits_must_be_positive() {
+        let mut runtime = Runtime::new().unwrap();
+        let code = r#"
+            try {
+                new Worker('data:,', { resourceLimits: { maxExecutionTimeMs: -1 } });
+            } catch (e) {
+                globalThis.error = e.name + ': ' + e.message;
+            }
+        "#;
+        runtime.run(code, "main.js").unwrap();
+        let error = runtime.eval("error", "check.js").unwrap();
+        assert_eq!(
+            runtime.value_to_string(&error),
+            "RangeError: resourceLimits.maxExecutionTimeMs must be a positive number"
+        );
+    }
+
     #[test]
     fn test_blob_url() {
         let url = create_blob_url(
@@ -20,6 +20,8 @@ use std::collections::HashMap;
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::net::TcpListener;
 
 /// Server configuration
@@ -47,6 +49,75 @@ pub struct JsRequest {
     pub url: String,
     pub headers: HashMap<String, String>,
     pub body: Option<Bytes>,
+    pub connection: ClientConnection,
+}
+
+/// The client socket behind a request, used to notice disconnects
+#[derive(Debug, Clone, Copy)]
+pub struct ClientConnection {
+    #[cfg(unix)]
+    fd: std::os::fd::RawFd,
+}
+
+/// Stops a disconnect watch when dropped
+pub struct CloseWatch {
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for CloseWatch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl ClientConnection {
+    /// Call `on_close` from a helper thread once the client hangs up.
+    /// The socket is peeked, never read, so hyper still sees every byte.
+    pub fn watch(&self, on_close: impl FnOnce() + Send + 'static) -> CloseWatch {
+        let stop = Arc::new(AtomicBool::new(false));
+
+        #[cfg(unix)]
+        {
+            // Our own descriptor, so the thread never touches a reused fd
+            let fd = unsafe { libc::dup(self.fd) };
+            if fd >= 0 {
+                let stop = stop.clone();
+                std::thread::spawn(move || {
+                    let mut byte = 0u8;
+                    while !stop.load(Ordering::Relaxed) {
+                        let n = unsafe {
+                            libc::recv(
+                                fd,
+                                &mut byte as *mut u8 as *mut libc::c_void,
+                                1,
+                                libc::MSG_PEEK | libc::MSG_DONTWAIT,
+                            )
+                        };
+                        let closed = n == 0
+                            || (n < 0
+                                && !matches!(
+                                    std::io::Error::last_os_error().kind(),
+                                    std::io::ErrorKind::WouldBlock
+                                        | std::io::ErrorKind::Interrupted
+                                ));
+                        if closed {
+                            if !stop.load(Ordering::Relaxed) {
+                                on_close();
+                            }
+                            break;
+                        }
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                    }
+                    unsafe { libc::close(fd) };
+                });
+            }
+        }
+
+        #[cfg(not(unix))]
+        drop(on_close);
+
+        CloseWatch { stop }
+    }
 }
 
 /// Response data from the JS handler
@@ -142,6 +213,10 @@ pub fn run_server(
 
                 loop {
                     let (stream, _remote_addr) = listener.accept().await?;
+                    let connection = ClientConnection {
+                        #[cfg(unix)]
+                        fd: std::os::fd::AsRawFd::as_raw_fd(&stream),
+                    };
                     let io = TokioIo::new(stream);
                     let handler = handler.clone();
                     let max_body_size = config.max_body_size;
@@ -151,7 +226,9 @@ pub fn run_server(
                         // Process connection
                         let service = service_fn(move |req: Request<Incoming>| {
                             let handler = handler.clone();
-                            async move { handle_request(req, handler, max_body_size).await }
+                            async move {
+                                handle_request(req, handler, max_body_size, connection).await
+                            }
                         });
 
                         // Serve the connection
@@ -175,6 +252,7 @@ async fn handle_request(
     req: Request<Incoming>,
     handler: RequestHandler,
     max_body_size: usize,
+    connection: ClientConnection,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
     let method = req.method().to_string();
     let uri = req.uri().to_string();
@@ -203,6 +281,7 @@ async fn handle_request(
         url: uri,
         headers,
         body,
+        connection,
     };
 
     // Call the handler directly